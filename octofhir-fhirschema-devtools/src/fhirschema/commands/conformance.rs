@@ -0,0 +1,299 @@
+use super::validate::{ResourceValidator, create_validator, resolve_profiles};
+use crate::ConformanceArgs;
+use crate::report::format_path;
+use anyhow::{Context, Result, bail};
+use octofhir_fhirschema::{ValidationOptions, get_schema_manifest};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Stdio};
+use std::time::Duration;
+use wait_timeout::ChildExt;
+
+#[derive(Debug, Serialize)]
+struct ConformanceReport {
+    path: PathBuf,
+    octofhir_valid: bool,
+    reference_valid: bool,
+    /// Element paths both validators flagged with an error
+    agreed: Vec<String>,
+    /// Element paths only fhirschema flagged, with its messages
+    only_octofhir: Vec<String>,
+    /// Element paths only the reference validator flagged, with its messages
+    only_reference: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reference_stderr: Option<String>,
+}
+
+/// Validate each file with fhirschema and the HL7 Java validator and
+/// compare their error findings by element path.
+///
+/// Paths are compared without array indices and without the leading
+/// resource type, so `Patient.name[0].given[1]` and `name.given` match.
+/// Returns whether both validators agreed on every file's validity.
+pub(crate) async fn conformance_check(args: ConformanceArgs) -> Result<bool> {
+    let jar = args
+        .reference_validator
+        .clone()
+        .or_else(|| std::env::var_os("HL7_VALIDATOR_JAR").map(PathBuf::from))
+        .context("no reference validator: pass --reference-validator or set HL7_VALIDATOR_JAR")?;
+    if !jar.is_file() {
+        bail!("reference validator jar does not exist: {}", jar.display());
+    }
+    let version = args.fhir_version.schema_version();
+    let fhir_version = get_schema_manifest(version)
+        .map(|manifest| manifest.package_version.clone())
+        .with_context(|| format!("no manifest for embedded {} schemas", version.as_str()))?;
+
+    let mut schemas = args.schemas.load(version)?;
+    let profiles = resolve_profiles(&args.profiles, &mut schemas)?;
+    let validator = ResourceValidator {
+        validator: create_validator(schemas, args.fhir_version, true).await?,
+        profiles: profiles.clone(),
+        type_profiles: HashMap::new(),
+        meta_profile: true,
+        options: ValidationOptions::default(),
+    };
+    fs::create_dir_all(&args.work_dir)
+        .with_context(|| format!("failed to create {}", args.work_dir.display()))?;
+
+    let mut reports = Vec::with_capacity(args.files.len());
+    for (idx, path) in args.files.iter().enumerate() {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let resource: Value = serde_json::from_str(&content)
+            .with_context(|| format!("failed to parse {}", path.display()))?;
+        let ours = validator.check(path, None, &resource).await.result;
+
+        let mut command = std::process::Command::new("java");
+        command.arg("-jar").arg(&jar).arg(path);
+        command.arg("-version").arg(&fhir_version);
+        command.arg("-tx").arg(&args.reference_tx);
+        for ig in &args.reference_igs {
+            command.arg("-ig").arg(ig);
+        }
+        for (arg, name) in args.profiles.iter().zip(&profiles) {
+            if Path::new(arg).is_file() {
+                command.arg("-ig").arg(arg);
+            }
+            command.arg("-profile").arg(name);
+        }
+        let outcome_path = args.work_dir.join(format!("outcome-{idx}.json"));
+        let _ = fs::remove_file(&outcome_path);
+        command.arg("-output").arg(&outcome_path);
+
+        let timeout = Duration::from_secs(args.timeout_secs);
+        let run = tokio::task::spawn_blocking(move || run_reference_validator(command, timeout))
+            .await
+            .context("reference validator task panicked")??;
+        let outcome = fs::read_to_string(&outcome_path)
+            .ok()
+            .and_then(|text| serde_json::from_str::<Value>(&text).ok());
+        let Some(outcome) = outcome else {
+            let reason = if run.timed_out {
+                format!("timed out after {}s", args.timeout_secs)
+            } else {
+                run.stderr.trim().to_string()
+            };
+            bail!(
+                "reference validator produced no OperationOutcome for {}: {reason}",
+                path.display()
+            );
+        };
+
+        let resource_type = resource.get("resourceType").and_then(Value::as_str);
+        let mut octofhir_findings: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for error in &ours.errors {
+            octofhir_findings
+                .entry(comparable_path(&format_path(&error.path), resource_type))
+                .or_default()
+                .push(format!("{} {error}", error.error_type));
+        }
+        let mut reference_findings: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for issue in outcome
+            .get("issue")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            let severity = issue.get("severity").and_then(Value::as_str);
+            if !matches!(severity, Some("error" | "fatal")) {
+                continue;
+            }
+            let expression = issue
+                .get("expression")
+                .and_then(Value::as_array)
+                .and_then(|e| e.first())
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            let message = issue
+                .get("details")
+                .and_then(|d| d.get("text"))
+                .and_then(Value::as_str)
+                .unwrap_or("(no message)");
+            reference_findings
+                .entry(comparable_path(expression, resource_type))
+                .or_default()
+                .push(message.to_string());
+        }
+
+        let mut report = ConformanceReport {
+            path: path.clone(),
+            octofhir_valid: ours.valid,
+            reference_valid: reference_findings.is_empty(),
+            agreed: vec![],
+            only_octofhir: vec![],
+            only_reference: vec![],
+            reference_stderr: (!run.status.success())
+                .then(|| run.stderr.trim().to_string())
+                .filter(|stderr| !stderr.is_empty()),
+        };
+        for (element, messages) in &octofhir_findings {
+            if reference_findings.contains_key(element) {
+                report.agreed.push(element.clone());
+            } else {
+                report
+                    .only_octofhir
+                    .push(format!("{element}: {}", messages.join("; ")));
+            }
+        }
+        for (element, messages) in &reference_findings {
+            if !octofhir_findings.contains_key(element) {
+                report
+                    .only_reference
+                    .push(format!("{element}: {}", messages.join("; ")));
+            }
+        }
+        reports.push(report);
+    }
+
+    let disagreements = reports
+        .iter()
+        .filter(|report| report.octofhir_valid != report.reference_valid)
+        .count();
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+    } else {
+        for report in &reports {
+            let verdict = if report.octofhir_valid == report.reference_valid {
+                "AGREE"
+            } else {
+                "DIFFER"
+            };
+            println!(
+                "{verdict} {} (fhirschema valid={}, reference valid={})",
+                report.path.display(),
+                report.octofhir_valid,
+                report.reference_valid
+            );
+            for element in &report.only_octofhir {
+                println!("  only fhirschema: {element}");
+            }
+            for element in &report.only_reference {
+                println!("  only reference:  {element}");
+            }
+        }
+        println!(
+            "{} file(s) compared, {disagreements} validity disagreement(s)",
+            reports.len()
+        );
+    }
+    Ok(disagreements == 0)
+}
+
+/// A finished reference validator run.
+struct ReferenceRun {
+    status: ExitStatus,
+    stderr: String,
+    timed_out: bool,
+}
+
+/// Run the reference validator, killing it once `timeout` passes.
+///
+/// stderr is drained on its own thread while waiting: the Java validator
+/// logs heavily, and a full pipe would otherwise block it until the timeout.
+/// Blocks, so call it from `spawn_blocking`.
+fn run_reference_validator(
+    mut command: std::process::Command,
+    timeout: Duration,
+) -> Result<ReferenceRun> {
+    let mut child = command
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("failed to spawn java")?;
+    let mut stderr = child
+        .stderr
+        .take()
+        .context("java stderr was not captured")?;
+    let drain = std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = stderr.read_to_end(&mut buf);
+        String::from_utf8_lossy(&buf).into_owned()
+    });
+
+    let (status, timed_out) = match child.wait_timeout(timeout)? {
+        Some(status) => (status, false),
+        None => {
+            let _ = child.kill();
+            (child.wait().context("failed to wait for java")?, true)
+        }
+    };
+    let stderr = drain.join().unwrap_or_default();
+    Ok(ReferenceRun {
+        status,
+        stderr,
+        timed_out,
+    })
+}
+
+/// Reduce a finding location to an element path without array indices and
+/// without the leading resource type.
+fn comparable_path(path: &str, resource_type: Option<&str>) -> String {
+    let mut segments: Vec<String> = path
+        .split('.')
+        .map(|segment| match segment.find('[') {
+            Some(idx) => segment[..idx].to_string(),
+            None => segment.to_string(),
+        })
+        .filter(|segment| !segment.is_empty() && segment.parse::<usize>().is_err())
+        .collect();
+    if segments.first().map(String::as_str) == resource_type {
+        segments.remove(0);
+    }
+    if segments.is_empty() {
+        "(root)".to_string()
+    } else {
+        segments.join(".")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_comparable_path_drops_indices_and_resource_type() {
+        assert_eq!(
+            comparable_path("Patient.name[0].given[1]", Some("Patient")),
+            "name.given"
+        );
+        assert_eq!(
+            comparable_path("name.0.given", Some("Patient")),
+            "name.given"
+        );
+        assert_eq!(
+            comparable_path("Observation.value", Some("Patient")),
+            "Observation.value"
+        );
+    }
+
+    #[test]
+    fn test_comparable_path_of_root() {
+        assert_eq!(comparable_path("Patient", Some("Patient")), "(root)");
+        assert_eq!(comparable_path("", None), "(root)");
+    }
+}
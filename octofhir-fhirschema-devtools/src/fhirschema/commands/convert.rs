@@ -0,0 +1,337 @@
+use crate::ConvertArgs;
+use crate::config::CONFIG_FILE;
+use anyhow::{Context, Result, bail};
+use octofhir_fhirschema::fsh::{FshProject, FshSource};
+use octofhir_fhirschema::serialization::BundleFormat;
+use octofhir_fhirschema::validation::check_structure_definition;
+use octofhir_fhirschema::{
+    FhirSchema, StructureDefinition, encode_schema, get_schemas, inline_types, translate,
+};
+use octofhir_fhirschema_devtools::schema_files::collect_json_files;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::task::JoinSet;
+
+#[derive(Debug, Default, Serialize)]
+struct ConversionReport {
+    converted: Vec<ConvertedFile>,
+    failed: Vec<FileIssue>,
+    skipped: Vec<FileIssue>,
+    /// Encoded schemas not yet written, see [`Self::write_pending`]
+    #[serde(skip)]
+    pending: Vec<(ConvertedFile, Vec<u8>)>,
+}
+
+#[derive(Debug, Serialize)]
+struct ConvertedFile {
+    input: PathBuf,
+    output: PathBuf,
+    url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fsh_source: Option<FshSource>,
+}
+
+#[derive(Debug, Serialize)]
+struct FileIssue {
+    input: PathBuf,
+    reason: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fsh_source: Option<FshSource>,
+}
+
+impl ConversionReport {
+    fn record(&mut self, conversion: Conversion) {
+        match conversion {
+            Conversion::Encoded(file, bytes) => self.pending.push((file, bytes)),
+            Conversion::Failed(file) => {
+                match &file.fsh_source {
+                    Some(source) => eprintln!("failed {source}: {}", file.reason),
+                    None => eprintln!("failed {}: {}", file.input.display(), file.reason),
+                }
+                self.failed.push(file);
+            }
+            Conversion::Skipped(file) => self.skipped.push(file),
+        }
+    }
+
+    /// Write the encoded schemas once every conversion has finished.
+    ///
+    /// Inputs whose schemas map to the same output file (StructureDefinitions
+    /// sharing a `name`, or one profile published under several URLs) all
+    /// fail, instead of the last one to finish silently overwriting the rest.
+    fn write_pending(&mut self) {
+        let mut by_output: BTreeMap<PathBuf, Vec<(ConvertedFile, Vec<u8>)>> = BTreeMap::new();
+        for (file, bytes) in std::mem::take(&mut self.pending) {
+            by_output
+                .entry(file.output.clone())
+                .or_default()
+                .push((file, bytes));
+        }
+
+        for (output, claims) in by_output {
+            if claims.len() > 1 {
+                let inputs: Vec<PathBuf> =
+                    claims.iter().map(|(file, _)| file.input.clone()).collect();
+                for (file, _) in claims {
+                    let others: Vec<String> = inputs
+                        .iter()
+                        .filter(|input| **input != file.input)
+                        .map(|input| input.display().to_string())
+                        .collect();
+                    self.record(Conversion::Failed(FileIssue {
+                        reason: format!(
+                            "{} would also be written from {}; schema names must be unique",
+                            output.display(),
+                            others.join(", ")
+                        ),
+                        input: file.input,
+                        fsh_source: file.fsh_source,
+                    }));
+                }
+                continue;
+            }
+
+            for (file, bytes) in claims {
+                match fs::write(&output, bytes) {
+                    Ok(()) => self.converted.push(file),
+                    Err(err) => self.record(Conversion::Failed(FileIssue {
+                        reason: format!("write to {} failed: {err}", output.display()),
+                        input: file.input,
+                        fsh_source: file.fsh_source,
+                    })),
+                }
+            }
+        }
+    }
+}
+
+enum Conversion {
+    /// Converted and encoded; written by [`ConversionReport::write_pending`]
+    Encoded(ConvertedFile, Vec<u8>),
+    Failed(FileIssue),
+    Skipped(FileIssue),
+}
+
+impl Conversion {
+    fn attach_fsh_source(&mut self, project: &FshProject) {
+        let (input, fsh_source) = match self {
+            Conversion::Encoded(file, _) => (&file.input, &mut file.fsh_source),
+            Conversion::Failed(file) | Conversion::Skipped(file) => {
+                (&file.input, &mut file.fsh_source)
+            }
+        };
+        *fsh_source = project.source_of(input).cloned();
+    }
+}
+
+/// Compile a FSH project with SUSHI, failing with its output if it reports
+/// errors.
+fn run_sushi(sushi: &Path, project: &Path) -> Result<()> {
+    let output = std::process::Command::new(sushi)
+        .arg("build")
+        .arg(project)
+        .output()
+        .with_context(|| format!("failed to run {}", sushi.display()))?;
+    if !output.status.success() {
+        bail!(
+            "sushi build failed:\n{}",
+            String::from_utf8_lossy(&output.stdout)
+        );
+    }
+    Ok(())
+}
+
+/// Convert every StructureDefinition among the inputs in parallel, keeping
+/// going past individual failures.
+///
+/// Returns whether the number of failures stayed within `--max-failures`.
+pub(crate) async fn convert(args: ConvertArgs) -> Result<bool> {
+    if args.inputs.is_empty() && args.fsh.is_none() {
+        bail!("no inputs to convert: pass them, --fsh, or set `[convert] inputs` in {CONFIG_FILE}");
+    }
+    let mut files = Vec::new();
+    let fsh_project = match &args.fsh {
+        Some(dir) => {
+            if args.sushi {
+                run_sushi(&args.sushi_bin, dir)?;
+            }
+            let project = FshProject::open(dir)?;
+            files.extend(project.resource_files()?);
+            Some(Arc::new(project))
+        }
+        None => None,
+    };
+    for input in &args.inputs {
+        if input.is_dir() {
+            collect_json_files(input, &mut files)
+                .with_context(|| format!("failed to scan {}", input.display()))?;
+        } else {
+            files.push(input.clone());
+        }
+    }
+    fs::create_dir_all(&args.output)
+        .with_context(|| format!("failed to create {}", args.output.display()))?;
+
+    let output = Arc::new(args.output.clone());
+    let format = args.format.format();
+    let inline = match args.inline_depth {
+        Some(depth) => Some((get_schemas(args.fhir_version.schema_version())?, depth)),
+        None => None,
+    };
+    let check_definitions = args.check_definitions;
+    let jobs = args.jobs.max(1);
+    let mut report = ConversionReport::default();
+    let mut in_flight = JoinSet::new();
+
+    for input in files {
+        while in_flight.len() >= jobs {
+            if let Some(conversion) = in_flight.join_next().await {
+                report.record(conversion?);
+            }
+        }
+        let output = Arc::clone(&output);
+        let fsh_project = fsh_project.clone();
+        in_flight.spawn_blocking(move || {
+            let mut conversion = convert_file(input, &output, format, inline, check_definitions);
+            if let Some(project) = fsh_project {
+                conversion.attach_fsh_source(&project);
+            }
+            conversion
+        });
+    }
+    while let Some(conversion) = in_flight.join_next().await {
+        report.record(conversion?);
+    }
+    report.write_pending();
+
+    report.converted.sort_by(|a, b| a.input.cmp(&b.input));
+    report.failed.sort_by(|a, b| a.input.cmp(&b.input));
+    report.skipped.sort_by(|a, b| a.input.cmp(&b.input));
+    if let Some(path) = &args.report {
+        fs::write(path, serde_json::to_string_pretty(&report)?)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+    }
+
+    println!(
+        "{} converted, {} failed, {} skipped",
+        report.converted.len(),
+        report.failed.len(),
+        report.skipped.len()
+    );
+    Ok(report.failed.len() <= args.max_failures)
+}
+
+fn convert_file(
+    input: PathBuf,
+    output_dir: &Path,
+    format: BundleFormat,
+    inline: Option<(&HashMap<String, FhirSchema>, usize)>,
+    check_definitions: bool,
+) -> Conversion {
+    let content = match fs::read_to_string(&input) {
+        Ok(content) => content,
+        Err(err) => return conversion_failed(input, format!("read failed: {err}")),
+    };
+    let value: Value = match serde_json::from_str(&content) {
+        Ok(value) => value,
+        Err(err) => return conversion_failed(input, format!("invalid JSON: {err}")),
+    };
+    match value.get("resourceType").and_then(Value::as_str) {
+        Some("StructureDefinition") => {}
+        other => {
+            return Conversion::Skipped(FileIssue {
+                input,
+                reason: format!("resourceType is {}", other.unwrap_or("missing")),
+                fsh_source: None,
+            });
+        }
+    }
+
+    if check_definitions {
+        let errors = check_structure_definition(&value);
+        if !errors.is_empty() {
+            let reasons: Vec<String> = errors.iter().map(ToString::to_string).collect();
+            return conversion_failed(input, format!("invalid definition: {}", reasons.join("; ")));
+        }
+    }
+
+    let structure_definition: StructureDefinition = match serde_json::from_value(value) {
+        Ok(sd) => sd,
+        Err(err) => return conversion_failed(input, format!("invalid StructureDefinition: {err}")),
+    };
+    let schema = match translate(structure_definition, None) {
+        Ok(schema) => schema,
+        Err(err) => return conversion_failed(input, format!("conversion failed: {err}")),
+    };
+    let schema = match inline {
+        Some((types, depth)) => inline_types(&schema, types, depth),
+        None => schema,
+    };
+
+    let output = output_dir.join(format!("{}.{}", schema.name, format.extension()));
+    let bytes = match encode_schema(&schema, format) {
+        Ok(bytes) => bytes,
+        Err(err) => return conversion_failed(input, format!("encoding failed: {err}")),
+    };
+    Conversion::Encoded(
+        ConvertedFile {
+            input,
+            output,
+            url: schema.url,
+            fsh_source: None,
+        },
+        bytes,
+    )
+}
+
+fn conversion_failed(input: PathBuf, reason: String) -> Conversion {
+    Conversion::Failed(FileIssue {
+        input,
+        reason,
+        fsh_source: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoded(input: &str, output: &Path) -> Conversion {
+        Conversion::Encoded(
+            ConvertedFile {
+                input: PathBuf::from(input),
+                output: output.to_path_buf(),
+                url: format!("http://example.org/{input}"),
+                fsh_source: None,
+            },
+            b"{}".to_vec(),
+        )
+    }
+
+    #[test]
+    fn test_conversions_sharing_an_output_all_fail() {
+        let dir = std::env::temp_dir().join(format!("fhirschema-convert-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let shared = dir.join("SharedName.json");
+        let unique = dir.join("Unique.json");
+
+        let mut report = ConversionReport::default();
+        report.record(encoded("b/profile.json", &shared));
+        report.record(encoded("unique.json", &unique));
+        report.record(encoded("a/profile.json", &shared));
+        report.write_pending();
+
+        assert_eq!(report.converted.len(), 1);
+        assert_eq!(report.converted[0].output, unique);
+        assert!(unique.is_file());
+        assert!(!shared.exists());
+        assert_eq!(report.failed.len(), 2);
+        assert!(report.failed[0].reason.contains("a/profile.json"));
+        assert!(report.failed[1].reason.contains("b/profile.json"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,257 @@
+use crate::DiffVersionsArgs;
+use anyhow::Result;
+use octofhir_fhirschema::{FhirSchema, FhirSchemaElement, get_schemas};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Debug, Serialize)]
+struct VersionDiff {
+    from: String,
+    to: String,
+    added_resources: Vec<String>,
+    removed_resources: Vec<String>,
+    resources: BTreeMap<String, ResourceDiff>,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct ResourceDiff {
+    added: Vec<String>,
+    removed: Vec<String>,
+    renamed: Vec<ElementChange>,
+    type_changes: Vec<ElementChange>,
+    cardinality_changes: Vec<ElementChange>,
+}
+
+impl ResourceDiff {
+    fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.renamed.is_empty()
+            && self.type_changes.is_empty()
+            && self.cardinality_changes.is_empty()
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ElementChange {
+    path: String,
+    from: String,
+    to: String,
+}
+
+/// Type and cardinality of one element, flattened for comparison.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ElementShape {
+    types: String,
+    cardinality: String,
+}
+
+/// Report added, removed and renamed elements and type and cardinality
+/// changes for every resource defined in both versions.
+///
+/// Only elements declared by the resource itself are compared; changes to
+/// the datatypes it uses are not followed. A rename is reported when exactly one element under a
+/// parent disappeared and exactly one with the same shape appeared.
+pub(crate) fn diff_versions(args: DiffVersionsArgs) -> Result<bool> {
+    let from = get_schemas(args.from.schema_version())?;
+    let to = get_schemas(args.to.schema_version())?;
+    let resources = |schemas: &HashMap<String, FhirSchema>| -> BTreeMap<String, FhirSchema> {
+        schemas
+            .values()
+            .filter(|schema| {
+                schema.kind == "resource"
+                    && schema.derivation.as_deref() != Some("constraint")
+                    && (args.resources.is_empty() || args.resources.contains(&schema.name))
+            })
+            .map(|schema| (schema.name.clone(), schema.clone()))
+            .collect()
+    };
+    let from_resources = resources(from);
+    let to_resources = resources(to);
+
+    let mut report = VersionDiff {
+        from: args.from.schema_version().as_str().to_string(),
+        to: args.to.schema_version().as_str().to_string(),
+        added_resources: to_resources
+            .keys()
+            .filter(|name| !from_resources.contains_key(*name))
+            .cloned()
+            .collect(),
+        removed_resources: from_resources
+            .keys()
+            .filter(|name| !to_resources.contains_key(*name))
+            .cloned()
+            .collect(),
+        resources: BTreeMap::new(),
+    };
+
+    for (name, old) in &from_resources {
+        let Some(new) = to_resources.get(name) else {
+            continue;
+        };
+        let diff = diff_resource(&element_shapes(old), &element_shapes(new));
+        if !diff.is_empty() {
+            report.resources.insert(name.clone(), diff);
+        }
+    }
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(true);
+    }
+
+    println!("FHIR {} -> {}", report.from, report.to);
+    for name in &report.added_resources {
+        println!("+ {name}");
+    }
+    for name in &report.removed_resources {
+        println!("- {name}");
+    }
+    for (name, diff) in &report.resources {
+        println!("{name}");
+        for path in &diff.added {
+            println!("  + {path}");
+        }
+        for path in &diff.removed {
+            println!("  - {path}");
+        }
+        for change in &diff.renamed {
+            println!("  renamed {} -> {}", change.from, change.to);
+        }
+        for change in &diff.type_changes {
+            println!("  {} type {} -> {}", change.path, change.from, change.to);
+        }
+        for change in &diff.cardinality_changes {
+            println!(
+                "  {} cardinality {} -> {}",
+                change.path, change.from, change.to
+            );
+        }
+    }
+    Ok(true)
+}
+
+fn diff_resource(
+    old: &BTreeMap<String, ElementShape>,
+    new: &BTreeMap<String, ElementShape>,
+) -> ResourceDiff {
+    let mut diff = ResourceDiff::default();
+    let mut removed: Vec<&String> = old.keys().filter(|p| !new.contains_key(*p)).collect();
+    let mut added: Vec<&String> = new.keys().filter(|p| !old.contains_key(*p)).collect();
+
+    let parent = |path: &str| path.rsplit_once('.').map(|(p, _)| p.to_string());
+    let siblings = |paths: &[&String],
+                    shapes: &BTreeMap<String, ElementShape>,
+                    path: &str,
+                    shape: &ElementShape| {
+        paths
+            .iter()
+            .copied()
+            .filter(|p| parent(p) == parent(path) && shapes[p.as_str()] == *shape)
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+    let mut renamed = Vec::new();
+    for &old_path in &removed {
+        let shape = &old[old_path];
+        let matches = siblings(&added, new, old_path, shape);
+        if matches.len() == 1 && siblings(&removed, old, old_path, shape).len() == 1 {
+            renamed.push((old_path.clone(), matches[0].clone()));
+        }
+    }
+    for (old_path, new_path) in renamed {
+        removed.retain(|p| **p != old_path);
+        added.retain(|p| **p != new_path);
+        diff.renamed.push(ElementChange {
+            path: old_path.clone(),
+            from: old_path,
+            to: new_path,
+        });
+    }
+    diff.removed = removed.into_iter().cloned().collect();
+    diff.added = added.into_iter().cloned().collect();
+
+    for (path, old_shape) in old {
+        let Some(new_shape) = new.get(path) else {
+            continue;
+        };
+        if old_shape.types != new_shape.types {
+            diff.type_changes.push(ElementChange {
+                path: path.clone(),
+                from: old_shape.types.clone(),
+                to: new_shape.types.clone(),
+            });
+        }
+        if old_shape.cardinality != new_shape.cardinality {
+            diff.cardinality_changes.push(ElementChange {
+                path: path.clone(),
+                from: old_shape.cardinality.clone(),
+                to: new_shape.cardinality.clone(),
+            });
+        }
+    }
+    diff
+}
+
+/// Flatten a schema's own elements (including backbone elements) into
+/// dot-separated paths. Choice variants are folded into their choice element.
+fn element_shapes(schema: &FhirSchema) -> BTreeMap<String, ElementShape> {
+    fn walk(
+        elements: &HashMap<String, FhirSchemaElement>,
+        required: Option<&Vec<String>>,
+        prefix: &str,
+        out: &mut BTreeMap<String, ElementShape>,
+    ) {
+        for (name, element) in elements {
+            if element.choice_of.is_some() {
+                continue;
+            }
+            let path = if prefix.is_empty() {
+                name.clone()
+            } else {
+                format!("{prefix}.{name}")
+            };
+            let types = match (&element.choices, &element.type_name) {
+                (Some(choices), _) => {
+                    let mut types: Vec<&str> = choices
+                        .iter()
+                        .map(|choice| {
+                            elements
+                                .get(choice)
+                                .and_then(|e| e.type_name.as_deref())
+                                .unwrap_or(choice.as_str())
+                        })
+                        .collect();
+                    types.sort_unstable();
+                    types.join("|")
+                }
+                (None, Some(type_name)) => type_name.clone(),
+                (None, None) => "BackboneElement".to_string(),
+            };
+            let min = element
+                .min
+                .unwrap_or(i32::from(required.is_some_and(|r| r.contains(name))));
+            let max = match element.max {
+                Some(max) => max.to_string(),
+                None if element.array == Some(true) => "*".to_string(),
+                None => "1".to_string(),
+            };
+            out.insert(
+                path.clone(),
+                ElementShape {
+                    types,
+                    cardinality: format!("{min}..{max}"),
+                },
+            );
+            if let Some(children) = &element.elements {
+                walk(children, element.required.as_ref(), &path, out);
+            }
+        }
+    }
+
+    let mut out = BTreeMap::new();
+    if let Some(elements) = &schema.elements {
+        walk(elements, schema.required.as_ref(), "", &mut out);
+    }
+    out
+}
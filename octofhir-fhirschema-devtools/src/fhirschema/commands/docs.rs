@@ -0,0 +1,21 @@
+use super::{find_schema, schema_chain, write_output};
+use crate::{DocsArgs, DocsFormat};
+use anyhow::{Context, Result};
+use octofhir_fhirschema::docs::SchemaDoc;
+
+/// Render the documentation of a schema, resolved against its base chain.
+pub(crate) fn docs(args: DocsArgs) -> Result<bool> {
+    let schemas = args.schemas.load(args.fhir_version.schema_version())?;
+
+    let root = find_schema(&schemas, &args.schema)
+        .with_context(|| format!("unknown schema {}", args.schema))?;
+    let chain = schema_chain(&schemas, root);
+    let doc = SchemaDoc::new(root, &chain[1..]);
+    let rendered = match args.format {
+        DocsFormat::Markdown => doc.to_markdown(),
+        DocsFormat::Html => doc.to_html(),
+    };
+
+    write_output(args.output.as_deref(), &rendered)?;
+    Ok(true)
+}
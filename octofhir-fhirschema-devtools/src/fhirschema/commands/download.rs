@@ -0,0 +1,136 @@
+use crate::DownloadArgs;
+use crate::registry::{
+    LOCKFILE_VERSION, LockedPackage, Lockfile, PackageRegistry, install_package,
+    read_package_manifest,
+};
+use anyhow::{Context, Result, bail};
+use octofhir_fhirschema::package::{PackageGraph, PackageManifest};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Name of the synthetic package depending on every requested package, so
+/// the whole install resolves as one dependency closure.
+const DOWNLOAD_ROOT: &str = "fhirschema.download";
+
+/// Install a package set from the registry, or from the lockfile with
+/// --frozen, and write the lockfile.
+pub(crate) async fn download(args: DownloadArgs) -> Result<bool> {
+    fs::create_dir_all(&args.output)
+        .with_context(|| format!("failed to create {}", args.output.display()))?;
+    if args.frozen {
+        return install_locked(&args).await;
+    }
+    if args.packages.is_empty() {
+        bail!("no packages to download: pass name#version or use --frozen");
+    }
+
+    let mut root = PackageManifest::new(DOWNLOAD_ROOT, "0");
+    for spec in &args.packages {
+        let (name, version) = spec.split_once('#').unwrap_or((spec.as_str(), "latest"));
+        root = root.with_dependency(name, version);
+    }
+    let mut graph = PackageGraph::new();
+    graph.add_package(root);
+
+    let mut registry = PackageRegistry::new(&args.registry);
+    let mut packages = BTreeMap::new();
+    let resolved = loop {
+        let resolved = graph.resolve(DOWNLOAD_ROOT, "0")?;
+        if resolved.missing.is_empty() {
+            break resolved;
+        }
+        for (name, requested) in resolved.missing {
+            let (version, url) = registry.pin(&name, &requested).await?;
+            let tarball = registry.fetch(&url).await?;
+            let mut manifest = read_package_manifest(&tarball)
+                .with_context(|| format!("invalid package {name}#{version}"))?;
+            install_package(&args.output, &name, &version, &tarball)?;
+            packages.insert(
+                name,
+                LockedPackage {
+                    version,
+                    resolved: url,
+                    sha256: format!("{:x}", Sha256::digest(&tarball)),
+                    dependencies: manifest.dependencies.clone(),
+                },
+            );
+            // Record it under the requested version so the graph finds it
+            manifest.version = requested;
+            graph.add_package(manifest);
+        }
+    };
+    for conflict in &resolved.conflicts {
+        for (version, requested_by) in &conflict.requested {
+            eprintln!(
+                "warning: {} {version} requested by {requested_by}, using {}",
+                conflict.name, conflict.selected
+            );
+        }
+    }
+
+    let lockfile = Lockfile {
+        version: LOCKFILE_VERSION,
+        registry: registry.base_url,
+        roots: args.packages.clone(),
+        packages,
+    };
+    fs::write(
+        &args.lockfile,
+        serde_json::to_string_pretty(&lockfile)? + "\n",
+    )
+    .with_context(|| format!("failed to write {}", args.lockfile.display()))?;
+    print_installed(&lockfile, &args.output);
+    Ok(true)
+}
+
+/// Install the lockfile's packages, reusing tarballs already on disk whose
+/// hash matches.
+async fn install_locked(args: &DownloadArgs) -> Result<bool> {
+    let content = fs::read_to_string(&args.lockfile)
+        .with_context(|| format!("failed to read {}", args.lockfile.display()))?;
+    let lockfile: Lockfile = serde_json::from_str(&content)
+        .with_context(|| format!("failed to parse {}", args.lockfile.display()))?;
+    if lockfile.version != LOCKFILE_VERSION {
+        bail!("unsupported lockfile version {}", lockfile.version);
+    }
+    if !args.packages.is_empty() && args.packages != lockfile.roots {
+        bail!(
+            "{} was written for {}; run without --frozen to update it",
+            args.lockfile.display(),
+            lockfile.roots.join(", ")
+        );
+    }
+
+    let registry = PackageRegistry::new(&lockfile.registry);
+    for (name, package) in &lockfile.packages {
+        let cached = args.output.join(format!("{name}-{}.tgz", package.version));
+        let tarball = match fs::read(&cached) {
+            Ok(bytes) if format!("{:x}", Sha256::digest(&bytes)) == package.sha256 => bytes,
+            _ => registry.fetch(&package.resolved).await?,
+        };
+        let sha256 = format!("{:x}", Sha256::digest(&tarball));
+        if sha256 != package.sha256 {
+            bail!(
+                "{name}#{}: sha256 {sha256} does not match the lockfile's {}",
+                package.version,
+                package.sha256
+            );
+        }
+        install_package(&args.output, name, &package.version, &tarball)?;
+    }
+    print_installed(&lockfile, &args.output);
+    Ok(true)
+}
+
+fn print_installed(lockfile: &Lockfile, output: &Path) {
+    for (name, package) in &lockfile.packages {
+        println!("{name}#{}", package.version);
+    }
+    println!(
+        "{} package(s) installed in {}",
+        lockfile.packages.len(),
+        output.display()
+    );
+}
@@ -0,0 +1,49 @@
+use crate::ExplainArgs;
+use anyhow::{Context, Result};
+use octofhir_fhirschema::error_catalog::{self, ErrorCodeInfo};
+
+/// Describe one error code, or list the whole catalog.
+pub(crate) fn explain(args: ExplainArgs) -> Result<bool> {
+    let entries: Vec<&ErrorCodeInfo> = match &args.code {
+        Some(code) => vec![
+            error_catalog::lookup(code)
+                .with_context(|| format!("unknown error code {code}; run `explain` to list all"))?,
+        ],
+        None => error_catalog::all().iter().collect(),
+    };
+
+    if args.json {
+        let json = match entries.as_slice() {
+            [entry] if args.code.is_some() => serde_json::to_string_pretty(entry)?,
+            _ => serde_json::to_string_pretty(&entries)?,
+        };
+        println!("{json}");
+        return Ok(true);
+    }
+
+    if args.code.is_none() {
+        for entry in entries {
+            println!(
+                "{:<8} {:<8} {}",
+                entry.code,
+                format!("{:?}", entry.severity).to_lowercase(),
+                entry.name
+            );
+        }
+        return Ok(true);
+    }
+
+    for entry in entries {
+        println!("{} {} ({:?})", entry.code, entry.name, entry.family);
+        println!();
+        println!("  {}", entry.description);
+        println!();
+        println!(
+            "  Default severity: {}",
+            format!("{:?}", entry.severity).to_lowercase()
+        );
+        println!("  Specification:    {}", entry.spec);
+        println!("  Remediation:      {}", entry.remediation);
+    }
+    Ok(true)
+}
@@ -0,0 +1,183 @@
+use super::{find_schema, schema_chain};
+use crate::InspectArgs;
+use anyhow::{Context, Result, bail};
+use octofhir_fhirschema::path_navigator::split_path;
+use octofhir_fhirschema::types::{FhirSchemaConstraint, FhirSchemaSliceMatch};
+use octofhir_fhirschema::{FhirSchema, FhirSchemaElement, PathNavigator, merge_profile_chain};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// The effective definition of an element path and the schema it came from.
+#[derive(Debug, Serialize)]
+struct ElementLayer<'a> {
+    schema: String,
+    element: FhirSchemaElement,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    slice: Option<&'a FhirSchemaSliceMatch>,
+}
+
+/// Print the effective definition of an element path.
+///
+/// The schema is merged with its base chain and the path resolved by
+/// [`PathNavigator`], the way the validator sees it, so slices
+/// (`identifier:mrn`) and extensions (`extension('race')`) resolve as in the
+/// library. A path that goes below what the schema defines continues into
+/// the schema of the deepest typed element on the way.
+pub(crate) fn inspect(args: InspectArgs) -> Result<bool> {
+    let schemas = args.schemas.load(args.fhir_version.schema_version())?;
+
+    let root = find_schema(&schemas, &args.schema)
+        .with_context(|| format!("unknown schema {}", args.schema))?;
+    let path = args.path.as_deref().unwrap_or_default();
+    let segments = split_path(path);
+
+    if segments.is_empty() {
+        for schema in schema_chain(&schemas, root) {
+            println!(
+                "{} ({}, {}) {}",
+                schema.name,
+                schema.kind,
+                schema.derivation.as_deref().unwrap_or("specialization"),
+                schema.url
+            );
+            print_constraints(schema.constraint.as_ref(), "  ");
+        }
+        return Ok(true);
+    }
+
+    let mut merged = merged_schema(&schemas, root);
+    let mut rest = segments.as_slice();
+    let (element, slice) = loop {
+        let navigator = PathNavigator::new(&merged);
+        if let Some(element) = navigator.element(&rest.join(".")) {
+            let slice = navigator.slice(&rest.join(".")).cloned();
+            break (element, slice);
+        }
+        let Some((len, type_schema)) = (1..rest.len()).rev().find_map(|len| {
+            let type_name = navigator.element(&rest[..len].join("."))?.type_name?;
+            Some((len, find_schema(&schemas, &type_name)?))
+        }) else {
+            bail!("{} has no element {path}", args.schema);
+        };
+        merged = merged_schema(&schemas, type_schema);
+        rest = &rest[len..];
+    };
+    let layer = ElementLayer {
+        schema: merged.name.clone(),
+        element,
+        slice: slice.as_ref(),
+    };
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&layer)?);
+        return Ok(true);
+    }
+
+    println!("{}.{}", args.schema, segments.join("."));
+    print_element(&layer);
+    Ok(true)
+}
+
+/// A schema merged with its base chain, root first, as the validator and
+/// [`PathNavigator`] see it.
+fn merged_schema(schemas: &HashMap<String, FhirSchema>, schema: &FhirSchema) -> FhirSchema {
+    let chain: Vec<Arc<FhirSchema>> = schema_chain(schemas, schema)
+        .into_iter()
+        .rev()
+        .map(|schema| Arc::new(schema.clone()))
+        .collect();
+    merge_profile_chain(&chain)
+}
+
+fn print_element(layer: &ElementLayer<'_>) {
+    let element = &layer.element;
+    println!("  from {}:", layer.schema);
+    if let Some(slice) = layer.slice {
+        let max = slice.max.map_or("*".to_string(), |max| max.to_string());
+        println!("    slice: {}..{max}", slice.min.unwrap_or(0));
+        if let Some(pattern) = &slice.match_value {
+            println!("    match: {pattern}");
+        }
+    }
+    if let Some(short) = &element.short {
+        println!("    short: {short}");
+    }
+    if let Some(choices) = &element.choices {
+        println!("    choice of: {}", choices.join(" | "));
+    } else if let Some(type_name) = &element.type_name {
+        println!("    type: {type_name}");
+    }
+    if element.min.is_some() || element.max.is_some() || element.array.is_some() {
+        let max = match element.max {
+            Some(max) => max.to_string(),
+            None if element.array == Some(true) => "*".to_string(),
+            None => "1".to_string(),
+        };
+        println!("    cardinality: {}..{max}", element.min.unwrap_or(0));
+    }
+    if let Some(refers) = &element.refers {
+        println!("    refers: {}", refers.join(", "));
+    }
+    if let Some(binding) = &element.binding {
+        println!(
+            "    binding: {} {}",
+            binding.strength,
+            binding.value_set.as_deref().unwrap_or("(no value set)")
+        );
+    }
+    if let Some(pattern) = &element.pattern {
+        println!("    pattern ({}): {}", pattern.type_name, pattern.value);
+    }
+    if element.must_support == Some(true) {
+        println!("    must support");
+    }
+    if element.is_modifier == Some(true) {
+        println!("    modifier element");
+    }
+    print_constraints(element.constraint.as_ref(), "    ");
+    if let Some(slicing) = &element.slicing {
+        let discriminators = slicing
+            .discriminator
+            .iter()
+            .flatten()
+            .map(|d| format!("{}:{}", d.type_name, d.path))
+            .collect::<Vec<_>>()
+            .join(", ");
+        println!(
+            "    slicing: rules={} discriminator=[{discriminators}]",
+            slicing.rules.as_deref().unwrap_or("open")
+        );
+        let mut slices: Vec<_> = slicing.slices.iter().flatten().collect();
+        slices.sort_by_key(|(name, _)| name.as_str());
+        for (name, slice) in slices {
+            let max = slice.max.map_or("*".to_string(), |max| max.to_string());
+            println!("      {name} {}..{max}", slice.min.unwrap_or(0));
+        }
+    }
+    if let Some(elements) = &element.elements {
+        let mut children: Vec<_> = elements.keys().map(String::as_str).collect();
+        children.sort_unstable();
+        println!("    children: {}", children.join(", "));
+    }
+}
+
+fn print_constraints(constraints: Option<&HashMap<String, FhirSchemaConstraint>>, indent: &str) {
+    let Some(constraints) = constraints else {
+        return;
+    };
+    let mut keys: Vec<_> = constraints.keys().collect();
+    keys.sort();
+    for key in keys {
+        let constraint = &constraints[key];
+        let suppressed = if constraint.is_suppressed() {
+            " [suppressed]"
+        } else {
+            ""
+        };
+        println!(
+            "{indent}constraint {key} ({}){suppressed}: {}",
+            constraint.severity, constraint.expression
+        );
+    }
+}
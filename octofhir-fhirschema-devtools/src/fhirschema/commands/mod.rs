@@ -0,0 +1,67 @@
+//! One module per subcommand.
+
+mod conformance;
+mod convert;
+mod diff_versions;
+mod docs;
+mod download;
+mod explain;
+mod inspect;
+mod package;
+mod validate;
+mod viz;
+
+use anyhow::{Context, Result};
+use octofhir_fhirschema::FhirSchema;
+use std::collections::HashMap;
+use std::path::Path;
+
+pub(crate) use conformance::conformance_check;
+pub(crate) use convert::convert;
+pub(crate) use diff_versions::diff_versions;
+pub(crate) use docs::docs;
+pub(crate) use download::download;
+pub(crate) use explain::explain;
+pub(crate) use inspect::inspect;
+pub(crate) use package::build_package;
+pub(crate) use validate::validate;
+pub(crate) use viz::viz;
+
+/// Write `contents` to `path`, or to stdout when no path is given.
+fn write_output(path: Option<&Path>, contents: &str) -> Result<()> {
+    match path {
+        Some(path) => std::fs::write(path, contents)
+            .with_context(|| format!("failed to write {}", path.display())),
+        None => {
+            print!("{contents}");
+            Ok(())
+        }
+    }
+}
+
+/// A schema followed by its base schemas, most specific first.
+fn schema_chain<'a>(
+    schemas: &'a HashMap<String, FhirSchema>,
+    schema: &'a FhirSchema,
+) -> Vec<&'a FhirSchema> {
+    let mut chain = vec![schema];
+    let mut current = schema;
+    while let Some(base) = current
+        .base
+        .as_deref()
+        .and_then(|url| find_schema(schemas, url))
+    {
+        if chain.iter().any(|seen| seen.url == base.url) {
+            break;
+        }
+        chain.push(base);
+        current = base;
+    }
+    chain
+}
+
+fn find_schema<'a>(schemas: &'a HashMap<String, FhirSchema>, key: &str) -> Option<&'a FhirSchema> {
+    schemas
+        .get(key)
+        .or_else(|| schemas.values().find(|schema| schema.url == key))
+}
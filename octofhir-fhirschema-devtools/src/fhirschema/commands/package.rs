@@ -0,0 +1,163 @@
+use crate::PackageBuildArgs;
+use crate::report::format_path;
+use anyhow::{Context, Result};
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use octofhir_fhirschema::package::PackageManifest;
+use octofhir_fhirschema::{FhirValidator, get_schema_manifest, get_schemas};
+use octofhir_fhirschema_devtools::schema_files::{
+    collect_json_files, insert_schema_aliases, read_schema_file,
+};
+use serde_json::{Value, json};
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Convert and validate every resource under a directory and write them as
+/// a FHIR NPM package with `package.json` and `.index.json`.
+///
+/// Nothing is written when a StructureDefinition fails to convert or a
+/// resource fails structural validation.
+pub(crate) async fn build_package(args: PackageBuildArgs) -> Result<bool> {
+    let version = args.fhir_version.schema_version();
+    let core = get_schema_manifest(version)
+        .with_context(|| format!("no manifest for embedded {} schemas", version.as_str()))?;
+    let mut manifest = PackageManifest::new(&args.id, &args.version)
+        .with_dependency(&core.package_name, &core.package_version);
+    for spec in &args.dependencies {
+        let (name, dep_version) = spec
+            .split_once('#')
+            .filter(|(name, v)| !name.is_empty() && !v.is_empty())
+            .with_context(|| format!("dependency must be name#version, got {spec}"))?;
+        manifest = manifest.with_dependency(name, dep_version);
+    }
+
+    let mut files = Vec::new();
+    collect_json_files(&args.dir, &mut files)
+        .with_context(|| format!("failed to scan {}", args.dir.display()))?;
+
+    let mut schemas = get_schemas(version)?.clone();
+    let mut resources = Vec::new();
+    let mut failures = Vec::new();
+    for path in files {
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let resource: Value = serde_json::from_str(&content)
+            .with_context(|| format!("failed to parse {}", path.display()))?;
+        if resource
+            .get("resourceType")
+            .and_then(Value::as_str)
+            .is_none()
+        {
+            println!("skipped {}: not a FHIR resource", path.display());
+            continue;
+        }
+
+        if resource.get("resourceType").and_then(Value::as_str) == Some("StructureDefinition") {
+            match read_schema_file(&path) {
+                Ok(read) => {
+                    for schema in read {
+                        insert_schema_aliases(&mut schemas, schema);
+                    }
+                }
+                Err(err) => failures.push(format!("{}: {err:#}", path.display())),
+            }
+        }
+        resources.push((path, resource));
+    }
+
+    let validator = FhirValidator::from_schemas(schemas, None);
+    let mut index = Vec::with_capacity(resources.len());
+    let mut entries = Vec::with_capacity(resources.len());
+    for (path, resource) in resources {
+        let resource_type = resource["resourceType"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+        let result = validator
+            .validate(&resource, vec![resource_type.clone()])
+            .await;
+        for error in &result.errors {
+            failures.push(format!(
+                "{}: {} at {}: {error}",
+                path.display(),
+                error.error_type,
+                format_path(&error.path)
+            ));
+        }
+
+        let Some(id) = resource.get("id").and_then(Value::as_str) else {
+            failures.push(format!("{}: resource has no id", path.display()));
+            continue;
+        };
+        let filename = format!("{resource_type}-{id}.json");
+        if index
+            .iter()
+            .any(|entry: &Value| entry["filename"] == filename)
+        {
+            failures.push(format!("{}: duplicate resource {filename}", path.display()));
+            continue;
+        }
+
+        let mut entry = json!({ "filename": filename, "resourceType": resource_type, "id": id });
+        for key in ["url", "version", "kind", "type", "supplements", "content"] {
+            if let Some(value) = resource.get(key).filter(|v| v.is_string()) {
+                entry[key] = value.clone();
+            }
+        }
+        index.push(entry);
+        entries.push((filename, resource));
+    }
+
+    if !failures.is_empty() {
+        for failure in &failures {
+            println!("FAIL {failure}");
+        }
+        println!("{} problem(s) found, package not written", failures.len());
+        return Ok(false);
+    }
+
+    let mut package_json = serde_json::to_value(&manifest)?;
+    package_json["fhirVersions"] = json!([core.package_version]);
+    package_json["type"] = json!("fhir.ig");
+    let index_json = json!({ "index-version": 2, "files": index });
+
+    let output = args
+        .output
+        .unwrap_or_else(|| PathBuf::from(format!("{}-{}.tgz", args.id, args.version)));
+    let file = fs::File::create(&output)
+        .with_context(|| format!("failed to create {}", output.display()))?;
+    let mut tarball = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+    append_json(&mut tarball, "package.json", &package_json)?;
+    append_json(&mut tarball, ".index.json", &index_json)?;
+    for (filename, resource) in &entries {
+        append_json(&mut tarball, filename, resource)?;
+    }
+    tarball
+        .into_inner()?
+        .finish()
+        .with_context(|| format!("failed to write {}", output.display()))?;
+
+    println!(
+        "wrote {}#{} ({} resources) to {}",
+        args.id,
+        args.version,
+        entries.len(),
+        output.display()
+    );
+    Ok(true)
+}
+
+fn append_json<W: Write>(
+    tarball: &mut tar::Builder<W>,
+    filename: &str,
+    value: &Value,
+) -> Result<()> {
+    let bytes = serde_json::to_vec_pretty(value)?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(bytes.len() as u64);
+    header.set_mode(0o644);
+    tarball
+        .append_data(&mut header, format!("package/{filename}"), bytes.as_slice())
+        .with_context(|| format!("failed to add {filename} to package"))
+}
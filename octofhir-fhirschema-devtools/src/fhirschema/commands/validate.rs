@@ -0,0 +1,336 @@
+use crate::config::CONFIG_FILE;
+use crate::report::{
+    FileReport, InputError, JSON_REPORT_VERSION, JsonReport, Outcome, RunSummary, junit,
+    print_text, sarif,
+};
+use crate::terminology::{RecordingTerminology, Terminology, terminology_service};
+use crate::{OutputFormat, ValidateArgs, VersionArg};
+use anyhow::{Context, Result, bail};
+use octofhir_fhirschema::validation::apply_fixes;
+use octofhir_fhirschema::{
+    CacheTuning, FhirSchema, FhirValidator, ValidationOptions, ValidationResult, parse_resource,
+};
+use octofhir_fhirschema_devtools::schema_files::{
+    fhirpath_validator, insert_schema_aliases, read_schema_file,
+};
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tokio::task::JoinSet;
+
+/// Validator plus the per-resource schema selection from the command line.
+pub(super) struct ResourceValidator {
+    pub(super) validator: FhirValidator,
+    pub(super) profiles: Vec<String>,
+    pub(super) type_profiles: HashMap<String, Vec<String>>,
+    pub(super) meta_profile: bool,
+    pub(super) options: ValidationOptions,
+}
+
+impl ResourceValidator {
+    pub(super) async fn check(
+        &self,
+        path: &Path,
+        line: Option<usize>,
+        resource: &Value,
+    ) -> FileReport {
+        let resource_type = resource
+            .get("resourceType")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+
+        let mut schema_names: Vec<String> = resource_type.iter().cloned().collect();
+        let extra = if self.meta_profile {
+            meta_profiles(resource)
+        } else {
+            vec![]
+        };
+        let typed = resource_type
+            .as_ref()
+            .and_then(|resource_type| self.type_profiles.get(resource_type))
+            .into_iter()
+            .flatten();
+        for name in self.profiles.iter().chain(typed).chain(&extra) {
+            if !schema_names.contains(name) {
+                schema_names.push(name.clone());
+            }
+        }
+
+        let result = if schema_names.is_empty() {
+            single_error(
+                "FS1002",
+                "Resource has no resourceType and no --profile was given",
+            )
+        } else {
+            self.validator
+                .validate_with_options(resource, schema_names.clone(), &self.options)
+                .await
+        };
+        FileReport {
+            path: path.to_path_buf(),
+            line,
+            resource_type,
+            schema_names,
+            result,
+        }
+    }
+}
+
+/// Validate every input file and print the results.
+pub(crate) async fn validate(args: ValidateArgs) -> Result<Outcome> {
+    if args.files.is_empty() {
+        bail!("no files to validate: pass them or set `[validate] files` in {CONFIG_FILE}");
+    }
+    let mut schemas = args.schemas.load(args.fhir_version.schema_version())?;
+    let profiles = resolve_profiles(&args.profiles, &mut schemas)?;
+    let mut type_profiles = HashMap::new();
+    for (resource_type, names) in &args.type_profiles {
+        type_profiles.insert(
+            resource_type.clone(),
+            resolve_profiles(names, &mut schemas)?,
+        );
+    }
+    let tuning = CacheTuning::embedded_cli();
+    let Terminology {
+        service,
+        cache: terminology_cache,
+    } = terminology_service(&args, &tuning)?;
+    let terminology = Arc::new(RecordingTerminology {
+        inner: service,
+        unchecked: Mutex::new(BTreeSet::new()),
+    });
+    let mut validator = create_validator(schemas, args.fhir_version, args.fhirpath)
+        .await?
+        .with_terminology_service(terminology.clone())
+        .with_cache_tuning(&tuning);
+    if let Some(dir) = &args.compiled_cache {
+        validator = validator.with_compiled_cache_dir(dir);
+    }
+    let validator = Arc::new(ResourceValidator {
+        validator,
+        profiles,
+        type_profiles,
+        meta_profile: args.meta_profile,
+        options: ValidationOptions {
+            explain: args.explain,
+            ..Default::default()
+        },
+    });
+
+    let mut summary = RunSummary::default();
+    for path in &args.files {
+        if args.ndjson {
+            validate_ndjson(&validator, path, args.concurrency.max(1), &mut summary).await?;
+            continue;
+        }
+
+        let mut content =
+            fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+        let xml = path.extension().is_some_and(|ext| ext == "xml");
+        let mut resource = if xml {
+            validator.validator.parse_xml(&content).await
+        } else {
+            parse_resource(&mut content)
+        }
+        .with_context(|| format!("failed to parse {}", path.display()))?;
+        let mut report = validator.check(path, None, &resource).await;
+        if args.fix && !xml {
+            report = fix_file(&validator, path, &mut resource, report, args.quiet).await?;
+        }
+        summary.record(report, true);
+    }
+    if let Some(cache) = &terminology_cache
+        && let Err(e) = cache.persist()
+    {
+        // The results are still worth printing without the cache
+        eprintln!("failed to write the terminology cache: {e}");
+    }
+    if args.report_unchecked_bindings {
+        summary.unchecked_bindings = terminology
+            .unchecked
+            .lock()
+            .unwrap()
+            .iter()
+            .cloned()
+            .collect();
+    }
+
+    let outcome = Outcome::of(&summary, args.warnings_as_errors);
+    match args.format {
+        OutputFormat::Text => print_text(&summary, args.quiet),
+        OutputFormat::Json => {
+            let report = JsonReport {
+                version: JSON_REPORT_VERSION,
+                outcome,
+                exit_code: outcome as u8,
+                summary: &summary,
+            };
+            println!("{}", serde_json::to_string_pretty(&report)?)
+        }
+        OutputFormat::Sarif => println!("{}", serde_json::to_string_pretty(&sarif(&summary))?),
+        OutputFormat::Junit => print!("{}", junit(&summary)),
+    }
+
+    Ok(outcome)
+}
+
+/// Upper bound on fix-and-revalidate rounds. Fixing an element can uncover
+/// errors inside it (a wrapped scalar whose own children are scalars), and
+/// conflicting profiles could otherwise undo each other's fixes forever.
+const MAX_FIX_ROUNDS: usize = 8;
+
+/// Apply the fixes suggested in `report` to `resource` until none are left,
+/// write the result back to `path` and return the final report.
+async fn fix_file(
+    validator: &ResourceValidator,
+    path: &Path,
+    resource: &mut Value,
+    mut report: FileReport,
+    quiet: bool,
+) -> Result<FileReport> {
+    let mut fixed = 0;
+    for _ in 0..MAX_FIX_ROUNDS {
+        let applied = apply_fixes(resource, &report.result.errors);
+        if applied == 0 {
+            break;
+        }
+        fixed += applied;
+        report = validator.check(path, None, resource).await;
+    }
+    if fixed > 0 {
+        let mut json = serde_json::to_string_pretty(resource)?;
+        json.push('\n');
+        fs::write(path, json).with_context(|| format!("failed to write {}", path.display()))?;
+        if !quiet {
+            eprintln!("{}: applied {fixed} fix(es)", path.display());
+        }
+    }
+    Ok(report)
+}
+
+/// Stream an NDJSON file line by line, validating up to `concurrency`
+/// resources at a time.
+async fn validate_ndjson(
+    validator: &Arc<ResourceValidator>,
+    path: &Path,
+    concurrency: usize,
+    summary: &mut RunSummary,
+) -> Result<()> {
+    let file =
+        fs::File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let first_report = summary.reports.len();
+    let mut in_flight = JoinSet::new();
+
+    for (idx, line) in BufReader::new(file).lines().enumerate() {
+        let line_no = idx + 1;
+        let line = line.with_context(|| format!("failed to read {}:{line_no}", path.display()))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        while in_flight.len() >= concurrency {
+            if let Some(report) = in_flight.join_next().await {
+                summary.record(report?, false);
+            }
+        }
+
+        let resource = match parse_resource(&mut line.into_bytes()) {
+            Ok(resource) => resource,
+            Err(err) => {
+                summary.input_errors.push(InputError {
+                    path: path.to_path_buf(),
+                    line: Some(line_no),
+                    message: err.to_string(),
+                });
+                continue;
+            }
+        };
+        let validator = Arc::clone(validator);
+        let path = path.to_path_buf();
+        in_flight.spawn(async move { validator.check(&path, Some(line_no), &resource).await });
+    }
+
+    while let Some(report) = in_flight.join_next().await {
+        summary.record(report?, false);
+    }
+    summary.reports[first_report..].sort_by_key(|report| report.line);
+    Ok(())
+}
+
+pub(super) async fn create_validator(
+    schemas: HashMap<String, FhirSchema>,
+    version: VersionArg,
+    fhirpath: bool,
+) -> Result<FhirValidator> {
+    if !fhirpath {
+        return Ok(FhirValidator::from_schemas(schemas, None));
+    }
+    fhirpath_validator(schemas, version.model_version()).await
+}
+
+/// Turn `--profile` arguments into schema names, loading any that point at
+/// StructureDefinition or FhirSchema files.
+pub(super) fn resolve_profiles(
+    profiles: &[String],
+    schemas: &mut HashMap<String, FhirSchema>,
+) -> Result<Vec<String>> {
+    let mut names = Vec::with_capacity(profiles.len());
+    for profile in profiles {
+        if schemas.contains_key(profile) {
+            names.push(profile.clone());
+            continue;
+        }
+
+        let path = Path::new(profile);
+        if !path.is_file() {
+            bail!("unknown profile {profile}: not a loaded schema or a schema file");
+        }
+        let [schema] = <[FhirSchema; 1]>::try_from(read_schema_file(path)?).map_err(|_| {
+            anyhow::anyhow!(
+                "{} is not a StructureDefinition or a single FhirSchema",
+                path.display()
+            )
+        })?;
+        names.push(schema.url.clone());
+        insert_schema_aliases(schemas, schema);
+    }
+    Ok(names)
+}
+
+fn meta_profiles(resource: &Value) -> Vec<String> {
+    resource
+        .get("meta")
+        .and_then(|meta| meta.get("profile"))
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .map(str::to_string)
+        .collect()
+}
+
+fn single_error(code: &'static str, message: impl Into<String>) -> ValidationResult {
+    ValidationResult {
+        errors: vec![octofhir_fhirschema::ValidationError {
+            error_type: code.into(),
+            path: Default::default(),
+            message: Some(message.into().into()),
+            value: None,
+            expected: None,
+            got: None,
+            schema_path: None,
+            constraint_key: None,
+            constraint_expression: None,
+            constraint_severity: None,
+            fix: None,
+            schema_url: None,
+            schema_version: None,
+        }],
+        valid: false,
+        warnings: vec![],
+        explain: None,
+    }
+}
@@ -0,0 +1,20 @@
+use super::{find_schema, schema_chain, write_output};
+use crate::{VizArgs, VizFormat};
+use anyhow::{Context, Result};
+use octofhir_fhirschema::diagram::Diagram;
+
+/// Draw a schema as a class diagram, optionally with its derivation chain.
+pub(crate) fn viz(args: VizArgs) -> Result<bool> {
+    let schemas = args.schemas.load(args.fhir_version.schema_version())?;
+
+    let root = find_schema(&schemas, &args.schema)
+        .with_context(|| format!("unknown schema {}", args.schema))?;
+    let chain = schema_chain(&schemas, root);
+    let diagram = Diagram::new(root, &chain[1..], args.derivation);
+    let rendered = match args.format {
+        VizFormat::Mermaid => diagram.to_mermaid(),
+        VizFormat::Dot => diagram.to_dot(),
+    };
+    write_output(args.output.as_deref(), &rendered)?;
+    Ok(true)
+}
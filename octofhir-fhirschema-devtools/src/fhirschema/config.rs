@@ -0,0 +1,347 @@
+//! Project configuration from `fhirschema.toml`.
+
+use crate::{Command, OutputFormat, PackageCommand, SchemaFormatArg, ValidateArgs, VersionArg};
+use anyhow::{Context, Result, bail};
+use clap::ArgMatches;
+use clap::parser::ValueSource;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the project config file looked up in the working directory.
+pub(crate) const CONFIG_FILE: &str = "fhirschema.toml";
+
+/// Project settings from `fhirschema.toml`. Every value only applies where
+/// the command line leaves the matching option unset, so the same config
+/// serves CI runs without flags and local runs that override a few.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub(crate) struct Config {
+    /// FHIR version of the embedded base schemas for every command
+    fhir_version: Option<VersionArg>,
+    /// Schema package directories, as `--schema-package-dir`
+    packages: Vec<PathBuf>,
+    /// Profiles validated per resource type, on top of `--profile`
+    profiles: BTreeMap<String, Vec<String>>,
+    terminology: TerminologyConfig,
+    validate: ValidateConfig,
+    convert: ConvertConfig,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+struct TerminologyConfig {
+    server: Option<String>,
+    cache: Option<PathBuf>,
+    /// Environment variable holding the bearer token, so the token itself
+    /// stays out of version control
+    token_env: Option<String>,
+    headers: Vec<String>,
+    offline: Vec<PathBuf>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+struct ValidateConfig {
+    files: Vec<PathBuf>,
+    fhirpath: bool,
+    meta_profile: bool,
+    format: Option<OutputFormat>,
+    compiled_cache: Option<PathBuf>,
+    warnings_as_errors: bool,
+}
+
+/// Conversion target: where and in which format `convert` writes schemas.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+struct ConvertConfig {
+    inputs: Vec<PathBuf>,
+    fsh: Option<PathBuf>,
+    output: Option<PathBuf>,
+    format: Option<SchemaFormatArg>,
+    inline_depth: Option<usize>,
+    check_definitions: bool,
+    report: Option<PathBuf>,
+}
+
+impl Config {
+    /// Load `path`, or `./fhirschema.toml` when no path is given and it
+    /// exists. Relative paths in the file are taken relative to the file.
+    pub(crate) fn load(path: Option<&Path>) -> Result<Self> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None if Path::new(CONFIG_FILE).is_file() => PathBuf::from(CONFIG_FILE),
+            None => return Ok(Self::default()),
+        };
+        let text = fs::read_to_string(&path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let mut config: Config =
+            toml::from_str(&text).with_context(|| format!("invalid {}", path.display()))?;
+        if config.terminology.server.is_some() && !config.terminology.offline.is_empty() {
+            bail!(
+                "{}: terminology.server and terminology.offline cannot both be set",
+                path.display()
+            );
+        }
+        if let Some(base) = path.parent() {
+            config.rebase(base);
+        }
+        Ok(config)
+    }
+
+    fn rebase(&mut self, base: &Path) {
+        let join = |path: &mut PathBuf| {
+            if path.is_relative() {
+                *path = base.join(&*path);
+            }
+        };
+        let tx = &mut self.terminology;
+        let validate = &mut self.validate;
+        let convert = &mut self.convert;
+        self.packages
+            .iter_mut()
+            .chain(&mut tx.offline)
+            .chain(&mut tx.cache)
+            .chain(&mut validate.files)
+            .chain(&mut validate.compiled_cache)
+            .chain(&mut convert.inputs)
+            .chain(&mut convert.fsh)
+            .chain(&mut convert.output)
+            .chain(&mut convert.report)
+            .for_each(join);
+        for profile in self.profiles.values_mut().flatten() {
+            let path = base.join(&*profile);
+            if Path::new(profile).is_relative() && path.is_file() {
+                *profile = path.to_string_lossy().into_owned();
+            }
+        }
+    }
+
+    /// Fill the options of `command` that `matches` shows were not given on
+    /// the command line.
+    pub(crate) fn apply(&self, command: &mut Command, matches: &ArgMatches) -> Result<()> {
+        let given = |id: &str| from_command_line(matches, id);
+        let version = |arg: &mut VersionArg| {
+            if let Some(version) = self.fhir_version
+                && !given("fhir_version")
+            {
+                *arg = version;
+            }
+        };
+        let packages = |dirs: &mut Vec<PathBuf>| {
+            if dirs.is_empty() {
+                dirs.clone_from(&self.packages);
+            }
+        };
+
+        match command {
+            Command::Validate(args) => {
+                version(&mut args.fhir_version);
+                packages(&mut args.schemas.schema_package_dirs);
+                let validate = &self.validate;
+                if args.files.is_empty() {
+                    args.files.clone_from(&validate.files);
+                }
+                args.type_profiles.clone_from(&self.profiles);
+                args.fhirpath |= validate.fhirpath;
+                args.meta_profile |= validate.meta_profile;
+                args.warnings_as_errors |= validate.warnings_as_errors;
+                if let Some(format) = validate.format
+                    && !given("format")
+                {
+                    args.format = format;
+                }
+                if args.compiled_cache.is_none() {
+                    args.compiled_cache.clone_from(&validate.compiled_cache);
+                }
+                self.apply_terminology(args)?;
+            }
+            Command::Convert(args) => {
+                version(&mut args.fhir_version);
+                let convert = &self.convert;
+                if args.inputs.is_empty() && args.fsh.is_none() {
+                    args.inputs.clone_from(&convert.inputs);
+                    args.fsh.clone_from(&convert.fsh);
+                }
+                if let Some(output) = &convert.output
+                    && !given("output")
+                {
+                    args.output.clone_from(output);
+                }
+                if let Some(format) = convert.format
+                    && !given("format")
+                {
+                    args.format = format;
+                }
+                if args.inline_depth.is_none() {
+                    args.inline_depth = convert.inline_depth;
+                }
+                args.check_definitions |= convert.check_definitions;
+                if args.report.is_none() {
+                    args.report.clone_from(&convert.report);
+                }
+            }
+            Command::Inspect(args) => {
+                version(&mut args.fhir_version);
+                packages(&mut args.schemas.schema_package_dirs);
+            }
+            Command::Docs(args) => {
+                version(&mut args.fhir_version);
+                packages(&mut args.schemas.schema_package_dirs);
+            }
+            Command::Viz(args) => {
+                version(&mut args.fhir_version);
+                packages(&mut args.schemas.schema_package_dirs);
+            }
+            Command::ConformanceCheck(args) => {
+                version(&mut args.fhir_version);
+                packages(&mut args.schemas.schema_package_dirs);
+            }
+            Command::Package {
+                command: PackageCommand::Build(args),
+            } => version(&mut args.fhir_version),
+            Command::Explain(_) | Command::DiffVersions(_) | Command::Download(_) => {}
+        }
+        Ok(())
+    }
+
+    /// Terminology settings only apply when no terminology option was given,
+    /// so a server on the command line is never mixed with config headers.
+    fn apply_terminology(&self, args: &mut ValidateArgs) -> Result<()> {
+        if args.tx_server.is_some() || !args.tx_offline.is_empty() {
+            return Ok(());
+        }
+        let tx = &self.terminology;
+        args.tx_server.clone_from(&tx.server);
+        args.tx_offline.clone_from(&tx.offline);
+        if args.tx_server.is_none() {
+            return Ok(());
+        }
+        args.tx_cache.clone_from(&tx.cache);
+        args.tx_headers.clone_from(&tx.headers);
+        if let Some(var) = &tx.token_env {
+            let token = std::env::var(var)
+                .with_context(|| format!("terminology.token-env: {var} is not set"))?;
+            args.tx_token = Some(token);
+        }
+        Ok(())
+    }
+}
+
+/// Whether option `id` of the innermost subcommand was given on the command
+/// line rather than left at its default.
+fn from_command_line(matches: &ArgMatches, id: &str) -> bool {
+    let mut matches = matches;
+    while let Some((_, sub)) = matches.subcommand() {
+        matches = sub;
+    }
+    matches.value_source(id) == Some(ValueSource::CommandLine)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Cli;
+    use clap::{CommandFactory, FromArgMatches};
+
+    fn parse(args: &[&str]) -> (Command, ArgMatches) {
+        let matches = Cli::command().try_get_matches_from(args).unwrap();
+        let cli = Cli::from_arg_matches(&matches).unwrap();
+        (cli.command, matches)
+    }
+
+    fn validate_args(config: &str, args: &[&str]) -> ValidateArgs {
+        let config: Config = toml::from_str(config).unwrap();
+        let (mut command, matches) = parse(args);
+        config.apply(&mut command, &matches).unwrap();
+        let Command::Validate(args) = command else {
+            panic!("expected the validate command");
+        };
+        args
+    }
+
+    const CONFIG: &str = r#"
+        fhir-version = "r5"
+        packages = ["schemas"]
+
+        [validate]
+        files = ["a.json"]
+        format = "json"
+        fhirpath = true
+
+        [terminology]
+        server = "https://tx.example.org/fhir"
+        headers = ["X-Api-Key: secret"]
+    "#;
+
+    #[test]
+    fn test_config_fills_options_left_unset() {
+        let args = validate_args(CONFIG, &["fhirschema", "validate"]);
+        assert!(matches!(args.fhir_version, VersionArg::R5));
+        assert_eq!(args.files, [PathBuf::from("a.json")]);
+        assert!(matches!(args.format, OutputFormat::Json));
+        assert!(args.fhirpath);
+        assert_eq!(args.schemas.schema_package_dirs, [PathBuf::from("schemas")]);
+        assert_eq!(
+            args.tx_server.as_deref(),
+            Some("https://tx.example.org/fhir")
+        );
+        assert_eq!(args.tx_headers, ["X-Api-Key: secret"]);
+    }
+
+    #[test]
+    fn test_command_line_wins_over_config() {
+        // Values equal to the defaults still count when given explicitly
+        let args = validate_args(
+            CONFIG,
+            &[
+                "fhirschema",
+                "validate",
+                "b.json",
+                "--fhir-version",
+                "r4",
+                "--format",
+                "text",
+                "--schema-package-dir",
+                "mine",
+            ],
+        );
+        assert!(matches!(args.fhir_version, VersionArg::R4));
+        assert_eq!(args.files, [PathBuf::from("b.json")]);
+        assert!(matches!(args.format, OutputFormat::Text));
+        assert_eq!(args.schemas.schema_package_dirs, [PathBuf::from("mine")]);
+    }
+
+    #[test]
+    fn test_terminology_config_only_applies_without_terminology_options() {
+        let args = validate_args(
+            CONFIG,
+            &["fhirschema", "validate", "--tx-offline", "terminology.tgz"],
+        );
+        assert_eq!(args.tx_server, None);
+        assert!(args.tx_headers.is_empty());
+        assert_eq!(args.tx_offline, [PathBuf::from("terminology.tgz")]);
+    }
+
+    #[test]
+    fn test_relative_paths_are_relative_to_the_config_file() {
+        let mut config: Config = toml::from_str(
+            r#"
+            packages = ["schemas", "/opt/schemas"]
+            [convert]
+            output = "out"
+            "#,
+        )
+        .unwrap();
+        config.rebase(Path::new("/project"));
+        assert_eq!(
+            config.packages,
+            [
+                PathBuf::from("/project/schemas"),
+                PathBuf::from("/opt/schemas")
+            ]
+        );
+        assert_eq!(config.convert.output, Some(PathBuf::from("/project/out")));
+    }
+}
@@ -0,0 +1,585 @@
+mod commands;
+mod config;
+mod registry;
+mod report;
+mod terminology;
+
+use anyhow::Result;
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use commands::{
+    build_package, conformance_check, convert, diff_versions, docs, download, explain, inspect,
+    validate, viz,
+};
+use config::Config;
+use octofhir_fhir_model::provider::FhirVersion as ModelFhirVersion;
+use octofhir_fhirschema::serialization::BundleFormat;
+use octofhir_fhirschema::{FhirSchema, FhirVersion, get_schemas};
+use octofhir_fhirschema_devtools::schema_files::load_package_schemas;
+use report::{EXIT_TOOL_FAILURE, Outcome};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+#[derive(Debug, Parser)]
+#[command(name = "fhirschema")]
+#[command(about = "Validate FHIR resources against FHIR Schemas")]
+struct Cli {
+    /// Project config file. Defaults to ./fhirschema.toml when it exists.
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Validate one or more FHIR resource JSON files
+    Validate(ValidateArgs),
+    /// Show the resolved definition of an element path in a schema
+    Inspect(InspectArgs),
+    /// Render a schema or profile as Markdown or HTML documentation
+    Docs(DocsArgs),
+    /// Draw a schema's element tree as a Mermaid or Graphviz diagram
+    Viz(VizArgs),
+    /// Describe an error code such as FS1007, or list every code
+    Explain(ExplainArgs),
+    /// Convert StructureDefinitions to FHIR Schemas
+    Convert(ConvertArgs),
+    /// Validate with both fhirschema and the HL7 Java validator and diff
+    /// their findings
+    ConformanceCheck(ConformanceArgs),
+    /// Compare the core resource schemas of two FHIR versions
+    DiffVersions(DiffVersionsArgs),
+    /// Install FHIR packages and their dependencies from a package registry
+    Download(DownloadArgs),
+    /// Build and manage FHIR NPM packages
+    Package {
+        #[command(subcommand)]
+        command: PackageCommand,
+    },
+}
+
+#[derive(Debug, Args)]
+struct ConformanceArgs {
+    /// Resource JSON files to validate
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+
+    /// HL7 validator_cli.jar. Falls back to HL7_VALIDATOR_JAR.
+    #[arg(long)]
+    reference_validator: Option<PathBuf>,
+
+    /// FHIR version of the embedded base schemas, also passed to the
+    /// reference validator
+    #[arg(long = "fhir-version", value_enum, default_value_t = VersionArg::R4)]
+    fhir_version: VersionArg,
+
+    /// Profile to validate against: a canonical URL or name of a loaded
+    /// schema, or a path to a StructureDefinition JSON file. Can be repeated.
+    #[arg(long = "profile")]
+    profiles: Vec<String>,
+
+    #[command(flatten)]
+    schemas: SchemaSource,
+
+    /// Package or IG passed to the reference validator as '-ig'. Can be repeated.
+    #[arg(long = "reference-ig")]
+    reference_igs: Vec<String>,
+
+    /// Terminology server for the reference validator; 'n/a' disables it
+    #[arg(long, default_value = "n/a")]
+    reference_tx: String,
+
+    /// Per-resource reference validator timeout in seconds
+    #[arg(long, default_value_t = 120)]
+    timeout_secs: u64,
+
+    /// Directory for the reference validator's OperationOutcome files
+    #[arg(long, default_value = "target/fhirschema-conformance")]
+    work_dir: PathBuf,
+
+    /// Print the comparison as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+/// Schemas added on top of the embedded base schemas of a command.
+#[derive(Debug, Args)]
+struct SchemaSource {
+    /// Directory of StructureDefinition JSON files, or of FhirSchema files
+    /// and bundles in JSON, YAML, MessagePack or CBOR, to add to the schema
+    /// set. The dependencies its package.json declares are loaded from
+    /// `<name>#<version>` directories next to it. Can be repeated.
+    #[arg(long = "schema-package-dir")]
+    schema_package_dirs: Vec<PathBuf>,
+}
+
+impl SchemaSource {
+    /// The embedded schemas of `version` plus every schema in the package
+    /// directories.
+    fn load(&self, version: FhirVersion) -> Result<HashMap<String, FhirSchema>> {
+        let mut schemas = get_schemas(version)?.clone();
+        load_package_schemas(&self.schema_package_dirs, &mut schemas)?;
+        Ok(schemas)
+    }
+}
+
+#[derive(Debug, Args)]
+struct ConvertArgs {
+    /// StructureDefinition JSON files or directories (searched
+    /// recursively). Required unless --fsh or `[convert] inputs` is set.
+    inputs: Vec<PathBuf>,
+
+    /// FSH (SUSHI) project whose generated StructureDefinitions are
+    /// converted. Failures are reported against the FSH file and lines.
+    #[arg(long)]
+    fsh: Option<PathBuf>,
+
+    /// Run `sushi build` on the --fsh project before converting
+    #[arg(long, requires = "fsh")]
+    sushi: bool,
+
+    /// SUSHI executable used with --sushi
+    #[arg(long, default_value = "sushi")]
+    sushi_bin: PathBuf,
+
+    /// Directory the converted schemas are written to, one <name>.<format>
+    /// each. Inputs whose schemas share a name all fail rather than
+    /// overwrite each other.
+    #[arg(long, default_value = "schemas")]
+    output: PathBuf,
+
+    /// Serialization format of the converted schemas
+    #[arg(long, value_enum, default_value_t = SchemaFormatArg::Json)]
+    format: SchemaFormatArg,
+
+    /// Reject StructureDefinitions that break the metaprofile rules (sdf-*
+    /// invariants, element ordering, slicing) instead of converting them
+    #[arg(long)]
+    check_definitions: bool,
+
+    /// Write self-contained schemas: merge in the base chain and inline the
+    /// elements of referenced complex types, this many levels deep
+    #[arg(long)]
+    inline_depth: Option<usize>,
+
+    /// FHIR version of the embedded schemas that --inline-depth resolves
+    /// bases and types from
+    #[arg(long = "fhir-version", value_enum, default_value_t = VersionArg::R4)]
+    fhir_version: VersionArg,
+
+    /// Maximum files converted at once
+    #[arg(long, default_value_t = 8)]
+    jobs: usize,
+
+    /// Number of failed conversions tolerated before the command exits
+    /// non-zero. Conversion always continues past failures.
+    #[arg(long, default_value_t = 0)]
+    max_failures: usize,
+
+    /// Write a JSON conversion report listing converted, failed and skipped
+    /// files with reasons
+    #[arg(long)]
+    report: Option<PathBuf>,
+}
+
+#[derive(Debug, Subcommand)]
+enum PackageCommand {
+    /// Convert and validate a folder of conformance resources and write an
+    /// NPM-style FHIR package tarball
+    Build(PackageBuildArgs),
+}
+
+#[derive(Debug, Args)]
+struct PackageBuildArgs {
+    /// Directory with resource JSON files (StructureDefinitions, ValueSets, ...)
+    dir: PathBuf,
+
+    /// Package name, e.g. my.org.profiles
+    #[arg(long)]
+    id: String,
+
+    /// Package version
+    #[arg(long)]
+    version: String,
+
+    /// FHIR version the package targets; its core package becomes a dependency
+    #[arg(long = "fhir-version", value_enum, default_value_t = VersionArg::R4)]
+    fhir_version: VersionArg,
+
+    /// Additional dependency as name#version. Can be repeated.
+    #[arg(long = "dependency")]
+    dependencies: Vec<String>,
+
+    /// Output tarball. Defaults to <id>-<version>.tgz
+    #[arg(long)]
+    output: Option<PathBuf>,
+}
+
+/// Default FHIR package registry for `download`.
+const DEFAULT_REGISTRY: &str = "https://packages.fhir.org";
+
+#[derive(Debug, Args)]
+struct DownloadArgs {
+    /// Packages to install as name#version, or name for the latest version.
+    /// With --frozen, defaults to the packages the lockfile was written for.
+    packages: Vec<String>,
+
+    /// NPM-style FHIR package registry
+    #[arg(long, default_value = DEFAULT_REGISTRY)]
+    registry: String,
+
+    /// Directory the packages are installed in: each tarball as
+    /// <name>-<version>.tgz and its contents in <name>#<version>/
+    #[arg(long, default_value = "packages")]
+    output: PathBuf,
+
+    /// Lockfile recording the resolved versions, tarball URLs and hashes
+    #[arg(long, default_value = "fhirschema-lock.json")]
+    lockfile: PathBuf,
+
+    /// Install exactly the packages of the lockfile, failing on any hash
+    /// mismatch, instead of resolving versions from the registry
+    #[arg(long)]
+    frozen: bool,
+}
+
+#[derive(Debug, Args)]
+struct DiffVersionsArgs {
+    /// FHIR version to compare from
+    #[arg(long, value_enum, default_value_t = VersionArg::R4)]
+    from: VersionArg,
+
+    /// FHIR version to compare to
+    #[arg(long, value_enum, default_value_t = VersionArg::R5)]
+    to: VersionArg,
+
+    /// Only compare these resource types. Can be repeated.
+    #[arg(long = "resource")]
+    resources: Vec<String>,
+
+    /// Print the report as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Debug, Args)]
+struct InspectArgs {
+    /// Schema name or canonical URL, e.g. Patient
+    schema: String,
+
+    /// Element path below the schema root, e.g. name.given,
+    /// identifier:mrn.system or extension('race'). Omit to inspect the
+    /// schema root.
+    path: Option<String>,
+
+    /// FHIR version of the embedded base schemas
+    #[arg(long = "fhir-version", value_enum, default_value_t = VersionArg::R4)]
+    fhir_version: VersionArg,
+
+    #[command(flatten)]
+    schemas: SchemaSource,
+
+    /// Print the matching element definitions as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Debug, Args)]
+struct DocsArgs {
+    /// Schema name or canonical URL, e.g. Patient
+    schema: String,
+
+    /// FHIR version of the embedded base schemas
+    #[arg(long = "fhir-version", value_enum, default_value_t = VersionArg::R4)]
+    fhir_version: VersionArg,
+
+    #[command(flatten)]
+    schemas: SchemaSource,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t = DocsFormat::Markdown)]
+    format: DocsFormat,
+
+    /// Write to this file instead of stdout
+    #[arg(long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum DocsFormat {
+    Markdown,
+    Html,
+}
+
+#[derive(Debug, Args)]
+struct VizArgs {
+    /// Schema name or canonical URL, e.g. Patient
+    schema: String,
+
+    /// FHIR version of the embedded base schemas
+    #[arg(long = "fhir-version", value_enum, default_value_t = VersionArg::R4)]
+    fhir_version: VersionArg,
+
+    #[command(flatten)]
+    schemas: SchemaSource,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t = VizFormat::Mermaid)]
+    format: VizFormat,
+
+    /// Also draw the derivation chain down to the root type
+    #[arg(long)]
+    derivation: bool,
+
+    /// Write to this file instead of stdout
+    #[arg(long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum VizFormat {
+    Mermaid,
+    Dot,
+}
+
+#[derive(Debug, Args)]
+struct ExplainArgs {
+    /// Error code, e.g. FS1007, REF1002 or VS1004; omit to list all codes
+    code: Option<String>,
+
+    /// Print the catalog entries as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum SchemaFormatArg {
+    Json,
+    Yaml,
+    #[value(alias = "msgpack")]
+    #[serde(alias = "msgpack")]
+    Messagepack,
+    Cbor,
+}
+
+impl SchemaFormatArg {
+    fn format(self) -> BundleFormat {
+        match self {
+            SchemaFormatArg::Json => BundleFormat::Json,
+            SchemaFormatArg::Yaml => BundleFormat::Yaml,
+            SchemaFormatArg::Messagepack => BundleFormat::MessagePack,
+            SchemaFormatArg::Cbor => BundleFormat::Cbor,
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+struct ValidateArgs {
+    /// Resource JSON or XML (`.xml`) files to validate. Required unless
+    /// `[validate] files` is set in the config.
+    files: Vec<PathBuf>,
+
+    /// FHIR version of the embedded base schemas
+    #[arg(long = "fhir-version", value_enum, default_value_t = VersionArg::R4)]
+    fhir_version: VersionArg,
+
+    /// Profile to validate against in addition to resourceType: a canonical
+    /// URL or name of a loaded schema, or a path to a StructureDefinition
+    /// JSON file. Can be repeated.
+    #[arg(long = "profile")]
+    profiles: Vec<String>,
+
+    /// Profiles per resource type, from the config's `[profiles]` table
+    #[arg(skip)]
+    type_profiles: BTreeMap<String, Vec<String>>,
+
+    /// Also validate against every meta.profile entry of each resource
+    #[arg(long)]
+    meta_profile: bool,
+
+    #[command(flatten)]
+    schemas: SchemaSource,
+
+    /// Evaluate FHIRPath constraints (invariants) in addition to structural checks
+    #[arg(long)]
+    fhirpath: bool,
+
+    /// FHIR terminology server base URL for required-binding checks via
+    /// ValueSet/$validate-code
+    #[arg(long = "tx-server", conflicts_with = "tx_offline")]
+    tx_server: Option<String>,
+
+    /// File to persist the terminology server's answers in, so later runs
+    /// reuse them within the cache TTL
+    #[arg(long = "tx-cache", requires = "tx_server")]
+    tx_cache: Option<PathBuf>,
+
+    /// Bearer token sent to the terminology server
+    #[arg(
+        long = "tx-token",
+        requires = "tx_server",
+        conflicts_with = "tx_client_id"
+    )]
+    tx_token: Option<String>,
+
+    /// Extra header sent to the terminology server, as `Name: value`. Can
+    /// be repeated.
+    #[arg(long = "tx-header", requires = "tx_server")]
+    tx_headers: Vec<String>,
+
+    /// OAuth2 client id for a client credentials token from
+    /// `--tx-token-url` (SMART backend services)
+    #[arg(long = "tx-client-id", requires_all = ["tx_server", "tx_token_url"])]
+    tx_client_id: Option<String>,
+
+    /// OAuth2 client secret for `--tx-client-id`
+    #[arg(long = "tx-client-secret", requires = "tx_client_id")]
+    tx_client_secret: Option<String>,
+
+    /// OAuth2 token endpoint for `--tx-client-id`
+    #[arg(long = "tx-token-url", requires = "tx_client_id")]
+    tx_token_url: Option<String>,
+
+    /// Scopes requested with `--tx-client-id`, space-separated
+    #[arg(long = "tx-scope", requires = "tx_client_id")]
+    tx_scope: Option<String>,
+
+    /// FHIR package tarball whose ValueSets and CodeSystems are loaded for
+    /// offline binding checks, on top of the embedded core value sets. Can
+    /// be repeated.
+    #[arg(long = "tx-offline")]
+    tx_offline: Vec<PathBuf>,
+
+    /// List the value sets of required bindings that could not be checked
+    #[arg(long)]
+    report_unchecked_bindings: bool,
+
+    /// Treat inputs as NDJSON (e.g. bulk export files): one resource per
+    /// line, streamed and validated concurrently. Only invalid lines are
+    /// reported.
+    #[arg(long)]
+    ndjson: bool,
+
+    /// Maximum resources validated at once in NDJSON mode
+    #[arg(long, default_value_t = 8)]
+    concurrency: usize,
+
+    /// Directory to persist compiled schemas in, so later runs skip
+    /// compiling them
+    #[arg(long = "compiled-cache")]
+    compiled_cache: Option<PathBuf>,
+
+    /// Output format: text, JSON, SARIF 2.1.0 (GitHub code scanning) or
+    /// JUnit XML (CI test reports)
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+
+    /// Apply the suggested fixes for mechanical errors (scalar vs array,
+    /// primitive JSON types, missing resourceType) and rewrite the files.
+    /// Reported errors are those left after fixing. XML files are not
+    /// rewritten.
+    #[arg(long, conflicts_with = "ndjson")]
+    fix: bool,
+
+    /// Record which schemas were used for each element and how it was
+    /// resolved (choice, contentReference, extension url, cache hit), shown
+    /// as a tree in text output and as `explain` in JSON output
+    #[arg(long)]
+    explain: bool,
+
+    /// Exit with status 2 when resources have warnings but no errors
+    #[arg(long)]
+    warnings_as_errors: bool,
+
+    /// Only print resources with errors or warnings in text output and
+    /// nothing on stderr, so stdout carries just the report
+    #[arg(long, short)]
+    quiet: bool,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum OutputFormat {
+    Text,
+    Json,
+    Sarif,
+    Junit,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum VersionArg {
+    R4,
+    R4b,
+    R5,
+    R6,
+}
+
+impl VersionArg {
+    fn schema_version(self) -> FhirVersion {
+        match self {
+            VersionArg::R4 => FhirVersion::R4,
+            VersionArg::R4b => FhirVersion::R4B,
+            VersionArg::R5 => FhirVersion::R5,
+            VersionArg::R6 => FhirVersion::R6,
+        }
+    }
+
+    fn model_version(self) -> ModelFhirVersion {
+        match self {
+            VersionArg::R4 => ModelFhirVersion::R4,
+            VersionArg::R4b => ModelFhirVersion::R4B,
+            VersionArg::R5 => ModelFhirVersion::R5,
+            VersionArg::R6 => ModelFhirVersion::R6,
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let matches = match Cli::command().try_get_matches() {
+        Ok(matches) => matches,
+        Err(err) if !err.use_stderr() => err.exit(),
+        Err(err) => {
+            let _ = err.print();
+            return ExitCode::from(EXIT_TOOL_FAILURE);
+        }
+    };
+    let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+    let outcome = match Config::load(cli.config.as_deref())
+        .and_then(|config| config.apply(&mut cli.command, &matches))
+    {
+        Ok(()) => run(cli.command).await,
+        Err(err) => Err(err),
+    };
+
+    match outcome {
+        Ok(outcome) => outcome.exit_code(),
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            ExitCode::from(EXIT_TOOL_FAILURE)
+        }
+    }
+}
+
+async fn run(command: Command) -> Result<Outcome> {
+    let passed = match command {
+        Command::Validate(args) => return validate(args).await,
+        Command::Inspect(args) => inspect(args),
+        Command::Docs(args) => docs(args),
+        Command::Viz(args) => viz(args),
+        Command::Explain(args) => explain(args),
+        Command::Convert(args) => convert(args).await,
+        Command::ConformanceCheck(args) => conformance_check(args).await,
+        Command::DiffVersions(args) => diff_versions(args),
+        Command::Download(args) => download(args).await,
+        Command::Package {
+            command: PackageCommand::Build(args),
+        } => build_package(args).await,
+    };
+    passed.map(Outcome::from)
+}
@@ -0,0 +1,161 @@
+//! Client for NPM-style FHIR package registries, the lockfile `download`
+//! writes, and installing package tarballs.
+
+mod version;
+
+use anyhow::{Context, Result, bail};
+use flate2::read::GzDecoder;
+use octofhir_fhirschema::package::PackageManifest;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use version::{version_key, version_matches};
+
+/// Version of the lockfile layout.
+pub(crate) const LOCKFILE_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct Lockfile {
+    pub(crate) version: u32,
+    pub(crate) registry: String,
+    /// Packages requested on the command line, as given
+    pub(crate) roots: Vec<String>,
+    pub(crate) packages: BTreeMap<String, LockedPackage>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct LockedPackage {
+    pub(crate) version: String,
+    /// Tarball URL
+    pub(crate) resolved: String,
+    pub(crate) sha256: String,
+    /// Declared dependencies as package name -> requested version
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) dependencies: BTreeMap<String, String>,
+}
+
+/// Registry client caching the version metadata of each package.
+pub(crate) struct PackageRegistry {
+    client: reqwest::Client,
+    pub(crate) base_url: String,
+    metadata: HashMap<String, Value>,
+}
+
+impl PackageRegistry {
+    pub(crate) fn new(base_url: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            metadata: HashMap::new(),
+        }
+    }
+
+    /// Pin a requested version (exact, `latest`, or a wildcard such as
+    /// `4.0.x`) to a published version and its tarball URL.
+    pub(crate) async fn pin(&mut self, name: &str, requested: &str) -> Result<(String, String)> {
+        if !self.metadata.contains_key(name) {
+            let url = format!("{}/{name}", self.base_url);
+            let metadata: Value = self.get(&url).await?.json().await?;
+            self.metadata.insert(name.to_string(), metadata);
+        }
+        let metadata = &self.metadata[name];
+        let versions = metadata
+            .get("versions")
+            .and_then(Value::as_object)
+            .with_context(|| format!("registry has no versions of {name}"))?;
+
+        let version = if matches!(requested, "latest" | "current") {
+            metadata
+                .pointer("/dist-tags/latest")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        } else if versions.contains_key(requested) {
+            Some(requested.to_string())
+        } else {
+            versions
+                .keys()
+                .filter(|version| version_matches(version, requested))
+                .max_by_key(|version| version_key(version))
+                .cloned()
+        }
+        .with_context(|| format!("no published version of {name} matches {requested}"))?;
+
+        let tarball = versions[&version]
+            .pointer("/dist/tarball")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("{}/{name}/{version}", self.base_url));
+        Ok((version, tarball))
+    }
+
+    pub(crate) async fn fetch(&self, url: &str) -> Result<Vec<u8>> {
+        Ok(self.get(url).await?.bytes().await?.to_vec())
+    }
+
+    async fn get(&self, url: &str) -> Result<reqwest::Response> {
+        self.client
+            .get(url)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .with_context(|| format!("failed to fetch {url}"))
+    }
+}
+
+/// Read `package/package.json` from a package tarball.
+pub(crate) fn read_package_manifest(tarball: &[u8]) -> Result<PackageManifest> {
+    let mut archive = tar::Archive::new(GzDecoder::new(tarball));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if entry.path()? == Path::new("package/package.json") {
+            let mut content = String::new();
+            entry.read_to_string(&mut content)?;
+            return Ok(PackageManifest::from_package_json(&serde_json::from_str(
+                &content,
+            )?)?);
+        }
+    }
+    bail!("no package/package.json in the tarball")
+}
+
+/// Write the tarball as `<name>-<version>.tgz` and unpack its `package/`
+/// folder into a fresh `<name>#<version>/` directory.
+pub(crate) fn install_package(
+    output: &Path,
+    name: &str,
+    version: &str,
+    tarball: &[u8],
+) -> Result<()> {
+    fs::write(output.join(format!("{name}-{version}.tgz")), tarball)?;
+    let dir = output.join(format!("{name}#{version}"));
+    if dir.exists() {
+        fs::remove_dir_all(&dir)?;
+    }
+    let mut archive = tar::Archive::new(GzDecoder::new(tarball));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry.path()?.to_path_buf();
+        let relative = path.strip_prefix("package").unwrap_or(&path);
+        if !relative
+            .components()
+            .all(|part| matches!(part, std::path::Component::Normal(_)))
+        {
+            bail!(
+                "{name}#{version}: unsafe path {} in tarball",
+                path.display()
+            );
+        }
+        let target = dir.join(relative);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        entry.unpack(&target)?;
+    }
+    Ok(())
+}
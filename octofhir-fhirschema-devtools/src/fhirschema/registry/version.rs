@@ -0,0 +1,129 @@
+//! Version matching and ordering for package requests.
+
+/// Whether `version` satisfies a wildcard request such as `4.0.x` or `6.*`.
+///
+/// As with npm ranges, a pre-release such as `6.1.0-ballot` only matches a
+/// request that names a pre-release itself, so `6.1.x` never resolves to a
+/// ballot when a release exists or gets published later.
+pub(super) fn version_matches(version: &str, requested: &str) -> bool {
+    if version.contains('-') && !requested.contains('-') {
+        return false;
+    }
+    let mut parts = version.split('.');
+    for wanted in requested.split('.') {
+        if matches!(wanted, "x" | "X" | "*") {
+            return true;
+        }
+        if parts.next() != Some(wanted) {
+            return false;
+        }
+    }
+    parts.next().is_none()
+}
+
+/// One dot-separated identifier of a pre-release. Numeric identifiers sort
+/// below alphanumeric ones, as in semver.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum PreReleasePart {
+    Numeric(u64),
+    Text(String),
+}
+
+/// Semver precedence of a version: release segments compare numerically,
+/// and a release sorts above every pre-release of it (`6.1.0` >
+/// `6.1.0-snapshot1`). Build metadata after `+` is ignored.
+#[derive(Debug, PartialEq, Eq)]
+pub(super) struct VersionKey {
+    release: Vec<u64>,
+    pre_release: Vec<PreReleasePart>,
+}
+
+impl Ord for VersionKey {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        use std::cmp::Ordering;
+        self.release.cmp(&other.release).then_with(|| {
+            match (self.pre_release.is_empty(), other.pre_release.is_empty()) {
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => self.pre_release.cmp(&other.pre_release),
+            }
+        })
+    }
+}
+
+impl PartialOrd for VersionKey {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+pub(super) fn version_key(version: &str) -> VersionKey {
+    let version = version
+        .split_once('+')
+        .map_or(version, |(version, _)| version);
+    let (release, pre_release) = version.split_once('-').unwrap_or((version, ""));
+    VersionKey {
+        release: release
+            .split('.')
+            .map(|part| part.parse().unwrap_or(0))
+            .collect(),
+        pre_release: pre_release
+            .split('.')
+            .filter(|part| !part.is_empty())
+            .map(|part| match part.parse() {
+                Ok(number) => PreReleasePart::Numeric(number),
+                Err(_) => PreReleasePart::Text(part.to_string()),
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_matches_wildcards() {
+        assert!(version_matches("4.0.1", "4.0.x"));
+        assert!(version_matches("4.0.1", "4.*"));
+        assert!(version_matches("4.0.1", "4.0.1"));
+        assert!(!version_matches("4.1.0", "4.0.x"));
+        assert!(!version_matches("4.0.1", "4.0"));
+        assert!(!version_matches("4.0", "4.0.1"));
+    }
+
+    #[test]
+    fn test_version_matches_skips_pre_releases_unless_requested() {
+        assert!(!version_matches("6.1.0-snapshot1", "6.1.x"));
+        assert!(!version_matches("6.1.0-snapshot1", "6.*"));
+        assert!(version_matches("6.1.0-snapshot1", "6.1.0-snapshot1"));
+    }
+
+    #[test]
+    fn test_version_key_orders_releases_numerically() {
+        assert!(version_key("4.0.10") > version_key("4.0.9"));
+        assert!(version_key("5.0.0") > version_key("4.3.0"));
+        assert_eq!(version_key("4.0.1+build5"), version_key("4.0.1"));
+    }
+
+    #[test]
+    fn test_version_key_sorts_release_above_pre_release() {
+        assert!(version_key("6.1.0") > version_key("6.1.0-snapshot1"));
+        assert!(version_key("6.1.0-snapshot1") > version_key("6.0.0"));
+        assert!(version_key("5.0.0-ballot.2") > version_key("5.0.0-ballot.1"));
+        assert!(version_key("5.0.0-ballot.10") > version_key("5.0.0-ballot.9"));
+        assert!(version_key("5.0.0-ballot") > version_key("5.0.0-1"));
+        assert!(version_key("5.0.0-ballot.1") > version_key("5.0.0-ballot"));
+    }
+
+    #[test]
+    fn test_latest_matching_version_prefers_release() {
+        let versions = ["6.1.0-snapshot1", "6.1.0", "6.0.0", "6.1.0-ballot"];
+        let latest = versions
+            .iter()
+            .filter(|version| version_matches(version, "6.1.x"))
+            .max_by_key(|version| version_key(version));
+        assert_eq!(latest, Some(&"6.1.0"));
+    }
+}
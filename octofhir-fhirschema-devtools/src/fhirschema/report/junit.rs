@@ -0,0 +1,89 @@
+use super::{RunSummary, format_path};
+
+/// Render a run as a JUnit XML test suite with one test case per resource.
+pub(crate) fn junit(summary: &RunSummary) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!(
+        "<testsuite name=\"fhirschema\" tests=\"{}\" failures=\"{}\" errors=\"{}\">\n",
+        summary.resources + summary.input_errors.len(),
+        summary.invalid,
+        summary.input_errors.len()
+    ));
+    for report in &summary.reports {
+        let name = match report.line {
+            Some(line) => format!("{}:{line}", report.path.display()),
+            None => report.path.display().to_string(),
+        };
+        xml.push_str(&format!(
+            "  <testcase classname=\"{}\" name=\"{}\"",
+            xml_escape(report.resource_type.as_deref().unwrap_or("unknown")),
+            xml_escape(&name)
+        ));
+        if report.result.valid {
+            xml.push_str("/>\n");
+            continue;
+        }
+        xml.push_str(">\n");
+        for error in &report.result.errors {
+            xml.push_str(&format!(
+                "    <failure type=\"{}\" message=\"{}\">{}</failure>\n",
+                xml_escape(&error.error_type),
+                xml_escape(&error.to_string()),
+                xml_escape(&format_path(&error.path))
+            ));
+        }
+        xml.push_str("  </testcase>\n");
+    }
+    // JUnit tells errors (the test could not run) from failures
+    for error in &summary.input_errors {
+        let name = match error.line {
+            Some(line) => format!("{}:{line}", error.path.display()),
+            None => error.path.display().to_string(),
+        };
+        xml.push_str(&format!(
+            "  <testcase classname=\"input\" name=\"{}\">\n    <error type=\"parse\" message=\"{}\"/>\n  </testcase>\n",
+            xml_escape(&name),
+            xml_escape(&error.message)
+        ));
+    }
+    xml.push_str("</testsuite>\n");
+    xml
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::sample_summary;
+
+    #[test]
+    fn test_junit_counts_input_errors_as_errors() {
+        let xml = junit(&sample_summary());
+        assert!(xml.contains(r#"tests="3" failures="1" errors="1""#));
+        assert!(xml.contains(r#"<testcase classname="Patient" name="valid.json"/>"#));
+        assert!(xml.contains(
+            r#"<failure type="FS1001" message="Unknown element">Patient.name.given</failure>"#
+        ));
+        assert!(xml.contains(r#"<testcase classname="input" name="bulk.ndjson:3">"#));
+        assert!(
+            xml.contains(
+                r#"<error type="parse" message="expected &quot;&lt;&quot; at column 1"/>"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_xml_escape() {
+        assert_eq!(
+            xml_escape(r#"a < b & "c" > d"#),
+            "a &lt; b &amp; &quot;c&quot; &gt; d"
+        );
+    }
+}
@@ -0,0 +1,226 @@
+//! Validation run results and their text, SARIF and JUnit renderings.
+
+mod junit;
+mod sarif;
+mod text;
+
+use octofhir_fhirschema::{ErrorPath, ValidationResult};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+pub(crate) use junit::junit;
+pub(crate) use sarif::sarif;
+pub(crate) use text::print_text;
+
+#[derive(Debug, Serialize)]
+pub(crate) struct FileReport {
+    pub(crate) path: PathBuf,
+    /// 1-based line number for NDJSON input
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) line: Option<usize>,
+    pub(crate) resource_type: Option<String>,
+    pub(crate) schema_names: Vec<String>,
+    #[serde(flatten)]
+    pub(crate) result: ValidationResult,
+}
+
+/// Reports of a validation run. NDJSON input only keeps reports for invalid
+/// lines so memory stays bounded on large bulk exports.
+#[derive(Debug, Default, Serialize)]
+pub(crate) struct RunSummary {
+    pub(crate) resources: usize,
+    pub(crate) invalid: usize,
+    /// Valid resources that have warnings
+    pub(crate) warnings: usize,
+    pub(crate) reports: Vec<FileReport>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) unchecked_bindings: Vec<String>,
+    /// Input that could not be parsed into a resource, such as malformed
+    /// NDJSON lines. These are not validation findings and are not counted
+    /// as resources.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) input_errors: Vec<InputError>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct InputError {
+    pub(crate) path: PathBuf,
+    /// 1-based line number for NDJSON input
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) line: Option<usize>,
+    pub(crate) message: String,
+}
+
+impl RunSummary {
+    pub(crate) fn record(&mut self, report: FileReport, keep_valid: bool) {
+        self.resources += 1;
+        if !report.result.valid {
+            self.invalid += 1;
+        } else if !report.result.warnings.is_empty() {
+            self.warnings += 1;
+        }
+        if keep_valid || !report.result.valid {
+            self.reports.push(report);
+        }
+    }
+}
+
+/// Outcome of a command and its process exit code. Tool failures (bad
+/// arguments, unreadable files) exit with [`EXIT_TOOL_FAILURE`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[repr(u8)]
+pub(crate) enum Outcome {
+    Ok = 0,
+    /// Invalid resources, or a failed check for commands other than validate
+    Errors = 1,
+    /// Warnings but no errors, with --warnings-as-errors
+    Warnings = 2,
+    /// Some input could not be parsed (NDJSON lines); the rest was validated
+    InputErrors = 4,
+}
+
+pub(crate) const EXIT_TOOL_FAILURE: u8 = 3;
+
+impl Outcome {
+    pub(crate) fn of(summary: &RunSummary, warnings_as_errors: bool) -> Self {
+        if !summary.input_errors.is_empty() {
+            Outcome::InputErrors
+        } else if summary.invalid > 0 {
+            Outcome::Errors
+        } else if warnings_as_errors && summary.warnings > 0 {
+            Outcome::Warnings
+        } else {
+            Outcome::Ok
+        }
+    }
+
+    pub(crate) fn exit_code(self) -> ExitCode {
+        ExitCode::from(self as u8)
+    }
+}
+
+impl From<bool> for Outcome {
+    fn from(passed: bool) -> Self {
+        if passed { Outcome::Ok } else { Outcome::Errors }
+    }
+}
+
+/// Version of the `validate --format json` document. Fields are only added
+/// within a version; renaming or removing one bumps it.
+pub(crate) const JSON_REPORT_VERSION: u32 = 1;
+
+/// The `validate --format json` document: the run summary plus its outcome.
+#[derive(Debug, Serialize)]
+pub(crate) struct JsonReport<'a> {
+    pub(crate) version: u32,
+    pub(crate) outcome: Outcome,
+    pub(crate) exit_code: u8,
+    #[serde(flatten)]
+    pub(crate) summary: &'a RunSummary,
+}
+
+/// The schema a finding came from, when validation recorded it.
+fn provenance(error: &octofhir_fhirschema::ValidationError) -> String {
+    match (&error.schema_url, &error.schema_version) {
+        (Some(url), Some(version)) => format!(" [{url}|{version}]"),
+        (Some(url), None) => format!(" [{url}]"),
+        _ => String::new(),
+    }
+}
+
+/// An element path for display; `(root)` for the resource itself.
+pub(crate) fn format_path(path: &ErrorPath) -> String {
+    if path.is_empty() {
+        return "(root)".to_string();
+    }
+    path.to_string()
+}
+
+/// A run with one invalid and one valid resource and one unparsable
+/// NDJSON line.
+#[cfg(test)]
+fn sample_summary() -> RunSummary {
+    use octofhir_fhirschema::ValidationError;
+
+    let error = ValidationError {
+        error_type: "FS1001".into(),
+        path: ErrorPath::new("Patient.name.given"),
+        message: Some("Unknown element".into()),
+        value: None,
+        expected: None,
+        got: None,
+        schema_path: None,
+        constraint_key: None,
+        constraint_expression: None,
+        constraint_severity: None,
+        fix: None,
+        schema_url: None,
+        schema_version: None,
+    };
+    let report = |path: &str, errors: Vec<ValidationError>| FileReport {
+        path: PathBuf::from(path),
+        line: None,
+        resource_type: Some("Patient".to_string()),
+        schema_names: vec!["Patient".to_string()],
+        result: ValidationResult {
+            valid: errors.is_empty(),
+            errors,
+            ..Default::default()
+        },
+    };
+
+    let mut summary = RunSummary::default();
+    summary.record(report("invalid.json", vec![error]), true);
+    summary.record(report("valid.json", vec![]), true);
+    summary.input_errors.push(InputError {
+        path: PathBuf::from("bulk.ndjson"),
+        line: Some(3),
+        message: "expected \"<\" at column 1".to_string(),
+    });
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unparsable_input_has_its_own_outcome() {
+        let mut summary = RunSummary::default();
+        assert_eq!(Outcome::of(&summary, false), Outcome::Ok);
+        summary.input_errors.push(InputError {
+            path: PathBuf::from("Patient.ndjson"),
+            line: Some(3),
+            message: "expected value at line 1 column 1".to_string(),
+        });
+        let outcome = Outcome::of(&summary, false);
+        assert_eq!(outcome, Outcome::InputErrors);
+        assert_eq!(outcome as u8, 4);
+        assert_eq!(summary.resources, 0);
+        assert_eq!(serde_json::to_value(outcome).unwrap(), "input_errors");
+    }
+
+    #[test]
+    fn test_outcome_precedence() {
+        let summary = sample_summary();
+        assert_eq!(Outcome::of(&summary, false), Outcome::InputErrors);
+
+        let mut summary = RunSummary::default();
+        summary.warnings = 1;
+        assert_eq!(Outcome::of(&summary, false), Outcome::Ok);
+        assert_eq!(Outcome::of(&summary, true), Outcome::Warnings);
+        summary.invalid = 1;
+        assert_eq!(Outcome::of(&summary, true), Outcome::Errors);
+    }
+
+    #[test]
+    fn test_format_path_names_the_root() {
+        assert_eq!(format_path(&ErrorPath::default()), "(root)");
+        assert_eq!(
+            format_path(&ErrorPath::new("name[0].given")),
+            "name[0].given"
+        );
+    }
+}
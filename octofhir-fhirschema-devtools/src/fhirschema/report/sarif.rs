@@ -0,0 +1,137 @@
+use super::{RunSummary, format_path};
+use serde_json::{Value, json};
+use std::collections::BTreeSet;
+
+/// Render a run as a SARIF 2.1.0 log. Error codes become rule ids and the
+/// element path is reported as a logical location.
+pub(crate) fn sarif(summary: &RunSummary) -> Value {
+    let mut rule_ids = BTreeSet::new();
+    let mut results = Vec::new();
+    for report in &summary.reports {
+        let issues = report
+            .result
+            .errors
+            .iter()
+            .map(|issue| (issue, "error"))
+            .chain(
+                report
+                    .result
+                    .warnings
+                    .iter()
+                    .map(|issue| (issue, "warning")),
+            );
+        for (issue, level) in issues {
+            rule_ids.insert(issue.error_type.to_string());
+            let mut location = json!({
+                "physicalLocation": {
+                    "artifactLocation": { "uri": report.path.to_string_lossy() },
+                },
+                "logicalLocations": [{
+                    "fullyQualifiedName": format_path(&issue.path),
+                    "kind": "element",
+                }],
+            });
+            if let Some(line) = report.line {
+                location["physicalLocation"]["region"] = json!({ "startLine": line });
+            }
+            results.push(json!({
+                "ruleId": issue.error_type,
+                "level": level,
+                "message": { "text": issue.to_string() },
+                "locations": [location],
+            }));
+        }
+    }
+
+    // Unparsable input is a problem with the run, not a finding in a resource
+    let notifications: Vec<Value> = summary
+        .input_errors
+        .iter()
+        .map(|error| {
+            let mut location = json!({
+                "physicalLocation": {
+                    "artifactLocation": { "uri": error.path.to_string_lossy() },
+                },
+            });
+            if let Some(line) = error.line {
+                location["physicalLocation"]["region"] = json!({ "startLine": line });
+            }
+            json!({
+                "level": "error",
+                "message": { "text": error.message },
+                "locations": [location],
+            })
+        })
+        .collect();
+
+    let rules: Vec<Value> = rule_ids
+        .into_iter()
+        .map(|id| json!({ "id": id, "shortDescription": { "text": format!("FHIR Schema {id}") } }))
+        .collect();
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "fhirschema",
+                    "version": octofhir_fhirschema::VERSION,
+                    "informationUri": "https://github.com/octofhir/fhirschema-rs",
+                    "rules": rules,
+                },
+            },
+            "invocations": [{
+                "executionSuccessful": notifications.is_empty(),
+                "toolExecutionNotifications": notifications,
+            }],
+            "results": results,
+        }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::sample_summary;
+
+    #[test]
+    fn test_sarif_results_carry_rule_and_element() {
+        let log = sarif(&sample_summary());
+        let run = &log["runs"][0];
+        assert_eq!(log["version"], "2.1.0");
+        assert_eq!(run["tool"]["driver"]["rules"][0]["id"], "FS1001");
+
+        let results = run["results"].as_array().unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0]["ruleId"], "FS1001");
+        assert_eq!(results[0]["level"], "error");
+        let location = &results[0]["locations"][0];
+        assert_eq!(
+            location["physicalLocation"]["artifactLocation"]["uri"],
+            "invalid.json"
+        );
+        assert_eq!(
+            location["logicalLocations"][0]["fullyQualifiedName"],
+            "Patient.name.given"
+        );
+    }
+
+    #[test]
+    fn test_sarif_reports_unparsable_input_as_notifications() {
+        let log = sarif(&sample_summary());
+        let invocation = &log["runs"][0]["invocations"][0];
+        assert_eq!(invocation["executionSuccessful"], false);
+        let notification = &invocation["toolExecutionNotifications"][0];
+        assert_eq!(notification["level"], "error");
+        assert_eq!(
+            notification["locations"][0]["physicalLocation"]["region"]["startLine"],
+            3
+        );
+
+        let clean = sarif(&RunSummary::default());
+        assert_eq!(
+            clean["runs"][0]["invocations"][0]["executionSuccessful"],
+            true
+        );
+    }
+}
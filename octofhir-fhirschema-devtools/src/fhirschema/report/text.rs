@@ -0,0 +1,64 @@
+use super::{RunSummary, format_path, provenance};
+
+pub(crate) fn print_text(summary: &RunSummary, quiet: bool) {
+    for report in &summary.reports {
+        if quiet && report.result.valid && report.result.warnings.is_empty() {
+            continue;
+        }
+        let status = if report.result.valid { "OK" } else { "FAIL" };
+        let location = match report.line {
+            Some(line) => format!("{}:{line}", report.path.display()),
+            None => report.path.display().to_string(),
+        };
+        println!("{status} {location} ({})", report.schema_names.join(", "));
+        for error in &report.result.errors {
+            let fixable = if error.fix.is_some() {
+                " (fixable with --fix)"
+            } else {
+                ""
+            };
+            println!(
+                "  error {} at {}: {error}{fixable}{}",
+                error.error_type,
+                format_path(&error.path),
+                provenance(error)
+            );
+        }
+        for warning in &report.result.warnings {
+            println!(
+                "  warning {} at {}: {warning}{}",
+                warning.error_type,
+                format_path(&warning.path),
+                provenance(warning)
+            );
+        }
+        if let Some(trace) = &report.result.explain {
+            println!("  schemata:");
+            for line in trace.to_tree().lines() {
+                println!("    {line}");
+            }
+        }
+    }
+    for error in &summary.input_errors {
+        match error.line {
+            Some(line) => println!("INPUT {}:{line}: {}", error.path.display(), error.message),
+            None => println!("INPUT {}: {}", error.path.display(), error.message),
+        }
+    }
+    println!(
+        "{} resource(s) validated, {} invalid, {} with warnings",
+        summary.resources, summary.invalid, summary.warnings
+    );
+    if !summary.input_errors.is_empty() {
+        println!(
+            "{} input(s) could not be parsed",
+            summary.input_errors.len()
+        );
+    }
+    if !summary.unchecked_bindings.is_empty() {
+        println!("required bindings not checked:");
+        for value_set in &summary.unchecked_bindings {
+            println!("  {value_set}");
+        }
+    }
+}
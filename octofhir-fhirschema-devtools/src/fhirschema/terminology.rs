@@ -0,0 +1,437 @@
+//! Terminology backends for binding checks: a remote server with optional
+//! authentication, or value sets loaded from package tarballs.
+
+use crate::ValidateArgs;
+use anyhow::{Context, Result, bail};
+use async_trait::async_trait;
+use flate2::read::GzDecoder;
+use octofhir_fhirschema::{
+    AuthError, AuthProvider, AuthResult, CacheTuning, CachedTerminologyService,
+    ClientCredentialsAuth, CodeValidationResult, HeaderAuth, InMemoryTerminologyService,
+    StaticTokenAuth, TerminologyError, TerminologyResult, TerminologyService, TokenTransport,
+    core_terminology_service,
+};
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// A terminology backend and, for a server, the cache of its answers.
+pub(crate) struct Terminology {
+    pub(crate) service: Arc<dyn TerminologyService>,
+    /// Written to `--tx-cache` with [`CachedTerminologyService::persist`]
+    /// once the run is done
+    pub(crate) cache: Option<Arc<CachedTerminologyService>>,
+}
+
+/// Terminology backend for binding checks: a remote server, or the embedded
+/// core value sets plus any offline packages. Server answers are cached, in
+/// `--tx-cache` across runs when given.
+pub(crate) fn terminology_service(
+    args: &ValidateArgs,
+    tuning: &CacheTuning,
+) -> Result<Terminology> {
+    if let Some(base_url) = &args.tx_server {
+        let client = reqwest::Client::new();
+        let server = HttpTerminologyService {
+            auth: terminology_auth(args, &client)?,
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        };
+        let mut config = tuning.terminology.clone();
+        if let Some(path) = &args.tx_cache {
+            config = config.with_persistence(path);
+        }
+        let cache = Arc::new(CachedTerminologyService::new(Arc::new(server), config));
+        return Ok(Terminology {
+            service: cache.clone(),
+            cache: Some(cache),
+        });
+    }
+    if args.tx_offline.is_empty() {
+        return Ok(Terminology {
+            service: core_terminology_service(),
+            cache: None,
+        });
+    }
+
+    let mut service = InMemoryTerminologyService::with_core_value_sets();
+    for package in &args.tx_offline {
+        let loaded = load_offline_value_sets(package, &mut service)
+            .with_context(|| format!("failed to load terminology from {}", package.display()))?;
+        if !args.quiet {
+            eprintln!("loaded {loaded} value sets from {}", package.display());
+        }
+    }
+    Ok(Terminology {
+        service: Arc::new(service),
+        cache: None,
+    })
+}
+
+/// Authentication for the terminology server: a client credentials token,
+/// a static token, or `--tx-header` headers alone.
+fn terminology_auth(
+    args: &ValidateArgs,
+    client: &reqwest::Client,
+) -> Result<Option<Arc<dyn AuthProvider>>> {
+    let mut headers = HeaderAuth::new();
+    for header in &args.tx_headers {
+        let Some((name, value)) = header.split_once(':') else {
+            bail!("--tx-header must be `Name: value`, got {header:?}");
+        };
+        headers = headers.header(name.trim(), value.trim());
+    }
+
+    let token: Option<Arc<dyn AuthProvider>> = if let Some(client_id) = &args.tx_client_id {
+        let token_url = args
+            .tx_token_url
+            .as_deref()
+            .context("--tx-client-id needs --tx-token-url")?;
+        let transport = Arc::new(ReqwestTokenTransport {
+            client: client.clone(),
+        });
+        let mut auth = ClientCredentialsAuth::new(token_url, client_id, transport);
+        if let Some(secret) = &args.tx_client_secret {
+            auth = auth.with_client_secret(secret);
+        }
+        if let Some(scope) = &args.tx_scope {
+            auth = auth.with_scope(scope);
+        }
+        Some(Arc::new(auth) as Arc<dyn AuthProvider>)
+    } else {
+        args.tx_token
+            .as_ref()
+            .map(|token| Arc::new(StaticTokenAuth::new(token)) as Arc<dyn AuthProvider>)
+    };
+
+    let headers: Arc<dyn AuthProvider> = Arc::new(headers);
+    Ok(match (token, args.tx_headers.is_empty()) {
+        (None, true) => None,
+        (None, false) => Some(headers),
+        (Some(token), true) => Some(token),
+        (Some(token), false) => {
+            Some(Arc::new(CombinedAuth(vec![headers, token])) as Arc<dyn AuthProvider>)
+        }
+    })
+}
+
+/// Headers of several providers, in order.
+struct CombinedAuth(Vec<Arc<dyn AuthProvider>>);
+
+#[async_trait]
+impl AuthProvider for CombinedAuth {
+    async fn headers(&self) -> AuthResult<Vec<(String, String)>> {
+        let mut headers = Vec::new();
+        for provider in &self.0 {
+            headers.extend(provider.headers().await?);
+        }
+        Ok(headers)
+    }
+
+    async fn invalidate(&self) {
+        for provider in &self.0 {
+            provider.invalidate().await;
+        }
+    }
+}
+
+/// Token requests of [`ClientCredentialsAuth`] sent with reqwest.
+struct ReqwestTokenTransport {
+    client: reqwest::Client,
+}
+
+#[async_trait]
+impl TokenTransport for ReqwestTokenTransport {
+    async fn post_form(&self, url: &str, form: &[(&str, &str)]) -> AuthResult<Value> {
+        let failed = |message: String| AuthError::TokenRequest {
+            url: url.to_string(),
+            message,
+        };
+        // Url's query serializer is application/x-www-form-urlencoded
+        let mut encoder = reqwest::Url::parse("http://localhost/").expect("valid url");
+        encoder.query_pairs_mut().extend_pairs(form);
+        let body = encoder.query().unwrap_or_default().to_string();
+
+        self.client
+            .post(url)
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .header("Accept", "application/json")
+            .body(body)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|e| failed(e.to_string()))?
+            .json()
+            .await
+            .map_err(|e| failed(e.to_string()))
+    }
+}
+
+/// Remote terminology server queried through `ValueSet/$validate-code`.
+struct HttpTerminologyService {
+    client: reqwest::Client,
+    base_url: String,
+    auth: Option<Arc<dyn AuthProvider>>,
+}
+
+impl HttpTerminologyService {
+    /// GET `url` with the authentication headers, retrying once with fresh
+    /// credentials when the server answers 401.
+    async fn get(&self, url: reqwest::Url) -> TerminologyResult<reqwest::Response> {
+        let mut retried = false;
+        loop {
+            let mut request = self
+                .client
+                .get(url.clone())
+                .header("Accept", "application/fhir+json");
+            if let Some(auth) = &self.auth {
+                for (name, value) in auth.headers().await? {
+                    request = request.header(name, value);
+                }
+            }
+            let response = request
+                .send()
+                .await
+                .map_err(|e| TerminologyError::NetworkError(e.to_string()))?;
+            match &self.auth {
+                Some(auth)
+                    if response.status() == reqwest::StatusCode::UNAUTHORIZED && !retried =>
+                {
+                    auth.invalidate().await;
+                    retried = true;
+                }
+                _ => return Ok(response),
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl TerminologyService for HttpTerminologyService {
+    async fn validate_code(
+        &self,
+        value_set_url: &str,
+        code: &str,
+        system: Option<&str>,
+    ) -> TerminologyResult<CodeValidationResult> {
+        let mut params = vec![("url", value_set_url), ("code", code)];
+        if let Some(system) = system {
+            params.push(("system", system));
+        }
+        let url = reqwest::Url::parse_with_params(
+            &format!("{}/ValueSet/$validate-code", self.base_url),
+            &params,
+        )
+        .map_err(|e| TerminologyError::InternalError(e.to_string()))?;
+
+        let response = self.get(url).await?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(TerminologyError::ValueSetNotFound {
+                url: value_set_url.to_string(),
+            });
+        }
+        let parameters: Value = response
+            .error_for_status()
+            .map_err(|e| TerminologyError::ServiceUnavailable {
+                message: e.to_string(),
+            })?
+            .json()
+            .await
+            .map_err(|e| TerminologyError::NetworkError(e.to_string()))?;
+
+        let parameter = |name: &str| {
+            parameters
+                .get("parameter")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .find(|p| p.get("name").and_then(Value::as_str) == Some(name))
+        };
+        let Some(result) = parameter("result")
+            .and_then(|p| p.get("valueBoolean"))
+            .and_then(Value::as_bool)
+        else {
+            return Err(TerminologyError::ServiceUnavailable {
+                message: format!("no result from $validate-code for {value_set_url}"),
+            });
+        };
+        let display = parameter("display")
+            .and_then(|p| p.get("valueString"))
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        let message = parameter("message")
+            .and_then(|p| p.get("valueString"))
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        Ok(CodeValidationResult {
+            valid: result,
+            display,
+            warning: message,
+        })
+    }
+}
+
+/// Passes lookups through and remembers value sets that could not be checked.
+pub(crate) struct RecordingTerminology {
+    pub(crate) inner: Arc<dyn TerminologyService>,
+    pub(crate) unchecked: Mutex<BTreeSet<String>>,
+}
+
+#[async_trait]
+impl TerminologyService for RecordingTerminology {
+    async fn validate_code(
+        &self,
+        value_set_url: &str,
+        code: &str,
+        system: Option<&str>,
+    ) -> TerminologyResult<CodeValidationResult> {
+        let result = self.inner.validate_code(value_set_url, code, system).await;
+        if let Err(err) = &result {
+            self.unchecked
+                .lock()
+                .unwrap()
+                .insert(format!("{value_set_url} ({err})"));
+        }
+        result
+    }
+
+    async fn value_set_exists(&self, value_set_url: &str) -> TerminologyResult<bool> {
+        self.inner.value_set_exists(value_set_url).await
+    }
+
+    async fn get_display(&self, system: &str, code: &str) -> TerminologyResult<Option<String>> {
+        self.inner.get_display(system, code).await
+    }
+}
+
+/// Register the ValueSets of a FHIR package tarball that can be expanded
+/// locally: those with an `expansion`, or whose `compose.include` entries
+/// list concepts or name a whole CodeSystem from the same package. Value
+/// sets using filters or imports are skipped and stay unchecked.
+fn load_offline_value_sets(
+    package: &Path,
+    service: &mut InMemoryTerminologyService,
+) -> Result<usize> {
+    let file = fs::File::open(package)?;
+    let mut archive = tar::Archive::new(GzDecoder::new(file));
+    let mut value_sets = Vec::new();
+    let mut code_systems: HashMap<String, Vec<(String, Option<String>)>> = HashMap::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.to_path_buf();
+        if path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        let mut content = String::new();
+        entry.read_to_string(&mut content)?;
+        let Ok(resource) = serde_json::from_str::<Value>(&content) else {
+            continue;
+        };
+        match resource.get("resourceType").and_then(Value::as_str) {
+            Some("ValueSet") => value_sets.push(resource),
+            Some("CodeSystem") => {
+                if let Some(url) = resource.get("url").and_then(Value::as_str) {
+                    let mut codes = Vec::new();
+                    collect_concepts(resource.get("concept"), "concept", &mut codes);
+                    code_systems.insert(url.to_string(), codes);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut loaded = 0usize;
+    for value_set in &value_sets {
+        let Some(url) = value_set.get("url").and_then(Value::as_str) else {
+            continue;
+        };
+        let Some(codes) = expand_value_set(value_set, &code_systems) else {
+            continue;
+        };
+        let mut keys = vec![url.to_string()];
+        if let Some(version) = value_set.get("version").and_then(Value::as_str) {
+            keys.push(format!("{url}|{version}"));
+        }
+        for key in &keys {
+            for (system, code, display) in &codes {
+                service.add_code(key, code, Some(system), display.as_deref());
+            }
+        }
+        loaded += 1;
+    }
+    Ok(loaded)
+}
+
+type ExpandedCode = (String, String, Option<String>);
+
+fn expand_value_set(
+    value_set: &Value,
+    code_systems: &HashMap<String, Vec<(String, Option<String>)>>,
+) -> Option<Vec<ExpandedCode>> {
+    if let Some(contains) = value_set.get("expansion").and_then(|e| e.get("contains")) {
+        let mut codes = Vec::new();
+        collect_expansion(contains, &mut codes);
+        return Some(codes);
+    }
+
+    let mut codes = Vec::new();
+    let includes = value_set.get("compose")?.get("include")?.as_array()?;
+    for include in includes {
+        if include.get("filter").is_some() || include.get("valueSet").is_some() {
+            return None;
+        }
+        let system = include.get("system")?.as_str()?;
+        let mut concepts = Vec::new();
+        if include.get("concept").is_some() {
+            collect_concepts(include.get("concept"), "", &mut concepts);
+        } else {
+            concepts = code_systems.get(system)?.clone();
+        }
+        codes.extend(
+            concepts
+                .into_iter()
+                .map(|(code, display)| (system.to_string(), code, display)),
+        );
+    }
+    Some(codes)
+}
+
+/// Collect `code`/`display` pairs, descending into `nested` children
+/// (CodeSystem concept hierarchies) when a key is given.
+fn collect_concepts(
+    concepts: Option<&Value>,
+    nested: &str,
+    out: &mut Vec<(String, Option<String>)>,
+) {
+    for concept in concepts.and_then(Value::as_array).into_iter().flatten() {
+        if let Some(code) = concept.get("code").and_then(Value::as_str) {
+            let display = concept.get("display").and_then(Value::as_str);
+            out.push((code.to_string(), display.map(str::to_string)));
+        }
+        if !nested.is_empty() {
+            collect_concepts(concept.get(nested), nested, out);
+        }
+    }
+}
+
+fn collect_expansion(contains: &Value, out: &mut Vec<ExpandedCode>) {
+    for item in contains.as_array().into_iter().flatten() {
+        if let (Some(system), Some(code)) = (
+            item.get("system").and_then(Value::as_str),
+            item.get("code").and_then(Value::as_str),
+        ) {
+            let display = item.get("display").and_then(Value::as_str);
+            out.push((
+                system.to_string(),
+                code.to_string(),
+                display.map(str::to_string),
+            ));
+        }
+        if let Some(nested) = item.get("contains") {
+            collect_expansion(nested, out);
+        }
+    }
+}
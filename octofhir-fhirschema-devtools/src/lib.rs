@@ -0,0 +1,3 @@
+//! Code shared by the devtools binaries.
+
+pub mod schema_files;
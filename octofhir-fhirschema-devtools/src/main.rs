@@ -1,9 +1,17 @@
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use octofhir_canonical_manager::{CanonicalManager, FcmConfig, PackageSpec};
-use octofhir_fhirschema::{FhirSchema, StructureDefinition, translate};
+use octofhir_fhirschema::serialization::{BundleFormat, encode};
+use octofhir_fhirschema::validation::{CompiledSchema, SchemaCompiler};
+use octofhir_fhirschema::{
+    FhirSchema, InMemorySchemaProvider, SchemaManifest, SearchParameter, SearchParameterRegistry,
+    StructureDefinition, translate,
+};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 #[derive(Parser, Clone)]
 #[command(name = "schema-generator")]
@@ -34,10 +42,99 @@ struct Args {
     #[arg(long, help = "Generate schemas for all FHIR versions")]
     all_versions: bool,
 
+    #[arg(
+        long,
+        help = "Also write compiled schemas for the embedded-compiled feature"
+    )]
+    compiled: bool,
+
+    #[arg(
+        long,
+        help = "Also write the core SearchParameters for the embedded-search-params feature"
+    )]
+    search_parameters: bool,
+
+    #[arg(
+        long,
+        help = "Generate an implementation guide profile pack (us-core, ips) instead of core schemas"
+    )]
+    profile_pack: Option<String>,
+
+    #[arg(
+        long = "package",
+        help = "Generate a schema pack for an arbitrary IG package, e.g. hl7.fhir.us.core@6.1.0. Can be repeated."
+    )]
+    packages: Vec<String>,
+
+    #[arg(
+        long,
+        value_enum,
+        help = "Serialization format of the schema bundle. Only zstd-compressed JSON can be embedded; bincode is not offered because schemas carry arbitrary JSON values",
+        default_value = "json"
+    )]
+    format: BundleFormatArg,
+
+    #[arg(
+        long,
+        value_enum,
+        help = "Compression of the schema bundle",
+        default_value = "zstd"
+    )]
+    compress: CompressionArg,
+
+    #[arg(
+        long,
+        help = "Do not write anything; fail if regeneration would change the existing artifacts"
+    )]
+    check: bool,
+
     #[arg(long, help = "Verbose output")]
     verbose: bool,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum BundleFormatArg {
+    Json,
+    #[value(alias = "msgpack")]
+    Messagepack,
+    Yaml,
+    Cbor,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum CompressionArg {
+    Zstd,
+    None,
+}
+
+impl BundleFormatArg {
+    fn extension(self) -> &'static str {
+        match self {
+            BundleFormatArg::Json => "json",
+            BundleFormatArg::Messagepack => "msgpack",
+            BundleFormatArg::Yaml => "yaml",
+            BundleFormatArg::Cbor => "cbor",
+        }
+    }
+
+    /// Encodes with object keys in sorted order, like [`to_stable_json`].
+    fn encode<T: Serialize>(self, value: &T) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        match self {
+            BundleFormatArg::Json => to_stable_json(value),
+            BundleFormatArg::Messagepack | BundleFormatArg::Yaml | BundleFormatArg::Cbor => {
+                let format = match self {
+                    BundleFormatArg::Yaml => BundleFormat::Yaml,
+                    BundleFormatArg::Cbor => BundleFormat::Cbor,
+                    _ => BundleFormat::MessagePack,
+                };
+                let value = serde_json::to_value(value)
+                    .map_err(|e| format!("JSON serialization error: {e}"))?;
+                Ok(encode(&value, format)?)
+            }
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
@@ -55,6 +152,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Create output directory
     fs::create_dir_all(&args.output)?;
 
+    if let Some(pack) = &args.profile_pack {
+        return generate_profile_pack(&args, pack).await;
+    }
+
+    if !args.packages.is_empty() {
+        return generate_package_packs(&args).await;
+    }
+
     if args.all_versions {
         println!("🔧 Generating schemas for all FHIR versions");
         println!("📂 Output directory: {}", args.output.display());
@@ -100,9 +205,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             let schemas = generate_schemas_with_manager(&version_args, &canonical_manager).await?;
 
             if args.individual {
-                save_individual_schemas(&schemas, &args.output, version).await?;
+                save_individual_schemas(&schemas, &args.output, version, args.check).await?;
             } else {
-                save_binary_schemas(&schemas, &args.output, version).await?;
+                save_binary_schemas(&schemas, &args.output, version, &args).await?;
+            }
+            if args.compiled {
+                save_compiled_schemas(&schemas, &args.output, version, args.check).await?;
+            }
+            if args.search_parameters {
+                save_search_parameters(&canonical_manager, &args.output, version, args.check)
+                    .await?;
             }
 
             println!(
@@ -120,12 +232,20 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         println!("🔧 Generating schemas for FHIR version: {}", args.version);
         println!("📂 Output directory: {}", args.output.display());
 
-        let schemas = generate_schemas(&args).await?;
+        let canonical_manager = install_core_package(&args.version).await?;
+        let schemas = generate_schemas_with_manager(&args, &canonical_manager).await?;
 
         if args.individual {
-            save_individual_schemas(&schemas, &args.output, &args.version).await?;
+            save_individual_schemas(&schemas, &args.output, &args.version, args.check).await?;
         } else {
-            save_binary_schemas(&schemas, &args.output, &args.version).await?;
+            save_binary_schemas(&schemas, &args.output, &args.version, &args).await?;
+        }
+        if args.compiled {
+            save_compiled_schemas(&schemas, &args.output, &args.version, args.check).await?;
+        }
+        if args.search_parameters {
+            save_search_parameters(&canonical_manager, &args.output, &args.version, args.check)
+                .await?;
         }
 
         println!("✅ Generated {} schemas successfully!", schemas.len());
@@ -134,10 +254,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-async fn generate_schemas(
-    args: &Args,
-) -> Result<HashMap<String, FhirSchema>, Box<dyn std::error::Error>> {
-    let (package_name, package_version) = get_package_info(&args.version)?;
+async fn install_core_package(
+    version: &str,
+) -> Result<CanonicalManager, Box<dyn std::error::Error>> {
+    let (package_name, package_version) = get_package_info(version)?;
     println!("📦 Using FHIR package: {}", package_name);
 
     // Initialize canonical manager with default config
@@ -154,7 +274,7 @@ async fn generate_schemas(
         .install_package(&package_name, &package_version)
         .await?;
 
-    generate_schemas_with_manager(args, &canonical_manager).await
+    Ok(canonical_manager)
 }
 
 async fn generate_schemas_with_manager(
@@ -319,16 +439,376 @@ fn get_package_info(fhir_version: &str) -> Result<(String, String), Box<dyn std:
     }
 }
 
+/// Maps a profile pack name to the package it is generated from.
+///
+/// Keep in sync with `PROFILE_PACKS` in the library's embedded module.
+fn get_profile_pack_info(pack: &str) -> Result<(String, String), Box<dyn std::error::Error>> {
+    match pack {
+        "us-core" => Ok(("hl7.fhir.us.core".to_string(), "6.1.0".to_string())),
+        "ips" => Ok(("hl7.fhir.uv.ips".to_string(), "1.1.0".to_string())),
+        _ => Err(format!("Unsupported profile pack: {pack}").into()),
+    }
+}
+
+async fn generate_profile_pack(args: &Args, pack: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let (package_name, package_version) = get_profile_pack_info(pack)?;
+    println!("🔧 Generating profile pack {pack} from {package_name}#{package_version}");
+
+    let config = FcmConfig::load().await?;
+    let canonical_manager = CanonicalManager::new(config).await?;
+    canonical_manager
+        .install_package(&package_name, &package_version)
+        .await?;
+
+    let schemas =
+        collect_schemas_from_package(&canonical_manager, &package_name, args.verbose).await?;
+    save_pack(
+        &schemas,
+        &args.output,
+        &format!("{pack}-{package_version}"),
+        // Every bundled profile pack is an R4 implementation guide
+        "r4",
+        &package_name,
+        &package_version,
+        args.check,
+    )
+}
+
+/// Generates one schema pack per `--package` spec. Canonical manager
+/// installs each package together with its declared dependencies, so
+/// profiles are converted against the dependency closure; each pack holds
+/// only the package's own StructureDefinitions.
+async fn generate_package_packs(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    let specs = args
+        .packages
+        .iter()
+        .map(|spec| parse_package_spec(spec))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    println!("🔧 Initializing Canonical Manager...");
+    let config = FcmConfig::load().await?;
+    let canonical_manager = CanonicalManager::new(config).await?;
+
+    println!(
+        "📥 Installing {} package(s) with dependencies...",
+        specs.len()
+    );
+    canonical_manager
+        .install_packages_parallel(
+            specs
+                .iter()
+                .map(|(name, version)| PackageSpec {
+                    name: name.clone(),
+                    version: version.clone(),
+                    priority: 1,
+                    url: None,
+                })
+                .collect(),
+        )
+        .await?;
+
+    for (name, version) in &specs {
+        println!("\n🏭 Processing package: {name}#{version}");
+        let schemas = collect_schemas_from_package(&canonical_manager, name, args.verbose).await?;
+        save_pack(
+            &schemas,
+            &args.output,
+            &format!("{name}-{version}"),
+            &args.version,
+            name,
+            version,
+            args.check,
+        )?;
+    }
+    Ok(())
+}
+
+/// Parses `name@version` (or `name#version`) into its parts.
+fn parse_package_spec(spec: &str) -> Result<(String, String), Box<dyn std::error::Error>> {
+    match spec.split_once('@').or_else(|| spec.split_once('#')) {
+        Some((name, version)) if !name.is_empty() && !version.is_empty() => {
+            Ok((name.to_string(), version.to_string()))
+        }
+        _ => Err(format!("package must be name@version, got {spec}").into()),
+    }
+}
+
+/// Writes a pack as `packs/{stem}.json.zst` with a `packs/{stem}_manifest.json`
+/// recording the source package.
+fn save_pack(
+    schemas: &HashMap<String, FhirSchema>,
+    output_dir: &Path,
+    stem: &str,
+    fhir_version: &str,
+    package_name: &str,
+    package_version: &str,
+    check: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let packs_dir = output_dir.join("packs");
+    fs::create_dir_all(&packs_dir)?;
+    let output_file = packs_dir.join(format!("{stem}.json.zst"));
+    let sha256 = write_compressed_json(schemas, &output_file, check)?;
+
+    let manifest = SchemaManifest {
+        fhir_version: fhir_version.to_string(),
+        package_name: package_name.to_string(),
+        package_version: package_version.to_string(),
+        generated_at: Some(chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true)),
+        converter_version: Some(octofhir_fhirschema::VERSION.to_string()),
+        schema_count: schemas.len(),
+        sha256,
+    };
+    write_manifest(
+        &manifest,
+        &packs_dir.join(format!("{stem}_manifest.json")),
+        check,
+    )?;
+
+    println!(
+        "✅ Saved {} schemas for {package_name}#{package_version} to: {}",
+        schemas.len(),
+        output_file.display()
+    );
+    Ok(())
+}
+
+/// Serializes with object keys in sorted order.
+///
+/// Schemas hold `HashMap`s whose iteration order changes between runs;
+/// going through `serde_json::Value` (a `BTreeMap` without the
+/// `preserve_order` feature) makes the output byte-stable.
+fn to_stable_json<T: Serialize>(value: &T) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let value =
+        serde_json::to_value(value).map_err(|e| format!("JSON serialization error: {e}"))?;
+    Ok(serde_json::to_vec(&value)?)
+}
+
+/// Writes JSON as a zstd-compressed file and returns the hex SHA-256 of the
+/// file on disk.
+///
+/// An existing file with the same decompressed content is left untouched, so
+/// unchanged artifacts keep their bytes and hash. With `check`, nothing is
+/// written and a differing or missing file is an error.
+fn write_compressed(
+    json: &[u8],
+    output_file: &Path,
+    check: bool,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let existing = fs::read(output_file).ok();
+    if let Some(existing) = &existing
+        && zstd::stream::decode_all(existing.as_slice())
+            .ok()
+            .as_deref()
+            == Some(json)
+    {
+        return Ok(format!("{:x}", Sha256::digest(existing)));
+    }
+    if check {
+        return Err(format!(
+            "{} is out of date; regenerate the schemas",
+            output_file.display()
+        )
+        .into());
+    }
+
+    let compressed = zstd::stream::encode_all(json, 19)?;
+    let sha256 = format!("{:x}", Sha256::digest(&compressed));
+    fs::write(output_file, compressed)?;
+    Ok(sha256)
+}
+
+/// Uncompressed counterpart of [`write_compressed`].
+fn write_uncompressed(
+    bytes: &[u8],
+    output_file: &Path,
+    check: bool,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let sha256 = format!("{:x}", Sha256::digest(bytes));
+    if fs::read(output_file).ok().as_deref() == Some(bytes) {
+        return Ok(sha256);
+    }
+    if check {
+        return Err(format!(
+            "{} is out of date; regenerate the schemas",
+            output_file.display()
+        )
+        .into());
+    }
+    fs::write(output_file, bytes)?;
+    Ok(sha256)
+}
+
+fn write_compressed_json(
+    schemas: &HashMap<String, FhirSchema>,
+    output_file: &Path,
+    check: bool,
+) -> Result<String, Box<dyn std::error::Error>> {
+    write_compressed(&to_stable_json(schemas)?, output_file, check)
+}
+
+/// Writes a manifest, keeping the existing file when only `generated_at`
+/// would change. With `check`, fails if anything besides `generated_at` and
+/// `converter_version` differs from the file on disk.
+fn write_manifest(
+    manifest: &SchemaManifest,
+    manifest_file: &Path,
+    check: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let existing = fs::read_to_string(manifest_file)
+        .ok()
+        .and_then(|text| serde_json::from_str::<SchemaManifest>(&text).ok());
+    let same = |ignore_converter: bool| {
+        existing.as_ref().is_some_and(|old| {
+            old.fhir_version == manifest.fhir_version
+                && old.package_name == manifest.package_name
+                && old.package_version == manifest.package_version
+                && old.schema_count == manifest.schema_count
+                && old.sha256 == manifest.sha256
+                && (ignore_converter || old.converter_version == manifest.converter_version)
+        })
+    };
+
+    if check {
+        if !same(true) {
+            return Err(format!(
+                "{} is out of date; regenerate the schemas",
+                manifest_file.display()
+            )
+            .into());
+        }
+        return Ok(());
+    }
+    if same(false) {
+        return Ok(());
+    }
+    fs::write(
+        manifest_file,
+        serde_json::to_string_pretty(manifest)? + "\n",
+    )?;
+    println!("🧾 Saved schema manifest to: {}", manifest_file.display());
+    Ok(())
+}
+
 async fn save_binary_schemas(
     schemas: &HashMap<String, FhirSchema>,
     output_dir: &Path,
     version: &str,
+    args: &Args,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let output_file = output_dir.join(format!("{version}_schemas.json"));
-    let serialized =
-        serde_json::to_vec(schemas).map_err(|e| format!("JSON serialization error: {e}"))?;
-    fs::write(&output_file, serialized)?;
-    println!("💾 Saved JSON schemas to: {}", output_file.display());
+    let compressed = args.compress == CompressionArg::Zstd;
+    let mut file_name = format!("{version}_schemas.{}", args.format.extension());
+    if compressed {
+        file_name.push_str(".zst");
+    }
+    let output_file = output_dir.join(file_name);
+    let encoded = args.format.encode(schemas)?;
+    let sha256 = if compressed {
+        write_compressed(&encoded, &output_file, args.check)?
+    } else {
+        write_uncompressed(&encoded, &output_file, args.check)?
+    };
+
+    // The manifest describes the embedded bundle, which is always
+    // zstd-compressed JSON; other formats are for runtime loading only.
+    if args.format == BundleFormatArg::Json && compressed {
+        let (package_name, package_version) = get_package_info(version)?;
+        let manifest = SchemaManifest {
+            fhir_version: version.to_string(),
+            package_name,
+            package_version,
+            generated_at: Some(
+                chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            ),
+            converter_version: Some(octofhir_fhirschema::VERSION.to_string()),
+            schema_count: schemas.len(),
+            sha256,
+        };
+        write_manifest(
+            &manifest,
+            &output_dir.join(format!("{version}_manifest.json")),
+            args.check,
+        )?;
+    }
+    if args.check {
+        println!("✅ {} is up to date", output_file.display());
+    } else {
+        println!("💾 Saved schemas to: {}", output_file.display());
+    }
+
+    Ok(())
+}
+
+/// Compiles every schema (inlining nested types) and writes the compiled
+/// forms as zstd-compressed JSON, keyed by schema id like the source bundle.
+async fn save_compiled_schemas(
+    schemas: &HashMap<String, FhirSchema>,
+    output_dir: &Path,
+    version: &str,
+    check: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let provider = InMemorySchemaProvider::from_map(
+        schemas
+            .iter()
+            .map(|(name, schema)| (name.clone(), Arc::new(schema.clone())))
+            .collect(),
+    );
+    let compiler = SchemaCompiler::new(Arc::new(provider));
+
+    let mut compiled = HashMap::new();
+    let mut failures = 0;
+    for name in schemas.keys() {
+        match compiler.compile(name).await {
+            Ok(schema) => {
+                compiled.insert(name.clone(), CompiledSchema::clone(&schema));
+            }
+            Err(e) => {
+                failures += 1;
+                println!("   ⚠️  {e}");
+            }
+        }
+    }
+
+    let output_file = output_dir.join(format!("{version}_compiled.json.zst"));
+    write_compressed(&to_stable_json(&compiled)?, &output_file, check)?;
+    println!(
+        "💾 Saved {} compiled schemas ({failures} failed) to: {}",
+        compiled.len(),
+        output_file.display()
+    );
+
+    Ok(())
+}
+
+/// Writes the SearchParameters of the version's core package as a
+/// zstd-compressed JSON list, ordered by code and url.
+async fn save_search_parameters(
+    canonical_manager: &CanonicalManager,
+    output_dir: &Path,
+    version: &str,
+    check: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (package_name, _) = get_package_info(version)?;
+    let resource_indices = canonical_manager
+        .find_by_type_and_package("SearchParameter", &package_name)
+        .await?;
+
+    let mut resources = Vec::with_capacity(resource_indices.len());
+    for resource_index in resource_indices {
+        let resolved = canonical_manager
+            .resolve_with_fhir_version(&resource_index.canonical_url, &resource_index.fhir_version)
+            .await?;
+        resources.push(resolved.resource.content.clone());
+    }
+    let registry = SearchParameterRegistry::from_resources(&resources);
+    let params: Vec<&SearchParameter> = registry.all();
+
+    let output_file = output_dir.join(format!("{version}_search_parameters.json.zst"));
+    write_compressed(&to_stable_json(&params)?, &output_file, check)?;
+    println!(
+        "💾 Saved {} search parameters to: {}",
+        params.len(),
+        output_file.display()
+    );
 
     Ok(())
 }
@@ -337,13 +817,24 @@ async fn save_individual_schemas(
     schemas: &HashMap<String, FhirSchema>,
     output_dir: &Path,
     version: &str,
+    check: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let schemas_dir = output_dir.join(format!("{version}_schemas"));
     fs::create_dir_all(&schemas_dir)?;
 
     for (name, schema) in schemas {
         let schema_file = schemas_dir.join(format!("{name}.json"));
-        let json = serde_json::to_string_pretty(schema)?;
+        let json = serde_json::to_string_pretty(&serde_json::to_value(schema)?)?;
+        if check {
+            if fs::read_to_string(&schema_file).ok().as_deref() != Some(json.as_str()) {
+                return Err(format!(
+                    "{} is out of date; regenerate the schemas",
+                    schema_file.display()
+                )
+                .into());
+            }
+            continue;
+        }
         fs::write(&schema_file, json)?;
     }
 
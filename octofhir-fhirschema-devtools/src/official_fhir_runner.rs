@@ -8,7 +8,7 @@ use octofhir_fhirschema::{
 };
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
 use std::fs;
 use std::io::{Cursor, Read};
@@ -71,6 +71,18 @@ struct Args {
     #[arg(long, help = "Exit non-zero if any Java-comparable case disagrees")]
     fail_on_mismatch: bool,
 
+    #[arg(
+        long,
+        help = "Previous report to diff against. Defaults to the report left in --output by the last run."
+    )]
+    baseline: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Exit non-zero if any case that passed in the baseline now fails"
+    )]
+    fail_on_regression: bool,
+
     #[arg(
         long,
         help = "Enable required-binding validation via a terminology server (default tx.fhir.org/r4). Off by default (offline)."
@@ -199,9 +211,46 @@ struct Report {
     elapsed_ms: f64,
     avg_ms_per_completed_case: f64,
     cases_per_second: f64,
+    modules: BTreeMap<String, ModuleSummary>,
+    baseline_diff: Option<BaselineDiff>,
     cases: Vec<CaseReport>,
 }
 
+/// Pass/fail counts for one manifest module.
+#[derive(Debug, Default, Serialize)]
+struct ModuleSummary {
+    passed: usize,
+    failed: usize,
+    skipped: usize,
+}
+
+/// Cases whose outcome changed since the baseline report.
+#[derive(Debug, Default, Serialize)]
+struct BaselineDiff {
+    baseline: PathBuf,
+    /// Passed in the baseline, fail now
+    regressions: Vec<String>,
+    /// Failed in the baseline, pass now
+    fixes: Vec<String>,
+    /// Not in the baseline
+    new_cases: Vec<String>,
+    /// In the baseline, not selected in this run
+    missing_cases: Vec<String>,
+}
+
+/// The parts of a previous [`Report`] needed for [`BaselineDiff`].
+#[derive(Debug, Deserialize)]
+struct BaselineReport {
+    cases: Vec<BaselineCase>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BaselineCase {
+    name: String,
+    passed: bool,
+    skipped: bool,
+}
+
 #[derive(Debug, Serialize)]
 struct CaseReport {
     name: String,
@@ -249,6 +298,10 @@ async fn main() -> Result<()> {
     fs::create_dir_all(&args.output)
         .with_context(|| format!("failed to create {}", args.output.display()))?;
 
+    let report_path = args.output.join("official-fhir-runner-report.json");
+    let baseline_path = args.baseline.clone().unwrap_or_else(|| report_path.clone());
+    let baseline = load_baseline(&baseline_path, args.baseline.is_some())?;
+
     let validator_dir = ensure_test_cases(&args).await?;
     let manifest = load_manifest(&validator_dir)?;
     let cli = match args.runner {
@@ -337,6 +390,20 @@ async fn main() -> Result<()> {
     } else {
         (java_matches as f64 / completed_cases as f64) * 100.0
     };
+    let mut modules: BTreeMap<String, ModuleSummary> = BTreeMap::new();
+    for case in &cases {
+        let module = modules
+            .entry(case.module.clone().unwrap_or_else(|| "(none)".to_string()))
+            .or_default();
+        if case.skipped {
+            module.skipped += 1;
+        } else if case.passed {
+            module.passed += 1;
+        } else {
+            module.failed += 1;
+        }
+    }
+    let baseline_diff = baseline.map(|baseline| diff_baseline(baseline_path, &baseline, &cases));
     let report = Report {
         suite_url: args.download_url,
         validator_dir,
@@ -366,10 +433,11 @@ async fn main() -> Result<()> {
         } else {
             completed_cases as f64 / (elapsed_ms / 1000.0)
         },
+        modules,
+        baseline_diff,
         cases,
     };
 
-    let report_path = args.output.join("official-fhir-runner-report.json");
     fs::write(&report_path, serde_json::to_string_pretty(&report)?)
         .with_context(|| format!("failed to write {}", report_path.display()))?;
 
@@ -377,10 +445,63 @@ async fn main() -> Result<()> {
     if args.fail_on_mismatch && report.java_mismatches > 0 {
         bail!("official FHIR Java agreement mismatches found");
     }
+    if args.fail_on_regression
+        && report
+            .baseline_diff
+            .as_ref()
+            .is_some_and(|diff| !diff.regressions.is_empty())
+    {
+        bail!("official FHIR cases regressed since the baseline");
+    }
 
     Ok(())
 }
 
+/// Reads a previous report. A missing explicit baseline is an error; a
+/// missing default one just means this is the first run.
+fn load_baseline(path: &Path, explicit: bool) -> Result<Option<BaselineReport>> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if !explicit && err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err).with_context(|| format!("failed to read {}", path.display())),
+    };
+    let baseline = serde_json::from_str(&text)
+        .with_context(|| format!("failed to parse baseline report {}", path.display()))?;
+    Ok(Some(baseline))
+}
+
+fn diff_baseline(path: PathBuf, baseline: &BaselineReport, cases: &[CaseReport]) -> BaselineDiff {
+    let previous: HashMap<&str, &BaselineCase> = baseline
+        .cases
+        .iter()
+        .map(|case| (case.name.as_str(), case))
+        .collect();
+    let mut diff = BaselineDiff {
+        baseline: path,
+        ..BaselineDiff::default()
+    };
+    for case in cases.iter().filter(|case| !case.skipped) {
+        match previous.get(case.name.as_str()) {
+            None => diff.new_cases.push(case.name.clone()),
+            Some(old) if old.skipped => diff.new_cases.push(case.name.clone()),
+            Some(old) if old.passed && !case.passed => diff.regressions.push(case.name.clone()),
+            Some(old) if !old.passed && case.passed => diff.fixes.push(case.name.clone()),
+            Some(_) => {}
+        }
+    }
+    let current: HashMap<&str, &CaseReport> = cases
+        .iter()
+        .map(|case| (case.name.as_str(), case))
+        .collect();
+    diff.missing_cases = baseline
+        .cases
+        .iter()
+        .filter(|case| !case.skipped && !current.contains_key(case.name.as_str()))
+        .map(|case| case.name.clone())
+        .collect();
+    diff
+}
+
 async fn ensure_test_cases(args: &Args) -> Result<PathBuf> {
     let validator_dir = args.cache_dir.join("validator");
     let manifest_path = validator_dir.join("manifest.json");
@@ -574,9 +695,9 @@ async fn validate_library(
             .errors
             .into_iter()
             .map(|error| IssueSummary {
-                error_type: error.error_type,
-                message: error.message,
-                path: error.path,
+                error_type: error.error_type.into_owned(),
+                message: error.message.map(|message| message.to_string()),
+                path: error.path.to_values(),
             })
             .collect(),
     })
@@ -671,7 +792,7 @@ async fn create_r4_validator_with_fhirpath(
     questionnaire_provider: Option<Arc<MapQuestionnaireProvider>>,
     supporting_schemas: HashMap<String, FhirSchema>,
 ) -> Result<FhirValidator> {
-    let mut schemas = get_schemas(FhirVersion::R4).clone();
+    let mut schemas = get_schemas(FhirVersion::R4)?.clone();
     // Supporting StructureDefinitions win over embedded ones so a test's own
     // profile/base definition is used when both are present.
     schemas.extend(supporting_schemas);
@@ -1001,6 +1122,30 @@ fn print_summary(report: &Report, report_path: &Path) {
         report.cases_per_second, report.avg_ms_per_completed_case, report.elapsed_ms
     );
 
+    println!("modules:");
+    for (module, summary) in &report.modules {
+        println!(
+            "  {module}: passed={}, failed={}, skipped={}",
+            summary.passed, summary.failed, summary.skipped
+        );
+    }
+    if let Some(diff) = &report.baseline_diff {
+        println!(
+            "since {}: {} regressions, {} fixes, {} new, {} missing",
+            diff.baseline.display(),
+            diff.regressions.len(),
+            diff.fixes.len(),
+            diff.new_cases.len(),
+            diff.missing_cases.len()
+        );
+        for name in &diff.regressions {
+            println!("  regression: {name}");
+        }
+        for name in &diff.fixes {
+            println!("  fixed: {name}");
+        }
+    }
+
     for case in report.cases.iter().filter(|case| case.mismatch).take(20) {
         println!(
             "  mismatch: {} expected={:?} actual={:?} errors={} first={}",
@@ -0,0 +1,316 @@
+//! Loading schemas from package directories and schema files.
+//!
+//! Package directories may hold StructureDefinition JSON files, which are
+//! translated, and FhirSchema files or bundles in any [`BundleFormat`]. A
+//! directory with a `package.json` brings in the installed packages it
+//! depends on as well.
+
+use anyhow::{Context, Result};
+use octofhir_fhir_model::provider::FhirVersion as ModelFhirVersion;
+use octofhir_fhirpath::FhirPathEngine;
+use octofhir_fhirschema::package::{PackageGraph, PackageManifest};
+use octofhir_fhirschema::serialization::{BundleFormat, load_schema_bundle};
+use octofhir_fhirschema::{
+    DynamicSchemaProvider, FhirSchema, FhirValidator, StructureDefinition, load_schema_file,
+    translate,
+};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Name of the synthetic package depending on every package directory, so
+/// they resolve as one dependency closure.
+const LOAD_ROOT: &str = "fhirschema.load";
+
+/// Add every schema in the package directories, and in the installed
+/// packages they depend on, to `schemas`, under its name and its url.
+/// Returns the number of schemas read.
+pub fn load_package_schemas(
+    package_dirs: &[PathBuf],
+    schemas: &mut HashMap<String, FhirSchema>,
+) -> Result<usize> {
+    let mut loaded = 0usize;
+    for package_dir in &with_dependencies(package_dirs)? {
+        let mut files = Vec::new();
+        collect_schema_files(package_dir, &mut files)
+            .with_context(|| format!("failed to scan {}", package_dir.display()))?;
+        for path in files {
+            for schema in read_schema_file(&path)? {
+                insert_schema_aliases(schemas, schema);
+                loaded += 1;
+            }
+        }
+    }
+    Ok(loaded)
+}
+
+/// The package directories followed by those of the packages they depend
+/// on, transitively.
+///
+/// Dependencies are read from the `package.json` of each directory (or of
+/// its `package/` folder) and looked up as `<name>#<version>` directories
+/// next to it, the layout `fhirschema download` installs. Core packages are
+/// skipped, as the embedded schemas provide them; other dependencies that
+/// are not installed, and version conflicts, are reported as warnings.
+fn with_dependencies(package_dirs: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut dirs = package_dirs.to_vec();
+    let mut installed_in = BTreeSet::new();
+    let mut root = PackageManifest::new(LOAD_ROOT, "0");
+    let mut graph = PackageGraph::new();
+    for package_dir in package_dirs {
+        let Some(manifest) = read_dir_manifest(package_dir)? else {
+            continue;
+        };
+        if let Some(parent) = package_dir.parent() {
+            installed_in.insert(parent.to_path_buf());
+        }
+        root = root.with_dependency(&manifest.name, &manifest.version);
+        graph.add_package(manifest);
+    }
+    graph.add_package(root);
+
+    let mut unavailable = BTreeMap::new();
+    let resolved = loop {
+        let resolved = graph.resolve(LOAD_ROOT, "0")?;
+        let mut found = false;
+        for (name, version) in resolved.missing.iter().cloned() {
+            if unavailable.contains_key(&name) {
+                continue;
+            }
+            let installed = installed_in
+                .iter()
+                .map(|dir| dir.join(format!("{name}#{version}")))
+                .find(|dir| dir.is_dir());
+            let manifest = match &installed {
+                Some(dir) if !is_core_package(&name) => read_dir_manifest(dir)?,
+                _ => None,
+            };
+            match (installed, manifest) {
+                (Some(dir), Some(mut manifest)) => {
+                    // Record it under the requested version so the graph finds it
+                    manifest.version = version;
+                    graph.add_package(manifest);
+                    dirs.push(dir);
+                    found = true;
+                }
+                _ => {
+                    unavailable.insert(name, version);
+                }
+            }
+        }
+        if !found {
+            break resolved;
+        }
+    };
+
+    for (name, version) in &unavailable {
+        if !is_core_package(name) {
+            eprintln!("warning: dependency {name}#{version} is not installed, skipping it");
+        }
+    }
+    for conflict in &resolved.conflicts {
+        for (version, requested_by) in &conflict.requested {
+            eprintln!(
+                "warning: {} {version} requested by {requested_by}, using {}",
+                conflict.name, conflict.selected
+            );
+        }
+    }
+    Ok(dirs)
+}
+
+/// The manifest of a package directory, if it has a `package.json`.
+fn read_dir_manifest(package_dir: &Path) -> Result<Option<PackageManifest>> {
+    let Some(path) = [
+        package_dir.join("package.json"),
+        package_dir.join("package").join("package.json"),
+    ]
+    .into_iter()
+    .find(|path| path.is_file()) else {
+        return Ok(None);
+    };
+    let content =
+        fs::read_to_string(&path).with_context(|| format!("failed to read {}", path.display()))?;
+    let value: Value = serde_json::from_str(&content)
+        .with_context(|| format!("failed to parse {}", path.display()))?;
+    let manifest = PackageManifest::from_package_json(&value)
+        .with_context(|| format!("invalid package manifest {}", path.display()))?;
+    Ok(Some(manifest))
+}
+
+/// Whether `name` is a FHIR core package such as `hl7.fhir.r4.core`.
+fn is_core_package(name: &str) -> bool {
+    name.strip_prefix("hl7.fhir.r")
+        .is_some_and(|rest| rest.ends_with(".core"))
+}
+
+/// Read the schemas in a file: a StructureDefinition JSON file is
+/// translated, and a FhirSchema or schema bundle in any supported format
+/// (detected from the extension, else the content) is read as is. Other
+/// FHIR resources yield nothing.
+pub fn read_schema_file(path: &Path) -> Result<Vec<FhirSchema>> {
+    let file_name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default();
+    if BundleFormat::from_file_name(file_name) != Some((BundleFormat::Json, false)) {
+        if let Ok(schema) = load_schema_file(path) {
+            return Ok(vec![schema]);
+        }
+        let bundle = load_schema_bundle(path)
+            .with_context(|| format!("failed to read schemas from {}", path.display()))?;
+        return Ok(bundle.into_values().collect());
+    }
+
+    let content =
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let value: Value = serde_json::from_str(&content)
+        .with_context(|| format!("failed to parse {}", path.display()))?;
+    match value.get("resourceType").and_then(Value::as_str) {
+        Some("StructureDefinition") => {}
+        Some(_) => return Ok(Vec::new()),
+        // package.json and .index.json have no resourceType either
+        None if value.get("url").is_none() || value.get("kind").is_none() => {
+            return Ok(Vec::new());
+        }
+        None => {
+            let schema: FhirSchema = serde_json::from_value(value)
+                .with_context(|| format!("failed to decode FhirSchema {}", path.display()))?;
+            return Ok(vec![schema]);
+        }
+    }
+
+    let structure_definition: StructureDefinition = serde_json::from_value(value)
+        .with_context(|| format!("failed to decode StructureDefinition {}", path.display()))?;
+    let schema = translate(structure_definition, None)
+        .with_context(|| format!("failed to translate {}", path.display()))?;
+    Ok(vec![schema])
+}
+
+/// Register `schema` under both its name and its canonical url.
+pub fn insert_schema_aliases(schemas: &mut HashMap<String, FhirSchema>, schema: FhirSchema) {
+    schemas.insert(schema.name.clone(), schema.clone());
+    schemas.insert(schema.url.clone(), schema);
+}
+
+/// Collect files with an extension [`BundleFormat`] recognizes.
+fn collect_schema_files(dir: &Path, out: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_schema_files(&path, out)?;
+        } else if path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(BundleFormat::from_file_name)
+            .is_some()
+        {
+            out.push(path);
+        }
+    }
+    out.sort();
+    Ok(())
+}
+
+/// Collect `.json` files below `dir`, recursively and sorted.
+pub fn collect_json_files(dir: &Path, out: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_json_files(&path, out)?;
+        } else if path.extension().is_some_and(|ext| ext == "json") {
+            out.push(path);
+        }
+    }
+    out.sort();
+    Ok(())
+}
+
+/// A validator over `schemas` that evaluates FHIRPath constraints with a
+/// model of the same schemas.
+pub async fn fhirpath_validator(
+    schemas: HashMap<String, FhirSchema>,
+    version: ModelFhirVersion,
+) -> Result<FhirValidator> {
+    let model_provider = Arc::new(DynamicSchemaProvider::new(schemas.clone(), version));
+    let registry = Arc::new(octofhir_fhirpath::create_function_registry());
+    let fhirpath_engine = Arc::new(
+        FhirPathEngine::new(registry, model_provider)
+            .await
+            .context("failed to initialize FHIRPath engine")?,
+    );
+    Ok(FhirValidator::from_schemas(schemas, Some(fhirpath_engine)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use octofhir_fhirschema::{FhirVersion, get_schemas};
+    use serde_json::json;
+
+    fn write_json(path: &Path, value: Value) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, value.to_string()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_profile_based_on_dependency_package() {
+        let dir = std::env::temp_dir().join(format!("fhirschema-packages-{}", std::process::id()));
+        let base = dir.join("example.base#1.0.0");
+        let ig = dir.join("example.ig#2.0.0");
+        write_json(
+            &base.join("package.json"),
+            json!({
+                "name": "example.base", "version": "1.0.0",
+                "dependencies": {"hl7.fhir.r4.core": "4.0.1"}
+            }),
+        );
+        write_json(
+            &base.join("BasePatient.json"),
+            json!({
+                "url": "http://example.org/StructureDefinition/BasePatient",
+                "name": "BasePatient", "type": "Patient", "kind": "resource",
+                "class": "profile", "derivation": "constraint",
+                "base": "http://hl7.org/fhir/StructureDefinition/Patient",
+                "required": ["birthDate"]
+            }),
+        );
+        // Laid out like the FHIR package cache, with a package/ folder
+        write_json(
+            &ig.join("package").join("package.json"),
+            json!({
+                "name": "example.ig", "version": "2.0.0",
+                "dependencies": {"example.base": "1.0.0"}
+            }),
+        );
+        write_json(
+            &ig.join("package").join("IgPatient.json"),
+            json!({
+                "url": "http://example.org/StructureDefinition/IgPatient",
+                "name": "IgPatient", "type": "Patient", "kind": "resource",
+                "class": "profile", "derivation": "constraint",
+                "base": "http://example.org/StructureDefinition/BasePatient",
+                "required": ["gender"]
+            }),
+        );
+
+        let mut schemas = get_schemas(FhirVersion::R4).unwrap().clone();
+        let loaded = load_package_schemas(std::slice::from_ref(&ig), &mut schemas);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(loaded.unwrap(), 2);
+
+        let validator = FhirValidator::from_schemas(schemas, None);
+        let profile = vec!["IgPatient".to_string()];
+        let complete =
+            json!({"resourceType": "Patient", "gender": "female", "birthDate": "1980-01-01"});
+        let result = validator.validate(&complete, profile.clone()).await;
+        assert!(result.valid, "errors: {:?}", result.errors);
+
+        // The base profile's constraint applies through the dependency
+        let missing_birth_date = json!({"resourceType": "Patient", "gender": "female"});
+        let result = validator.validate(&missing_birth_date, profile).await;
+        assert!(!result.valid);
+    }
+}
@@ -2,10 +2,11 @@ use anyhow::{Context, Result, bail};
 use clap::{Parser, ValueEnum};
 use octofhir_canonical_manager::{CanonicalManager, FcmConfig};
 use octofhir_fhir_model::provider::FhirVersion as ModelFhirVersion;
-use octofhir_fhirpath::FhirPathEngine;
 use octofhir_fhirschema::{
-    DynamicSchemaProvider, FhirSchema, FhirValidator, FhirVersion, StructureDefinition,
-    get_schemas, translate,
+    FhirSchema, FhirValidator, FhirVersion, StructureDefinition, get_schemas, translate,
+};
+use octofhir_fhirschema_devtools::schema_files::{
+    collect_json_files, fhirpath_validator, insert_schema_aliases, load_package_schemas,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -15,7 +16,6 @@ use std::ffi::OsStr;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
-use std::sync::Arc;
 use std::time::{Duration, Instant};
 use wait_timeout::ChildExt;
 
@@ -298,9 +298,9 @@ async fn main() -> Result<()> {
                 .errors
                 .iter()
                 .map(|error| ValidationIssueSummary {
-                    error_type: error.error_type.clone(),
-                    message: error.message.clone(),
-                    path: error.path.clone(),
+                    error_type: error.error_type.to_string(),
+                    message: error.message.as_ref().map(ToString::to_string),
+                    path: error.path.to_values(),
                 })
                 .collect(),
         };
@@ -654,7 +654,8 @@ fn load_cases(root: &Path, octofhir_profile_mode: OctofhirProfileMode) -> Result
         }
         root.parent().unwrap_or_else(|| Path::new(""))
     } else {
-        collect_json_files(root, &mut files)?;
+        collect_json_files(root, &mut files)
+            .with_context(|| format!("failed to read {}", root.display()))?;
         root
     };
     files.sort();
@@ -722,20 +723,6 @@ fn octofhir_schema_names(
     }
 }
 
-fn collect_json_files(dir: &Path, out: &mut Vec<PathBuf>) -> Result<()> {
-    for entry in fs::read_dir(dir).with_context(|| format!("failed to read {}", dir.display()))? {
-        let entry = entry?;
-        let path = entry.path();
-        let file_type = entry.file_type()?;
-        if file_type.is_dir() {
-            collect_json_files(&path, out)?;
-        } else if path.extension() == Some(OsStr::new("json")) {
-            out.push(path);
-        }
-    }
-    Ok(())
-}
-
 fn infer_expected_validity(path: &Path) -> ExpectedValidity {
     let parts: Vec<_> = path
         .components()
@@ -823,9 +810,9 @@ async fn validate_octofhir_library(
             .errors
             .iter()
             .map(|error| ValidationIssueSummary {
-                error_type: error.error_type.clone(),
-                message: error.message.clone(),
-                path: error.path.clone(),
+                error_type: error.error_type.to_string(),
+                message: error.message.as_ref().map(ToString::to_string),
+                path: error.path.to_values(),
             })
             .collect(),
         status: None,
@@ -838,10 +825,10 @@ async fn create_r4_validator_with_fhirpath(
     schema_package_dirs: &[PathBuf],
     schema_packages: &[String],
 ) -> Result<FhirValidator> {
-    let mut schemas = get_schemas(FhirVersion::R4).clone();
+    let mut schemas = get_schemas(FhirVersion::R4)?.clone();
     let package_schema_count = load_package_schemas(schema_package_dirs, &mut schemas)?;
     if package_schema_count > 0 {
-        println!("loaded {package_schema_count} package-dir StructureDefinition schemas");
+        println!("loaded {package_schema_count} package-dir schemas");
     }
     let canonical_schema_count =
         load_canonical_package_schemas(schema_packages, &mut schemas).await?;
@@ -849,18 +836,7 @@ async fn create_r4_validator_with_fhirpath(
         println!("loaded {canonical_schema_count} canonical-manager StructureDefinition schemas");
     }
 
-    let model_provider = Arc::new(DynamicSchemaProvider::new(
-        schemas.clone(),
-        ModelFhirVersion::R4,
-    ));
-    let registry = Arc::new(octofhir_fhirpath::create_function_registry());
-    let fhirpath_engine = Arc::new(
-        FhirPathEngine::new(registry, model_provider)
-            .await
-            .context("failed to initialize FHIRPath engine")?,
-    );
-
-    Ok(FhirValidator::from_schemas(schemas, Some(fhirpath_engine)))
+    fhirpath_validator(schemas, ModelFhirVersion::R4).await
 }
 
 async fn load_canonical_package_schemas(
@@ -928,8 +904,9 @@ async fn load_canonical_package_schemas(
                 .and_then(Value::as_str)
                 .unwrap_or(&schema.name)
                 .to_string();
-            insert_schema_aliases(schemas, id, schema.clone());
-            schemas.insert(resource_index.canonical_url.clone(), schema);
+            schemas.insert(id, schema.clone());
+            schemas.insert(resource_index.canonical_url.clone(), schema.clone());
+            insert_schema_aliases(schemas, schema);
             loaded += 1;
         }
     }
@@ -947,50 +924,6 @@ fn parse_package_spec(spec: &str) -> Result<(String, String)> {
     Ok((name.to_string(), version.to_string()))
 }
 
-fn load_package_schemas(
-    package_dirs: &[PathBuf],
-    schemas: &mut HashMap<String, FhirSchema>,
-) -> Result<usize> {
-    let mut loaded = 0usize;
-    for package_dir in package_dirs {
-        let mut files = Vec::new();
-        collect_json_files(package_dir, &mut files)
-            .with_context(|| format!("failed to scan {}", package_dir.display()))?;
-
-        for path in files {
-            let content = fs::read_to_string(&path)
-                .with_context(|| format!("failed to read {}", path.display()))?;
-            let value: Value = serde_json::from_str(&content)
-                .with_context(|| format!("failed to parse {}", path.display()))?;
-            if value.get("resourceType").and_then(Value::as_str) != Some("StructureDefinition") {
-                continue;
-            }
-
-            let structure_definition: StructureDefinition = serde_json::from_value(value)
-                .with_context(|| {
-                    format!("failed to decode StructureDefinition {}", path.display())
-                })?;
-            let schema = translate(structure_definition, None)
-                .with_context(|| format!("failed to translate {}", path.display()))?;
-            insert_schema_aliases(schemas, schema.name.clone(), schema);
-            loaded += 1;
-        }
-    }
-    Ok(loaded)
-}
-
-fn insert_schema_aliases(
-    schemas: &mut HashMap<String, FhirSchema>,
-    primary_key: String,
-    schema: FhirSchema,
-) {
-    let url = schema.url.clone();
-    let name = schema.name.clone();
-    schemas.insert(primary_key, schema.clone());
-    schemas.insert(name, schema.clone());
-    schemas.insert(url, schema);
-}
-
 fn run_octofhir_cli(
     bin: &Path,
     input: &Path,
@@ -0,0 +1,45 @@
+/*
+ * C ABI for octofhir-fhirschema.
+ *
+ * All strings are NUL-terminated UTF-8. Strings returned through
+ * out_result are owned by the library; release them with
+ * fhirschema_string_free.
+ */
+#ifndef FHIRSCHEMA_H
+#define FHIRSCHEMA_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* The resource conforms to every requested schema. */
+#define FHIRSCHEMA_VALID 0
+/* The resource has validation errors; out_result holds the result. */
+#define FHIRSCHEMA_INVALID 1
+/* The call failed before validation; out_result holds {"error": "..."}. */
+#define FHIRSCHEMA_ERROR (-1)
+
+/*
+ * Validate a resource against the FHIR R4 core schemas.
+ *
+ * profiles: JSON array of schema names or canonical URLs, or NULL to use
+ * the resource's resourceType. *out_result receives the JSON validation
+ * result ({"valid": ..., "errors": [...], "warnings": [...]}).
+ */
+int fhirschema_validate(const char *json, const char *profiles, char **out_result);
+
+/* Same as fhirschema_validate for "r4", "r4b", "r5" or "r6". */
+int fhirschema_validate_version(const char *fhir_version, const char *json,
+                                const char *profiles, char **out_result);
+
+/* Release a string returned by the library. NULL is ignored. */
+void fhirschema_string_free(char *s);
+
+/* Library version; static, do not free. */
+const char *fhirschema_version(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* FHIRSCHEMA_H */
@@ -0,0 +1,326 @@
+//! C ABI for FHIR schema validation.
+//!
+//! Every function takes and returns NUL-terminated UTF-8 JSON strings.
+//! Strings returned through `out_result` are owned by the library and must
+//! be released with [`fhirschema_string_free`]. Validators are built lazily
+//! from the embedded core schemas on first use and are safe to share across
+//! threads. The matching declarations are in `include/fhirschema.h`.
+
+use octofhir_fhirschema::{EmbeddedSchemas, FhirValidator, FhirVersion};
+use serde_json::{Value, json};
+use std::ffi::{CStr, CString, c_char, c_int};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::ptr;
+use std::sync::{Arc, OnceLock};
+
+/// The resource conforms to every requested schema.
+pub const FHIRSCHEMA_VALID: c_int = 0;
+/// The resource has validation errors; `out_result` holds the result.
+pub const FHIRSCHEMA_INVALID: c_int = 1;
+/// The call failed before validation; `out_result` holds `{"error": ...}`.
+pub const FHIRSCHEMA_ERROR: c_int = -1;
+
+static VERSION: &CStr =
+    match CStr::from_bytes_with_nul(concat!(env!("CARGO_PKG_VERSION"), "\0").as_bytes()) {
+        Ok(version) => version,
+        Err(_) => panic!("package version contains a NUL byte"),
+    };
+
+static VALIDATORS: [OnceLock<Result<FhirValidator, String>>; 4] = [
+    OnceLock::new(),
+    OnceLock::new(),
+    OnceLock::new(),
+    OnceLock::new(),
+];
+
+/// Validate a resource against the FHIR R4 core schemas.
+///
+/// `profiles` is a JSON array of schema names or canonical URLs, or NULL to
+/// validate against the schema for the resource's `resourceType`. On return
+/// `*out_result` points to the JSON validation result (or an error object)
+/// and must be freed with [`fhirschema_string_free`].
+///
+/// Returns [`FHIRSCHEMA_VALID`], [`FHIRSCHEMA_INVALID`] or [`FHIRSCHEMA_ERROR`].
+///
+/// # Safety
+///
+/// `json` must be a valid NUL-terminated string, `profiles` NULL or a valid
+/// NUL-terminated string, and `out_result` a valid pointer to writable
+/// storage for one pointer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fhirschema_validate(
+    json: *const c_char,
+    profiles: *const c_char,
+    out_result: *mut *mut c_char,
+) -> c_int {
+    // SAFETY: forwarded from the caller's contract
+    unsafe { fhirschema_validate_version(c"r4".as_ptr(), json, profiles, out_result) }
+}
+
+/// Like [`fhirschema_validate`], for the core schemas of `fhir_version`
+/// (`"r4"`, `"r4b"`, `"r5"` or `"r6"`).
+///
+/// # Safety
+///
+/// Same as [`fhirschema_validate`]; `fhir_version` must be a valid
+/// NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fhirschema_validate_version(
+    fhir_version: *const c_char,
+    json: *const c_char,
+    profiles: *const c_char,
+    out_result: *mut *mut c_char,
+) -> c_int {
+    if out_result.is_null() {
+        return FHIRSCHEMA_ERROR;
+    }
+
+    // SAFETY: the caller guarantees the pointers are NULL or valid C strings
+    let outcome = catch_unwind(AssertUnwindSafe(|| unsafe {
+        validate(
+            read_str(fhir_version, "fhir_version")?,
+            read_str(json, "json")?,
+            (!profiles.is_null())
+                .then(|| read_str(profiles, "profiles"))
+                .transpose()?,
+        )
+    }))
+    .unwrap_or_else(|_| Err("validator panicked".to_string()));
+
+    let (code, result) = match outcome {
+        Ok((valid, result)) => (
+            if valid {
+                FHIRSCHEMA_VALID
+            } else {
+                FHIRSCHEMA_INVALID
+            },
+            result,
+        ),
+        Err(message) => (FHIRSCHEMA_ERROR, json!({ "error": message })),
+    };
+    // SAFETY: checked non-null above
+    unsafe { *out_result = into_c_string(result.to_string()) };
+    code
+}
+
+/// Release a string returned by this library. NULL is ignored.
+///
+/// # Safety
+///
+/// `s` must be NULL or a pointer obtained from this library that has not
+/// been freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fhirschema_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        // SAFETY: the pointer came from CString::into_raw in this library
+        drop(unsafe { CString::from_raw(s) });
+    }
+}
+
+/// Version of this library as a static string; do not free it.
+#[unsafe(no_mangle)]
+pub extern "C" fn fhirschema_version() -> *const c_char {
+    VERSION.as_ptr()
+}
+
+fn validate(
+    fhir_version: &str,
+    json: &str,
+    profiles: Option<&str>,
+) -> Result<(bool, Value), String> {
+    let version = FhirVersion::parse(fhir_version)
+        .ok_or_else(|| format!("unknown FHIR version {fhir_version}"))?;
+    let resource: Value =
+        serde_json::from_str(json).map_err(|e| format!("invalid resource JSON: {e}"))?;
+    let schema_names = match profiles {
+        Some(profiles) => serde_json::from_str::<Vec<String>>(profiles)
+            .map_err(|e| format!("profiles must be a JSON array of strings: {e}"))?,
+        None => vec![
+            resource
+                .get("resourceType")
+                .and_then(Value::as_str)
+                .ok_or("resource has no resourceType")?
+                .to_string(),
+        ],
+    };
+
+    let validator = validator(version)?;
+    let result = futures::executor::block_on(validator.validate(&resource, schema_names));
+    let valid = result.valid;
+    let result = serde_json::to_value(&result).map_err(|e| e.to_string())?;
+    Ok((valid, result))
+}
+
+fn validator(version: FhirVersion) -> Result<&'static FhirValidator, String> {
+    let slot = match version {
+        FhirVersion::R4 => &VALIDATORS[0],
+        FhirVersion::R4B => &VALIDATORS[1],
+        FhirVersion::R5 => &VALIDATORS[2],
+        FhirVersion::R6 => &VALIDATORS[3],
+    };
+    slot.get_or_init(|| {
+        EmbeddedSchemas::new(version)
+            .map(|schemas| FhirValidator::new(Arc::new(schemas)))
+            .map_err(|e| e.to_string())
+    })
+    .as_ref()
+    .map_err(Clone::clone)
+}
+
+/// # Safety
+///
+/// `s` must be NULL or a valid NUL-terminated string.
+unsafe fn read_str<'a>(s: *const c_char, name: &str) -> Result<&'a str, String> {
+    if s.is_null() {
+        return Err(format!("{name} is NULL"));
+    }
+    // SAFETY: non-null and NUL-terminated per the caller's contract
+    unsafe { CStr::from_ptr(s) }
+        .to_str()
+        .map_err(|_| format!("{name} is not valid UTF-8"))
+}
+
+fn into_c_string(s: String) -> *mut c_char {
+    // Serialized JSON escapes control characters, so it has no interior NUL
+    CString::new(s).map_or(ptr::null_mut(), CString::into_raw)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    unsafe fn call(json: &CStr, profiles: Option<&CStr>) -> (c_int, Value) {
+        let mut out = ptr::null_mut();
+        let code = unsafe {
+            fhirschema_validate(
+                json.as_ptr(),
+                profiles.map_or(ptr::null(), CStr::as_ptr),
+                &mut out,
+            )
+        };
+        (code, unsafe { take_result(out) })
+    }
+
+    unsafe fn call_version(version: &CStr, json: &CStr, profiles: Option<&CStr>) -> (c_int, Value) {
+        let mut out = ptr::null_mut();
+        let code = unsafe {
+            fhirschema_validate_version(
+                version.as_ptr(),
+                json.as_ptr(),
+                profiles.map_or(ptr::null(), CStr::as_ptr),
+                &mut out,
+            )
+        };
+        (code, unsafe { take_result(out) })
+    }
+
+    /// Parse and free a string returned through `out_result`.
+    unsafe fn take_result(out: *mut c_char) -> Value {
+        assert!(!out.is_null());
+        let result = serde_json::from_str(unsafe { CStr::from_ptr(out) }.to_str().unwrap());
+        unsafe { fhirschema_string_free(out) };
+        result.unwrap()
+    }
+
+    fn error_message(result: &Value) -> &str {
+        result["error"].as_str().unwrap()
+    }
+
+    #[test]
+    fn test_validate_patient() {
+        let (code, result) =
+            unsafe { call(c"{\"resourceType\": \"Patient\", \"active\": true}", None) };
+        assert_eq!(code, FHIRSCHEMA_VALID);
+        assert_eq!(result["valid"], true);
+
+        let (code, result) = unsafe {
+            call(
+                c"{\"resourceType\": \"Patient\", \"active\": \"yes\"}",
+                Some(c"[\"Patient\"]"),
+            )
+        };
+        assert_eq!(code, FHIRSCHEMA_INVALID);
+        assert_eq!(result["valid"], false);
+    }
+
+    #[test]
+    fn test_invalid_input_reports_error() {
+        let (code, result) = unsafe { call(c"not json", None) };
+        assert_eq!(code, FHIRSCHEMA_ERROR);
+        assert!(
+            result["error"]
+                .as_str()
+                .unwrap()
+                .contains("invalid resource JSON")
+        );
+
+        let mut out = ptr::null_mut();
+        let code = unsafe { fhirschema_validate(ptr::null(), ptr::null(), &mut out) };
+        assert_eq!(code, FHIRSCHEMA_ERROR);
+        unsafe { fhirschema_string_free(out) };
+    }
+
+    #[test]
+    fn test_validate_version() {
+        let (code, result) = unsafe {
+            call_version(
+                c"r4",
+                c"{\"resourceType\": \"Patient\", \"active\": true}",
+                None,
+            )
+        };
+        assert_eq!(code, FHIRSCHEMA_VALID);
+        assert_eq!(result["valid"], true);
+
+        let (code, result) = unsafe {
+            call_version(
+                c"r4",
+                c"{\"resourceType\": \"Patient\", \"active\": \"yes\"}",
+                Some(c"[\"Patient\"]"),
+            )
+        };
+        assert_eq!(code, FHIRSCHEMA_INVALID);
+        assert!(!result["errors"].as_array().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_unknown_version_reports_error() {
+        let (code, result) =
+            unsafe { call_version(c"r9", c"{\"resourceType\": \"Patient\"}", None) };
+        assert_eq!(code, FHIRSCHEMA_ERROR);
+        assert!(error_message(&result).contains("unknown FHIR version r9"));
+    }
+
+    #[test]
+    fn test_bad_profiles_report_error() {
+        let patient = c"{\"resourceType\": \"Patient\"}";
+        for profiles in [c"Patient", c"[1]", c"{\"profile\": \"Patient\"}"] {
+            let (code, result) = unsafe { call(patient, Some(profiles)) };
+            assert_eq!(code, FHIRSCHEMA_ERROR, "{profiles:?}");
+            assert!(error_message(&result).contains("profiles must be a JSON array"));
+        }
+    }
+
+    #[test]
+    fn test_missing_resource_type_reports_error() {
+        let (code, result) = unsafe { call(c"{\"active\": true}", None) };
+        assert_eq!(code, FHIRSCHEMA_ERROR);
+        assert_eq!(error_message(&result), "resource has no resourceType");
+    }
+
+    #[test]
+    fn test_null_arguments_report_error() {
+        let mut out = ptr::null_mut();
+        let code = unsafe {
+            fhirschema_validate_version(ptr::null(), c"{}".as_ptr(), ptr::null(), &mut out)
+        };
+        assert_eq!(code, FHIRSCHEMA_ERROR);
+        assert_eq!(
+            error_message(&unsafe { take_result(out) }),
+            "fhir_version is NULL"
+        );
+
+        let code = unsafe { fhirschema_validate(c"{}".as_ptr(), ptr::null(), ptr::null_mut()) };
+        assert_eq!(code, FHIRSCHEMA_ERROR);
+    }
+}
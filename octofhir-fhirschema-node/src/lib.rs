@@ -0,0 +1,375 @@
+//! Node.js native bindings for FHIR schema conversion and validation.
+//!
+//! Built with napi-rs as an optional alternative to the WASM package for
+//! services that validate at high volume. Inputs are passed as `Buffer`s
+//! holding JSON, which are parsed straight from the memory Node owns
+//! without a copy into a JS string. Validation and conversion run on the
+//! libuv thread pool and resolve a `Promise`, so the event loop stays free
+//! while constraint-heavy profiles are checked.
+//!
+//! ```js
+//! const { validate, convert, registerSchema } = require("@octofhir/fhirschema");
+//!
+//! const result = await validate(Buffer.from(JSON.stringify(patient)));
+//! const schema = await convert(fs.readFileSync("StructureDefinition-my-patient.json"));
+//! await registerSchema("r4", fs.readFileSync("StructureDefinition-my-patient.json"));
+//! ```
+
+use async_trait::async_trait;
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use octofhir_fhirschema::{
+    EmbeddedSchemas, FhirSchema, FhirValidator, FhirVersion, SchemaProvider, StructureDefinition,
+    translate,
+};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+
+/// Core schemas of one FHIR version plus the profiles registered for it.
+///
+/// The core schemas are the embedded ones, decoded on demand and shared by
+/// every validator, so registering a profile only copies the (small) profile map.
+struct RegistryProvider {
+    core: EmbeddedSchemas,
+    profiles: HashMap<String, Arc<FhirSchema>>,
+}
+
+#[async_trait]
+impl SchemaProvider for RegistryProvider {
+    async fn get_schema(&self, name: &str) -> Option<Arc<FhirSchema>> {
+        match self.profiles.get(name) {
+            Some(schema) => Some(Arc::clone(schema)),
+            None => self.core.get_schema(name).await,
+        }
+    }
+
+    async fn get_schema_by_url(&self, url: &str) -> Option<Arc<FhirSchema>> {
+        match self.profiles.get(url) {
+            Some(schema) => Some(Arc::clone(schema)),
+            None => self.core.get_schema_by_url(url).await,
+        }
+    }
+}
+
+/// Per-version validators, replaced when profiles are registered.
+#[derive(Clone)]
+struct VersionState {
+    provider: Arc<RegistryProvider>,
+    validator: Arc<FhirValidator>,
+}
+
+static STATE: RwLock<Vec<(FhirVersion, VersionState)>> = RwLock::new(Vec::new());
+
+/// Serializes registrations, so two profiles registered at once both end up
+/// in the final validator. Validation never takes it.
+static REGISTRATION: Mutex<()> = Mutex::new(());
+
+/// Validates `resource` (a JSON buffer) against `profiles`, or against the
+/// schema for its `resourceType` when no profiles are given.
+pub struct ValidateTask {
+    version: FhirVersion,
+    resource: Buffer,
+    profiles: Option<Vec<String>>,
+}
+
+impl Task for ValidateTask {
+    type Output = Value;
+    type JsValue = Value;
+
+    fn compute(&mut self) -> Result<Value> {
+        let resource: Value = serde_json::from_slice(&self.resource)
+            .map_err(|e| Error::from_reason(format!("invalid resource JSON: {e}")))?;
+        let schema_names = match self.profiles.take() {
+            Some(profiles) if !profiles.is_empty() => profiles,
+            _ => vec![
+                resource
+                    .get("resourceType")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| Error::from_reason("resource has no resourceType"))?
+                    .to_string(),
+            ],
+        };
+
+        let validator = version_state(self.version)?.validator;
+        let result = futures::executor::block_on(validator.validate(&resource, schema_names));
+        serde_json::to_value(result).map_err(|e| Error::from_reason(e.to_string()))
+    }
+
+    fn resolve(&mut self, _env: Env, output: Value) -> Result<Value> {
+        Ok(output)
+    }
+}
+
+/// Converts a StructureDefinition (a JSON buffer) to a FHIR Schema.
+pub struct ConvertTask {
+    structure_definition: Buffer,
+}
+
+impl Task for ConvertTask {
+    type Output = Value;
+    type JsValue = Value;
+
+    fn compute(&mut self) -> Result<Value> {
+        let schema = convert_buffer(&self.structure_definition)?;
+        serde_json::to_value(schema).map_err(|e| Error::from_reason(e.to_string()))
+    }
+
+    fn resolve(&mut self, _env: Env, output: Value) -> Result<Value> {
+        Ok(output)
+    }
+}
+
+/// Converts a StructureDefinition and adds it to the schemas used by
+/// [`validate`] for its FHIR version.
+pub struct RegisterSchemaTask {
+    version: FhirVersion,
+    structure_definition: Buffer,
+}
+
+impl Task for RegisterSchemaTask {
+    type Output = String;
+    type JsValue = String;
+
+    fn compute(&mut self) -> Result<String> {
+        let schema = Arc::new(convert_buffer(&self.structure_definition)?);
+        let url = schema.url.clone();
+        let _registering = REGISTRATION.lock().map_err(|_| poisoned())?;
+
+        // Build the new validator without holding STATE, so validations
+        // keep running against the current one meanwhile
+        let current = version_state(self.version)?;
+        let mut profiles = current.provider.profiles.clone();
+        profiles.insert(schema.url.clone(), Arc::clone(&schema));
+        profiles.insert(schema.name.clone(), schema);
+        let provider = Arc::new(RegistryProvider {
+            core: current.provider.core,
+            profiles,
+        });
+        let validator = Arc::new(FhirValidator::new(provider.clone()));
+
+        let mut state = STATE.write().map_err(|_| poisoned())?;
+        let entry = state
+            .iter_mut()
+            .find(|(v, _)| *v == self.version)
+            .expect("version_state registered the version");
+        entry.1 = VersionState {
+            provider,
+            validator,
+        };
+        Ok(url)
+    }
+
+    fn resolve(&mut self, _env: Env, output: String) -> Result<String> {
+        Ok(output)
+    }
+}
+
+/// Validate a FHIR resource. Resolves to the validation result
+/// (`{ valid, errors, warnings }`).
+#[napi(ts_return_type = "Promise<{ valid: boolean; errors: any[]; warnings: any[] }>")]
+pub fn validate(
+    resource: Buffer,
+    profiles: Option<Vec<String>>,
+    fhir_version: Option<String>,
+) -> Result<AsyncTask<ValidateTask>> {
+    Ok(AsyncTask::new(ValidateTask {
+        version: parse_version(fhir_version)?,
+        resource,
+        profiles,
+    }))
+}
+
+/// Convert a StructureDefinition to a FHIR Schema.
+#[napi(ts_return_type = "Promise<any>")]
+pub fn convert(structure_definition: Buffer) -> AsyncTask<ConvertTask> {
+    AsyncTask::new(ConvertTask {
+        structure_definition,
+    })
+}
+
+/// Convert a StructureDefinition and make it available as a profile to
+/// `validate` for the given FHIR version. Resolves to the profile URL.
+#[napi(ts_return_type = "Promise<string>")]
+pub fn register_schema(
+    fhir_version: Option<String>,
+    structure_definition: Buffer,
+) -> Result<AsyncTask<RegisterSchemaTask>> {
+    Ok(AsyncTask::new(RegisterSchemaTask {
+        version: parse_version(fhir_version)?,
+        structure_definition,
+    }))
+}
+
+fn parse_version(fhir_version: Option<String>) -> Result<FhirVersion> {
+    match fhir_version.as_deref() {
+        None => Ok(FhirVersion::R4),
+        Some(version) => FhirVersion::parse(version)
+            .ok_or_else(|| Error::from_reason(format!("unknown FHIR version {version}"))),
+    }
+}
+
+fn convert_buffer(bytes: &[u8]) -> Result<FhirSchema> {
+    let structure_definition: StructureDefinition = serde_json::from_slice(bytes)
+        .map_err(|e| Error::from_reason(format!("invalid StructureDefinition JSON: {e}")))?;
+    translate(structure_definition, None).map_err(|e| Error::from_reason(e.to_string()))
+}
+
+/// The state of `version`, building it from the embedded core schemas on
+/// first use.
+fn version_state(version: FhirVersion) -> Result<VersionState> {
+    if let Some(entry) = find_state(&STATE.read().map_err(|_| poisoned())?, version) {
+        return Ok(entry);
+    }
+
+    // Build the state outside the lock; if another call initialized the
+    // version meanwhile, its state wins and this one is dropped
+    let core = EmbeddedSchemas::new(version).map_err(|e| Error::from_reason(e.to_string()))?;
+    let provider = Arc::new(RegistryProvider {
+        core,
+        profiles: HashMap::new(),
+    });
+    let validator = Arc::new(FhirValidator::new(provider.clone()));
+
+    let mut state = STATE.write().map_err(|_| poisoned())?;
+    if let Some(entry) = find_state(&state, version) {
+        return Ok(entry);
+    }
+    let entry = VersionState {
+        provider,
+        validator,
+    };
+    state.push((version, entry.clone()));
+    Ok(entry)
+}
+
+fn find_state(state: &[(FhirVersion, VersionState)], version: FhirVersion) -> Option<VersionState> {
+    state
+        .iter()
+        .find(|(v, _)| *v == version)
+        .map(|(_, entry)| entry.clone())
+}
+
+fn poisoned() -> Error {
+    Error::from_reason("schema registry is poisoned")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn buffer(value: &Value) -> Buffer {
+        Buffer::from(serde_json::to_vec(value).unwrap())
+    }
+
+    /// A Patient profile named `name` that requires `birthDate`.
+    fn birth_date_profile(name: &str) -> Value {
+        json!({
+            "resourceType": "StructureDefinition",
+            "url": format!("http://example.org/StructureDefinition/{name}"),
+            "name": name,
+            "status": "active",
+            "kind": "resource",
+            "abstract": false,
+            "type": "Patient",
+            "baseDefinition": "http://hl7.org/fhir/StructureDefinition/Patient",
+            "derivation": "constraint",
+            "differential": {
+                "element": [
+                    { "id": "Patient", "path": "Patient" },
+                    { "id": "Patient.birthDate", "path": "Patient.birthDate", "min": 1 }
+                ]
+            }
+        })
+    }
+
+    fn validate_task(resource: &Value, profiles: Option<Vec<String>>) -> ValidateTask {
+        ValidateTask {
+            version: FhirVersion::R4,
+            resource: buffer(resource),
+            profiles,
+        }
+    }
+
+    #[test]
+    fn test_validate_uses_resource_type_without_profiles() {
+        let valid = validate_task(&json!({"resourceType": "Patient", "active": true}), None)
+            .compute()
+            .unwrap();
+        assert_eq!(valid["valid"], json!(true));
+
+        let invalid = validate_task(&json!({"resourceType": "Patient", "active": "yes"}), None)
+            .compute()
+            .unwrap();
+        assert_eq!(invalid["valid"], json!(false));
+    }
+
+    #[test]
+    fn test_validate_rejects_unusable_input() {
+        let mut task = ValidateTask {
+            version: FhirVersion::R4,
+            resource: Buffer::from(b"{not json".to_vec()),
+            profiles: None,
+        };
+        assert!(task.compute().is_err());
+        assert!(
+            validate_task(&json!({"active": true}), None)
+                .compute()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_convert_translates_structure_definition() {
+        let mut task = ConvertTask {
+            structure_definition: buffer(&birth_date_profile("ConvertedPatient")),
+        };
+        let schema = task.compute().unwrap();
+        assert_eq!(
+            schema["url"],
+            json!("http://example.org/StructureDefinition/ConvertedPatient")
+        );
+
+        let mut invalid = ConvertTask {
+            structure_definition: Buffer::from(b"[]".to_vec()),
+        };
+        assert!(invalid.compute().is_err());
+    }
+
+    #[test]
+    fn test_registered_schema_is_available_to_validate() {
+        let url = RegisterSchemaTask {
+            version: FhirVersion::R4,
+            structure_definition: buffer(&birth_date_profile("RegisteredPatient")),
+        }
+        .compute()
+        .unwrap();
+        assert_eq!(
+            url,
+            "http://example.org/StructureDefinition/RegisteredPatient"
+        );
+
+        for profile in [url, "RegisteredPatient".to_string()] {
+            let profiles = Some(vec![profile]);
+            let missing = validate_task(&json!({"resourceType": "Patient"}), profiles.clone())
+                .compute()
+                .unwrap();
+            assert_eq!(missing["valid"], json!(false), "{missing}");
+
+            let present = validate_task(
+                &json!({"resourceType": "Patient", "birthDate": "1970-01-01"}),
+                profiles,
+            )
+            .compute()
+            .unwrap();
+            assert_eq!(present["valid"], json!(true), "{present}");
+        }
+    }
+
+    #[test]
+    fn test_unknown_version_is_rejected() {
+        assert!(parse_version(Some("r9".to_string())).is_err());
+        assert_eq!(parse_version(None).unwrap(), FhirVersion::R4);
+    }
+}
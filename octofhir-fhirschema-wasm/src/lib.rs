@@ -0,0 +1,99 @@
+//! WebAssembly bindings for structural FHIR validation.
+//!
+//! Exposes the validator core over `wasm-bindgen` so browsers and Node can
+//! validate resources against the embedded core schemas without a server.
+//! Only structural checks run: there is no FHIRPath evaluator and no
+//! terminology server, so invariants and non-core bindings are skipped.
+//!
+//! Build with `wasm-pack build octofhir-fhirschema-wasm --target web` (or
+//! `--target nodejs`). Each result is the JSON form of
+//! [`ValidationResult`](octofhir_fhirschema::ValidationResult).
+//!
+//! ```js
+//! import init, { validate } from "octofhir-fhirschema-wasm";
+//!
+//! await init();
+//! const result = JSON.parse(validate(JSON.stringify(patient), null));
+//! ```
+
+use octofhir_fhirschema::{EmbeddedSchemas, FhirValidator, FhirVersion};
+use serde_json::Value;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::Arc;
+use wasm_bindgen::prelude::*;
+
+thread_local! {
+    // wasm32 is single-threaded, so one validator per version is enough
+    static VALIDATORS: RefCell<Vec<(FhirVersion, Rc<FhirValidator>)>> =
+        const { RefCell::new(Vec::new()) };
+}
+
+/// Validate a resource against the FHIR R4 core schemas.
+///
+/// `profile` is a profile name or canonical URL to validate against instead
+/// of the schema for the resource's `resourceType`. Returns the validation
+/// result as a JSON string.
+#[wasm_bindgen]
+pub fn validate(resource_json: &str, profile: Option<String>) -> Result<String, JsError> {
+    validate_version("r4", resource_json, profile)
+}
+
+/// Validate a resource against the core schemas of a FHIR version
+/// (`r4`, `r4b`, `r5` or `r6`). The version must have been compiled in
+/// through the crate feature of the same name.
+#[wasm_bindgen(js_name = validateVersion)]
+pub fn validate_version(
+    fhir_version: &str,
+    resource_json: &str,
+    profile: Option<String>,
+) -> Result<String, JsError> {
+    let version = FhirVersion::parse(fhir_version)
+        .ok_or_else(|| JsError::new(&format!("unknown FHIR version {fhir_version}")))?;
+    let resource: Value = serde_json::from_str(resource_json)?;
+    let schema_name = match profile {
+        Some(profile) => profile,
+        None => resource
+            .get("resourceType")
+            .and_then(Value::as_str)
+            .ok_or_else(|| JsError::new("resource has no resourceType"))?
+            .to_string(),
+    };
+
+    let validator = validator(version)?;
+    let result = futures::executor::block_on(validator.validate(&resource, vec![schema_name]));
+    Ok(serde_json::to_string(&result)?)
+}
+
+/// FHIR versions whose core schemas are embedded in this build.
+#[wasm_bindgen(js_name = embeddedVersions)]
+pub fn embedded_versions() -> Vec<String> {
+    [
+        FhirVersion::R4,
+        FhirVersion::R4B,
+        FhirVersion::R5,
+        FhirVersion::R6,
+    ]
+    .into_iter()
+    .filter(|version| octofhir_fhirschema::is_embedded(*version))
+    .map(|version| version.as_str().to_string())
+    .collect()
+}
+
+fn validator(version: FhirVersion) -> Result<Rc<FhirValidator>, JsError> {
+    let cached = VALIDATORS.with(|cache| {
+        cache
+            .borrow()
+            .iter()
+            .find(|(v, _)| *v == version)
+            .map(|(_, validator)| Rc::clone(validator))
+    });
+    if let Some(validator) = cached {
+        return Ok(validator);
+    }
+
+    let schemas = EmbeddedSchemas::new(version).map_err(|e| JsError::new(&e.to_string()))?;
+    let validator = Rc::new(FhirValidator::new(Arc::new(schemas)));
+    VALIDATORS.with(|cache| cache.borrow_mut().push((version, Rc::clone(&validator))));
+    Ok(validator)
+}
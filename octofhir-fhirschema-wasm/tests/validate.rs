@@ -0,0 +1,46 @@
+//! Bindings tests, run in Node with `wasm-pack test --node`.
+#![cfg(target_arch = "wasm32")]
+
+use octofhir_fhirschema_wasm::{embedded_versions, validate, validate_version};
+use serde_json::{Value, json};
+use wasm_bindgen_test::*;
+
+fn validate_json(resource: Value, profile: Option<&str>) -> Value {
+    let result = validate(&resource.to_string(), profile.map(str::to_string))
+        .unwrap_or_else(|_| panic!("validate failed for {resource}"));
+    serde_json::from_str(&result).unwrap()
+}
+
+#[wasm_bindgen_test]
+fn test_validate_valid_patient() {
+    let result = validate_json(json!({"resourceType": "Patient", "active": true}), None);
+    assert_eq!(result["valid"], json!(true), "{result}");
+}
+
+#[wasm_bindgen_test]
+fn test_validate_reports_errors() {
+    let result = validate_json(json!({"resourceType": "Patient", "active": "yes"}), None);
+    assert_eq!(result["valid"], json!(false));
+    assert!(!result["errors"].as_array().unwrap().is_empty());
+}
+
+#[wasm_bindgen_test]
+fn test_validate_against_named_profile() {
+    let result = validate_json(
+        json!({"resourceType": "Patient", "active": "yes"}),
+        Some("Patient"),
+    );
+    assert_eq!(result["valid"], json!(false));
+}
+
+#[wasm_bindgen_test]
+fn test_validate_rejects_unusable_input() {
+    assert!(validate("{not json", None).is_err());
+    assert!(validate(r#"{"active": true}"#, None).is_err());
+    assert!(validate_version("r9", r#"{"resourceType": "Patient"}"#, None).is_err());
+}
+
+#[wasm_bindgen_test]
+fn test_r4_is_embedded_by_default() {
+    assert!(embedded_versions().contains(&"r4".to_string()));
+}
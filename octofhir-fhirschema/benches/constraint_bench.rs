@@ -0,0 +1,124 @@
+//! FHIRPath constraint evaluation benchmarks
+//!
+//! Each resource is validated structurally only and then with the FHIRPath
+//! engine, so the difference is the cost of evaluating invariants.
+//!
+//! Run:
+//!   cargo bench --bench constraint_bench --features bench-util
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use octofhir_fhirpath::FhirPathEngine;
+use octofhir_fhirschema::{
+    DynamicSchemaProvider, FhirValidator, FhirVersion, ModelFhirVersion, get_schemas, synthetic,
+    translate,
+};
+use serde_json::Value as JsonValue;
+use std::hint::black_box;
+use std::sync::Arc;
+use tokio::runtime::Runtime;
+
+fn create_runtime() -> Runtime {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+}
+
+/// Structural-only and FHIRPath-enabled validators over the R4 core schemas
+/// plus the synthetic Patient profile.
+fn validators(rt: &Runtime, profile_slices: usize) -> (FhirValidator, FhirValidator, String) {
+    let mut schemas = get_schemas(FhirVersion::R4)
+        .expect("R4 schemas embedded")
+        .clone();
+    let profile = translate(synthetic::patient_profile(profile_slices), None)
+        .expect("synthetic profile converts");
+    let profile_url = profile.url.clone();
+    schemas.insert(profile_url.clone(), profile);
+
+    let structural = FhirValidator::from_schemas(schemas.clone(), None);
+    let engine = rt.block_on(async {
+        let model_provider = Arc::new(DynamicSchemaProvider::new(
+            schemas.clone(),
+            ModelFhirVersion::R4,
+        ));
+        let registry = Arc::new(octofhir_fhirpath::create_function_registry());
+        FhirPathEngine::new(registry, model_provider)
+            .await
+            .expect("FHIRPath engine")
+    });
+    let with_fhirpath = FhirValidator::from_schemas(schemas, Some(Arc::new(engine)));
+    (structural, with_fhirpath, profile_url)
+}
+
+fn bench_case(
+    c: &mut Criterion,
+    rt: &Runtime,
+    group_name: &str,
+    validators: &(FhirValidator, FhirValidator, String),
+    cases: &[(String, JsonValue, Vec<String>)],
+) {
+    let (structural, with_fhirpath, _) = validators;
+    let mut group = c.benchmark_group(group_name);
+    for (name, resource, schema_names) in cases {
+        for (mode, validator) in [("structural", structural), ("fhirpath", with_fhirpath)] {
+            group.bench_with_input(BenchmarkId::new(mode, name), resource, |b, resource| {
+                b.iter(|| {
+                    rt.block_on(validator.validate(black_box(resource), schema_names.clone()))
+                });
+            });
+        }
+    }
+    group.finish();
+}
+
+/// Benchmark: core invariants (ele-1, dom-*, pat-1, obs-*) on growing resources
+fn bench_core_constraints(c: &mut Criterion) {
+    let rt = create_runtime();
+    let validators = validators(&rt, 5);
+
+    let mut cases = Vec::new();
+    for size in [1, 10, 50] {
+        cases.push((
+            format!("patient_{size}"),
+            synthetic::patient(size),
+            vec!["Patient".to_string()],
+        ));
+    }
+    for components in [1, 20] {
+        cases.push((
+            format!("observation_{components}"),
+            synthetic::observation(components),
+            vec!["Observation".to_string()],
+        ));
+    }
+    cases.push((
+        "bundle_20".to_string(),
+        synthetic::bundle(20),
+        vec!["Bundle".to_string()],
+    ));
+
+    bench_case(c, &rt, "core_constraints", &validators, &cases);
+}
+
+/// Benchmark: a sliced profile with its own invariant on top of the base type
+fn bench_profile_constraints(c: &mut Criterion) {
+    let rt = create_runtime();
+    let validators = validators(&rt, 20);
+    let schema_names = vec!["Patient".to_string(), validators.2.clone()];
+
+    let cases: Vec<_> = [1, 10, 50]
+        .into_iter()
+        .map(|size| {
+            (
+                format!("patient_{size}"),
+                synthetic::patient(size),
+                schema_names.clone(),
+            )
+        })
+        .collect();
+
+    bench_case(c, &rt, "profile_constraints", &validators, &cases);
+}
+
+criterion_group!(benches, bench_core_constraints, bench_profile_constraints);
+criterion_main!(benches);
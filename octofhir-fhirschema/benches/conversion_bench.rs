@@ -0,0 +1,51 @@
+//! StructureDefinition to FHIR Schema conversion benchmarks
+//!
+//! Run:
+//!   cargo bench --bench conversion_bench --features bench-util
+
+use criterion::{BatchSize, BenchmarkId, Criterion, criterion_group, criterion_main};
+use octofhir_fhirschema::{synthetic, translate};
+use std::hint::black_box;
+
+/// Benchmark: constraint profiles with a growing number of slices
+fn bench_convert_profile(c: &mut Criterion) {
+    let mut group = c.benchmark_group("convert_profile");
+
+    for slices in [1, 10, 50] {
+        let profile = synthetic::patient_profile(slices);
+        group.bench_with_input(
+            BenchmarkId::new("patient", slices),
+            &profile,
+            |b, profile| {
+                b.iter_batched(
+                    || profile.clone(),
+                    |profile| translate(black_box(profile), None).unwrap(),
+                    BatchSize::SmallInput,
+                );
+            },
+        );
+    }
+
+    group.finish();
+}
+
+/// Benchmark: logical models with a growing number of elements
+fn bench_convert_logical_model(c: &mut Criterion) {
+    let mut group = c.benchmark_group("convert_logical_model");
+
+    for elements in [10, 100, 1000] {
+        let model = synthetic::logical_model(elements);
+        group.bench_with_input(BenchmarkId::new("model", elements), &model, |b, model| {
+            b.iter_batched(
+                || model.clone(),
+                |model| translate(black_box(model), None).unwrap(),
+                BatchSize::SmallInput,
+            );
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_convert_profile, bench_convert_logical_model);
+criterion_main!(benches);
@@ -0,0 +1,91 @@
+//! Model provider navigation benchmarks
+//!
+//! Measures the type lookups FHIRPath evaluation performs against the
+//! embedded R4 schemas.
+//!
+//! Run:
+//!   cargo bench --bench provider_bench --features bench-util
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use octofhir_fhirschema::{EmbeddedSchemaProvider, ModelProvider, synthetic};
+use std::hint::black_box;
+use tokio::runtime::Runtime;
+
+fn create_runtime() -> Runtime {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+}
+
+/// Benchmark: type lookup and element navigation
+fn bench_navigation(c: &mut Criterion) {
+    let rt = create_runtime();
+    let provider = EmbeddedSchemaProvider::r4();
+    let mut group = c.benchmark_group("navigation");
+
+    group.bench_function("get_type_patient", |b| {
+        b.iter(|| {
+            rt.block_on(provider.get_type(black_box("Patient")))
+                .unwrap()
+        });
+    });
+
+    group.bench_function("patient_contact_name_given", |b| {
+        b.iter(|| {
+            rt.block_on(async {
+                let mut current = provider.get_type("Patient").await.unwrap().unwrap();
+                for element in ["contact", "name", "given"] {
+                    current = provider
+                        .get_element_type(&current, black_box(element))
+                        .await
+                        .unwrap()
+                        .unwrap();
+                }
+                current
+            })
+        });
+    });
+
+    group.bench_function("get_elements_observation", |b| {
+        b.iter(|| {
+            rt.block_on(provider.get_elements(black_box("Observation")))
+                .unwrap()
+        });
+    });
+
+    group.bench_function("get_resource_types", |b| {
+        b.iter(|| rt.block_on(provider.get_resource_types()).unwrap());
+    });
+
+    group.finish();
+}
+
+/// Benchmark: resolving the type of every element path in a large resource,
+/// as a FHIRPath walk over it would
+fn bench_resource_walk(c: &mut Criterion) {
+    let rt = create_runtime();
+    let provider = EmbeddedSchemaProvider::r4();
+    let patient = synthetic::patient(10);
+    let keys: Vec<String> = patient
+        .as_object()
+        .unwrap()
+        .keys()
+        .filter(|key| *key != "resourceType")
+        .cloned()
+        .collect();
+
+    c.bench_function("walk_patient_elements", |b| {
+        b.iter(|| {
+            rt.block_on(async {
+                let patient_type = provider.get_type("Patient").await.unwrap().unwrap();
+                for key in &keys {
+                    black_box(provider.get_element_type(&patient_type, key).await.unwrap());
+                }
+            })
+        });
+    });
+}
+
+criterion_group!(benches, bench_navigation, bench_resource_walk);
+criterion_main!(benches);
@@ -2,12 +2,13 @@
 //!
 //! Run:
 //!   cargo bench --bench validation_bench
+//!   cargo bench --bench validation_bench --features simd-json -- parse
 //!
 //! Profiling with flamegraph:
 //!   cargo flamegraph --bench validation_bench -- --bench validate_bundle
 
 use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
-use octofhir_fhirschema::{FhirValidator, FhirVersion, get_schemas};
+use octofhir_fhirschema::{FhirValidator, FhirVersion, get_schemas, parse_resource};
 use serde_json::{Value as JsonValue, json};
 use std::hint::black_box;
 use tokio::runtime::Runtime;
@@ -170,9 +171,81 @@ fn bundle_with_resources(count: usize) -> JsonValue {
     })
 }
 
+/// Observation with nested components, codings and ranges, so the walk
+/// descends several levels and records a path for every element
+fn observation_deep(i: usize) -> JsonValue {
+    let components: Vec<JsonValue> = (0..5)
+        .map(|c| {
+            json!({
+                "code": {
+                    "coding": [
+                        {"system": "http://loinc.org", "code": format!("8480-{c}"), "display": "Component"},
+                        {"system": "http://snomed.info/sct", "code": format!("27113001{c}")}
+                    ],
+                    "text": "Component"
+                },
+                "valueQuantity": {
+                    "value": 100 + c,
+                    "unit": "mmHg",
+                    "system": "http://unitsofmeasure.org",
+                    "code": "mm[Hg]"
+                },
+                "interpretation": [{
+                    "coding": [{
+                        "system": "http://terminology.hl7.org/CodeSystem/v3-ObservationInterpretation",
+                        "code": "N"
+                    }]
+                }],
+                "referenceRange": [{
+                    "low": {"value": 90, "unit": "mmHg", "system": "http://unitsofmeasure.org", "code": "mm[Hg]"},
+                    "high": {"value": 140, "unit": "mmHg", "system": "http://unitsofmeasure.org", "code": "mm[Hg]"}
+                }]
+            })
+        })
+        .collect();
+
+    json!({
+        "resourceType": "Observation",
+        "id": format!("obs-{i}"),
+        "status": "final",
+        "category": [{
+            "coding": [{
+                "system": "http://terminology.hl7.org/CodeSystem/observation-category",
+                "code": "vital-signs"
+            }]
+        }],
+        "code": {
+            "coding": [{"system": "http://loinc.org", "code": "85354-9", "display": "Blood pressure panel"}]
+        },
+        "subject": {"reference": format!("Patient/patient-{i}")},
+        "effectiveDateTime": "2024-01-01T00:00:00Z",
+        "performer": [{"reference": "Practitioner/example", "display": "Dr. Example"}],
+        "component": components
+    })
+}
+
+/// Bundle of N deep Observations
+fn bundle_of_observations(count: usize) -> JsonValue {
+    let entries: Vec<JsonValue> = (0..count)
+        .map(|i| {
+            json!({
+                "fullUrl": format!("urn:uuid:obs-{i}"),
+                "resource": observation_deep(i)
+            })
+        })
+        .collect();
+
+    json!({
+        "resourceType": "Bundle",
+        "id": "deep-bundle",
+        "type": "collection",
+        "entry": entries
+    })
+}
+
 /// Benchmark: schema lookup (isolated)
 fn bench_schema_lookup(c: &mut Criterion) {
-    let schemas = get_schemas(FhirVersion::R4);
+    let schemas = get_schemas(FhirVersion::R4).expect("R4 schemas embedded");
 
     c.bench_function("schema_lookup_by_name", |b| {
         b.iter(|| {
@@ -190,7 +263,9 @@ fn bench_schema_lookup(c: &mut Criterion) {
 /// Benchmark: Patient validation
 fn bench_validate_patient(c: &mut Criterion) {
     let rt = create_runtime();
-    let schemas = get_schemas(FhirVersion::R4).clone();
+    let schemas = get_schemas(FhirVersion::R4)
+        .expect("R4 schemas embedded")
+        .clone();
     let validator = FhirValidator::from_schemas(schemas, None);
 
     let patient_min = patient_minimal();
@@ -235,7 +310,9 @@ fn bench_validate_patient(c: &mut Criterion) {
 /// Benchmark: Observation validation
 fn bench_validate_observation(c: &mut Criterion) {
     let rt = create_runtime();
-    let schemas = get_schemas(FhirVersion::R4).clone();
+    let schemas = get_schemas(FhirVersion::R4)
+        .expect("R4 schemas embedded")
+        .clone();
     let validator = FhirValidator::from_schemas(schemas, None);
 
     let observation = observation_simple();
@@ -254,7 +331,9 @@ fn bench_validate_observation(c: &mut Criterion) {
 /// Benchmark: Bundle validation with varying sizes
 fn bench_validate_bundle(c: &mut Criterion) {
     let rt = create_runtime();
-    let schemas = get_schemas(FhirVersion::R4).clone();
+    let schemas = get_schemas(FhirVersion::R4)
+        .expect("R4 schemas embedded")
+        .clone();
     let validator = FhirValidator::from_schemas(schemas, None);
 
     let mut group = c.benchmark_group("validate_bundle");
@@ -277,10 +356,47 @@ fn bench_validate_bundle(c: &mut Criterion) {
     group.finish();
 }
 
+/// Benchmark: Bundles of deep Observations, where the structural walk's
+/// per-thread scratch arena does most of its allocating. Each Observation
+/// records a path for roughly 170 elements, so the 10-entry bundle keeps
+/// its arena well under `SCRATCH_RETAIN_BYTES` (1 MiB) and reuses it
+/// between iterations, while the 1000-entry one grows it well past the cap
+/// and drops and regrows it every time. For before/after numbers, save a
+/// baseline on the commit to compare against (`-- validate_deep_bundle
+/// --save-baseline before`) and rerun with `--baseline before`.
+fn bench_validate_deep_bundle(c: &mut Criterion) {
+    let rt = create_runtime();
+    let schemas = get_schemas(FhirVersion::R4)
+        .expect("R4 schemas embedded")
+        .clone();
+    let validator = FhirValidator::from_schemas(schemas, None);
+
+    let mut group = c.benchmark_group("validate_deep_bundle");
+
+    for count in [10, 100, 1000] {
+        let bundle = bundle_of_observations(count);
+
+        group.throughput(Throughput::Elements(count as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(count), &bundle, |b, bundle| {
+            b.iter(|| {
+                rt.block_on(async {
+                    validator
+                        .validate(black_box(bundle), vec!["Bundle".to_string()])
+                        .await
+                })
+            });
+        });
+    }
+
+    group.finish();
+}
+
 /// Benchmark: throughput (resources per second)
 fn bench_throughput(c: &mut Criterion) {
     let rt = create_runtime();
-    let schemas = get_schemas(FhirVersion::R4).clone();
+    let schemas = get_schemas(FhirVersion::R4)
+        .expect("R4 schemas embedded")
+        .clone();
     let validator = FhirValidator::from_schemas(schemas, None);
 
     // Подготовим batch разных ресурсов
@@ -318,9 +434,108 @@ fn bench_throughput(c: &mut Criterion) {
     group.finish();
 }
 
+/// Patient that produces many findings: unknown elements and wrong types
+fn patient_with_findings(count: usize) -> JsonValue {
+    let mut patient = patient_simple();
+    let obj = patient.as_object_mut().unwrap();
+    for i in 0..count {
+        obj.insert(format!("unknownElement{i}"), json!(i));
+    }
+    obj.insert(
+        "name".to_string(),
+        JsonValue::Array(
+            (0..count)
+                .map(|_| json!({"family": 42, "given": "not-an-array"}))
+                .collect(),
+        ),
+    );
+    patient
+}
+
+/// Benchmark: resources that generate many errors, dominated by building
+/// and collecting `ValidationError`s. Messages are rendered only when read,
+/// so `patient` never formats them and `patient_rendered` formats every one;
+/// the gap between the two is what deferring the rendering saves callers
+/// that only count or filter findings. Compare runs with
+/// `--save-baseline`/`--baseline`.
+fn bench_validate_findings(c: &mut Criterion) {
+    let rt = create_runtime();
+    let schemas = get_schemas(FhirVersion::R4)
+        .expect("R4 schemas embedded")
+        .clone();
+    let validator = FhirValidator::from_schemas(schemas, None);
+
+    let mut group = c.benchmark_group("validate_findings");
+
+    for count in [10, 100, 500] {
+        let resource = patient_with_findings(count);
+        group.bench_with_input(
+            BenchmarkId::new("patient", count),
+            &resource,
+            |b, resource| {
+                b.iter(|| {
+                    rt.block_on(async {
+                        validator
+                            .validate(black_box(resource), vec!["Patient".to_string()])
+                            .await
+                    })
+                });
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("patient_rendered", count),
+            &resource,
+            |b, resource| {
+                b.iter(|| {
+                    let result = rt.block_on(async {
+                        validator
+                            .validate(black_box(resource), vec!["Patient".to_string()])
+                            .await
+                    });
+                    result
+                        .errors
+                        .iter()
+                        .map(|error| error.to_string())
+                        .collect::<Vec<_>>()
+                });
+            },
+        );
+    }
+
+    group.finish();
+}
+
+/// Benchmark: parsing resource JSON (serde_json, or simd-json with the feature)
+fn bench_parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+
+    for (name, resource) in [
+        ("patient_full", patient_full()),
+        ("bundle_50", bundle_with_resources(50)),
+    ] {
+        let bytes = serde_json::to_vec(&resource).unwrap();
+        group.throughput(Throughput::Bytes(bytes.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::new("parse_resource", name),
+            &bytes,
+            |b, bytes| {
+                b.iter_batched_ref(
+                    || bytes.clone(),
+                    |buf| parse_resource(black_box(buf)).unwrap(),
+                    criterion::BatchSize::SmallInput,
+                );
+            },
+        );
+    }
+
+    group.finish();
+}
+
 /// Benchmark: validator creation
 fn bench_validator_creation(c: &mut Criterion) {
-    let schemas = get_schemas(FhirVersion::R4).clone();
+    let schemas = get_schemas(FhirVersion::R4)
+        .expect("R4 schemas embedded")
+        .clone();
 
     c.bench_function("validator_creation", |b| {
         b.iter(|| {
@@ -335,7 +550,10 @@ criterion_group!(
     bench_validate_patient,
     bench_validate_observation,
     bench_validate_bundle,
+    bench_validate_deep_bundle,
     bench_throughput,
+    bench_validate_findings,
+    bench_parse,
     bench_validator_creation,
 );
 
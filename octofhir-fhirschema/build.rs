@@ -0,0 +1,90 @@
+//! Fail early, with instructions, when a feature embeds a generated artifact
+//! that is not in the tree.
+//!
+//! The core schema bundles are committed, but some optional embeddings are
+//! produced on demand by `schema-generator`. Without this check, enabling one
+//! of those features before generating its artifact ends in an opaque
+//! `include_bytes!` error.
+
+use std::path::Path;
+
+/// An optional embedding and the artifacts it includes.
+struct GeneratedArtifact {
+    /// Cargo feature that embeds the artifact
+    feature: &'static str,
+    /// Files under `precompiled_schemas/`, each with the `embedded-*` version
+    /// feature it additionally depends on, if any
+    files: &'static [(&'static str, Option<&'static str>)],
+    /// Command that generates the files
+    generate: &'static str,
+}
+
+const GENERATED_ARTIFACTS: &[GeneratedArtifact] = &[
+    GeneratedArtifact {
+        feature: "profile-pack-us-core",
+        files: &[("packs/us-core-6.1.0.json.zst", None)],
+        generate: "just generate-profile-packs",
+    },
+    GeneratedArtifact {
+        feature: "profile-pack-ips",
+        files: &[("packs/ips-1.1.0.json.zst", None)],
+        generate: "just generate-profile-packs",
+    },
+    GeneratedArtifact {
+        feature: "embedded-compiled",
+        files: &[
+            ("r4_compiled.json.zst", Some("embedded-r4")),
+            ("r4b_compiled.json.zst", Some("embedded-r4b")),
+            ("r5_compiled.json.zst", Some("embedded-r5")),
+            ("r6_compiled.json.zst", Some("embedded-r6")),
+        ],
+        generate: "just generate-compiled-schemas",
+    },
+    GeneratedArtifact {
+        feature: "embedded-search-params",
+        files: &[
+            ("r4_search_parameters.json.zst", Some("embedded-r4")),
+            ("r4b_search_parameters.json.zst", Some("embedded-r4b")),
+            ("r5_search_parameters.json.zst", Some("embedded-r5")),
+            ("r6_search_parameters.json.zst", Some("embedded-r6")),
+        ],
+        generate: "just generate-search-parameters",
+    },
+];
+
+fn feature_enabled(feature: &str) -> bool {
+    let var = format!("CARGO_FEATURE_{}", feature.to_uppercase().replace('-', "_"));
+    std::env::var_os(var).is_some()
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=precompiled_schemas");
+
+    let dir = Path::new("precompiled_schemas");
+    let mut missing = Vec::new();
+    for artifact in GENERATED_ARTIFACTS {
+        if !feature_enabled(artifact.feature) {
+            continue;
+        }
+        for (file, version_feature) in artifact.files {
+            if version_feature.is_some_and(|version| !feature_enabled(version)) {
+                continue;
+            }
+            if !dir.join(file).is_file() {
+                missing.push(format!(
+                    "  feature `{}` needs precompiled_schemas/{} (generate it with `{}`)",
+                    artifact.feature, file, artifact.generate
+                ));
+            }
+        }
+    }
+
+    if !missing.is_empty() {
+        panic!(
+            "generated artifacts are missing for enabled features:\n{}\n\
+             These artifacts are not committed; generate them first or disable the feature.",
+            missing.join("\n")
+        );
+    }
+}
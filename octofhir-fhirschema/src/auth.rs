@@ -0,0 +1,387 @@
+//! Authentication for HTTP terminology and reference resolution clients.
+//!
+//! Terminology servers and FHIR stores in enterprise deployments sit behind
+//! authentication, commonly SMART backend services (OAuth2 client
+//! credentials). HTTP clients ask an [`AuthProvider`] for the headers to send
+//! with each request, so the authentication scheme stays independent of the
+//! HTTP library:
+//!
+//! - [`StaticTokenAuth`] - a fixed bearer token
+//! - [`HeaderAuth`] - arbitrary headers (API keys, gateway headers)
+//! - [`ClientCredentialsAuth`] - OAuth2 client credentials with the token
+//!   cached until shortly before it expires, optionally with a signed client
+//!   assertion (SMART backend services)
+//!
+//! The token request itself goes through a [`TokenTransport`], implemented
+//! by the caller with its HTTP client.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use octofhir_fhirschema::auth::{AuthProvider, ClientCredentialsAuth};
+//! use std::sync::Arc;
+//!
+//! let auth = ClientCredentialsAuth::new(
+//!     "https://auth.example.org/token",
+//!     "validator",
+//!     Arc::new(MyReqwestTransport::new()),
+//! )
+//! .with_client_secret("secret")
+//! .with_scope("system/ValueSet.read");
+//!
+//! for (name, value) in auth.headers().await? {
+//!     request = request.header(name, value);
+//! }
+//! ```
+
+use async_trait::async_trait;
+use serde_json::Value as JsonValue;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+use crate::reference::ReferenceError;
+use crate::terminology::TerminologyError;
+
+/// `client_assertion_type` of a signed JWT client assertion (RFC 7523)
+pub const JWT_BEARER_ASSERTION: &str = "urn:ietf:params:oauth:client-assertion-type:jwt-bearer";
+
+/// Errors that can occur while authenticating a request
+#[derive(Debug, Error)]
+pub enum AuthError {
+    /// The token endpoint could not be reached or answered with an error
+    #[error("Token request to {url} failed: {message}")]
+    TokenRequest { url: String, message: String },
+
+    /// The token endpoint's answer has no usable access token
+    #[error("Invalid token response from {url}: {message}")]
+    InvalidTokenResponse { url: String, message: String },
+
+    /// The client assertion could not be created
+    #[error("Client assertion failed: {0}")]
+    ClientAssertion(String),
+}
+
+/// Result type for authentication operations
+pub type AuthResult<T> = Result<T, AuthError>;
+
+impl From<AuthError> for TerminologyError {
+    fn from(error: AuthError) -> Self {
+        TerminologyError::ServiceUnavailable {
+            message: error.to_string(),
+        }
+    }
+}
+
+impl From<AuthError> for ReferenceError {
+    fn from(error: AuthError) -> Self {
+        ReferenceError::ServiceUnavailable {
+            message: error.to_string(),
+        }
+    }
+}
+
+/// Supplies the authentication headers of outgoing requests.
+#[async_trait]
+pub trait AuthProvider: Send + Sync {
+    /// Headers to add to the next request, as (name, value) pairs.
+    async fn headers(&self) -> AuthResult<Vec<(String, String)>>;
+
+    /// Drop cached credentials, e.g. after the server answered 401, so the
+    /// next [`Self::headers`] call obtains fresh ones.
+    async fn invalidate(&self) {}
+}
+
+/// A fixed bearer token.
+#[derive(Debug, Clone)]
+pub struct StaticTokenAuth {
+    token: String,
+}
+
+impl StaticTokenAuth {
+    pub fn new(token: impl Into<String>) -> Self {
+        Self {
+            token: token.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl AuthProvider for StaticTokenAuth {
+    async fn headers(&self) -> AuthResult<Vec<(String, String)>> {
+        Ok(vec![bearer(&self.token)])
+    }
+}
+
+/// Fixed headers added to every request, e.g. an API key.
+#[derive(Debug, Clone, Default)]
+pub struct HeaderAuth {
+    headers: Vec<(String, String)>,
+}
+
+impl HeaderAuth {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a header
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+}
+
+#[async_trait]
+impl AuthProvider for HeaderAuth {
+    async fn headers(&self) -> AuthResult<Vec<(String, String)>> {
+        Ok(self.headers.clone())
+    }
+}
+
+/// Sends the form-encoded token request of [`ClientCredentialsAuth`].
+///
+/// Implemented with the caller's HTTP client: POST `form` as
+/// `application/x-www-form-urlencoded` to `url` and return the JSON body of a
+/// successful response.
+#[async_trait]
+pub trait TokenTransport: Send + Sync {
+    async fn post_form(&self, url: &str, form: &[(&str, &str)]) -> AuthResult<JsonValue>;
+}
+
+/// Creates a signed JWT client assertion for each token request.
+pub type ClientAssertionFn = Arc<dyn Fn() -> AuthResult<String> + Send + Sync>;
+
+/// An access token and when to stop using it
+struct CachedToken {
+    access_token: String,
+    refresh_at: Option<Instant>,
+}
+
+/// OAuth2 client credentials grant.
+///
+/// The access token is requested on first use and reused until
+/// `refresh_margin` (30 seconds by default) before its `expires_in`; tokens
+/// without `expires_in` are kept until [`AuthProvider::invalidate`]. The
+/// client authenticates with a secret in the form body, or with a signed
+/// client assertion as SMART backend services require.
+pub struct ClientCredentialsAuth {
+    token_url: String,
+    client_id: String,
+    client_secret: Option<String>,
+    client_assertion: Option<ClientAssertionFn>,
+    scope: Option<String>,
+    refresh_margin: Duration,
+    transport: Arc<dyn TokenTransport>,
+    token: futures::lock::Mutex<Option<CachedToken>>,
+}
+
+impl ClientCredentialsAuth {
+    pub fn new(
+        token_url: impl Into<String>,
+        client_id: impl Into<String>,
+        transport: Arc<dyn TokenTransport>,
+    ) -> Self {
+        Self {
+            token_url: token_url.into(),
+            client_id: client_id.into(),
+            client_secret: None,
+            client_assertion: None,
+            scope: None,
+            refresh_margin: Duration::from_secs(30),
+            transport,
+            token: futures::lock::Mutex::new(None),
+        }
+    }
+
+    /// Authenticate with a client secret
+    pub fn with_client_secret(mut self, secret: impl Into<String>) -> Self {
+        self.client_secret = Some(secret.into());
+        self
+    }
+
+    /// Authenticate with a JWT client assertion created for each token
+    /// request (SMART backend services)
+    pub fn with_client_assertion(mut self, assertion: ClientAssertionFn) -> Self {
+        self.client_assertion = Some(assertion);
+        self
+    }
+
+    /// Scopes to request, space-separated
+    pub fn with_scope(mut self, scope: impl Into<String>) -> Self {
+        self.scope = Some(scope.into());
+        self
+    }
+
+    /// How long before expiry a token is refreshed
+    pub fn with_refresh_margin(mut self, margin: Duration) -> Self {
+        self.refresh_margin = margin;
+        self
+    }
+
+    async fn request_token(&self) -> AuthResult<CachedToken> {
+        let assertion = self
+            .client_assertion
+            .as_ref()
+            .map(|assertion| assertion())
+            .transpose()?;
+
+        let mut form = vec![
+            ("grant_type", "client_credentials"),
+            ("client_id", self.client_id.as_str()),
+        ];
+        if let Some(secret) = &self.client_secret {
+            form.push(("client_secret", secret));
+        }
+        if let Some(assertion) = &assertion {
+            form.push(("client_assertion_type", JWT_BEARER_ASSERTION));
+            form.push(("client_assertion", assertion));
+        }
+        if let Some(scope) = &self.scope {
+            form.push(("scope", scope));
+        }
+
+        let response = self.transport.post_form(&self.token_url, &form).await?;
+        let invalid = |message: &str| AuthError::InvalidTokenResponse {
+            url: self.token_url.clone(),
+            message: message.to_string(),
+        };
+        let access_token = response
+            .get("access_token")
+            .and_then(JsonValue::as_str)
+            .ok_or_else(|| invalid("missing access_token"))?;
+        if let Some(token_type) = response.get("token_type").and_then(JsonValue::as_str)
+            && !token_type.eq_ignore_ascii_case("bearer")
+        {
+            return Err(invalid(&format!("unsupported token_type {token_type}")));
+        }
+        let refresh_at = response
+            .get("expires_in")
+            .and_then(JsonValue::as_u64)
+            .map(|secs| {
+                Instant::now() + Duration::from_secs(secs).saturating_sub(self.refresh_margin)
+            });
+
+        Ok(CachedToken {
+            access_token: access_token.to_string(),
+            refresh_at,
+        })
+    }
+}
+
+#[async_trait]
+impl AuthProvider for ClientCredentialsAuth {
+    async fn headers(&self) -> AuthResult<Vec<(String, String)>> {
+        // Held across the request so concurrent callers share one refresh
+        let mut token = self.token.lock().await;
+        let fresh = token.as_ref().is_some_and(|token| {
+            token
+                .refresh_at
+                .is_none_or(|refresh_at| Instant::now() < refresh_at)
+        });
+        if !fresh {
+            *token = Some(self.request_token().await?);
+        }
+        let token = token.as_ref().expect("token was just requested");
+        Ok(vec![bearer(&token.access_token)])
+    }
+
+    async fn invalidate(&self) {
+        *self.token.lock().await = None;
+    }
+}
+
+impl std::fmt::Debug for ClientCredentialsAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClientCredentialsAuth")
+            .field("token_url", &self.token_url)
+            .field("client_id", &self.client_id)
+            .field("scope", &self.scope)
+            .finish_non_exhaustive()
+    }
+}
+
+fn bearer(token: &str) -> (String, String) {
+    ("Authorization".to_string(), format!("Bearer {token}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    /// Token endpoint that hands out numbered tokens and records the forms
+    struct FakeEndpoint {
+        expires_in: u64,
+        forms: Mutex<Vec<Vec<(String, String)>>>,
+    }
+
+    #[async_trait]
+    impl TokenTransport for FakeEndpoint {
+        async fn post_form(&self, _url: &str, form: &[(&str, &str)]) -> AuthResult<JsonValue> {
+            let mut forms = self.forms.lock().unwrap();
+            forms.push(
+                form.iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect(),
+            );
+            Ok(json!({
+                "access_token": format!("token-{}", forms.len()),
+                "token_type": "bearer",
+                "expires_in": self.expires_in
+            }))
+        }
+    }
+
+    fn endpoint(expires_in: u64) -> Arc<FakeEndpoint> {
+        Arc::new(FakeEndpoint {
+            expires_in,
+            forms: Mutex::new(Vec::new()),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_static_and_header_auth() {
+        assert_eq!(
+            StaticTokenAuth::new("abc").headers().await.unwrap(),
+            [("Authorization".to_string(), "Bearer abc".to_string())]
+        );
+        let headers = HeaderAuth::new().header("X-Api-Key", "k").headers().await;
+        assert_eq!(
+            headers.unwrap(),
+            [("X-Api-Key".to_string(), "k".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_client_credentials_caches_and_refreshes() {
+        let transport = endpoint(3600);
+        let auth = ClientCredentialsAuth::new("https://auth/token", "app", transport.clone())
+            .with_client_secret("s3cret")
+            .with_scope("system/*.read");
+
+        let first = auth.headers().await.unwrap();
+        assert_eq!(first[0].1, "Bearer token-1");
+        assert_eq!(auth.headers().await.unwrap(), first);
+        {
+            let forms = transport.forms.lock().unwrap();
+            assert_eq!(forms.len(), 1);
+            assert!(forms[0].contains(&("grant_type".into(), "client_credentials".into())));
+            assert!(forms[0].contains(&("client_secret".into(), "s3cret".into())));
+            assert!(forms[0].contains(&("scope".into(), "system/*.read".into())));
+        }
+
+        auth.invalidate().await;
+        assert_eq!(auth.headers().await.unwrap()[0].1, "Bearer token-2");
+
+        // Tokens expiring within the refresh margin are requested again
+        let transport = endpoint(10);
+        let auth = ClientCredentialsAuth::new("https://auth/token", "app", transport.clone())
+            .with_client_assertion(Arc::new(|| Ok("signed.jwt".to_string())));
+        auth.headers().await.unwrap();
+        assert_eq!(auth.headers().await.unwrap()[0].1, "Bearer token-2");
+        let forms = transport.forms.lock().unwrap();
+        assert!(forms[0].contains(&("client_assertion_type".into(), JWT_BEARER_ASSERTION.into())));
+        assert!(forms[0].contains(&("client_assertion".into(), "signed.jwt".into())));
+    }
+}
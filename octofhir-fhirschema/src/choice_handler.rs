@@ -1,5 +1,5 @@
-use crate::types::StructureDefinitionElement;
-use std::collections::HashSet;
+use crate::types::{FhirSchema, FhirSchemaElement, StructureDefinitionElement};
+use std::collections::{HashMap, HashSet};
 
 pub fn is_choice_element(element: &StructureDefinitionElement) -> bool {
     // Check if path ends with [x]
@@ -80,6 +80,135 @@ pub fn expand_choice_element(
     Ok(expanded)
 }
 
+/// A concrete element of a choice, e.g. `valueQuantity` of `value[x]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChoiceVariant {
+    /// Name of the choice stem (`value`)
+    pub choice: String,
+    /// Key of the variant in instances (`valueQuantity`)
+    pub key: String,
+    /// FHIR type of the variant (`Quantity`)
+    pub type_name: String,
+}
+
+/// Expands choice elements of converted schemas into their concrete keys
+/// and types, from the stem's `choices` and each variant's `type`.
+///
+/// Schemas are looked up by name, or by the last segment of a canonical
+/// `base`, so profiles resolve through their base chain.
+///
+/// ```ignore
+/// let resolver = ChoiceTypeResolver::new(get_schemas(FhirVersion::R4).unwrap());
+/// let variants = resolver.variants("Observation", "value").unwrap();
+/// assert!(variants.iter().any(|v| v.key == "valueQuantity"));
+/// ```
+pub struct ChoiceTypeResolver<'a> {
+    schemas: &'a HashMap<String, FhirSchema>,
+}
+
+/// Bound on base chain and type hops, against cyclic schema sets
+const MAX_DEPTH: usize = 32;
+
+impl<'a> ChoiceTypeResolver<'a> {
+    pub fn new(schemas: &'a HashMap<String, FhirSchema>) -> Self {
+        Self { schemas }
+    }
+
+    /// The variants of the choice at `path` in `type_name`, in declaration
+    /// order. `path` is relative to the type (`value`, `component.value`),
+    /// with or without the `[x]` suffix. `None` when the path does not name
+    /// a choice.
+    pub fn variants(&self, type_name: &str, path: &str) -> Option<Vec<ChoiceVariant>> {
+        let path = path.strip_suffix("[x]").unwrap_or(path);
+        let (parent, choice) = path.rsplit_once('.').unwrap_or(("", path));
+        let layers = self.layers_at(type_name, parent)?;
+        let choices = layers
+            .iter()
+            .find_map(|layer| layer.get(choice)?.choices.as_ref())?;
+
+        Some(
+            choices
+                .iter()
+                .map(|key| ChoiceVariant {
+                    choice: choice.to_string(),
+                    key: key.clone(),
+                    type_name: layers
+                        .iter()
+                        .find_map(|layer| layer.get(key)?.type_name.clone())
+                        .unwrap_or_else(|| key.strip_prefix(choice).unwrap_or(key).to_string()),
+                })
+                .collect(),
+        )
+    }
+
+    /// The variant a concrete key at `path` stands for: `valueQuantity` in
+    /// `Observation` is the `Quantity` variant of `value`.
+    pub fn resolve(&self, type_name: &str, path: &str) -> Option<ChoiceVariant> {
+        let (parent, key) = path.rsplit_once('.').unwrap_or(("", path));
+        let layers = self.layers_at(type_name, parent)?;
+        let choice = layers
+            .iter()
+            .find_map(|layer| layer.get(key)?.choice_of.as_deref())?;
+        let parent_choice = if parent.is_empty() {
+            choice.to_string()
+        } else {
+            format!("{parent}.{choice}")
+        };
+        self.variants(type_name, &parent_choice)?
+            .into_iter()
+            .find(|variant| variant.key == key)
+    }
+
+    fn schema(&self, name_or_url: &str) -> Option<&'a FhirSchema> {
+        self.schemas.get(name_or_url).or_else(|| {
+            let name = name_or_url.split('|').next()?.rsplit('/').next()?;
+            self.schemas.get(name)
+        })
+    }
+
+    /// The element maps of `type_name` and its bases, most derived first.
+    fn type_layers(&self, type_name: &str) -> Vec<&'a HashMap<String, FhirSchemaElement>> {
+        let mut layers = Vec::new();
+        let mut schema = self.schema(type_name);
+        for _ in 0..MAX_DEPTH {
+            let Some(current) = schema else { break };
+            layers.extend(current.elements.as_ref());
+            schema = current.base.as_deref().and_then(|base| self.schema(base));
+        }
+        layers
+    }
+
+    /// The element maps that define the children at the dotted `path`,
+    /// following backbone elements inline and other elements into their
+    /// type.
+    fn layers_at(
+        &self,
+        type_name: &str,
+        path: &str,
+    ) -> Option<Vec<&'a HashMap<String, FhirSchemaElement>>> {
+        let mut layers = self.type_layers(type_name);
+        for segment in path.split('.').filter(|s| !s.is_empty()) {
+            let elements: Vec<&FhirSchemaElement> = layers
+                .iter()
+                .filter_map(|layer| layer.get(segment))
+                .collect();
+            let inline: Vec<_> = elements
+                .iter()
+                .filter_map(|element| element.elements.as_ref())
+                .collect();
+            layers = if inline.is_empty() {
+                let type_name = elements
+                    .iter()
+                    .find_map(|element| element.type_name.as_deref())?;
+                self.type_layers(type_name)
+            } else {
+                inline
+            };
+        }
+        (!layers.is_empty()).then_some(layers)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -194,4 +323,65 @@ mod tests {
         assert!(result[2].type_info.is_some());
         assert_eq!(result[2].type_info.as_ref().unwrap()[0].code, "integer");
     }
+
+    fn schema(name: &str, base: Option<&str>, elements: serde_json::Value) -> FhirSchema {
+        serde_json::from_value(serde_json::json!({
+            "url": format!("http://example.org/StructureDefinition/{name}"),
+            "name": name, "type": "Observation", "kind": "resource",
+            "class": "resource", "base": base, "elements": elements
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_choice_type_resolver() {
+        let schemas = HashMap::from([
+            (
+                "Observation".to_string(),
+                schema(
+                    "Observation",
+                    None,
+                    serde_json::json!({
+                        "value": {"choices": ["valueQuantity", "valueString"]},
+                        "valueQuantity": {"type": "Quantity", "choiceOf": "value"},
+                        "valueString": {"type": "string", "choiceOf": "value"},
+                        "component": {"type": "BackboneElement", "array": true, "elements": {
+                            "value": {"choices": ["valueBoolean"]},
+                            "valueBoolean": {"type": "boolean", "choiceOf": "value"}
+                        }}
+                    }),
+                ),
+            ),
+            (
+                "Vitals".to_string(),
+                schema(
+                    "Vitals",
+                    Some("http://example.org/StructureDefinition/Observation"),
+                    serde_json::json!({"status": {"type": "code"}}),
+                ),
+            ),
+        ]);
+        let resolver = ChoiceTypeResolver::new(&schemas);
+
+        let variants = resolver.variants("Observation", "value[x]").unwrap();
+        assert_eq!(
+            variants
+                .iter()
+                .map(|v| (v.key.as_str(), v.type_name.as_str()))
+                .collect::<Vec<_>>(),
+            vec![("valueQuantity", "Quantity"), ("valueString", "string")]
+        );
+        // Through the base chain and into backbone elements.
+        assert_eq!(resolver.variants("Vitals", "value"), Some(variants));
+        assert_eq!(
+            resolver.resolve("Vitals", "component.valueBoolean"),
+            Some(ChoiceVariant {
+                choice: "value".to_string(),
+                key: "valueBoolean".to_string(),
+                type_name: "boolean".to_string(),
+            })
+        );
+        assert_eq!(resolver.variants("Observation", "component"), None);
+        assert_eq!(resolver.resolve("Observation", "value"), None);
+    }
 }
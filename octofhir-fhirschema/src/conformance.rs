@@ -0,0 +1,655 @@
+//! Conformance test-suite runner.
+//!
+//! Runs test suites against a validator built by the caller, so embedders and
+//! forks can check that their schema provider, FHIRPath evaluator and
+//! terminology wiring behave as the specification expects:
+//!
+//! - [`SchemaTestSuite`] - FHIR Schema validation cases: a resource, the
+//!   schemas to validate it against and the expected outcome
+//! - [`Hl7TestSuite`] - the validator cases of the HL7 `fhir-test-cases`
+//!   repository (its `validator` folder with `manifest.json`), with the
+//!   expected validity taken from the outcomes recorded for the Java validator
+//!
+//! Every run returns a [`ConformanceReport`] with one [`CaseResult`] per case.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use octofhir_fhirschema::conformance::{ConformanceRunner, SchemaTestSuite};
+//!
+//! let runner = ConformanceRunner::embedded(FhirVersion::R4)?;
+//! let suite = SchemaTestSuite::from_file("tests/choice-types.json")?;
+//! let report = runner.run_schema_suite(&suite).await;
+//! assert!(report.is_success(), "{:#?}", report.failures().collect::<Vec<_>>());
+//! ```
+
+use crate::converter::translate;
+use crate::embedded::{FhirVersion, get_schemas};
+use crate::error::{FhirSchemaError, Result};
+use crate::types::{FhirSchema, StructureDefinition, ValidationError};
+use crate::validation::FhirValidator;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Outcome of a single case.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CaseStatus {
+    Passed,
+    Failed,
+    /// The case could not be run or has no expected outcome
+    Skipped,
+}
+
+/// Result of a single case.
+#[derive(Debug, Clone, Serialize)]
+pub struct CaseResult {
+    /// Case name or description
+    pub name: String,
+    pub status: CaseStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected_valid: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actual_valid: Option<bool>,
+    /// Why the case failed or was skipped
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Errors the validator reported
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<ValidationError>,
+}
+
+impl CaseResult {
+    fn skipped(name: String, reason: String) -> Self {
+        Self {
+            name,
+            status: CaseStatus::Skipped,
+            expected_valid: None,
+            actual_valid: None,
+            reason: Some(reason),
+            errors: Vec::new(),
+        }
+    }
+}
+
+/// Results of a suite run.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ConformanceReport {
+    pub cases: Vec<CaseResult>,
+}
+
+impl ConformanceReport {
+    fn count(&self, status: CaseStatus) -> usize {
+        self.cases.iter().filter(|c| c.status == status).count()
+    }
+
+    pub fn passed(&self) -> usize {
+        self.count(CaseStatus::Passed)
+    }
+
+    pub fn failed(&self) -> usize {
+        self.count(CaseStatus::Failed)
+    }
+
+    pub fn skipped(&self) -> usize {
+        self.count(CaseStatus::Skipped)
+    }
+
+    /// Cases that failed
+    pub fn failures(&self) -> impl Iterator<Item = &CaseResult> {
+        self.cases.iter().filter(|c| c.status == CaseStatus::Failed)
+    }
+
+    /// Whether no case failed. Skipped cases do not count as failures.
+    pub fn is_success(&self) -> bool {
+        self.failed() == 0
+    }
+}
+
+// ============================================================================
+// FHIR Schema suites
+// ============================================================================
+
+/// A FHIR Schema validation suite, read from JSON:
+///
+/// ```json
+/// {
+///   "description": "required elements",
+///   "schemas": [{ "url": "...", "name": "MyPatient", ... }],
+///   "tests": [{
+///     "description": "missing name",
+///     "schemas": ["MyPatient"],
+///     "data": { "resourceType": "Patient" },
+///     "errors": [{ "type": "FS1002", "path": "Patient.name" }]
+///   }]
+/// }
+/// ```
+///
+/// `desc` is accepted for `description` and `resource` for `data`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SchemaTestSuite {
+    #[serde(default, alias = "desc")]
+    pub description: Option<String>,
+    /// Schemas the cases use, on top of the runner's base schemas
+    #[serde(default)]
+    pub schemas: Vec<FhirSchema>,
+    pub tests: Vec<SchemaTestCase>,
+}
+
+/// A case of a [`SchemaTestSuite`].
+#[derive(Debug, Clone, Deserialize)]
+pub struct SchemaTestCase {
+    #[serde(alias = "desc")]
+    pub description: String,
+    /// Schema names or URLs to validate against; defaults to the resource's
+    /// `resourceType`
+    #[serde(default)]
+    pub schemas: Vec<String>,
+    #[serde(alias = "resource")]
+    pub data: Value,
+    /// Errors the validator must report
+    #[serde(default)]
+    pub errors: Vec<ExpectedError>,
+    /// Expected validity; defaults to valid exactly when `errors` is empty
+    #[serde(default)]
+    pub valid: Option<bool>,
+}
+
+/// An error a [`SchemaTestCase`] expects.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExpectedError {
+    /// Error code, e.g. `FS1002`
+    #[serde(rename = "type")]
+    pub error_type: String,
+    /// Dotted element path; any path matches when absent
+    #[serde(default)]
+    pub path: Option<String>,
+}
+
+impl ExpectedError {
+    fn matches(&self, error: &ValidationError) -> bool {
+        error.error_type == self.error_type
+            && self
+                .path
+                .as_ref()
+                .is_none_or(|path| error.path.to_string() == *path)
+    }
+}
+
+impl SchemaTestSuite {
+    pub fn from_json(value: Value) -> Result<Self> {
+        Ok(serde_json::from_value(value)?)
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+}
+
+// ============================================================================
+// HL7 fhir-test-cases suites
+// ============================================================================
+
+/// The validator cases of the HL7 `fhir-test-cases` repository.
+#[derive(Debug, Clone)]
+pub struct Hl7TestSuite {
+    dir: PathBuf,
+    manifest: Hl7Manifest,
+}
+
+/// Which cases of an [`Hl7TestSuite`] to run.
+#[derive(Debug, Clone)]
+pub struct Hl7SuiteOptions {
+    /// FHIR version of the cases, as written in the manifest
+    pub version: String,
+    /// Only run cases of this manifest module
+    pub module: Option<String>,
+    /// Stop after this many cases
+    pub max_cases: Option<usize>,
+}
+
+impl Default for Hl7SuiteOptions {
+    fn default() -> Self {
+        Self {
+            version: "4.0".to_string(),
+            module: None,
+            max_cases: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Hl7Manifest {
+    #[serde(rename = "test-cases")]
+    test_cases: Vec<Hl7Case>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Hl7Case {
+    name: String,
+    file: String,
+    #[serde(default)]
+    version: Option<String>,
+    #[serde(default)]
+    module: Option<String>,
+    #[serde(default)]
+    supporting: Vec<String>,
+    #[serde(default)]
+    profile: Option<Hl7ProfileTest>,
+    #[serde(default)]
+    scoring: Option<Hl7ScoringTest>,
+    #[serde(rename = "allow-comments", default)]
+    allow_comments: bool,
+    #[serde(rename = "use-test", default = "default_true")]
+    use_test: bool,
+    #[serde(default)]
+    java: Option<Hl7Outcome>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Hl7ProfileTest {
+    #[serde(default)]
+    java: Option<Hl7Outcome>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Hl7ScoringTest {
+    profile: String,
+}
+
+/// Expected outcome: a file under `outcomes/` or inline.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum Hl7Outcome {
+    Path(String),
+    Inline {
+        #[serde(rename = "errorCount", default)]
+        error_count: Option<usize>,
+        #[serde(default)]
+        outcome: Option<Value>,
+    },
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Hl7TestSuite {
+    /// Load the suite from the `validator` folder of a `fhir-test-cases`
+    /// checkout.
+    pub fn load(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        let content = fs::read_to_string(dir.join("manifest.json"))?;
+        let manifest = serde_json::from_str(&content)?;
+        Ok(Self { dir, manifest })
+    }
+
+    /// Number of cases in the manifest
+    pub fn len(&self) -> usize {
+        self.manifest.test_cases.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.manifest.test_cases.is_empty()
+    }
+
+    fn selected<'a>(&'a self, options: &'a Hl7SuiteOptions) -> impl Iterator<Item = &'a Hl7Case> {
+        self.manifest
+            .test_cases
+            .iter()
+            .filter(|case| {
+                case.use_test
+                    && case.version.as_deref() == Some(options.version.as_str())
+                    && case.file.ends_with(".json")
+                    && options
+                        .module
+                        .as_ref()
+                        .is_none_or(|module| case.module.as_ref() == Some(module))
+            })
+            .take(options.max_cases.unwrap_or(usize::MAX))
+    }
+
+    /// Schemas converted from the supporting StructureDefinitions of the
+    /// selected cases, keyed by name and URL.
+    fn supporting_schemas(&self, options: &Hl7SuiteOptions) -> HashMap<String, FhirSchema> {
+        let mut schemas = HashMap::new();
+        for rel in self.selected(options).flat_map(|case| &case.supporting) {
+            let Ok(content) = fs::read_to_string(self.dir.join(rel)) else {
+                continue;
+            };
+            let Ok(sd) = serde_json::from_str::<StructureDefinition>(&content) else {
+                continue;
+            };
+            if let Ok(schema) = translate(sd, None) {
+                schemas.insert(schema.url.clone(), schema.clone());
+                schemas.insert(schema.name.clone(), schema);
+            }
+        }
+        schemas
+    }
+
+    fn expected_valid(&self, case: &Hl7Case) -> Option<bool> {
+        let outcome = case
+            .java
+            .as_ref()
+            .or_else(|| case.profile.as_ref()?.java.as_ref())?;
+        match outcome {
+            Hl7Outcome::Path(path) => {
+                let content = fs::read_to_string(self.dir.join("outcomes").join(path)).ok()?;
+                outcome_validity(&serde_json::from_str(&content).ok()?)
+            }
+            Hl7Outcome::Inline {
+                error_count: Some(count),
+                ..
+            } => Some(*count == 0),
+            Hl7Outcome::Inline { outcome, .. } => {
+                Some(outcome.as_ref().and_then(outcome_validity).unwrap_or(true))
+            }
+        }
+    }
+}
+
+/// Whether an OperationOutcome has no fatal or error issues.
+fn outcome_validity(outcome: &Value) -> Option<bool> {
+    let issues = outcome.get("issue")?.as_array()?;
+    Some(!issues.iter().any(|issue| {
+        issue
+            .get("severity")
+            .and_then(Value::as_str)
+            .is_some_and(|severity| {
+                severity.eq_ignore_ascii_case("error") || severity.eq_ignore_ascii_case("fatal")
+            })
+    }))
+}
+
+/// Parse a case resource, tolerating a BOM and, when the case allows it,
+/// `//` line comments.
+fn parse_case_resource(source: &str, allow_comments: bool) -> serde_json::Result<Value> {
+    let source = source.strip_prefix('\u{feff}').unwrap_or(source);
+    match serde_json::from_str(source) {
+        Err(_) if allow_comments => serde_json::from_str(&strip_line_comments(source)),
+        parsed => parsed,
+    }
+}
+
+fn strip_line_comments(source: &str) -> String {
+    let mut output = String::with_capacity(source.len());
+    let mut chars = source.chars().peekable();
+    let mut in_string = false;
+    let mut escaped = false;
+    while let Some(ch) = chars.next() {
+        if in_string {
+            in_string = escaped || ch != '"';
+            escaped = !escaped && ch == '\\';
+        } else if ch == '"' {
+            in_string = true;
+        } else if ch == '/' && chars.peek() == Some(&'/') {
+            for skipped in chars.by_ref() {
+                if skipped == '\n' {
+                    output.push('\n');
+                    break;
+                }
+            }
+            continue;
+        }
+        output.push(ch);
+    }
+    output
+}
+
+fn meta_profiles(resource: &Value) -> impl Iterator<Item = String> + '_ {
+    resource
+        .pointer("/meta/profile")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .map(str::to_string)
+}
+
+// ============================================================================
+// Runner
+// ============================================================================
+
+type ValidatorFactory = dyn Fn(HashMap<String, FhirSchema>) -> FhirValidator + Send + Sync;
+
+/// Runs suites against validators built by a caller-supplied factory.
+///
+/// The factory receives the base schemas plus those the suite brings and
+/// returns the validator under test, wired with whatever FHIRPath engine,
+/// terminology service or cache settings the embedder uses.
+pub struct ConformanceRunner {
+    base: HashMap<String, FhirSchema>,
+    build: Box<ValidatorFactory>,
+}
+
+impl ConformanceRunner {
+    pub fn new(
+        base: HashMap<String, FhirSchema>,
+        build: impl Fn(HashMap<String, FhirSchema>) -> FhirValidator + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            base,
+            build: Box::new(build),
+        }
+    }
+
+    /// Runner over the embedded core schemas of `version`, validating
+    /// structure only (no FHIRPath engine).
+    pub fn embedded(version: FhirVersion) -> Result<Self> {
+        Ok(Self::new(get_schemas(version)?.clone(), |schemas| {
+            FhirValidator::from_schemas(schemas, None)
+        }))
+    }
+
+    fn validator(&self, extra: HashMap<String, FhirSchema>) -> FhirValidator {
+        let mut schemas = self.base.clone();
+        schemas.extend(extra);
+        (self.build)(schemas)
+    }
+
+    /// Run every case of a FHIR Schema suite.
+    pub async fn run_schema_suite(&self, suite: &SchemaTestSuite) -> ConformanceReport {
+        let mut extra = HashMap::new();
+        for schema in &suite.schemas {
+            extra.insert(schema.url.clone(), schema.clone());
+            extra.insert(schema.name.clone(), schema.clone());
+        }
+        let validator = self.validator(extra);
+
+        let mut report = ConformanceReport::default();
+        for case in &suite.tests {
+            let mut schemas = case.schemas.clone();
+            if schemas.is_empty()
+                && let Some(resource_type) = case.data.get("resourceType").and_then(Value::as_str)
+            {
+                schemas.push(resource_type.to_string());
+            }
+            if schemas.is_empty() {
+                report.cases.push(CaseResult::skipped(
+                    case.description.clone(),
+                    "no schemas and no resourceType".to_string(),
+                ));
+                continue;
+            }
+
+            let result = validator.validate(&case.data, schemas).await;
+            let expected_valid = case.valid.unwrap_or(case.errors.is_empty());
+            let missing: Vec<String> = case
+                .errors
+                .iter()
+                .filter(|expected| !result.errors.iter().any(|error| expected.matches(error)))
+                .map(|expected| match &expected.path {
+                    Some(path) => format!("{} at {path}", expected.error_type),
+                    None => expected.error_type.clone(),
+                })
+                .collect();
+            let reason = if result.valid != expected_valid {
+                Some(format!(
+                    "expected {}, got {}",
+                    validity(expected_valid),
+                    validity(result.valid)
+                ))
+            } else if !missing.is_empty() {
+                Some(format!(
+                    "expected errors not reported: {}",
+                    missing.join(", ")
+                ))
+            } else {
+                None
+            };
+            report.cases.push(CaseResult {
+                name: case.description.clone(),
+                status: if reason.is_none() {
+                    CaseStatus::Passed
+                } else {
+                    CaseStatus::Failed
+                },
+                expected_valid: Some(expected_valid),
+                actual_valid: Some(result.valid),
+                reason,
+                errors: result.errors,
+            });
+        }
+        report
+    }
+
+    /// Run the selected cases of an HL7 `fhir-test-cases` suite. A case
+    /// passes when its validity agrees with the Java validator's; cases
+    /// without a recorded outcome or with unreadable resources are skipped.
+    pub async fn run_hl7_suite(
+        &self,
+        suite: &Hl7TestSuite,
+        options: &Hl7SuiteOptions,
+    ) -> ConformanceReport {
+        let validator = self.validator(suite.supporting_schemas(options));
+
+        let mut report = ConformanceReport::default();
+        for case in suite.selected(options) {
+            let Some(expected_valid) = suite.expected_valid(case) else {
+                report.cases.push(CaseResult::skipped(
+                    case.name.clone(),
+                    "no expected outcome recorded".to_string(),
+                ));
+                continue;
+            };
+            let resource = fs::read_to_string(suite.dir.join(&case.file))
+                .map_err(FhirSchemaError::from)
+                .and_then(|source| Ok(parse_case_resource(&source, case.allow_comments)?));
+            let resource = match resource {
+                Ok(resource) => resource,
+                Err(error) => {
+                    report
+                        .cases
+                        .push(CaseResult::skipped(case.name.clone(), error.to_string()));
+                    continue;
+                }
+            };
+
+            let schemas: Vec<String> = match &case.scoring {
+                Some(scoring) => vec![scoring.profile.clone()],
+                None => resource
+                    .get("resourceType")
+                    .and_then(Value::as_str)
+                    .map(str::to_string)
+                    .into_iter()
+                    .chain(meta_profiles(&resource))
+                    .collect(),
+            };
+            let result = validator.validate(&resource, schemas).await;
+            let agrees = result.valid == expected_valid;
+            report.cases.push(CaseResult {
+                name: case.name.clone(),
+                status: if agrees {
+                    CaseStatus::Passed
+                } else {
+                    CaseStatus::Failed
+                },
+                expected_valid: Some(expected_valid),
+                actual_valid: Some(result.valid),
+                reason: (!agrees).then(|| {
+                    format!(
+                        "Java validator found it {}, got {}",
+                        validity(expected_valid),
+                        validity(result.valid)
+                    )
+                }),
+                errors: result.errors,
+            });
+        }
+        report
+    }
+}
+
+fn validity(valid: bool) -> &'static str {
+    if valid { "valid" } else { "invalid" }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_schema_suite() {
+        let suite = SchemaTestSuite::from_json(json!({
+            "desc": "patient basics",
+            "tests": [
+                {
+                    "desc": "valid patient",
+                    "data": { "resourceType": "Patient", "active": true }
+                },
+                {
+                    "desc": "unknown element",
+                    "data": { "resourceType": "Patient", "notAnElement": 1 },
+                    "errors": [{ "type": "FS1001" }]
+                },
+                {
+                    "desc": "wrongly expected valid",
+                    "data": { "resourceType": "Patient", "notAnElement": 1 }
+                },
+                {
+                    "desc": "no schema",
+                    "data": { "active": true }
+                }
+            ]
+        }))
+        .unwrap();
+
+        let runner = ConformanceRunner::embedded(FhirVersion::R4).unwrap();
+        let report = runner.run_schema_suite(&suite).await;
+
+        assert_eq!(report.passed(), 2, "{:#?}", report.cases);
+        assert_eq!(report.failed(), 1);
+        assert_eq!(report.skipped(), 1);
+        assert!(!report.is_success());
+        let failure = report.failures().next().unwrap();
+        assert_eq!(failure.name, "wrongly expected valid");
+        assert_eq!(
+            failure.reason.as_deref(),
+            Some("expected valid, got invalid")
+        );
+    }
+
+    #[test]
+    fn test_hl7_outcomes_and_comments() {
+        assert_eq!(
+            outcome_validity(&json!({ "issue": [{ "severity": "warning" }] })),
+            Some(true)
+        );
+        assert_eq!(
+            outcome_validity(&json!({ "issue": [{ "severity": "error" }] })),
+            Some(false)
+        );
+
+        let source = "{\n  // comment\n  \"url\": \"http://x//y\" // trailing\n}";
+        assert!(parse_case_resource(source, false).is_err());
+        assert_eq!(
+            parse_case_resource(source, true).unwrap(),
+            json!({ "url": "http://x//y" })
+        );
+    }
+}
@@ -3,13 +3,16 @@ use crate::choice_handler::{expand_choice_element, is_choice_element};
 use crate::element_transformer::transform_element;
 use crate::error::{FhirSchemaError, Result};
 use crate::path_parser::{enrich_path, parse_path};
+use crate::profiles::{merge_elements, merge_profile_chain};
 use crate::stack_processor::apply_actions;
 use crate::types::{
     ConversionContext, FhirSchema, FhirSchemaConstraint, FhirSchemaElement, StructureDefinition,
     StructureDefinitionElement,
 };
+use crate::validation::is_primitive_type;
 use serde_json::{Value, json};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
 fn build_resource_header(
     structure_definition: &StructureDefinition,
@@ -20,6 +23,7 @@ fn build_resource_header(
         type_name: structure_definition.type_name.clone(),
         url: structure_definition.url.clone(),
         version: structure_definition.version.clone(),
+        title: structure_definition.title.clone(),
         description: structure_definition.description.clone(),
         package_name: structure_definition.package_name.clone(),
         package_version: structure_definition.package_version.clone(),
@@ -60,11 +64,7 @@ fn build_resource_header(
         for constraint in constraints {
             constraint_map.insert(
                 constraint.key.clone(),
-                FhirSchemaConstraint {
-                    expression: constraint.expression.clone(),
-                    human: constraint.human.clone(),
-                    severity: constraint.severity.clone(),
-                },
+                FhirSchemaConstraint::from(constraint),
             );
         }
         schema.constraint = Some(constraint_map);
@@ -286,6 +286,105 @@ pub fn translate(
     Ok(final_schema)
 }
 
+/// Make `schema` self-contained: merge its base chain into it and inline the
+/// elements of the complex types its elements refer to, up to `max_depth`
+/// levels of types. `types` holds the schemas referenced types and bases are
+/// resolved from, keyed by name (as returned by `get_schemas`); bases are
+/// also found by URL.
+///
+/// Like the compiled schema, primitives, `Reference`, `Resource` and
+/// `Extension` stay references by type name, as do types `types` does not
+/// know. An inlined element keeps its `type` and gets the type's elements
+/// with its own nested constraints merged over them.
+pub fn inline_types(
+    schema: &FhirSchema,
+    types: &HashMap<String, FhirSchema>,
+    max_depth: usize,
+) -> FhirSchema {
+    let mut result = merge_chain(schema, types);
+    result.elements = result
+        .elements
+        .map(|elements| inline_elements(&elements, types, max_depth));
+    result
+}
+
+/// Merge the base chain of `schema` as far as `types` resolves it.
+fn merge_chain(schema: &FhirSchema, types: &HashMap<String, FhirSchema>) -> FhirSchema {
+    let mut chain = vec![Arc::new(schema.clone())];
+    let mut visited = HashSet::from([schema.url.clone()]);
+    let mut base = schema.base.as_deref();
+    while let Some(base_url) = base {
+        if !visited.insert(base_url.to_string()) {
+            break;
+        }
+        let Some(base_schema) = types
+            .get(base_url)
+            .or_else(|| types.values().find(|s| s.url == base_url))
+        else {
+            break;
+        };
+        base = base_schema.base.as_deref();
+        chain.push(Arc::new(base_schema.clone()));
+    }
+    chain.reverse();
+    merge_profile_chain(&chain)
+}
+
+fn inline_elements(
+    elements: &HashMap<String, FhirSchemaElement>,
+    types: &HashMap<String, FhirSchema>,
+    depth: usize,
+) -> HashMap<String, FhirSchemaElement> {
+    elements
+        .iter()
+        .map(|(name, element)| (name.clone(), inline_element(element, types, depth)))
+        .collect()
+}
+
+fn inline_element(
+    element: &FhirSchemaElement,
+    types: &HashMap<String, FhirSchema>,
+    depth: usize,
+) -> FhirSchemaElement {
+    let mut result = element.clone();
+    let type_schema = element
+        .type_name
+        .as_deref()
+        .filter(|type_name| {
+            depth > 0
+                && !is_primitive_type(type_name)
+                && !matches!(
+                    *type_name,
+                    "Reference" | "Resource" | "Extension" | "BackboneElement"
+                )
+        })
+        .and_then(|type_name| types.get(type_name));
+
+    match type_schema {
+        Some(type_schema) => {
+            let mut children = merge_chain(type_schema, types).elements.unwrap_or_default();
+            for (key, overlay) in element.elements.iter().flatten() {
+                let merged = match children.get(key) {
+                    Some(base) => merge_elements(base, overlay),
+                    None => overlay.clone(),
+                };
+                children.insert(key.clone(), merged);
+            }
+            if !children.is_empty() {
+                result.elements = Some(inline_elements(&children, types, depth - 1));
+            }
+        }
+        // Backbone elements are part of this schema, not a referenced type
+        None => {
+            result.elements = element
+                .elements
+                .as_ref()
+                .map(|nested| inline_elements(nested, types, depth));
+        }
+    }
+    result
+}
+
 // Export all modules for testing
 pub use crate::action_calculator::calculate_actions as calculate_actions_export;
 pub use crate::choice_handler::{
@@ -483,4 +582,82 @@ mod tests {
             "engine.script should be present"
         );
     }
+
+    #[test]
+    fn test_inline_types() {
+        let schema = |value: Value| -> FhirSchema { serde_json::from_value(value).unwrap() };
+        let fhir = |name: &str| format!("http://hl7.org/fhir/StructureDefinition/{name}");
+        let types = HashMap::from([
+            (
+                "Element".to_string(),
+                schema(json!({
+                    "url": fhir("Element"), "name": "Element", "type": "Element",
+                    "kind": "complex-type", "class": "complex-type",
+                    "elements": {"id": {"type": "string"}, "extension": {"type": "Extension", "array": true}}
+                })),
+            ),
+            (
+                "Period".to_string(),
+                schema(json!({
+                    "url": fhir("Period"), "name": "Period", "type": "Period",
+                    "kind": "complex-type", "class": "complex-type", "base": fhir("Element"),
+                    "elements": {"start": {"type": "dateTime"}}
+                })),
+            ),
+            (
+                "HumanName".to_string(),
+                schema(json!({
+                    "url": fhir("HumanName"), "name": "HumanName", "type": "HumanName",
+                    "kind": "complex-type", "class": "complex-type", "base": fhir("Element"),
+                    "elements": {"family": {"type": "string"}, "period": {"type": "Period"}}
+                })),
+            ),
+        ]);
+        let person = schema(json!({
+            "url": "http://example.org/Person", "name": "Person", "type": "Person",
+            "kind": "resource", "class": "resource", "base": fhir("Element"),
+            "elements": {
+                "name": {"type": "HumanName", "array": true, "elements": {"family": {"min": 1}}},
+                "contact": {"type": "BackboneElement", "elements": {
+                    "name": {"type": "HumanName"}
+                }}
+            }
+        }));
+
+        let inlined = inline_types(&person, &types, 1);
+        let elements = inlined.elements.as_ref().unwrap();
+        assert!(elements.contains_key("id"), "base elements are merged in");
+        let name = elements["name"].elements.as_ref().unwrap();
+        assert_eq!(name["family"].type_name.as_deref(), Some("string"));
+        assert_eq!(name["family"].min, Some(1));
+        assert!(name.contains_key("extension"));
+        assert!(name["period"].elements.is_none());
+        assert!(name["extension"].elements.is_none());
+        let contact_name = &elements["contact"].elements.as_ref().unwrap()["name"];
+        assert!(
+            contact_name
+                .elements
+                .as_ref()
+                .unwrap()
+                .contains_key("family")
+        );
+
+        let inlined = inline_types(&person, &types, 2);
+        let name = inlined.elements.as_ref().unwrap()["name"]
+            .elements
+            .as_ref()
+            .unwrap();
+        assert!(
+            name["period"]
+                .elements
+                .as_ref()
+                .unwrap()
+                .contains_key("start")
+        );
+
+        // Depth 0 only merges the base chain
+        let untouched = inline_types(&person, &types, 0);
+        let name = &untouched.elements.as_ref().unwrap()["name"];
+        assert_eq!(name.elements.as_ref().unwrap().len(), 1);
+    }
 }
@@ -0,0 +1,341 @@
+//! Mermaid and Graphviz diagrams of a schema's element tree.
+//!
+//! [`Diagram`] draws a resource, data type or profile as a class diagram:
+//! the root and each backbone element are classes listing their elements,
+//! backbone elements hang off their parent by composition, and elements of
+//! a Reference or canonical type point at the types they may target.
+//! Optionally the derivation chain is drawn as inheritance from the root to
+//! its bases. Element definitions are merged with the base chain as in
+//! [`SchemaDoc`], so a profile shows its constrained cardinalities.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use octofhir_fhirschema::diagram::Diagram;
+//!
+//! let diagram = Diagram::new(&patient, &[&domain_resource, &resource], true);
+//! std::fs::write("patient.mmd", diagram.to_mermaid())?;
+//! std::fs::write("patient.dot", diagram.to_dot())?;
+//! ```
+
+use std::fmt::Write;
+
+use serde::Serialize;
+
+use crate::docs::SchemaDoc;
+use crate::types::FhirSchema;
+
+/// A class diagram of one schema.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagram {
+    /// Name of the diagram, the schema name
+    pub name: String,
+    /// The root first, then backbone elements in definition order
+    pub classes: Vec<DiagramClass>,
+    /// Composition, reference and derivation edges
+    pub edges: Vec<DiagramEdge>,
+}
+
+/// A box of the diagram.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DiagramClass {
+    /// Identifier safe for Mermaid and DOT, e.g. `Patient_contact`
+    pub id: String,
+    /// Displayed name, e.g. `Patient.contact`
+    pub label: String,
+    /// Elements as `name : Type [min..max]`
+    pub attributes: Vec<String>,
+}
+
+/// How two classes are related.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EdgeKind {
+    /// A backbone element owned by its parent
+    Composition,
+    /// An element referencing another resource or canonical
+    Reference,
+    /// The source is derived from (constrains or specializes) the target
+    Derivation,
+}
+
+/// An arrow between two classes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DiagramEdge {
+    /// Source class id
+    pub from: String,
+    /// Target class id; references and bases may have no [`DiagramClass`]
+    pub to: String,
+    /// Relationship
+    pub kind: EdgeKind,
+    /// Element name, absent for derivation
+    pub label: Option<String>,
+    /// `min..max` of the element, absent for derivation
+    pub cardinality: Option<String>,
+}
+
+impl Diagram {
+    /// Build the diagram of `schema` merged with `bases`, ordered from the
+    /// direct base to the root. With `derivation`, each schema of the chain
+    /// is linked to its base.
+    pub fn new(schema: &FhirSchema, bases: &[&FhirSchema], derivation: bool) -> Self {
+        let doc = SchemaDoc::new(schema, bases);
+        let root_path = schema.type_name.as_str();
+        let root_id = sanitize(&schema.name);
+        let class_id = |path: &str| match path.strip_prefix(root_path) {
+            Some(rest) => format!("{root_id}{}", sanitize(rest)),
+            None => sanitize(path),
+        };
+
+        let mut classes = vec![DiagramClass {
+            id: root_id.clone(),
+            label: schema.name.clone(),
+            attributes: Vec::new(),
+        }];
+        let mut edges = Vec::new();
+
+        for (i, row) in doc.elements.iter().enumerate() {
+            let plain_path = row.path.trim_end_matches("[x]");
+            let (parent, name) = row
+                .path
+                .rsplit_once('.')
+                .unwrap_or((root_path, row.path.as_str()));
+            let parent_id = class_id(parent);
+            let has_children = doc
+                .elements
+                .get(i + 1)
+                .is_some_and(|next| next.depth > row.depth);
+
+            if has_children {
+                let id = class_id(plain_path);
+                classes.push(DiagramClass {
+                    id: id.clone(),
+                    label: row.path.replacen(root_path, &schema.name, 1),
+                    attributes: Vec::new(),
+                });
+                edges.push(DiagramEdge {
+                    from: parent_id,
+                    to: id,
+                    kind: EdgeKind::Composition,
+                    label: Some(name.to_string()),
+                    cardinality: Some(row.cardinality.clone()),
+                });
+                continue;
+            }
+
+            if let Some(class) = classes.iter_mut().find(|c| c.id == parent_id) {
+                class
+                    .attributes
+                    .push(format!("{name} : {} [{}]", row.types, row.cardinality));
+            }
+            for target in &row.targets {
+                edges.push(DiagramEdge {
+                    from: parent_id.clone(),
+                    to: sanitize(target),
+                    kind: EdgeKind::Reference,
+                    label: Some(name.to_string()),
+                    cardinality: Some(row.cardinality.clone()),
+                });
+            }
+        }
+
+        if derivation {
+            let mut derived = root_id;
+            for base in bases {
+                let id = sanitize(&base.name);
+                edges.push(DiagramEdge {
+                    from: derived,
+                    to: id.clone(),
+                    kind: EdgeKind::Derivation,
+                    label: None,
+                    cardinality: None,
+                });
+                derived = id;
+            }
+        }
+
+        Self {
+            name: schema.name.clone(),
+            classes,
+            edges,
+        }
+    }
+
+    /// Render as a Mermaid `classDiagram`.
+    pub fn to_mermaid(&self) -> String {
+        let mut out = String::from("classDiagram\n");
+        for class in &self.classes {
+            if class.label == class.id {
+                let _ = writeln!(out, "    class {} {{", class.id);
+            } else {
+                let _ = writeln!(
+                    out,
+                    "    class {}[\"{}\"] {{",
+                    class.id,
+                    class.label.replace('"', "'")
+                );
+            }
+            for attribute in &class.attributes {
+                let _ = writeln!(out, "        {}", mermaid_member(attribute));
+            }
+            out.push_str("    }\n");
+        }
+        for edge in &self.edges {
+            match edge.kind {
+                EdgeKind::Derivation => {
+                    let _ = writeln!(out, "    {} <|-- {}", edge.to, edge.from);
+                }
+                EdgeKind::Composition | EdgeKind::Reference => {
+                    let arrow = if edge.kind == EdgeKind::Composition {
+                        "*--"
+                    } else {
+                        "-->"
+                    };
+                    let _ = writeln!(
+                        out,
+                        "    {} {arrow} \"{}\" {} : {}",
+                        edge.from,
+                        edge.cardinality.as_deref().unwrap_or_default(),
+                        edge.to,
+                        edge.label.as_deref().unwrap_or_default()
+                    );
+                }
+            }
+        }
+        out
+    }
+
+    /// Render as a Graphviz `digraph` of record nodes.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "digraph \"{}\" {{", dot_string(&self.name));
+        out.push_str("    rankdir=LR;\n");
+        out.push_str("    node [shape=record, fontname=\"Helvetica\", fontsize=10];\n");
+        out.push_str("    edge [fontname=\"Helvetica\", fontsize=9];\n");
+        for class in &self.classes {
+            let mut label = dot_record(&class.label);
+            label.push('|');
+            for attribute in &class.attributes {
+                label.push_str(&dot_record(attribute));
+                label.push_str("\\l");
+            }
+            let _ = writeln!(out, "    \"{}\" [label=\"{{{label}}}\"];", class.id);
+        }
+        for edge in &self.edges {
+            let attrs = match edge.kind {
+                EdgeKind::Composition => "dir=back, arrowtail=diamond",
+                EdgeKind::Reference => "style=dashed",
+                EdgeKind::Derivation => "arrowhead=empty",
+            };
+            let label = match (&edge.label, &edge.cardinality) {
+                (Some(label), Some(card)) => format!(", label=\"{} {card}\"", dot_string(label)),
+                _ => String::new(),
+            };
+            let _ = writeln!(
+                out,
+                "    \"{}\" -> \"{}\" [{attrs}{label}];",
+                edge.from, edge.to
+            );
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+/// Keep ASCII letters, digits and `_`; map everything else to `_`.
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+/// Mermaid reads `(`, `)` and `~` in members as method and generic syntax.
+fn mermaid_member(attribute: &str) -> String {
+    attribute
+        .replace('(', "&lpar;")
+        .replace(')', "&rpar;")
+        .replace('~', "-")
+}
+
+fn dot_string(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Escape the characters that structure a record label.
+fn dot_record(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in dot_string(text).chars() {
+        if matches!(c, '{' | '}' | '|' | '<' | '>') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embedded::{FhirVersion, get_schemas};
+
+    fn patient() -> Diagram {
+        let schemas = get_schemas(FhirVersion::R4).unwrap();
+        Diagram::new(
+            &schemas["Patient"],
+            &[&schemas["DomainResource"], &schemas["Resource"]],
+            true,
+        )
+    }
+
+    #[test]
+    fn test_patient_diagram() {
+        let diagram = patient();
+        assert_eq!(diagram.classes[0].id, "Patient");
+        assert!(
+            diagram.classes[0]
+                .attributes
+                .contains(&"gender : code [0..1]".to_string())
+        );
+
+        let contact = diagram
+            .classes
+            .iter()
+            .find(|c| c.id == "Patient_contact")
+            .unwrap();
+        assert_eq!(contact.label, "Patient.contact");
+        assert!(
+            contact
+                .attributes
+                .iter()
+                .any(|a| a.starts_with("name : HumanName"))
+        );
+
+        assert!(diagram.edges.contains(&DiagramEdge {
+            from: "Patient".into(),
+            to: "Organization".into(),
+            kind: EdgeKind::Reference,
+            label: Some("managingOrganization".into()),
+            cardinality: Some("0..1".into()),
+        }));
+        assert!(diagram.edges.iter().any(|e| e.kind == EdgeKind::Derivation
+            && e.from == "DomainResource"
+            && e.to == "Resource"));
+    }
+
+    #[test]
+    fn test_render_formats() {
+        let diagram = patient();
+
+        let mermaid = diagram.to_mermaid();
+        assert!(mermaid.starts_with("classDiagram\n"));
+        assert!(mermaid.contains("    class Patient_contact[\"Patient.contact\"] {"));
+        assert!(mermaid.contains("    Patient *-- \"0..*\" Patient_contact : contact"));
+        assert!(mermaid.contains("    DomainResource <|-- Patient"));
+
+        let dot = diagram.to_dot();
+        assert!(dot.starts_with("digraph \"Patient\" {"));
+        assert!(dot.contains("\"Patient\" -> \"Patient_contact\" [dir=back, arrowtail=diamond, label=\"contact 0..*\"];"));
+        assert!(dot.contains("\"Patient\" -> \"DomainResource\" [arrowhead=empty];"));
+        assert!(dot.trim_end().ends_with('}'));
+    }
+}
@@ -0,0 +1,668 @@
+//! Markdown and HTML documentation for FHIR Schemas.
+//!
+//! [`SchemaDoc`] flattens a schema and its base chain into the tables an
+//! implementation guide page shows: one row per element with flags,
+//! cardinality, types, binding and fixed value, followed by the invariants
+//! and a table per sliced element. A profile only states what it changes,
+//! so every property is taken from the most derived schema in the chain that
+//! sets it, the way a snapshot is built from a differential.
+//!
+//! The HTML is a `<section>` fragment without styles, meant to be embedded
+//! in a page template of the publication pipeline.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use octofhir_fhirschema::docs::SchemaDoc;
+//!
+//! let doc = SchemaDoc::new(&us_core_patient, &[&patient, &domain_resource, &resource]);
+//! std::fs::write("us-core-patient.md", doc.to_markdown())?;
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+use serde::Serialize;
+
+use crate::types::{FhirSchema, FhirSchemaElement, FhirSchemaSlicing};
+
+/// A schema laid out for documentation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SchemaDoc {
+    /// Schema name
+    pub name: String,
+    /// Canonical URL
+    pub url: String,
+    /// resource | complex-type | primitive-type | logical
+    pub kind: String,
+    /// Canonical URL of the base schema
+    pub base: Option<String>,
+    /// Schema description
+    pub description: Option<String>,
+    /// Element rows in definition order, parents before children
+    pub elements: Vec<ElementRow>,
+    /// Invariants of the schema and its elements, most derived first
+    pub invariants: Vec<Invariant>,
+    /// Slicing of each sliced element
+    pub slicings: Vec<SlicingTable>,
+}
+
+/// One element of the element table.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ElementRow {
+    /// Dotted path including the type, e.g. `Patient.name.given`
+    pub path: String,
+    /// Nesting depth below the root, starting at 1
+    pub depth: usize,
+    /// `min..max`
+    pub cardinality: String,
+    /// Types, e.g. `string`, `Reference(Patient | Group)`, `Quantity | string`
+    pub types: String,
+    /// Target types of Reference and canonical types, e.g. `Patient`
+    pub targets: Vec<String>,
+    /// `S` (must support), `?!` (modifier) and `Σ` (summary), space separated
+    pub flags: String,
+    /// `strength: value set`
+    pub binding: Option<String>,
+    /// Fixed or pattern value as compact JSON
+    pub pattern: Option<String>,
+    /// Short description
+    pub short: Option<String>,
+}
+
+/// A FHIRPath invariant.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Invariant {
+    /// Constraint key, e.g. `pat-1`
+    pub key: String,
+    /// error | warning
+    pub severity: String,
+    /// Path of the element declaring it
+    pub path: String,
+    /// Human-readable description
+    pub human: String,
+    /// FHIRPath expression
+    pub expression: String,
+}
+
+/// The slicing of one element.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SlicingTable {
+    /// Path of the sliced element
+    pub path: String,
+    /// Discriminators as `type @ path`
+    pub discriminators: Vec<String>,
+    /// closed | open | openAtEnd
+    pub rules: String,
+    /// Whether slices must appear in order
+    pub ordered: bool,
+    /// Slices by name
+    pub slices: Vec<SliceRow>,
+}
+
+/// One slice of a [`SlicingTable`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SliceRow {
+    /// Slice name
+    pub name: String,
+    /// `min..max`
+    pub cardinality: String,
+    /// Value an item must match to belong to the slice, as compact JSON
+    pub matches: Option<String>,
+}
+
+impl SchemaDoc {
+    /// Lay out `schema`, filling in what it leaves unstated from `bases`,
+    /// ordered from the direct base to the root.
+    pub fn new(schema: &FhirSchema, bases: &[&FhirSchema]) -> Self {
+        let chain: Vec<&FhirSchema> = std::iter::once(schema)
+            .chain(bases.iter().copied())
+            .collect();
+        let mut doc = Self {
+            name: schema.name.clone(),
+            url: schema.url.clone(),
+            kind: schema.kind.clone(),
+            base: schema.base.clone(),
+            description: schema.description.clone(),
+            elements: Vec::new(),
+            invariants: Vec::new(),
+            slicings: Vec::new(),
+        };
+
+        let root = schema.type_name.as_str();
+        let mut seen_invariants = HashSet::new();
+        for layer in &chain {
+            doc.add_invariants(layer.constraint.as_ref(), root, &mut seen_invariants);
+        }
+
+        let layers: Vec<_> = chain.iter().filter_map(|s| s.elements.as_ref()).collect();
+        let required: Vec<&str> = chain
+            .iter()
+            .flat_map(|s| s.required.iter().flatten())
+            .map(String::as_str)
+            .collect();
+        doc.add_elements(&layers, &required, root, 1, &mut seen_invariants);
+        doc
+    }
+
+    fn add_elements(
+        &mut self,
+        layers: &[&HashMap<String, FhirSchemaElement>],
+        required: &[&str],
+        parent: &str,
+        depth: usize,
+        seen_invariants: &mut HashSet<String>,
+    ) {
+        let mut names: Vec<(usize, &str)> = Vec::new();
+        for layer in layers {
+            for (name, element) in layer.iter() {
+                if element.choice_of.is_some() || names.iter().any(|(_, n)| n == name) {
+                    continue;
+                }
+                let index = layers
+                    .iter()
+                    .filter_map(|l| l.get(name).and_then(|e| e.index))
+                    .min()
+                    .unwrap_or(usize::MAX);
+                names.push((index, name.as_str()));
+            }
+        }
+        names.sort_unstable();
+
+        for (_, name) in names {
+            let defs: Vec<&FhirSchemaElement> = layers.iter().filter_map(|l| l.get(name)).collect();
+            let choices = defs.iter().find_map(|e| e.choices.as_ref());
+            let path = if choices.is_some() {
+                format!("{parent}.{name}[x]")
+            } else {
+                format!("{parent}.{name}")
+            };
+
+            let min = defs
+                .iter()
+                .find_map(|e| e.min)
+                .unwrap_or(i32::from(required.contains(&name)));
+            let max = match defs.iter().find_map(|e| e.max) {
+                Some(max) => max.to_string(),
+                None if defs.iter().find_map(|e| e.array) == Some(true) => "*".to_string(),
+                None => "1".to_string(),
+            };
+            let targets = match choices {
+                Some(choices) => {
+                    let variants: Vec<&FhirSchemaElement> = choices
+                        .iter()
+                        .filter_map(|choice| layers.iter().find_map(|l| l.get(choice)))
+                        .collect();
+                    let mut targets = reference_targets(&variants);
+                    targets.dedup();
+                    targets
+                }
+                None => reference_targets(&defs),
+            };
+            let types = match choices {
+                Some(choices) => choices
+                    .iter()
+                    .map(|choice| {
+                        layers
+                            .iter()
+                            .find_map(|l| l.get(choice).and_then(|e| e.type_name.as_deref()))
+                            .unwrap_or(choice.as_str())
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" | "),
+                None => element_type(&defs, &targets),
+            };
+            let mut flags = Vec::new();
+            if defs.iter().find_map(|e| e.must_support) == Some(true) {
+                flags.push("S");
+            }
+            if defs.iter().find_map(|e| e.is_modifier) == Some(true) {
+                flags.push("?!");
+            }
+            if defs.iter().find_map(|e| e.is_summary) == Some(true) {
+                flags.push("Σ");
+            }
+
+            self.elements.push(ElementRow {
+                path: path.clone(),
+                depth,
+                cardinality: format!("{min}..{max}"),
+                types,
+                targets,
+                flags: flags.join(" "),
+                binding: defs.iter().find_map(|e| e.binding.as_ref()).map(|b| {
+                    let target = b
+                        .value_set
+                        .as_deref()
+                        .or(b.binding_name.as_deref())
+                        .unwrap_or("(unspecified)");
+                    format!("{}: {target}", b.strength)
+                }),
+                pattern: defs
+                    .iter()
+                    .find_map(|e| e.pattern.as_ref())
+                    .map(|p| p.value.to_string()),
+                short: defs.iter().find_map(|e| e.short.clone()),
+            });
+
+            for def in &defs {
+                self.add_invariants(def.constraint.as_ref(), &path, seen_invariants);
+            }
+            if let Some(slicing) = defs.iter().find_map(|e| e.slicing.as_ref()) {
+                self.slicings.push(slicing_table(&path, slicing));
+            }
+
+            let children: Vec<_> = defs.iter().filter_map(|e| e.elements.as_ref()).collect();
+            if !children.is_empty() {
+                let child_required: Vec<&str> = defs
+                    .iter()
+                    .flat_map(|e| e.required.iter().flatten())
+                    .map(String::as_str)
+                    .collect();
+                let parent = path.trim_end_matches("[x]").to_string();
+                self.add_elements(
+                    &children,
+                    &child_required,
+                    &parent,
+                    depth + 1,
+                    seen_invariants,
+                );
+            }
+        }
+    }
+
+    fn add_invariants(
+        &mut self,
+        constraints: Option<&HashMap<String, crate::types::FhirSchemaConstraint>>,
+        path: &str,
+        seen: &mut HashSet<String>,
+    ) {
+        let Some(constraints) = constraints else {
+            return;
+        };
+        let mut keys: Vec<&String> = constraints.keys().collect();
+        keys.sort_unstable();
+        for key in keys {
+            if !seen.insert(key.clone()) {
+                continue;
+            }
+            let constraint = &constraints[key];
+            self.invariants.push(Invariant {
+                key: key.clone(),
+                severity: constraint.severity.clone(),
+                path: path.to_string(),
+                human: constraint.human.clone(),
+                expression: constraint.expression.clone(),
+            });
+        }
+    }
+
+    /// Render as GitHub-flavoured Markdown.
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# {}\n", self.name);
+        if let Some(description) = &self.description {
+            let _ = writeln!(out, "{description}\n");
+        }
+        let _ = writeln!(out, "- URL: `{}`", self.url);
+        let _ = writeln!(out, "- Kind: {}", self.kind);
+        if let Some(base) = &self.base {
+            let _ = writeln!(out, "- Base: `{base}`");
+        }
+
+        out.push_str("\n## Elements\n\n");
+        out.push_str("| Path | Flags | Card. | Type | Description |\n");
+        out.push_str("| --- | --- | --- | --- | --- |\n");
+        for row in &self.elements {
+            let mut description = row.short.as_deref().map(md_cell).unwrap_or_default();
+            if let Some(binding) = &row.binding {
+                push_line(&mut description, &format!("Binding: {}", md_cell(binding)));
+            }
+            if let Some(pattern) = &row.pattern {
+                push_line(&mut description, &format!("Fixed: `{}`", md_cell(pattern)));
+            }
+            let _ = writeln!(
+                out,
+                "| {} | {} | {} | {} | {} |",
+                md_cell(&row.path),
+                row.flags,
+                row.cardinality,
+                md_cell(&row.types),
+                description
+            );
+        }
+
+        if !self.invariants.is_empty() {
+            out.push_str("\n## Invariants\n\n");
+            out.push_str("| Key | Severity | Path | Description | Expression |\n");
+            out.push_str("| --- | --- | --- | --- | --- |\n");
+            for inv in &self.invariants {
+                let _ = writeln!(
+                    out,
+                    "| {} | {} | {} | {} | `{}` |",
+                    inv.key,
+                    inv.severity,
+                    md_cell(&inv.path),
+                    md_cell(&inv.human),
+                    md_cell(&inv.expression)
+                );
+            }
+        }
+
+        if !self.slicings.is_empty() {
+            out.push_str("\n## Slicing\n");
+            for slicing in &self.slicings {
+                let _ = writeln!(out, "\n### {}\n", slicing.path);
+                let _ = writeln!(
+                    out,
+                    "Discriminators: {}; rules: {}{}\n",
+                    slicing.discriminators.join(", "),
+                    slicing.rules,
+                    if slicing.ordered { ", ordered" } else { "" }
+                );
+                out.push_str("| Slice | Card. | Match |\n");
+                out.push_str("| --- | --- | --- |\n");
+                for slice in &slicing.slices {
+                    let matches = slice
+                        .matches
+                        .as_deref()
+                        .map(|m| format!("`{}`", md_cell(m)))
+                        .unwrap_or_default();
+                    let _ = writeln!(
+                        out,
+                        "| {} | {} | {} |",
+                        md_cell(&slice.name),
+                        slice.cardinality,
+                        matches
+                    );
+                }
+            }
+        }
+        out
+    }
+
+    /// Render as an HTML `<section>` fragment.
+    pub fn to_html(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "<section class=\"fhirschema-doc\">");
+        let _ = writeln!(out, "<h1>{}</h1>", html(&self.name));
+        if let Some(description) = &self.description {
+            let _ = writeln!(out, "<p>{}</p>", html(description));
+        }
+        out.push_str("<dl>\n");
+        let _ = writeln!(out, "<dt>URL</dt><dd><code>{}</code></dd>", html(&self.url));
+        let _ = writeln!(out, "<dt>Kind</dt><dd>{}</dd>", html(&self.kind));
+        if let Some(base) = &self.base {
+            let _ = writeln!(out, "<dt>Base</dt><dd><code>{}</code></dd>", html(base));
+        }
+        out.push_str("</dl>\n");
+
+        out.push_str("<h2>Elements</h2>\n<table class=\"elements\">\n");
+        out.push_str(
+            "<tr><th>Path</th><th>Flags</th><th>Card.</th><th>Type</th><th>Description</th></tr>\n",
+        );
+        for row in &self.elements {
+            let mut description = row.short.as_deref().map(html).unwrap_or_default();
+            if let Some(binding) = &row.binding {
+                push_line(&mut description, &format!("Binding: {}", html(binding)));
+            }
+            if let Some(pattern) = &row.pattern {
+                push_line(
+                    &mut description,
+                    &format!("Fixed: <code>{}</code>", html(pattern)),
+                );
+            }
+            let _ = writeln!(
+                out,
+                "<tr><td style=\"padding-left: {}em\">{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                row.depth - 1,
+                html(&row.path),
+                html(&row.flags),
+                row.cardinality,
+                html(&row.types),
+                description
+            );
+        }
+        out.push_str("</table>\n");
+
+        if !self.invariants.is_empty() {
+            out.push_str("<h2>Invariants</h2>\n<table class=\"invariants\">\n");
+            out.push_str("<tr><th>Key</th><th>Severity</th><th>Path</th><th>Description</th><th>Expression</th></tr>\n");
+            for inv in &self.invariants {
+                let _ = writeln!(
+                    out,
+                    "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td><code>{}</code></td></tr>",
+                    html(&inv.key),
+                    html(&inv.severity),
+                    html(&inv.path),
+                    html(&inv.human),
+                    html(&inv.expression)
+                );
+            }
+            out.push_str("</table>\n");
+        }
+
+        if !self.slicings.is_empty() {
+            out.push_str("<h2>Slicing</h2>\n");
+            for slicing in &self.slicings {
+                let _ = writeln!(out, "<h3>{}</h3>", html(&slicing.path));
+                let _ = writeln!(
+                    out,
+                    "<p>Discriminators: {}; rules: {}{}</p>",
+                    html(&slicing.discriminators.join(", ")),
+                    html(&slicing.rules),
+                    if slicing.ordered { ", ordered" } else { "" }
+                );
+                out.push_str("<table class=\"slices\">\n<tr><th>Slice</th><th>Card.</th><th>Match</th></tr>\n");
+                for slice in &slicing.slices {
+                    let matches = slice
+                        .matches
+                        .as_deref()
+                        .map(|m| format!("<code>{}</code>", html(m)))
+                        .unwrap_or_default();
+                    let _ = writeln!(
+                        out,
+                        "<tr><td>{}</td><td>{}</td><td>{}</td></tr>",
+                        html(&slice.name),
+                        slice.cardinality,
+                        matches
+                    );
+                }
+                out.push_str("</table>\n");
+            }
+        }
+        out.push_str("</section>\n");
+        out
+    }
+}
+
+/// Type column for a non-choice element: its type, with the target types of
+/// references spelled out, or `BackboneElement` for inline structures.
+fn element_type(defs: &[&FhirSchemaElement], targets: &[String]) -> String {
+    let Some(type_name) = defs.iter().find_map(|e| e.type_name.as_deref()) else {
+        return match defs.iter().find_map(|e| e.element_reference.as_ref()) {
+            Some(reference) => format!(
+                "see {}",
+                reference
+                    .iter()
+                    .skip(1)
+                    .filter(|s| *s != "elements")
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(".")
+            ),
+            None => "BackboneElement".to_string(),
+        };
+    };
+    if targets.is_empty() {
+        type_name.to_string()
+    } else {
+        format!("{type_name}({})", targets.join(" | "))
+    }
+}
+
+/// Type names of the profiles a reference may point to, taken from the
+/// last segment of each target URL.
+fn reference_targets(defs: &[&FhirSchemaElement]) -> Vec<String> {
+    defs.iter()
+        .find_map(|e| e.refers.as_ref())
+        .into_iter()
+        .flatten()
+        .map(|url| url.rsplit('/').next().unwrap_or(url).to_string())
+        .collect()
+}
+
+fn slicing_table(path: &str, slicing: &FhirSchemaSlicing) -> SlicingTable {
+    let mut slices: Vec<SliceRow> = slicing
+        .slices
+        .iter()
+        .flatten()
+        .map(|(name, slice)| SliceRow {
+            name: name.clone(),
+            cardinality: format!(
+                "{}..{}",
+                slice.min.unwrap_or(0),
+                slice
+                    .max
+                    .map_or_else(|| "*".to_string(), |max| max.to_string())
+            ),
+            matches: slice.match_value.as_ref().map(|m| m.to_string()),
+        })
+        .collect();
+    slices.sort_by(|a, b| a.name.cmp(&b.name));
+    SlicingTable {
+        path: path.to_string(),
+        discriminators: slicing
+            .discriminator
+            .iter()
+            .flatten()
+            .map(|d| format!("{} @ {}", d.type_name, d.path))
+            .collect(),
+        rules: slicing.rules.clone().unwrap_or_else(|| "open".to_string()),
+        ordered: slicing.ordered.unwrap_or(false),
+        slices,
+    }
+}
+
+fn push_line(cell: &mut String, line: &str) {
+    if !cell.is_empty() {
+        cell.push_str("<br>");
+    }
+    cell.push_str(line);
+}
+
+fn md_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', "<br>")
+}
+
+fn html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embedded::{FhirVersion, get_schemas};
+    use serde_json::json;
+
+    fn profile() -> FhirSchema {
+        serde_json::from_value(json!({
+            "url": "http://example.org/StructureDefinition/doc-patient",
+            "name": "DocPatient",
+            "type": "Patient",
+            "kind": "resource",
+            "derivation": "constraint",
+            "base": "http://hl7.org/fhir/StructureDefinition/Patient",
+            "class": "profile",
+            "description": "Patient with <b>an</b> MRN",
+            "required": ["identifier"],
+            "constraint": {
+                "doc-1": {
+                    "expression": "name.exists() or identifier.exists()",
+                    "human": "Name | identifier",
+                    "severity": "error"
+                }
+            },
+            "elements": {
+                "identifier": {
+                    "mustSupport": true,
+                    "slicing": {
+                        "discriminator": [{"type": "pattern", "path": "system"}],
+                        "rules": "open",
+                        "slices": {
+                            "mrn": {
+                                "match": {"system": "http://example.org/mrn"},
+                                "min": 1,
+                                "max": 1
+                            }
+                        }
+                    }
+                },
+                "gender": {"pattern": {"type": "code", "value": "female"}}
+            }
+        }))
+        .unwrap()
+    }
+
+    fn doc() -> SchemaDoc {
+        let schemas = get_schemas(FhirVersion::R4).unwrap();
+        let profile = profile();
+        SchemaDoc::new(&profile, &[&schemas["Patient"]])
+    }
+
+    #[test]
+    fn test_profile_merges_with_base() {
+        let doc = doc();
+        let row = |path: &str| doc.elements.iter().find(|r| r.path == path).unwrap();
+
+        let identifier = row("Patient.identifier");
+        assert_eq!(identifier.cardinality, "1..*");
+        assert_eq!(identifier.types, "Identifier");
+        assert!(identifier.flags.contains('S'));
+        assert!(identifier.flags.contains('Σ'));
+
+        assert_eq!(row("Patient.gender").pattern.as_deref(), Some("\"female\""));
+        assert!(
+            row("Patient.gender")
+                .binding
+                .as_deref()
+                .unwrap()
+                .starts_with("required: ")
+        );
+        assert_eq!(row("Patient.deceased[x]").types, "boolean | dateTime");
+        assert!(
+            row("Patient.managingOrganization")
+                .types
+                .starts_with("Reference(Organization")
+        );
+        assert_eq!(row("Patient.contact.name").depth, 2);
+
+        assert_eq!(doc.invariants[0].key, "doc-1");
+        assert!(doc.invariants.iter().any(|i| i.key == "pat-1"));
+        assert_eq!(doc.slicings[0].slices[0].cardinality, "1..1");
+    }
+
+    #[test]
+    fn test_render_escapes() {
+        let doc = doc();
+        let markdown = doc.to_markdown();
+        assert!(markdown.starts_with("# DocPatient\n"));
+        assert!(markdown.contains("| Patient.identifier | S Σ | 1..* | Identifier |"));
+        assert!(markdown.contains("Name \\| identifier"));
+        assert!(markdown.contains("### Patient.identifier"));
+
+        let html = doc.to_html();
+        assert!(html.contains("Patient with &lt;b&gt;an&lt;/b&gt; MRN"));
+        assert!(html.trim_end().ends_with("</section>"));
+    }
+}
@@ -10,6 +10,7 @@ const BINDING_NAME_EXT: &str =
 const DEFAULT_TYPE_EXT: &str =
     "http://hl7.org/fhir/StructureDefinition/elementdefinition-defaulttype";
 const FHIR_TYPE_EXT: &str = "http://hl7.org/fhir/StructureDefinition/structuredefinition-fhir-type";
+const REGEX_EXT: &str = "http://hl7.org/fhir/StructureDefinition/regex";
 
 fn get_extension<'a>(
     extensions: &'a Option<Vec<StructureDefinitionExtension>>,
@@ -187,16 +188,18 @@ fn build_element_constraints(
         for constraint in constraints {
             constraint_map.insert(
                 constraint.key.clone(),
-                FhirSchemaConstraint {
-                    expression: constraint.expression.clone(),
-                    human: constraint.human.clone(),
-                    severity: constraint.severity.clone(),
-                },
+                FhirSchemaConstraint::from(constraint),
             );
         }
         result.constraint = Some(constraint_map);
     }
 
+    if let Some(condition) = &definition_element.condition
+        && !condition.is_empty()
+    {
+        result.condition = Some(condition.clone());
+    }
+
     result
 }
 
@@ -239,6 +242,37 @@ fn build_element_type(
     result
 }
 
+/// Carry the `regex` extension (on the element or its type) and the
+/// profiles of a primitive type into the element.
+fn build_element_primitive(
+    element: &FhirSchemaElement,
+    definition_element: &StructureDefinitionElement,
+) -> FhirSchemaElement {
+    let mut result = element.clone();
+    let types = definition_element.type_info.as_deref().unwrap_or_default();
+
+    result.regex = get_extension(&definition_element.extension, REGEX_EXT)
+        .or_else(|| {
+            types
+                .iter()
+                .find_map(|type_def| get_extension(&type_def.extension, REGEX_EXT))
+        })
+        .and_then(|ext| ext.value_string.clone());
+
+    // Primitive type codes start lowercase; complex types carry their
+    // profiles as slices and extension urls instead
+    let profiles: Vec<String> = types
+        .iter()
+        .filter(|type_def| type_def.code.starts_with(|c: char| c.is_ascii_lowercase()))
+        .flat_map(|type_def| type_def.profile.iter().flatten().cloned())
+        .collect();
+    if !profiles.is_empty() {
+        result.type_profile = Some(profiles);
+    }
+
+    result
+}
+
 fn build_element_extension(
     element: &FhirSchemaElement,
     definition_element: &StructureDefinitionElement,
@@ -392,9 +426,12 @@ pub fn transform_element(
         binding: None,
         pattern: None,
         constraint: None,
+        condition: None,
         elements: None,
         choice_of: element.choice_of.clone(),
         choices: element.choices.clone(),
+        regex: None,
+        type_profile: None,
         url: None,
         must_support: element.must_support,
         is_modifier: element.is_modifier,
@@ -417,6 +454,7 @@ pub fn transform_element(
     transformed = build_element_extension(&transformed, &preprocessed);
     transformed = build_element_cardinality(&transformed, &preprocessed);
     transformed = build_element_type(&transformed, &preprocessed, structure_definition);
+    transformed = build_element_primitive(&transformed, &preprocessed);
     process_patterns(&mut transformed, &element.pattern_fields);
 
     Ok(transformed)
@@ -451,6 +489,44 @@ mod tests {
         assert!(!is_array_element(&element2));
     }
 
+    #[test]
+    fn test_transform_primitive_constraints() {
+        let sd: StructureDefinition = serde_json::from_value(serde_json::json!({
+            "resourceType": "StructureDefinition", "url": "http://example.org/P",
+            "name": "P", "status": "active", "kind": "resource", "type": "Patient"
+        }))
+        .unwrap();
+        let element: StructureDefinitionElement = serde_json::from_value(serde_json::json!({
+            "path": "Patient.identifier.value",
+            "extension": [{"url": REGEX_EXT, "valueString": "MRN-[0-9]+"}],
+            "type": [{"code": "string", "profile": ["http://example.org/mrn-string"]}]
+        }))
+        .unwrap();
+        let transformed = transform_element(&element, &sd).unwrap();
+        assert_eq!(transformed.regex.as_deref(), Some("MRN-[0-9]+"));
+        assert_eq!(
+            transformed.type_profile,
+            Some(vec!["http://example.org/mrn-string".to_string()])
+        );
+
+        // Primitive type definitions carry the regex on their type
+        let element: StructureDefinitionElement = serde_json::from_value(serde_json::json!({
+            "path": "date.value",
+            "type": [{
+                "code": "http://hl7.org/fhirpath/System.String",
+                "extension": [
+                    {"url": FHIR_TYPE_EXT, "valueUrl": "date"},
+                    {"url": REGEX_EXT, "valueString": "[0-9]{4}"}
+                ]
+            }]
+        }))
+        .unwrap();
+        let transformed = transform_element(&element, &sd).unwrap();
+        assert_eq!(transformed.regex.as_deref(), Some("[0-9]{4}"));
+        assert_eq!(transformed.type_name.as_deref(), Some("date"));
+        assert!(transformed.type_profile.is_none());
+    }
+
     #[test]
     fn test_is_required_element() {
         let element = StructureDefinitionElement {
@@ -1,41 +1,388 @@
+//! Precompiled FHIR core schemas embedded in the binary.
+//!
+//! Each version's schemas are stored as a zstd-compressed JSON map of
+//! schema name to [`FhirSchema`]. Nothing is decompressed until a version is
+//! first used; after that, [`get_schema`], [`has_schema`] and
+//! [`get_schema_names`] only index the bundle and deserialize individual
+//! schemas on demand. [`EmbeddedSchemas`] does the same for a whole version
+//! and backs the embedded providers and validators, so they only deserialize
+//! the schemas they use. [`get_schemas`] still materializes the full map for
+//! callers that need all of them, while [`get_schema_closure`] deserializes
+//! just the schemas a set of types depends on.
+//!
+//! Each version is only embedded when its cargo feature (`embedded-r4`,
+//! `embedded-r4b`, `embedded-r5`, `embedded-r6`) is enabled; all four are on
+//! by default. Lookups for a version that was not compiled in find nothing,
+//! and [`get_schemas`] reports [`FhirSchemaError::VersionNotEmbedded`].
+//!
+//! Profiles from widely used implementation guides can be embedded the same
+//! way through the `profile-pack-*` features and looked up with
+//! [`get_profile_pack`]. A pack only holds the guide's own schemas; the
+//! core schemas of its FHIR version are still needed alongside it.
+//!
+//! Every core bundle ships with a [`SchemaManifest`] recording the package it
+//! was generated from and the SHA-256 of the embedded blob; manifests written
+//! by the schema generator also record when and by which converter version. [`verify_integrity`] checks the blob against it; with
+//! the `verify-embedded` feature this runs before a bundle is first decoded,
+//! and a bundle that fails it is refused. [`get_schemas`], [`get_schema_info`]
+//! and the embedded providers then report
+//! [`FhirSchemaError::IntegrityCheckFailed`], as they do for a bundle that
+//! does not decode, while lookups such as [`get_schema`] find nothing.
+//!
+//! With the `embedded-compiled` feature, already compiled forms of the core
+//! schemas are embedded too. [`get_compiled_schemas`] hands them to
+//! [`FhirValidator::with_precompiled_schemas`](crate::FhirValidator::with_precompiled_schemas)
+//! so the first validation of each type skips compilation.
+//!
+//! With the `embedded-search-params` feature, the core SearchParameters of
+//! each version are embedded as well and read with [`get_search_parameters`].
+//!
+//! Bundles generated outside the crate can be read at runtime with
+//! [`load_schema_bundle`], which accepts every format of
+//! [`crate::serialization`], either plain or zstd-compressed.
+
+#![cfg_attr(
+    not(any(
+        feature = "embedded-r4",
+        feature = "embedded-r4b",
+        feature = "embedded-r5",
+        feature = "embedded-r6",
+        feature = "profile-pack-us-core",
+        feature = "profile-pack-ips"
+    )),
+    allow(dead_code)
+)]
+
+use crate::error::{FhirSchemaError, Result};
+use crate::search_params::{SearchParameter, SearchParameterRegistry};
 use crate::types::{FhirSchema, ValidationContext};
-use once_cell::sync::Lazy;
+use crate::validation::{CompiledSchema, SchemaProvider, SharedCompiledSchema};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 
-// Precompiled schema constants - use precompiled JSON files
-pub static R4_SCHEMAS: &[u8] = include_bytes!("../precompiled_schemas/r4_schemas.json");
-pub static R4B_SCHEMAS: &[u8] = include_bytes!("../precompiled_schemas/r4b_schemas.json");
-pub static R5_SCHEMAS: &[u8] = include_bytes!("../precompiled_schemas/r5_schemas.json");
-pub static R6_SCHEMAS: &[u8] = include_bytes!("../precompiled_schemas/r6_schemas.json");
-
-// Lazy-loaded deserialized schemas
-static R4_SCHEMA_MAP: Lazy<HashMap<String, FhirSchema>> = Lazy::new(|| {
-    serde_json::from_slice::<HashMap<String, FhirSchema>>(R4_SCHEMAS).unwrap_or_else(|e| {
-        eprintln!("Failed to deserialize R4 schemas from JSON: {e}");
-        HashMap::new()
-    })
-});
+// Precompiled schema constants - zstd-compressed JSON generated by devtools
+#[cfg(feature = "embedded-r4")]
+pub static R4_SCHEMAS: &[u8] = include_bytes!("../precompiled_schemas/r4_schemas.json.zst");
+#[cfg(feature = "embedded-r4b")]
+pub static R4B_SCHEMAS: &[u8] = include_bytes!("../precompiled_schemas/r4b_schemas.json.zst");
+#[cfg(feature = "embedded-r5")]
+pub static R5_SCHEMAS: &[u8] = include_bytes!("../precompiled_schemas/r5_schemas.json.zst");
+#[cfg(feature = "embedded-r6")]
+pub static R6_SCHEMAS: &[u8] = include_bytes!("../precompiled_schemas/r6_schemas.json.zst");
 
-static R4B_SCHEMA_MAP: Lazy<HashMap<String, FhirSchema>> = Lazy::new(|| {
-    serde_json::from_slice::<HashMap<String, FhirSchema>>(R4B_SCHEMAS).unwrap_or_else(|e| {
-        eprintln!("Failed to deserialize R4B schemas from JSON: {e}");
-        HashMap::new()
-    })
-});
+#[cfg(feature = "embedded-r4")]
+static R4_BUNDLE: EmbeddedBundle = EmbeddedBundle::new(
+    "R4",
+    R4_SCHEMAS,
+    Some(include_str!("../precompiled_schemas/r4_manifest.json")),
+);
+#[cfg(feature = "embedded-r4b")]
+static R4B_BUNDLE: EmbeddedBundle = EmbeddedBundle::new(
+    "R4B",
+    R4B_SCHEMAS,
+    Some(include_str!("../precompiled_schemas/r4b_manifest.json")),
+);
+#[cfg(feature = "embedded-r5")]
+static R5_BUNDLE: EmbeddedBundle = EmbeddedBundle::new(
+    "R5",
+    R5_SCHEMAS,
+    Some(include_str!("../precompiled_schemas/r5_manifest.json")),
+);
+#[cfg(feature = "embedded-r6")]
+static R6_BUNDLE: EmbeddedBundle = EmbeddedBundle::new(
+    "R6",
+    R6_SCHEMAS,
+    Some(include_str!("../precompiled_schemas/r6_manifest.json")),
+);
 
-static R5_SCHEMA_MAP: Lazy<HashMap<String, FhirSchema>> = Lazy::new(|| {
-    serde_json::from_slice::<HashMap<String, FhirSchema>>(R5_SCHEMAS).unwrap_or_else(|e| {
-        eprintln!("Failed to deserialize R5 schemas from JSON: {e}");
-        HashMap::new()
-    })
-});
+/// A single schema entry: its raw JSON until first use, then the schema.
+struct LazySchema {
+    raw: Mutex<Option<Box<RawValue>>>,
+    schema: OnceCell<Option<Arc<FhirSchema>>>,
+}
 
-static R6_SCHEMA_MAP: Lazy<HashMap<String, FhirSchema>> = Lazy::new(|| {
-    serde_json::from_slice::<HashMap<String, FhirSchema>>(R6_SCHEMAS).unwrap_or_else(|e| {
-        eprintln!("Failed to deserialize R6 schemas from JSON: {e}");
-        HashMap::new()
-    })
-});
+/// The one field of a schema that listing by kind needs.
+#[derive(Deserialize)]
+struct SchemaKind {
+    kind: String,
+}
+
+impl LazySchema {
+    /// The schema, deserialized on first use; its raw JSON is dropped then.
+    fn schema(&self, label: &str, name: &str) -> Option<&Arc<FhirSchema>> {
+        self.schema
+            .get_or_init(|| {
+                let raw = self.raw.lock().unwrap_or_else(|e| e.into_inner()).take()?;
+                serde_json::from_str::<FhirSchema>(raw.get())
+                    .map(Arc::new)
+                    .map_err(|e| {
+                        eprintln!("Failed to deserialize {label} schema '{name}' from JSON: {e}")
+                    })
+                    .ok()
+            })
+            .as_ref()
+    }
+
+    /// The schema's `kind`, read without deserializing the rest of it.
+    fn kind(&self, label: &str, name: &str) -> Option<String> {
+        if let Some(schema) = self.schema.get() {
+            return schema.as_ref().map(|schema| schema.kind.clone());
+        }
+        let raw = self.raw.lock().unwrap_or_else(|e| e.into_inner());
+        match raw.as_ref() {
+            Some(raw) => serde_json::from_str::<SchemaKind>(raw.get())
+                .ok()
+                .map(|schema| schema.kind),
+            // Taken by a deserialization still in progress; wait for it
+            None => {
+                drop(raw);
+                self.schema(label, name).map(|schema| schema.kind.clone())
+            }
+        }
+    }
+}
+
+/// Compressed schema bundle for one FHIR version, decoded on first use.
+struct EmbeddedBundle {
+    label: &'static str,
+    compressed: &'static [u8],
+    manifest_json: Option<&'static str>,
+    manifest: OnceCell<Option<SchemaManifest>>,
+    /// The decoded entries, or why the bundle was refused
+    index: OnceCell<std::result::Result<HashMap<String, LazySchema>, String>>,
+    all: OnceCell<HashMap<String, FhirSchema>>,
+}
+
+impl EmbeddedBundle {
+    const fn new(
+        label: &'static str,
+        compressed: &'static [u8],
+        manifest_json: Option<&'static str>,
+    ) -> Self {
+        Self {
+            label,
+            compressed,
+            manifest_json,
+            manifest: OnceCell::new(),
+            index: OnceCell::new(),
+            all: OnceCell::new(),
+        }
+    }
+
+    /// The bundle's per-schema raw JSON entries, decoded on first use.
+    ///
+    /// The outcome is kept, so a bundle that fails verification or does not
+    /// decode reports the same error on every use.
+    fn index(&self) -> Result<&HashMap<String, LazySchema>> {
+        self.index
+            .get_or_init(|| self.decode())
+            .as_ref()
+            .map_err(|message| FhirSchemaError::integrity_check_failed(self.label, message))
+    }
+
+    /// Decompress the bundle and split it into per-schema raw JSON entries.
+    ///
+    /// Each entry keeps its own copy of its JSON until it is deserialized, so
+    /// the decompressed buffer is freed once the bundle is split.
+    fn decode(&self) -> std::result::Result<HashMap<String, LazySchema>, String> {
+        #[cfg(feature = "verify-embedded")]
+        self.check_hash()?;
+
+        let decoded = zstd::stream::decode_all(self.compressed)
+            .map_err(|e| format!("failed to decompress: {e}"))?;
+
+        let entries = serde_json::from_slice::<HashMap<String, Box<RawValue>>>(&decoded)
+            .map_err(|e| format!("failed to deserialize: {e}"))?;
+        Ok(entries
+            .into_iter()
+            .map(|(name, raw)| {
+                (
+                    name,
+                    LazySchema {
+                        raw: Mutex::new(Some(raw)),
+                        schema: OnceCell::new(),
+                    },
+                )
+            })
+            .collect())
+    }
+
+    fn manifest(&self) -> Option<&SchemaManifest> {
+        self.manifest
+            .get_or_init(|| {
+                let json = self.manifest_json?;
+                serde_json::from_str(json)
+                    .map_err(|e| eprintln!("Failed to parse {} schema manifest: {e}", self.label))
+                    .ok()
+            })
+            .as_ref()
+    }
+
+    /// Check the compressed blob against the SHA-256 recorded in the manifest.
+    fn verify(&self) -> Result<()> {
+        self.check_hash()
+            .map_err(|message| FhirSchemaError::integrity_check_failed(self.label, message))
+    }
+
+    fn check_hash(&self) -> std::result::Result<(), String> {
+        let expected = self
+            .manifest()
+            .map(|manifest| manifest.sha256.as_str())
+            .ok_or("no manifest embedded")?;
+        let actual = format!("{:x}", Sha256::digest(self.compressed));
+        if actual != expected {
+            return Err(format!("expected sha256 {expected}, got {actual}"));
+        }
+        Ok(())
+    }
+
+    fn get(&self, name: &str) -> Option<&FhirSchema> {
+        self.shared(name).map(Arc::as_ref)
+    }
+
+    fn shared(&self, name: &str) -> Option<&Arc<FhirSchema>> {
+        self.index().ok()?.get(name)?.schema(self.label, name)
+    }
+
+    fn all(&self) -> Result<&HashMap<String, FhirSchema>> {
+        Ok(self.collect_all(self.index()?))
+    }
+
+    fn collect_all(&self, index: &HashMap<String, LazySchema>) -> &HashMap<String, FhirSchema> {
+        self.all.get_or_init(|| {
+            index
+                .iter()
+                .filter_map(|(name, entry)| {
+                    let schema = entry.schema(self.label, name)?;
+                    Some((name.clone(), FhirSchema::clone(schema)))
+                })
+                .collect()
+        })
+    }
+
+    /// Name and kind of every schema, without deserializing the schemas.
+    fn kinds<'a>(
+        &'a self,
+        index: &'a HashMap<String, LazySchema>,
+    ) -> impl Iterator<Item = (&'a String, String)> {
+        index.iter().filter_map(|(name, entry)| {
+            let kind = entry.kind(self.label, name)?;
+            Some((name, kind))
+        })
+    }
+}
+
+/// The embedded schemas of one FHIR version, looked up one at a time.
+///
+/// Each schema is deserialized the first time it is asked for and then
+/// shared by every user of the version, so providers and validators built on
+/// this never hold a copy of the whole set. It is also a [`SchemaProvider`],
+/// serving core schemas by name or canonical URL.
+#[derive(Clone, Copy)]
+pub struct EmbeddedSchemas {
+    version: FhirVersion,
+    bundle: &'static EmbeddedBundle,
+    index: &'static HashMap<String, LazySchema>,
+}
+
+impl EmbeddedSchemas {
+    /// The embedded schemas of `version`.
+    ///
+    /// Returns [`FhirSchemaError::VersionNotEmbedded`] when the version's
+    /// `embedded-*` feature is disabled, and
+    /// [`FhirSchemaError::IntegrityCheckFailed`] when its bundle is refused.
+    pub fn new(version: FhirVersion) -> Result<Self> {
+        let bundle = bundle(version)
+            .ok_or_else(|| FhirSchemaError::version_not_embedded(version.as_str()))?;
+        Ok(Self {
+            version,
+            bundle,
+            index: bundle.index()?,
+        })
+    }
+
+    /// The FHIR version of these schemas
+    pub fn version(&self) -> FhirVersion {
+        self.version
+    }
+
+    /// Get a schema by name
+    pub fn get(&self, name: &str) -> Option<&'static FhirSchema> {
+        self.shared(name).map(Arc::as_ref)
+    }
+
+    /// Get a schema by canonical URL. Core schemas are named after the last
+    /// segment of their URL.
+    pub fn get_by_url(&self, url: &str) -> Option<&'static FhirSchema> {
+        let name = url.rsplit('/').next()?;
+        self.get(name).filter(|schema| schema.url == url)
+    }
+
+    fn shared(&self, name: &str) -> Option<&'static Arc<FhirSchema>> {
+        self.index.get(name)?.schema(self.bundle.label, name)
+    }
+
+    /// Whether a schema with this name is embedded
+    pub fn contains(&self, name: &str) -> bool {
+        self.index.contains_key(name)
+    }
+
+    /// Names of all embedded schemas
+    pub fn names(&self) -> impl Iterator<Item = &'static String> {
+        self.index.keys()
+    }
+
+    /// Number of embedded schemas
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    /// Whether no schemas are embedded
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Name and kind (`resource`, `complex-type`, ...) of every schema,
+    /// without deserializing the schemas.
+    pub fn kinds(&self) -> impl Iterator<Item = (&'static String, String)> {
+        self.bundle.kinds(self.index)
+    }
+
+    /// All schemas, deserializing the ones not used yet (see [`get_schemas`])
+    pub fn all(&self) -> &'static HashMap<String, FhirSchema> {
+        self.bundle.collect_all(self.index)
+    }
+}
+
+impl std::fmt::Debug for EmbeddedSchemas {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EmbeddedSchemas")
+            .field("version", &self.version)
+            .field("schemas", &self.index.len())
+            .finish()
+    }
+}
+
+#[async_trait::async_trait]
+impl SchemaProvider for EmbeddedSchemas {
+    async fn get_schema(&self, name: &str) -> Option<Arc<FhirSchema>> {
+        self.shared(name).cloned()
+    }
+
+    async fn get_schema_by_url(&self, url: &str) -> Option<Arc<FhirSchema>> {
+        if let Some(schema) = self.shared(url) {
+            return Some(Arc::clone(schema));
+        }
+        let name = url.rsplit('/').next()?;
+        self.shared(name)
+            .filter(|schema| schema.url == url)
+            .cloned()
+    }
+}
 
 /// FHIR version enumeration
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -70,62 +417,152 @@ impl FhirVersion {
 impl std::str::FromStr for FhirVersion {
     type Err = &'static str;
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
         Self::parse(s).ok_or("Invalid FHIR version")
     }
 }
 
 /// Get precompiled schemas for a specific FHIR version
-pub fn get_schemas(version: FhirVersion) -> &'static HashMap<String, FhirSchema> {
+///
+/// This deserializes every schema of the version on first call; prefer
+/// [`get_schema`] when only a few schemas are needed. Returns
+/// [`FhirSchemaError::VersionNotEmbedded`] when the version's `embedded-*`
+/// feature is disabled, and [`FhirSchemaError::IntegrityCheckFailed`] when
+/// the bundle is refused (see the [module docs](self)).
+pub fn get_schemas(version: FhirVersion) -> Result<&'static HashMap<String, FhirSchema>> {
+    bundle(version)
+        .ok_or_else(|| FhirSchemaError::version_not_embedded(version.as_str()))?
+        .all()
+}
+
+/// Get the provenance manifest of a version's embedded schemas
+pub fn get_schema_manifest(version: FhirVersion) -> Option<&'static SchemaManifest> {
+    bundle(version)?.manifest()
+}
+
+/// Verify a version's embedded schemas against their manifest hash
+pub fn verify_integrity(version: FhirVersion) -> Result<()> {
+    bundle(version)
+        .ok_or_else(|| FhirSchemaError::version_not_embedded(version.as_str()))?
+        .verify()
+}
+
+/// Whether schemas for a FHIR version are embedded in this build
+pub fn is_embedded(version: FhirVersion) -> bool {
+    bundle(version).is_some()
+}
+
+fn bundle(version: FhirVersion) -> Option<&'static EmbeddedBundle> {
+    #[allow(unreachable_patterns)]
     match version {
-        FhirVersion::R4 => &R4_SCHEMA_MAP,
-        FhirVersion::R4B => &R4B_SCHEMA_MAP,
-        FhirVersion::R5 => &R5_SCHEMA_MAP,
-        FhirVersion::R6 => &R6_SCHEMA_MAP,
+        #[cfg(feature = "embedded-r4")]
+        FhirVersion::R4 => Some(&R4_BUNDLE),
+        #[cfg(feature = "embedded-r4b")]
+        FhirVersion::R4B => Some(&R4B_BUNDLE),
+        #[cfg(feature = "embedded-r5")]
+        FhirVersion::R5 => Some(&R5_BUNDLE),
+        #[cfg(feature = "embedded-r6")]
+        FhirVersion::R6 => Some(&R6_BUNDLE),
+        _ => None,
     }
 }
 
 /// Get a specific schema by name for a FHIR version
 pub fn get_schema(version: FhirVersion, name: &str) -> Option<&'static FhirSchema> {
-    get_schemas(version).get(name)
+    bundle(version)?.get(name)
 }
 
 /// Get all available schema names for a FHIR version
 pub fn get_schema_names(version: FhirVersion) -> Vec<&'static String> {
-    get_schemas(version).keys().collect()
+    bundle(version)
+        .and_then(|bundle| bundle.index().ok())
+        .map(|index| index.keys().collect())
+        .unwrap_or_default()
 }
 
 /// Create a validation context from precompiled schemas
+///
+/// The context holds a copy of every schema, and is empty when the version
+/// is not embedded in this build. Validators look schemas up one at a time
+/// through [`EmbeddedSchemas`] instead.
 pub fn create_validation_context(version: FhirVersion) -> ValidationContext {
     ValidationContext {
-        schemas: get_schemas(version).clone(),
+        schemas: get_schemas(version).cloned().unwrap_or_default(),
     }
 }
 
 /// Check if a schema exists for a given resource type and version
 pub fn has_schema(version: FhirVersion, resource_type: &str) -> bool {
-    get_schemas(version).contains_key(resource_type)
+    bundle(version)
+        .and_then(|bundle| bundle.index().ok())
+        .is_some_and(|index| index.contains_key(resource_type))
+}
+
+/// Get the schemas `roots` depend on: the roots themselves, their base
+/// chains and the types of their elements, transitively. Only those schemas
+/// are deserialized, so a process that handles a few resource types does not
+/// pay for the whole version.
+///
+/// Unknown names are skipped; the map is empty when the version is not
+/// embedded in this build.
+pub fn get_schema_closure<S: AsRef<str>>(
+    version: FhirVersion,
+    roots: &[S],
+) -> HashMap<String, FhirSchema> {
+    let mut closure = HashMap::new();
+    let Some(bundle) = bundle(version) else {
+        return closure;
+    };
+    let mut pending: Vec<String> = roots.iter().map(|r| r.as_ref().to_string()).collect();
+    while let Some(name) = pending.pop() {
+        if closure.contains_key(&name) {
+            continue;
+        }
+        let Some(schema) = bundle.get(&name) else {
+            continue;
+        };
+        // Core bases and types are canonical URLs or names; both end in the name
+        let mut depends_on: Vec<&str> = schema.base.iter().map(String::as_str).collect();
+        let mut elements: Vec<_> = schema.elements.iter().flat_map(|e| e.values()).collect();
+        while let Some(element) = elements.pop() {
+            depends_on.extend(element.type_name.as_deref());
+            elements.extend(element.elements.iter().flat_map(|e| e.values()));
+        }
+        for dependency in depends_on {
+            let dependency = dependency.rsplit('/').next().unwrap_or(dependency);
+            if !closure.contains_key(dependency) {
+                pending.push(dependency.to_string());
+            }
+        }
+        closure.insert(name, schema.clone());
+    }
+    closure
 }
 
 /// Get schema information (counts, versions, etc.)
-pub fn get_schema_info(version: FhirVersion) -> SchemaInfo {
-    let schemas = get_schemas(version);
-    let resource_count = schemas
-        .values()
-        .filter(|s| matches!(s.kind.as_str(), "resource" | "complex-type"))
-        .count();
-    let primitive_count = schemas
-        .values()
-        .filter(|s| s.kind == "primitive-type")
-        .count();
-
-    SchemaInfo {
+///
+/// Fails like [`get_schemas`] when the version is not embedded or its bundle
+/// is refused.
+pub fn get_schema_info(version: FhirVersion) -> Result<SchemaInfo> {
+    let schemas = EmbeddedSchemas::new(version)?;
+    let mut resource_count = 0;
+    let mut primitive_count = 0;
+    for (_, kind) in schemas.kinds() {
+        match kind.as_str() {
+            "resource" | "complex-type" => resource_count += 1,
+            "primitive-type" => primitive_count += 1,
+            _ => {}
+        }
+    }
+
+    Ok(SchemaInfo {
         version,
         total_schemas: schemas.len(),
         resource_schemas: resource_count,
         primitive_schemas: primitive_count,
         data_type_schemas: schemas.len() - resource_count - primitive_count,
-    }
+        manifest: get_schema_manifest(version).cloned(),
+    })
 }
 
 #[derive(Debug, Clone)]
@@ -135,6 +572,30 @@ pub struct SchemaInfo {
     pub resource_schemas: usize,
     pub primitive_schemas: usize,
     pub data_type_schemas: usize,
+    /// Provenance of the embedded schemas, if a manifest was embedded
+    pub manifest: Option<SchemaManifest>,
+}
+
+/// Provenance record generated alongside each embedded schema bundle
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchemaManifest {
+    /// FHIR version identifier (`r4`, `r4b`, ...)
+    pub fhir_version: String,
+    /// FHIR package the schemas were generated from
+    pub package_name: String,
+    /// Version of that package
+    pub package_version: String,
+    /// Generation time (RFC 3339, UTC), when it was recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generated_at: Option<String>,
+    /// Version of this crate's converter that produced the schemas, when it
+    /// was recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub converter_version: Option<String>,
+    /// Number of schemas in the bundle
+    pub schema_count: usize,
+    /// Hex SHA-256 of the embedded (compressed) bundle
+    pub sha256: String,
 }
 
 impl SchemaInfo {
@@ -147,27 +608,321 @@ impl SchemaInfo {
         println!("  🏥 Resource types: {}", self.resource_schemas);
         println!("  🔤 Primitive types: {}", self.primitive_schemas);
         println!("  📋 Data types: {}", self.data_type_schemas);
+        if let Some(manifest) = &self.manifest {
+            let mut provenance = String::new();
+            if let Some(generated_at) = &manifest.generated_at {
+                provenance.push_str(&format!(" (generated {generated_at}"));
+            }
+            if let Some(converter) = &manifest.converter_version {
+                provenance.push_str(if provenance.is_empty() { " (" } else { " " });
+                provenance.push_str(&format!("by converter {converter}"));
+            }
+            if !provenance.is_empty() {
+                provenance.push(')');
+            }
+            println!(
+                "  📦 Package: {}#{}{provenance}",
+                manifest.package_name, manifest.package_version
+            );
+        }
     }
 }
 
 /// Utility function to list all available resources for a version
 pub fn list_resources(version: FhirVersion) -> Vec<&'static String> {
-    get_schemas(version)
-        .iter()
-        .filter(|(_, schema)| matches!(schema.kind.as_str(), "resource" | "complex-type"))
+    let Ok(schemas) = EmbeddedSchemas::new(version) else {
+        return Vec::new();
+    };
+    schemas
+        .kinds()
+        .filter(|(_, kind)| matches!(kind.as_str(), "resource" | "complex-type"))
         .map(|(name, _)| name)
         .collect()
 }
 
 /// Utility function to list all primitive types for a version
 pub fn list_primitives(version: FhirVersion) -> Vec<&'static String> {
-    get_schemas(version)
-        .iter()
-        .filter(|(_, schema)| schema.kind == "primitive-type")
+    let Ok(schemas) = EmbeddedSchemas::new(version) else {
+        return Vec::new();
+    };
+    schemas
+        .kinds()
+        .filter(|(_, kind)| kind == "primitive-type")
         .map(|(name, _)| name)
         .collect()
 }
 
+// ============================================================================
+// Precompiled CompiledSchema artifacts
+// ============================================================================
+
+/// Compressed map of compiled schemas for one FHIR version.
+#[cfg_attr(not(feature = "embedded-compiled"), allow(dead_code))]
+struct CompiledBundle {
+    label: &'static str,
+    compressed: &'static [u8],
+    all: OnceCell<HashMap<String, SharedCompiledSchema>>,
+}
+
+#[cfg_attr(not(feature = "embedded-compiled"), allow(dead_code))]
+impl CompiledBundle {
+    const fn new(label: &'static str, compressed: &'static [u8]) -> Self {
+        Self {
+            label,
+            compressed,
+            all: OnceCell::new(),
+        }
+    }
+
+    fn all(&self) -> &HashMap<String, SharedCompiledSchema> {
+        self.all.get_or_init(|| {
+            let decoded = match zstd::stream::decode_all(self.compressed) {
+                Ok(decoded) => decoded,
+                Err(e) => {
+                    eprintln!("Failed to decompress {} compiled schemas: {e}", self.label);
+                    return HashMap::new();
+                }
+            };
+            match serde_json::from_slice::<HashMap<String, CompiledSchema>>(&decoded) {
+                Ok(schemas) => schemas
+                    .into_iter()
+                    .map(|(name, schema)| (name, Arc::new(schema)))
+                    .collect(),
+                Err(e) => {
+                    eprintln!("Failed to deserialize {} compiled schemas: {e}", self.label);
+                    HashMap::new()
+                }
+            }
+        })
+    }
+}
+
+#[cfg(all(feature = "embedded-compiled", feature = "embedded-r4"))]
+static R4_COMPILED: CompiledBundle = CompiledBundle::new(
+    "R4",
+    include_bytes!("../precompiled_schemas/r4_compiled.json.zst"),
+);
+#[cfg(all(feature = "embedded-compiled", feature = "embedded-r4b"))]
+static R4B_COMPILED: CompiledBundle = CompiledBundle::new(
+    "R4B",
+    include_bytes!("../precompiled_schemas/r4b_compiled.json.zst"),
+);
+#[cfg(all(feature = "embedded-compiled", feature = "embedded-r5"))]
+static R5_COMPILED: CompiledBundle = CompiledBundle::new(
+    "R5",
+    include_bytes!("../precompiled_schemas/r5_compiled.json.zst"),
+);
+#[cfg(all(feature = "embedded-compiled", feature = "embedded-r6"))]
+static R6_COMPILED: CompiledBundle = CompiledBundle::new(
+    "R6",
+    include_bytes!("../precompiled_schemas/r6_compiled.json.zst"),
+);
+
+fn compiled_bundle(version: FhirVersion) -> Option<&'static CompiledBundle> {
+    #[allow(unreachable_patterns)]
+    match version {
+        #[cfg(all(feature = "embedded-compiled", feature = "embedded-r4"))]
+        FhirVersion::R4 => Some(&R4_COMPILED),
+        #[cfg(all(feature = "embedded-compiled", feature = "embedded-r4b"))]
+        FhirVersion::R4B => Some(&R4B_COMPILED),
+        #[cfg(all(feature = "embedded-compiled", feature = "embedded-r5"))]
+        FhirVersion::R5 => Some(&R5_COMPILED),
+        #[cfg(all(feature = "embedded-compiled", feature = "embedded-r6"))]
+        FhirVersion::R6 => Some(&R6_COMPILED),
+        _ => None,
+    }
+}
+
+/// Get the precompiled forms of a version's embedded schemas, keyed by name
+///
+/// Returns [`FhirSchemaError::VersionNotEmbedded`] unless both
+/// `embedded-compiled` and the version's `embedded-*` feature are enabled.
+pub fn get_compiled_schemas(
+    version: FhirVersion,
+) -> Result<&'static HashMap<String, SharedCompiledSchema>> {
+    compiled_bundle(version)
+        .map(CompiledBundle::all)
+        .ok_or_else(|| FhirSchemaError::version_not_embedded(version.as_str()))
+}
+
+// ============================================================================
+// Search parameters
+// ============================================================================
+
+/// Compressed list of the core SearchParameters of one FHIR version.
+#[cfg_attr(not(feature = "embedded-search-params"), allow(dead_code))]
+struct SearchParameterBundle {
+    label: &'static str,
+    compressed: &'static [u8],
+    registry: OnceCell<SearchParameterRegistry>,
+}
+
+#[cfg_attr(not(feature = "embedded-search-params"), allow(dead_code))]
+impl SearchParameterBundle {
+    const fn new(label: &'static str, compressed: &'static [u8]) -> Self {
+        Self {
+            label,
+            compressed,
+            registry: OnceCell::new(),
+        }
+    }
+
+    fn registry(&self) -> &SearchParameterRegistry {
+        self.registry.get_or_init(|| {
+            let decoded = match zstd::stream::decode_all(self.compressed) {
+                Ok(decoded) => decoded,
+                Err(e) => {
+                    eprintln!("Failed to decompress {} search parameters: {e}", self.label);
+                    return SearchParameterRegistry::new();
+                }
+            };
+            match serde_json::from_slice::<Vec<SearchParameter>>(&decoded) {
+                Ok(params) => params.into_iter().collect(),
+                Err(e) => {
+                    eprintln!(
+                        "Failed to deserialize {} search parameters: {e}",
+                        self.label
+                    );
+                    SearchParameterRegistry::new()
+                }
+            }
+        })
+    }
+}
+
+#[cfg(all(feature = "embedded-search-params", feature = "embedded-r4"))]
+static R4_SEARCH_PARAMS: SearchParameterBundle = SearchParameterBundle::new(
+    "R4",
+    include_bytes!("../precompiled_schemas/r4_search_parameters.json.zst"),
+);
+#[cfg(all(feature = "embedded-search-params", feature = "embedded-r4b"))]
+static R4B_SEARCH_PARAMS: SearchParameterBundle = SearchParameterBundle::new(
+    "R4B",
+    include_bytes!("../precompiled_schemas/r4b_search_parameters.json.zst"),
+);
+#[cfg(all(feature = "embedded-search-params", feature = "embedded-r5"))]
+static R5_SEARCH_PARAMS: SearchParameterBundle = SearchParameterBundle::new(
+    "R5",
+    include_bytes!("../precompiled_schemas/r5_search_parameters.json.zst"),
+);
+#[cfg(all(feature = "embedded-search-params", feature = "embedded-r6"))]
+static R6_SEARCH_PARAMS: SearchParameterBundle = SearchParameterBundle::new(
+    "R6",
+    include_bytes!("../precompiled_schemas/r6_search_parameters.json.zst"),
+);
+
+fn search_parameter_bundle(version: FhirVersion) -> Option<&'static SearchParameterBundle> {
+    #[allow(unreachable_patterns)]
+    match version {
+        #[cfg(all(feature = "embedded-search-params", feature = "embedded-r4"))]
+        FhirVersion::R4 => Some(&R4_SEARCH_PARAMS),
+        #[cfg(all(feature = "embedded-search-params", feature = "embedded-r4b"))]
+        FhirVersion::R4B => Some(&R4B_SEARCH_PARAMS),
+        #[cfg(all(feature = "embedded-search-params", feature = "embedded-r5"))]
+        FhirVersion::R5 => Some(&R5_SEARCH_PARAMS),
+        #[cfg(all(feature = "embedded-search-params", feature = "embedded-r6"))]
+        FhirVersion::R6 => Some(&R6_SEARCH_PARAMS),
+        _ => None,
+    }
+}
+
+/// Get the core search parameters of a FHIR version
+///
+/// Returns [`FhirSchemaError::VersionNotEmbedded`] unless both
+/// `embedded-search-params` and the version's `embedded-*` feature are
+/// enabled.
+pub fn get_search_parameters(version: FhirVersion) -> Result<&'static SearchParameterRegistry> {
+    search_parameter_bundle(version)
+        .map(SearchParameterBundle::registry)
+        .ok_or_else(|| FhirSchemaError::version_not_embedded(version.as_str()))
+}
+
+// ============================================================================
+// Profile packs
+// ============================================================================
+
+/// An implementation guide profile pack that can be embedded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProfilePack {
+    /// Short pack name used for lookup, e.g. `us-core`
+    pub name: &'static str,
+    /// IG version, e.g. `6.1.0`
+    pub version: &'static str,
+    /// FHIR package the pack was generated from
+    pub package: &'static str,
+    /// FHIR version the IG is built on
+    pub fhir_version: FhirVersion,
+    /// Cargo feature that embeds the pack
+    pub feature: &'static str,
+}
+
+/// Profile packs known to this crate, whether or not they are embedded
+pub const PROFILE_PACKS: &[ProfilePack] = &[
+    ProfilePack {
+        name: "us-core",
+        version: "6.1.0",
+        package: "hl7.fhir.us.core",
+        fhir_version: FhirVersion::R4,
+        feature: "profile-pack-us-core",
+    },
+    ProfilePack {
+        name: "ips",
+        version: "1.1.0",
+        package: "hl7.fhir.uv.ips",
+        fhir_version: FhirVersion::R4,
+        feature: "profile-pack-ips",
+    },
+];
+
+#[cfg(feature = "profile-pack-us-core")]
+static US_CORE_6_1_0: EmbeddedBundle = EmbeddedBundle::new(
+    "us-core 6.1.0",
+    include_bytes!("../precompiled_schemas/packs/us-core-6.1.0.json.zst"),
+    None,
+);
+#[cfg(feature = "profile-pack-ips")]
+static IPS_1_1_0: EmbeddedBundle = EmbeddedBundle::new(
+    "ips 1.1.0",
+    include_bytes!("../precompiled_schemas/packs/ips-1.1.0.json.zst"),
+    None,
+);
+
+fn profile_pack_bundle(name: &str, version: &str) -> Option<&'static EmbeddedBundle> {
+    #[allow(unreachable_patterns)]
+    match (name, version) {
+        #[cfg(feature = "profile-pack-us-core")]
+        ("us-core", "6.1.0") => Some(&US_CORE_6_1_0),
+        #[cfg(feature = "profile-pack-ips")]
+        ("ips", "1.1.0") => Some(&IPS_1_1_0),
+        _ => None,
+    }
+}
+
+/// Get the schemas of an embedded profile pack, keyed by schema id
+///
+/// Returns [`FhirSchemaError::ProfilePackNotEmbedded`] for packs that are
+/// unknown or whose `profile-pack-*` feature is disabled, and
+/// [`FhirSchemaError::IntegrityCheckFailed`] when the pack does not decode.
+pub fn get_profile_pack(name: &str, version: &str) -> Result<&'static HashMap<String, FhirSchema>> {
+    profile_pack_bundle(name, version)
+        .ok_or_else(|| FhirSchemaError::profile_pack_not_embedded(name, version))?
+        .all()
+}
+
+/// List the profile packs embedded in this build
+pub fn list_profile_packs() -> Vec<&'static ProfilePack> {
+    PROFILE_PACKS
+        .iter()
+        .filter(|pack| profile_pack_bundle(pack.name, pack.version).is_some())
+        .collect()
+}
+
+// ============================================================================
+// Schema bundle files
+// ============================================================================
+
+pub use crate::serialization::{BundleFormat, decode_schema_bundle, load_schema_bundle};
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -193,18 +948,196 @@ mod tests {
         assert_eq!(FhirVersion::R5.as_str(), "r5");
     }
 
+    #[test]
+    fn test_bundle_format_from_file_name() {
+        assert_eq!(
+            BundleFormat::from_file_name("r4_schemas.json.zst"),
+            Some((BundleFormat::Json, true))
+        );
+        assert_eq!(
+            BundleFormat::from_file_name("r4_schemas.msgpack"),
+            Some((BundleFormat::MessagePack, false))
+        );
+        assert_eq!(BundleFormat::from_file_name("r4_schemas.bin"), None);
+    }
+
+    #[test]
+    fn test_decode_empty_json_bundle() {
+        let compressed = zstd::stream::encode_all(&b"{}"[..], 3).unwrap();
+        let schemas = decode_schema_bundle(&compressed, BundleFormat::Json, true).unwrap();
+        assert!(schemas.is_empty());
+        assert!(decode_schema_bundle(b"[", BundleFormat::Json, false).is_err());
+    }
+
     #[test]
     fn test_get_schemas() {
         // Test that we can get schemas (even if empty in test environment)
         let schemas = get_schemas(FhirVersion::R4);
-        assert!(schemas.is_empty() || !schemas.is_empty()); // Always true, just testing access
+        assert!(schemas.is_ok_and(|s| s.is_empty() || !s.is_empty())); // Just testing access
+    }
+
+    #[test]
+    fn test_lazy_schema_lookup() {
+        let names = get_schema_names(FhirVersion::R4);
+        assert!(has_schema(FhirVersion::R4, "Patient"));
+        assert!(names.iter().any(|name| name.as_str() == "Patient"));
+
+        let patient = get_schema(FhirVersion::R4, "Patient").expect("Patient schema");
+        assert_eq!(patient.name, "Patient");
+        assert!(get_schema(FhirVersion::R4, "NotAResource").is_none());
+
+        // The full map agrees with the per-schema lookup
+        assert_eq!(
+            get_schemas(FhirVersion::R4).unwrap()["Patient"].url,
+            patient.url
+        );
+    }
+
+    #[tokio::test]
+    async fn test_embedded_schemas_provider() {
+        let schemas = EmbeddedSchemas::new(FhirVersion::R4).unwrap();
+        let patient = schemas.get("Patient").expect("Patient schema");
+        assert_eq!(
+            schemas.get_by_url(&patient.url).map(|s| &s.name),
+            Some(&patient.name)
+        );
+        assert!(schemas.get_by_url("http://example.org/Patient").is_none());
+
+        // Every user shares the one deserialized schema
+        let via_provider = schemas.get_schema_by_url(&patient.url).await.unwrap();
+        assert!(std::ptr::eq(via_provider.as_ref(), patient));
+        assert!(schemas.get_schema("NotAResource").await.is_none());
+
+        let info = get_schema_info(FhirVersion::R4).unwrap();
+        assert_eq!(info.total_schemas, schemas.len());
+        assert_eq!(
+            info.primitive_schemas,
+            schemas
+                .kinds()
+                .filter(|(_, kind)| kind == "primitive-type")
+                .count()
+        );
+    }
+
+    #[test]
+    fn test_schema_closure() {
+        let closure = get_schema_closure(FhirVersion::R4, &["Patient", "NotAResource"]);
+        if !is_embedded(FhirVersion::R4) {
+            assert!(closure.is_empty());
+            return;
+        }
+
+        // Base chain, complex and primitive element types are pulled in
+        for name in [
+            "Patient",
+            "DomainResource",
+            "Resource",
+            "HumanName",
+            "string",
+        ] {
+            assert!(closure.contains_key(name), "{name} missing from closure");
+        }
+        assert!(!closure.contains_key("Observation"));
+        assert!(!closure.contains_key("NotAResource"));
+        assert!(closure.len() < get_schema_names(FhirVersion::R4).len());
+    }
+
+    #[test]
+    fn test_embedded_versions() {
+        assert_eq!(is_embedded(FhirVersion::R4), cfg!(feature = "embedded-r4"));
+        assert_eq!(is_embedded(FhirVersion::R6), cfg!(feature = "embedded-r6"));
+
+        for version in [
+            FhirVersion::R4,
+            FhirVersion::R4B,
+            FhirVersion::R5,
+            FhirVersion::R6,
+        ] {
+            if !is_embedded(version) {
+                assert!(matches!(
+                    get_schemas(version),
+                    Err(FhirSchemaError::VersionNotEmbedded { .. })
+                ));
+                assert!(get_schema_names(version).is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn test_profile_packs() {
+        for pack in PROFILE_PACKS {
+            let embedded = list_profile_packs().contains(&pack);
+            assert_eq!(get_profile_pack(pack.name, pack.version).is_ok(), embedded);
+        }
+
+        assert!(matches!(
+            get_profile_pack("us-core", "0.0.1"),
+            Err(FhirSchemaError::ProfilePackNotEmbedded { .. })
+        ));
+    }
+
+    #[test]
+    fn test_schema_manifest_integrity() {
+        let manifest = get_schema_manifest(FhirVersion::R4).expect("R4 manifest");
+        assert_eq!(manifest.package_name, "hl7.fhir.r4.core");
+        assert_eq!(
+            manifest.schema_count,
+            get_schema_names(FhirVersion::R4).len()
+        );
+        assert!(verify_integrity(FhirVersion::R4).is_ok());
     }
 
     #[test]
     fn test_schema_info() {
-        let info = get_schema_info(FhirVersion::R4);
+        let info = get_schema_info(FhirVersion::R4).unwrap();
         assert_eq!(info.version, FhirVersion::R4);
         // In test environment, schemas might be empty
         // Schema count should be meaningful (usize is always >= 0)
     }
+
+    /// A copy of the R4 bundle with its manifest and one byte flipped, or
+    /// cut in half.
+    #[cfg(feature = "embedded-r4")]
+    fn damaged_r4_bundle(truncate: bool) -> EmbeddedBundle {
+        let mut blob = R4_SCHEMAS.to_vec();
+        if truncate {
+            blob.truncate(blob.len() / 2);
+        } else {
+            let middle = blob.len() / 2;
+            blob[middle] ^= 0xff;
+        }
+        EmbeddedBundle::new(
+            "R4",
+            Box::leak(blob.into_boxed_slice()),
+            Some(include_str!("../precompiled_schemas/r4_manifest.json")),
+        )
+    }
+
+    #[cfg(all(feature = "embedded-r4", feature = "verify-embedded"))]
+    #[test]
+    fn test_tampered_bundle_is_refused() {
+        let bundle = damaged_r4_bundle(false);
+        assert!(matches!(
+            bundle.all(),
+            Err(FhirSchemaError::IntegrityCheckFailed { .. })
+        ));
+        // The failure is kept rather than decoding on a later call
+        assert!(matches!(
+            bundle.index(),
+            Err(FhirSchemaError::IntegrityCheckFailed { .. })
+        ));
+        assert!(bundle.get("Patient").is_none());
+    }
+
+    #[cfg(feature = "embedded-r4")]
+    #[test]
+    fn test_undecodable_bundle_is_an_error() {
+        let bundle = damaged_r4_bundle(true);
+        assert!(bundle.verify().is_err());
+        assert!(matches!(
+            bundle.all(),
+            Err(FhirSchemaError::IntegrityCheckFailed { .. })
+        ));
+        assert!(bundle.get("Patient").is_none());
+    }
 }
@@ -47,6 +47,32 @@ pub enum FhirSchemaError {
     #[error("Invalid FHIR version: {version}")]
     InvalidFhirVersion { version: String },
 
+    #[error(
+        "FHIR {version} schemas are not embedded in this build (enable the `embedded-{version}` feature)"
+    )]
+    VersionNotEmbedded { version: String },
+
+    #[error("Embedded {label} schemas failed integrity check: {message}")]
+    IntegrityCheckFailed { label: String, message: String },
+
+    #[error("Profile pack {name}#{version} is not embedded in this build")]
+    ProfilePackNotEmbedded { name: String, version: String },
+
+    #[error("Failed to decode {format} schema bundle: {message}")]
+    BundleDecodeError { format: String, message: String },
+
+    #[error("Failed to decode {format} schema: {message}")]
+    SchemaDecodeError { format: String, message: String },
+
+    #[error("Failed to encode {format} schema: {message}")]
+    SchemaEncodeError { format: String, message: String },
+
+    #[error("Invalid resource JSON: {message}")]
+    InvalidResourceJson { message: String },
+
+    #[error("Invalid resource XML: {message}")]
+    InvalidResourceXml { message: String },
+
     #[error("Schema compilation error: {message}")]
     CompilationError { message: String },
 
@@ -134,6 +160,59 @@ impl FhirSchemaError {
         }
     }
 
+    pub fn version_not_embedded<S: Into<String>>(version: S) -> Self {
+        Self::VersionNotEmbedded {
+            version: version.into(),
+        }
+    }
+
+    pub fn integrity_check_failed<L: Into<String>, M: Into<String>>(label: L, message: M) -> Self {
+        Self::IntegrityCheckFailed {
+            label: label.into(),
+            message: message.into(),
+        }
+    }
+
+    pub fn profile_pack_not_embedded<S: Into<String>>(name: S, version: S) -> Self {
+        Self::ProfilePackNotEmbedded {
+            name: name.into(),
+            version: version.into(),
+        }
+    }
+
+    pub fn bundle_decode_error<F: Into<String>, M: Into<String>>(format: F, message: M) -> Self {
+        Self::BundleDecodeError {
+            format: format.into(),
+            message: message.into(),
+        }
+    }
+
+    pub fn schema_decode_error<F: Into<String>, M: Into<String>>(format: F, message: M) -> Self {
+        Self::SchemaDecodeError {
+            format: format.into(),
+            message: message.into(),
+        }
+    }
+
+    pub fn schema_encode_error<F: Into<String>, M: Into<String>>(format: F, message: M) -> Self {
+        Self::SchemaEncodeError {
+            format: format.into(),
+            message: message.into(),
+        }
+    }
+
+    pub fn invalid_resource_json<S: Into<String>>(message: S) -> Self {
+        Self::InvalidResourceJson {
+            message: message.into(),
+        }
+    }
+
+    pub fn invalid_resource_xml<S: Into<String>>(message: S) -> Self {
+        Self::InvalidResourceXml {
+            message: message.into(),
+        }
+    }
+
     pub fn compilation_error<S: Into<String>>(message: S) -> Self {
         Self::CompilationError {
             message: message.into(),
@@ -0,0 +1,471 @@
+//! Catalog of every error code the validator reports.
+//!
+//! Codes come in three families: `FS` for structural schema validation
+//! ([`FhirSchemaErrorCode`]), `REF` for reference resolution
+//! ([`ReferenceErrorCode`]) and `VS` for terminology and bindings
+//! ([`TerminologyErrorCode`]). Each [`ErrorCodeInfo`] carries a description,
+//! the severity the code is reported with by default, the part of the FHIR
+//! specification it enforces and a hint on how to fix the input.
+//!
+//! ```ignore
+//! use octofhir_fhirschema::error_catalog;
+//!
+//! let info = error_catalog::lookup("fs1007").unwrap();
+//! println!("{}: {}\n{}", info.code, info.description, info.remediation);
+//! ```
+
+use serde::Serialize;
+
+use crate::reference::ReferenceErrorCode;
+use crate::terminology::TerminologyErrorCode;
+use crate::validation::FhirSchemaErrorCode;
+
+use DefaultSeverity::{Error, Warning};
+use ErrorFamily::{Reference, Schema, Terminology};
+
+/// Which part of validation reports a code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ErrorFamily {
+    /// `FS` codes: structure, cardinality, types, slicing, constraints
+    Schema,
+    /// `REF` codes: resolving references to other resources
+    Reference,
+    /// `VS` codes: code systems, value sets and bindings
+    Terminology,
+}
+
+/// Severity a code is reported with unless the definition that triggers it
+/// says otherwise (e.g. a constraint with `severity: warning`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DefaultSeverity {
+    Error,
+    Warning,
+}
+
+/// Machine-readable description of one error code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ErrorCodeInfo {
+    /// The code as reported in `ValidationError::error_type`, e.g. `FS1007`
+    pub code: &'static str,
+    /// Variant name of the code enum, e.g. `SlicingUnmatched`
+    pub name: &'static str,
+    pub family: ErrorFamily,
+    /// What the code means
+    pub description: &'static str,
+    pub severity: DefaultSeverity,
+    /// Page of the FHIR specification the check enforces
+    pub spec: &'static str,
+    /// How to make the input valid
+    pub remediation: &'static str,
+}
+
+const fn info(
+    code: &'static str,
+    name: &'static str,
+    family: ErrorFamily,
+    severity: DefaultSeverity,
+    description: &'static str,
+    spec: &'static str,
+    remediation: &'static str,
+) -> ErrorCodeInfo {
+    ErrorCodeInfo {
+        code,
+        name,
+        family,
+        description,
+        severity,
+        spec,
+        remediation,
+    }
+}
+
+static CATALOG: &[ErrorCodeInfo] = &[
+    info(
+        "FS1001",
+        "UnknownElement",
+        Schema,
+        Error,
+        "The resource contains an element that is not defined by its type or profile.",
+        "https://hl7.org/fhir/R4/json.html",
+        "Remove the element, fix its spelling, or carry the data in an extension.",
+    ),
+    info(
+        "FS1002",
+        "UnknownSchema",
+        Schema,
+        Error,
+        "The resource type or a requested profile has no loaded schema.",
+        "https://hl7.org/fhir/R4/structuredefinition.html",
+        "Check the resourceType or profile URL, or load the package that defines the profile.",
+    ),
+    info(
+        "FS1003",
+        "ExpectedArray",
+        Schema,
+        Error,
+        "A repeating element (max > 1) holds a single value instead of an array.",
+        "https://hl7.org/fhir/R4/json.html",
+        "Wrap the value in a JSON array, even when there is only one.",
+    ),
+    info(
+        "FS1004",
+        "UnexpectedArray",
+        Schema,
+        Error,
+        "A non-repeating element (max = 1) holds an array.",
+        "https://hl7.org/fhir/R4/json.html",
+        "Send a single value instead of an array.",
+    ),
+    info(
+        "FS1005",
+        "UnknownKeyword",
+        Schema,
+        Error,
+        "A schema uses a keyword the validator does not understand.",
+        "https://fhir-schema.github.io/fhir-schema/",
+        "Regenerate the schema with a matching converter version.",
+    ),
+    info(
+        "FS1006",
+        "WrongType",
+        Schema,
+        Error,
+        "A value has the wrong JSON type for its FHIR data type, e.g. a string for a boolean.",
+        "https://hl7.org/fhir/R4/datatypes.html",
+        "Send the value with the JSON type of its data type (see the expected type in the error).",
+    ),
+    info(
+        "FS1007",
+        "SlicingUnmatched",
+        Schema,
+        Error,
+        "An item of a closed slicing matches none of the defined slices.",
+        "https://hl7.org/fhir/R4/profiling.html#slicing",
+        "Make the item match a slice discriminator, or remove it; closed slicing admits no other items.",
+    ),
+    info(
+        "FS1008",
+        "SlicingAmbiguous",
+        Schema,
+        Error,
+        "An item matches more than one slice, so the profile's discriminators do not separate them.",
+        "https://hl7.org/fhir/R4/profiling.html#slicing",
+        "Usually a profile defect: tighten the slice discriminators so each item matches at most one.",
+    ),
+    info(
+        "FS1009",
+        "SliceCardinality",
+        Schema,
+        Error,
+        "A slice has fewer or more items than its min..max allows.",
+        "https://hl7.org/fhir/R4/profiling.html#slicing",
+        "Add or remove items matching the slice named in the error.",
+    ),
+    info(
+        "FS1010",
+        "ConstraintViolation",
+        Schema,
+        Error,
+        "A FHIRPath invariant of the type or profile evaluated to false.",
+        "https://hl7.org/fhir/R4/conformance-rules.html#constraints",
+        "Read the constraint's human description and change the data so the expression holds.",
+    ),
+    info(
+        "FS1011",
+        "CardinalityViolation",
+        Schema,
+        Error,
+        "A required element is missing or an element repeats more often than allowed.",
+        "https://hl7.org/fhir/R4/conformance-rules.html#cardinality",
+        "Supply the missing element or drop the extra repetitions.",
+    ),
+    info(
+        "FS1012",
+        "BindingViolation",
+        Schema,
+        Error,
+        "A coded value is not in the value set of a required binding.",
+        "https://hl7.org/fhir/R4/terminologies.html#strength",
+        "Use a code from the bound value set; extensible bindings report a warning instead.",
+    ),
+    info(
+        "FS1013",
+        "ReferenceTypeViolation",
+        Schema,
+        Error,
+        "A reference points at a resource type the element does not allow.",
+        "https://hl7.org/fhir/R4/references.html",
+        "Reference one of the target types listed in the element definition.",
+    ),
+    info(
+        "FS1014",
+        "InvalidValue",
+        Schema,
+        Error,
+        "A primitive value does not match the lexical format of its type, e.g. a malformed date.",
+        "https://hl7.org/fhir/R4/datatypes.html#primitive",
+        "Format the value as the data type's regex requires.",
+    ),
+    info(
+        "FS1015",
+        "ReferenceNotFound",
+        Schema,
+        Error,
+        "The configured reference resolver could not find the referenced resource.",
+        "https://hl7.org/fhir/R4/references.html",
+        "Create the target first, fix the reference, or validate without a reference resolver.",
+    ),
+    info(
+        "FS1016",
+        "QuestionnaireViolation",
+        Schema,
+        Error,
+        "A QuestionnaireResponse does not conform to its Questionnaire.",
+        "https://hl7.org/fhir/R4/questionnaireresponse.html",
+        "Match answer types, repeats and answer options to the Questionnaire item named in the error.",
+    ),
+    info(
+        "FS1017",
+        "ReferenceTargetProfileMismatch",
+        Schema,
+        Error,
+        "A referenced resource does not conform to any targetProfile of the element.",
+        "https://hl7.org/fhir/R4/elementdefinition-definitions.html#ElementDefinition.type.targetProfile",
+        "Reference a resource that conforms to one of the required target profiles.",
+    ),
+    info(
+        "FS1018",
+        "CapabilityViolation",
+        Schema,
+        Error,
+        "The server's CapabilityStatement does not accept this resource type or claimed profile.",
+        "https://hl7.org/fhir/R4/capabilitystatement.html",
+        "Send a resource type and profile the server declares, or update its CapabilityStatement.",
+    ),
+    info(
+        "FS1019",
+        "DefinitionViolation",
+        Schema,
+        Error,
+        "A StructureDefinition lists its elements out of order or defines malformed slicing.",
+        "https://hl7.org/fhir/R4/profiling.html#slicing",
+        "List parents before children and each subtree together; give slicings rules and discriminators, and slices unique names after their slicing.",
+    ),
+    info(
+        "REF1001",
+        "NonExistentResource",
+        Reference,
+        Error,
+        "The referenced resource does not exist on the server.",
+        "https://hl7.org/fhir/R4/references.html",
+        "Create the target resource first or correct its id.",
+    ),
+    info(
+        "REF1002",
+        "ContainedNotFound",
+        Reference,
+        Error,
+        "A `#id` reference names no contained resource.",
+        "https://hl7.org/fhir/R4/references.html#contained",
+        "Add the resource to `contained` with that id, or fix the fragment.",
+    ),
+    info(
+        "REF1003",
+        "BundleEntryNotFound",
+        Reference,
+        Error,
+        "A reference inside a Bundle resolves to no entry's fullUrl.",
+        "https://hl7.org/fhir/R4/bundle.html#references",
+        "Add the entry or make the reference match its fullUrl.",
+    ),
+    info(
+        "REF1004",
+        "ServiceUnavailable",
+        Reference,
+        Warning,
+        "The reference resolver could not be reached, so references were not checked.",
+        "https://hl7.org/fhir/R4/references.html",
+        "Check the resolver's connectivity and retry.",
+    ),
+    info(
+        "REF1005",
+        "InvalidReferenceFormat",
+        Reference,
+        Error,
+        "A reference is neither a relative `Type/id`, an absolute URL nor a `#` fragment.",
+        "https://hl7.org/fhir/R4/references.html#literal",
+        "Write the reference as `Type/id`, an absolute URL or `#id`.",
+    ),
+    info(
+        "VS1001",
+        "ValueSetNotFound",
+        Terminology,
+        Error,
+        "The value set of a binding is unknown to the terminology service.",
+        "https://hl7.org/fhir/R4/terminologies.html",
+        "Load the package defining the value set or point the validator at a server that has it.",
+    ),
+    info(
+        "VS1002",
+        "CodeNotInValueSet",
+        Terminology,
+        Error,
+        "A code is not a member of the bound value set.",
+        "https://hl7.org/fhir/R4/valueset-operation-validate-code.html",
+        "Use a code from the value set expansion.",
+    ),
+    info(
+        "VS1003",
+        "InvalidCodeSystem",
+        Terminology,
+        Error,
+        "A code's system is unknown or not a valid code system URI.",
+        "https://hl7.org/fhir/R4/terminologies-systems.html",
+        "Use the canonical system URI of the code system.",
+    ),
+    info(
+        "VS1004",
+        "RequiredBindingViolation",
+        Terminology,
+        Error,
+        "An element with a required binding has a code outside the value set.",
+        "https://hl7.org/fhir/R4/terminologies.html#required",
+        "Required bindings admit no other codes; pick one from the value set.",
+    ),
+    info(
+        "VS1005",
+        "ExtensibleBindingViolation",
+        Terminology,
+        Warning,
+        "An element with an extensible binding uses a code outside the value set.",
+        "https://hl7.org/fhir/R4/terminologies.html#extensible",
+        "Prefer a code from the value set; others are only allowed when none applies.",
+    ),
+    info(
+        "VS1006",
+        "CodeSystemMismatch",
+        Terminology,
+        Error,
+        "The code exists, but in a different code system than the one given.",
+        "https://hl7.org/fhir/R4/datatypes.html#Coding",
+        "Send the code with the system it belongs to.",
+    ),
+    info(
+        "VS1007",
+        "MissingRequiredCode",
+        Terminology,
+        Error,
+        "A coded element with a required binding carries no code.",
+        "https://hl7.org/fhir/R4/terminologies.html#required",
+        "Supply a code from the bound value set.",
+    ),
+    info(
+        "VS1008",
+        "ServiceUnavailable",
+        Terminology,
+        Warning,
+        "The terminology service could not be reached, so codes were not checked.",
+        "https://hl7.org/fhir/R4/terminology-service.html",
+        "Check the terminology server, or validate offline against loaded value sets.",
+    ),
+    info(
+        "VS1009",
+        "InvalidCodeFormat",
+        Terminology,
+        Error,
+        "A code is not a valid `code` value (leading/trailing or repeated whitespace).",
+        "https://hl7.org/fhir/R4/datatypes.html#code",
+        "Trim the code and collapse internal whitespace.",
+    ),
+];
+
+/// Every error code, FS first, then REF, then VS.
+pub fn all() -> &'static [ErrorCodeInfo] {
+    CATALOG
+}
+
+/// Look up a code, ignoring ASCII case.
+pub fn lookup(code: &str) -> Option<&'static ErrorCodeInfo> {
+    CATALOG
+        .iter()
+        .find(|info| info.code.eq_ignore_ascii_case(code))
+}
+
+fn expect(code: &str) -> &'static ErrorCodeInfo {
+    lookup(code).unwrap_or_else(|| panic!("{code} is missing from the error catalog"))
+}
+
+impl FhirSchemaErrorCode {
+    /// Catalog entry for this code.
+    pub fn info(&self) -> &'static ErrorCodeInfo {
+        expect(self.as_str())
+    }
+}
+
+impl ReferenceErrorCode {
+    /// Catalog entry for this code.
+    pub fn info(&self) -> &'static ErrorCodeInfo {
+        expect(&self.to_string())
+    }
+}
+
+impl TerminologyErrorCode {
+    /// Catalog entry for this code.
+    pub fn info(&self) -> &'static ErrorCodeInfo {
+        expect(&self.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_code_is_cataloged() {
+        let fs = [
+            FhirSchemaErrorCode::UnknownElement,
+            FhirSchemaErrorCode::UnknownSchema,
+            FhirSchemaErrorCode::ExpectedArray,
+            FhirSchemaErrorCode::UnexpectedArray,
+            FhirSchemaErrorCode::UnknownKeyword,
+            FhirSchemaErrorCode::WrongType,
+            FhirSchemaErrorCode::SlicingUnmatched,
+            FhirSchemaErrorCode::SlicingAmbiguous,
+            FhirSchemaErrorCode::SliceCardinality,
+            FhirSchemaErrorCode::ConstraintViolation,
+            FhirSchemaErrorCode::CardinalityViolation,
+            FhirSchemaErrorCode::BindingViolation,
+            FhirSchemaErrorCode::ReferenceTypeViolation,
+            FhirSchemaErrorCode::InvalidValue,
+            FhirSchemaErrorCode::ReferenceNotFound,
+            FhirSchemaErrorCode::QuestionnaireViolation,
+            FhirSchemaErrorCode::ReferenceTargetProfileMismatch,
+            FhirSchemaErrorCode::CapabilityViolation,
+            FhirSchemaErrorCode::DefinitionViolation,
+        ];
+        for code in &fs {
+            assert_eq!(code.info().name, format!("{code:?}"));
+        }
+        assert_eq!(
+            ReferenceErrorCode::ContainedNotFound.info().name,
+            "ContainedNotFound"
+        );
+        assert_eq!(
+            TerminologyErrorCode::ExtensibleBindingViolation
+                .info()
+                .severity,
+            DefaultSeverity::Warning
+        );
+        assert_eq!(all().len(), fs.len() + 5 + 9);
+    }
+
+    #[test]
+    fn test_lookup_ignores_case() {
+        let info = lookup("fs1007").unwrap();
+        assert_eq!(info.name, "SlicingUnmatched");
+        assert_eq!(info.family, ErrorFamily::Schema);
+        assert!(lookup("FS9999").is_none());
+    }
+}
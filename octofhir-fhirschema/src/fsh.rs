@@ -0,0 +1,165 @@
+//! FHIR Shorthand (FSH) project output.
+//!
+//! SUSHI compiles a FSH project into `fsh-generated/resources/*.json` and
+//! records where each generated file came from in
+//! `fsh-generated/fsh-index.json`. This module locates that output and
+//! reads the index, so conversion diagnostics for a generated
+//! StructureDefinition can point at the FSH file and lines that define it
+//! instead of at the generated JSON.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use octofhir_fhirschema::fsh::FshProject;
+//!
+//! let project = FshProject::open("my-ig")?;
+//! for file in project.resource_files()? {
+//!     if let Err(err) = convert(&file) {
+//!         match project.source_of(&file) {
+//!             Some(source) => eprintln!("{source}: {err}"),
+//!             None => eprintln!("{}: {err}", file.display()),
+//!         }
+//!     }
+//! }
+//! ```
+
+use crate::error::{FhirSchemaError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Directory SUSHI writes its output to, relative to the project root
+pub const FSH_GENERATED_DIR: &str = "fsh-generated";
+
+/// FSH definition a generated resource was compiled from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FshSource {
+    /// Generated file name, e.g. `StructureDefinition-my-patient.json`
+    pub output_file: String,
+    /// Name of the FSH entity, e.g. `MyPatient`
+    pub fsh_name: String,
+    /// Kind of FSH entity, e.g. `Profile`, `Extension`, `Instance`
+    pub fsh_type: String,
+    /// FSH file relative to `input/fsh`
+    pub fsh_file: String,
+    /// First line of the definition (1-based)
+    pub start_line: usize,
+    /// Last line of the definition (1-based)
+    pub end_line: usize,
+}
+
+impl fmt::Display for FshSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}-{} ({} {})",
+            self.fsh_file, self.start_line, self.end_line, self.fsh_type, self.fsh_name
+        )
+    }
+}
+
+/// A SUSHI project directory (or its `fsh-generated` output directory).
+#[derive(Debug, Clone)]
+pub struct FshProject {
+    generated_dir: PathBuf,
+    sources: HashMap<String, FshSource>,
+}
+
+impl FshProject {
+    /// Open a project from its root or from its `fsh-generated` directory.
+    ///
+    /// A missing `fsh-index.json` is not an error (older SUSHI versions do
+    /// not write one); resources are still listed but have no source.
+    pub fn open<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        let dir = dir.as_ref();
+        let generated_dir = if dir.join(FSH_GENERATED_DIR).is_dir() {
+            dir.join(FSH_GENERATED_DIR)
+        } else if dir.join("resources").is_dir() {
+            dir.to_path_buf()
+        } else {
+            return Err(FhirSchemaError::missing_element(format!(
+                "SUSHI output in {} (run `sushi build` first)",
+                dir.display()
+            )));
+        };
+
+        let index_file = generated_dir.join("fsh-index.json");
+        let sources = if index_file.is_file() {
+            Self::parse_index(&std::fs::read_to_string(&index_file)?)?
+        } else {
+            HashMap::new()
+        };
+        Ok(Self {
+            generated_dir,
+            sources,
+        })
+    }
+
+    /// Parse the contents of `fsh-index.json`, keyed by output file name.
+    pub fn parse_index(json: &str) -> Result<HashMap<String, FshSource>> {
+        let entries: Vec<FshSource> = serde_json::from_str(json)?;
+        Ok(entries
+            .into_iter()
+            .map(|entry| (entry.output_file.clone(), entry))
+            .collect())
+    }
+
+    /// Directory holding the generated resource JSON files.
+    pub fn resources_dir(&self) -> PathBuf {
+        self.generated_dir.join("resources")
+    }
+
+    /// Generated resource JSON files, sorted by name.
+    pub fn resource_files(&self) -> Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(self.resources_dir())? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                files.push(path);
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+
+    /// FSH definition a generated file was compiled from, if indexed.
+    pub fn source_of(&self, generated_file: &Path) -> Option<&FshSource> {
+        let name = generated_file.file_name()?.to_str()?;
+        self.sources.get(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_index() {
+        let sources = FshProject::parse_index(
+            r#"[{
+                "outputFile": "StructureDefinition-my-patient.json",
+                "fshName": "MyPatient",
+                "fshType": "Profile",
+                "fshFile": "profiles/patient.fsh",
+                "startLine": 3,
+                "endLine": 17
+            }]"#,
+        )
+        .unwrap();
+
+        let source = &sources["StructureDefinition-my-patient.json"];
+        assert_eq!(source.fsh_name, "MyPatient");
+        assert_eq!(
+            source.to_string(),
+            "profiles/patient.fsh:3-17 (Profile MyPatient)"
+        );
+    }
+
+    #[test]
+    fn test_open_without_output() {
+        let dir = std::env::temp_dir().join("fhirschema-fsh-missing-output");
+        assert!(FshProject::open(dir).is_err());
+    }
+}
@@ -0,0 +1,145 @@
+//! Resource input parsing.
+//!
+//! Batch validation spends a large share of its time turning JSON text into
+//! [`serde_json::Value`]. [`parse_resource`] is the single entry point for
+//! that step: with the `simd-json` feature it parses in place with simd-json,
+//! otherwise it falls back to `serde_json`. Both produce the same `Value`, so
+//! callers do not change with the feature.
+//!
+//! [`RawResource`] keeps a resource as unparsed JSON and only builds the
+//! `Value` the validator walks when it is first needed. Peeking at the
+//! `resourceType` does not allocate a tree, so resources rejected up front
+//! (unsupported types, routing by type) never pay for a full parse.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use octofhir_fhirschema::input::parse_resource;
+//!
+//! let mut line = line.into_bytes();
+//! let resource = parse_resource(&mut line)?;
+//! let result = validator.validate(&resource, vec!["Patient".to_string()]).await;
+//! ```
+
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use serde_json::value::RawValue;
+use std::sync::OnceLock;
+
+use crate::error::{FhirSchemaError, Result};
+
+/// Parse a resource from JSON bytes.
+///
+/// With the `simd-json` feature the buffer is used as scratch space and its
+/// contents are unspecified afterwards; without it the buffer is left as is.
+pub fn parse_resource(bytes: &mut [u8]) -> Result<JsonValue> {
+    #[cfg(feature = "simd-json")]
+    {
+        simd_json::serde::from_slice(bytes)
+            .map_err(|e| FhirSchemaError::invalid_resource_json(e.to_string()))
+    }
+    #[cfg(not(feature = "simd-json"))]
+    {
+        serde_json::from_slice(bytes)
+            .map_err(|e| FhirSchemaError::invalid_resource_json(e.to_string()))
+    }
+}
+
+#[derive(Deserialize)]
+struct ResourceTypePeek<'a> {
+    #[serde(rename = "resourceType", borrow)]
+    resource_type: Option<&'a str>,
+}
+
+/// A resource held as unparsed JSON, converted to a [`JsonValue`] on first use.
+#[derive(Debug)]
+pub struct RawResource {
+    raw: Box<RawValue>,
+    value: OnceLock<JsonValue>,
+}
+
+impl RawResource {
+    /// Check that `json` is well-formed JSON and keep it unparsed.
+    pub fn new(json: String) -> Result<Self> {
+        let raw = RawValue::from_string(json)
+            .map_err(|e| FhirSchemaError::invalid_resource_json(e.to_string()))?;
+        Ok(Self::from_raw(raw))
+    }
+
+    /// Wrap a raw value taken from a larger document, e.g. a Bundle entry.
+    pub fn from_raw(raw: Box<RawValue>) -> Self {
+        Self {
+            raw,
+            value: OnceLock::new(),
+        }
+    }
+
+    /// The unparsed JSON text.
+    pub fn json(&self) -> &str {
+        self.raw.get()
+    }
+
+    /// The top-level `resourceType`, read without building the value tree.
+    /// Returns `None` for non-objects and type names containing escapes.
+    pub fn resource_type(&self) -> Option<&str> {
+        serde_json::from_str::<ResourceTypePeek<'_>>(self.raw.get())
+            .ok()
+            .and_then(|peek| peek.resource_type)
+    }
+
+    /// Whether the value tree has been built yet.
+    pub fn is_parsed(&self) -> bool {
+        self.value.get().is_some()
+    }
+
+    /// The resource as a value tree, parsing it on first call.
+    pub fn value(&self) -> Result<&JsonValue> {
+        if let Some(value) = self.value.get() {
+            return Ok(value);
+        }
+        let mut bytes = self.raw.get().as_bytes().to_vec();
+        let value = parse_resource(&mut bytes)?;
+        Ok(self.value.get_or_init(|| value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_resource() {
+        let mut bytes =
+            br#"{"resourceType":"Patient","active":true,"name":[{"family":"Chalmers"}]}"#.to_vec();
+        let resource = parse_resource(&mut bytes).unwrap();
+        assert_eq!(
+            resource,
+            json!({"resourceType": "Patient", "active": true, "name": [{"family": "Chalmers"}]})
+        );
+
+        let mut broken = br#"{"resourceType":"Patient""#.to_vec();
+        assert!(matches!(
+            parse_resource(&mut broken),
+            Err(FhirSchemaError::InvalidResourceJson { .. })
+        ));
+    }
+
+    #[test]
+    fn test_raw_resource_parses_lazily() {
+        let resource =
+            RawResource::new(r#"{"id":"a","resourceType":"Observation","status":"final"}"#.into())
+                .unwrap();
+        assert_eq!(resource.resource_type(), Some("Observation"));
+        assert!(!resource.is_parsed());
+
+        assert_eq!(resource.value().unwrap()["status"], "final");
+        assert!(resource.is_parsed());
+
+        assert!(RawResource::new("[1, 2".into()).is_err());
+        assert_eq!(
+            RawResource::new("[1, 2]".into()).unwrap().resource_type(),
+            None
+        );
+    }
+}
@@ -25,7 +25,27 @@
 //! - [`provider`] - Schema and validation providers
 //! - [`validation`] - Validation engine and error codes
 //! - [`embedded`] - Pre-compiled schemas for different FHIR versions
+//! - [`error_catalog`] - Descriptions and remediation hints for every error code
 //! - [`converter`] - StructureDefinition to FhirSchema conversion
+//! - [`conformance`] - FHIR Schema and HL7 validator test-suite runner
+//! - [`profiles`] - Profile chain resolution and cached merging
+//! - [`path_navigator`] - Element definitions by path, through slices and extensions
+//! - [`schema_builder`] - Runtime profiles derived from a base schema in code
+//! - [`search_params`] - Search parameters per resource type
+//! - [`session`] - Conversion of several packages with references resolved across them
+//! - [`auth`] - Authentication headers for HTTP terminology and reference clients
+//! - [`docs`] - Markdown and HTML documentation of schemas and profiles
+//! - [`diagram`] - Mermaid and Graphviz diagrams of schema element trees
+//! - [`package`] - FHIR package dependency resolution
+//! - [`fsh`] - FHIR Shorthand (SUSHI) project output
+//! - [`input`] - Resource JSON parsing (optionally with simd-json)
+//! - [`serialization`] - Schema files in JSON, YAML, MessagePack and CBOR
+//! - [`view_definition`] - SQL-on-FHIR ViewDefinition checking and column typing
+//! - `snapshot` - Golden-file snapshots of schemas for tests (`test-support` feature)
+//! - `synthetic` - Large synthetic resources and profiles for benchmarks (`bench-util` feature)
+
+/// Version of this crate, recorded in embedded schema manifests
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 // Conversion modules
 pub mod action_calculator;
@@ -36,52 +56,105 @@ pub mod path_parser;
 pub mod stack_processor;
 
 // Core modules
+pub mod auth;
+pub mod conformance;
+pub mod diagram;
+pub mod docs;
 pub mod embedded;
 pub mod error;
+pub mod error_catalog;
+pub mod fsh;
+pub mod input;
+pub mod package;
+pub mod path_navigator;
+pub mod profiles;
 pub mod provider;
 pub mod reference;
+pub mod schema_builder;
+pub mod search_params;
+pub mod serialization;
+pub mod session;
+#[cfg(feature = "test-support")]
+pub mod snapshot;
+#[cfg(feature = "bench-util")]
+pub mod synthetic;
 pub mod terminology;
 pub mod types;
 pub mod validation;
+pub mod view_definition;
 
 // Converter exports
-pub use converter::translate;
+pub use choice_handler::{ChoiceTypeResolver, ChoiceVariant};
+pub use converter::{inline_types, translate};
+
+// Conformance exports
+pub use conformance::{
+    CaseResult, CaseStatus, ConformanceReport, ConformanceRunner, Hl7SuiteOptions, Hl7TestSuite,
+    SchemaTestSuite,
+};
 
 // Embedded schema exports
 pub use embedded::{
-    FhirVersion, SchemaInfo, create_validation_context, get_schema, get_schema_info,
-    get_schema_names, get_schemas, has_schema, list_primitives, list_resources,
+    BundleFormat, EmbeddedSchemas, FhirVersion, ProfilePack, SchemaInfo, SchemaManifest,
+    create_validation_context, decode_schema_bundle, get_compiled_schemas, get_profile_pack,
+    get_schema, get_schema_closure, get_schema_info, get_schema_manifest, get_schema_names,
+    get_schemas, get_search_parameters, has_schema, is_embedded, list_primitives,
+    list_profile_packs, list_resources, load_schema_bundle, verify_integrity,
+};
+
+// Serialization exports
+pub use serialization::{encode_schema, load_schema_file};
+
+// Input exports
+pub use input::{RawResource, parse_resource};
+
+// Package exports
+pub use package::{PackageGraph, PackageManifest, ResolvedPackages, VersionConflict};
+
+// Profile merge exports
+pub use path_navigator::PathNavigator;
+pub use profiles::{ProfileMergeCache, merge_profile_chain};
+pub use schema_builder::{SchemaBuilder, Slice};
+
+// Conversion session exports
+pub use session::{
+    CanonicalKind, ConversionFailure, ConversionOutput, ConversionSession, UnresolvedCanonical,
 };
 
+// Search parameter exports
+pub use search_params::{SearchParameter, SearchParameterRegistry};
+
 // Error exports
 pub use error::{FhirSchemaError, Result};
 
 // Type exports
 pub use types::{
-    FhirSchema, FhirSchemaElement, StructureDefinition, ValidationContext, ValidationError,
-    ValidationResult,
+    ErrorMessage, ErrorPath, FhirSchema, FhirSchemaElement, PatchOperation, SchemaTrace,
+    StructureDefinition, ValidationContext, ValidationError, ValidationResult,
 };
 
 // Validation exports
 pub use validation::{
-    FhirSchemaErrorCode, FhirValidator, InMemorySchemaProvider, QrStrictness,
-    QuestionnaireProvider, SchemaProvider,
+    CacheTuning, CapabilityPolicy, FhirSchemaErrorCode, FhirValidator, InMemorySchemaProvider,
+    QrStrictness, QuestionnaireProvider, SchemaProvider, ValidationOptions,
 };
 
 // Provider exports (from new module structure)
 pub use provider::{
     DynamicSchemaProvider, EmbeddedSchemaProvider, FhirSchemaModelProvider,
-    FhirSchemaValidationProvider, ValidationProviderBuilder,
+    FhirSchemaValidationProvider, ProfileInfo, ValidationProviderBuilder,
     create_validation_provider_from_dynamic, create_validation_provider_from_embedded,
     create_validation_provider_with_fhirpath,
 };
 
 // Terminology exports
 pub use terminology::{
-    BindingStrength, CacheConfig, CacheStats, CachedTerminologyService, CodeValidationResult,
-    InMemoryTerminologyService, TerminologyError, TerminologyErrorCode, TerminologyProviderAdapter,
-    TerminologyResult, TerminologyService,
+    BindingStrength, CacheConfig, CodeValidationResult, InMemoryTerminologyService,
+    TerminologyError, TerminologyErrorCode, TerminologyProviderAdapter, TerminologyResult,
+    TerminologyService, core_terminology_service,
 };
+#[cfg(not(target_arch = "wasm32"))]
+pub use terminology::{CacheStats, CachedTerminologyService};
 
 // Reference validation exports
 pub use reference::{
@@ -89,6 +162,12 @@ pub use reference::{
     ReferenceResolutionResult, ReferenceResolver, ReferenceResult,
 };
 
+// Authentication exports for HTTP terminology and reference clients
+pub use auth::{
+    AuthError, AuthProvider, AuthResult, ClientCredentialsAuth, HeaderAuth, StaticTokenAuth,
+    TokenTransport,
+};
+
 // Re-export key types from fhir-model-rs for convenience
 pub use octofhir_fhir_model::error::{ModelError, Result as ModelResult};
 pub use octofhir_fhir_model::provider::{
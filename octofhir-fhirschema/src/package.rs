@@ -0,0 +1,289 @@
+//! FHIR package dependency tracking.
+//!
+//! Implementation guides declare the packages they build on in the
+//! `dependencies` map of their `package.json`. Validating a profile from an
+//! IG needs the schemas of every package in that closure, so this module
+//! records the declared dependencies of each imported package and resolves
+//! the transitive set, reporting any package that is requested at more
+//! than one version.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use octofhir_fhirschema::package::{PackageGraph, PackageManifest};
+//!
+//! let mut graph = PackageGraph::new();
+//! graph.add_package(PackageManifest::from_package_json(&us_core_package_json)?);
+//! graph.add_package(PackageManifest::from_package_json(&r4_core_package_json)?);
+//!
+//! let resolved = graph.resolve("hl7.fhir.us.core", "6.1.0")?;
+//! for (name, version) in &resolved.packages {
+//!     load_schemas_for(name, version);
+//! }
+//! ```
+
+use crate::error::{FhirSchemaError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+/// Name and declared dependencies of a single FHIR package.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackageManifest {
+    /// Package name, e.g. `hl7.fhir.us.core`
+    pub name: String,
+    /// Package version, e.g. `6.1.0`
+    pub version: String,
+    /// Declared dependencies as package name -> version
+    #[serde(default)]
+    pub dependencies: BTreeMap<String, String>,
+}
+
+impl PackageManifest {
+    /// Create a manifest without dependencies.
+    pub fn new<N: Into<String>, V: Into<String>>(name: N, version: V) -> Self {
+        Self {
+            name: name.into(),
+            version: version.into(),
+            dependencies: BTreeMap::new(),
+        }
+    }
+
+    /// Add a declared dependency.
+    pub fn with_dependency<N: Into<String>, V: Into<String>>(
+        mut self,
+        name: N,
+        version: V,
+    ) -> Self {
+        self.dependencies.insert(name.into(), version.into());
+        self
+    }
+
+    /// Read a manifest from the contents of a package's `package.json`.
+    pub fn from_package_json(value: &serde_json::Value) -> Result<Self> {
+        let name = value
+            .get("name")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| FhirSchemaError::missing_element("package.json name"))?;
+        let version = value
+            .get("version")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| FhirSchemaError::missing_element("package.json version"))?;
+
+        let mut manifest = Self::new(name, version);
+        if let Some(deps) = value.get("dependencies").and_then(|v| v.as_object()) {
+            for (dep_name, dep_version) in deps {
+                if let Some(dep_version) = dep_version.as_str() {
+                    manifest
+                        .dependencies
+                        .insert(dep_name.clone(), dep_version.to_string());
+                }
+            }
+        }
+        Ok(manifest)
+    }
+}
+
+/// A package requested at more than one version within a dependency closure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionConflict {
+    /// Conflicting package name
+    pub name: String,
+    /// Version kept in the resolved set (the first one reached)
+    pub selected: String,
+    /// Other versions requested, with the package that requested each
+    pub requested: Vec<(String, String)>,
+}
+
+/// Transitive dependency closure of a package.
+#[derive(Debug, Clone, Default)]
+pub struct ResolvedPackages {
+    /// Every package in the closure (root included) as name -> version
+    pub packages: BTreeMap<String, String>,
+    /// Packages reached in the closure that were never added to the graph
+    pub missing: Vec<(String, String)>,
+    /// Packages requested at more than one version
+    pub conflicts: Vec<VersionConflict>,
+}
+
+impl ResolvedPackages {
+    /// Whether every package was available and no version conflicts were found.
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty() && self.conflicts.is_empty()
+    }
+}
+
+/// Registry of imported packages and their declared dependencies.
+#[derive(Debug, Clone, Default)]
+pub struct PackageGraph {
+    packages: HashMap<(String, String), PackageManifest>,
+}
+
+impl PackageGraph {
+    /// Create an empty graph.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record an imported package, replacing any earlier manifest for the
+    /// same name and version.
+    pub fn add_package(&mut self, manifest: PackageManifest) {
+        self.packages
+            .insert((manifest.name.clone(), manifest.version.clone()), manifest);
+    }
+
+    /// Look up a recorded package.
+    pub fn get(&self, name: &str, version: &str) -> Option<&PackageManifest> {
+        self.packages.get(&(name.to_string(), version.to_string()))
+    }
+
+    /// Number of recorded packages.
+    pub fn len(&self) -> usize {
+        self.packages.len()
+    }
+
+    /// Whether no packages have been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.packages.is_empty()
+    }
+
+    /// Resolve the transitive dependency closure of a recorded package.
+    ///
+    /// Dependencies are walked breadth-first, so when a package is requested
+    /// at several versions the one closest to the root wins and the others
+    /// are reported in [`ResolvedPackages::conflicts`].
+    pub fn resolve(&self, name: &str, version: &str) -> Result<ResolvedPackages> {
+        if self.get(name, version).is_none() {
+            return Err(FhirSchemaError::missing_element(format!(
+                "package {name}#{version}"
+            )));
+        }
+
+        let mut resolved = ResolvedPackages::default();
+        let mut conflicts: BTreeMap<String, VersionConflict> = BTreeMap::new();
+        let mut queue = VecDeque::new();
+
+        resolved
+            .packages
+            .insert(name.to_string(), version.to_string());
+        queue.push_back((name.to_string(), version.to_string()));
+
+        while let Some((pkg_name, pkg_version)) = queue.pop_front() {
+            let Some(manifest) = self.get(&pkg_name, &pkg_version) else {
+                resolved.missing.push((pkg_name, pkg_version));
+                continue;
+            };
+
+            for (dep_name, dep_version) in &manifest.dependencies {
+                match resolved.packages.get(dep_name) {
+                    None => {
+                        resolved
+                            .packages
+                            .insert(dep_name.clone(), dep_version.clone());
+                        queue.push_back((dep_name.clone(), dep_version.clone()));
+                    }
+                    Some(selected) if selected != dep_version => {
+                        conflicts
+                            .entry(dep_name.clone())
+                            .or_insert_with(|| VersionConflict {
+                                name: dep_name.clone(),
+                                selected: selected.clone(),
+                                requested: Vec::new(),
+                            })
+                            .requested
+                            .push((
+                                dep_version.clone(),
+                                format!("{}#{}", manifest.name, manifest.version),
+                            ));
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+
+        resolved.conflicts = conflicts.into_values().collect();
+        Ok(resolved)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn graph() -> PackageGraph {
+        let mut graph = PackageGraph::new();
+        graph.add_package(PackageManifest::new("hl7.fhir.r4.core", "4.0.1"));
+        graph.add_package(
+            PackageManifest::new("hl7.terminology.r4", "5.0.0")
+                .with_dependency("hl7.fhir.r4.core", "4.0.1"),
+        );
+        graph.add_package(
+            PackageManifest::new("hl7.fhir.us.core", "6.1.0")
+                .with_dependency("hl7.fhir.r4.core", "4.0.1")
+                .with_dependency("hl7.terminology.r4", "5.0.0"),
+        );
+        graph
+    }
+
+    #[test]
+    fn test_parse_package_json() {
+        let manifest = PackageManifest::from_package_json(&json!({
+            "name": "hl7.fhir.us.core",
+            "version": "6.1.0",
+            "dependencies": {
+                "hl7.fhir.r4.core": "4.0.1",
+                "hl7.terminology.r4": "5.0.0"
+            }
+        }))
+        .unwrap();
+
+        assert_eq!(manifest.name, "hl7.fhir.us.core");
+        assert_eq!(manifest.dependencies.len(), 2);
+        assert!(PackageManifest::from_package_json(&json!({"name": "x"})).is_err());
+    }
+
+    #[test]
+    fn test_resolve_transitive_closure() {
+        let resolved = graph().resolve("hl7.fhir.us.core", "6.1.0").unwrap();
+
+        assert!(resolved.is_complete());
+        assert_eq!(resolved.packages.len(), 3);
+        assert_eq!(
+            resolved.packages.get("hl7.fhir.r4.core"),
+            Some(&"4.0.1".to_string())
+        );
+    }
+
+    #[test]
+    fn test_resolve_reports_conflicts_and_missing() {
+        let mut graph = graph();
+        graph.add_package(
+            PackageManifest::new("hl7.terminology.r4", "6.0.0")
+                .with_dependency("hl7.fhir.r4.core", "4.0.1"),
+        );
+        graph.add_package(
+            PackageManifest::new("example.ig", "1.0.0")
+                .with_dependency("hl7.fhir.us.core", "6.1.0")
+                .with_dependency("hl7.terminology.r4", "6.0.0")
+                .with_dependency("example.missing", "0.1.0"),
+        );
+
+        let resolved = graph.resolve("example.ig", "1.0.0").unwrap();
+
+        assert!(!resolved.is_complete());
+        assert_eq!(
+            resolved.missing,
+            vec![("example.missing".to_string(), "0.1.0".to_string())]
+        );
+        assert_eq!(resolved.conflicts.len(), 1);
+        let conflict = &resolved.conflicts[0];
+        assert_eq!(conflict.name, "hl7.terminology.r4");
+        assert_eq!(conflict.selected, "6.0.0");
+        assert_eq!(conflict.requested[0].0, "5.0.0");
+    }
+
+    #[test]
+    fn test_resolve_unknown_root() {
+        assert!(graph().resolve("unknown", "1.0.0").is_err());
+    }
+}
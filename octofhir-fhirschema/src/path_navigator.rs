@@ -0,0 +1,316 @@
+//! Element definitions by path, through slices and extensions.
+//!
+//! Profiles constrain elements per slice, so the definition that applies to
+//! `Patient.identifier:mrn.system` is the `system` of the `mrn` slice laid
+//! over the `system` of `identifier`. [`PathNavigator`] walks a merged
+//! profile schema (see [`crate::profiles`]) along such paths and merges the
+//! definitions it finds on the way, most specific last.
+//!
+//! Paths use the FHIR element path syntax:
+//!
+//! - `name` or `name[x]` for an element
+//! - `name:slice` for a named slice of an element
+//! - `extension('race')` or `modifierExtension('...')` for an extension
+//!   slice, named by its slice name or its extension url
+//!
+//! # Example
+//!
+//! ```ignore
+//! use octofhir_fhirschema::{PathNavigator, ProfileMergeCache};
+//!
+//! let merged = cache.merged(provider.as_ref(), &us_core_patient).await;
+//! let navigator = PathNavigator::new(&merged);
+//! let race = navigator.element("Patient.extension('race')").unwrap();
+//! assert_eq!(race.url.as_deref(), Some(US_CORE_RACE));
+//! ```
+
+use crate::profiles::merge_elements;
+use crate::types::{FhirSchema, FhirSchemaElement, FhirSchemaSliceMatch};
+
+/// One step of an element path.
+#[derive(Debug, Clone, PartialEq)]
+enum Segment<'p> {
+    /// An element, optionally narrowed to a named slice
+    Element {
+        name: &'p str,
+        slice: Option<&'p str>,
+    },
+    /// `extension(key)`: the extension slice with that name or url
+    Extension { element: &'p str, key: &'p str },
+}
+
+impl<'p> Segment<'p> {
+    fn parse(segment: &'p str) -> Self {
+        for element in ["extension", "modifierExtension"] {
+            if let Some(key) = segment
+                .strip_prefix(element)
+                .and_then(|rest| rest.strip_prefix('('))
+                .and_then(|rest| rest.strip_suffix(')'))
+            {
+                let key = key.trim().trim_matches(['\'', '"']);
+                return Segment::Extension { element, key };
+            }
+        }
+        let (name, slice) = match segment.split_once(':') {
+            Some((name, slice)) => (name, Some(slice)),
+            None => (segment, None),
+        };
+        Segment::Element {
+            name: name.strip_suffix("[x]").unwrap_or(name),
+            slice,
+        }
+    }
+}
+
+/// Split a path on the dots outside parentheses and string literals, so an
+/// extension url stays in one segment.
+pub fn split_path(path: &str) -> Vec<&str> {
+    let mut segments = Vec::new();
+    let (mut depth, mut quote, mut start) = (0usize, None, 0);
+    for (i, c) in path.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') => depth = depth.saturating_sub(1),
+            (None, '.') if depth == 0 => {
+                segments.push(&path[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    segments.push(&path[start..]);
+    segments.retain(|segment| !segment.is_empty());
+    segments
+}
+
+/// Resolves element paths with slices and extension shortcuts against a
+/// merged schema.
+pub struct PathNavigator<'s> {
+    schema: &'s FhirSchema,
+}
+
+impl<'s> PathNavigator<'s> {
+    pub fn new(schema: &'s FhirSchema) -> Self {
+        Self { schema }
+    }
+
+    /// The effective definition of the element at `path`: the element
+    /// merged with the definitions of the slices named on the way. The
+    /// leading type name (`Patient.`) is optional. `None` when the schema
+    /// does not define the element, e.g. a child the profile leaves to its
+    /// data type.
+    pub fn element(&self, path: &str) -> Option<FhirSchemaElement> {
+        let layers = self.layers(path)?;
+        let (first, rest) = layers.split_first()?;
+        Some(rest.iter().fold((*first).clone(), |merged, layer| {
+            merge_elements(&merged, layer)
+        }))
+    }
+
+    /// The slice of the element at `path` named by its last segment, with
+    /// its `match` and cardinality: `identifier:mrn` or
+    /// `extension('race')`.
+    pub fn slice(&self, path: &str) -> Option<&'s FhirSchemaSliceMatch> {
+        let segments = self.segments(path);
+        let (last, parent) = segments.split_last()?;
+        let parents = self.walk(parent)?;
+        let root = parent.is_empty().then_some(self.schema);
+        match Segment::parse(last) {
+            Segment::Element {
+                name,
+                slice: Some(slice),
+            } => Self::find_slice(&Self::children(&parents, root, name), |n, _| n == slice),
+            Segment::Extension { element, key } => {
+                Self::find_slice(&Self::children(&parents, root, element), |name, slice| {
+                    Self::is_extension(name, slice, key)
+                })
+            }
+            Segment::Element { slice: None, .. } => None,
+        }
+    }
+
+    fn segments<'p>(&self, path: &'p str) -> Vec<&'p str> {
+        let mut segments = split_path(path);
+        if segments
+            .first()
+            .is_some_and(|first| *first == self.schema.type_name || *first == self.schema.name)
+        {
+            segments.remove(0);
+        }
+        segments
+    }
+
+    /// The definitions of the element at `path`, least specific first.
+    fn layers(&self, path: &str) -> Option<Vec<&'s FhirSchemaElement>> {
+        let segments = self.segments(path);
+        if segments.is_empty() {
+            return None;
+        }
+        self.walk(&segments)
+    }
+
+    /// Walk `segments` from the schema root. At the root the parents are
+    /// empty and children come from the schema itself.
+    fn walk(&self, segments: &[&str]) -> Option<Vec<&'s FhirSchemaElement>> {
+        let mut layers: Vec<&'s FhirSchemaElement> = Vec::new();
+        for (i, segment) in segments.iter().enumerate() {
+            let root = (i == 0).then_some(self.schema);
+            layers = match Segment::parse(segment) {
+                Segment::Element { name, slice } => {
+                    let mut children = Self::children(&layers, root, name);
+                    if let Some(slice) = slice {
+                        let slice = Self::find_slice(&children, |n, _| n == slice)?;
+                        children.extend(slice.schema.as_ref());
+                    }
+                    children
+                }
+                Segment::Extension { element, key } => {
+                    let mut children = Self::children(&layers, root, element);
+                    let slice = Self::find_slice(&children, |name, slice| {
+                        Self::is_extension(name, slice, key)
+                    })?;
+                    children.extend(slice.schema.as_ref());
+                    children
+                }
+            };
+            if layers.is_empty() {
+                return None;
+            }
+        }
+        Some(layers)
+    }
+
+    /// The definitions of child `name` across `parents`, or of the root
+    /// element `name` of `root`.
+    fn children(
+        parents: &[&'s FhirSchemaElement],
+        root: Option<&'s FhirSchema>,
+        name: &str,
+    ) -> Vec<&'s FhirSchemaElement> {
+        let root_child = root.and_then(|schema| schema.elements.as_ref()?.get(name));
+        root_child
+            .into_iter()
+            .chain(
+                parents
+                    .iter()
+                    .filter_map(|parent| parent.elements.as_ref()?.get(name)),
+            )
+            .collect()
+    }
+
+    /// The most specific slice of `elements` accepted by `accept`.
+    fn find_slice(
+        elements: &[&'s FhirSchemaElement],
+        accept: impl Fn(&str, &FhirSchemaSliceMatch) -> bool,
+    ) -> Option<&'s FhirSchemaSliceMatch> {
+        elements.iter().rev().find_map(|element| {
+            element
+                .slicing
+                .as_ref()?
+                .slices
+                .as_ref()?
+                .iter()
+                .find(|(name, slice)| accept(name, slice))
+                .map(|(_, slice)| slice)
+        })
+    }
+
+    /// Whether the slice `name` is the extension `key`, given by slice name
+    /// or extension url.
+    fn is_extension(name: &str, slice: &FhirSchemaSliceMatch, key: &str) -> bool {
+        name == key
+            || slice.schema.as_ref().and_then(|s| s.url.as_deref()) == Some(key)
+            || slice
+                .match_value
+                .as_ref()
+                .and_then(|m| m.get("url"))
+                .and_then(|u| u.as_str())
+                == Some(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const RACE: &str = "http://hl7.org/fhir/us/core/StructureDefinition/us-core-race";
+
+    fn patient_profile() -> FhirSchema {
+        serde_json::from_value(json!({
+            "url": "http://example.org/StructureDefinition/MrnPatient",
+            "name": "MrnPatient", "type": "Patient", "kind": "resource",
+            "class": "profile", "derivation": "constraint",
+            "elements": {
+                "identifier": {
+                    "type": "Identifier", "array": true,
+                    "elements": {"system": {"type": "uri", "short": "Namespace"}},
+                    "slicing": {
+                        "discriminator": [{"type": "value", "path": "system"}],
+                        "slices": {"mrn": {
+                            "match": {"system": "urn:mrn"}, "min": 1,
+                            "schema": {"type": "Identifier", "elements": {
+                                "system": {"type": "uri", "min": 1},
+                                "value": {"type": "string", "min": 1}
+                            }}
+                        }}
+                    }
+                },
+                "extension": {
+                    "type": "Extension", "array": true,
+                    "slicing": {
+                        "discriminator": [{"type": "value", "path": "url"}],
+                        "slices": {"race": {
+                            "match": {"url": RACE}, "max": 1,
+                            "schema": {"type": "Extension", "url": RACE, "short": "Race"}
+                        }}
+                    }
+                }
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_split_path() {
+        assert_eq!(
+            split_path("Patient.extension('http://x.org/a.b').value"),
+            vec!["Patient", "extension('http://x.org/a.b')", "value"]
+        );
+    }
+
+    #[test]
+    fn test_navigate_slices_and_extensions() {
+        let schema = patient_profile();
+        let navigator = PathNavigator::new(&schema);
+
+        // The slice's definition lies over the sliced element's.
+        let system = navigator.element("Patient.identifier:mrn.system").unwrap();
+        assert_eq!(system.min, Some(1));
+        assert_eq!(system.short.as_deref(), Some("Namespace"));
+        let value = navigator.element("identifier:mrn.value").unwrap();
+        assert_eq!(value.type_name.as_deref(), Some("string"));
+        assert!(navigator.element("Patient.identifier.value").is_none());
+        assert!(navigator.element("Patient.identifier:other").is_none());
+
+        // Extensions by slice name or url.
+        let race = navigator.element("Patient.extension('race')").unwrap();
+        assert_eq!(race.short.as_deref(), Some("Race"));
+        assert_eq!(race.url.as_deref(), Some(RACE));
+        let by_url = navigator
+            .element(&format!("Patient.extension('{RACE}')"))
+            .unwrap();
+        assert_eq!(by_url.short.as_deref(), Some("Race"));
+        assert_eq!(
+            navigator.slice("Patient.extension:race").unwrap().max,
+            Some(1)
+        );
+        assert_eq!(
+            navigator.slice("Patient.identifier:mrn").unwrap().min,
+            Some(1)
+        );
+    }
+}
@@ -0,0 +1,460 @@
+//! Profile chain resolution and merging.
+//!
+//! A profile only states what it changes about its base. [`merge_profile_chain`]
+//! folds an inheritance chain (root type first, profile last) into one
+//! [`FhirSchema`] holding the effective cardinalities, bindings, patterns,
+//! type narrowings and constraints. The schema compiler, providers and code
+//! generation all work from this merged view.
+//!
+//! Merging a deep profile chain is repeated work, so [`ProfileMergeCache`]
+//! keeps merged schemas behind an [`Arc`], keyed by the profile URL and a
+//! fingerprint of its chain. Share one cache between consumers to merge each
+//! profile once.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use octofhir_fhirschema::profiles::{ProfileMergeCache, resolve_chain};
+//!
+//! let cache = ProfileMergeCache::new();
+//! let chain = resolve_chain(provider.as_ref(), &us_core_patient).await;
+//! let merged = cache.get_or_merge(&chain);
+//! assert_eq!(merged.url, us_core_patient.url);
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::{Arc, RwLock};
+
+use crate::types::{FhirSchema, FhirSchemaElement, FhirSchemaSliceMatch, FhirSchemaSlicing};
+use crate::validation::SchemaProvider;
+
+/// Resolve the inheritance chain of `schema` through its `base` URLs,
+/// ordered from the root type to `schema` itself.
+///
+/// Resolution stops at a base the provider does not know and at a cycle.
+pub async fn resolve_chain(
+    provider: &dyn SchemaProvider,
+    schema: &FhirSchema,
+) -> Vec<Arc<FhirSchema>> {
+    let mut chain = vec![Arc::new(schema.clone())];
+    let mut visited = HashSet::new();
+    visited.insert(schema.url.clone());
+
+    let mut base = schema.base.clone();
+    while let Some(base_url) = base {
+        if !visited.insert(base_url.clone()) {
+            // Cycle detected
+            break;
+        }
+        let Some(base_schema) = provider.get_schema_by_url(&base_url).await else {
+            // Base not found, stop here
+            break;
+        };
+        base = base_schema.base.clone();
+        chain.push(base_schema);
+    }
+
+    // Reverse so base comes first
+    chain.reverse();
+    chain
+}
+
+/// Merge an inheritance chain, root type first, into a single schema.
+pub fn merge_profile_chain(chain: &[Arc<FhirSchema>]) -> FhirSchema {
+    let Some((root, rest)) = chain.split_first() else {
+        return FhirSchema::default();
+    };
+
+    let mut merged = (**root).clone();
+    for schema in rest {
+        merged = merge_schemas(&merged, schema);
+    }
+    merged
+}
+
+/// Merge two schemas (base + overlay)
+pub fn merge_schemas(base: &FhirSchema, overlay: &FhirSchema) -> FhirSchema {
+    let mut result = base.clone();
+
+    // Overlay takes precedence for metadata
+    result.url = overlay.url.clone();
+    result.name = overlay.name.clone();
+    if overlay.version.is_some() {
+        result.version = overlay.version.clone();
+    }
+
+    // Merge elements
+    if let Some(overlay_elements) = &overlay.elements {
+        let mut merged_elements = result.elements.unwrap_or_default();
+        for (key, element) in overlay_elements {
+            if let Some(base_element) = merged_elements.get(key) {
+                merged_elements.insert(key.clone(), merge_elements(base_element, element));
+            } else {
+                merged_elements.insert(key.clone(), element.clone());
+            }
+        }
+        result.elements = Some(merged_elements);
+    }
+
+    if overlay.extensions.is_some() {
+        result.extensions = overlay.extensions.clone();
+    }
+
+    // Union required elements
+    if let Some(overlay_required) = &overlay.required {
+        let mut required = result.required.unwrap_or_default();
+        required.extend(overlay_required.iter().cloned());
+        result.required = Some(required);
+    }
+
+    // Union excluded elements
+    if let Some(overlay_excluded) = &overlay.excluded {
+        let mut excluded = result.excluded.unwrap_or_default();
+        excluded.extend(overlay_excluded.iter().cloned());
+        result.excluded = Some(excluded);
+    }
+
+    // Union constraints (overlay takes precedence for same key)
+    if let Some(overlay_constraints) = &overlay.constraint {
+        let mut constraints = result.constraint.unwrap_or_default();
+        for (key, constraint) in overlay_constraints {
+            constraints.insert(key.clone(), constraint.clone());
+        }
+        result.constraint = Some(constraints);
+    }
+
+    result
+}
+
+/// Merge two elements
+pub fn merge_elements(base: &FhirSchemaElement, overlay: &FhirSchemaElement) -> FhirSchemaElement {
+    let mut result = base.clone();
+
+    // Overlay cardinality
+    if overlay.min.is_some() {
+        result.min = overlay.min;
+    }
+    if overlay.max.is_some() {
+        result.max = overlay.max;
+    }
+    if overlay.array.is_some() {
+        result.array = overlay.array;
+    }
+
+    // Overlay binding
+    if overlay.binding.is_some() {
+        result.binding = overlay.binding.clone();
+    }
+
+    // Overlay pattern
+    if overlay.pattern.is_some() {
+        result.pattern = overlay.pattern.clone();
+    }
+
+    // Overlay must_support
+    if overlay.must_support.is_some() {
+        result.must_support = overlay.must_support;
+    }
+
+    // Overlay refers (reference targets)
+    if overlay.refers.is_some() {
+        result.refers = overlay.refers.clone();
+    }
+
+    // Overlay choice restrictions — profiles use this to narrow value[x] to
+    // a single concrete type (e.g. humanname-own-prefix → ["valueString"]).
+    // Without this, the base Extension.value choices list survives and any
+    // valueXxx variant is accepted.
+    if overlay.choices.is_some() {
+        result.choices = overlay.choices.clone();
+    }
+
+    // Overlay type narrowing (similar idea: profile may declare an explicit
+    // type on what was a generic Element).
+    if overlay.type_name.is_some() {
+        result.type_name = overlay.type_name.clone();
+    }
+
+    // Primitive format constraints
+    if overlay.regex.is_some() {
+        result.regex = overlay.regex.clone();
+    }
+    if overlay.type_profile.is_some() {
+        result.type_profile = overlay.type_profile.clone();
+    }
+
+    // Union the constraint keys the element is involved in
+    if let Some(overlay_condition) = &overlay.condition {
+        let mut condition = result.condition.unwrap_or_default();
+        for key in overlay_condition {
+            if !condition.contains(key) {
+                condition.push(key.clone());
+            }
+        }
+        result.condition = Some(condition);
+    }
+
+    // Extension profile and inline extension definitions
+    if overlay.url.is_some() {
+        result.url = overlay.url.clone();
+    }
+    if overlay.extensions.is_some() {
+        result.extensions = overlay.extensions.clone();
+    }
+
+    // Union required and excluded children
+    if let Some(overlay_required) = &overlay.required {
+        let mut required = result.required.unwrap_or_default();
+        for key in overlay_required {
+            if !required.contains(key) {
+                required.push(key.clone());
+            }
+        }
+        result.required = Some(required);
+    }
+    if let Some(overlay_excluded) = &overlay.excluded {
+        let mut excluded = result.excluded.unwrap_or_default();
+        for key in overlay_excluded {
+            if !excluded.contains(key) {
+                excluded.push(key.clone());
+            }
+        }
+        result.excluded = Some(excluded);
+    }
+
+    // Slices declared along the chain accumulate
+    if let Some(overlay_slicing) = &overlay.slicing {
+        result.slicing = Some(match &result.slicing {
+            Some(base_slicing) => merge_slicing(base_slicing, overlay_slicing),
+            None => overlay_slicing.clone(),
+        });
+    }
+
+    // Merge nested elements
+    if let Some(overlay_nested) = &overlay.elements {
+        let mut nested = result.elements.unwrap_or_default();
+        for (key, element) in overlay_nested {
+            if let Some(base_element) = nested.get(key) {
+                nested.insert(key.clone(), merge_elements(base_element, element));
+            } else {
+                nested.insert(key.clone(), element.clone());
+            }
+        }
+        result.elements = Some(nested);
+    }
+
+    // Union constraints
+    if let Some(overlay_constraints) = &overlay.constraint {
+        let mut constraints = result.constraint.unwrap_or_default();
+        for (key, constraint) in overlay_constraints {
+            constraints.insert(key.clone(), constraint.clone());
+        }
+        result.constraint = Some(constraints);
+    }
+
+    result
+}
+
+/// Merge two slicings: the overlay's rules and discriminators win, slices
+/// of the same name merge.
+fn merge_slicing(base: &FhirSchemaSlicing, overlay: &FhirSchemaSlicing) -> FhirSchemaSlicing {
+    let mut result = base.clone();
+    if overlay.discriminator.is_some() {
+        result.discriminator = overlay.discriminator.clone();
+    }
+    if overlay.rules.is_some() {
+        result.rules = overlay.rules.clone();
+    }
+    if overlay.ordered.is_some() {
+        result.ordered = overlay.ordered;
+    }
+    if let Some(overlay_slices) = &overlay.slices {
+        let mut slices = result.slices.unwrap_or_default();
+        for (name, slice) in overlay_slices {
+            let merged = match slices.get(name) {
+                Some(base_slice) => FhirSchemaSliceMatch {
+                    match_value: slice
+                        .match_value
+                        .clone()
+                        .or_else(|| base_slice.match_value.clone()),
+                    schema: match (&base_slice.schema, &slice.schema) {
+                        (Some(base), Some(overlay)) => Some(merge_elements(base, overlay)),
+                        (base, overlay) => overlay.clone().or_else(|| base.clone()),
+                    },
+                    min: slice.min.or(base_slice.min),
+                    max: slice.max.or(base_slice.max),
+                },
+                None => slice.clone(),
+            };
+            slices.insert(name.clone(), merged);
+        }
+        result.slices = Some(slices);
+    }
+    result
+}
+
+/// Fingerprint of an inheritance chain: the URL, version and package of
+/// every schema in it, in order.
+///
+/// Schemas are identified rather than hashed in full, so a provider that
+/// replaces a schema must also change its version or package version (or
+/// [`ProfileMergeCache::clear`] the cache) for the change to be seen.
+pub fn chain_fingerprint(chain: &[Arc<FhirSchema>]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for schema in chain {
+        schema.url.hash(&mut hasher);
+        schema.version.hash(&mut hasher);
+        schema.package_name.hash(&mut hasher);
+        schema.package_version.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Merged profile schemas keyed by profile URL and chain fingerprint.
+///
+/// Entries are never evicted; there is one per profile (and per set of base
+/// versions it was merged against) and they are shared, not copied.
+#[derive(Debug, Default)]
+pub struct ProfileMergeCache {
+    entries: RwLock<HashMap<(String, u64), Arc<FhirSchema>>>,
+}
+
+impl ProfileMergeCache {
+    /// Create an empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The merged schema of `chain` (root type first, profile last), merging
+    /// it on first use.
+    pub fn get_or_merge(&self, chain: &[Arc<FhirSchema>]) -> Arc<FhirSchema> {
+        let Some(profile) = chain.last() else {
+            return Arc::new(FhirSchema::default());
+        };
+        let key = (profile.url.clone(), chain_fingerprint(chain));
+
+        if let Ok(entries) = self.entries.read()
+            && let Some(merged) = entries.get(&key)
+        {
+            return Arc::clone(merged);
+        }
+
+        let merged = Arc::new(merge_profile_chain(chain));
+        let mut entries = self.entries.write().unwrap_or_else(|e| e.into_inner());
+        Arc::clone(entries.entry(key).or_insert(merged))
+    }
+
+    /// Resolve the chain of `schema` through `provider` and return its merged
+    /// schema.
+    pub async fn merged(
+        &self,
+        provider: &dyn SchemaProvider,
+        schema: &FhirSchema,
+    ) -> Arc<FhirSchema> {
+        self.get_or_merge(&resolve_chain(provider, schema).await)
+    }
+
+    /// Number of merged schemas held.
+    pub fn len(&self) -> usize {
+        self.entries.read().map(|e| e.len()).unwrap_or(0)
+    }
+
+    /// Whether the cache holds no merged schemas.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drop every merged schema.
+    pub fn clear(&self) {
+        if let Ok(mut entries) = self.entries.write() {
+            entries.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embedded::{FhirVersion, get_schemas};
+    use crate::validation::InMemorySchemaProvider;
+
+    fn provider() -> InMemorySchemaProvider {
+        let schemas = get_schemas(FhirVersion::R4).unwrap();
+        InMemorySchemaProvider::from_map(
+            schemas
+                .iter()
+                .map(|(name, schema)| (name.clone(), Arc::new(schema.clone())))
+                .collect(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_merged_profile_is_cached() {
+        let provider = provider();
+        let mut profile = FhirSchema {
+            url: "http://example.org/StructureDefinition/named-patient".to_string(),
+            name: "NamedPatient".to_string(),
+            type_name: "Patient".to_string(),
+            kind: "resource".to_string(),
+            derivation: Some("constraint".to_string()),
+            base: Some("http://hl7.org/fhir/StructureDefinition/Patient".to_string()),
+            ..Default::default()
+        };
+        profile.elements = Some(HashMap::from([(
+            "name".to_string(),
+            FhirSchemaElement {
+                min: Some(1),
+                ..Default::default()
+            },
+        )]));
+
+        let chain = resolve_chain(&provider, &profile).await;
+        let names: Vec<&str> = chain.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["Resource", "DomainResource", "Patient", "NamedPatient"]
+        );
+
+        let cache = ProfileMergeCache::new();
+        let merged = cache.get_or_merge(&chain);
+        assert_eq!(merged.url, profile.url);
+        let name = &merged.elements.as_ref().unwrap()["name"];
+        assert_eq!(name.min, Some(1));
+        assert_eq!(name.type_name.as_deref(), Some("HumanName"));
+        assert!(merged.elements.as_ref().unwrap().contains_key("text"));
+
+        let again = cache.merged(&provider, &profile).await;
+        assert!(Arc::ptr_eq(&merged, &again));
+        assert_eq!(cache.len(), 1);
+
+        profile.version = Some("2.0.0".to_string());
+        let bumped = cache.merged(&provider, &profile).await;
+        assert!(!Arc::ptr_eq(&merged, &bumped));
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn test_merge_accumulates_slices() {
+        let element = |value: serde_json::Value| -> FhirSchemaElement {
+            serde_json::from_value(value).unwrap()
+        };
+        let base = element(serde_json::json!({
+            "type": "Identifier", "array": true,
+            "slicing": {"rules": "open", "slices": {"mrn": {"match": {"system": "urn:mrn"}, "min": 1}}}
+        }));
+        let overlay = element(serde_json::json!({
+            "slicing": {"rules": "closed", "slices": {
+                "mrn": {"max": 1},
+                "ssn": {"match": {"system": "urn:ssn"}}
+            }}
+        }));
+
+        let slicing = merge_elements(&base, &overlay).slicing.unwrap();
+        assert_eq!(slicing.rules.as_deref(), Some("closed"));
+        let slices = slicing.slices.unwrap();
+        assert_eq!((slices["mrn"].min, slices["mrn"].max), (Some(1), Some(1)));
+        assert!(slices["mrn"].match_value.is_some());
+        assert!(slices.contains_key("ssn"));
+    }
+}
@@ -28,11 +28,22 @@ use octofhir_fhir_model::{FhirPathEvaluator, Result as ModelResult, error::Model
 
 use super::model_provider::FhirSchemaModelProvider;
 use super::validation_provider::FhirSchemaValidationProvider;
-use crate::embedded::{FhirVersion, create_validation_context, get_schemas};
+use crate::embedded::{EmbeddedSchemas, FhirVersion};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::terminology::CachedTerminologyService;
 use crate::terminology::TerminologyService;
-use crate::types::FhirSchema;
+use crate::types::{FhirSchema, ValidationContext};
+use crate::validation::CacheTuning;
 use octofhir_fhir_model::provider::FhirVersion as ModelFhirVersion;
 
+/// Where a [`ValidationProviderBuilder`] takes its schemas from.
+enum Schemas {
+    /// The schemas embedded for the builder's FHIR version, decoded on demand
+    Embedded,
+    /// Caller-supplied schemas keyed by name
+    Custom(HashMap<String, FhirSchema>),
+}
+
 /// Builder for creating [`FhirSchemaValidationProvider`] instances.
 ///
 /// Provides a fluent API for configuring validation providers with various options:
@@ -52,9 +63,10 @@ use octofhir_fhir_model::provider::FhirVersion as ModelFhirVersion;
 /// ```
 pub struct ValidationProviderBuilder {
     fhir_version: FhirVersion,
-    schemas: Option<HashMap<String, FhirSchema>>,
+    schemas: Option<Schemas>,
     fhirpath_evaluator: Option<Arc<dyn FhirPathEvaluator>>,
     terminology_service: Option<Arc<dyn TerminologyService>>,
+    cache_tuning: Option<CacheTuning>,
 }
 
 impl ValidationProviderBuilder {
@@ -75,13 +87,15 @@ impl ValidationProviderBuilder {
             schemas: None,
             fhirpath_evaluator: None,
             terminology_service: None,
+            cache_tuning: None,
         }
     }
 
     /// Use embedded (pre-compiled) schemas for the specified FHIR version.
     ///
     /// This is the recommended option for most use cases as it provides
-    /// fast startup with all standard FHIR types pre-loaded.
+    /// fast startup with all standard FHIR types pre-loaded. If the version's
+    /// `embedded-*` feature is disabled, `build()` returns an error saying so.
     ///
     /// # Example
     ///
@@ -91,7 +105,7 @@ impl ValidationProviderBuilder {
     ///     .build()?;
     /// ```
     pub fn with_embedded_schemas(mut self) -> Self {
-        self.schemas = Some(get_schemas(self.fhir_version).clone());
+        self.schemas = Some(Schemas::Embedded);
         self
     }
 
@@ -118,7 +132,7 @@ impl ValidationProviderBuilder {
     ///     .build()?;
     /// ```
     pub fn with_schemas(mut self, schemas: HashMap<String, FhirSchema>) -> Self {
-        self.schemas = Some(schemas);
+        self.schemas = Some(Schemas::Custom(schemas));
         self
     }
 
@@ -184,12 +198,34 @@ impl ValidationProviderBuilder {
         self
     }
 
+    /// Size the provider's caches from a [`CacheTuning`] preset.
+    ///
+    /// The terminology service, if any, is wrapped in a
+    /// [`CachedTerminologyService`](crate::terminology::CachedTerminologyService)
+    /// sized by `tuning.terminology` (except on `wasm32`), so pass the
+    /// uncached service to [`with_terminology`](Self::with_terminology).
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let provider = ValidationProviderBuilder::new(FhirVersion::R4)
+    ///     .with_embedded_schemas()
+    ///     .with_terminology(remote_service)
+    ///     .with_cache_tuning(CacheTuning::server())
+    ///     .build()?;
+    /// ```
+    pub fn with_cache_tuning(mut self, tuning: CacheTuning) -> Self {
+        self.cache_tuning = Some(tuning);
+        self
+    }
+
     /// Build the validation provider.
     ///
     /// # Errors
     ///
     /// Returns an error if:
     /// - No schemas were provided (call `with_embedded_schemas()` or `with_schemas()` first)
+    /// - Embedded schemas were requested for a version not compiled into this build
     ///
     /// # Example
     ///
@@ -202,6 +238,7 @@ impl ValidationProviderBuilder {
         let schemas = self.schemas.ok_or_else(|| {
             ModelError::schema_load_error(
                 "No schemas provided. Call with_embedded_schemas() or with_schemas() before build()"
+                    .to_string(),
             )
         })?;
 
@@ -212,20 +249,42 @@ impl ValidationProviderBuilder {
             FhirVersion::R6 => ModelFhirVersion::R6,
         };
 
-        let schema_provider = Arc::new(FhirSchemaModelProvider::new(schemas, model_fhir_version));
-
-        let validation_context = create_validation_context(self.fhir_version);
+        let schema_provider = Arc::new(match schemas {
+            Schemas::Embedded => {
+                let embedded = EmbeddedSchemas::new(self.fhir_version)
+                    .map_err(|e| ModelError::schema_load_error(e.to_string()))?;
+                FhirSchemaModelProvider::from_embedded(embedded, model_fhir_version)
+            }
+            Schemas::Custom(schemas) => FhirSchemaModelProvider::new(schemas, model_fhir_version),
+        });
 
-        let mut provider = FhirSchemaValidationProvider::new(schema_provider, validation_context);
+        let mut provider =
+            FhirSchemaValidationProvider::new(schema_provider, ValidationContext::default());
 
         if let Some(evaluator) = self.fhirpath_evaluator {
             provider = provider.with_fhirpath_evaluator(evaluator);
         }
 
-        if let Some(terminology) = self.terminology_service {
+        #[cfg_attr(target_arch = "wasm32", allow(unused_mut))]
+        let mut terminology = self.terminology_service;
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(tuning) = &self.cache_tuning {
+            terminology = terminology.map(|service| {
+                Arc::new(CachedTerminologyService::new(
+                    service,
+                    tuning.terminology.clone(),
+                )) as Arc<dyn TerminologyService>
+            });
+        }
+
+        if let Some(terminology) = terminology {
             provider = provider.with_terminology_service(terminology);
         }
 
+        if let Some(tuning) = self.cache_tuning {
+            provider = provider.with_cache_tuning(tuning);
+        }
+
         Ok(provider)
     }
 }
@@ -256,4 +315,14 @@ mod tests {
             .build();
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_builder_with_cache_tuning() {
+        let result = ValidationProviderBuilder::new(FhirVersion::R4)
+            .with_schemas(HashMap::new())
+            .with_terminology(crate::terminology::core_terminology_service())
+            .with_cache_tuning(CacheTuning::lambda())
+            .build();
+        assert!(result.is_ok());
+    }
 }
@@ -63,7 +63,9 @@ pub mod validation_provider;
 
 // Re-export main types
 pub use builder::ValidationProviderBuilder;
-pub use model_provider::{DynamicSchemaProvider, EmbeddedSchemaProvider, FhirSchemaModelProvider};
+pub use model_provider::{
+    DynamicSchemaProvider, EmbeddedSchemaProvider, FhirSchemaModelProvider, ProfileInfo,
+};
 pub use validation_provider::{
     FhirSchemaValidationProvider, create_validation_provider_from_dynamic,
     create_validation_provider_from_embedded, create_validation_provider_with_fhirpath,
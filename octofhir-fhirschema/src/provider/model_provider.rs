@@ -4,14 +4,19 @@
 //! with schema-driven type checking following FHIR schema patterns.
 
 use async_trait::async_trait;
-use std::collections::HashMap;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
 
 use octofhir_fhir_model::{
     Result as ModelResult,
     provider::{ElementInfo, FhirVersion as ModelFhirVersion, ModelProvider, TypeInfo},
 };
 
+use crate::embedded::EmbeddedSchemas;
+use crate::search_params::{SearchParameter, SearchParameterRegistry};
 use crate::types::FhirSchema;
+use crate::validation::{InMemorySchemaProvider, SchemaProvider};
 
 /// Navigation result for testing purposes
 #[derive(Debug)]
@@ -20,6 +25,60 @@ pub struct NavigationResult {
     pub result_type: Option<TypeInfo>,
 }
 
+/// A profile constraining a base type, with the package it came from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProfileInfo {
+    /// Canonical URL of the profile
+    pub url: String,
+    /// Computable name
+    pub name: String,
+    /// Human-friendly title, when the StructureDefinition has one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Business version of the profile
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Definition the profile directly derives from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base: Option<String>,
+    /// Package the profile was loaded from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub package_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub package_version: Option<String>,
+}
+
+impl From<&FhirSchema> for ProfileInfo {
+    fn from(schema: &FhirSchema) -> Self {
+        Self {
+            url: schema.url.clone(),
+            name: schema.name.clone(),
+            title: schema.title.clone(),
+            version: schema.version.clone(),
+            base: schema.base.clone(),
+            package_name: schema.package_name.clone(),
+            package_version: schema.package_version.clone(),
+        }
+    }
+}
+
+/// Profiles among `schemas` whose type is `resource_type`, once per URL and
+/// sorted by URL. Schemas registered under several keys are listed once.
+fn collect_profiles<'s>(
+    schemas: impl IntoIterator<Item = &'s FhirSchema>,
+    resource_type: &str,
+    profiles: &mut BTreeMap<String, ProfileInfo>,
+) {
+    for schema in schemas {
+        if schema.type_name == resource_type
+            && schema.derivation.as_deref() == Some("constraint")
+            && !profiles.contains_key(&schema.url)
+        {
+            profiles.insert(schema.url.clone(), ProfileInfo::from(schema));
+        }
+    }
+}
+
 /// FHIR to FHIRPath type mapping - essential for type conversion
 const TYPE_MAPPING: &[(&str, &str)] = &[
     ("boolean", "Boolean"),
@@ -53,16 +112,106 @@ const TYPE_MAPPING: &[(&str, &str)] = &[
     ("Any", "Any"),
 ];
 
+/// The schemas a [`FhirSchemaModelProvider`] serves
+#[derive(Debug)]
+enum SchemaSet {
+    /// Schemas held by the provider
+    Owned {
+        schemas: HashMap<String, FhirSchema>,
+        /// URL to schema name mapping for O(1) lookup by URL
+        url_to_name: HashMap<String, String>,
+    },
+    /// A version's embedded schemas, deserialized as they are looked up
+    Embedded(EmbeddedSchemas),
+}
+
+impl SchemaSet {
+    fn owned(schemas: HashMap<String, FhirSchema>) -> Self {
+        let url_to_name = schemas
+            .iter()
+            .map(|(name, schema)| (schema.url.clone(), name.clone()))
+            .collect();
+        Self::Owned {
+            schemas,
+            url_to_name,
+        }
+    }
+
+    fn get(&self, name: &str) -> Option<&FhirSchema> {
+        match self {
+            Self::Owned { schemas, .. } => schemas.get(name),
+            Self::Embedded(embedded) => embedded.get(name),
+        }
+    }
+
+    fn get_by_url(&self, url: &str) -> Option<&FhirSchema> {
+        match self {
+            Self::Owned {
+                schemas,
+                url_to_name,
+            } => schemas.get(url_to_name.get(url)?),
+            Self::Embedded(embedded) => embedded.get_by_url(url),
+        }
+    }
+
+    fn contains(&self, name: &str) -> bool {
+        match self {
+            Self::Owned { schemas, .. } => schemas.contains_key(name),
+            Self::Embedded(embedded) => embedded.contains(name),
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Self::Owned { schemas, .. } => schemas.len(),
+            Self::Embedded(embedded) => embedded.len(),
+        }
+    }
+
+    /// Every schema; embedded ones are all deserialized
+    fn all(&self) -> &HashMap<String, FhirSchema> {
+        match self {
+            Self::Owned { schemas, .. } => schemas,
+            Self::Embedded(embedded) => embedded.all(),
+        }
+    }
+
+    /// Name and kind of every schema, without deserializing embedded ones
+    fn kinds(&self) -> Vec<(&String, String)> {
+        match self {
+            Self::Owned { schemas, .. } => schemas
+                .iter()
+                .map(|(name, schema)| (name, schema.kind.clone()))
+                .collect(),
+            Self::Embedded(embedded) => embedded.kinds().collect(),
+        }
+    }
+
+    /// The schemas as a [`SchemaProvider`] for a validator. Embedded schemas
+    /// are shared; owned ones are copied.
+    fn schema_provider(&self) -> Arc<dyn SchemaProvider> {
+        match self {
+            Self::Owned { schemas, .. } => Arc::new(InMemorySchemaProvider::from_map(
+                schemas
+                    .iter()
+                    .map(|(name, schema)| (name.clone(), Arc::new(schema.clone())))
+                    .collect(),
+            )),
+            Self::Embedded(embedded) => Arc::new(*embedded),
+        }
+    }
+}
+
 /// Production-ready FhirSchemaModelProvider with schema-driven functionality
 #[derive(Debug)]
 pub struct FhirSchemaModelProvider {
-    schemas: HashMap<String, FhirSchema>,
+    schemas: SchemaSet,
     type_mapping: HashMap<String, String>,
     fhir_version: ModelFhirVersion,
-    /// URL to schema name mapping for O(1) lookup by URL
-    url_to_name: HashMap<String, String>,
     /// Reverse mapping for FHIRPath types back to FHIR types
     reverse_type_mapping: HashMap<String, String>,
+    /// Search parameters of the core set and installed packages
+    search_parameters: SearchParameterRegistry,
 }
 
 impl FhirSchemaModelProvider {
@@ -192,6 +341,16 @@ impl FhirSchemaModelProvider {
 
     /// Create new provider with schemas and FHIR version
     pub fn new(schemas: HashMap<String, FhirSchema>, fhir_version: ModelFhirVersion) -> Self {
+        Self::with_schema_set(SchemaSet::owned(schemas), fhir_version)
+    }
+
+    /// Create a provider over a version's embedded schemas, deserializing
+    /// each one the first time it is needed rather than copying them all
+    pub fn from_embedded(schemas: EmbeddedSchemas, fhir_version: ModelFhirVersion) -> Self {
+        Self::with_schema_set(SchemaSet::Embedded(schemas), fhir_version)
+    }
+
+    fn with_schema_set(schemas: SchemaSet, fhir_version: ModelFhirVersion) -> Self {
         let type_mapping: HashMap<String, String> = TYPE_MAPPING
             .iter()
             .map(|(fhir_type, fhirpath_type)| (fhir_type.to_string(), fhirpath_type.to_string()))
@@ -203,35 +362,57 @@ impl FhirSchemaModelProvider {
             .map(|(fhir_type, fhirpath_type)| (fhirpath_type.to_string(), fhir_type.to_string()))
             .collect();
 
-        // Build URL to name mapping for O(1) lookup
-        let url_to_name: HashMap<String, String> = schemas
-            .iter()
-            .map(|(name, schema)| (schema.url.clone(), name.clone()))
-            .collect();
-
         Self {
             schemas,
             type_mapping,
             fhir_version,
-            url_to_name,
             reverse_type_mapping,
+            search_parameters: SearchParameterRegistry::new(),
         }
     }
 
+    /// Search parameters of a resource type with their code, type,
+    /// expression and reference targets (see [`SearchParameterRegistry::for_resource`])
+    pub fn search_parameters(&self, resource_type: &str) -> Vec<SearchParameter> {
+        self.search_parameters.for_resource(resource_type)
+    }
+
+    /// Add the SearchParameter resources among `resources`, e.g. those of an
+    /// installed package. They replace known parameters with the same code.
+    pub fn add_search_parameters<'r>(
+        &mut self,
+        resources: impl IntoIterator<Item = &'r serde_json::Value>,
+    ) {
+        self.search_parameters.add_resources(resources);
+    }
+
+    /// Profiles constraining `resource_type` (e.g. every Observation
+    /// profile), with their titles and package provenance, sorted by URL
+    pub fn profiles_for(&self, resource_type: &str) -> Vec<ProfileInfo> {
+        let mut profiles = BTreeMap::new();
+        collect_profiles(self.schemas.all().values(), resource_type, &mut profiles);
+        profiles.into_values().collect()
+    }
+
     /// Update schemas (for dynamic loading)
     pub fn update_schemas(&mut self, schemas: HashMap<String, FhirSchema>) {
-        // Rebuild URL to name mapping
-        self.url_to_name = schemas
-            .iter()
-            .map(|(name, schema)| (schema.url.clone(), name.clone()))
-            .collect();
-
-        self.schemas = schemas;
+        self.schemas = SchemaSet::owned(schemas);
     }
 
-    /// Get all schemas
+    /// Get all schemas. Embedded schemas are all deserialized by this, so
+    /// prefer the per-schema lookups.
     pub fn schemas(&self) -> &HashMap<String, FhirSchema> {
-        &self.schemas
+        self.schemas.all()
+    }
+
+    /// Number of schemas, without deserializing embedded ones
+    pub fn schema_count(&self) -> usize {
+        self.schemas.len()
+    }
+
+    /// The schemas as a [`SchemaProvider`] for a [`FhirValidator`](crate::FhirValidator)
+    pub fn validation_schemas(&self) -> Arc<dyn SchemaProvider> {
+        self.schemas.schema_provider()
     }
 
     /// Get a specific schema by URL or name
@@ -241,25 +422,14 @@ impl FhirSchemaModelProvider {
 
     /// Check if a schema exists by URL (supports both name and URL lookup)
     pub fn has_schema(&self, url_or_name: &str) -> bool {
-        // Check by name first (direct key lookup)
-        self.schemas.contains_key(url_or_name) ||
-        // Then check by URL (O(1) lookup)
-        self.url_to_name.contains_key(url_or_name)
+        self.schemas.contains(url_or_name) || self.schemas.get_by_url(url_or_name).is_some()
     }
 
     /// Get schema by URL or name
     pub fn get_schema_by_url_or_name(&self, url_or_name: &str) -> Option<&FhirSchema> {
-        // Try direct name lookup first
-        if let Some(schema) = self.schemas.get(url_or_name) {
-            return Some(schema);
-        }
-
-        // Try URL lookup with O(1) mapping
-        if let Some(name) = self.url_to_name.get(url_or_name) {
-            return self.schemas.get(name);
-        }
-
-        None
+        self.schemas
+            .get(url_or_name)
+            .or_else(|| self.schemas.get_by_url(url_or_name))
     }
 
     /// Map FHIR type to FHIRPath type using TYPE_MAPPING
@@ -571,21 +741,18 @@ impl ModelProvider for FhirSchemaModelProvider {
     async fn get_resource_types(&self) -> ModelResult<Vec<String>> {
         Ok(self
             .schemas
-            .keys()
-            .filter(|name| {
+            .kinds()
+            .into_iter()
+            .filter(|(name, kind)| {
                 // Check if it's a resource type by schema kind or naming convention
-                if let Some(schema) = self.schemas.get(*name) {
-                    schema.kind == "resource"
-                        || name
-                            .chars()
-                            .next()
-                            .map(|c| c.is_uppercase())
-                            .unwrap_or(false)
-                } else {
-                    false
-                }
+                kind == "resource"
+                    || name
+                        .chars()
+                        .next()
+                        .map(|c| c.is_uppercase())
+                        .unwrap_or(false)
             })
-            .cloned()
+            .map(|(name, _)| name.clone())
             .collect())
     }
 
@@ -593,17 +760,14 @@ impl ModelProvider for FhirSchemaModelProvider {
     async fn get_complex_types(&self) -> ModelResult<Vec<String>> {
         Ok(self
             .schemas
-            .keys()
-            .filter(|name| {
-                if let Some(schema) = self.schemas.get(*name) {
-                    // Only include actual complex types and resources, not primitives
-                    (schema.kind == "complex-type" || schema.kind == "resource")
-                        && !self.type_mapping.contains_key(*name)
-                } else {
-                    false
-                }
+            .kinds()
+            .into_iter()
+            .filter(|(name, kind)| {
+                // Only include actual complex types and resources, not primitives
+                (kind == "complex-type" || kind == "resource")
+                    && !self.type_mapping.contains_key(*name)
             })
-            .cloned()
+            .map(|(name, _)| name.clone())
             .collect())
     }
 
@@ -633,6 +797,19 @@ impl ModelProvider for FhirSchemaModelProvider {
     }
 }
 
+/// Embedded schema set for a model FHIR version; custom versions use R4
+fn embedded_version(fhir_version: &ModelFhirVersion) -> crate::embedded::FhirVersion {
+    use crate::embedded::FhirVersion;
+
+    match fhir_version {
+        ModelFhirVersion::R4 => FhirVersion::R4,
+        ModelFhirVersion::R4B => FhirVersion::R4B,
+        ModelFhirVersion::R5 => FhirVersion::R5,
+        ModelFhirVersion::R6 => FhirVersion::R6,
+        ModelFhirVersion::Custom { .. } => FhirVersion::R4,
+    }
+}
+
 /// Embedded schema provider using pre-bundled schemas for fastest startup
 #[derive(Debug)]
 pub struct EmbeddedSchemaProvider {
@@ -641,21 +818,51 @@ pub struct EmbeddedSchemaProvider {
 
 impl EmbeddedSchemaProvider {
     /// Create new embedded provider with bundled schemas for specified FHIR version
+    ///
+    /// # Panics
+    ///
+    /// If the version's embedded schemas are refused (see [`Self::try_new`]).
     pub fn new(fhir_version: ModelFhirVersion) -> Self {
-        use crate::embedded::{FhirVersion, get_schemas};
-
-        // Convert ModelFhirVersion to local FhirVersion
-        let local_version = match fhir_version {
-            ModelFhirVersion::R4 => FhirVersion::R4,
-            ModelFhirVersion::R4B => FhirVersion::R4B,
-            ModelFhirVersion::R5 => FhirVersion::R5,
-            ModelFhirVersion::R6 => FhirVersion::R6,
-            ModelFhirVersion::Custom { .. } => FhirVersion::R4, // Default to R4 for custom versions
+        Self::try_new(fhir_version).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Create new embedded provider with bundled schemas for specified FHIR
+    /// version, failing with [`FhirSchemaError::IntegrityCheckFailed`] when
+    /// the embedded bundle does not verify or decode. Versions left out of
+    /// the build via `embedded-*` features get an empty provider.
+    ///
+    /// [`FhirSchemaError::IntegrityCheckFailed`]: crate::FhirSchemaError::IntegrityCheckFailed
+    pub fn try_new(fhir_version: ModelFhirVersion) -> crate::Result<Self> {
+        use crate::FhirSchemaError;
+        use crate::embedded::get_search_parameters;
+
+        let local_version = embedded_version(&fhir_version);
+
+        let mut inner = match EmbeddedSchemas::new(local_version) {
+            Ok(schemas) => FhirSchemaModelProvider::from_embedded(schemas, fhir_version),
+            Err(FhirSchemaError::VersionNotEmbedded { .. }) => {
+                FhirSchemaModelProvider::new(HashMap::new(), fhir_version)
+            }
+            Err(e) => return Err(e),
         };
+        // Core search parameters need the `embedded-search-params` feature
+        if let Ok(registry) = get_search_parameters(local_version) {
+            inner.search_parameters = registry.clone();
+        }
+        Ok(Self { inner })
+    }
 
-        let schemas = get_schemas(local_version).clone();
-        let inner = FhirSchemaModelProvider::new(schemas, fhir_version);
-        Self { inner }
+    /// Create a provider with only the embedded schemas `resource_types`
+    /// depend on (see [`get_schema_closure`](crate::embedded::get_schema_closure)),
+    /// for processes with a startup or memory budget. The rest of the
+    /// version is never deserialized. Core search parameters are not loaded;
+    /// add the ones needed with [`Self::add_search_parameters`].
+    pub fn minimal<S: AsRef<str>>(fhir_version: ModelFhirVersion, resource_types: &[S]) -> Self {
+        let schemas =
+            crate::embedded::get_schema_closure(embedded_version(&fhir_version), resource_types);
+        Self {
+            inner: FhirSchemaModelProvider::new(schemas, fhir_version),
+        }
     }
 
     /// Convenience method to create R4 provider
@@ -685,12 +892,56 @@ impl EmbeddedSchemaProvider {
 
     /// Get the number of schemas in this provider
     pub fn schema_count(&self) -> usize {
-        self.inner.schemas.len()
+        self.inner.schema_count()
     }
 
-    /// Get access to all schemas
+    /// Get access to all schemas. This deserializes every embedded schema,
+    /// so prefer the per-schema lookups of [`ModelProvider`].
     pub fn schemas(&self) -> &std::collections::HashMap<String, crate::types::FhirSchema> {
-        &self.inner.schemas
+        self.inner.schemas()
+    }
+
+    /// Profiles constraining `resource_type` from the core schemas and the
+    /// profile packs embedded for this FHIR version. Pack profiles without
+    /// package metadata are attributed to the pack's package.
+    pub fn profiles_for(&self, resource_type: &str) -> Vec<ProfileInfo> {
+        use crate::embedded::{get_profile_pack, list_profile_packs};
+
+        let version = embedded_version(&self.inner.fhir_version);
+        let mut profiles = BTreeMap::new();
+        collect_profiles(self.inner.schemas().values(), resource_type, &mut profiles);
+        for pack in list_profile_packs() {
+            if pack.fhir_version != version {
+                continue;
+            }
+            let Ok(schemas) = get_profile_pack(pack.name, pack.version) else {
+                continue;
+            };
+            let mut from_pack = BTreeMap::new();
+            collect_profiles(schemas.values(), resource_type, &mut from_pack);
+            for (url, mut info) in from_pack {
+                if info.package_name.is_none() {
+                    info.package_name = Some(pack.package.to_string());
+                    info.package_version = Some(pack.version.to_string());
+                }
+                profiles.entry(url).or_insert(info);
+            }
+        }
+        profiles.into_values().collect()
+    }
+
+    /// Search parameters of a resource type, from the embedded core set and
+    /// any added with [`Self::add_search_parameters`]
+    pub fn search_parameters(&self, resource_type: &str) -> Vec<SearchParameter> {
+        self.inner.search_parameters(resource_type)
+    }
+
+    /// Add the SearchParameter resources of an installed package
+    pub fn add_search_parameters<'r>(
+        &mut self,
+        resources: impl IntoIterator<Item = &'r serde_json::Value>,
+    ) {
+        self.inner.add_search_parameters(resources);
     }
 
     /// Validate a resource against a specific profile URL (async)
@@ -702,18 +953,18 @@ impl EmbeddedSchemaProvider {
         use crate::validation::FhirValidator;
 
         // Create validator without FHIRPath evaluator (structural validation only)
-        let validator = FhirValidator::from_schemas(self.inner.schemas.clone(), None);
+        let validator = FhirValidator::new(self.inner.validation_schemas());
 
         // Find schema by URL
-        if let Some(schema) = self.inner.schemas.values().find(|s| s.url == profile_url) {
+        if let Some(schema) = self.inner.schemas.get_by_url(profile_url) {
             Ok(validator
                 .validate(resource, vec![schema.name.clone()])
                 .await)
         } else {
             Err(Box::new(crate::types::ValidationError {
-                error_type: "schema-not-found".to_string(),
-                path: vec![],
-                message: Some(format!("Profile not found: {profile_url}")),
+                error_type: "schema-not-found".into(),
+                path: crate::types::ErrorPath::default(),
+                message: Some(format!("Profile not found: {profile_url}").into()),
                 value: None,
                 expected: None,
                 got: None,
@@ -721,6 +972,9 @@ impl EmbeddedSchemaProvider {
                 constraint_key: None,
                 constraint_expression: None,
                 constraint_severity: None,
+                fix: None,
+                schema_url: None,
+                schema_version: None,
             }))
         }
     }
@@ -734,18 +988,18 @@ impl EmbeddedSchemaProvider {
         use crate::validation::FhirValidator;
 
         // Create validator without FHIRPath evaluator (structural validation only)
-        let validator = FhirValidator::from_schemas(self.inner.schemas.clone(), None);
+        let validator = FhirValidator::new(self.inner.validation_schemas());
 
         // Check if resource type exists
-        if self.inner.schemas.contains_key(resource_type) {
+        if self.inner.schemas.contains(resource_type) {
             Ok(validator
                 .validate(resource, vec![resource_type.to_string()])
                 .await)
         } else {
             Err(Box::new(crate::types::ValidationError {
-                error_type: "schema-not-found".to_string(),
-                path: vec![],
-                message: Some(format!("Resource type not found: {resource_type}")),
+                error_type: "schema-not-found".into(),
+                path: crate::types::ErrorPath::default(),
+                message: Some(format!("Resource type not found: {resource_type}").into()),
                 value: None,
                 expected: None,
                 got: None,
@@ -753,13 +1007,16 @@ impl EmbeddedSchemaProvider {
                 constraint_key: None,
                 constraint_expression: None,
                 constraint_severity: None,
+                fix: None,
+                schema_url: None,
+                schema_version: None,
             }))
         }
     }
 
     /// Check if a resource type exists
     pub async fn resource_type_exists(&self, resource_type: &str) -> Result<bool, String> {
-        Ok(self.inner.schemas.contains_key(resource_type))
+        Ok(self.inner.schemas.contains(resource_type))
     }
 
     /// Refresh resource types (no-op for embedded provider)
@@ -958,11 +1215,32 @@ impl DynamicSchemaProvider {
 
     /// Get the number of loaded schemas
     pub fn schema_count(&self) -> usize {
-        self.inner.schemas.len()
+        self.inner.schema_count()
     }
 
     pub fn schemas(&self) -> &HashMap<String, FhirSchema> {
-        &self.inner.schemas
+        self.inner.schemas()
+    }
+
+    /// Profiles constraining `resource_type` among the loaded schemas (see
+    /// [`FhirSchemaModelProvider::profiles_for`])
+    pub fn profiles_for(&self, resource_type: &str) -> Vec<ProfileInfo> {
+        self.inner.profiles_for(resource_type)
+    }
+
+    /// Search parameters of a resource type, from the SearchParameter
+    /// resources added with [`Self::add_search_parameters`]
+    pub fn search_parameters(&self, resource_type: &str) -> Vec<SearchParameter> {
+        self.inner.search_parameters(resource_type)
+    }
+
+    /// Add SearchParameter resources, e.g. the core set and those of
+    /// installed packages
+    pub fn add_search_parameters<'r>(
+        &mut self,
+        resources: impl IntoIterator<Item = &'r serde_json::Value>,
+    ) {
+        self.inner.add_search_parameters(resources);
     }
 }
 
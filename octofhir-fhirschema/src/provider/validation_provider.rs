@@ -8,9 +8,11 @@ use octofhir_fhir_model::{
 };
 
 use super::model_provider::FhirSchemaModelProvider;
-use crate::embedded::{FhirVersion, create_validation_context, get_schemas};
+use crate::embedded::{EmbeddedSchemas, FhirVersion};
+use crate::profiles::ProfileMergeCache;
 use crate::terminology::TerminologyService;
 use crate::types::ValidationContext;
+use crate::validation::CacheTuning;
 use octofhir_fhir_model::provider::FhirVersion as ModelFhirVersion;
 
 /// ValidationProvider implementation using FHIR schemas
@@ -22,6 +24,10 @@ pub struct FhirSchemaValidationProvider {
     fhirpath_evaluator: Option<Arc<dyn FhirPathEvaluator>>,
     /// Optional terminology service for binding validation
     terminology_service: Option<Arc<dyn TerminologyService>>,
+    /// Cache sizes for the validators this provider creates
+    cache_tuning: Option<CacheTuning>,
+    /// Merged profile chains, shared by the validators this provider creates
+    profile_cache: Arc<ProfileMergeCache>,
 }
 
 impl FhirSchemaValidationProvider {
@@ -35,6 +41,8 @@ impl FhirSchemaValidationProvider {
             validation_context,
             fhirpath_evaluator: None,
             terminology_service: None,
+            cache_tuning: None,
+            profile_cache: Arc::new(ProfileMergeCache::new()),
         }
     }
 
@@ -50,6 +58,23 @@ impl FhirSchemaValidationProvider {
         self
     }
 
+    /// Size the caches of the validators this provider creates
+    pub fn with_cache_tuning(mut self, tuning: CacheTuning) -> Self {
+        self.cache_tuning = Some(tuning);
+        self
+    }
+
+    /// Share a cache of merged profile chains with other consumers
+    pub fn with_profile_cache(mut self, cache: Arc<ProfileMergeCache>) -> Self {
+        self.profile_cache = cache;
+        self
+    }
+
+    /// The cache of merged profile chains
+    pub fn profile_cache(&self) -> &Arc<ProfileMergeCache> {
+        &self.profile_cache
+    }
+
     /// Create validation provider from EmbeddedModelProvider
     pub async fn from_embedded_provider(
         embedded_provider: Arc<dyn ModelProvider>,
@@ -64,8 +89,10 @@ impl FhirSchemaValidationProvider {
             ModelFhirVersion::Custom { .. } => FhirVersion::R4, // Default to R4 for custom versions
         };
 
-        let schema_provider = Arc::new(FhirSchemaModelProvider::new(
-            get_schemas(fhir_version).clone(),
+        let schemas = EmbeddedSchemas::new(fhir_version)
+            .map_err(|e| ModelError::schema_load_error(e.to_string()))?;
+        let schema_provider = Arc::new(FhirSchemaModelProvider::from_embedded(
+            schemas,
             model_fhir_version,
         ));
 
@@ -74,6 +101,8 @@ impl FhirSchemaValidationProvider {
             validation_context,
             fhirpath_evaluator: None,
             terminology_service: None,
+            cache_tuning: None,
+            profile_cache: Arc::new(ProfileMergeCache::new()),
         })
     }
 
@@ -91,8 +120,10 @@ impl FhirSchemaValidationProvider {
             ModelFhirVersion::Custom { .. } => FhirVersion::R4, // Default to R4 for custom versions
         };
 
-        let schema_provider = Arc::new(FhirSchemaModelProvider::new(
-            get_schemas(fhir_version).clone(),
+        let schemas = EmbeddedSchemas::new(fhir_version)
+            .map_err(|e| ModelError::schema_load_error(e.to_string()))?;
+        let schema_provider = Arc::new(FhirSchemaModelProvider::from_embedded(
+            schemas,
             model_fhir_version,
         ));
 
@@ -101,12 +132,15 @@ impl FhirSchemaValidationProvider {
             validation_context,
             fhirpath_evaluator: None,
             terminology_service: None,
+            cache_tuning: None,
+            profile_cache: Arc::new(ProfileMergeCache::new()),
         })
     }
 
     /// Create validation provider with embedded schemas
     pub fn with_embedded_schemas(fhir_version: FhirVersion) -> ModelResult<Self> {
-        let schemas = get_schemas(fhir_version);
+        let schemas = EmbeddedSchemas::new(fhir_version)
+            .map_err(|e| ModelError::schema_load_error(e.to_string()))?;
         let model_fhir_version = match fhir_version {
             FhirVersion::R4 => ModelFhirVersion::R4,
             FhirVersion::R4B => ModelFhirVersion::R4B,
@@ -114,18 +148,22 @@ impl FhirSchemaValidationProvider {
             FhirVersion::R6 => ModelFhirVersion::R6,
         };
 
-        let schema_provider = Arc::new(FhirSchemaModelProvider::new(
-            schemas.clone(),
+        let schema_provider = Arc::new(FhirSchemaModelProvider::from_embedded(
+            schemas,
             model_fhir_version,
         ));
 
-        let validation_context = create_validation_context(fhir_version);
+        // Validation reads schemas from the provider; copying them all into
+        // the context would defeat looking them up lazily
+        let validation_context = ValidationContext::default();
 
         Ok(Self {
             schema_provider,
             validation_context,
             fhirpath_evaluator: None,
             terminology_service: None,
+            cache_tuning: None,
+            profile_cache: Arc::new(ProfileMergeCache::new()),
         })
     }
 
@@ -229,15 +267,22 @@ impl ValidationProvider for FhirSchemaValidationProvider {
             })?;
 
         // Create FHIR Schema validator with all available schemas
-        let mut validator = crate::validation::FhirValidator::from_schemas(
-            self.schema_provider.schemas().clone(),
-            self.fhirpath_evaluator.clone(),
-        );
+        let schemas = self.schema_provider.validation_schemas();
+        let mut validator = match &self.fhirpath_evaluator {
+            Some(evaluator) => {
+                crate::validation::FhirValidator::new_with_fhirpath(schemas, Arc::clone(evaluator))
+            }
+            None => crate::validation::FhirValidator::new(schemas),
+        }
+        .with_profile_cache(Arc::clone(&self.profile_cache));
 
         // Add terminology service if available
         if let Some(terminology) = &self.terminology_service {
             validator = validator.with_terminology_service(terminology.clone());
         }
+        if let Some(tuning) = &self.cache_tuning {
+            validator = validator.with_cache_tuning(tuning);
+        }
 
         // Validate using the comprehensive FHIR Schema validation engine (async)
         let validation_result = validator
@@ -262,15 +307,7 @@ impl ValidationProvider for FhirSchemaValidationProvider {
 pub async fn create_validation_provider_from_embedded(
     embedded_provider: Arc<dyn ModelProvider>,
 ) -> ModelResult<Arc<dyn ValidationProvider>> {
-    let model_fhir_version = embedded_provider.get_fhir_version().await?;
-    let fhir_version = match model_fhir_version {
-        ModelFhirVersion::R4 => FhirVersion::R4,
-        ModelFhirVersion::R4B => FhirVersion::R4B,
-        ModelFhirVersion::R5 => FhirVersion::R5,
-        ModelFhirVersion::R6 => FhirVersion::R6,
-        ModelFhirVersion::Custom { .. } => FhirVersion::R4,
-    };
-    let validation_context = create_validation_context(fhir_version);
+    let validation_context = ValidationContext::default();
 
     // The EmbeddedModelProvider internally uses FhirSchemaModelProvider with embedded schemas
     // We extract those same schemas to create our ValidationProvider
@@ -286,15 +323,7 @@ pub async fn create_validation_provider_from_embedded(
 pub async fn create_validation_provider_from_dynamic(
     dynamic_provider: Arc<dyn ModelProvider>,
 ) -> ModelResult<Arc<dyn ValidationProvider>> {
-    let model_fhir_version = dynamic_provider.get_fhir_version().await?;
-    let fhir_version = match model_fhir_version {
-        ModelFhirVersion::R4 => FhirVersion::R4,
-        ModelFhirVersion::R4B => FhirVersion::R4B,
-        ModelFhirVersion::R5 => FhirVersion::R5,
-        ModelFhirVersion::R6 => FhirVersion::R6,
-        ModelFhirVersion::Custom { .. } => FhirVersion::R4,
-    };
-    let validation_context = create_validation_context(fhir_version);
+    let validation_context = ValidationContext::default();
 
     // The DynamicModelProvider internally uses FhirSchemaModelProvider with dynamic schemas
     // We extract those same schemas to create our ValidationProvider
@@ -313,15 +342,7 @@ pub async fn create_validation_provider_with_fhirpath(
     model_provider: Arc<dyn ModelProvider>,
     fhirpath_evaluator: Arc<dyn FhirPathEvaluator>,
 ) -> ModelResult<Arc<dyn ValidationProvider>> {
-    let model_fhir_version = model_provider.get_fhir_version().await?;
-    let fhir_version = match model_fhir_version {
-        ModelFhirVersion::R4 => FhirVersion::R4,
-        ModelFhirVersion::R4B => FhirVersion::R4B,
-        ModelFhirVersion::R5 => FhirVersion::R5,
-        ModelFhirVersion::R6 => FhirVersion::R6,
-        ModelFhirVersion::Custom { .. } => FhirVersion::R4,
-    };
-    let validation_context = create_validation_context(fhir_version);
+    let validation_context = ValidationContext::default();
 
     let validation_provider =
         FhirSchemaValidationProvider::from_embedded_provider(model_provider, validation_context)
@@ -366,6 +387,7 @@ mod tests {
             base: Some("http://hl7.org/fhir/StructureDefinition/Patient".to_string()),
             abstract_type: None,
             class: "resource".to_string(),
+            title: None,
             description: None,
             package_name: None,
             package_version: None,
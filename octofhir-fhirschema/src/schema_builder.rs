@@ -0,0 +1,444 @@
+//! Runtime profiles built in code.
+//!
+//! [`SchemaBuilder`] derives a constraint [`FhirSchema`] from a base schema
+//! without authoring a StructureDefinition: it records only what changes
+//! (cardinalities, bindings, patterns, slices, constraints) and points its
+//! `base` at the base schema, like a converted profile differential. The
+//! schema compiler merges it with its chain as for any other profile.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use octofhir_fhirschema::{BindingStrength, SchemaBuilder, Slice};
+//!
+//! let profile = SchemaBuilder::from(get_schema(FhirVersion::R4, "Patient").unwrap())
+//!     .url("http://example.org/StructureDefinition/AppPatient")
+//!     .require("identifier")
+//!     .bind("gender", "http://hl7.org/fhir/ValueSet/administrative-gender", BindingStrength::Required)
+//!     .slice("identifier", "mrn", Slice::new(json!({"system": "urn:mrn"})).min(1).max(1))
+//!     .build()?;
+//! ```
+
+use std::collections::HashMap;
+
+use serde_json::Value as JsonValue;
+
+use crate::error::{FhirSchemaError, Result};
+use crate::terminology::BindingStrength;
+use crate::types::{
+    FhirSchema, FhirSchemaBinding, FhirSchemaConstraint, FhirSchemaDiscriminator,
+    FhirSchemaElement, FhirSchemaPattern, FhirSchemaSliceMatch, FhirSchemaSlicing,
+};
+
+/// A slice added by [`SchemaBuilder::slice`].
+#[derive(Debug, Clone)]
+pub struct Slice {
+    pattern: JsonValue,
+    discriminators: Vec<FhirSchemaDiscriminator>,
+    min: Option<i32>,
+    max: Option<i32>,
+}
+
+impl Slice {
+    /// A slice of the items matching `pattern`. Unless discriminators are
+    /// given, each top-level key of the pattern is a `pattern`
+    /// discriminator.
+    pub fn new(pattern: JsonValue) -> Self {
+        Self {
+            pattern,
+            discriminators: Vec::new(),
+            min: None,
+            max: None,
+        }
+    }
+
+    /// Discriminate on `path` with `type_name` (value, pattern, exists, ...).
+    pub fn discriminator(mut self, type_name: &str, path: &str) -> Self {
+        self.discriminators.push(FhirSchemaDiscriminator {
+            type_name: type_name.to_string(),
+            path: path.to_string(),
+        });
+        self
+    }
+
+    pub fn min(mut self, min: i32) -> Self {
+        self.min = Some(min);
+        self
+    }
+
+    pub fn max(mut self, max: i32) -> Self {
+        self.max = Some(max);
+        self
+    }
+
+    fn discriminators(&self) -> Vec<FhirSchemaDiscriminator> {
+        if !self.discriminators.is_empty() {
+            return self.discriminators.clone();
+        }
+        self.pattern
+            .as_object()
+            .into_iter()
+            .flat_map(|pattern| pattern.keys())
+            .map(|key| FhirSchemaDiscriminator {
+                type_name: "pattern".to_string(),
+                path: key.clone(),
+            })
+            .collect()
+    }
+}
+
+/// Fluent builder of a profile derived from a base schema.
+///
+/// Element paths are relative to the type (`identifier`,
+/// `contact.name`). Paths are checked against the base schema as far as it
+/// defines elements inline; problems are reported together by
+/// [`SchemaBuilder::build`].
+pub struct SchemaBuilder<'b> {
+    base: &'b FhirSchema,
+    schema: FhirSchema,
+    errors: Vec<FhirSchemaError>,
+}
+
+impl<'b> From<&'b FhirSchema> for SchemaBuilder<'b> {
+    fn from(base: &'b FhirSchema) -> Self {
+        let schema = FhirSchema {
+            name: format!("{}Profile", base.name),
+            type_name: base.type_name.clone(),
+            kind: base.kind.clone(),
+            class: "profile".to_string(),
+            derivation: Some("constraint".to_string()),
+            base: Some(base.url.clone()),
+            ..Default::default()
+        };
+        Self {
+            base,
+            schema,
+            errors: Vec::new(),
+        }
+    }
+}
+
+impl<'b> SchemaBuilder<'b> {
+    /// Canonical URL of the profile. Required.
+    pub fn url(mut self, url: &str) -> Self {
+        self.schema.url = url.to_string();
+        self
+    }
+
+    pub fn name(mut self, name: &str) -> Self {
+        self.schema.name = name.to_string();
+        self
+    }
+
+    pub fn version(mut self, version: &str) -> Self {
+        self.schema.version = Some(version.to_string());
+        self
+    }
+
+    /// Require at least one `path` value.
+    pub fn require(mut self, path: &str) -> Self {
+        if !self.check_defined(path) {
+            return self;
+        }
+        let (parent, name) = path.rsplit_once('.').unwrap_or(("", path));
+        let required = if parent.is_empty() {
+            Some(&mut self.schema.required)
+        } else {
+            self.element(parent).map(|element| &mut element.required)
+        };
+        if let Some(required) = required {
+            let required = required.get_or_insert_with(Vec::new);
+            if !required.iter().any(|r| r == name) {
+                required.push(name.to_string());
+            }
+        }
+        self.with_element(path, |element| {
+            element.min = Some(element.min.unwrap_or(0).max(1));
+        })
+    }
+
+    /// Forbid `path`.
+    pub fn exclude(mut self, path: &str) -> Self {
+        if !self.check_defined(path) {
+            return self;
+        }
+        let (parent, name) = path.rsplit_once('.').unwrap_or(("", path));
+        let excluded = if parent.is_empty() {
+            Some(&mut self.schema.excluded)
+        } else {
+            self.element(parent).map(|element| &mut element.excluded)
+        };
+        if let Some(excluded) = excluded {
+            excluded.get_or_insert_with(Vec::new).push(name.to_string());
+        }
+        self.with_element(path, |element| element.max = Some(0))
+    }
+
+    /// Narrow the cardinality of `path`; `max` of `None` keeps the base's.
+    pub fn cardinality(mut self, path: &str, min: i32, max: Option<i32>) -> Self {
+        if max.is_some_and(|max| max < min) || min < 0 {
+            self.errors
+                .push(FhirSchemaError::invalid_cardinality(min, max.unwrap_or(-1)));
+            return self;
+        }
+        self.with_element(path, |element| {
+            element.min = Some(min);
+            if max.is_some() {
+                element.max = max;
+            }
+        })
+    }
+
+    /// Bind the coded element at `path` to `value_set`.
+    pub fn bind(self, path: &str, value_set: &str, strength: BindingStrength) -> Self {
+        let strength = match strength {
+            BindingStrength::Required => "required",
+            BindingStrength::Extensible => "extensible",
+            BindingStrength::Preferred => "preferred",
+            BindingStrength::Example => "example",
+        };
+        self.with_element(path, |element| {
+            element.binding = Some(FhirSchemaBinding {
+                strength: strength.to_string(),
+                value_set: Some(value_set.to_string()),
+                binding_name: None,
+            });
+        })
+    }
+
+    /// Require the value at `path` to contain `value` of FHIR type
+    /// `type_name`.
+    pub fn pattern(self, path: &str, type_name: &str, value: JsonValue) -> Self {
+        self.with_element(path, |element| {
+            element.pattern = Some(FhirSchemaPattern {
+                type_name: type_name.to_string(),
+                string: value.as_str().map(str::to_string),
+                value,
+            });
+        })
+    }
+
+    /// Add the slice `name` to the array at `path`. Slicing stays open.
+    pub fn slice(mut self, path: &str, name: &str, slice: Slice) -> Self {
+        if !self.is_array(path) {
+            self.errors.push(FhirSchemaError::invalid_slice(format!(
+                "{path} is not an array and cannot be sliced"
+            )));
+            return self;
+        }
+        let discriminators = slice.discriminators();
+        self.with_element(path, |element| {
+            let slicing = element.slicing.get_or_insert_with(|| FhirSchemaSlicing {
+                discriminator: None,
+                rules: Some("open".to_string()),
+                ordered: None,
+                slices: None,
+            });
+            let known = slicing.discriminator.get_or_insert_with(Vec::new);
+            for discriminator in discriminators {
+                if !known
+                    .iter()
+                    .any(|d| d.path == discriminator.path && d.type_name == discriminator.type_name)
+                {
+                    known.push(discriminator);
+                }
+            }
+            slicing.slices.get_or_insert_with(HashMap::new).insert(
+                name.to_string(),
+                FhirSchemaSliceMatch {
+                    match_value: Some(slice.pattern),
+                    schema: None,
+                    min: slice.min,
+                    max: slice.max,
+                },
+            );
+        })
+    }
+
+    /// Add the FHIRPath invariant `key` on the resource.
+    pub fn constraint(mut self, key: &str, expression: &str, human: &str) -> Self {
+        self.schema
+            .constraint
+            .get_or_insert_with(HashMap::new)
+            .insert(
+                key.to_string(),
+                FhirSchemaConstraint {
+                    expression: expression.to_string(),
+                    human: human.to_string(),
+                    severity: "error".to_string(),
+                    requirements: None,
+                    source: None,
+                    suppress: None,
+                    best_practice: None,
+                },
+            );
+        self
+    }
+
+    /// The derived schema, or every problem found while building it.
+    pub fn build(mut self) -> Result<FhirSchema> {
+        if self.schema.url.is_empty() {
+            self.errors
+                .insert(0, FhirSchemaError::missing_required_field("url"));
+        }
+        match self.errors.len() {
+            0 => Ok(self.schema),
+            1 => Err(self.errors.remove(0)),
+            _ => Err(FhirSchemaError::multiple_errors(self.errors)),
+        }
+    }
+
+    /// Apply `change` to the overlay element at `path`, if the base has it.
+    fn with_element(mut self, path: &str, change: impl FnOnce(&mut FhirSchemaElement)) -> Self {
+        if let Some(element) = self.element(path) {
+            change(element);
+        }
+        self
+    }
+
+    /// The overlay element at `path`, created on first use. Records an
+    /// error when the base does not define it.
+    fn element(&mut self, path: &str) -> Option<&mut FhirSchemaElement> {
+        if !self.check_defined(path) {
+            return None;
+        }
+        let mut segments = path.split('.');
+        let first = segments.next()?;
+        let mut element = self
+            .schema
+            .elements
+            .get_or_insert_with(HashMap::new)
+            .entry(first.to_string())
+            .or_default();
+        for segment in segments {
+            element = element
+                .elements
+                .get_or_insert_with(HashMap::new)
+                .entry(segment.to_string())
+                .or_default();
+        }
+        Some(element)
+    }
+
+    /// Whether the base defines `path`, recording an error when not.
+    fn check_defined(&mut self, path: &str) -> bool {
+        let defined = self.base_defines(path);
+        if !defined {
+            self.errors.push(FhirSchemaError::unknown_element(
+                path,
+                self.base.type_name.as_str(),
+            ));
+        }
+        defined
+    }
+
+    /// The base definition of `path`, when the base defines it inline.
+    fn base_element(&self, path: &str) -> Option<&'b FhirSchemaElement> {
+        let mut elements = self.base.elements.as_ref();
+        let mut found = None;
+        for segment in path.split('.') {
+            let element = elements?.get(segment)?;
+            elements = element.elements.as_ref();
+            found = Some(element);
+        }
+        found
+    }
+
+    /// Whether the base defines `path`. Below an element typed with a
+    /// complex type, whose children live in that type's schema, any path is
+    /// accepted.
+    fn base_defines(&self, path: &str) -> bool {
+        let mut elements = self.base.elements.as_ref();
+        for segment in path.split('.') {
+            let Some(current) = elements else {
+                return true;
+            };
+            let Some(element) = current.get(segment) else {
+                return false;
+            };
+            elements = element.elements.as_ref();
+        }
+        true
+    }
+
+    fn is_array(&self, path: &str) -> bool {
+        self.base_element(path)
+            .is_none_or(|element| element.array == Some(true))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embedded::{FhirVersion, get_schemas};
+    use crate::validation::FhirValidator;
+    use serde_json::json;
+
+    const PROFILE: &str = "http://example.org/StructureDefinition/AppPatient";
+
+    #[tokio::test]
+    async fn test_built_profile_validates() {
+        let mut schemas = get_schemas(FhirVersion::R4).unwrap().clone();
+        let profile = SchemaBuilder::from(&schemas["Patient"])
+            .url(PROFILE)
+            .name("AppPatient")
+            .require("identifier")
+            .bind(
+                "gender",
+                "http://hl7.org/fhir/ValueSet/administrative-gender",
+                BindingStrength::Required,
+            )
+            .slice(
+                "identifier",
+                "mrn",
+                Slice::new(json!({"system": "urn:mrn"})).min(1),
+            )
+            .build()
+            .unwrap();
+        assert_eq!(
+            profile.required.as_deref(),
+            Some(&["identifier".to_string()][..])
+        );
+        schemas.insert("AppPatient".to_string(), profile);
+        let validator = FhirValidator::from_schemas(schemas, None);
+        let profiles = [PROFILE.to_string()];
+
+        let valid = json!({
+            "resourceType": "Patient",
+            "identifier": [{"system": "urn:mrn", "value": "1"}],
+            "gender": "female"
+        });
+        let result = validator.validate_with_profiles(&valid, &profiles).await;
+        assert!(result.valid, "errors: {:?}", result.errors);
+
+        let missing = json!({"resourceType": "Patient", "gender": "female"});
+        let result = validator.validate_with_profiles(&missing, &profiles).await;
+        assert!(!result.valid);
+        assert!(
+            result
+                .errors
+                .iter()
+                .all(|e| e.schema_url.as_deref() == Some(PROFILE))
+        );
+    }
+
+    #[test]
+    fn test_build_reports_problems() {
+        let schemas = get_schemas(FhirVersion::R4).unwrap();
+        let err = SchemaBuilder::from(&schemas["Patient"])
+            .require("nickname")
+            .slice("gender", "x", Slice::new(json!("female")))
+            .cardinality("name", 2, Some(1))
+            .build()
+            .unwrap_err();
+        let errors = match err {
+            FhirSchemaError::MultipleErrors { errors } => errors,
+            other => panic!("expected several errors, got {other}"),
+        };
+        assert_eq!(errors.len(), 4);
+        assert!(matches!(
+            errors[0],
+            FhirSchemaError::MissingRequiredField { .. }
+        ));
+    }
+}
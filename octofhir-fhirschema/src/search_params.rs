@@ -0,0 +1,311 @@
+//! Search parameters per resource type.
+//!
+//! Servers need the search parameters of each resource type: their code,
+//! type, the FHIRPath expression that extracts the indexed values and the
+//! resource types a reference parameter points to. [`SearchParameterRegistry`]
+//! collects them from SearchParameter resources, the embedded core set (the
+//! `embedded-search-params` feature) and those of installed packages alike.
+//!
+//! Core parameters are often shared between resource types
+//! (`Patient.name | Practitioner.name`); [`SearchParameterRegistry::for_resource`]
+//! narrows their expression to the requested type.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use octofhir_fhirschema::{FhirVersion, get_search_parameters};
+//!
+//! let registry = get_search_parameters(FhirVersion::R4)?;
+//! for param in registry.for_resource("Patient") {
+//!     println!("{} ({}): {:?}", param.code, param.param_type, param.expression);
+//! }
+//! ```
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+/// Resource types that do not derive from DomainResource, so the parameters
+/// defined on DomainResource (`_text`) do not apply to them.
+const NON_DOMAIN_RESOURCES: &[&str] = &["Binary", "Bundle", "Parameters"];
+
+/// A search parameter, converted from a SearchParameter resource.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchParameter {
+    /// Canonical URL of the SearchParameter
+    pub url: String,
+    /// Code used in search URLs (`name`, `_id`)
+    pub code: String,
+    /// Parameter type: number | date | string | token | reference |
+    /// composite | quantity | uri | special
+    #[serde(rename = "type")]
+    pub param_type: String,
+    /// FHIRPath expression extracting the indexed values
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expression: Option<String>,
+    /// Resource types the parameter is defined on
+    #[serde(default)]
+    pub base: Vec<String>,
+    /// Resource types a reference parameter may point to
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub target: Vec<String>,
+}
+
+impl SearchParameter {
+    /// Convert a SearchParameter resource. `None` for other resources and
+    /// for SearchParameters without a code, type or base.
+    pub fn from_resource(resource: &JsonValue) -> Option<Self> {
+        if resource.get("resourceType")?.as_str()? != "SearchParameter" {
+            return None;
+        }
+        let string = |key: &str| resource.get(key)?.as_str().map(str::to_string);
+        let strings = |key: &str| -> Vec<String> {
+            resource
+                .get(key)
+                .and_then(JsonValue::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(|v| v.as_str().map(str::to_string))
+                .collect()
+        };
+        let base = strings("base");
+        if base.is_empty() {
+            return None;
+        }
+        Some(Self {
+            url: string("url").unwrap_or_default(),
+            code: string("code")?,
+            param_type: string("type")?,
+            expression: string("expression"),
+            base,
+            target: strings("target"),
+        })
+    }
+
+    /// The parts of the expression that apply to `resource_type`. Shared
+    /// parameters join one path per type with `|`; the whole expression is
+    /// kept when no part names a type (`Resource.id`) or none names this one.
+    pub fn expression_for(&self, resource_type: &str) -> Option<String> {
+        let expression = self.expression.as_deref()?;
+        let parts = split_union(expression);
+        let own: Vec<&str> = parts
+            .iter()
+            .copied()
+            .filter(|part| starts_with_type(part, resource_type))
+            .collect();
+        Some(if own.is_empty() {
+            expression.to_string()
+        } else {
+            own.join(" | ")
+        })
+    }
+}
+
+/// Whether the expression `part` starts at `resource_type`, allowing for
+/// leading parentheses: `(Observation.value as Quantity)`.
+fn starts_with_type(part: &str, resource_type: &str) -> bool {
+    part.trim_start_matches(['(', ' '])
+        .strip_prefix(resource_type)
+        .is_some_and(|rest| !rest.starts_with(|c: char| c.is_ascii_alphanumeric()))
+}
+
+/// Split a FHIRPath expression on the `|` operators outside parentheses and
+/// string literals.
+fn split_union(expression: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut quote, mut start) = (0usize, None, 0);
+    for (i, c) in expression.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"' | '`') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') => depth = depth.saturating_sub(1),
+            (None, '|') if depth == 0 => {
+                parts.push(expression[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(expression[start..].trim());
+    parts
+}
+
+/// Search parameters indexed by the resource type they are defined on.
+#[derive(Debug, Clone, Default)]
+pub struct SearchParameterRegistry {
+    by_base: HashMap<String, Vec<SearchParameter>>,
+}
+
+impl SearchParameterRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Collect the SearchParameter resources among `resources`; other
+    /// resources are skipped.
+    pub fn from_resources<'r>(resources: impl IntoIterator<Item = &'r JsonValue>) -> Self {
+        let mut registry = Self::new();
+        registry.add_resources(resources);
+        registry
+    }
+
+    /// Add the SearchParameter resources among `resources`, e.g. those of an
+    /// installed package.
+    pub fn add_resources<'r>(&mut self, resources: impl IntoIterator<Item = &'r JsonValue>) {
+        for param in resources
+            .into_iter()
+            .filter_map(SearchParameter::from_resource)
+        {
+            self.add(param);
+        }
+    }
+
+    /// Add `param` to each of its base types. A parameter with the code of
+    /// one already defined on a type replaces it there, so package
+    /// parameters override core ones.
+    pub fn add(&mut self, param: SearchParameter) {
+        for base in &param.base {
+            let params = self.by_base.entry(base.clone()).or_default();
+            params.retain(|known| known.code != param.code);
+            params.push(param.clone());
+        }
+    }
+
+    /// The search parameters of `resource_type`, including those every
+    /// resource inherits (`_id`, `_lastUpdated`, ...), ordered by code. Each
+    /// expression is narrowed to the type.
+    pub fn for_resource(&self, resource_type: &str) -> Vec<SearchParameter> {
+        let mut inherited = vec!["Resource"];
+        if !NON_DOMAIN_RESOURCES.contains(&resource_type) {
+            inherited.push("DomainResource");
+        }
+
+        let mut by_code: HashMap<&str, &SearchParameter> = HashMap::new();
+        for base in inherited.into_iter().chain([resource_type]) {
+            for param in self.by_base.get(base).into_iter().flatten() {
+                by_code.insert(&param.code, param);
+            }
+        }
+
+        let mut params: Vec<SearchParameter> = by_code
+            .into_values()
+            .map(|param| SearchParameter {
+                expression: param.expression_for(resource_type),
+                ..param.clone()
+            })
+            .collect();
+        params.sort_by(|a, b| a.code.cmp(&b.code));
+        params
+    }
+
+    /// Every search parameter, deduplicated across base types.
+    pub fn all(&self) -> Vec<&SearchParameter> {
+        let mut seen = std::collections::HashSet::new();
+        let mut params: Vec<&SearchParameter> = self
+            .by_base
+            .values()
+            .flatten()
+            .filter(|param| seen.insert((param.url.as_str(), param.code.as_str())))
+            .collect();
+        params.sort_by(|a, b| (&a.code, &a.url).cmp(&(&b.code, &b.url)));
+        params
+    }
+
+    /// Number of resource types with search parameters.
+    pub fn len(&self) -> usize {
+        self.by_base.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_base.is_empty()
+    }
+}
+
+impl FromIterator<SearchParameter> for SearchParameterRegistry {
+    fn from_iter<I: IntoIterator<Item = SearchParameter>>(iter: I) -> Self {
+        let mut registry = Self::new();
+        for param in iter {
+            registry.add(param);
+        }
+        registry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn search_parameter(code: &str, kind: &str, base: &[&str], expression: &str) -> JsonValue {
+        json!({
+            "resourceType": "SearchParameter",
+            "url": format!("http://hl7.org/fhir/SearchParameter/{code}"),
+            "code": code, "type": kind, "base": base, "expression": expression
+        })
+    }
+
+    #[test]
+    fn test_for_resource() {
+        let mut general = search_parameter(
+            "general-practitioner",
+            "reference",
+            &["Patient"],
+            "Patient.generalPractitioner",
+        );
+        general["target"] = json!(["Organization", "Practitioner"]);
+        let resources = [
+            search_parameter("_id", "token", &["Resource"], "Resource.id"),
+            search_parameter(
+                "_text",
+                "string",
+                &["DomainResource"],
+                "DomainResource.text",
+            ),
+            search_parameter(
+                "name",
+                "string",
+                &["Patient", "Practitioner"],
+                "Patient.name | (Practitioner.name)",
+            ),
+            general,
+            json!({"resourceType": "Patient"}),
+        ];
+        let registry = SearchParameterRegistry::from_resources(&resources);
+
+        let patient = registry.for_resource("Patient");
+        let codes: Vec<&str> = patient.iter().map(|p| p.code.as_str()).collect();
+        assert_eq!(codes, ["_id", "_text", "general-practitioner", "name"]);
+        assert_eq!(patient[3].expression.as_deref(), Some("Patient.name"));
+        assert_eq!(patient[2].target, ["Organization", "Practitioner"]);
+        assert_eq!(
+            registry.for_resource("Practitioner")[2]
+                .expression
+                .as_deref(),
+            Some("(Practitioner.name)")
+        );
+        let bundle: Vec<String> = registry
+            .for_resource("Bundle")
+            .into_iter()
+            .map(|p| p.code)
+            .collect();
+        assert_eq!(bundle, ["_id"]);
+
+        // A package parameter with the same code replaces the core one.
+        let mut registry = registry;
+        registry.add_resources([&search_parameter(
+            "name",
+            "string",
+            &["Patient"],
+            "Patient.name.family",
+        )]);
+        let name = registry
+            .for_resource("Patient")
+            .into_iter()
+            .find(|p| p.code == "name")
+            .unwrap();
+        assert_eq!(name.expression.as_deref(), Some("Patient.name.family"));
+    }
+}
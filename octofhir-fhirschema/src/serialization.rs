@@ -0,0 +1,293 @@
+//! Reading and writing schemas in JSON, YAML, MessagePack and CBOR.
+//!
+//! JSON is always available. The other formats are behind features:
+//!
+//! - `yaml` - human-editable schemas, e.g. reviewed in pull requests
+//! - `msgpack` - compact MessagePack, fastest to decode in full
+//! - `cbor` - compact CBOR, for stores that standardize on it
+//!
+//! Any of them may additionally be zstd-compressed. [`load_schema_file`] and
+//! [`load_schema_bundle`] pick the format from the file name and fall back to
+//! sniffing the content, so importers can accept whatever they are given.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use octofhir_fhirschema::serialization::{BundleFormat, encode_schema, load_schema_file};
+//!
+//! let schema = load_schema_file(Path::new("us-core-patient.yaml"))?;
+//! std::fs::write("us-core-patient.cbor", encode_schema(&schema, BundleFormat::Cbor)?)?;
+//! ```
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::error::{FhirSchemaError, Result};
+use crate::types::FhirSchema;
+
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Serialization format of a schema or schema bundle file.
+///
+/// The embedded bundles are always zstd-compressed JSON, which lets lookups
+/// decode single schemas lazily. Files loaded at runtime may use any format
+/// whose feature is enabled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BundleFormat {
+    Json,
+    MessagePack,
+    Yaml,
+    Cbor,
+}
+
+impl BundleFormat {
+    /// File extension used for this format, before any `.zst` suffix
+    pub fn extension(&self) -> &'static str {
+        match self {
+            BundleFormat::Json => "json",
+            BundleFormat::MessagePack => "msgpack",
+            BundleFormat::Yaml => "yaml",
+            BundleFormat::Cbor => "cbor",
+        }
+    }
+
+    /// Human-readable name, as used in error messages
+    pub fn name(&self) -> &'static str {
+        match self {
+            BundleFormat::Json => "JSON",
+            BundleFormat::MessagePack => "MessagePack",
+            BundleFormat::Yaml => "YAML",
+            BundleFormat::Cbor => "CBOR",
+        }
+    }
+
+    /// Detect the format and whether the file is zstd-compressed from a file
+    /// name such as `r4_schemas.msgpack.zst`.
+    pub fn from_file_name(name: &str) -> Option<(Self, bool)> {
+        let (name, compressed) = match name.strip_suffix(".zst") {
+            Some(name) => (name, true),
+            None => (name, false),
+        };
+        let (_, extension) = name.rsplit_once('.')?;
+        let format = match extension {
+            "json" => BundleFormat::Json,
+            "msgpack" => BundleFormat::MessagePack,
+            "yaml" | "yml" => BundleFormat::Yaml,
+            "cbor" => BundleFormat::Cbor,
+            _ => return None,
+        };
+        Some((format, compressed))
+    }
+
+    /// Guess the format of uncompressed data from its first byte.
+    ///
+    /// Schemas and bundles are maps, so JSON opens with `{`, MessagePack with
+    /// a map marker and CBOR with a major type 5 header. Other UTF-8 text is
+    /// taken to be YAML.
+    pub fn detect(bytes: &[u8]) -> Option<Self> {
+        match bytes.iter().find(|b| !b.is_ascii_whitespace())? {
+            b'{' | b'[' => Some(BundleFormat::Json),
+            0x80..=0x8f | 0xde | 0xdf => Some(BundleFormat::MessagePack),
+            0xa0..=0xbb | 0xbf => Some(BundleFormat::Cbor),
+            _ if std::str::from_utf8(bytes).is_ok() => Some(BundleFormat::Yaml),
+            _ => None,
+        }
+    }
+}
+
+/// Whether `bytes` start with the zstd frame magic number.
+pub fn is_zstd(bytes: &[u8]) -> bool {
+    bytes.starts_with(&ZSTD_MAGIC)
+}
+
+/// Deserialize a value in `format`.
+pub fn decode<T: DeserializeOwned>(bytes: &[u8], format: BundleFormat) -> Result<T> {
+    let error = |e: &dyn std::fmt::Display| {
+        FhirSchemaError::schema_decode_error(format.name(), e.to_string())
+    };
+    match format {
+        BundleFormat::Json => serde_json::from_slice(bytes).map_err(|e| error(&e)),
+        #[cfg(feature = "msgpack")]
+        BundleFormat::MessagePack => rmp_serde::from_slice(bytes).map_err(|e| error(&e)),
+        #[cfg(feature = "yaml")]
+        BundleFormat::Yaml => serde_yaml::from_slice(bytes).map_err(|e| error(&e)),
+        #[cfg(feature = "cbor")]
+        BundleFormat::Cbor => ciborium::from_reader(bytes).map_err(|e| error(&e)),
+        #[allow(unreachable_patterns)]
+        _ => Err(error(&not_compiled_in(format))),
+    }
+}
+
+/// Serialize a value in `format`. JSON is pretty-printed.
+pub fn encode<T: Serialize + ?Sized>(value: &T, format: BundleFormat) -> Result<Vec<u8>> {
+    let error = |e: &dyn std::fmt::Display| {
+        FhirSchemaError::schema_encode_error(format.name(), e.to_string())
+    };
+    match format {
+        BundleFormat::Json => serde_json::to_vec_pretty(value).map_err(|e| error(&e)),
+        #[cfg(feature = "msgpack")]
+        BundleFormat::MessagePack => rmp_serde::to_vec_named(value).map_err(|e| error(&e)),
+        #[cfg(feature = "yaml")]
+        BundleFormat::Yaml => serde_yaml::to_string(value)
+            .map(String::into_bytes)
+            .map_err(|e| error(&e)),
+        #[cfg(feature = "cbor")]
+        BundleFormat::Cbor => {
+            let mut out = Vec::new();
+            ciborium::into_writer(value, &mut out).map_err(|e| error(&e))?;
+            Ok(out)
+        }
+        #[allow(unreachable_patterns)]
+        _ => Err(error(&not_compiled_in(format))),
+    }
+}
+
+fn not_compiled_in(format: BundleFormat) -> String {
+    let feature = match format {
+        BundleFormat::Yaml => "yaml",
+        BundleFormat::Cbor => "cbor",
+        _ => "msgpack",
+    };
+    format!("support is not compiled in (enable the `{feature}` feature)")
+}
+
+fn decompress(bytes: &[u8], compressed: bool) -> Result<Cow<'_, [u8]>> {
+    if compressed {
+        Ok(Cow::Owned(zstd::stream::decode_all(bytes)?))
+    } else {
+        Ok(Cow::Borrowed(bytes))
+    }
+}
+
+/// Decode a schema bundle (a map of schema name to [`FhirSchema`]).
+pub fn decode_schema_bundle(
+    bytes: &[u8],
+    format: BundleFormat,
+    compressed: bool,
+) -> Result<HashMap<String, FhirSchema>> {
+    let bytes = decompress(bytes, compressed)?;
+    decode(&bytes, format).map_err(|e| match e {
+        FhirSchemaError::SchemaDecodeError { format, message } => {
+            FhirSchemaError::bundle_decode_error(format, message)
+        }
+        other => other,
+    })
+}
+
+/// Encode a schema bundle, optionally zstd-compressed.
+pub fn encode_schema_bundle(
+    schemas: &HashMap<String, FhirSchema>,
+    format: BundleFormat,
+    compressed: bool,
+) -> Result<Vec<u8>> {
+    compress(encode(schemas, format)?, compressed)
+}
+
+/// Decode a single [`FhirSchema`].
+pub fn decode_schema(bytes: &[u8], format: BundleFormat, compressed: bool) -> Result<FhirSchema> {
+    decode(&decompress(bytes, compressed)?, format)
+}
+
+/// Encode a single [`FhirSchema`].
+pub fn encode_schema(schema: &FhirSchema, format: BundleFormat) -> Result<Vec<u8>> {
+    encode(schema, format)
+}
+
+fn compress(bytes: Vec<u8>, compressed: bool) -> Result<Vec<u8>> {
+    if compressed {
+        Ok(zstd::stream::encode_all(bytes.as_slice(), 3)?)
+    } else {
+        Ok(bytes)
+    }
+}
+
+/// Read `path` and work out its format: from the file name when it has a
+/// known extension, otherwise from the zstd magic number and the content.
+fn read_detected(path: &Path) -> Result<(Vec<u8>, BundleFormat)> {
+    let file_name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default();
+    let bytes = std::fs::read(path)?;
+
+    if let Some((format, compressed)) = BundleFormat::from_file_name(file_name) {
+        let bytes = decompress(&bytes, compressed)?.into_owned();
+        return Ok((bytes, format));
+    }
+
+    let bytes = decompress(&bytes, is_zstd(&bytes))?.into_owned();
+    let format = BundleFormat::detect(&bytes).ok_or_else(|| {
+        FhirSchemaError::schema_decode_error(file_name, "unrecognized schema file format")
+    })?;
+    Ok((bytes, format))
+}
+
+/// Read a schema bundle file, detecting its format and compression.
+pub fn load_schema_bundle(path: &Path) -> Result<HashMap<String, FhirSchema>> {
+    let (bytes, format) = read_detected(path)?;
+    decode_schema_bundle(&bytes, format, false)
+}
+
+/// Read a single schema file, detecting its format and compression.
+pub fn load_schema_file(path: &Path) -> Result<FhirSchema> {
+    let (bytes, format) = read_detected(path)?;
+    decode(&bytes, format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embedded::{FhirVersion, get_schema};
+
+    #[test]
+    fn test_detect_format() {
+        assert_eq!(
+            BundleFormat::detect(b"  {\"a\": 1}"),
+            Some(BundleFormat::Json)
+        );
+        assert_eq!(
+            BundleFormat::detect(&[0x81, 0xa1]),
+            Some(BundleFormat::MessagePack)
+        );
+        assert_eq!(
+            BundleFormat::detect(&[0xa1, 0x61]),
+            Some(BundleFormat::Cbor)
+        );
+        assert_eq!(BundleFormat::detect(b"url: x\n"), Some(BundleFormat::Yaml));
+        assert_eq!(BundleFormat::detect(&[0xff, 0xfe]), None);
+        assert_eq!(
+            BundleFormat::from_file_name("patient.yml.zst"),
+            Some((BundleFormat::Yaml, true))
+        );
+        assert!(is_zstd(&zstd::stream::encode_all(&b"{}"[..], 3).unwrap()));
+    }
+
+    #[test]
+    fn test_schema_round_trip() {
+        let patient = get_schema(FhirVersion::R4, "Patient").unwrap();
+        let formats = [
+            BundleFormat::Json,
+            #[cfg(feature = "msgpack")]
+            BundleFormat::MessagePack,
+            #[cfg(feature = "yaml")]
+            BundleFormat::Yaml,
+            #[cfg(feature = "cbor")]
+            BundleFormat::Cbor,
+        ];
+        for format in formats {
+            let bytes = encode_schema(patient, format).unwrap();
+            assert_eq!(BundleFormat::detect(&bytes), Some(format));
+            let decoded = decode_schema(&bytes, format, false).unwrap();
+            assert_eq!(
+                serde_json::to_value(&decoded).unwrap(),
+                serde_json::to_value(patient).unwrap(),
+                "{} round trip",
+                format.name()
+            );
+        }
+    }
+}
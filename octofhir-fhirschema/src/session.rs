@@ -0,0 +1,440 @@
+//! Conversion of several packages at once.
+//!
+//! [`translate`] sees a single StructureDefinition: it copies the
+//! `baseDefinition`, type codes and profile canonicals it finds without
+//! checking that anything defines them. A [`ConversionSession`] holds the
+//! StructureDefinitions of every loaded package, plus already converted
+//! schemas such as the embedded core ones, and resolves those references
+//! across all of them while translating. Bases are converted before the
+//! profiles derived from them, versioned base canonicals (`url|1.0.0`) are
+//! reduced to the URL schema providers look up, and every canonical no
+//! loaded definition answers is reported.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use octofhir_fhirschema::{ConversionSession, FhirVersion, PackageManifest, get_schemas};
+//!
+//! let mut session = ConversionSession::new()
+//!     .with_schemas(get_schemas(FhirVersion::R4)?.values().cloned());
+//! session.add_package(PackageManifest::from_package_json(&us_core_json)?, us_core_resources)?;
+//! session.add_package(PackageManifest::from_package_json(&my_ig_json)?, my_ig_resources)?;
+//!
+//! let output = session.translate();
+//! for unresolved in &output.unresolved {
+//!     println!("{}: {} {}", unresolved.source, unresolved.kind.as_str(), unresolved.canonical);
+//! }
+//! ```
+
+use std::collections::{HashMap, HashSet};
+
+use serde_json::Value as JsonValue;
+
+use crate::converter::translate;
+use crate::error::{FhirSchemaError, Result};
+use crate::package::{PackageGraph, PackageManifest};
+use crate::types::{FhirSchema, StructureDefinition, is_structure_definition};
+
+/// Type codes of FHIRPath system types, used by primitive `value` elements.
+const SYSTEM_TYPE_PREFIX: &str = "http://hl7.org/fhirpath/System.";
+
+/// What a canonical that could not be resolved was referenced as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CanonicalKind {
+    /// `StructureDefinition.baseDefinition`
+    BaseDefinition,
+    /// An element's `type.code`
+    Type,
+    /// An element's `type.profile`
+    Profile,
+    /// An element's `type.targetProfile`
+    TargetProfile,
+}
+
+impl CanonicalKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CanonicalKind::BaseDefinition => "baseDefinition",
+            CanonicalKind::Type => "type",
+            CanonicalKind::Profile => "profile",
+            CanonicalKind::TargetProfile => "targetProfile",
+        }
+    }
+}
+
+/// A reference no loaded StructureDefinition or schema defines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnresolvedCanonical {
+    /// URL of the StructureDefinition holding the reference
+    pub source: String,
+    /// Element path holding the reference; the type for `baseDefinition`
+    pub path: String,
+    /// The canonical or type code as written
+    pub canonical: String,
+    pub kind: CanonicalKind,
+}
+
+/// A StructureDefinition that failed to convert.
+#[derive(Debug)]
+pub struct ConversionFailure {
+    /// URL of the StructureDefinition
+    pub url: String,
+    pub error: FhirSchemaError,
+}
+
+/// Result of [`ConversionSession::translate`].
+#[derive(Debug, Default)]
+pub struct ConversionOutput {
+    /// Converted schemas, each after the session schemas it derives from
+    pub schemas: Vec<FhirSchema>,
+    pub failures: Vec<ConversionFailure>,
+    /// References nothing in the session resolves, in definition order
+    pub unresolved: Vec<UnresolvedCanonical>,
+}
+
+/// StructureDefinitions of several packages, converted together so that
+/// references between them resolve.
+#[derive(Debug, Clone, Default)]
+pub struct ConversionSession {
+    packages: PackageGraph,
+    definitions: Vec<StructureDefinition>,
+    schemas: Vec<FhirSchema>,
+}
+
+impl ConversionSession {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Resolve references against already converted schemas as well, e.g.
+    /// the embedded core schemas. They are not part of the output.
+    pub fn with_schemas(mut self, schemas: impl IntoIterator<Item = FhirSchema>) -> Self {
+        self.schemas.extend(schemas);
+        self
+    }
+
+    /// Load the StructureDefinitions among a package's resources; other
+    /// resources are skipped. Definitions without package metadata are
+    /// attributed to the package. Returns the number loaded.
+    pub fn add_package(
+        &mut self,
+        manifest: PackageManifest,
+        resources: impl IntoIterator<Item = JsonValue>,
+    ) -> Result<usize> {
+        let mut loaded = 0;
+        for resource in resources.into_iter().filter(is_structure_definition) {
+            let mut definition: StructureDefinition =
+                serde_json::from_value(resource).map_err(FhirSchemaError::SerializationError)?;
+            if definition.package_name.is_none() {
+                definition.package_name = Some(manifest.name.clone());
+                definition.package_version = Some(manifest.version.clone());
+                definition.package_id = Some(format!("{}#{}", manifest.name, manifest.version));
+            }
+            self.definitions.push(definition);
+            loaded += 1;
+        }
+        self.packages.add_package(manifest);
+        Ok(loaded)
+    }
+
+    /// Load a StructureDefinition that belongs to no package.
+    pub fn add_structure_definition(&mut self, definition: StructureDefinition) {
+        self.definitions.push(definition);
+    }
+
+    /// The loaded packages and their declared dependencies.
+    pub fn packages(&self) -> &PackageGraph {
+        &self.packages
+    }
+
+    /// Number of loaded StructureDefinitions.
+    pub fn len(&self) -> usize {
+        self.definitions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.definitions.is_empty()
+    }
+
+    /// Convert every loaded StructureDefinition, resolving base and type
+    /// references across the session.
+    pub fn translate(&self) -> ConversionOutput {
+        let index = CanonicalIndex::new(&self.definitions, &self.schemas);
+        let mut output = ConversionOutput::default();
+
+        for definition in self.ordered() {
+            output
+                .unresolved
+                .extend(index.unresolved_references(definition));
+
+            match translate(definition.clone(), None) {
+                Ok(mut schema) => {
+                    // Providers look bases up by URL alone
+                    if let Some(base) = &schema.base
+                        && index.resolves(base)
+                    {
+                        schema.base = Some(strip_version(base).to_string());
+                    }
+                    output.schemas.push(schema);
+                }
+                Err(error) => output.failures.push(ConversionFailure {
+                    url: definition.url.clone(),
+                    error,
+                }),
+            }
+        }
+        output
+    }
+
+    /// The loaded definitions, each after the loaded definition it derives
+    /// from. Otherwise the load order is kept.
+    fn ordered(&self) -> Vec<&StructureDefinition> {
+        let by_url: HashMap<&str, usize> = self
+            .definitions
+            .iter()
+            .enumerate()
+            .map(|(i, definition)| (definition.url.as_str(), i))
+            .collect();
+        let mut placed = vec![false; self.definitions.len()];
+        let mut ordered = Vec::with_capacity(self.definitions.len());
+
+        for start in 0..self.definitions.len() {
+            // Walk up to the first base not yet placed, then place back down
+            let mut pending = Vec::new();
+            let mut visiting = HashSet::new();
+            let mut current = Some(start);
+            while let Some(i) = current {
+                if placed[i] || !visiting.insert(i) {
+                    break;
+                }
+                pending.push(i);
+                current = self.definitions[i]
+                    .base_definition
+                    .as_deref()
+                    .and_then(|base| by_url.get(strip_version(base)).copied());
+            }
+            for i in pending.into_iter().rev() {
+                placed[i] = true;
+                ordered.push(&self.definitions[i]);
+            }
+        }
+        ordered
+    }
+}
+
+/// Canonical URLs (with their versions) and type names the session defines.
+struct CanonicalIndex<'a> {
+    versions: HashMap<&'a str, HashSet<Option<&'a str>>>,
+    types: HashSet<&'a str>,
+}
+
+impl<'a> CanonicalIndex<'a> {
+    fn new(definitions: &'a [StructureDefinition], schemas: &'a [FhirSchema]) -> Self {
+        let mut index = Self {
+            versions: HashMap::new(),
+            types: HashSet::new(),
+        };
+        for definition in definitions {
+            index.add(
+                &definition.url,
+                definition.version.as_deref(),
+                &definition.type_name,
+                definition.derivation.as_deref(),
+            );
+        }
+        for schema in schemas {
+            index.add(
+                &schema.url,
+                schema.version.as_deref(),
+                &schema.type_name,
+                schema.derivation.as_deref(),
+            );
+        }
+        index
+    }
+
+    fn add(
+        &mut self,
+        url: &'a str,
+        version: Option<&'a str>,
+        type_name: &'a str,
+        derivation: Option<&str>,
+    ) {
+        self.versions.entry(url).or_default().insert(version);
+        // Profiles constrain a type; they do not define one
+        if derivation != Some("constraint") {
+            self.types.insert(type_name);
+        }
+    }
+
+    /// Whether a canonical, optionally pinned with `|version`, is defined.
+    fn resolves(&self, canonical: &str) -> bool {
+        let (url, version) = match canonical.split_once('|') {
+            Some((url, version)) => (url, Some(version)),
+            None => (canonical, None),
+        };
+        self.versions
+            .get(url)
+            .is_some_and(|versions| version.is_none() || versions.contains(&version))
+    }
+
+    /// Whether a type code names a defined type, by name or by URL.
+    fn resolves_type(&self, code: &str) -> bool {
+        code.starts_with(SYSTEM_TYPE_PREFIX) || self.types.contains(code) || self.resolves(code)
+    }
+
+    fn unresolved_references(&self, definition: &StructureDefinition) -> Vec<UnresolvedCanonical> {
+        let mut unresolved = Vec::new();
+        let mut report = |path: &str, canonical: &str, kind: CanonicalKind| {
+            unresolved.push(UnresolvedCanonical {
+                source: definition.url.clone(),
+                path: path.to_string(),
+                canonical: canonical.to_string(),
+                kind,
+            });
+        };
+
+        if let Some(base) = &definition.base_definition
+            && !self.resolves(base)
+        {
+            report(&definition.type_name, base, CanonicalKind::BaseDefinition);
+        }
+
+        let elements = definition
+            .differential
+            .as_ref()
+            .map(|differential| differential.element.as_slice())
+            .unwrap_or_default();
+        for element in elements {
+            for type_info in element.type_info.iter().flatten() {
+                if !self.resolves_type(&type_info.code) {
+                    report(&element.path, &type_info.code, CanonicalKind::Type);
+                }
+                for profile in type_info.profile.iter().flatten() {
+                    if !self.resolves(profile) {
+                        report(&element.path, profile, CanonicalKind::Profile);
+                    }
+                }
+                for target in type_info.target_profile.iter().flatten() {
+                    if !self.resolves(target) {
+                        report(&element.path, target, CanonicalKind::TargetProfile);
+                    }
+                }
+            }
+        }
+        unresolved
+    }
+}
+
+fn strip_version(canonical: &str) -> &str {
+    canonical.split_once('|').map_or(canonical, |(url, _)| url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn structure_definition(
+        url: &str,
+        type_name: &str,
+        base: &str,
+        derivation: &str,
+        elements: JsonValue,
+    ) -> JsonValue {
+        json!({
+            "resourceType": "StructureDefinition",
+            "url": url, "name": url.rsplit('/').next().unwrap(),
+            "status": "active", "kind": "resource", "type": type_name,
+            "baseDefinition": base, "derivation": derivation,
+            "differential": {"element": elements}
+        })
+    }
+
+    #[test]
+    fn test_cross_package_resolution() {
+        let core = [
+            "Patient",
+            "Organization",
+            "Reference",
+            "HumanName",
+            "string",
+        ]
+        .map(|name| {
+            serde_json::from_value::<FhirSchema>(json!({
+                "url": format!("http://hl7.org/fhir/StructureDefinition/{name}"),
+                "name": name, "type": name, "kind": "resource", "class": "resource",
+                "derivation": "specialization"
+            }))
+            .unwrap()
+        });
+        let mut session = ConversionSession::new().with_schemas(core);
+
+        // The derived profile is loaded first, from the dependent package
+        let ig =
+            PackageManifest::new("example.ig", "1.0.0").with_dependency("example.base", "1.0.0");
+        let loaded = session
+            .add_package(
+                ig,
+                [
+                    structure_definition(
+                        "http://example.org/StructureDefinition/ig-patient",
+                        "Patient",
+                        "http://example.org/StructureDefinition/base-patient|1.0.0",
+                        "constraint",
+                        json!([
+                            {"path": "Patient"},
+                            {"path": "Patient.managingOrganization", "type": [{
+                                "code": "Reference",
+                                "targetProfile": ["http://example.org/StructureDefinition/missing-org"]
+                            }]}
+                        ]),
+                    ),
+                    json!({"resourceType": "ValueSet", "url": "http://example.org/ValueSet/x"}),
+                ],
+            )
+            .unwrap();
+        assert_eq!(loaded, 1);
+        let mut base = structure_definition(
+            "http://example.org/StructureDefinition/base-patient",
+            "Patient",
+            "http://hl7.org/fhir/StructureDefinition/Patient",
+            "constraint",
+            json!([{"path": "Patient"}, {"path": "Patient.name", "min": 1}]),
+        );
+        base["version"] = json!("1.0.0");
+        session
+            .add_package(PackageManifest::new("example.base", "1.0.0"), [base])
+            .unwrap();
+
+        let output = session.translate();
+        assert!(output.failures.is_empty(), "{:?}", output.failures);
+        let urls: Vec<&str> = output.schemas.iter().map(|s| s.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            [
+                "http://example.org/StructureDefinition/base-patient",
+                "http://example.org/StructureDefinition/ig-patient"
+            ]
+        );
+        assert_eq!(
+            output.schemas[1].base.as_deref(),
+            Some("http://example.org/StructureDefinition/base-patient")
+        );
+        assert_eq!(
+            output.schemas[0].package_name.as_deref(),
+            Some("example.base")
+        );
+
+        // Reference resolves against the core schemas; the target profile does not
+        assert_eq!(
+            output.unresolved,
+            [UnresolvedCanonical {
+                source: "http://example.org/StructureDefinition/ig-patient".to_string(),
+                path: "Patient.managingOrganization".to_string(),
+                canonical: "http://example.org/StructureDefinition/missing-org".to_string(),
+                kind: CanonicalKind::TargetProfile,
+            }]
+        );
+    }
+}
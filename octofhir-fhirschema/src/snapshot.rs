@@ -0,0 +1,229 @@
+//! Golden-file snapshots of schemas.
+//!
+//! Enabled with the `test-support` feature. A snapshot is the pretty-printed
+//! JSON of a [`FhirSchema`], [`CompiledSchema`] or any other serializable
+//! value, with object keys sorted so it does not depend on map iteration
+//! order. [`assert_golden`] compares a snapshot with a file checked into the
+//! repository and panics with a line diff when they differ, so implementation
+//! guide maintainers notice when a converter change alters their schemas.
+//!
+//! Run the tests with `FHIRSCHEMA_UPDATE_GOLDEN=1` to write the current
+//! snapshots instead of comparing; missing golden files are written the same
+//! way.
+//!
+//! ```ignore
+//! use octofhir_fhirschema::snapshot::assert_golden;
+//!
+//! let schema = translate(structure_definition, None)?;
+//! assert_golden("tests/golden/us-core-patient.json", &schema);
+//! ```
+//!
+//! [`FhirSchema`]: crate::types::FhirSchema
+//! [`CompiledSchema`]: crate::validation::CompiledSchema
+
+use crate::error::Result;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::Path;
+
+/// Environment variable that makes [`assert_golden`] rewrite golden files.
+pub const UPDATE_ENV: &str = "FHIRSCHEMA_UPDATE_GOLDEN";
+
+/// Lines of unchanged context shown around each change in a diff.
+const DIFF_CONTEXT: usize = 3;
+
+/// Deterministic snapshot of `value`: pretty JSON with sorted object keys
+/// and a trailing newline.
+pub fn snapshot<T: Serialize + ?Sized>(value: &T) -> Result<String> {
+    let value = sort_keys(serde_json::to_value(value)?);
+    let mut text = serde_json::to_string_pretty(&value)?;
+    text.push('\n');
+    Ok(text)
+}
+
+fn sort_keys(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<(String, Value)> = map.into_iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key, sort_keys(value)))
+                    .collect::<Map<String, Value>>(),
+            )
+        }
+        Value::Array(items) => Value::Array(items.into_iter().map(sort_keys).collect()),
+        other => other,
+    }
+}
+
+/// Assert that the snapshot of `value` matches the golden file at `path`.
+///
+/// Writes the file instead when it does not exist yet or when
+/// [`UPDATE_ENV`] is set.
+///
+/// # Panics
+///
+/// When the snapshot differs from the golden file, with a line diff of the
+/// two, or when the file cannot be read or written.
+#[track_caller]
+pub fn assert_golden<T: Serialize + ?Sized>(path: impl AsRef<Path>, value: &T) {
+    let path = path.as_ref();
+    let actual = snapshot(value)
+        .unwrap_or_else(|e| panic!("cannot snapshot value for {}: {e}", path.display()));
+
+    if std::env::var_os(UPDATE_ENV).is_some() || !path.exists() {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .unwrap_or_else(|e| panic!("cannot create {}: {e}", parent.display()));
+        }
+        fs::write(path, &actual).unwrap_or_else(|e| panic!("cannot write {}: {e}", path.display()));
+        return;
+    }
+
+    let expected =
+        fs::read_to_string(path).unwrap_or_else(|e| panic!("cannot read {}: {e}", path.display()));
+    if let Some(diff) = diff(&expected, &actual) {
+        panic!(
+            "snapshot does not match {} (rerun with {UPDATE_ENV}=1 to accept):\n{diff}",
+            path.display()
+        );
+    }
+}
+
+/// Line diff from `expected` to `actual`, or `None` when they are equal.
+///
+/// Removed lines start with `-`, added lines with `+` and context lines with
+/// a space; hunks start with an `@@ expected line N @@` header.
+pub fn diff(expected: &str, actual: &str) -> Option<String> {
+    if expected == actual {
+        return None;
+    }
+    let old: Vec<&str> = expected.lines().collect();
+    let new: Vec<&str> = actual.lines().collect();
+
+    // Longest common subsequence table, filled from the end
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    // (tag, old line index, text)
+    let mut ops: Vec<(char, usize, &str)> = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            ops.push((' ', i, old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            ops.push(('-', i, old[i]));
+            i += 1;
+        } else {
+            ops.push(('+', i, new[j]));
+            j += 1;
+        }
+    }
+
+    let changed: Vec<usize> = (0..ops.len()).filter(|&k| ops[k].0 != ' ').collect();
+    if changed.is_empty() {
+        // Only the trailing newline differs
+        return Some("\\ trailing newline differs\n".to_string());
+    }
+
+    let mut output = String::new();
+    let mut k = 0;
+    while k < changed.len() {
+        let start = changed[k].saturating_sub(DIFF_CONTEXT);
+        let mut end = changed[k];
+        while k < changed.len() && changed[k] <= end + 2 * DIFF_CONTEXT {
+            end = changed[k];
+            k += 1;
+        }
+        let end = (end + DIFF_CONTEXT + 1).min(ops.len());
+        output.push_str(&format!("@@ expected line {} @@\n", ops[start].1 + 1));
+        for (tag, _, text) in &ops[start..end] {
+            output.push(*tag);
+            output.push_str(text);
+            output.push('\n');
+        }
+    }
+    Some(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embedded::{FhirVersion, get_schemas};
+    use crate::validation::{InMemorySchemaProvider, SchemaCompiler};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_snapshot_sorts_keys() {
+        let mut map = HashMap::new();
+        map.insert("b", 1);
+        map.insert("a", 2);
+        map.insert("c", 3);
+        assert_eq!(
+            snapshot(&map).unwrap(),
+            "{\n  \"a\": 2,\n  \"b\": 1,\n  \"c\": 3\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_diff() {
+        assert_eq!(diff("a\nb\n", "a\nb\n"), None);
+        assert_eq!(
+            diff("a\nb\nc\n", "a\nx\nc\n").unwrap(),
+            "@@ expected line 1 @@\n a\n-b\n+x\n c\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_assert_golden_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("golden/patient.json");
+        let schemas = get_schemas(FhirVersion::R4).unwrap();
+        let schema = &schemas["Patient"];
+
+        // First run writes the file, the second compares against it
+        assert_golden(&path, schema);
+        assert_golden(&path, schema);
+
+        let provider = InMemorySchemaProvider::from_map(
+            schemas
+                .iter()
+                .map(|(name, schema)| (name.clone(), Arc::new(schema.clone())))
+                .collect(),
+        );
+        let compiled = SchemaCompiler::new(Arc::new(provider))
+            .compile("Patient")
+            .await
+            .unwrap();
+        let compiled_path = dir.path().join("golden/patient.compiled.json");
+        assert_golden(&compiled_path, compiled.as_ref());
+        assert_eq!(
+            fs::read_to_string(&compiled_path).unwrap(),
+            snapshot(compiled.as_ref()).unwrap()
+        );
+
+        if std::env::var_os(UPDATE_ENV).is_some() {
+            return;
+        }
+        let changed = std::panic::catch_unwind(|| {
+            let mut schema = schema.clone();
+            schema.description = Some("changed".to_string());
+            assert_golden(&path, &schema);
+        });
+        assert!(changed.is_err());
+    }
+}
@@ -43,6 +43,16 @@ fn build_match_for_slice(slicing: &Value, slice_schema: &Value) -> Value {
             if let Some(path_str) = path {
                 let path_str = path_str.trim();
 
+                // Extension slices name their profile in the slice's `url`
+                // rather than in a pattern on `url`.
+                if path_str == "url"
+                    && let Some(url) = slice_schema.get("url").filter(|u| u.is_string())
+                    && let Some(obj) = match_obj.as_object_mut()
+                {
+                    obj.insert("url".to_string(), url.clone());
+                    continue;
+                }
+
                 if path_str == "$this" {
                     // Merge pattern value from slice schema
                     if let Some(pattern) = slice_schema.get("pattern")
@@ -399,6 +409,24 @@ mod tests {
         assert_eq!(result["system"], "http://example.com");
     }
 
+    #[test]
+    fn test_build_match_for_extension_slice() {
+        let slicing = json!({
+            "discriminator": [{"type": "value", "path": "url"}],
+            "rules": "open"
+        });
+        let slice_schema = json!({
+            "type": "Extension",
+            "url": "http://hl7.org/fhir/us/core/StructureDefinition/us-core-race"
+        });
+
+        let result = build_match_for_slice(&slicing, &slice_schema);
+        assert_eq!(
+            result,
+            json!({"url": "http://hl7.org/fhir/us/core/StructureDefinition/us-core-race"})
+        );
+    }
+
     #[test]
     fn test_apply_actions_simple() {
         let stack = vec![json!({"name": "Test"})];
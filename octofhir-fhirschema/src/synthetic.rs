@@ -0,0 +1,235 @@
+//! Synthetic resources and profiles for benchmarks.
+//!
+//! Enabled with the `bench-util` feature. Every builder takes a size knob so
+//! benchmarks can measure how validation, conversion and navigation scale
+//! with the input rather than with whatever fixture happened to be at hand.
+//! The output is deterministic: the same size always produces the same
+//! resource.
+//!
+//! ```ignore
+//! use octofhir_fhirschema::synthetic;
+//!
+//! let bundle = synthetic::bundle(500);
+//! let profile = synthetic::patient_profile(20);
+//! ```
+
+use serde_json::{Value as JsonValue, json};
+
+use crate::types::StructureDefinition;
+
+/// Canonical base of the synthetic profiles.
+pub const SYNTHETIC_BASE_URL: &str = "http://example.org/fhir/StructureDefinition";
+
+/// A valid R4 Patient with `size` names, identifiers, telecoms and addresses.
+pub fn patient(size: usize) -> JsonValue {
+    json!({
+        "resourceType": "Patient",
+        "id": format!("synthetic-{size}"),
+        "meta": {"lastUpdated": "2024-01-01T00:00:00Z"},
+        "identifier": (0..size).map(|i| json!({
+            "system": format!("http://example.org/mrn/{}", i % 5),
+            "value": format!("MRN-{i:06}")
+        })).collect::<Vec<_>>(),
+        "active": true,
+        "name": (0..size).map(|i| json!({
+            "use": if i == 0 { "official" } else { "usual" },
+            "family": format!("Family{i}"),
+            "given": [format!("Given{i}"), "Middle"]
+        })).collect::<Vec<_>>(),
+        "telecom": (0..size).map(|i| json!({
+            "system": if i % 2 == 0 { "phone" } else { "email" },
+            "value": format!("contact-{i}"),
+            "use": "home"
+        })).collect::<Vec<_>>(),
+        "gender": "female",
+        "birthDate": "1974-12-25",
+        "address": (0..size).map(|i| json!({
+            "use": "home",
+            "line": [format!("{i} Example Street")],
+            "city": "Springfield",
+            "postalCode": format!("{:05}", i),
+            "country": "US"
+        })).collect::<Vec<_>>()
+    })
+}
+
+/// A valid R4 vital-signs style Observation with `components` components.
+pub fn observation(components: usize) -> JsonValue {
+    json!({
+        "resourceType": "Observation",
+        "id": format!("synthetic-obs-{components}"),
+        "status": "final",
+        "category": [{
+            "coding": [{
+                "system": "http://terminology.hl7.org/CodeSystem/observation-category",
+                "code": "vital-signs"
+            }]
+        }],
+        "code": {"coding": [{"system": "http://loinc.org", "code": "85354-9"}]},
+        "subject": {"reference": "Patient/example"},
+        "effectiveDateTime": "2024-01-01T10:00:00Z",
+        "component": (0..components).map(|i| json!({
+            "code": {"coding": [{"system": "http://loinc.org", "code": format!("{}-{}", 8480 + i, i % 10)}]},
+            "valueQuantity": {
+                "value": 100 + i,
+                "unit": "mm[Hg]",
+                "system": "http://unitsofmeasure.org",
+                "code": "mm[Hg]"
+            }
+        })).collect::<Vec<_>>()
+    })
+}
+
+/// A collection Bundle of `entries` alternating Patients and Observations.
+pub fn bundle(entries: usize) -> JsonValue {
+    json!({
+        "resourceType": "Bundle",
+        "type": "collection",
+        "entry": (0..entries).map(|i| {
+            let mut resource = if i % 2 == 0 { patient(2) } else { observation(3) };
+            resource["id"] = json!(format!("entry-{i}"));
+            json!({
+                "fullUrl": format!("urn:uuid:00000000-0000-0000-0000-{i:012}"),
+                "resource": resource
+            })
+        }).collect::<Vec<_>>()
+    })
+}
+
+/// A Patient that produces about `count` findings of each of several kinds:
+/// unknown elements, wrong primitive types and wrong cardinality.
+pub fn invalid_patient(count: usize) -> JsonValue {
+    let mut resource = patient(1);
+    let obj = resource.as_object_mut().expect("patient is an object");
+    for i in 0..count {
+        obj.insert(format!("unknownElement{i}"), json!(i));
+    }
+    obj.insert(
+        "name".to_string(),
+        JsonValue::Array(
+            (0..count)
+                .map(|_| json!({"family": 42, "given": "not-an-array"}))
+                .collect(),
+        ),
+    );
+    resource
+}
+
+/// A Patient profile slicing `identifier` by system into `slices` slices,
+/// with must-support flags and a profile invariant.
+pub fn patient_profile(slices: usize) -> StructureDefinition {
+    let mut elements = vec![
+        json!({
+            "id": "Patient",
+            "path": "Patient",
+            "constraint": [{
+                "key": "syn-1",
+                "severity": "error",
+                "human": "A name or an identifier is required",
+                "expression": "name.exists() or identifier.exists()"
+            }]
+        }),
+        json!({
+            "id": "Patient.identifier",
+            "path": "Patient.identifier",
+            "slicing": {
+                "discriminator": [{"type": "pattern", "path": "system"}],
+                "rules": "open"
+            },
+            "min": 1,
+            "mustSupport": true
+        }),
+    ];
+    for i in 0..slices {
+        elements.push(json!({
+            "id": format!("Patient.identifier:slice{i}"),
+            "path": "Patient.identifier",
+            "sliceName": format!("slice{i}"),
+            "min": 0,
+            "max": "1"
+        }));
+        elements.push(json!({
+            "id": format!("Patient.identifier:slice{i}.system"),
+            "path": "Patient.identifier.system",
+            "min": 1,
+            "patternUri": format!("http://example.org/mrn/{i}")
+        }));
+    }
+    elements.push(json!({
+        "id": "Patient.name",
+        "path": "Patient.name",
+        "min": 1,
+        "mustSupport": true
+    }));
+    elements.push(json!({
+        "id": "Patient.name.family",
+        "path": "Patient.name.family",
+        "min": 1
+    }));
+
+    structure_definition(json!({
+        "resourceType": "StructureDefinition",
+        "url": format!("{SYNTHETIC_BASE_URL}/synthetic-patient-{slices}"),
+        "name": format!("SyntheticPatient{slices}"),
+        "status": "draft",
+        "kind": "resource",
+        "abstract": false,
+        "type": "Patient",
+        "baseDefinition": "http://hl7.org/fhir/StructureDefinition/Patient",
+        "derivation": "constraint",
+        "differential": {"element": elements}
+    }))
+}
+
+/// A logical model with `elements` top-level elements of mixed types; every
+/// tenth element is a backbone element with two children.
+pub fn logical_model(elements: usize) -> StructureDefinition {
+    const TYPES: [&str; 5] = [
+        "string",
+        "integer",
+        "boolean",
+        "CodeableConcept",
+        "Quantity",
+    ];
+
+    let mut differential = vec![json!({"id": "Model", "path": "Model"})];
+    for i in 0..elements {
+        let path = format!("Model.field{i}");
+        if i % 10 == 9 {
+            differential.push(json!({
+                "id": path, "path": path, "min": 0, "max": "*",
+                "type": [{"code": "BackboneElement"}]
+            }));
+            for child in ["code", "note"] {
+                let child_path = format!("{path}.{child}");
+                differential.push(json!({
+                    "id": child_path, "path": child_path, "min": 0, "max": "1",
+                    "type": [{"code": if child == "code" { "Coding" } else { "string" }}]
+                }));
+            }
+        } else {
+            differential.push(json!({
+                "id": path, "path": path, "min": i % 2, "max": "1",
+                "short": format!("Synthetic field {i}"),
+                "type": [{"code": TYPES[i % TYPES.len()]}]
+            }));
+        }
+    }
+
+    structure_definition(json!({
+        "resourceType": "StructureDefinition",
+        "url": format!("{SYNTHETIC_BASE_URL}/Model{elements}"),
+        "name": format!("Model{elements}"),
+        "status": "draft",
+        "kind": "logical",
+        "abstract": false,
+        "type": "Model",
+        "baseDefinition": "http://hl7.org/fhir/StructureDefinition/Base",
+        "derivation": "specialization",
+        "differential": {"element": differential}
+    }))
+}
+
+fn structure_definition(value: JsonValue) -> StructureDefinition {
+    serde_json::from_value(value).expect("synthetic StructureDefinition is well-formed")
+}
@@ -8,7 +8,8 @@
 //!
 //! The terminology validation follows the same optional feature pattern as FHIRPath:
 //! - `TerminologyService` trait defines the interface
-//! - `CachedTerminologyService` wraps any service with TTL-based caching
+//! - `CachedTerminologyService` wraps any service with TTL-based caching,
+//!   optionally persisted to disk so restarts do not start cold
 //! - Validators optionally accept a terminology service
 //!
 //! # Example
@@ -31,7 +32,10 @@
 //! ```
 
 use async_trait::async_trait;
+#[cfg(not(target_arch = "wasm32"))]
 use moka::future::Cache;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 use thiserror::Error;
@@ -122,7 +126,7 @@ impl TerminologyError {
 pub type TerminologyResult<T> = Result<T, TerminologyError>;
 
 /// Result of validating a code against a value set
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CodeValidationResult {
     /// Whether the code is valid
     pub valid: bool,
@@ -247,6 +251,9 @@ pub struct CacheConfig {
     pub ttl: Duration,
     /// Maximum number of entries in the cache
     pub max_size: u64,
+    /// File the cache is persisted to, if any. The TTL and size limit apply
+    /// to persisted entries as well.
+    pub persist_path: Option<PathBuf>,
 }
 
 impl Default for CacheConfig {
@@ -254,6 +261,7 @@ impl Default for CacheConfig {
         Self {
             ttl: Duration::from_secs(3600), // 1 hour
             max_size: 10_000,
+            persist_path: None,
         }
     }
 }
@@ -261,38 +269,96 @@ impl Default for CacheConfig {
 impl CacheConfig {
     /// Create a new cache configuration
     pub fn new(ttl: Duration, max_size: u64) -> Self {
-        Self { ttl, max_size }
+        Self {
+            ttl,
+            max_size,
+            persist_path: None,
+        }
     }
 
     /// Create configuration for short-lived caches (5 minutes)
     pub fn short_lived() -> Self {
-        Self {
-            ttl: Duration::from_secs(300),
-            max_size: 1_000,
-        }
+        Self::new(Duration::from_secs(300), 1_000)
     }
 
     /// Create configuration for long-lived caches (24 hours)
     pub fn long_lived() -> Self {
-        Self {
-            ttl: Duration::from_secs(86400),
-            max_size: 50_000,
-        }
+        Self::new(Duration::from_secs(86400), 50_000)
+    }
+
+    /// Persist cached results to `path` and reload them on the next start.
+    ///
+    /// See [`CachedTerminologyService::persist`].
+    pub fn with_persistence(mut self, path: impl Into<PathBuf>) -> Self {
+        self.persist_path = Some(path.into());
+        self
     }
 }
 
 /// Cache key for terminology lookups
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 struct CacheKey {
     value_set_url: String,
     code: String,
     system: Option<String>,
 }
 
+/// A cached value with the time it was obtained from the inner service, so
+/// entries reloaded from disk expire when they would have in memory.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Stamped<T> {
+    value: T,
+    /// Seconds since the Unix epoch
+    stored_at: u64,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<T> Stamped<T> {
+    fn now(value: T) -> Self {
+        Self {
+            value,
+            stored_at: unix_now(),
+        }
+    }
+
+    fn is_fresh(&self, ttl: Duration) -> bool {
+        unix_now().saturating_sub(self.stored_at) < ttl.as_secs()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// On-disk form of a [`CachedTerminologyService`].
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PersistedCache {
+    /// Crate version that wrote the file; files of other versions are ignored
+    version: String,
+    /// `$validate-code` results
+    codes: Vec<(CacheKey, Stamped<CodeValidationResult>)>,
+    /// Value set availability, as found by expanding the value set
+    value_sets: Vec<(String, Stamped<bool>)>,
+}
+
 /// A cached wrapper around a TerminologyService.
 ///
-/// Reduces calls to the underlying service by caching validation results.
-/// Uses moka's async cache with TTL-based eviction.
+/// Reduces calls to the underlying service by caching validation results
+/// and value set availability. Uses moka's async cache with TTL-based
+/// eviction.
+///
+/// With [`CacheConfig::with_persistence`] the cache is loaded from a file on
+/// creation and written back when the owner calls [`Self::persist`], so a
+/// restarted process does not send every lookup to the terminology server
+/// again. The
+/// file is zstd-compressed JSON; a missing or unreadable file starts an
+/// empty cache.
 ///
 /// # Example
 ///
@@ -306,42 +372,140 @@ struct CacheKey {
 /// // Second call with same parameters returns cached result
 /// let result = cached.validate_code("http://example.org/vs", "ABC", None).await?;
 /// ```
+///
+/// Not available on `wasm32`, where moka does not build.
+#[cfg(not(target_arch = "wasm32"))]
 pub struct CachedTerminologyService {
     inner: Arc<dyn TerminologyService>,
-    cache: Cache<CacheKey, CodeValidationResult>,
+    cache: Cache<CacheKey, Stamped<CodeValidationResult>>,
+    value_sets: Cache<String, Stamped<bool>>,
+    config: CacheConfig,
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 impl CachedTerminologyService {
     /// Create a new cached terminology service.
     ///
     /// # Arguments
     ///
     /// * `inner` - The underlying terminology service to wrap
-    /// * `config` - Cache configuration (TTL, max size)
+    /// * `config` - Cache configuration (TTL, max size, persistence)
     pub fn new(inner: Arc<dyn TerminologyService>, config: CacheConfig) -> Self {
         let cache = Cache::builder()
             .time_to_live(config.ttl)
             .max_capacity(config.max_size)
             .build();
+        let value_sets = Cache::builder()
+            .time_to_live(config.ttl)
+            .max_capacity(config.max_size)
+            .build();
 
-        Self { inner, cache }
+        let service = Self {
+            inner,
+            cache,
+            value_sets,
+            config,
+        };
+        service.load();
+        service
     }
 
     /// Get cache statistics
     pub fn cache_stats(&self) -> CacheStats {
         CacheStats {
-            entry_count: self.cache.entry_count(),
-            weighted_size: self.cache.weighted_size(),
+            entry_count: self.cache.entry_count() + self.value_sets.entry_count(),
+            weighted_size: self.cache.weighted_size() + self.value_sets.weighted_size(),
         }
     }
 
     /// Clear all cached entries
     pub fn clear_cache(&self) {
         self.cache.invalidate_all();
+        self.value_sets.invalidate_all();
+    }
+
+    /// Write the unexpired entries to the configured persistence file.
+    ///
+    /// Nothing is written implicitly, so call this before the service is
+    /// dropped, e.g. at shutdown. Does nothing without
+    /// [`CacheConfig::with_persistence`]. The file is written through a
+    /// temporary file and renamed into place, so a concurrent reader never
+    /// sees a partial file.
+    pub fn persist(&self) -> std::io::Result<()> {
+        let Some(path) = &self.config.persist_path else {
+            return Ok(());
+        };
+        let ttl = self.config.ttl;
+        let snapshot = PersistedCache {
+            version: crate::VERSION.to_string(),
+            codes: self
+                .cache
+                .iter()
+                .filter(|(_, entry)| entry.is_fresh(ttl))
+                .map(|(key, entry)| (CacheKey::clone(&key), entry))
+                .collect(),
+            value_sets: self
+                .value_sets
+                .iter()
+                .filter(|(_, entry)| entry.is_fresh(ttl))
+                .map(|(url, entry)| (String::clone(&url), entry))
+                .collect(),
+        };
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_vec(&snapshot)?;
+        let compressed = zstd::stream::encode_all(json.as_slice(), 3)?;
+        let tmp = path.with_extension(format!("tmp{}", std::process::id()));
+        std::fs::write(&tmp, compressed)?;
+        std::fs::rename(&tmp, path)
+    }
+
+    /// Fill the cache from the persistence file, skipping expired entries
+    /// and keeping the newest when the file holds more than `max_size`.
+    fn load(&self) {
+        let Some(path) = &self.config.persist_path else {
+            return;
+        };
+        let Some(mut persisted) = std::fs::read(path)
+            .ok()
+            .and_then(|compressed| zstd::stream::decode_all(compressed.as_slice()).ok())
+            .and_then(|json| serde_json::from_slice::<PersistedCache>(&json).ok())
+        else {
+            return;
+        };
+        if persisted.version != crate::VERSION {
+            return;
+        }
+
+        let (ttl, limit) = (self.config.ttl, self.config.max_size as usize);
+        persisted.codes.retain(|(_, entry)| entry.is_fresh(ttl));
+        persisted
+            .codes
+            .sort_by_key(|(_, entry)| std::cmp::Reverse(entry.stored_at));
+        persisted
+            .value_sets
+            .retain(|(_, entry)| entry.is_fresh(ttl));
+        persisted
+            .value_sets
+            .sort_by_key(|(_, entry)| std::cmp::Reverse(entry.stored_at));
+
+        // moka's futures do not depend on an async runtime, so they can be
+        // driven here without one
+        futures::executor::block_on(async {
+            for (key, entry) in persisted.codes.into_iter().take(limit) {
+                self.cache.insert(key, entry).await;
+            }
+            for (url, entry) in persisted.value_sets.into_iter().take(limit) {
+                self.value_sets.insert(url, entry).await;
+            }
+        });
     }
 }
 
 /// Statistics about the cache
+#[cfg(not(target_arch = "wasm32"))]
 #[derive(Debug, Clone)]
 pub struct CacheStats {
     /// Number of entries in the cache
@@ -350,6 +514,7 @@ pub struct CacheStats {
     pub weighted_size: u64,
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 #[async_trait]
 impl TerminologyService for CachedTerminologyService {
     async fn validate_code(
@@ -364,9 +529,12 @@ impl TerminologyService for CachedTerminologyService {
             system: system.map(|s| s.to_string()),
         };
 
-        // Try to get from cache
-        if let Some(result) = self.cache.get(&key).await {
-            return Ok(result);
+        // Try to get from cache; entries reloaded from disk keep their age
+        if let Some(entry) = self.cache.get(&key).await {
+            if entry.is_fresh(self.config.ttl) {
+                return Ok(entry.value);
+            }
+            self.cache.invalidate(&key).await;
         }
 
         // Cache miss - call underlying service
@@ -376,14 +544,26 @@ impl TerminologyService for CachedTerminologyService {
             .await?;
 
         // Cache the result
-        self.cache.insert(key, result.clone()).await;
+        self.cache.insert(key, Stamped::now(result.clone())).await;
 
         Ok(result)
     }
 
     async fn value_set_exists(&self, value_set_url: &str) -> TerminologyResult<bool> {
-        // Don't cache existence checks - they're usually fast and we want fresh data
-        self.inner.value_set_exists(value_set_url).await
+        // Existence checks expand the value set on terminology servers, so
+        // they are cached (and persisted) like code validations
+        if let Some(entry) = self.value_sets.get(value_set_url).await {
+            if entry.is_fresh(self.config.ttl) {
+                return Ok(entry.value);
+            }
+            self.value_sets.invalidate(value_set_url).await;
+        }
+
+        let exists = self.inner.value_set_exists(value_set_url).await?;
+        self.value_sets
+            .insert(value_set_url.to_string(), Stamped::now(exists))
+            .await;
+        Ok(exists)
     }
 
     async fn get_display(&self, system: &str, code: &str) -> TerminologyResult<Option<String>> {
@@ -537,6 +717,237 @@ impl TerminologyService for InMemoryTerminologyService {
     }
 }
 
+// ============================================================================
+// Embedded core value sets
+// ============================================================================
+
+/// Required-binding value sets from core R4 resources, as
+/// (ValueSet id, CodeSystem url, codes).
+///
+/// Only `code`-typed bindings whose value set is exactly one small code
+/// system are listed, so a code missing here really is invalid. They are registered
+/// under their `|4.0.1` canonicals, which is how R4 schemas reference them;
+/// bindings from other FHIR versions fall through as unknown value sets.
+const CORE_R4_VALUE_SETS: &[(&str, &str, &[&str])] = &[
+    (
+        "administrative-gender",
+        "http://hl7.org/fhir/administrative-gender",
+        &["male", "female", "other", "unknown"],
+    ),
+    (
+        "observation-status",
+        "http://hl7.org/fhir/observation-status",
+        &[
+            "registered",
+            "preliminary",
+            "final",
+            "amended",
+            "corrected",
+            "cancelled",
+            "entered-in-error",
+            "unknown",
+        ],
+    ),
+    (
+        "publication-status",
+        "http://hl7.org/fhir/publication-status",
+        &["draft", "active", "retired", "unknown"],
+    ),
+    (
+        "request-status",
+        "http://hl7.org/fhir/request-status",
+        &[
+            "draft",
+            "active",
+            "on-hold",
+            "revoked",
+            "completed",
+            "entered-in-error",
+            "unknown",
+        ],
+    ),
+    (
+        "request-intent",
+        "http://hl7.org/fhir/request-intent",
+        &[
+            "proposal",
+            "plan",
+            "directive",
+            "order",
+            "original-order",
+            "reflex-order",
+            "filler-order",
+            "instance-order",
+            "option",
+        ],
+    ),
+    (
+        "request-priority",
+        "http://hl7.org/fhir/request-priority",
+        &["routine", "urgent", "asap", "stat"],
+    ),
+    (
+        "event-status",
+        "http://hl7.org/fhir/event-status",
+        &[
+            "preparation",
+            "in-progress",
+            "not-done",
+            "on-hold",
+            "stopped",
+            "completed",
+            "entered-in-error",
+            "unknown",
+        ],
+    ),
+    (
+        "fm-status",
+        "http://hl7.org/fhir/fm-status",
+        &["active", "cancelled", "draft", "entered-in-error"],
+    ),
+    (
+        "encounter-status",
+        "http://hl7.org/fhir/encounter-status",
+        &[
+            "planned",
+            "arrived",
+            "triaged",
+            "in-progress",
+            "onleave",
+            "finished",
+            "cancelled",
+            "entered-in-error",
+            "unknown",
+        ],
+    ),
+    (
+        "narrative-status",
+        "http://hl7.org/fhir/narrative-status",
+        &["generated", "extensions", "additional", "empty"],
+    ),
+    (
+        "identifier-use",
+        "http://hl7.org/fhir/identifier-use",
+        &["usual", "official", "temp", "secondary", "old"],
+    ),
+    (
+        "name-use",
+        "http://hl7.org/fhir/name-use",
+        &[
+            "usual",
+            "official",
+            "temp",
+            "nickname",
+            "anonymous",
+            "old",
+            "maiden",
+        ],
+    ),
+    (
+        "contact-point-system",
+        "http://hl7.org/fhir/contact-point-system",
+        &["phone", "fax", "email", "pager", "url", "sms", "other"],
+    ),
+    (
+        "contact-point-use",
+        "http://hl7.org/fhir/contact-point-use",
+        &["home", "work", "temp", "old", "mobile"],
+    ),
+    (
+        "address-use",
+        "http://hl7.org/fhir/address-use",
+        &["home", "work", "temp", "old", "billing"],
+    ),
+    (
+        "address-type",
+        "http://hl7.org/fhir/address-type",
+        &["postal", "physical", "both"],
+    ),
+    (
+        "link-type",
+        "http://hl7.org/fhir/link-type",
+        &["replaced-by", "replaces", "refer", "seealso"],
+    ),
+    (
+        "quantity-comparator",
+        "http://hl7.org/fhir/quantity-comparator",
+        &["<", "<=", ">=", ">"],
+    ),
+    (
+        "days-of-week",
+        "http://hl7.org/fhir/days-of-week",
+        &["mon", "tue", "wed", "thu", "fri", "sat", "sun"],
+    ),
+    (
+        "note-type",
+        "http://hl7.org/fhir/note-type",
+        &["display", "print", "printoper"],
+    ),
+    (
+        "bundle-type",
+        "http://hl7.org/fhir/bundle-type",
+        &[
+            "document",
+            "message",
+            "transaction",
+            "transaction-response",
+            "batch",
+            "batch-response",
+            "history",
+            "searchset",
+            "collection",
+        ],
+    ),
+    (
+        "http-verb",
+        "http://hl7.org/fhir/http-verb",
+        &["GET", "HEAD", "POST", "PUT", "DELETE", "PATCH"],
+    ),
+    (
+        "search-entry-mode",
+        "http://hl7.org/fhir/search-entry-mode",
+        &["match", "include", "outcome"],
+    ),
+    (
+        "issue-severity",
+        "http://hl7.org/fhir/issue-severity",
+        &["fatal", "error", "warning", "information"],
+    ),
+    (
+        "remittance-outcome",
+        "http://hl7.org/fhir/remittance-outcome",
+        &["queued", "complete", "error", "partial"],
+    ),
+];
+
+static CORE_TERMINOLOGY: once_cell::sync::Lazy<Arc<InMemoryTerminologyService>> =
+    once_cell::sync::Lazy::new(|| Arc::new(InMemoryTerminologyService::with_core_value_sets()));
+
+impl InMemoryTerminologyService {
+    /// Create a service preloaded with the embedded core value sets.
+    ///
+    /// Covers common required bindings of core R4 resources
+    /// (administrative-gender, observation-status, publication-status, ...).
+    pub fn with_core_value_sets() -> Self {
+        let mut service = Self::new();
+        for (id, system, codes) in CORE_R4_VALUE_SETS {
+            let url = format!("http://hl7.org/fhir/ValueSet/{id}|4.0.1");
+            for code in *codes {
+                service.add_code(&url, code, Some(*system), None);
+            }
+        }
+        service
+    }
+}
+
+/// Shared terminology service holding the embedded core value sets.
+///
+/// This is what [`FhirValidator`](crate::FhirValidator) uses until a
+/// terminology service is configured explicitly.
+pub fn core_terminology_service() -> Arc<dyn TerminologyService> {
+    CORE_TERMINOLOGY.clone()
+}
+
 // ============================================================================
 // Adapter for fhir-model-rs TerminologyProvider
 // ============================================================================
@@ -682,6 +1093,38 @@ mod tests {
         assert!(!result.valid);
     }
 
+    #[tokio::test]
+    async fn test_core_value_sets() {
+        let service = core_terminology_service();
+        let gender = "http://hl7.org/fhir/ValueSet/administrative-gender|4.0.1";
+
+        assert!(service.value_set_exists(gender).await.unwrap());
+        assert!(
+            service
+                .validate_code(gender, "female", None)
+                .await
+                .unwrap()
+                .valid
+        );
+        assert!(
+            !service
+                .validate_code(gender, "f", None)
+                .await
+                .unwrap()
+                .valid
+        );
+        assert!(
+            service
+                .validate_code(
+                    "http://hl7.org/fhir/ValueSet/administrative-gender|5.0.0",
+                    "female",
+                    None
+                )
+                .await
+                .is_err()
+        );
+    }
+
     #[tokio::test]
     async fn test_cached_service() {
         let mut inner = InMemoryTerminologyService::new();
@@ -713,6 +1156,63 @@ mod tests {
         assert_eq!(stats.entry_count, 1);
     }
 
+    #[tokio::test]
+    async fn test_cached_service_persistence() {
+        let dir = tempfile::tempdir().unwrap();
+        let config =
+            CacheConfig::new(Duration::from_secs(60), 100).with_persistence(dir.path().join("tx"));
+
+        let mut inner = InMemoryTerminologyService::new();
+        inner.add_code("http://example.org/vs", "ABC", None, Some("Alpha"));
+        let cached = CachedTerminologyService::new(Arc::new(inner), config.clone());
+        cached
+            .validate_code("http://example.org/vs", "ABC", None)
+            .await
+            .unwrap();
+        assert!(
+            cached
+                .value_set_exists("http://example.org/vs")
+                .await
+                .unwrap()
+        );
+        cached.persist().unwrap();
+
+        // The restarted cache answers without the inner service, which no
+        // longer knows the value set
+        let empty = Arc::new(InMemoryTerminologyService::new());
+        let restarted = CachedTerminologyService::new(empty.clone(), config.clone());
+        let result = restarted
+            .validate_code("http://example.org/vs", "ABC", None)
+            .await
+            .unwrap();
+        assert_eq!(result.display.as_deref(), Some("Alpha"));
+        assert!(
+            restarted
+                .value_set_exists("http://example.org/vs")
+                .await
+                .unwrap()
+        );
+        assert!(
+            restarted
+                .validate_code("http://example.org/vs", "XYZ", None)
+                .await
+                .is_err()
+        );
+        restarted.persist().unwrap();
+
+        // Entries older than the TTL are not reloaded
+        let expired = CachedTerminologyService::new(
+            empty,
+            CacheConfig::new(Duration::ZERO, 100).with_persistence(dir.path().join("tx")),
+        );
+        assert!(
+            expired
+                .validate_code("http://example.org/vs", "ABC", None)
+                .await
+                .is_err()
+        );
+    }
+
     #[test]
     fn test_binding_strength() {
         assert_eq!(
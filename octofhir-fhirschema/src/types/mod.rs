@@ -39,9 +39,10 @@ pub mod validation;
 
 // Re-export commonly used types at the module level
 pub use schema::{
-    FHIR_COMPLEX_TYPES, FHIR_PRIMITIVE_TYPES, FhirSchema, FhirSchemaBinding, FhirSchemaConstraint,
-    FhirSchemaDiscriminator, FhirSchemaElement, FhirSchemaPattern, FhirSchemaSliceMatch,
-    FhirSchemaSlicing, is_fhir_schema, is_fhir_schema_element,
+    BEST_PRACTICE_EXTENSION, EffectiveConstraint, FHIR_COMPLEX_TYPES, FHIR_PRIMITIVE_TYPES,
+    FhirSchema, FhirSchemaBinding, FhirSchemaConstraint, FhirSchemaDiscriminator,
+    FhirSchemaElement, FhirSchemaPattern, FhirSchemaSliceMatch, FhirSchemaSlicing,
+    effective_constraints, is_fhir_schema, is_fhir_schema_element,
 };
 
 pub use structure_definition::{
@@ -53,5 +54,6 @@ pub use structure_definition::{
 };
 
 pub use validation::{
+    ErrorMessage, ErrorPath, PatchOperation, SchemaSource, SchemaTrace, TraceDecision, TraceEntry,
     VALIDATION_ERROR_TYPES, ValidationContext, ValidationError, ValidationResult,
 };
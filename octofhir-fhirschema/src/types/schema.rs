@@ -8,6 +8,8 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use super::structure_definition::StructureDefinitionConstraint;
+
 /// Value set binding information for an element.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FhirSchemaBinding {
@@ -43,6 +45,50 @@ pub struct FhirSchemaConstraint {
     pub human: String,
     /// Severity: error | warning
     pub severity: String,
+    /// Why the constraint exists
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requirements: Option<String>,
+    /// Canonical URL of the StructureDefinition that first defined it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// A profile switched this warning or best-practice constraint off; it
+    /// is not evaluated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suppress: Option<bool>,
+    /// Marked as a best practice guideline rather than a rule
+    #[serde(rename = "bestPractice", skip_serializing_if = "Option::is_none")]
+    pub best_practice: Option<bool>,
+}
+
+impl FhirSchemaConstraint {
+    /// Whether a profile suppressed this constraint.
+    pub fn is_suppressed(&self) -> bool {
+        self.suppress == Some(true)
+    }
+}
+
+/// Extension marking a constraint as a best practice guideline.
+pub const BEST_PRACTICE_EXTENSION: &str =
+    "http://hl7.org/fhir/StructureDefinition/elementdefinition-bestpractice";
+
+impl From<&StructureDefinitionConstraint> for FhirSchemaConstraint {
+    fn from(constraint: &StructureDefinitionConstraint) -> Self {
+        let best_practice = constraint
+            .extension
+            .iter()
+            .flatten()
+            .find(|ext| ext.url == BEST_PRACTICE_EXTENSION)
+            .and_then(|ext| ext.value_boolean);
+        Self {
+            expression: constraint.expression.clone(),
+            human: constraint.human.clone(),
+            severity: constraint.severity.clone(),
+            requirements: constraint.requirements.clone(),
+            source: constraint.source.clone(),
+            suppress: constraint.suppress,
+            best_practice,
+        }
+    }
 }
 
 /// Slicing discriminator definition.
@@ -140,6 +186,10 @@ pub struct FhirSchemaElement {
     /// FHIRPath constraints keyed by constraint ID
     #[serde(skip_serializing_if = "Option::is_none")]
     pub constraint: Option<HashMap<String, FhirSchemaConstraint>>,
+    /// Keys of constraints defined on an ancestor that involve this element
+    /// (`ElementDefinition.condition`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub condition: Option<Vec<String>>,
 
     // Nested elements
     /// Nested element definitions (for BackboneElement)
@@ -154,6 +204,16 @@ pub struct FhirSchemaElement {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub choices: Option<Vec<String>>,
 
+    // Primitive constraints
+    /// Regular expression the primitive value must match in full (the
+    /// `regex` extension of the element or its type)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub regex: Option<String>,
+    /// Profiles constraining the primitive type (`type.profile`), whose
+    /// `value` regexes apply as well
+    #[serde(rename = "typeProfile", skip_serializing_if = "Option::is_none")]
+    pub type_profile: Option<Vec<String>>,
+
     // Extension URL
     /// URL for extension definitions
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -247,6 +307,9 @@ pub struct FhirSchema {
     pub class: String,
 
     // Documentation
+    /// Human-friendly title, e.g. "US Core Patient Profile"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
     /// Description of this schema
     #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
@@ -342,6 +405,68 @@ pub const FHIR_COMPLEX_TYPES: &[&str] = &[
     "Timing",
 ];
 
+// Merged constraints
+
+/// A constraint as it applies to an element once a profile chain is merged.
+#[derive(Debug, Clone, Serialize)]
+pub struct EffectiveConstraint {
+    /// Constraint key, e.g. `pat-1`
+    pub key: String,
+    /// The most derived definition of the constraint
+    pub constraint: FhirSchemaConstraint,
+    /// URL of the schema that made that definition
+    pub defined_in: String,
+    /// URLs of base schemas whose definition of the same key it replaces,
+    /// nearest first
+    pub overrides: Vec<String>,
+}
+
+/// Constraints in effect on an element across a profile chain.
+///
+/// `chain` runs from the most derived schema to the root type, `path` is the
+/// dotted element path below the root (`""` for the schema itself,
+/// `contact` or `name.given` for elements; a trailing `[x]` is ignored). A
+/// key defined by several schemas takes the most derived definition and
+/// lists the ones it overrides. Suppressed constraints are included, check
+/// [`FhirSchemaConstraint::is_suppressed`]. The result is sorted by key.
+pub fn effective_constraints(chain: &[&FhirSchema], path: &str) -> Vec<EffectiveConstraint> {
+    let segments: Vec<&str> = path
+        .split('.')
+        .filter(|s| !s.is_empty())
+        .map(|s| s.trim_end_matches("[x]"))
+        .collect();
+
+    let mut result: Vec<EffectiveConstraint> = Vec::new();
+    for schema in chain {
+        let constraints = match segments.split_first() {
+            None => schema.constraint.as_ref(),
+            Some((first, rest)) => schema
+                .elements
+                .as_ref()
+                .and_then(|elements| elements.get(*first))
+                .and_then(|element| {
+                    rest.iter().try_fold(element, |element, segment| {
+                        element.elements.as_ref()?.get(*segment)
+                    })
+                })
+                .and_then(|element| element.constraint.as_ref()),
+        };
+        for (key, constraint) in constraints.into_iter().flatten() {
+            match result.iter_mut().find(|c| &c.key == key) {
+                Some(effective) => effective.overrides.push(schema.url.clone()),
+                None => result.push(EffectiveConstraint {
+                    key: key.clone(),
+                    constraint: constraint.clone(),
+                    defined_in: schema.url.clone(),
+                    overrides: Vec::new(),
+                }),
+            }
+        }
+    }
+    result.sort_by(|a, b| a.key.cmp(&b.key));
+    result
+}
+
 // Type guards
 
 /// Check if a JSON value represents a FHIR Schema
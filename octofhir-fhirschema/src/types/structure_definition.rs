@@ -39,6 +39,15 @@ pub struct StructureDefinitionConstraint {
     /// XPath expression (deprecated)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub xpath: Option<String>,
+    /// Canonical URL of the StructureDefinition that first defined the constraint
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// Suppress a warning or best-practice constraint (R5)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suppress: Option<bool>,
+    /// Extensions on the constraint, e.g. best practice markers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extension: Option<Vec<StructureDefinitionExtension>>,
 }
 
 /// Value set binding in StructureDefinition format.
@@ -130,6 +139,9 @@ pub struct StructureDefinitionElement {
     /// Constraints
     #[serde(skip_serializing_if = "Option::is_none")]
     pub constraint: Option<Vec<StructureDefinitionConstraint>>,
+    /// Keys of the constraints (defined elsewhere) this element is involved in
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub condition: Option<Vec<String>>,
     /// Must support flag
     #[serde(rename = "mustSupport", skip_serializing_if = "Option::is_none")]
     pub must_support: Option<bool>,
@@ -196,6 +208,9 @@ pub struct StructureDefinitionExtension {
     /// URL value
     #[serde(rename = "valueUrl", skip_serializing_if = "Option::is_none")]
     pub value_url: Option<String>,
+    /// Boolean value
+    #[serde(rename = "valueBoolean", skip_serializing_if = "Option::is_none")]
+    pub value_boolean: Option<bool>,
 }
 
 /// Main StructureDefinition resource.
@@ -3,9 +3,15 @@
 //! This module contains types for representing validation results:
 //! - [`ValidationContext`] - Context for validation with available schemas
 //! - [`ValidationError`] - Individual validation error
+//! - [`ErrorPath`] - Compact location of a validation error
+//! - [`ErrorMessage`] - Message of a validation error, rendered on demand
+//! - [`PatchOperation`] - JSON Patch operation of a fix or an edit
 //! - [`ValidationResult`] - Overall validation result with errors and warnings
+//! - [`SchemaTrace`] - How the schemas were resolved for each path (explain mode)
 
-use serde::{Deserialize, Serialize};
+use serde::ser::SerializeSeq;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Cow;
 use std::collections::HashMap;
 
 use super::schema::FhirSchema;
@@ -20,21 +26,251 @@ pub struct ValidationContext {
     pub schemas: HashMap<String, FhirSchema>,
 }
 
+/// Location of a validation error within the resource.
+///
+/// Stored as a single dotted string (`name[0].given`) instead of one JSON
+/// value per segment, so recording an error costs one allocation however
+/// deep it is. It serializes as the dotted string split on `.`, every
+/// segment a JSON string (`["name[0]", "given"]`), and the root path as
+/// `[]`. A segment cannot itself contain `.`, so arrays with such segments
+/// are rejected when deserializing.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ErrorPath {
+    dotted: Box<str>,
+}
+
+impl ErrorPath {
+    /// Create a path from its dotted form, e.g. `Patient.name[0].given`.
+    pub fn new(dotted: &str) -> Self {
+        Self {
+            dotted: dotted.into(),
+        }
+    }
+
+    /// The dotted form of the path.
+    pub fn as_str(&self) -> &str {
+        &self.dotted
+    }
+
+    /// Whether the path points at the resource root.
+    pub fn is_empty(&self) -> bool {
+        self.dotted.is_empty()
+    }
+
+    /// Number of segments.
+    pub fn len(&self) -> usize {
+        self.segments().count()
+    }
+
+    /// Iterate over the segments, as they are serialized.
+    pub fn segments(&self) -> impl Iterator<Item = &str> {
+        // The root path has no segments rather than one empty one
+        let count = if self.dotted.is_empty() {
+            0
+        } else {
+            usize::MAX
+        };
+        self.dotted.split('.').take(count)
+    }
+
+    /// The last segment, if any.
+    pub fn last(&self) -> Option<&str> {
+        self.segments().last()
+    }
+
+    /// The segments as JSON values, in the serialized form.
+    pub fn to_values(&self) -> Vec<serde_json::Value> {
+        self.segments()
+            .map(|segment| serde_json::Value::String(segment.to_string()))
+            .collect()
+    }
+}
+
+impl From<&str> for ErrorPath {
+    fn from(dotted: &str) -> Self {
+        Self::new(dotted)
+    }
+}
+
+impl From<String> for ErrorPath {
+    fn from(dotted: String) -> Self {
+        Self {
+            dotted: dotted.into_boxed_str(),
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.dotted)
+    }
+}
+
+impl PartialEq<Vec<serde_json::Value>> for ErrorPath {
+    fn eq(&self, other: &Vec<serde_json::Value>) -> bool {
+        self.to_values() == *other
+    }
+}
+
+impl Serialize for ErrorPath {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.len()))?;
+        for segment in self.segments() {
+            seq.serialize_element(segment)?;
+        }
+        seq.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for ErrorPath {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let segments = Vec::<serde_json::Value>::deserialize(deserializer)?;
+        let mut dotted = String::new();
+        for (i, segment) in segments.iter().enumerate() {
+            let segment = match segment {
+                serde_json::Value::String(name) => Cow::Borrowed(name.as_str()),
+                other => Cow::Owned(other.to_string()),
+            };
+            if segment.contains('.') {
+                return Err(serde::de::Error::custom(format!(
+                    "error path segment '{segment}' contains '.'"
+                )));
+            }
+            if i > 0 {
+                dotted.push('.');
+            }
+            dotted.push_str(&segment);
+        }
+        Ok(Self::from(dotted))
+    }
+}
+
+/// Human-readable message of a validation error.
+///
+/// Fixed messages are borrowed. A message with arguments keeps its
+/// template and arguments and is only formatted when it is displayed or
+/// serialized, so findings that are counted or filtered by code never
+/// render text. Each `{}` in the template takes the next argument.
+/// Messages serialize as the rendered string and deserialize as fixed text.
+#[derive(Clone)]
+pub struct ErrorMessage {
+    template: Cow<'static, str>,
+    args: Box<[Cow<'static, str>]>,
+}
+
+impl ErrorMessage {
+    /// A message rendered from `template` with `args` in place of its `{}`
+    /// placeholders.
+    pub fn template<A: Into<Cow<'static, str>>>(
+        template: &'static str,
+        args: impl IntoIterator<Item = A>,
+    ) -> Self {
+        Self {
+            template: Cow::Borrowed(template),
+            args: args.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// The message text, formatted only if it has arguments.
+    pub fn render(&self) -> Cow<'_, str> {
+        if self.args.is_empty() {
+            Cow::Borrowed(&self.template)
+        } else {
+            Cow::Owned(self.to_string())
+        }
+    }
+}
+
+impl From<&'static str> for ErrorMessage {
+    fn from(text: &'static str) -> Self {
+        Self::from(Cow::Borrowed(text))
+    }
+}
+
+impl From<String> for ErrorMessage {
+    fn from(text: String) -> Self {
+        Self::from(Cow::<'static, str>::Owned(text))
+    }
+}
+
+impl From<Cow<'static, str>> for ErrorMessage {
+    fn from(text: Cow<'static, str>) -> Self {
+        Self {
+            template: text,
+            args: Box::default(),
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut pieces = self.template.split("{}");
+        let mut args = self.args.iter();
+        f.write_str(pieces.next().unwrap_or_default())?;
+        for piece in pieces {
+            // Fixed text may contain `{}` itself
+            f.write_str(args.next().map_or("{}", |arg| arg.as_ref()))?;
+            f.write_str(piece)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for ErrorMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(&*self.render(), f)
+    }
+}
+
+impl PartialEq for ErrorMessage {
+    fn eq(&self, other: &Self) -> bool {
+        self.render() == other.render()
+    }
+}
+
+impl Eq for ErrorMessage {}
+
+impl PartialEq<str> for ErrorMessage {
+    fn eq(&self, other: &str) -> bool {
+        self.render() == other
+    }
+}
+
+impl PartialEq<&str> for ErrorMessage {
+    fn eq(&self, other: &&str) -> bool {
+        self.render() == *other
+    }
+}
+
+impl Serialize for ErrorMessage {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ErrorMessage {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self::from)
+    }
+}
+
 /// A single validation error or warning.
 ///
 /// Contains detailed information about what went wrong during validation,
 /// including the location (path), expected vs actual values, and constraint information.
+/// Error codes and fixed messages are borrowed, and messages with arguments
+/// are rendered only when read (see [`ErrorMessage`]).
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ValidationError {
     /// Error type code (e.g., "FS1001" for unknown element)
     #[serde(rename = "type", default)]
-    pub error_type: String,
-    /// Path to the element that failed validation (can contain strings and numbers)
+    pub error_type: Cow<'static, str>,
+    /// Path to the element that failed validation
     #[serde(default)]
-    pub path: Vec<serde_json::Value>,
+    pub path: ErrorPath,
     /// Human-readable error message
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub message: Option<String>,
+    pub message: Option<ErrorMessage>,
     /// The actual value that caused the error
     #[serde(skip_serializing_if = "Option::is_none")]
     pub value: Option<serde_json::Value>,
@@ -64,6 +300,54 @@ pub struct ValidationError {
         skip_serializing_if = "Option::is_none"
     )]
     pub constraint_severity: Option<String>,
+
+    /// Suggested fix as JSON Patch operations against the validated
+    /// resource, for mechanical errors such as a scalar where an array is
+    /// expected
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fix: Option<Vec<PatchOperation>>,
+
+    /// Canonical URL of the schema in the schemata set that produced this
+    /// finding: the base resource type, a profile, or an extension
+    /// definition. Tells core FHIR failures apart from profile ones.
+    #[serde(rename = "schema-url", skip_serializing_if = "Option::is_none")]
+    pub schema_url: Option<String>,
+    /// Business version of that schema, when it declares one
+    #[serde(rename = "schema-version", skip_serializing_if = "Option::is_none")]
+    pub schema_version: Option<String>,
+}
+
+/// One RFC 6902 JSON Patch operation, as used by suggested fixes and
+/// incremental revalidation.
+///
+/// `path` is a JSON Pointer (`/name/0/given`) into the resource, not the
+/// FHIRPath-style [`ErrorPath`] of an error.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum PatchOperation {
+    /// Add a member that is missing
+    Add {
+        path: String,
+        value: serde_json::Value,
+    },
+    /// Replace the value at `path`
+    Replace {
+        path: String,
+        value: serde_json::Value,
+    },
+    /// Remove the value at `path`
+    Remove { path: String },
+}
+
+impl PatchOperation {
+    /// JSON Pointer the operation applies to.
+    pub fn path(&self) -> &str {
+        match self {
+            PatchOperation::Add { path, .. }
+            | PatchOperation::Replace { path, .. }
+            | PatchOperation::Remove { path } => path,
+        }
+    }
 }
 
 impl std::fmt::Display for ValidationError {
@@ -103,6 +387,210 @@ pub struct ValidationResult {
     /// List of validation warnings (severity: warning)
     #[serde(skip_serializing_if = "Vec::is_empty", default)]
     pub warnings: Vec<ValidationError>,
+    /// How the schemas were resolved, when validating in explain mode
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub explain: Option<SchemaTrace>,
+}
+
+/// Where a compiled schema came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SchemaSource {
+    /// Supplied precompiled to the validator
+    Precompiled,
+    /// Found in the compiled schema cache
+    Cached,
+    /// Compiled (or loaded from the disk cache) for this call
+    Compiled,
+}
+
+impl std::fmt::Display for SchemaSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            SchemaSource::Precompiled => "precompiled",
+            SchemaSource::Cached => "cached",
+            SchemaSource::Compiled => "compiled",
+        })
+    }
+}
+
+/// One schemata-resolution decision.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum TraceDecision {
+    /// A schema joined the schemata at this path
+    SchemaAdded {
+        schema: String,
+        /// What brought it in, e.g. `requested` or `extension url`
+        from: String,
+        source: SchemaSource,
+    },
+    /// A schema could not be loaded
+    SchemaMissing {
+        schema: String,
+        from: String,
+        message: String,
+    },
+    /// The element is declared by `schema`
+    Declared {
+        schema: String,
+        /// Type the element is validated as
+        element_type: String,
+        /// How it was resolved when not by name, e.g. `choice value[x]`
+        #[serde(skip_serializing_if = "Option::is_none", default)]
+        via: Option<String>,
+    },
+    /// `schema` does not declare the element (`FS1001`)
+    Undeclared { schema: String },
+    /// A schema named by the data that validation does not follow
+    NotFollowed { schema: String, reason: String },
+}
+
+impl std::fmt::Display for TraceDecision {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TraceDecision::SchemaAdded {
+                schema,
+                from,
+                source,
+            } => write!(f, "+ {schema} ({from}, {source})"),
+            TraceDecision::SchemaMissing {
+                schema,
+                from,
+                message,
+            } => write!(f, "! {schema} ({from}): {message}"),
+            TraceDecision::Declared {
+                schema,
+                element_type,
+                via,
+            } => {
+                write!(f, "{schema}: {element_type}")?;
+                if let Some(via) = via {
+                    write!(f, " via {via}")?;
+                }
+                Ok(())
+            }
+            TraceDecision::Undeclared { schema } => write!(f, "{schema}: not declared"),
+            TraceDecision::NotFollowed { schema, reason } => {
+                write!(f, "{schema} not followed: {reason}")
+            }
+        }
+    }
+}
+
+/// A decision and the path it was made at.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TraceEntry {
+    pub path: String,
+    #[serde(flatten)]
+    pub decision: TraceDecision,
+}
+
+/// Schemata-resolution decisions of a validation, in walk order.
+///
+/// Shows which schemas took part and how each element was resolved against
+/// them, to explain e.g. why an element is unknown (`FS1001`) or checked
+/// against an unexpected type (`FS1006`).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SchemaTrace {
+    pub entries: Vec<TraceEntry>,
+}
+
+impl SchemaTrace {
+    /// Record `decision` at `path`.
+    pub fn push(&mut self, path: impl Into<String>, decision: TraceDecision) {
+        self.entries.push(TraceEntry {
+            path: path.into(),
+            decision,
+        });
+    }
+
+    /// The decisions made at `path`.
+    pub fn at<'a>(&'a self, path: &'a str) -> impl Iterator<Item = &'a TraceDecision> {
+        self.entries
+            .iter()
+            .filter(move |entry| entry.path == path)
+            .map(|entry| &entry.decision)
+    }
+
+    /// Render the decisions as a tree of paths, one indented line each.
+    pub fn to_tree(&self) -> String {
+        #[derive(Default)]
+        struct Node<'a> {
+            segment: &'a str,
+            decisions: Vec<&'a TraceDecision>,
+            children: Vec<Node<'a>>,
+        }
+
+        fn render(node: &Node<'_>, depth: usize, out: &mut String) {
+            let indent = "  ".repeat(depth);
+            out.push_str(&indent);
+            out.push_str(if node.segment.is_empty() {
+                "(root)"
+            } else {
+                node.segment
+            });
+            out.push('\n');
+            for decision in &node.decisions {
+                out.push_str(&format!("{indent}  - {decision}\n"));
+            }
+            for child in &node.children {
+                render(child, depth + 1, out);
+            }
+        }
+
+        let mut roots: Vec<Node<'_>> = Vec::new();
+        for entry in &self.entries {
+            let mut segments = path_segments(&entry.path).into_iter();
+            let first = segments.next().unwrap_or("");
+            let mut node = match roots.iter().position(|n| n.segment == first) {
+                Some(i) => &mut roots[i],
+                None => {
+                    roots.push(Node {
+                        segment: first,
+                        ..Node::default()
+                    });
+                    roots.last_mut().unwrap()
+                }
+            };
+            for segment in segments {
+                let i = match node.children.iter().position(|n| n.segment == segment) {
+                    Some(i) => i,
+                    None => {
+                        node.children.push(Node {
+                            segment,
+                            ..Node::default()
+                        });
+                        node.children.len() - 1
+                    }
+                };
+                node = &mut node.children[i];
+            }
+            node.decisions.push(&entry.decision);
+        }
+
+        let mut out = String::new();
+        for root in &roots {
+            render(root, 0, &mut out);
+        }
+        out
+    }
+}
+
+/// Split a dotted path into segments, keeping `value.ofType(boolean)` as
+/// one segment.
+fn path_segments(path: &str) -> Vec<&str> {
+    let mut segments: Vec<&str> = Vec::new();
+    let mut start = 0;
+    for (i, _) in path.match_indices('.') {
+        if path[i + 1..].starts_with("ofType(") {
+            continue;
+        }
+        segments.push(&path[start..i]);
+        start = i + 1;
+    }
+    segments.push(&path[start..]);
+    segments
 }
 
 /// Validation error type constants
@@ -118,3 +606,71 @@ pub const VALIDATION_ERROR_TYPES: &[&str] = &[
     "slice-cardinality",
     "discriminator",
 ];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_error_path_serializes_every_segment_as_a_string() {
+        let path = ErrorPath::from("meta.profile.0");
+        assert_eq!(
+            serde_json::to_value(&path).unwrap(),
+            json!(["meta", "profile", "0"])
+        );
+        assert_eq!(path.last(), Some("0"));
+        assert_eq!(path.len(), 3);
+    }
+
+    #[test]
+    fn test_error_path_keeps_empty_segments() {
+        let path = ErrorPath::from(".name..given");
+        assert_eq!(
+            serde_json::to_value(&path).unwrap(),
+            json!(["", "name", "", "given"])
+        );
+    }
+
+    #[test]
+    fn test_root_error_path_serializes_as_empty_array() {
+        let path = ErrorPath::default();
+        assert_eq!(serde_json::to_value(&path).unwrap(), json!([]));
+        assert_eq!(path.len(), 0);
+        assert_eq!(path.last(), None);
+    }
+
+    #[test]
+    fn test_error_message_renders_on_demand() {
+        let message = ErrorMessage::template("Element '{}' has {} items", ["name", "3"]);
+        assert!(matches!(message.render(), Cow::Owned(_)));
+        assert_eq!(message, "Element 'name' has 3 items");
+        assert_eq!(
+            serde_json::to_value(&message).unwrap(),
+            json!("Element 'name' has 3 items")
+        );
+        assert_eq!(format!("{message:?}"), r#""Element 'name' has 3 items""#);
+
+        // Fixed text is borrowed, braces included
+        let fixed = ErrorMessage::from("expected {} here");
+        assert!(matches!(fixed.render(), Cow::Borrowed("expected {} here")));
+        let back: ErrorMessage = serde_json::from_value(json!("expected {} here")).unwrap();
+        assert_eq!(back, fixed);
+    }
+
+    #[test]
+    fn test_error_path_round_trips() {
+        for dotted in ["", "name[0].given", "meta.profile.0", "a..b"] {
+            let path = ErrorPath::from(dotted);
+            let json = serde_json::to_string(&path).unwrap();
+            let back: ErrorPath = serde_json::from_str(&json).unwrap();
+            assert_eq!(back, path, "{dotted}");
+        }
+    }
+
+    #[test]
+    fn test_error_path_rejects_segments_containing_dots() {
+        let result = serde_json::from_value::<ErrorPath>(json!(["name", "a.b"]));
+        assert!(result.is_err());
+    }
+}
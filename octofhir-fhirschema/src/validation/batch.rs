@@ -0,0 +1,137 @@
+//! Blocking, rayon-parallel structural validation for offline batches.
+//!
+//! Structural validation against compiled schemas is pure CPU work, so a
+//! batch is validated fastest by compiling every schema it needs once and
+//! then sharding the resources across a rayon pool. Workers share the
+//! compiled schemas read-only through their `Arc`s; there is no async
+//! runtime, no task spawning and no per-resource cache lookup.
+//!
+//! Only the structural phase runs here. FHIRPath invariants, extension
+//! profiles, terminology, QuestionnaireResponse checks and reference
+//! resolution need the async [`FhirValidator::validate`].
+
+use std::collections::HashMap;
+
+use rayon::prelude::*;
+use serde_json::Value as JsonValue;
+
+use super::{CompileError, FhirValidator, SharedCompiledSchema};
+use crate::types::ValidationResult;
+
+impl FhirValidator {
+    /// Structurally validate a batch of resources in parallel, blocking the
+    /// calling thread.
+    ///
+    /// Each resource is validated against its `resourceType` and every entry
+    /// of `profiles`. Schemas are compiled up front (concurrently, see
+    /// [`SchemaCompiler::compile_many`](super::SchemaCompiler::compile_many));
+    /// the resources are then validated on the current rayon pool, so wrap the
+    /// call in `ThreadPool::install` to bound its threads. Results are in the
+    /// order of `resources`.
+    ///
+    /// Must not be called from inside an async task: compilation is driven
+    /// with a blocking executor.
+    pub fn validate_batch_parallel(
+        &self,
+        resources: &[JsonValue],
+        profiles: &[String],
+    ) -> Vec<ValidationResult> {
+        let mut names: Vec<String> = resources
+            .iter()
+            .filter_map(|r| r.get("resourceType").and_then(JsonValue::as_str))
+            .map(str::to_string)
+            .collect();
+        names.extend(profiles.iter().cloned());
+
+        let compiled: HashMap<String, Result<SharedCompiledSchema, CompileError>> =
+            futures::executor::block_on(self.compiler.compile_many(names))
+                .into_iter()
+                .collect();
+
+        resources
+            .par_iter()
+            .map(|resource| self.validate_structure(resource, profiles, &compiled))
+            .collect()
+    }
+
+    fn validate_structure(
+        &self,
+        resource: &JsonValue,
+        profiles: &[String],
+        compiled: &HashMap<String, Result<SharedCompiledSchema, CompileError>>,
+    ) -> ValidationResult {
+        let mut errors = Vec::new();
+        let mut warnings = Vec::new();
+        let root_path = resource
+            .get("resourceType")
+            .and_then(JsonValue::as_str)
+            .unwrap_or_default();
+
+        let schema_names = resource
+            .get("resourceType")
+            .and_then(JsonValue::as_str)
+            .into_iter()
+            .chain(profiles.iter().map(String::as_str));
+        for schema_name in schema_names {
+            match compiled.get(schema_name) {
+                Some(Ok(schema)) => {
+                    self.validate_resource(resource, schema, &mut errors, root_path, None)
+                }
+                Some(Err(e)) => Self::record_unresolved_schema(
+                    schema_name,
+                    e.message.clone(),
+                    &mut errors,
+                    &mut warnings,
+                ),
+                None => Self::record_unresolved_schema(
+                    schema_name,
+                    format!("Schema not found: {schema_name}"),
+                    &mut errors,
+                    &mut warnings,
+                ),
+            }
+        }
+
+        ValidationResult {
+            valid: errors.is_empty(),
+            errors,
+            warnings,
+            explain: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embedded::{FhirVersion, get_schemas};
+    use serde_json::json;
+
+    #[test]
+    fn test_validate_batch_parallel() {
+        let schemas = get_schemas(FhirVersion::R4).unwrap().clone();
+        let validator = FhirValidator::from_schemas(schemas, None);
+        let resources: Vec<JsonValue> = (0..64)
+            .map(|i| match i % 4 {
+                0 => json!({"resourceType": "Patient", "active": true}),
+                1 => json!({"resourceType": "Patient", "active": "yes"}),
+                2 => {
+                    json!({"resourceType": "Observation", "status": "final", "code": {"text": "x"}})
+                }
+                _ => json!({"resourceType": "NotAResource"}),
+            })
+            .collect();
+
+        let results = validator.validate_batch_parallel(&resources, &[]);
+
+        assert_eq!(results.len(), resources.len());
+        for (i, result) in results.iter().enumerate() {
+            assert_eq!(result.valid, i % 4 == 0 || i % 4 == 2, "resource {i}");
+        }
+
+        let serial = futures::executor::block_on(
+            validator.validate(&resources[1], vec!["Patient".to_string()]),
+        );
+        assert_eq!(results[1].errors.len(), serial.errors.len());
+    }
+}
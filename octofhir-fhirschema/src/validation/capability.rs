@@ -0,0 +1,351 @@
+//! Validation policy derived from a server CapabilityStatement.
+//!
+//! A gateway in front of a FHIR server should only forward what that server
+//! accepts. [`CapabilityPolicy`] reads the `rest` entries of a server's
+//! CapabilityStatement into the resource types it supports, the base
+//! `profile` and `supportedProfile`s declared for each, and the search
+//! parameters it marks as `SHALL` through the
+//! `capabilitystatement-expectation` extension.
+//! [`FhirValidator::validate_against_capability`](super::FhirValidator::validate_against_capability)
+//! then rejects unsupported resource types and profile claims and validates
+//! the resource against the profiles the server enforces.
+
+use serde_json::Value as JsonValue;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+use super::{FhirSchemaErrorCode, ValidationError};
+use crate::error::{FhirSchemaError, Result};
+use crate::types::ErrorPath;
+
+const EXPECTATION_EXTENSION: &str =
+    "http://hl7.org/fhir/StructureDefinition/capabilitystatement-expectation";
+
+/// What a server accepts for one resource type.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResourcePolicy {
+    /// Resource type, e.g. `Patient`
+    pub resource_type: String,
+    /// Base profile every instance must conform to
+    pub profile: Option<String>,
+    /// Profiles the server declares support for
+    pub supported_profiles: Vec<String>,
+    /// Supported interaction codes, e.g. `read`, `create`
+    pub interactions: Vec<String>,
+    /// Declared search parameters
+    pub search_params: Vec<SearchParamPolicy>,
+}
+
+impl ResourcePolicy {
+    /// Whether a profile may be claimed in `meta.profile`. Without any
+    /// declared profiles every claim is accepted.
+    pub fn allows_profile(&self, profile: &str) -> bool {
+        let canonical = profile.split('|').next().unwrap_or(profile);
+        (self.profile.is_none() && self.supported_profiles.is_empty())
+            || self.profile.as_deref() == Some(canonical)
+            || self.supported_profiles.iter().any(|p| p == canonical)
+    }
+
+    /// Whether the server supports an interaction (`read`, `create`, ...).
+    pub fn supports_interaction(&self, code: &str) -> bool {
+        self.interactions.iter().any(|i| i == code)
+    }
+}
+
+/// A search parameter declared for a resource type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchParamPolicy {
+    /// Parameter name, e.g. `identifier`
+    pub name: String,
+    /// Parameter type, e.g. `token`
+    pub param_type: Option<String>,
+    /// Whether the server expects clients to use it (`SHALL`)
+    pub required: bool,
+}
+
+/// Validation policy derived from a server CapabilityStatement.
+#[derive(Debug, Clone, Default)]
+pub struct CapabilityPolicy {
+    /// Canonical URL of the CapabilityStatement, if it has one
+    pub url: Option<String>,
+    /// Supported resource types
+    pub resources: BTreeMap<String, ResourcePolicy>,
+}
+
+impl CapabilityPolicy {
+    /// Read the server-mode `rest` entries of a CapabilityStatement.
+    pub fn from_capability_statement(capability: &JsonValue) -> Result<Self> {
+        let resource_type = capability
+            .get("resourceType")
+            .and_then(JsonValue::as_str)
+            .unwrap_or("none");
+        if resource_type != "CapabilityStatement" {
+            return Err(FhirSchemaError::invalid_element_type(
+                "CapabilityStatement",
+                resource_type,
+            ));
+        }
+
+        let mut policy = Self {
+            url: str_field(capability, "url"),
+            resources: BTreeMap::new(),
+        };
+        for rest in array(capability, "rest") {
+            if rest.get("mode").and_then(JsonValue::as_str) == Some("client") {
+                continue;
+            }
+            for resource in array(rest, "resource") {
+                let Some(resource_type) = str_field(resource, "type") else {
+                    continue;
+                };
+                let entry = policy
+                    .resources
+                    .entry(resource_type.clone())
+                    .or_insert_with(|| ResourcePolicy {
+                        resource_type,
+                        ..ResourcePolicy::default()
+                    });
+                if let Some(profile) = str_field(resource, "profile") {
+                    entry.profile = Some(profile);
+                }
+                entry.supported_profiles.extend(
+                    array(resource, "supportedProfile")
+                        .iter()
+                        .filter_map(|p| p.as_str().map(str::to_string)),
+                );
+                entry.interactions.extend(
+                    array(resource, "interaction")
+                        .iter()
+                        .filter_map(|i| str_field(i, "code")),
+                );
+                entry
+                    .search_params
+                    .extend(array(resource, "searchParam").iter().filter_map(|param| {
+                        Some(SearchParamPolicy {
+                            name: str_field(param, "name")?,
+                            param_type: str_field(param, "type"),
+                            required: expectation(param) == Some("SHALL"),
+                        })
+                    }));
+            }
+        }
+        Ok(policy)
+    }
+
+    /// Policy for a resource type, if the server supports it.
+    pub fn resource(&self, resource_type: &str) -> Option<&ResourcePolicy> {
+        self.resources.get(resource_type)
+    }
+
+    /// Whether the server supports a resource type.
+    pub fn supports(&self, resource_type: &str) -> bool {
+        self.resources.contains_key(resource_type)
+    }
+
+    /// Search parameters the server requires for a resource type.
+    pub fn required_search_params(&self, resource_type: &str) -> Vec<&str> {
+        self.resource(resource_type)
+            .map(|policy| {
+                policy
+                    .search_params
+                    .iter()
+                    .filter(|param| param.required)
+                    .map(|param| param.name.as_str())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Required search parameters missing from a search on a resource type.
+    pub fn missing_search_params(&self, resource_type: &str, provided: &[&str]) -> Vec<&str> {
+        self.required_search_params(resource_type)
+            .into_iter()
+            .filter(|name| !provided.iter().any(|p| p.split(':').next() == Some(*name)))
+            .collect()
+    }
+
+    /// Check a resource's type and `meta.profile` claims against the policy.
+    ///
+    /// Returns the profiles it must be validated against (the resource
+    /// type, the server's base profile and the claimed profiles) along with
+    /// any violations.
+    pub(crate) fn check(&self, resource: &JsonValue) -> (Vec<String>, Vec<ValidationError>) {
+        let Some(resource_type) = resource.get("resourceType").and_then(JsonValue::as_str) else {
+            return (
+                Vec::new(),
+                vec![error(
+                    ErrorPath::default(),
+                    "Resource has no resourceType".into(),
+                )],
+            );
+        };
+        let Some(policy) = self.resource(resource_type) else {
+            return (
+                Vec::new(),
+                vec![error(
+                    ErrorPath::new("resourceType"),
+                    format!("The server does not support {resource_type} resources").into(),
+                )],
+            );
+        };
+
+        let mut schema_names = vec![resource_type.to_string()];
+        schema_names.extend(policy.profile.clone());
+        let mut errors = Vec::new();
+        let claimed = resource
+            .pointer("/meta/profile")
+            .and_then(JsonValue::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        for (idx, profile) in claimed.iter().enumerate() {
+            let Some(profile) = profile.as_str() else {
+                continue;
+            };
+            if policy.allows_profile(profile) {
+                let canonical = profile.split('|').next().unwrap_or(profile).to_string();
+                if !schema_names.contains(&canonical) {
+                    schema_names.push(canonical);
+                }
+            } else {
+                errors.push(error(
+                    ErrorPath::from(format!("meta.profile.{idx}")),
+                    format!("The server does not support profile {profile} for {resource_type}")
+                        .into(),
+                ));
+            }
+        }
+        (schema_names, errors)
+    }
+}
+
+fn expectation(element: &JsonValue) -> Option<&str> {
+    array(element, "extension")
+        .iter()
+        .find(|ext| ext.get("url").and_then(JsonValue::as_str) == Some(EXPECTATION_EXTENSION))
+        .and_then(|ext| ext.get("valueCode"))
+        .and_then(JsonValue::as_str)
+}
+
+fn array<'a>(value: &'a JsonValue, key: &str) -> &'a [JsonValue] {
+    value
+        .get(key)
+        .and_then(JsonValue::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+fn str_field(value: &JsonValue, key: &str) -> Option<String> {
+    value
+        .get(key)
+        .and_then(JsonValue::as_str)
+        .map(str::to_string)
+}
+
+fn error(path: ErrorPath, message: Cow<'static, str>) -> ValidationError {
+    ValidationError {
+        error_type: FhirSchemaErrorCode::CapabilityViolation.into(),
+        path,
+        message: Some(message.into()),
+        value: None,
+        expected: None,
+        got: None,
+        schema_path: None,
+        constraint_key: None,
+        constraint_expression: None,
+        constraint_severity: Some("error".to_string()),
+        fix: None,
+        schema_url: None,
+        schema_version: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const US_CORE_PATIENT: &str = "http://hl7.org/fhir/us/core/StructureDefinition/us-core-patient";
+
+    fn policy() -> CapabilityPolicy {
+        CapabilityPolicy::from_capability_statement(&json!({
+            "resourceType": "CapabilityStatement",
+            "rest": [{
+                "mode": "server",
+                "resource": [{
+                    "type": "Patient",
+                    "supportedProfile": [US_CORE_PATIENT],
+                    "interaction": [{"code": "read"}, {"code": "create"}],
+                    "searchParam": [{
+                        "name": "identifier",
+                        "type": "token",
+                        "extension": [{"url": EXPECTATION_EXTENSION, "valueCode": "SHALL"}]
+                    }, {
+                        "name": "name",
+                        "type": "string"
+                    }]
+                }]
+            }, {
+                "mode": "client",
+                "resource": [{"type": "Observation"}]
+            }]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_policy_from_capability_statement() {
+        let policy = policy();
+
+        assert!(policy.supports("Patient"));
+        assert!(!policy.supports("Observation"));
+        assert!(
+            policy
+                .resource("Patient")
+                .unwrap()
+                .supports_interaction("create")
+        );
+        assert_eq!(policy.required_search_params("Patient"), vec!["identifier"]);
+        assert_eq!(
+            policy.missing_search_params("Patient", &["name"]),
+            vec!["identifier"]
+        );
+        assert!(
+            policy
+                .missing_search_params("Patient", &["identifier:of-type"])
+                .is_empty()
+        );
+        assert!(
+            CapabilityPolicy::from_capability_statement(&json!({"resourceType": "Patient"}))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_check_resource() {
+        let policy = policy();
+
+        let (schemas, errors) = policy.check(&json!({
+            "resourceType": "Patient",
+            "meta": {"profile": [format!("{US_CORE_PATIENT}|6.1.0")]}
+        }));
+        assert!(errors.is_empty());
+        assert_eq!(
+            schemas,
+            vec!["Patient".to_string(), US_CORE_PATIENT.to_string()]
+        );
+
+        let (_, errors) = policy.check(&json!({
+            "resourceType": "Patient",
+            "meta": {"profile": ["http://example.org/other-patient"]}
+        }));
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].error_type, "FS1018");
+        assert_eq!(
+            serde_json::to_value(&errors[0]).unwrap()["path"],
+            json!(["meta", "profile", 0])
+        );
+
+        let (_, errors) = policy.check(&json!({"resourceType": "Observation"}));
+        assert_eq!(errors[0].path, vec![json!("resourceType")]);
+    }
+}
@@ -4,17 +4,24 @@
 //! types are inlined recursively. This eliminates the need for follow/collect
 //! operations during validation, resulting in significant performance improvements.
 
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 /// A fully-compiled schema with all nested types inlined.
 /// No external references - ready for direct validation.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompiledSchema {
     /// Original schema URL/name for identification
     pub url: String,
+    /// Business version of the schema, if declared
+    #[serde(default)]
+    pub version: Option<String>,
     /// Schema name (e.g., "Patient", "HumanName")
     pub name: String,
+    /// Base type the schema constrains (e.g., "Patient" for a Patient profile)
+    #[serde(default)]
+    pub type_name: String,
     /// Root element definitions with all types expanded inline
     pub elements: HashMap<String, CompiledElement>,
     /// All FHIRPath constraints collected from the type hierarchy
@@ -29,8 +36,37 @@ pub struct CompiledSchema {
     pub kind: SchemaKind,
 }
 
+impl CompiledSchema {
+    /// Rough heap footprint in bytes, used to weigh schemas against a cache
+    /// memory budget. Counts elements and their strings, not allocator or
+    /// hash table overhead.
+    pub fn approx_size(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + self.url.len()
+            + self.version.as_ref().map_or(0, String::len)
+            + self.name.len()
+            + self.type_name.len()
+            + self
+                .elements
+                .iter()
+                .map(|(k, e)| k.len() + e.approx_size())
+                .sum::<usize>()
+            + self
+                .constraints
+                .iter()
+                .map(CompiledConstraint::approx_size)
+                .sum::<usize>()
+            + self
+                .required
+                .iter()
+                .chain(&self.excluded)
+                .map(String::len)
+                .sum::<usize>()
+    }
+}
+
 /// Schema kind classification
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SchemaKind {
     /// FHIR Resource (Patient, Observation, etc.)
     Resource,
@@ -55,7 +91,7 @@ impl SchemaKind {
 }
 
 /// Compiled element with all type information inlined
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompiledElement {
     /// Element name (e.g., "name", "birthDate")
     pub name: String,
@@ -69,6 +105,12 @@ pub struct CompiledElement {
     pub max: Option<i32>,
     /// Nested elements (for complex types, inlined from type schema)
     pub children: HashMap<String, CompiledElement>,
+    /// Children that must be present, e.g. `method` of `Bundle.entry.request`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub required: Vec<String>,
+    /// Children that must be absent
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub excluded: Vec<String>,
     /// `contentReference` target path, if this element reuses another element's
     /// definition (e.g. `QuestionnaireResponse.item.item` -> the root `item`).
     /// Stored as the transformer's segment path `[url, "elements", name, ...]`;
@@ -82,6 +124,10 @@ pub struct CompiledElement {
     pub constraints: Vec<CompiledConstraint>,
     /// Pattern/fixed value constraints
     pub pattern: Option<serde_json::Value>,
+    /// Regular expressions a primitive value must match in full: the
+    /// element's own and those of its type profiles
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub regexes: Vec<String>,
     /// Choice type variants
     pub choices: Option<Vec<String>>,
     /// Slicing definition (for array elements with slices)
@@ -95,6 +141,38 @@ pub struct CompiledElement {
 }
 
 impl CompiledElement {
+    fn approx_size(&self) -> usize {
+        let strings = |v: &Option<Vec<String>>| v.iter().flatten().map(String::len).sum::<usize>();
+        std::mem::size_of::<Self>()
+            + self.name.len()
+            + self.short.as_ref().map_or(0, String::len)
+            + strings(&self.element_reference)
+            + strings(&self.reference_targets)
+            + strings(&self.choices)
+            + self.regexes.iter().map(String::len).sum::<usize>()
+            + self.required.iter().map(String::len).sum::<usize>()
+            + self.excluded.iter().map(String::len).sum::<usize>()
+            + self.binding.as_ref().map_or(0, |b| b.value_set.len())
+            + self
+                .constraints
+                .iter()
+                .map(CompiledConstraint::approx_size)
+                .sum::<usize>()
+            + self
+                .children
+                .iter()
+                .map(|(k, e)| k.len() + e.approx_size())
+                .sum::<usize>()
+            + self.slicing.as_ref().map_or(0, |slicing| {
+                slicing
+                    .slices
+                    .values()
+                    .filter_map(|slice| slice.schema.as_deref())
+                    .map(CompiledElement::approx_size)
+                    .sum()
+            })
+    }
+
     /// FHIR type name to hand a FHIRPath evaluator as the type of this element's
     /// value, for invariants declared on it.
     ///
@@ -121,11 +199,14 @@ impl Default for CompiledElement {
             min: 0,
             max: None,
             children: HashMap::new(),
+            required: Vec::new(),
+            excluded: Vec::new(),
             element_reference: None,
             binding: None,
             reference_targets: None,
             constraints: Vec::new(),
             pattern: None,
+            regexes: Vec::new(),
             choices: None,
             slicing: None,
             short: None,
@@ -136,7 +217,7 @@ impl Default for CompiledElement {
 }
 
 /// Type classification for compiled elements
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum CompiledTypeInfo {
     /// Primitive FHIR type
     Primitive(PrimitiveType),
@@ -162,7 +243,7 @@ pub enum CompiledTypeInfo {
 }
 
 /// FHIR primitive types
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PrimitiveType {
     Boolean,
     Integer,
@@ -248,7 +329,7 @@ pub fn is_primitive_type(type_name: &str) -> bool {
 }
 
 /// Compiled FHIRPath constraint
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompiledConstraint {
     /// Constraint key (e.g., "ele-1", "pat-1")
     pub key: String,
@@ -258,10 +339,24 @@ pub struct CompiledConstraint {
     pub human: String,
     /// Severity: error or warning
     pub severity: ConstraintSeverity,
+    /// Paths, relative to the constrained element, of the descendants whose
+    /// `condition` names this constraint (e.g. `contact.name` for `pat-1`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub implicated: Vec<String>,
+}
+
+impl CompiledConstraint {
+    fn approx_size(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + self.key.len()
+            + self.expression.len()
+            + self.human.len()
+            + self.implicated.iter().map(String::len).sum::<usize>()
+    }
 }
 
 /// Constraint severity level
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ConstraintSeverity {
     Error,
     Warning,
@@ -277,7 +372,7 @@ impl ConstraintSeverity {
 }
 
 /// Compiled binding information for coded elements
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompiledBinding {
     /// Value set URL
     pub value_set: String,
@@ -288,7 +383,7 @@ pub struct CompiledBinding {
 }
 
 /// Binding strength levels
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum BindingStrength {
     Required,
     Extensible,
@@ -315,7 +410,7 @@ pub type SharedCompiledSchema = Arc<CompiledSchema>;
 // =============================================================================
 
 /// Compiled slicing definition for array elements
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompiledSlicing {
     /// Slicing rules: "open", "closed", or "openAtEnd"
     pub rules: SlicingRules,
@@ -328,7 +423,7 @@ pub struct CompiledSlicing {
 }
 
 /// Slicing rules
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum SlicingRules {
     /// Additional content allowed anywhere
     #[default]
@@ -350,7 +445,7 @@ impl SlicingRules {
 }
 
 /// Compiled discriminator
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompiledDiscriminator {
     /// Discriminator type
     pub discriminator_type: DiscriminatorType,
@@ -359,7 +454,7 @@ pub struct CompiledDiscriminator {
 }
 
 /// Discriminator type
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum DiscriminatorType {
     /// Match by value
     Value,
@@ -386,12 +481,15 @@ impl DiscriminatorType {
 }
 
 /// Compiled slice definition
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompiledSlice {
     /// Slice name
     pub name: String,
     /// Match pattern (for discriminator matching)
     pub match_value: Option<serde_json::Value>,
+    /// Extension url of the slice, for slicing discriminated by `url`
+    #[serde(default)]
+    pub url: Option<String>,
     /// Minimum cardinality for this slice
     pub min: Option<i32>,
     /// Maximum cardinality for this slice
@@ -2,14 +2,31 @@
 //!
 //! The compiler resolves inheritance chains, merges schemas, and expands
 //! all nested types inline for fast validation without runtime lookups.
-
-use std::collections::{HashMap, HashSet};
-use std::sync::Arc;
+//! Chains are merged through a [`ProfileMergeCache`] that can be shared
+//! with other consumers of merged profiles.
+//!
+//! Compiled schemas can also be persisted to a directory with
+//! [`SchemaCompiler::with_disk_cache`]. Each file is named after the crate
+//! version and the requested schema, and records a hash of every schema the
+//! compile looked up: its inheritance chain, the datatypes inlined into it
+//! and the profiles its regexes came from. A restart reloads what an earlier
+//! process compiled as long as none of those schemas changed.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 
 use async_recursion::async_recursion;
+use futures::future::join_all;
 
 use super::SchemaProvider;
-use crate::types::{FhirSchema, FhirSchemaConstraint, FhirSchemaElement, FhirSchemaSlicing};
+use super::tuning::CacheTuning;
+use crate::profiles::{self, ProfileMergeCache};
+use crate::types::{
+    FhirSchema, FhirSchemaConstraint, FhirSchemaElement, FhirSchemaSliceMatch, FhirSchemaSlicing,
+    SchemaSource,
+};
 
 use super::compiled::{
     BindingStrength, CompiledBinding, CompiledConstraint, CompiledDiscriminator, CompiledElement,
@@ -37,12 +54,157 @@ impl std::fmt::Display for CompileError {
 
 impl std::error::Error for CompileError {}
 
+/// Schemas a compile looked up, by the name or URL asked for, with the
+/// [`content_hash`] of what the provider returned (`None` when it had no
+/// such schema).
+type Dependencies = BTreeMap<String, Option<String>>;
+
+/// Records the [`Dependencies`] of one compile; the default recorder is
+/// disabled and ignores everything.
+#[derive(Default)]
+struct Lookups(Option<Mutex<Dependencies>>);
+
+impl Lookups {
+    #[cfg(not(target_arch = "wasm32"))]
+    fn recording() -> Self {
+        Self(Some(Mutex::default()))
+    }
+
+    fn is_recording(&self) -> bool {
+        self.0.is_some()
+    }
+
+    fn record(&self, key: &str, schema: Option<&FhirSchema>) {
+        let Some(dependencies) = &self.0 else {
+            return;
+        };
+        let mut dependencies = dependencies.lock().unwrap_or_else(|e| e.into_inner());
+        if !dependencies.contains_key(key) {
+            dependencies.insert(key.to_string(), schema.map(content_hash));
+        }
+    }
+
+    fn extend(&self, other: &Dependencies) {
+        let Some(dependencies) = &self.0 else {
+            return;
+        };
+        let mut dependencies = dependencies.lock().unwrap_or_else(|e| e.into_inner());
+        for (key, hash) in other {
+            dependencies
+                .entry(key.clone())
+                .or_insert_with(|| hash.clone());
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn into_inner(self) -> Dependencies {
+        self.0
+            .map(|d| d.into_inner().unwrap_or_else(|e| e.into_inner()))
+            .unwrap_or_default()
+    }
+}
+
+/// SHA-256 of a schema's JSON with object keys sorted, so a schema hashes
+/// the same in every process whatever the iteration order of its maps.
+fn content_hash(schema: &FhirSchema) -> String {
+    use sha2::{Digest, Sha256};
+
+    fn update(hasher: &mut Sha256, value: &serde_json::Value) {
+        match value {
+            serde_json::Value::Object(map) => {
+                let mut entries: Vec<_> = map.iter().collect();
+                entries.sort_by(|a, b| a.0.cmp(b.0));
+                hasher.update(b"{");
+                for (key, value) in entries {
+                    update(hasher, &serde_json::Value::from(key.as_str()));
+                    hasher.update(b":");
+                    update(hasher, value);
+                    hasher.update(b",");
+                }
+                hasher.update(b"}");
+            }
+            serde_json::Value::Array(items) => {
+                hasher.update(b"[");
+                for item in items {
+                    update(hasher, item);
+                    hasher.update(b",");
+                }
+                hasher.update(b"]");
+            }
+            other => hasher.update(other.to_string().as_bytes()),
+        }
+    }
+
+    let value = serde_json::to_value(schema).unwrap_or_default();
+    let mut hasher = Sha256::new();
+    update(&mut hasher, &value);
+    format!("{:x}", hasher.finalize())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+type CompiledCache = moka::future::Cache<String, SharedCompiledSchema>;
+
+#[cfg(not(target_arch = "wasm32"))]
+fn compiled_cache(tuning: &CacheTuning) -> CompiledCache {
+    match tuning.compiled_schema_bytes {
+        Some(budget) => moka::future::Cache::builder()
+            .max_capacity(budget)
+            .weigher(|_, schema: &SharedCompiledSchema| CacheTuning::compiled_schema_weight(schema))
+            .build(),
+        None => moka::future::Cache::new(tuning.compiled_schemas),
+    }
+}
+
+/// moka needs threads and a monotonic clock, which `wasm32-unknown-unknown`
+/// lacks, so the WASM build keeps compiled schemas unbounded for the
+/// lifetime of the validator.
+#[cfg(target_arch = "wasm32")]
+#[derive(Default)]
+struct CompiledCache(std::sync::RwLock<HashMap<String, SharedCompiledSchema>>);
+
+#[cfg(target_arch = "wasm32")]
+fn compiled_cache(_tuning: &CacheTuning) -> CompiledCache {
+    CompiledCache::default()
+}
+
+#[cfg(target_arch = "wasm32")]
+impl CompiledCache {
+    async fn get(&self, key: &str) -> Option<SharedCompiledSchema> {
+        self.0.read().ok()?.get(key).cloned()
+    }
+
+    async fn insert(&self, key: String, value: SharedCompiledSchema) {
+        if let Ok(mut cache) = self.0.write() {
+            cache.insert(key, value);
+        }
+    }
+}
+
 /// Schema compiler with caching
 pub struct SchemaCompiler {
     /// Schema provider for loading raw schemas
     schema_provider: Arc<dyn SchemaProvider>,
     /// Cache of compiled schemas
-    compiled_cache: moka::future::Cache<String, SharedCompiledSchema>,
+    compiled_cache: CompiledCache,
+    /// Ready-made compiled schemas (e.g. generated by devtools), keyed by
+    /// URL. Checked before the cache and never evicted.
+    precompiled: HashMap<String, SharedCompiledSchema>,
+    /// URL of the precompiled schema each name refers to, or `None` when
+    /// several precompiled schemas share the name
+    precompiled_names: HashMap<String, Option<String>>,
+    /// One gate per schema currently being compiled. A second caller for the
+    /// same name waits on the gate and then reads the cache instead of
+    /// compiling it again.
+    in_flight: Mutex<HashMap<String, Arc<futures::lock::Mutex<()>>>>,
+    /// Directory compiled schemas are persisted to and reloaded from
+    #[cfg(not(target_arch = "wasm32"))]
+    disk_cache: Option<PathBuf>,
+    /// Dependencies of the schemas compiled or reloaded through the disk
+    /// cache, by the names they are cached under, so a schema that inlines
+    /// one of them records them too
+    dependencies: Mutex<HashMap<String, Arc<Dependencies>>>,
+    /// Merged inheritance chains, possibly shared with other consumers
+    profile_cache: Arc<ProfileMergeCache>,
 }
 
 impl SchemaCompiler {
@@ -50,9 +212,70 @@ impl SchemaCompiler {
     pub fn new(schema_provider: Arc<dyn SchemaProvider>) -> Self {
         Self {
             schema_provider,
-            // Cache ~500 compiled schemas (covers most FHIR types)
-            compiled_cache: moka::future::Cache::new(500),
+            compiled_cache: compiled_cache(&CacheTuning::default()),
+            precompiled: HashMap::new(),
+            precompiled_names: HashMap::new(),
+            in_flight: Mutex::new(HashMap::new()),
+            #[cfg(not(target_arch = "wasm32"))]
+            disk_cache: None,
+            dependencies: Mutex::new(HashMap::new()),
+            profile_cache: Arc::new(ProfileMergeCache::new()),
+        }
+    }
+
+    /// Seed the compiler with already compiled schemas.
+    ///
+    /// Each schema is served for its URL, and for its name as long as no
+    /// other precompiled schema has the same name; an ambiguous name is
+    /// compiled through the provider as usual. Lookups that hit skip
+    /// compilation entirely. The schemas must have been compiled from the
+    /// same schemas the provider serves, or validation will diverge from them.
+    pub fn with_precompiled<I>(mut self, schemas: I) -> Self
+    where
+        I: IntoIterator<Item = SharedCompiledSchema>,
+    {
+        for schema in schemas {
+            self.precompiled_names
+                .entry(schema.name.clone())
+                .and_modify(|url| {
+                    if url.as_deref() != Some(schema.url.as_str()) {
+                        *url = None;
+                    }
+                })
+                .or_insert_with(|| Some(schema.url.clone()));
+            self.precompiled.insert(schema.url.clone(), schema);
         }
+        self
+    }
+
+    /// Precompiled schema served for a URL or an unambiguous name.
+    fn precompiled(&self, schema_name: &str) -> Option<&SharedCompiledSchema> {
+        self.precompiled.get(schema_name).or_else(|| {
+            let url = self.precompiled_names.get(schema_name)?.as_deref()?;
+            self.precompiled.get(url)
+        })
+    }
+
+    /// Size the compiled schema cache (see [`CacheTuning`]).
+    ///
+    /// Replaces the cache, so call it while building the compiler. WASM
+    /// builds keep compiled schemas unbounded and ignore the tuning.
+    pub fn with_cache_tuning(mut self, tuning: &CacheTuning) -> Self {
+        self.compiled_cache = compiled_cache(tuning);
+        self
+    }
+
+    /// Persist compiled schemas to `dir` and reload them from there.
+    ///
+    /// Files are written as zstd-compressed JSON, one per crate version and
+    /// requested schema. Each records a hash of every schema the compile
+    /// looked up; a file is only reused while the provider still serves the
+    /// same schemas, and is rewritten otherwise. Failures to read or write
+    /// the cache fall back to compiling in memory.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_disk_cache(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.disk_cache = Some(dir.into());
+        self
     }
 
     /// Access the underlying schema provider (e.g. to read a profile's base
@@ -61,47 +284,262 @@ impl SchemaCompiler {
         &self.schema_provider
     }
 
+    /// Merge profile chains through a cache shared with other consumers.
+    pub fn with_profile_cache(mut self, cache: Arc<ProfileMergeCache>) -> Self {
+        self.profile_cache = cache;
+        self
+    }
+
+    /// The cache of merged profile chains.
+    pub fn profile_cache(&self) -> &Arc<ProfileMergeCache> {
+        &self.profile_cache
+    }
+
+    /// The schema (by name or URL) merged with its inheritance chain, before
+    /// element types are expanded.
+    pub async fn merged_schema(&self, schema_name: &str) -> Option<Arc<FhirSchema>> {
+        let schema = self.schema_provider.get_schema_by_url(schema_name).await?;
+        Some(
+            self.profile_cache
+                .merged(self.schema_provider.as_ref(), &schema)
+                .await,
+        )
+    }
+
     /// Get or compile a schema by name/URL
-    #[async_recursion]
     pub async fn compile(&self, schema_name: &str) -> Result<SharedCompiledSchema, CompileError> {
+        self.compile_traced(schema_name)
+            .await
+            .map(|(compiled, _)| compiled)
+    }
+
+    /// [`Self::compile`], also telling where the schema came from.
+    #[async_recursion]
+    pub async fn compile_traced(
+        &self,
+        schema_name: &str,
+    ) -> Result<(SharedCompiledSchema, SchemaSource), CompileError> {
+        if let Some(precompiled) = self.precompiled(schema_name) {
+            return Ok((Arc::clone(precompiled), SchemaSource::Precompiled));
+        }
+
         // Check cache first
         if let Some(cached) = self.compiled_cache.get(schema_name).await {
-            return Ok(cached);
+            return Ok((cached, SchemaSource::Cached));
+        }
+
+        // Wait for any in-flight compilation of the same schema, then check
+        // whether it left a result behind
+        let gate = self.gate(schema_name);
+        let _guard = gate.lock().await;
+        if let Some(cached) = self.compiled_cache.get(schema_name).await {
+            return Ok((cached, SchemaSource::Cached));
+        }
+
+        let Some(schema) = self.find_schema(schema_name).await else {
+            self.release_gate(schema_name);
+            return Err(CompileError {
+                message: format!("Schema not found: {}", schema_name),
+                schema_name: Some(schema_name.to_string()),
+            });
+        };
+
+        // The same profile asked for by another name (type name, URL,
+        // `url|version`) shares the schema compiled under its canonical.
+        let canonical = Self::canonical(&schema).unwrap_or_else(|| schema_name.to_string());
+        if let Some(cached) = self.compiled_cache.get(&canonical).await {
+            self.compiled_cache
+                .insert(schema_name.to_string(), cached.clone())
+                .await;
+            self.alias_dependencies(&canonical, schema_name);
+            self.release_gate(schema_name);
+            return Ok((cached, SchemaSource::Cached));
         }
 
-        // Compile and cache
-        let compiled = self.compile_internal(schema_name).await?;
-        let arc = Arc::new(compiled);
+        // Compile (or reload from disk) and cache
+        let compiled = self.load_or_compile(schema_name, &schema).await;
+        let compiled = match compiled {
+            Ok(compiled) => Arc::new(compiled),
+            Err(e) => {
+                self.release_gate(schema_name);
+                return Err(e);
+            }
+        };
         self.compiled_cache
-            .insert(schema_name.to_string(), arc.clone())
+            .insert(schema_name.to_string(), compiled.clone())
             .await;
-        Ok(arc)
+        if canonical != schema_name {
+            self.alias_dependencies(schema_name, &canonical);
+            self.compiled_cache
+                .insert(canonical, compiled.clone())
+                .await;
+        }
+        self.release_gate(schema_name);
+        Ok((compiled, SchemaSource::Compiled))
     }
 
-    /// Internal compilation logic
-    #[async_recursion]
-    async fn compile_internal(&self, schema_name: &str) -> Result<CompiledSchema, CompileError> {
-        // 1. Load base schema (use get_schema_by_url to support both names and URLs)
-        let schema = self
-            .schema_provider
-            .get_schema_by_url(schema_name)
+    /// Look a schema up by name or canonical URL, also accepting a versioned
+    /// canonical (`url|version`).
+    async fn find_schema(&self, schema_name: &str) -> Option<Arc<FhirSchema>> {
+        if let Some(schema) = self.schema_provider.get_schema_by_url(schema_name).await {
+            return Some(schema);
+        }
+        let (url, version) = schema_name.split_once('|')?;
+        self.schema_provider
+            .get_schema_by_url(url)
             .await
-            .ok_or_else(|| CompileError {
-                message: format!("Schema not found: {}", schema_name),
-                schema_name: Some(schema_name.to_string()),
-            })?;
+            .filter(|schema| schema.version.as_deref() == Some(version))
+    }
 
-        // 2. Resolve inheritance chain and merge
-        let chain = self.resolve_chain(&schema).await?;
-        let merged = self.merge_chain(&chain);
+    /// Cache key shared by every name of `schema`: its versioned canonical.
+    fn canonical(schema: &FhirSchema) -> Option<String> {
+        if schema.url.is_empty() {
+            return None;
+        }
+        Some(match &schema.version {
+            Some(version) => format!("{}|{}", schema.url, version),
+            None => schema.url.clone(),
+        })
+    }
+
+    /// Compile several schemas concurrently, e.g. to warm the cache with the
+    /// resource types of a Bundle before validating its entries.
+    ///
+    /// Duplicate names are compiled once, and types shared between the
+    /// schemas (`CodeableConcept`, `Identifier`, ...) are compiled by
+    /// whichever schema reaches them first while the others wait for it.
+    /// Results are returned in the order the names were first given.
+    pub async fn compile_many<I, S>(
+        &self,
+        names: I,
+    ) -> Vec<(String, Result<SharedCompiledSchema, CompileError>)>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut visited = HashSet::new();
+        let names: Vec<String> = names
+            .into_iter()
+            .map(Into::into)
+            .filter(|name| visited.insert(name.clone()))
+            .collect();
+
+        let results = join_all(names.iter().map(|name| self.compile(name))).await;
+        names.into_iter().zip(results).collect()
+    }
+
+    /// Read a compiled schema from the disk cache, or compile it and write it
+    /// there.
+    async fn load_or_compile(
+        &self,
+        schema_name: &str,
+        schema: &FhirSchema,
+    ) -> Result<CompiledSchema, CompileError> {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(dir) = &self.disk_cache {
+            let path = dir.join(format!("{}.json.zst", Self::fingerprint(schema_name)));
+            if let Some(entry) = disk_cache::read(&path)
+                && self.is_current(&entry.dependencies).await
+            {
+                self.remember_dependencies(schema_name, entry.dependencies);
+                return Ok(entry.schema);
+            }
+            let lookups = Lookups::recording();
+            lookups.record(schema_name, Some(schema));
+            let schema = self.compile_internal(schema, &lookups).await?;
+            let entry = disk_cache::Entry {
+                dependencies: lookups.into_inner(),
+                schema,
+            };
+            if let Err(e) = disk_cache::write(dir, &path, &entry) {
+                eprintln!(
+                    "Failed to write compiled schema cache {}: {e}",
+                    path.display()
+                );
+            }
+            self.remember_dependencies(schema_name, entry.dependencies);
+            return Ok(entry.schema);
+        }
+        self.compile_internal(schema, &Lookups::default()).await
+    }
+
+    /// Disk cache file name for a schema: SHA-256 over the crate version and
+    /// the requested name.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn fingerprint(schema_name: &str) -> String {
+        use sha2::{Digest, Sha256};
+
+        let mut hasher = Sha256::new();
+        hasher.update(crate::VERSION.as_bytes());
+        hasher.update([0]);
+        hasher.update(schema_name.as_bytes());
+        format!("{:x}", hasher.finalize())
+    }
+
+    /// Whether the provider still serves every schema a cached compile
+    /// looked up, unchanged.
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn is_current(&self, dependencies: &Dependencies) -> bool {
+        for (key, hash) in dependencies {
+            let schema = self.find_schema(key).await;
+            if schema.as_deref().map(content_hash) != *hash {
+                return false;
+            }
+        }
+        true
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn remember_dependencies(&self, schema_name: &str, dependencies: Dependencies) {
+        let mut remembered = self.dependencies.lock().unwrap_or_else(|e| e.into_inner());
+        remembered.insert(schema_name.to_string(), Arc::new(dependencies));
+    }
+
+    /// Share the dependencies remembered for `from` with another name of the
+    /// same schema.
+    fn alias_dependencies(&self, from: &str, to: &str) {
+        let mut remembered = self.dependencies.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(dependencies) = remembered.get(from).cloned() {
+            remembered.insert(to.to_string(), dependencies);
+        }
+    }
+
+    /// Dependencies remembered for a schema compiled by this compiler.
+    fn dependencies_of(&self, schema_name: &str) -> Option<Arc<Dependencies>> {
+        let remembered = self.dependencies.lock().unwrap_or_else(|e| e.into_inner());
+        remembered.get(schema_name).cloned()
+    }
+
+    fn gate(&self, schema_name: &str) -> Arc<futures::lock::Mutex<()>> {
+        let mut in_flight = self.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+        Arc::clone(in_flight.entry(schema_name.to_string()).or_default())
+    }
+
+    fn release_gate(&self, schema_name: &str) {
+        let mut in_flight = self.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+        in_flight.remove(schema_name);
+    }
+
+    /// Internal compilation logic
+    #[async_recursion]
+    async fn compile_internal(
+        &self,
+        schema: &FhirSchema,
+        lookups: &Lookups,
+    ) -> Result<CompiledSchema, CompileError> {
+        // 1. Resolve inheritance chain and merge
+        let chain = self.resolve_chain(schema, lookups).await?;
+        let merged = self.profile_cache.get_or_merge(&chain);
 
-        // 3. Recursively expand all element types
-        let elements = self.expand_elements(merged.elements.as_ref()).await?;
+        // 2. Recursively expand all element types
+        let elements = self
+            .expand_elements(merged.elements.as_ref(), lookups)
+            .await?;
 
-        // 4. Collect all constraints from the chain
-        let constraints = self.collect_constraints(&chain);
+        // 3. Collect all constraints from the chain
+        let constraints = self.collect_constraints(&chain, merged.elements.as_ref());
 
-        // 5. Build required/excluded sets
+        // 4. Build required/excluded sets
         let required: HashSet<String> = merged
             .required
             .as_ref()
@@ -116,7 +554,9 @@ impl SchemaCompiler {
 
         Ok(CompiledSchema {
             url: schema.url.clone(),
+            version: schema.version.clone(),
             name: schema.name.clone(),
+            type_name: schema.type_name.clone(),
             elements,
             constraints,
             required,
@@ -126,179 +566,26 @@ impl SchemaCompiler {
         })
     }
 
-    /// Resolve inheritance chain from base to derived
+    /// Resolve inheritance chain from base to derived, recording the base
+    /// lookups
     async fn resolve_chain(
         &self,
         schema: &FhirSchema,
+        lookups: &Lookups,
     ) -> Result<Vec<Arc<FhirSchema>>, CompileError> {
-        let mut chain = vec![Arc::new(schema.clone())];
-        let mut current = schema.clone();
-        let mut visited = HashSet::new();
-        visited.insert(schema.url.clone());
-
-        // Follow base references
-        while let Some(base_url) = &current.base {
-            if visited.contains(base_url) {
-                // Cycle detected
-                break;
-            }
-            visited.insert(base_url.clone());
-
-            if let Some(base_schema) = self.schema_provider.get_schema_by_url(base_url).await {
-                chain.push(base_schema.clone());
-                current = (*base_schema).clone();
-            } else {
-                // Base not found, stop here
-                break;
-            }
-        }
-
-        // Reverse so base comes first
-        chain.reverse();
-        Ok(chain)
-    }
-
-    /// Merge inheritance chain into single schema
-    fn merge_chain(&self, chain: &[Arc<FhirSchema>]) -> FhirSchema {
-        if chain.is_empty() {
-            return FhirSchema::default();
-        }
-
-        let mut merged = (*chain[0]).clone();
-
-        for schema in chain.iter().skip(1) {
-            merged = self.merge_schemas(&merged, schema);
-        }
-
-        merged
-    }
-
-    /// Merge two schemas (base + overlay)
-    fn merge_schemas(&self, base: &FhirSchema, overlay: &FhirSchema) -> FhirSchema {
-        let mut result = base.clone();
-
-        // Overlay takes precedence for metadata
-        result.url = overlay.url.clone();
-        result.name = overlay.name.clone();
-        if overlay.version.is_some() {
-            result.version = overlay.version.clone();
-        }
-
-        // Merge elements
-        if let Some(overlay_elements) = &overlay.elements {
-            let mut merged_elements = result.elements.unwrap_or_default();
-            for (key, element) in overlay_elements {
-                if let Some(base_element) = merged_elements.get(key) {
-                    merged_elements.insert(key.clone(), self.merge_elements(base_element, element));
-                } else {
-                    merged_elements.insert(key.clone(), element.clone());
-                }
+        let chain = profiles::resolve_chain(self.schema_provider.as_ref(), schema).await;
+        for (base, derived) in chain.iter().zip(chain.iter().skip(1)) {
+            if let Some(url) = &derived.base {
+                lookups.record(url, Some(base));
             }
-            result.elements = Some(merged_elements);
-        }
-
-        // Union required elements
-        if let Some(overlay_required) = &overlay.required {
-            let mut required = result.required.unwrap_or_default();
-            required.extend(overlay_required.iter().cloned());
-            result.required = Some(required);
         }
-
-        // Union excluded elements
-        if let Some(overlay_excluded) = &overlay.excluded {
-            let mut excluded = result.excluded.unwrap_or_default();
-            excluded.extend(overlay_excluded.iter().cloned());
-            result.excluded = Some(excluded);
-        }
-
-        // Union constraints (overlay takes precedence for same key)
-        if let Some(overlay_constraints) = &overlay.constraint {
-            let mut constraints = result.constraint.unwrap_or_default();
-            for (key, constraint) in overlay_constraints {
-                constraints.insert(key.clone(), constraint.clone());
-            }
-            result.constraint = Some(constraints);
+        // The chain stops at a base the provider does not have, or at a cycle
+        if let Some(url) = chain.first().and_then(|root| root.base.as_ref())
+            && !chain.iter().any(|s| &s.url == url)
+        {
+            lookups.record(url, None);
         }
-
-        result
-    }
-
-    /// Merge two elements
-    fn merge_elements(
-        &self,
-        base: &FhirSchemaElement,
-        overlay: &FhirSchemaElement,
-    ) -> FhirSchemaElement {
-        let mut result = base.clone();
-
-        // Overlay cardinality
-        if overlay.min.is_some() {
-            result.min = overlay.min;
-        }
-        if overlay.max.is_some() {
-            result.max = overlay.max;
-        }
-        if overlay.array.is_some() {
-            result.array = overlay.array;
-        }
-
-        // Overlay binding
-        if overlay.binding.is_some() {
-            result.binding = overlay.binding.clone();
-        }
-
-        // Overlay pattern
-        if overlay.pattern.is_some() {
-            result.pattern = overlay.pattern.clone();
-        }
-
-        // Overlay must_support
-        if overlay.must_support.is_some() {
-            result.must_support = overlay.must_support;
-        }
-
-        // Overlay refers (reference targets)
-        if overlay.refers.is_some() {
-            result.refers = overlay.refers.clone();
-        }
-
-        // Overlay choice restrictions — profiles use this to narrow value[x] to
-        // a single concrete type (e.g. humanname-own-prefix → ["valueString"]).
-        // Without this, the base Extension.value choices list survives and any
-        // valueXxx variant is accepted.
-        if overlay.choices.is_some() {
-            result.choices = overlay.choices.clone();
-        }
-
-        // Overlay type narrowing (similar idea: profile may declare an explicit
-        // type on what was a generic Element).
-        if overlay.type_name.is_some() {
-            result.type_name = overlay.type_name.clone();
-        }
-
-        // Merge nested elements
-        if let Some(overlay_nested) = &overlay.elements {
-            let mut nested = result.elements.unwrap_or_default();
-            for (key, element) in overlay_nested {
-                if let Some(base_element) = nested.get(key) {
-                    nested.insert(key.clone(), self.merge_elements(base_element, element));
-                } else {
-                    nested.insert(key.clone(), element.clone());
-                }
-            }
-            result.elements = Some(nested);
-        }
-
-        // Union constraints
-        if let Some(overlay_constraints) = &overlay.constraint {
-            let mut constraints = result.constraint.unwrap_or_default();
-            for (key, constraint) in overlay_constraints {
-                constraints.insert(key.clone(), constraint.clone());
-            }
-            result.constraint = Some(constraints);
-        }
-
-        result
+        Ok(chain)
     }
 
     /// Recursively expand element types inline
@@ -306,6 +593,7 @@ impl SchemaCompiler {
     async fn expand_elements(
         &self,
         elements: Option<&HashMap<String, FhirSchemaElement>>,
+        lookups: &Lookups,
     ) -> Result<HashMap<String, CompiledElement>, CompileError> {
         let Some(elements) = elements else {
             return Ok(HashMap::new());
@@ -314,7 +602,7 @@ impl SchemaCompiler {
         let mut result = HashMap::new();
 
         for (name, element) in elements {
-            let compiled = self.expand_element(name, element).await?;
+            let compiled = self.expand_element(name, element, lookups).await?;
             result.insert(name.clone(), compiled);
         }
 
@@ -327,9 +615,14 @@ impl SchemaCompiler {
         &self,
         name: &str,
         element: &FhirSchemaElement,
+        lookups: &Lookups,
     ) -> Result<CompiledElement, CompileError> {
         let type_info = self.determine_type_info(element);
         let mut children = HashMap::new();
+        // Children that must be present or absent: the element's own, and
+        // those of its type when the type's elements are inlined
+        let mut required = element.required.clone().unwrap_or_default();
+        let mut excluded = element.excluded.clone().unwrap_or_default();
 
         // Expand nested elements based on type
         match &type_info {
@@ -338,31 +631,49 @@ impl SchemaCompiler {
                     && Self::should_expand_named_type(type_name)
                 {
                     if let Some(nested) = &element.elements {
-                        if let Some(type_schema) =
-                            self.schema_provider.get_schema_by_url(type_name).await
-                        {
+                        let type_schema = self.schema_provider.get_schema_by_url(type_name).await;
+                        lookups.record(type_name, type_schema.as_deref());
+                        if let Some(type_schema) = type_schema {
+                            required.extend(type_schema.required.iter().flatten().cloned());
+                            excluded.extend(type_schema.excluded.iter().flatten().cloned());
                             let mut merged_children =
                                 type_schema.elements.as_ref().cloned().unwrap_or_default();
                             for (key, overlay_child) in nested {
                                 if let Some(base_child) = merged_children.get(key) {
                                     merged_children.insert(
                                         key.clone(),
-                                        self.merge_elements(base_child, overlay_child),
+                                        profiles::merge_elements(base_child, overlay_child),
                                     );
                                 } else {
                                     merged_children.insert(key.clone(), overlay_child.clone());
                                 }
                             }
                             children =
-                                Box::pin(self.expand_elements(Some(&merged_children))).await?;
+                                Box::pin(self.expand_elements(Some(&merged_children), lookups))
+                                    .await?;
                         } else {
-                            children = Box::pin(self.expand_elements(Some(nested))).await?;
+                            children =
+                                Box::pin(self.expand_elements(Some(nested), lookups)).await?;
+                        }
+                    } else {
+                        match self.compile(type_name).await {
+                            Ok(type_schema) => {
+                                if let Some(dependencies) = self.dependencies_of(type_name) {
+                                    lookups.extend(&dependencies);
+                                }
+                                children = type_schema.elements.clone();
+                                required.extend(type_schema.required.iter().cloned());
+                                excluded.extend(type_schema.excluded.iter().cloned());
+                            }
+                            Err(_) if lookups.is_recording() => {
+                                let type_schema = self.find_schema(type_name).await;
+                                lookups.record(type_name, type_schema.as_deref());
+                            }
+                            Err(_) => {}
                         }
-                    } else if let Ok(type_schema) = self.compile(type_name).await {
-                        children = type_schema.elements.clone();
                     }
                 } else if let Some(nested) = &element.elements {
-                    children = Box::pin(self.expand_elements(Some(nested))).await?;
+                    children = Box::pin(self.expand_elements(Some(nested), lookups)).await?;
                 }
             }
             _ => {
@@ -373,15 +684,30 @@ impl SchemaCompiler {
         // Extract constraints
         let constraints = self.extract_element_constraints(element);
 
-        // Extract binding
-        let binding = element.binding.as_ref().map(|b| CompiledBinding {
-            value_set: b.value_set.clone().unwrap_or_default(),
-            strength: BindingStrength::parse(&b.strength),
-            description: b.binding_name.clone(),
+        // Extract binding; one without a value set has nothing to check codes against
+        let binding = element.binding.as_ref().and_then(|b| {
+            Some(CompiledBinding {
+                value_set: b.value_set.clone()?,
+                strength: BindingStrength::parse(&b.strength),
+                description: b.binding_name.clone(),
+            })
         });
 
         // Compile slicing if present
-        let slicing = element.slicing.as_ref().map(|s| self.compile_slicing(s));
+        let slicing = match &element.slicing {
+            Some(slicing) => Some(
+                self.compile_slicing(name, element, slicing, lookups)
+                    .await?,
+            ),
+            None => None,
+        };
+
+        let regexes = self.primitive_regexes(element, lookups).await;
+
+        required.sort();
+        required.dedup();
+        excluded.sort();
+        excluded.dedup();
 
         Ok(CompiledElement {
             name: name.to_string(),
@@ -390,11 +716,14 @@ impl SchemaCompiler {
             min: element.min.unwrap_or(0),
             max: element.max,
             children,
+            required,
+            excluded,
             element_reference: element.element_reference.clone(),
             binding,
             reference_targets: element.refers.clone(),
             constraints,
             pattern: element.pattern.as_ref().map(|p| p.value.clone()),
+            regexes,
             choices: element.choices.clone(),
             slicing,
             short: element.short.clone(),
@@ -403,6 +732,43 @@ impl SchemaCompiler {
         })
     }
 
+    /// The regexes a primitive element's value must match: its own, and the
+    /// `value` regexes of its type profiles and their base chains. Profiles
+    /// the provider does not know add nothing.
+    async fn primitive_regexes(
+        &self,
+        element: &FhirSchemaElement,
+        lookups: &Lookups,
+    ) -> Vec<String> {
+        let mut regexes: Vec<String> = element.regex.iter().cloned().collect();
+        for profile in element.type_profile.iter().flatten() {
+            let schema = self.schema_provider.get_schema_by_url(profile).await;
+            lookups.record(profile, schema.as_deref());
+            let Some(schema) = schema else {
+                continue;
+            };
+            let Ok(chain) = self.resolve_chain(&schema, lookups).await else {
+                continue;
+            };
+            for schema in chain
+                .iter()
+                .filter(|s| s.derivation.as_deref() == Some("constraint"))
+            {
+                let value_regex = schema
+                    .elements
+                    .as_ref()
+                    .and_then(|elements| elements.get("value"))
+                    .and_then(|value| value.regex.as_ref());
+                if let Some(regex) = value_regex
+                    && !regexes.contains(regex)
+                {
+                    regexes.push(regex.clone());
+                }
+            }
+        }
+        regexes
+    }
+
     /// Whether an element's named type should be expanded into child elements.
     fn should_expand_named_type(type_name: &str) -> bool {
         !is_primitive_type(type_name) && type_name != "Resource" && type_name != "Reference"
@@ -445,43 +811,112 @@ impl SchemaCompiler {
 
         constraints
             .iter()
-            .map(|(key, c)| self.convert_constraint(key, c))
+            .filter(|(_, c)| !c.is_suppressed())
+            .map(|(key, c)| self.convert_constraint(key, c, element.elements.as_ref()))
             .collect()
     }
 
-    /// Collect all constraints from inheritance chain
-    fn collect_constraints(&self, chain: &[Arc<FhirSchema>]) -> Vec<CompiledConstraint> {
-        let mut result = Vec::new();
+    /// Collect all constraints from inheritance chain (base first). A key
+    /// redefined by a derived schema replaces the base definition, and a
+    /// suppressed definition removes the constraint.
+    fn collect_constraints(
+        &self,
+        chain: &[Arc<FhirSchema>],
+        elements: Option<&HashMap<String, FhirSchemaElement>>,
+    ) -> Vec<CompiledConstraint> {
+        let mut result: Vec<(&str, &FhirSchemaConstraint)> = Vec::new();
 
         for schema in chain {
             if let Some(constraints) = &schema.constraint {
                 for (key, constraint) in constraints {
-                    result.push(self.convert_constraint(key, constraint));
+                    match result.iter_mut().find(|(k, _)| *k == key.as_str()) {
+                        Some(existing) => existing.1 = constraint,
+                        None => result.push((key, constraint)),
+                    }
                 }
             }
         }
 
         result
+            .into_iter()
+            .filter(|(_, c)| !c.is_suppressed())
+            .map(|(key, c)| self.convert_constraint(key, c, elements))
+            .collect()
     }
 
-    /// Convert FhirSchemaConstraint to CompiledConstraint
+    /// Convert FhirSchemaConstraint to CompiledConstraint. `elements` are the
+    /// children of the constrained element, searched for the descendants
+    /// whose `condition` names the constraint.
     fn convert_constraint(
         &self,
         key: &str,
         constraint: &FhirSchemaConstraint,
+        elements: Option<&HashMap<String, FhirSchemaElement>>,
     ) -> CompiledConstraint {
+        let mut implicated = Vec::new();
+        Self::collect_implicated(elements, key, "", &mut implicated);
+        implicated.sort();
         CompiledConstraint {
             key: key.to_string(),
             expression: constraint.expression.clone(),
             human: constraint.human.clone(),
             severity: ConstraintSeverity::parse(&constraint.severity),
+            implicated,
         }
     }
 
-    /// Compile slicing definition
-    fn compile_slicing(&self, slicing: &FhirSchemaSlicing) -> CompiledSlicing {
+    /// Walk the inline element tree for elements whose `condition` lists
+    /// `key`. Named types are not entered: their conditions refer to their
+    /// own constraints.
+    fn collect_implicated(
+        elements: Option<&HashMap<String, FhirSchemaElement>>,
+        key: &str,
+        prefix: &str,
+        implicated: &mut Vec<String>,
+    ) {
+        for (name, element) in elements.into_iter().flatten() {
+            let path = if prefix.is_empty() {
+                name.clone()
+            } else {
+                format!("{prefix}.{name}")
+            };
+            if element
+                .condition
+                .as_ref()
+                .is_some_and(|condition| condition.iter().any(|k| k == key))
+            {
+                implicated.push(path.clone());
+            }
+            Self::collect_implicated(element.elements.as_ref(), key, &path, implicated);
+        }
+    }
+
+    /// The extension url a slice admits: from its `match`, or from the
+    /// extension profile the converter put in the slice schema's `url`.
+    fn slice_url(slice: &FhirSchemaSliceMatch) -> Option<String> {
+        slice
+            .match_value
+            .as_ref()
+            .and_then(|m| m.get("url"))
+            .and_then(|u| u.as_str())
+            .or(slice.schema.as_ref().and_then(|s| s.url.as_deref()))
+            .map(str::to_string)
+    }
+
+    /// Compile slicing definition of the element `name`.
+    ///
+    /// A slice's schema is compiled over the sliced element's definition, so
+    /// it can stand in for that element when validating the items the slice
+    /// matches.
+    async fn compile_slicing(
+        &self,
+        name: &str,
+        element: &FhirSchemaElement,
+        slicing: &FhirSchemaSlicing,
+        lookups: &Lookups,
+    ) -> Result<CompiledSlicing, CompileError> {
         // Compile discriminators
-        let discriminators = slicing
+        let discriminators: Vec<CompiledDiscriminator> = slicing
             .discriminator
             .as_ref()
             .map(|discs| {
@@ -495,34 +930,84 @@ impl SchemaCompiler {
             })
             .unwrap_or_default();
 
+        // Extension slicing discriminates on `url`: keep each slice's
+        // extension url so items are classified by it first.
+        let by_url = discriminators.iter().any(|d| d.path.trim() == "url");
+
         // Compile slices
-        let slices = slicing
-            .slices
-            .as_ref()
-            .map(|slice_map| {
-                slice_map
-                    .iter()
-                    .map(|(name, slice_def)| {
-                        let compiled_slice = CompiledSlice {
-                            name: name.clone(),
-                            match_value: slice_def.match_value.clone(),
-                            min: slice_def.min,
-                            max: slice_def.max,
-                            // TODO: compile nested schema if needed
-                            schema: None,
-                        };
-                        (name.clone(), compiled_slice)
-                    })
-                    .collect()
-            })
-            .unwrap_or_default();
+        let mut base = element.clone();
+        base.slicing = None;
+        let mut slices = HashMap::new();
+        for (slice_name, slice_def) in slicing.slices.iter().flatten() {
+            let schema = match &slice_def.schema {
+                Some(slice_schema) => {
+                    let merged = profiles::merge_elements(&base, slice_schema);
+                    Some(Box::new(
+                        Box::pin(self.expand_element(name, &merged, lookups)).await?,
+                    ))
+                }
+                None => None,
+            };
+            slices.insert(
+                slice_name.clone(),
+                CompiledSlice {
+                    name: slice_name.clone(),
+                    match_value: slice_def.match_value.clone(),
+                    url: if by_url {
+                        Self::slice_url(slice_def)
+                    } else {
+                        None
+                    },
+                    min: slice_def.min,
+                    max: slice_def.max,
+                    schema,
+                },
+            );
+        }
 
-        CompiledSlicing {
+        Ok(CompiledSlicing {
             rules: SlicingRules::parse(slicing.rules.as_deref().unwrap_or("open")),
             ordered: slicing.ordered.unwrap_or(false),
             discriminators,
             slices,
-        }
+        })
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod disk_cache {
+    use std::fs;
+    use std::io;
+    use std::path::Path;
+
+    use serde::{Deserialize, Serialize};
+
+    use super::{CompiledSchema, Dependencies};
+
+    /// A cached compiled schema and the schemas it was compiled from.
+    #[derive(Serialize, Deserialize)]
+    pub(super) struct Entry {
+        pub(super) dependencies: Dependencies,
+        pub(super) schema: CompiledSchema,
+    }
+
+    /// Files that cannot be read or decoded, including those written in an
+    /// older format, count as missing.
+    pub(super) fn read(path: &Path) -> Option<Entry> {
+        let compressed = fs::read(path).ok()?;
+        let decoded = zstd::stream::decode_all(compressed.as_slice()).ok()?;
+        serde_json::from_slice(&decoded).ok()
+    }
+
+    /// Write through a temporary file and rename it into place, so
+    /// concurrent processes never read a partial file.
+    pub(super) fn write(dir: &Path, path: &Path, entry: &Entry) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        let json = serde_json::to_vec(entry)?;
+        let compressed = zstd::stream::encode_all(json.as_slice(), 3)?;
+        let tmp = path.with_extension(format!("tmp{}", std::process::id()));
+        fs::write(&tmp, compressed)?;
+        fs::rename(&tmp, path)
     }
 }
 
@@ -538,6 +1023,7 @@ impl Default for FhirSchema {
             base: None,
             abstract_type: None,
             class: String::new(),
+            title: None,
             description: None,
             package_name: None,
             package_version: None,
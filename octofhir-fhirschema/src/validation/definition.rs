@@ -0,0 +1,510 @@
+//! Metaprofile rules for StructureDefinitions.
+//!
+//! A server accepting profiles in an IG upload should reject malformed ones
+//! before converting them, with errors pointing at the offending element.
+//! The StructureDefinition schema covers structure and value types; this
+//! module adds the rules the schema cannot express:
+//!
+//! - The `sdf-*` invariants of StructureDefinition (base definition,
+//!   extension context, element ids and paths, root element content,
+//!   default values in constraints), reported as `FS1010` with the
+//!   invariant key, at the element that breaks them.
+//! - Element ordering: a snapshot lists every parent before its children,
+//!   and neither view returns to an element's subtree once it has left it
+//!   (other than for a new slice of the element).
+//! - Slicing: slicing definitions carry `rules` and well-formed
+//!   discriminators, slice names are unique per element and, in a
+//!   snapshot, a slice follows the slicing definition of its element.
+//!
+//! Ordering and slicing problems are reported as `FS1019`.
+//! [`FhirValidator::validate_structure_definition`](super::FhirValidator::validate_structure_definition)
+//! runs these checks together with schema validation.
+
+use std::collections::{HashMap, HashSet};
+
+use serde_json::Value as JsonValue;
+
+use super::{FhirSchemaErrorCode, ValidationError};
+use crate::types::ErrorPath;
+
+/// Invariants checked here. Their FHIRPath evaluation reports them on the
+/// resource root only, so schema validation results for these keys are
+/// superseded by the located errors of this module.
+pub const CHECKED_INVARIANTS: &[&str] = &[
+    "sdf-1", "sdf-3", "sdf-4", "sdf-5", "sdf-6", "sdf-7", "sdf-8", "sdf-8a", "sdf-14", "sdf-15",
+    "sdf-15a", "sdf-16", "sdf-17", "sdf-18", "sdf-21", "sdf-23",
+];
+
+/// Slicing discriminator types (`position` is R5)
+const DISCRIMINATOR_TYPES: &[&str] = &["value", "exists", "pattern", "type", "profile", "position"];
+
+const SLICING_RULES: &[&str] = &["closed", "open", "openAtEnd"];
+
+/// Check a StructureDefinition against the metaprofile rules.
+pub fn check_structure_definition(sd: &JsonValue) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    if sd.get("resourceType").and_then(JsonValue::as_str) != Some("StructureDefinition") {
+        errors.push(definition_error(
+            "StructureDefinition".to_string(),
+            "Resource is not a StructureDefinition".to_string(),
+        ));
+        return errors;
+    }
+
+    let string = |key: &str| sd.get(key).and_then(JsonValue::as_str);
+    let type_name = string("type").unwrap_or_default();
+    let kind = string("kind").unwrap_or_default();
+    let derivation = string("derivation");
+    let is_logical = kind == "logical";
+    let is_constraint = derivation == Some("constraint");
+
+    if sd.get("abstract").and_then(JsonValue::as_bool) != Some(true)
+        && string("baseDefinition").is_none()
+    {
+        errors.push(invariant(
+            "StructureDefinition",
+            "sdf-4",
+            "A structure that is not abstract must have a baseDefinition",
+        ));
+    }
+    if type_name == "Extension"
+        && derivation != Some("specialization")
+        && array(sd, "context").is_empty()
+    {
+        errors.push(invariant(
+            "StructureDefinition",
+            "sdf-5",
+            "An extension definition must have a context",
+        ));
+    }
+    if sd.get("snapshot").is_none() && sd.get("differential").is_none() {
+        errors.push(invariant(
+            "StructureDefinition",
+            "sdf-6",
+            "A structure must have a differential, a snapshot or both",
+        ));
+    }
+    if derivation == Some("specialization") && !is_logical {
+        let expected = format!(
+            "http://hl7.org/fhir/StructureDefinition/{}",
+            string("id").unwrap_or_default()
+        );
+        if string("url") != Some(expected.as_str()) {
+            errors.push(invariant(
+                "StructureDefinition.url",
+                "sdf-7",
+                &format!("A base resource or type definition must have the url {expected}"),
+            ));
+        }
+    }
+    if type_name != "Extension" && !array(sd, "contextInvariant").is_empty() {
+        errors.push(invariant(
+            "StructureDefinition.contextInvariant",
+            "sdf-18",
+            "Context invariants can only be used for extensions",
+        ));
+    }
+
+    let view = View {
+        type_name,
+        is_logical,
+        is_constraint,
+    };
+    for (name, is_snapshot) in [("snapshot", true), ("differential", false)] {
+        let elements = sd
+            .get(name)
+            .map(|v| array(v, "element"))
+            .unwrap_or_default();
+        view.check(
+            elements,
+            &format!("StructureDefinition.{name}.element"),
+            is_snapshot,
+            &mut errors,
+        );
+    }
+    errors
+}
+
+struct View<'a> {
+    type_name: &'a str,
+    is_logical: bool,
+    is_constraint: bool,
+}
+
+impl View<'_> {
+    fn check(
+        &self,
+        elements: &[JsonValue],
+        base_path: &str,
+        is_snapshot: bool,
+        errors: &mut Vec<ValidationError>,
+    ) {
+        let Some(first) = elements.first() else {
+            return;
+        };
+        let root = if self.is_logical {
+            // Logical models name their root element freely
+            str_field(first, "path").unwrap_or_default()
+        } else {
+            self.type_name
+        };
+        let (path_key, ids_key) = if is_snapshot {
+            ("sdf-8", "sdf-16")
+        } else {
+            ("sdf-8a", "sdf-17")
+        };
+
+        let mut ids = HashSet::new();
+        let mut paths = HashSet::new();
+        // Slice names seen per sliced element path, and the paths with a
+        // slicing definition so far
+        let mut slices: HashMap<&str, HashSet<&str>> = HashMap::new();
+        let mut sliced: HashSet<&str> = HashSet::new();
+        // Elements whose subtree is being listed, and those already left
+        let mut open: Vec<&str> = Vec::new();
+        let mut closed: HashSet<&str> = HashSet::new();
+
+        for (i, element) in elements.iter().enumerate() {
+            let at = format!("{base_path}[{i}]");
+            let path = str_field(element, "path").unwrap_or_default();
+            let slice_name = str_field(element, "sliceName");
+            let is_root = !path.contains('.');
+
+            let in_root = path == root || path.starts_with(&format!("{root}."));
+            if !in_root {
+                errors.push(invariant(
+                    &format!("{at}.path"),
+                    path_key,
+                    &format!("Element path {path} does not start with {root}"),
+                ));
+            }
+            match str_field(element, "id") {
+                None => errors.push(invariant(
+                    &at,
+                    "sdf-14",
+                    &format!("Element {path} has no id"),
+                )),
+                Some(id) if !ids.insert(id) => errors.push(invariant(
+                    &format!("{at}.id"),
+                    ids_key,
+                    &format!("Element id {id} is not unique"),
+                )),
+                Some(_) => {}
+            }
+            if is_snapshot {
+                if !self.is_constraint && !paths.insert(path) {
+                    errors.push(invariant(
+                        &format!("{at}.path"),
+                        "sdf-1",
+                        &format!("Element path {path} is not unique"),
+                    ));
+                }
+                let missing: Vec<&str> = ["definition", "min", "max"]
+                    .into_iter()
+                    .filter(|key| element.get(*key).is_none())
+                    .collect();
+                if !missing.is_empty() {
+                    errors.push(invariant(
+                        &at,
+                        "sdf-3",
+                        &format!("Snapshot element {path} has no {}", missing.join(", ")),
+                    ));
+                }
+            }
+            if i == 0 && is_root && !self.is_logical && element.get("type").is_some() {
+                errors.push(invariant(
+                    &format!("{at}.type"),
+                    if is_snapshot { "sdf-15" } else { "sdf-15a" },
+                    "The root element of a non-logical model has no type",
+                ));
+            }
+            if is_root && slice_name.is_some() {
+                errors.push(invariant(
+                    &format!("{at}.sliceName"),
+                    "sdf-23",
+                    "The root element cannot have a slice name",
+                ));
+            }
+            if self.is_constraint
+                && let Some(key) = element
+                    .as_object()
+                    .and_then(|obj| obj.keys().find(|k| k.starts_with("defaultValue")))
+            {
+                errors.push(invariant(
+                    &format!("{at}.{key}"),
+                    "sdf-21",
+                    "Default values can only be specified on specializations",
+                ));
+            }
+
+            // Ordering, for the elements of this structure (sdf-8 reports
+            // the others)
+            if !in_root {
+                continue;
+            }
+            while let Some(top) = open.last() {
+                if is_ancestor_or_self(top, path) {
+                    break;
+                }
+                closed.insert(*top);
+                open.pop();
+            }
+            if slice_name.is_some() {
+                closed.retain(|c| !is_ancestor_or_self(path, c) || *c == path);
+            }
+            if closed.contains(path) || ancestors(path).any(|a| closed.contains(a)) {
+                errors.push(definition_error(
+                    format!("{at}.path"),
+                    format!("Element {path} is out of order: its subtree was already listed"),
+                ));
+            } else if is_snapshot
+                && i > 0
+                && let Some((parent, _)) = path.rsplit_once('.')
+                && open.last().is_none_or(|top| *top != parent && *top != path)
+            {
+                errors.push(definition_error(
+                    format!("{at}.path"),
+                    format!("Element {path} is not preceded by its parent {parent}"),
+                ));
+            }
+            open.push(path);
+
+            // Slicing
+            if let Some(slicing) = element.get("slicing") {
+                sliced.insert(path);
+                check_slicing(slicing, &format!("{at}.slicing"), errors);
+            }
+            if let Some(slice_name) = slice_name {
+                if !slices.entry(path).or_default().insert(slice_name) {
+                    errors.push(definition_error(
+                        format!("{at}.sliceName"),
+                        format!("Slice name {slice_name} is used twice on {path}"),
+                    ));
+                }
+                if is_snapshot && !sliced.contains(path) {
+                    errors.push(definition_error(
+                        format!("{at}.sliceName"),
+                        format!("Slice {slice_name} of {path} has no preceding slicing definition"),
+                    ));
+                }
+            }
+        }
+    }
+}
+
+fn check_slicing(slicing: &JsonValue, at: &str, errors: &mut Vec<ValidationError>) {
+    match str_field(slicing, "rules") {
+        Some(rules) if SLICING_RULES.contains(&rules) => {}
+        Some(rules) => errors.push(definition_error(
+            format!("{at}.rules"),
+            format!("Unknown slicing rules {rules}"),
+        )),
+        None => errors.push(definition_error(
+            at.to_string(),
+            "Slicing has no rules".to_string(),
+        )),
+    }
+    for (i, discriminator) in array(slicing, "discriminator").iter().enumerate() {
+        let at = format!("{at}.discriminator[{i}]");
+        match str_field(discriminator, "type") {
+            Some(kind) if DISCRIMINATOR_TYPES.contains(&kind) => {}
+            kind => errors.push(definition_error(
+                format!("{at}.type"),
+                format!(
+                    "Discriminator type must be one of {}, got {}",
+                    DISCRIMINATOR_TYPES.join(", "),
+                    kind.unwrap_or("nothing")
+                ),
+            )),
+        }
+        if str_field(discriminator, "path").is_none_or(str::is_empty) {
+            errors.push(definition_error(
+                format!("{at}.path"),
+                "Discriminator has no path".to_string(),
+            ));
+        }
+    }
+}
+
+fn is_ancestor_or_self(ancestor: &str, path: &str) -> bool {
+    path.strip_prefix(ancestor)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+}
+
+/// Proper ancestors of a dotted path, nearest last: `A`, `A.b` for `A.b.c`.
+fn ancestors(path: &str) -> impl Iterator<Item = &str> {
+    path.match_indices('.').map(move |(i, _)| &path[..i])
+}
+
+fn array<'a>(value: &'a JsonValue, key: &str) -> &'a [JsonValue] {
+    value
+        .get(key)
+        .and_then(JsonValue::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+fn str_field<'a>(value: &'a JsonValue, key: &str) -> Option<&'a str> {
+    value.get(key).and_then(JsonValue::as_str)
+}
+
+fn invariant(path: &str, key: &str, message: &str) -> ValidationError {
+    ValidationError {
+        error_type: FhirSchemaErrorCode::ConstraintViolation.into(),
+        path: ErrorPath::new(path),
+        message: Some(format!("Constraint '{key}' failed: {message}").into()),
+        value: None,
+        expected: None,
+        got: None,
+        schema_path: None,
+        constraint_key: Some(key.to_string()),
+        constraint_expression: None,
+        constraint_severity: Some("error".to_string()),
+        fix: None,
+        schema_url: None,
+        schema_version: None,
+    }
+}
+
+fn definition_error(path: String, message: String) -> ValidationError {
+    ValidationError {
+        error_type: FhirSchemaErrorCode::DefinitionViolation.into(),
+        path: ErrorPath::new(&path),
+        message: Some(message.into()),
+        value: None,
+        expected: None,
+        got: None,
+        schema_path: None,
+        constraint_key: None,
+        constraint_expression: None,
+        constraint_severity: Some("error".to_string()),
+        fix: None,
+        schema_url: None,
+        schema_version: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn element(id: &str) -> JsonValue {
+        let path: String = id
+            .split('.')
+            .map(|part| part.split(':').next().unwrap())
+            .collect::<Vec<_>>()
+            .join(".");
+        let mut element = json!({"id": id, "path": path});
+        if let Some((_, slice)) = id.rsplit_once(':')
+            && !slice.contains('.')
+        {
+            element["sliceName"] = json!(slice);
+        }
+        element
+    }
+
+    fn profile(elements: Vec<JsonValue>) -> JsonValue {
+        json!({
+            "resourceType": "StructureDefinition",
+            "url": "http://example.org/StructureDefinition/p", "name": "P",
+            "status": "active", "kind": "resource", "abstract": false, "type": "Patient",
+            "baseDefinition": "http://hl7.org/fhir/StructureDefinition/Patient",
+            "derivation": "constraint",
+            "differential": {"element": elements}
+        })
+    }
+
+    fn keys(errors: &[ValidationError]) -> Vec<String> {
+        errors
+            .iter()
+            .map(|e| match &e.constraint_key {
+                Some(key) => format!("{key} {}", e.path),
+                None => format!("{} {}", e.error_type, e.path),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_well_formed_profile() {
+        let mut identifier = element("Patient.identifier");
+        identifier["slicing"] = json!({
+            "discriminator": [{"type": "pattern", "path": "system"}], "rules": "open"
+        });
+        let sd = profile(vec![
+            element("Patient"),
+            identifier,
+            element("Patient.identifier.system"),
+            element("Patient.identifier:mrn"),
+            element("Patient.identifier:mrn.system"),
+            element("Patient.name"),
+        ]);
+        assert!(check_structure_definition(&sd).is_empty());
+    }
+
+    #[test]
+    fn test_metaprofile_violations() {
+        let mut root = element("Patient");
+        root["type"] = json!([{"code": "Patient"}]);
+        let mut identifier = element("Patient.identifier");
+        identifier["slicing"] = json!({"discriminator": [{"type": "equals", "path": ""}]});
+        let mut gender = element("Patient.gender");
+        gender["defaultValueCode"] = json!("unknown");
+        let mut sd = profile(vec![
+            root,
+            identifier,
+            element("Patient.identifier:mrn"),
+            element("Patient.identifier:mrn"),
+            element("Patient.name"),
+            element("Patient.identifier.system"),
+            gender,
+            element("Observation.code"),
+            json!({"path": "Patient.birthDate"}),
+        ]);
+        sd.as_object_mut().unwrap().remove("baseDefinition");
+
+        assert_eq!(
+            keys(&check_structure_definition(&sd)),
+            [
+                "sdf-4 StructureDefinition",
+                "sdf-15a StructureDefinition.differential.element[0].type",
+                "FS1019 StructureDefinition.differential.element[1].slicing",
+                "FS1019 StructureDefinition.differential.element[1].slicing.discriminator[0].type",
+                "FS1019 StructureDefinition.differential.element[1].slicing.discriminator[0].path",
+                "sdf-17 StructureDefinition.differential.element[3].id",
+                "FS1019 StructureDefinition.differential.element[3].sliceName",
+                "FS1019 StructureDefinition.differential.element[5].path",
+                "sdf-21 StructureDefinition.differential.element[6].defaultValueCode",
+                "sdf-8a StructureDefinition.differential.element[7].path",
+                "sdf-14 StructureDefinition.differential.element[8]",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_snapshot_ordering_and_slicing() {
+        let full = |id: &str| {
+            let mut element = element(id);
+            element["definition"] = json!("d");
+            element["min"] = json!(0);
+            element["max"] = json!("*");
+            element
+        };
+        let mut sd = profile(vec![]);
+        sd["snapshot"] = json!({"element": [
+            full("Patient"),
+            full("Patient.contact.name"),
+            full("Patient.identifier:mrn"),
+            element("Patient.name"),
+        ]});
+
+        assert_eq!(
+            keys(&check_structure_definition(&sd)),
+            [
+                "FS1019 StructureDefinition.snapshot.element[1].path",
+                "FS1019 StructureDefinition.snapshot.element[2].sliceName",
+                "sdf-3 StructureDefinition.snapshot.element[3]",
+            ]
+        );
+    }
+}
@@ -0,0 +1,450 @@
+//! Slice membership by discriminator.
+//!
+//! A slice's `match` pattern describes the whole item, but slicing tells
+//! items apart only by the values at the discriminator paths. Comparing just
+//! those values keeps slices apart when their patterns overlap elsewhere,
+//! e.g. two `coding` slices discriminated by `coding.system` whose patterns
+//! share a `code`.
+//!
+//! Paths made of element names, `$this`, `ofType()` and `extension(url)` are
+//! walked natively. Other paths (`resolve()`, `where(...)`, ...) are checked
+//! with the FHIRPath evaluator in the constraint phase; without an evaluator
+//! those discriminators fall back to matching the whole pattern.
+//!
+//! Extension slicing discriminates on `url`. Slices compiled with their
+//! extension url classify items by it before any pattern is compared.
+
+use std::collections::HashMap;
+
+use serde_json::Value as JsonValue;
+
+use super::FhirValidator;
+use super::compiled::{CompiledSlice, CompiledSlicing, DiscriminatorType};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    Field(String),
+    /// `extension(url)`: the extensions with that url
+    Extension(String),
+    /// A function the native walk does not evaluate
+    Opaque,
+}
+
+/// A discriminator path split into steps.
+#[derive(Debug, Clone, PartialEq)]
+struct DiscriminatorPath {
+    steps: Vec<Step>,
+}
+
+impl DiscriminatorPath {
+    fn parse(path: &str) -> Self {
+        let mut steps = Vec::new();
+        for segment in split_top_level(path) {
+            let segment = segment.trim();
+            if segment.is_empty() || segment == "$this" {
+                continue;
+            }
+            let step = if let Some(type_name) = call_argument(segment, "ofType") {
+                // `value.ofType(Quantity)` is the `valueQuantity` element.
+                match steps.pop() {
+                    Some(Step::Field(stem)) => {
+                        let mut chars = type_name.chars();
+                        let first = chars.next().map(|c| c.to_ascii_uppercase().to_string());
+                        Step::Field(format!(
+                            "{stem}{}{}",
+                            first.unwrap_or_default(),
+                            chars.as_str()
+                        ))
+                    }
+                    _ => Step::Opaque,
+                }
+            } else if let Some(url) = call_argument(segment, "extension") {
+                Step::Extension(url.trim_matches(['\'', '"']).to_string())
+            } else if segment
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_')
+            {
+                Step::Field(segment.to_string())
+            } else {
+                Step::Opaque
+            };
+            steps.push(step);
+        }
+        Self { steps }
+    }
+
+    fn is_native(&self) -> bool {
+        !self.steps.contains(&Step::Opaque)
+    }
+
+    /// The values at this path in `value`, flattening arrays. Opaque steps
+    /// are skipped, which is exact for native paths and an approximation
+    /// used to read the expected values out of a slice pattern.
+    fn select<'v>(&self, value: &'v JsonValue) -> Vec<&'v JsonValue> {
+        let mut current = vec![value];
+        for step in &self.steps {
+            let mut next = Vec::new();
+            for value in current {
+                let children = match step {
+                    Step::Field(name) => value.get(name),
+                    Step::Extension(_) => value.get("extension"),
+                    Step::Opaque => {
+                        next.push(value);
+                        continue;
+                    }
+                };
+                let children: Vec<&JsonValue> = match children {
+                    Some(JsonValue::Array(items)) => items.iter().collect(),
+                    Some(child) => vec![child],
+                    None => Vec::new(),
+                };
+                next.extend(children.into_iter().filter(|child| match step {
+                    Step::Extension(url) => {
+                        child.get("url").and_then(JsonValue::as_str) == Some(url.as_str())
+                    }
+                    _ => true,
+                }));
+            }
+            current = next;
+        }
+        current
+    }
+}
+
+/// Split a FHIRPath path on the dots outside parentheses and string literals.
+fn split_top_level(path: &str) -> Vec<&str> {
+    let mut segments = Vec::new();
+    let (mut depth, mut quote, mut start) = (0usize, None, 0);
+    for (i, c) in path.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"' | '`') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') => depth = depth.saturating_sub(1),
+            (None, '.') if depth == 0 => {
+                segments.push(&path[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    segments.push(&path[start..]);
+    segments
+}
+
+/// The argument of `segment` when it is a call of `function`.
+fn call_argument<'s>(segment: &'s str, function: &str) -> Option<&'s str> {
+    segment
+        .strip_prefix(function)?
+        .strip_prefix('(')?
+        .strip_suffix(')')
+        .map(str::trim)
+}
+
+/// The discriminators of a slicing, parsed once per array.
+pub(crate) struct Discriminators<'s> {
+    entries: Vec<(DiscriminatorType, &'s str, DiscriminatorPath)>,
+    /// A value or pattern discriminator on `url` (extension slicing)
+    by_url: bool,
+}
+
+impl<'s> Discriminators<'s> {
+    /// Collect the discriminators that can be checked against values: type
+    /// and profile discriminators need the slice's element definitions,
+    /// which compiled slices do not carry.
+    pub(crate) fn new(slicing: &'s CompiledSlicing) -> Self {
+        let entries = slicing
+            .discriminators
+            .iter()
+            .filter(|d| {
+                matches!(
+                    d.discriminator_type,
+                    DiscriminatorType::Value
+                        | DiscriminatorType::Pattern
+                        | DiscriminatorType::Exists
+                )
+            })
+            .map(|d| {
+                (
+                    d.discriminator_type,
+                    d.path.as_str(),
+                    DiscriminatorPath::parse(&d.path),
+                )
+            })
+            .collect::<Vec<_>>();
+        let by_url = entries
+            .iter()
+            .any(|(kind, source, _)| *kind != DiscriminatorType::Exists && source.trim() == "url");
+        Self { entries, by_url }
+    }
+
+    /// Whether some discriminator needs the FHIRPath evaluator.
+    pub(crate) fn needs_fhirpath(&self) -> bool {
+        self.entries.iter().any(|(_, _, path)| !path.is_native())
+    }
+
+    /// The FHIRPath expressions that check an item against `slice` on the
+    /// discriminators that are not walked natively.
+    pub(crate) fn fhirpath_checks(&self, slice: &CompiledSlice) -> Vec<String> {
+        let Some(pattern) = &slice.match_value else {
+            return Vec::new();
+        };
+        self.entries
+            .iter()
+            .filter(|(_, _, path)| !path.is_native())
+            .filter_map(|(kind, source, path)| {
+                check_expression(*kind, source, &path.select(pattern))
+            })
+            .collect()
+    }
+
+    /// Whether `item` belongs to `slice`, comparing only the discriminated
+    /// values. `evaluated` holds the results of [`Self::fhirpath_checks`] for
+    /// the item. Without discriminators that constrain the slice, the whole
+    /// pattern is matched.
+    pub(crate) fn matches(
+        &self,
+        item: &JsonValue,
+        slice: &CompiledSlice,
+        evaluated: &HashMap<String, bool>,
+    ) -> bool {
+        let slice_url = slice.url.as_deref().filter(|_| self.by_url);
+        if let Some(url) = slice_url
+            && item.get("url").and_then(JsonValue::as_str) != Some(url)
+        {
+            return false;
+        }
+        let Some(pattern) = &slice.match_value else {
+            // No pattern = unconditional match (catch-all)
+            return true;
+        };
+
+        let mut discriminated = slice_url.is_some();
+        for (kind, source, path) in &self.entries {
+            if slice_url.is_some() && source.trim() == "url" {
+                continue;
+            }
+            let expected = path.select(pattern);
+            if expected.is_empty() {
+                continue;
+            }
+            let matched = if path.is_native() {
+                let found = path.select(item);
+                match kind {
+                    DiscriminatorType::Exists => !found.is_empty(),
+                    _ => expected.iter().all(|expected| {
+                        found
+                            .iter()
+                            .any(|value| FhirValidator::deep_partial_match(value, expected))
+                    }),
+                }
+            } else {
+                let Some(matched) = check_expression(*kind, source, &expected)
+                    .and_then(|expression| evaluated.get(&expression).copied())
+                else {
+                    continue;
+                };
+                matched
+            };
+            if !matched {
+                return false;
+            }
+            discriminated = true;
+        }
+        discriminated || FhirValidator::deep_partial_match(item, pattern)
+    }
+}
+
+/// A FHIRPath expression, evaluated on an item, that is true when the values
+/// at `path` contain every `expected` value.
+fn check_expression(
+    kind: DiscriminatorType,
+    path: &str,
+    expected: &[&JsonValue],
+) -> Option<String> {
+    if expected.is_empty() {
+        return None;
+    }
+    if kind == DiscriminatorType::Exists {
+        return Some(format!("({path}).exists()"));
+    }
+    let checks = expected
+        .iter()
+        .map(|value| Some(format!("({path}).where({}).exists()", condition(value)?)))
+        .collect::<Option<Vec<_>>>()?;
+    Some(checks.join(" and "))
+}
+
+/// A FHIRPath condition on `$this` that mirrors
+/// [`FhirValidator::deep_partial_match`] against `pattern`.
+fn condition(pattern: &JsonValue) -> Option<String> {
+    match pattern {
+        JsonValue::Null => Some("true".to_string()),
+        JsonValue::Bool(b) => Some(format!("$this = {b}")),
+        JsonValue::Number(n) => Some(format!("$this = {n}")),
+        JsonValue::String(s) => Some(format!("$this = {}", string_literal(s))),
+        JsonValue::Array(_) => None,
+        JsonValue::Object(map) => {
+            let mut terms = Vec::new();
+            for (key, value) in map {
+                let key = identifier(key);
+                let values: Vec<&JsonValue> = match value {
+                    JsonValue::Array(items) if !items.is_empty() => items.iter().collect(),
+                    JsonValue::Array(_) => Vec::new(),
+                    JsonValue::Object(inner) if inner.is_empty() => Vec::new(),
+                    value => vec![value],
+                };
+                if values.is_empty() {
+                    terms.push(format!("{key}.exists()"));
+                }
+                for value in values {
+                    terms.push(format!("{key}.where({}).exists()", condition(value)?));
+                }
+            }
+            Some(if terms.is_empty() {
+                "true".to_string()
+            } else {
+                terms.join(" and ")
+            })
+        }
+    }
+}
+
+fn string_literal(s: &str) -> String {
+    format!("'{}'", s.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+fn identifier(name: &str) -> String {
+    let simple = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if simple {
+        name.to_string()
+    } else {
+        format!("`{}`", name.replace('`', "\\`"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validation::compiled::{CompiledDiscriminator, SlicingRules};
+    use serde_json::json;
+
+    fn slicing(
+        kind: DiscriminatorType,
+        path: &str,
+        slices: &[(&str, JsonValue)],
+    ) -> CompiledSlicing {
+        CompiledSlicing {
+            rules: SlicingRules::Open,
+            ordered: false,
+            discriminators: vec![CompiledDiscriminator {
+                discriminator_type: kind,
+                path: path.to_string(),
+            }],
+            slices: slices
+                .iter()
+                .map(|(name, pattern)| {
+                    let slice = CompiledSlice {
+                        name: name.to_string(),
+                        match_value: Some(pattern.clone()),
+                        url: None,
+                        min: None,
+                        max: None,
+                        schema: None,
+                    };
+                    (name.to_string(), slice)
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_parse_path() {
+        let path = DiscriminatorPath::parse("$this.value.ofType(Quantity).code");
+        assert_eq!(
+            path.steps,
+            vec![
+                Step::Field("valueQuantity".to_string()),
+                Step::Field("code".to_string())
+            ]
+        );
+        let path = DiscriminatorPath::parse("extension('http://x.org/a.b').value");
+        assert_eq!(
+            path.steps[0],
+            Step::Extension("http://x.org/a.b".to_string())
+        );
+        assert!(path.is_native());
+        assert!(!DiscriminatorPath::parse("reference.resolve()").is_native());
+    }
+
+    #[test]
+    fn test_matches_discriminated_values_only() {
+        // Both patterns carry the same code; only the system discriminates.
+        let slicing = slicing(
+            DiscriminatorType::Pattern,
+            "coding.system",
+            &[
+                (
+                    "loinc",
+                    json!({"coding": [{"system": "http://loinc.org", "code": "x"}]}),
+                ),
+                (
+                    "local",
+                    json!({"coding": [{"system": "urn:local", "code": "x"}]}),
+                ),
+            ],
+        );
+        let discriminators = Discriminators::new(&slicing);
+        let item = json!({"coding": [{"system": "http://loinc.org", "code": "other"}]});
+        let evaluated = HashMap::new();
+        assert!(discriminators.matches(&item, &slicing.slices["loinc"], &evaluated));
+        assert!(!discriminators.matches(&item, &slicing.slices["local"], &evaluated));
+    }
+
+    #[test]
+    fn test_fhirpath_checks() {
+        let slicing = slicing(
+            DiscriminatorType::Value,
+            "coding.where(code.exists()).system",
+            &[("loinc", json!({"coding": {"system": "http://loinc.org"}}))],
+        );
+        let discriminators = Discriminators::new(&slicing);
+        assert!(discriminators.needs_fhirpath());
+        let slice = &slicing.slices["loinc"];
+        let checks = discriminators.fhirpath_checks(slice);
+        assert_eq!(
+            checks,
+            vec!["(coding.where(code.exists()).system).where($this = 'http://loinc.org').exists()"]
+        );
+
+        let item = json!({"coding": [{"system": "urn:other"}]});
+        let evaluated = HashMap::from([(checks[0].clone(), false)]);
+        assert!(!discriminators.matches(&item, slice, &evaluated));
+        // Without a result the whole pattern decides.
+        assert!(!discriminators.matches(&item, slice, &HashMap::new()));
+    }
+
+    #[test]
+    fn test_matches_extension_url() {
+        const RACE: &str = "http://hl7.org/fhir/us/core/StructureDefinition/us-core-race";
+        // Slices converted before their match carried the url.
+        let mut slicing = slicing(
+            DiscriminatorType::Value,
+            "url",
+            &[("race", json!({})), ("birthsex", json!({}))],
+        );
+        slicing.slices.get_mut("race").unwrap().url = Some(RACE.to_string());
+        slicing.slices.get_mut("birthsex").unwrap().url =
+            Some("http://hl7.org/fhir/us/core/StructureDefinition/us-core-birthsex".to_string());
+        let discriminators = Discriminators::new(&slicing);
+        let evaluated = HashMap::new();
+
+        let item = json!({"url": RACE, "extension": [{"url": "text", "valueString": "x"}]});
+        assert!(discriminators.matches(&item, &slicing.slices["race"], &evaluated));
+        assert!(!discriminators.matches(&item, &slicing.slices["birthsex"], &evaluated));
+        let other = json!({"url": "http://example.org/other", "valueString": "x"});
+        assert!(!discriminators.matches(&other, &slicing.slices["race"], &evaluated));
+    }
+}
@@ -0,0 +1,305 @@
+//! Explain mode: how the schemata were resolved for each path.
+//!
+//! [`FhirValidator::explain`] walks a resource the way validation does and
+//! records the decisions that shape the reported errors:
+//!
+//! - which schemas joined the schemata, what brought them in (the requested
+//!   schema names, an extension's `url`) and whether they came precompiled,
+//!   from the cache or were compiled for the call
+//! - for each element, which schema declares it and as what type, including
+//!   resolution through a choice stem or a `contentReference`
+//! - elements a schema does not declare, the source of `FS1001`
+//!
+//! Nested resources (`contained`, `Bundle.entry.resource`) are recorded as
+//! not followed: validation checks them structurally only.
+
+use std::collections::HashMap;
+
+use serde_json::Value as JsonValue;
+
+use super::FhirValidator;
+use super::compiled::{CompiledElement, CompiledTypeInfo};
+use crate::types::{SchemaTrace, TraceDecision};
+
+/// How `element` is validated, for the trace.
+fn element_type(element: &CompiledElement) -> String {
+    match &element.type_info {
+        CompiledTypeInfo::Primitive(primitive) => primitive.as_str().to_string(),
+        CompiledTypeInfo::Complex => "complex".to_string(),
+        CompiledTypeInfo::BackboneElement => "BackboneElement".to_string(),
+        CompiledTypeInfo::Reference => "Reference".to_string(),
+        CompiledTypeInfo::Resource => "Resource".to_string(),
+        CompiledTypeInfo::Extension => "Extension".to_string(),
+        CompiledTypeInfo::Unspecified => "unspecified".to_string(),
+    }
+}
+
+fn child_path(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{path}.{key}")
+    }
+}
+
+impl FhirValidator {
+    /// Record how `resource` resolves against `schema_names` without
+    /// validating it. Compiles the schemas involved on the way, like
+    /// validation would.
+    pub async fn explain(&self, resource: &JsonValue, schema_names: &[String]) -> SchemaTrace {
+        let mut trace = SchemaTrace::default();
+        let root_path = resource
+            .get("resourceType")
+            .and_then(JsonValue::as_str)
+            .unwrap_or_default();
+
+        for schema_name in schema_names {
+            match self.compiler.compile_traced(schema_name).await {
+                Ok((compiled, source)) => {
+                    trace.push(
+                        root_path,
+                        TraceDecision::SchemaAdded {
+                            schema: schema_name.clone(),
+                            from: "requested".to_string(),
+                            source,
+                        },
+                    );
+                    self.explain_elements(
+                        resource,
+                        &compiled.elements,
+                        &compiled.elements,
+                        schema_name,
+                        root_path,
+                        &mut trace,
+                    );
+                }
+                Err(e) => trace.push(
+                    root_path,
+                    TraceDecision::SchemaMissing {
+                        schema: schema_name.clone(),
+                        from: "requested".to_string(),
+                        message: e.to_string(),
+                    },
+                ),
+            }
+        }
+
+        self.explain_extensions(resource, root_path, &mut trace)
+            .await;
+        trace
+    }
+
+    /// Record how the properties of `value` resolve against `children` of
+    /// `schema`, following the rules of the structural phase.
+    fn explain_elements(
+        &self,
+        value: &JsonValue,
+        children: &HashMap<String, CompiledElement>,
+        root: &HashMap<String, CompiledElement>,
+        schema: &str,
+        path: &str,
+        trace: &mut SchemaTrace,
+    ) {
+        let JsonValue::Object(obj) = value else {
+            return;
+        };
+
+        for (key, val) in obj {
+            if key == "resourceType" || key == "fhir_comments" || key.starts_with('_') {
+                continue;
+            }
+            let element_path = child_path(path, &self.choice_display_key(key, children));
+
+            // Choice variants resolve by name or, without a definition of
+            // their own, through the stem.
+            let stem = children
+                .values()
+                .find(|el| el.choices.as_ref().is_some_and(|c| c.contains(key)));
+            let mut via = stem.map(|stem| format!("choice {}[x]", stem.name));
+            let Some(element) = children.get(key).or(stem) else {
+                // Allowed on every element without being declared.
+                if key != "extension" && key != "id" {
+                    trace.push(
+                        element_path,
+                        TraceDecision::Undeclared {
+                            schema: schema.to_string(),
+                        },
+                    );
+                }
+                continue;
+            };
+
+            let mut element_children = &element.children;
+            if element.children.is_empty()
+                && let Some(reference) = element.element_reference.as_deref()
+                && let Some(target) = Self::resolve_element_reference(root, Some(reference))
+            {
+                element_children = &target.children;
+                via = Some(format!("contentReference #{}", reference.join(".")));
+            }
+            trace.push(
+                element_path.as_str(),
+                TraceDecision::Declared {
+                    schema: schema.to_string(),
+                    element_type: element_type(element),
+                    via,
+                },
+            );
+
+            let items: Vec<(String, &JsonValue)> = match val {
+                JsonValue::Array(items) => items
+                    .iter()
+                    .enumerate()
+                    .map(|(i, item)| (format!("{element_path}[{i}]"), item))
+                    .collect(),
+                item => vec![(element_path, item)],
+            };
+            for (item_path, item) in items {
+                match &element.type_info {
+                    CompiledTypeInfo::Complex | CompiledTypeInfo::BackboneElement => self
+                        .explain_elements(item, element_children, root, schema, &item_path, trace),
+                    CompiledTypeInfo::Resource => {
+                        if let Some(resource_type) =
+                            item.get("resourceType").and_then(JsonValue::as_str)
+                        {
+                            trace.push(
+                                item_path,
+                                TraceDecision::NotFollowed {
+                                    schema: resource_type.to_string(),
+                                    reason: "nested resources are checked structurally only"
+                                        .to_string(),
+                                },
+                            );
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// Record the extension profiles the extension phase loads by `url`.
+    #[async_recursion::async_recursion]
+    async fn explain_extensions(&self, value: &JsonValue, path: &str, trace: &mut SchemaTrace) {
+        match value {
+            JsonValue::Object(obj) => {
+                if let Some(JsonValue::Array(exts)) = obj.get("extension") {
+                    for (i, ext) in exts.iter().enumerate() {
+                        let Some(url) = ext.get("url").and_then(JsonValue::as_str) else {
+                            continue;
+                        };
+                        let ext_path = format!("{path}.extension[{i}]");
+                        let decision = match self.compiler.compile_traced(url).await {
+                            Ok((_, source)) => TraceDecision::SchemaAdded {
+                                schema: url.to_string(),
+                                from: "extension url".to_string(),
+                                source,
+                            },
+                            Err(e) => TraceDecision::SchemaMissing {
+                                schema: url.to_string(),
+                                from: "extension url".to_string(),
+                                message: e.to_string(),
+                            },
+                        };
+                        trace.push(ext_path, decision);
+                    }
+                }
+                for (key, child) in obj {
+                    self.explain_extensions(child, &child_path(path, key), trace)
+                        .await;
+                }
+            }
+            JsonValue::Array(items) => {
+                for (i, item) in items.iter().enumerate() {
+                    self.explain_extensions(item, &format!("{path}[{i}]"), trace)
+                        .await;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embedded::{FhirVersion, get_schemas};
+    use crate::types::SchemaSource;
+    use crate::validation::ValidationOptions;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_explain_trace() {
+        let schemas = get_schemas(FhirVersion::R4).unwrap().clone();
+        let validator = FhirValidator::from_schemas(schemas, None);
+        let patient = json!({
+            "resourceType": "Patient",
+            "name": [{"family": "Doe"}],
+            "deceasedBoolean": false,
+            "nickname": "JD",
+            "contained": [{"resourceType": "Organization", "name": "Acme"}],
+            "extension": [{"url": "http://example.org/unknown", "valueString": "x"}]
+        });
+        let options = ValidationOptions {
+            explain: true,
+            ..Default::default()
+        };
+
+        let result = validator
+            .validate_with_options(&patient, vec!["Patient".to_string()], &options)
+            .await;
+        assert!(!result.valid);
+        let trace = result.explain.unwrap();
+
+        assert_eq!(
+            trace.at("Patient").next(),
+            Some(&TraceDecision::SchemaAdded {
+                schema: "Patient".to_string(),
+                from: "requested".to_string(),
+                source: SchemaSource::Compiled,
+            })
+        );
+        assert_eq!(
+            trace.at("Patient.nickname").next(),
+            Some(&TraceDecision::Undeclared {
+                schema: "Patient".to_string()
+            })
+        );
+        assert!(matches!(
+            trace.at("Patient.name[0].family").next(),
+            Some(TraceDecision::Declared { element_type, .. }) if element_type == "string"
+        ));
+        assert!(matches!(
+            trace.at("Patient.deceased.ofType(boolean)").next(),
+            Some(TraceDecision::Declared { via: Some(via), .. }) if via == "choice deceased[x]"
+        ));
+        assert!(matches!(
+            trace.at("Patient.contained[0]").next(),
+            Some(TraceDecision::NotFollowed { schema, .. }) if schema == "Organization"
+        ));
+        assert!(matches!(
+            trace.at("Patient.extension[0]").next(),
+            Some(TraceDecision::SchemaMissing { .. })
+        ));
+
+        let tree = trace.to_tree();
+        assert!(
+            tree.starts_with("Patient\n  - + Patient (requested, compiled)\n"),
+            "{tree}"
+        );
+        assert!(
+            tree.contains("\n  nickname\n    - Patient: not declared\n"),
+            "{tree}"
+        );
+
+        // The second run finds the schema in the cache.
+        let trace = validator.explain(&patient, &["Patient".to_string()]).await;
+        assert!(matches!(
+            trace.at("Patient").next(),
+            Some(TraceDecision::SchemaAdded {
+                source: SchemaSource::Cached,
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,286 @@
+//! Suggested fixes for mechanical validation errors.
+//!
+//! Structural errors whose correction needs no judgement carry JSON Patch
+//! operations in [`ValidationError::fix`]:
+//!
+//! - `FS1003`: a single value where an array is expected is wrapped
+//! - `FS1004`: a one-item array where a single value is expected is unwrapped
+//! - `FS1006`: a primitive of the wrong JSON type is converted when its text
+//!   converts losslessly, e.g. `"true"` for a boolean or `42` for a string
+//! - a resource without `resourceType` gets the type of the schema it was
+//!   validated against
+//!
+//! [`apply_fixes`] applies the suggestions of a validation result, e.g. for an
+//! auto-fix mode in an editor or the CLI.
+
+use serde_json::Value as JsonValue;
+
+use super::FhirSchemaErrorCode;
+use super::compiled::PrimitiveType;
+use crate::error::{FhirSchemaError, Result};
+use crate::types::{PatchOperation, ValidationError};
+
+/// Attach fix suggestions to `errors` found in `resource`, whose error paths
+/// start at `root_path`. Errors that already carry a fix are left alone.
+pub(crate) fn suggest_fixes(resource: &JsonValue, root_path: &str, errors: &mut [ValidationError]) {
+    for error in errors.iter_mut().filter(|e| e.fix.is_none()) {
+        error.fix = suggest_fix(resource, root_path, error);
+    }
+}
+
+fn suggest_fix(
+    resource: &JsonValue,
+    root_path: &str,
+    error: &ValidationError,
+) -> Option<Vec<PatchOperation>> {
+    let code = error.error_type.as_ref();
+    let fixable = [
+        FhirSchemaErrorCode::ExpectedArray,
+        FhirSchemaErrorCode::UnexpectedArray,
+        FhirSchemaErrorCode::WrongType,
+    ]
+    .iter()
+    .any(|c| c.as_str() == code);
+    if !fixable {
+        return None;
+    }
+
+    let pointer = json_pointer(error.path.as_str(), root_path)?;
+    let value = resource.pointer(&pointer)?;
+    let replacement = if code == FhirSchemaErrorCode::ExpectedArray.as_str() {
+        JsonValue::Array(vec![value.clone()])
+    } else if code == FhirSchemaErrorCode::UnexpectedArray.as_str() {
+        match value.as_array()?.as_slice() {
+            [item] => item.clone(),
+            _ => return None,
+        }
+    } else {
+        let expected = PrimitiveType::parse(error.expected.as_ref()?.as_str()?)?;
+        convert_primitive(value, expected)?
+    };
+
+    Some(vec![PatchOperation::Replace {
+        path: pointer,
+        value: replacement,
+    }])
+}
+
+/// Convert a primitive to the JSON type `expected` uses, when that loses
+/// nothing: numeric and boolean text to numbers and booleans, numbers and
+/// booleans to strings.
+fn convert_primitive(value: &JsonValue, expected: PrimitiveType) -> Option<JsonValue> {
+    use PrimitiveType::*;
+
+    match (expected, value) {
+        (Boolean, JsonValue::String(s)) => match s.trim() {
+            "true" => Some(JsonValue::Bool(true)),
+            "false" => Some(JsonValue::Bool(false)),
+            _ => None,
+        },
+        (Integer | Integer64 | UnsignedInt | PositiveInt, JsonValue::String(s)) => {
+            s.trim().parse::<i64>().ok().map(JsonValue::from)
+        }
+        (Integer | Integer64 | UnsignedInt | PositiveInt, JsonValue::Number(n)) => n
+            .as_f64()
+            .filter(|f| f.fract() == 0.0 && f.abs() < i64::MAX as f64)
+            .map(|f| JsonValue::from(f as i64)),
+        (Decimal, JsonValue::String(s)) => s
+            .trim()
+            .parse::<serde_json::Number>()
+            .ok()
+            .map(JsonValue::Number),
+        (Boolean | Integer | Integer64 | UnsignedInt | PositiveInt | Decimal, _) => None,
+        (_, JsonValue::Number(n)) => Some(JsonValue::String(n.to_string())),
+        (_, JsonValue::Bool(b)) => Some(JsonValue::String(b.to_string())),
+        _ => None,
+    }
+}
+
+/// Convert an error path such as `Patient.name[0].given` or
+/// `Observation.value.ofType(dateTime)` into a JSON Pointer into the
+/// resource (`/name/0/given`, `/valueDateTime`).
+///
+/// `root_path` is the resourceType the validator starts paths at, empty for
+/// a resource without one. Returns `None` for paths outside the resource.
+pub fn json_pointer(path: &str, root_path: &str) -> Option<String> {
+    let relative = if root_path.is_empty() {
+        path
+    } else if path == root_path {
+        ""
+    } else {
+        path.strip_prefix(root_path)?.strip_prefix('.')?
+    };
+
+    let mut tokens: Vec<String> = Vec::new();
+    for segment in relative.split('.').filter(|s| !s.is_empty()) {
+        if let Some(type_name) = segment
+            .strip_prefix("ofType(")
+            .and_then(|s| s.strip_suffix(')'))
+        {
+            let mut chars = type_name.chars();
+            let first = chars.next()?;
+            let stem = tokens.last_mut()?;
+            stem.push(first.to_ascii_uppercase());
+            stem.push_str(chars.as_str());
+            continue;
+        }
+
+        let (name, mut indices) = match segment.find('[') {
+            Some(at) => segment.split_at(at),
+            None => (segment, ""),
+        };
+        tokens.push(name.to_string());
+        while let Some(rest) = indices.strip_prefix('[') {
+            let (index, tail) = rest.split_once(']')?;
+            tokens.push(index.to_string());
+            indices = tail;
+        }
+    }
+
+    Some(
+        tokens
+            .iter()
+            .map(|token| format!("/{}", token.replace('~', "~0").replace('/', "~1")))
+            .collect(),
+    )
+}
+
+/// Apply JSON Patch operations to `resource`.
+///
+/// Fails when a target, or the parent of an added value, does not exist,
+/// leaving earlier operations applied.
+pub fn apply_fix(resource: &mut JsonValue, operations: &[PatchOperation]) -> Result<()> {
+    for operation in operations {
+        match operation {
+            PatchOperation::Remove { path } => {
+                let (parent, key) = path
+                    .rsplit_once('/')
+                    .ok_or_else(|| FhirSchemaError::invalid_path(path.as_str()))?;
+                let key = key.replace("~1", "/").replace("~0", "~");
+                let removed = match resource.pointer_mut(parent) {
+                    Some(JsonValue::Object(map)) => map.remove(&key).is_some(),
+                    Some(JsonValue::Array(items)) => key
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|i| *i < items.len())
+                        .map(|i| items.remove(i))
+                        .is_some(),
+                    _ => false,
+                };
+                if !removed {
+                    return Err(FhirSchemaError::invalid_path(path.as_str()));
+                }
+            }
+            PatchOperation::Replace { path, value } => {
+                let target = resource
+                    .pointer_mut(path)
+                    .ok_or_else(|| FhirSchemaError::invalid_path(path.as_str()))?;
+                *target = value.clone();
+            }
+            PatchOperation::Add { path, value } => {
+                let (parent, key) = path
+                    .rsplit_once('/')
+                    .ok_or_else(|| FhirSchemaError::invalid_path(path.as_str()))?;
+                let key = key.replace("~1", "/").replace("~0", "~");
+                match resource.pointer_mut(parent) {
+                    Some(JsonValue::Object(map)) => {
+                        map.insert(key, value.clone());
+                    }
+                    Some(JsonValue::Array(items)) => {
+                        let index = if key == "-" {
+                            items.len()
+                        } else {
+                            key.parse::<usize>()
+                                .ok()
+                                .filter(|i| *i <= items.len())
+                                .ok_or_else(|| FhirSchemaError::invalid_path(path.as_str()))?
+                        };
+                        items.insert(index, value.clone());
+                    }
+                    _ => return Err(FhirSchemaError::invalid_path(path.as_str())),
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Apply every fix suggested in `errors` to `resource`, returning how many
+/// were applied.
+///
+/// The same error reported against several schemas (a base type and its
+/// profiles) is fixed once. Suggestions that no longer apply are skipped.
+pub fn apply_fixes(resource: &mut JsonValue, errors: &[ValidationError]) -> usize {
+    let mut applied: Vec<&[PatchOperation]> = Vec::new();
+    for fix in errors.iter().filter_map(|e| e.fix.as_deref()) {
+        if applied.contains(&fix) {
+            continue;
+        }
+        if apply_fix(resource, fix).is_ok() {
+            applied.push(fix);
+        }
+    }
+    applied.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embedded::{FhirVersion, get_schemas};
+    use crate::validation::FhirValidator;
+    use serde_json::json;
+
+    #[test]
+    fn test_json_pointer() {
+        assert_eq!(
+            json_pointer("Patient.name[0].given", "Patient").as_deref(),
+            Some("/name/0/given")
+        );
+        assert_eq!(
+            json_pointer("Observation.value.ofType(dateTime)", "Observation").as_deref(),
+            Some("/valueDateTime")
+        );
+        assert_eq!(json_pointer("Patient", "Patient").as_deref(), Some(""));
+        assert_eq!(json_pointer("active", "").as_deref(), Some("/active"));
+        assert_eq!(json_pointer("Observation.status", "Patient"), None);
+    }
+
+    #[tokio::test]
+    async fn test_fixes_make_resource_valid() {
+        let schemas = get_schemas(FhirVersion::R4).unwrap().clone();
+        let validator = FhirValidator::from_schemas(schemas, None);
+        let mut patient = json!({
+            "active": "true",
+            "name": {"family": "Doe", "given": "John"},
+            "gender": ["female"],
+            "multipleBirthInteger": "2"
+        });
+
+        let result = validator
+            .validate(&patient, vec!["Patient".to_string()])
+            .await;
+        assert!(!result.valid);
+        assert!(
+            result.errors.iter().all(|e| e.fix.is_some()),
+            "errors: {:?}",
+            result.errors
+        );
+
+        assert_eq!(apply_fixes(&mut patient, &result.errors), 5);
+        assert_eq!(patient["resourceType"], "Patient");
+        assert_eq!(patient["name"], json!([{"family": "Doe", "given": "John"}]));
+
+        // Fixing the outer array uncovers the scalar `given` inside it.
+        let result = validator
+            .validate(&patient, vec!["Patient".to_string()])
+            .await;
+        assert_eq!(apply_fixes(&mut patient, &result.errors), 1);
+        let result = validator
+            .validate(&patient, vec!["Patient".to_string()])
+            .await;
+        assert!(result.valid, "errors: {:?}", result.errors);
+        assert_eq!(patient["active"], json!(true));
+        assert_eq!(patient["gender"], json!("female"));
+        assert_eq!(patient["multipleBirthInteger"], json!(2));
+    }
+}
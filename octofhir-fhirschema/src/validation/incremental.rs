@@ -0,0 +1,389 @@
+//! Incremental revalidation after small edits.
+//!
+//! Editors revalidate on every keystroke, and an edit usually touches one
+//! element of one entry. [`FhirValidator::revalidate`] applies a JSON Patch to
+//! the last validated resource and re-runs validation only for the branches
+//! the patch touches, keeping the previous findings everywhere else.
+//!
+//! A branch is a top-level element, or one item of a top-level array
+//! (`Bundle.entry[3]`, `Patient.name[1]`), so editing a Bundle entry
+//! revalidates that entry alone. Findings about the resource as a whole
+//! (required elements, resource-level invariants, unresolved profiles) are
+//! always recomputed. A patch that replaces the resource or changes its
+//! `resourceType` falls back to full validation.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use serde_json::Value as JsonValue;
+
+use super::FhirValidator;
+use super::fixes::{apply_fix, json_pointer};
+use crate::error::Result;
+use crate::types::{PatchOperation, ValidationError, ValidationResult};
+
+/// Part of a top-level element touched by a patch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum DirtyBranch {
+    /// The element as a whole, including its array length and order
+    Whole,
+    /// Only these items of an array-valued element
+    Items(BTreeSet<usize>),
+}
+
+impl DirtyBranch {
+    /// The dirty items, or `None` when the whole element is dirty.
+    pub(crate) fn items(&self) -> Option<&BTreeSet<usize>> {
+        match self {
+            DirtyBranch::Whole => None,
+            DirtyBranch::Items(items) => Some(items),
+        }
+    }
+}
+
+/// The branches of a resource that must be revalidated after a patch.
+#[derive(Debug, Default)]
+pub(crate) struct RevalidationScope {
+    branches: HashMap<String, DirtyBranch>,
+}
+
+impl RevalidationScope {
+    /// Work out the dirty branches of `patch`. Returns `None` when the patch
+    /// touches the resource root or `resourceType`, which needs full
+    /// validation.
+    pub(crate) fn from_patch(patch: &[PatchOperation]) -> Option<Self> {
+        let mut scope = Self::default();
+        for operation in patch {
+            let tokens = pointer_tokens(operation.path())?;
+            let key = tokens.first()?;
+            if branch_key(key) == "resourceType" {
+                return None;
+            }
+            // Adding or removing an array item shifts the items after it,
+            // so only an edit inside an item, or replacing it, stays local.
+            let index = tokens
+                .get(1)
+                .and_then(|token| token.parse::<usize>().ok())
+                .filter(|_| {
+                    tokens.len() > 2 || matches!(operation, PatchOperation::Replace { .. })
+                });
+            scope.mark(key, index);
+        }
+        Some(scope)
+    }
+
+    fn mark(&mut self, key: &str, index: Option<usize>) {
+        let branch = self
+            .branches
+            .entry(branch_key(key).to_string())
+            .or_insert_with(|| DirtyBranch::Items(BTreeSet::new()));
+        match (branch, index) {
+            (DirtyBranch::Items(items), Some(index)) => {
+                items.insert(index);
+            }
+            (branch, _) => *branch = DirtyBranch::Whole,
+        }
+    }
+
+    /// The dirty part of top-level element `key`, or `None` when it is clean.
+    /// A primitive's `_key` extension sibling shares its branch.
+    pub(crate) fn branch(&self, key: &str) -> Option<&DirtyBranch> {
+        self.branches.get(branch_key(key))
+    }
+
+    /// Whether item `index` of top-level array `key` is dirty.
+    pub(crate) fn is_dirty_item(&self, key: &str, index: usize) -> bool {
+        self.branch(key)
+            .is_some_and(|branch| branch.items().is_none_or(|items| items.contains(&index)))
+    }
+
+    /// Whether a finding at the JSON Pointer `pointer` has to be recomputed.
+    fn is_dirty_pointer(&self, pointer: &str) -> bool {
+        let Some(tokens) = pointer_tokens(pointer) else {
+            return true;
+        };
+        let Some(key) = tokens.first() else {
+            return true;
+        };
+        match (self.branch(key), tokens.get(1)) {
+            (None, _) => false,
+            (Some(DirtyBranch::Whole), _) => true,
+            (Some(DirtyBranch::Items(items)), Some(token)) => token
+                .parse::<usize>()
+                .ok()
+                .is_none_or(|index| items.contains(&index)),
+            // The array itself: length, order and slicing
+            (Some(DirtyBranch::Items(_)), None) => true,
+        }
+    }
+
+    /// Whether a finding at the error path `path` has to be recomputed.
+    /// Paths that do not map into the resource always are.
+    pub(crate) fn is_dirty_path(&self, path: &str, root_path: &str) -> bool {
+        json_pointer(path, root_path).is_none_or(|pointer| self.is_dirty_pointer(&pointer))
+    }
+
+    fn is_dirty(&self, issue: &ValidationError, root_path: &str) -> bool {
+        self.is_dirty_path(issue.path.as_str(), root_path)
+    }
+}
+
+/// Top-level element a key belongs to: `_birthDate` belongs to `birthDate`.
+fn branch_key(key: &str) -> &str {
+    key.strip_prefix('_').unwrap_or(key)
+}
+
+/// Split a JSON Pointer into unescaped reference tokens.
+fn pointer_tokens(pointer: &str) -> Option<Vec<String>> {
+    if pointer.is_empty() {
+        return Some(Vec::new());
+    }
+    let tokens = pointer.strip_prefix('/')?.split('/');
+    Some(
+        tokens
+            .map(|token| token.replace("~1", "/").replace("~0", "~"))
+            .collect(),
+    )
+}
+
+/// Compute a patch turning `previous` into `current` at branch granularity:
+/// one operation per changed top-level element, or per changed item when a
+/// top-level array keeps its length.
+///
+/// Lets callers that only hold the edited document, such as an editor
+/// sending its whole buffer, use [`FhirValidator::revalidate`].
+pub fn resource_diff(previous: &JsonValue, current: &JsonValue) -> Vec<PatchOperation> {
+    let (JsonValue::Object(before), JsonValue::Object(after)) = (previous, current) else {
+        if previous == current {
+            return Vec::new();
+        }
+        return vec![PatchOperation::Replace {
+            path: String::new(),
+            value: current.clone(),
+        }];
+    };
+
+    let escape = |key: &str| key.replace('~', "~0").replace('/', "~1");
+    let mut patch = Vec::new();
+    for (key, old) in before {
+        let path = format!("/{}", escape(key));
+        match after.get(key) {
+            None => patch.push(PatchOperation::Remove { path }),
+            Some(new) if new == old => {}
+            Some(JsonValue::Array(new_items)) => match old {
+                JsonValue::Array(old_items) if old_items.len() == new_items.len() => {
+                    for (index, (old_item, new_item)) in old_items.iter().zip(new_items).enumerate()
+                    {
+                        if old_item != new_item {
+                            patch.push(PatchOperation::Replace {
+                                path: format!("{path}/{index}"),
+                                value: new_item.clone(),
+                            });
+                        }
+                    }
+                }
+                _ => patch.push(PatchOperation::Replace {
+                    path,
+                    value: JsonValue::Array(new_items.clone()),
+                }),
+            },
+            Some(new) => patch.push(PatchOperation::Replace {
+                path,
+                value: new.clone(),
+            }),
+        }
+    }
+    for (key, new) in after {
+        if !before.contains_key(key) {
+            patch.push(PatchOperation::Add {
+                path: format!("/{}", escape(key)),
+                value: new.clone(),
+            });
+        }
+    }
+    patch
+}
+
+impl FhirValidator {
+    /// Apply `patch` to `previous_resource` and revalidate only what it
+    /// changed, reusing `previous_result` for untouched branches.
+    ///
+    /// `previous_result` must be the result of validating
+    /// `previous_resource` against the same `schema_names`; when an edit
+    /// changes which profiles apply (e.g. `meta.profile`), validate the new
+    /// resource in full instead. Returns the patched resource with its
+    /// result, or an error when the patch does not apply.
+    pub async fn revalidate(
+        &self,
+        previous_resource: &JsonValue,
+        patch: &[PatchOperation],
+        previous_result: &ValidationResult,
+        schema_names: Vec<String>,
+    ) -> Result<(JsonValue, ValidationResult)> {
+        let mut resource = previous_resource.clone();
+        apply_fix(&mut resource, patch)?;
+
+        let Some(scope) = RevalidationScope::from_patch(patch) else {
+            let result = self.validate(&resource, schema_names).await;
+            return Ok((resource, result));
+        };
+
+        let root_path = resource
+            .get("resourceType")
+            .and_then(JsonValue::as_str)
+            .unwrap_or_default();
+        let mut visited = HashSet::new();
+        let fresh = self
+            .validate_impl(
+                &resource,
+                schema_names,
+                None,
+                0,
+                &mut visited,
+                Some(&scope),
+                &HashMap::new(),
+            )
+            .await;
+
+        // Findings of clean branches come from the previous run; the fresh run
+        // only covers dirty branches and the resource root. Filtering both
+        // sides keeps phases that are not scoped from reporting twice.
+        let merge = |previous: &[ValidationError], fresh: Vec<ValidationError>| {
+            previous
+                .iter()
+                .filter(|issue| !scope.is_dirty(issue, root_path))
+                .cloned()
+                .chain(
+                    fresh
+                        .into_iter()
+                        .filter(|issue| scope.is_dirty(issue, root_path)),
+                )
+                .collect::<Vec<_>>()
+        };
+        let errors = merge(&previous_result.errors, fresh.errors);
+        let warnings = merge(&previous_result.warnings, fresh.warnings);
+        let result = ValidationResult {
+            valid: errors.is_empty(),
+            errors,
+            warnings,
+            explain: None,
+        };
+        Ok((resource, result))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embedded::{FhirVersion, get_schemas};
+    use serde_json::json;
+
+    #[test]
+    fn test_scope_from_patch() {
+        let scope = RevalidationScope::from_patch(&[
+            PatchOperation::Replace {
+                path: "/entry/3/resource/active".to_string(),
+                value: json!(true),
+            },
+            PatchOperation::Add {
+                path: "/_birthDate".to_string(),
+                value: json!({"id": "b"}),
+            },
+        ])
+        .unwrap();
+        assert_eq!(
+            scope.branch("entry"),
+            Some(&DirtyBranch::Items(BTreeSet::from([3])))
+        );
+        assert_eq!(scope.branch("birthDate"), Some(&DirtyBranch::Whole));
+        assert!(scope.is_dirty_path("Bundle.entry[3].resource", "Bundle"));
+        assert!(scope.is_dirty_path("Bundle.entry", "Bundle"));
+        assert!(scope.is_dirty_path("Bundle", "Bundle"));
+        assert!(!scope.is_dirty_path("Bundle.entry[2].resource", "Bundle"));
+        assert!(!scope.is_dirty_path("Bundle.identifier", "Bundle"));
+
+        let removal = RevalidationScope::from_patch(&[PatchOperation::Remove {
+            path: "/entry/1".to_string(),
+        }])
+        .unwrap();
+        assert_eq!(removal.branch("entry"), Some(&DirtyBranch::Whole));
+
+        assert!(
+            RevalidationScope::from_patch(&[PatchOperation::Replace {
+                path: "/resourceType".to_string(),
+                value: json!("Group"),
+            }])
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn test_resource_diff() {
+        let previous = json!({"resourceType": "Patient", "name": [{"family": "A"}, {"family": "B"}], "active": true});
+        let current = json!({"resourceType": "Patient", "name": [{"family": "A"}, {"family": "C"}], "gender": "male"});
+        let mut patch = resource_diff(&previous, &current);
+        patch.sort_by(|a, b| a.path().cmp(b.path()));
+        assert_eq!(
+            patch,
+            vec![
+                PatchOperation::Remove {
+                    path: "/active".to_string()
+                },
+                PatchOperation::Add {
+                    path: "/gender".to_string(),
+                    value: json!("male")
+                },
+                PatchOperation::Replace {
+                    path: "/name/1".to_string(),
+                    value: json!({"family": "C"})
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_revalidate_matches_full_validation() {
+        let schemas = get_schemas(FhirVersion::R4).unwrap().clone();
+        let validator = FhirValidator::from_schemas(schemas, None);
+        let names = || vec!["Patient".to_string()];
+        let patient = json!({
+            "resourceType": "Patient",
+            "active": "yes",
+            "name": [{"family": "Doe"}, {"family": 7}],
+            "gender": "female"
+        });
+        let previous = validator.validate(&patient, names()).await;
+        assert_eq!(previous.errors.len(), 2);
+
+        // Fix name[1]; the untouched `active` error is carried over.
+        let patch = [PatchOperation::Replace {
+            path: "/name/1/family".to_string(),
+            value: json!("Roe"),
+        }];
+        let (patched, result) = validator
+            .revalidate(&patient, &patch, &previous, names())
+            .await
+            .unwrap();
+        let full = validator.validate(&patched, names()).await;
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(
+            serde_json::to_value(&result).unwrap(),
+            serde_json::to_value(&full).unwrap()
+        );
+
+        // A new error in a new element is found.
+        let patch = [PatchOperation::Add {
+            path: "/birthDate".to_string(),
+            value: json!(1990),
+        }];
+        let (patched, result) = validator
+            .revalidate(&patched, &patch, &full, names())
+            .await
+            .unwrap();
+        let full = validator.validate(&patched, names()).await;
+        assert_eq!(result.errors.len(), 2);
+        assert_eq!(
+            serde_json::to_value(&result).unwrap(),
+            serde_json::to_value(&full).unwrap()
+        );
+    }
+}
@@ -10,24 +10,50 @@
 //! - `CompiledSchema` - Schema with all nested types inlined
 //! - `SchemaCompiler` - Lazily compiles and caches schemas
 //! - `FhirValidator` - Fast validator using compiled schemas
+//!
+//! With the `rayon` feature, `FhirValidator::validate_batch_parallel` runs the
+//! structural phase over a batch of resources on a rayon pool.
 
+#[cfg(feature = "rayon")]
+mod batch;
+pub mod capability;
 pub mod compiled;
 pub mod compiler;
+pub mod definition;
+mod discriminator;
+mod explain;
+pub mod fixes;
+mod incremental;
 pub mod questionnaire;
+pub mod tuning;
+mod xml;
 
+pub use capability::{CapabilityPolicy, ResourcePolicy, SearchParamPolicy};
 pub use compiled::*;
 pub use compiler::*;
+pub use definition::check_structure_definition;
+pub use fixes::{apply_fix, apply_fixes};
+pub use incremental::resource_diff;
 pub use questionnaire::{QrStrictness, QuestionnaireProvider};
+pub use tuning::CacheTuning;
 
 use crate::reference::{ReferenceResolver, reference_resource_type};
-use crate::terminology::TerminologyService;
-use crate::types::{FhirSchema, FhirSchemaSlicing, ValidationError, ValidationResult};
+use crate::terminology::{TerminologyService, core_terminology_service};
+use crate::types::{
+    ErrorMessage, ErrorPath, FhirSchema, FhirSchemaSlicing, PatchOperation, ValidationError,
+    ValidationResult,
+};
 use async_trait::async_trait;
+use bumpalo::Bump;
+use discriminator::Discriminators;
+use incremental::RevalidationScope;
 use octofhir_fhir_model::FhirPathEvaluator;
 use once_cell::sync::Lazy;
 use regex::Regex;
 use serde_json::Value as JsonValue;
-use std::collections::{HashMap, HashSet};
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::sync::Arc;
 
 /// Default maximum recursion depth for `targetProfile` conformance validation.
@@ -35,6 +61,21 @@ use std::sync::Arc;
 /// `targetProfile`; this bounds how deep the transitive check descends.
 const DEFAULT_MAX_REFERENCE_DEPTH: usize = 5;
 
+/// Value of the FHIRPath `%ucum` environment variable
+const UCUM_SYSTEM: &str = "http://unitsofmeasure.org";
+
+/// Largest scratch arena a thread keeps between walks. `Bump::reset` keeps
+/// the biggest chunk, so one huge resource would otherwise pin its memory
+/// on the thread for good; arenas grown past this are dropped instead.
+const SCRATCH_RETAIN_BYTES: usize = 1024 * 1024;
+
+thread_local! {
+    /// Scratch arena for the structural walk, kept per thread so its chunks
+    /// are reused across resources. Taken out for the duration of a walk, so
+    /// a nested walk on the same thread starts from an empty arena.
+    static SCRATCH: Cell<Bump> = Cell::new(Bump::new());
+}
+
 /// A Reference site discovered during structural validation, paired with the
 /// `targetProfile` canonical URLs declared for it. Consumed by the async
 /// `targetProfile` conformance phase.
@@ -49,6 +90,19 @@ struct RefCheck {
     targets: Vec<String>,
 }
 
+/// Per-validate memo of the constraint phase, shared across every schema
+/// in one `validate` call.
+#[derive(Default)]
+struct ConstraintMemo {
+    /// FHIRPath results, keyed by `path \u{1f} expression`
+    constraints: HashMap<String, bool>,
+    /// Required-binding lookups
+    bindings: HashMap<BindingKey, bool>,
+}
+
+/// `(value set, system, code)` of a required-binding lookup.
+type BindingKey = (String, Option<String>, String);
+
 // FHIR R4 primitive type regexes (anchored full-match)
 // Source: https://www.hl7.org/fhir/R4/datatypes.html
 const INT32_MIN: i64 = -2_147_483_648;
@@ -78,6 +132,23 @@ static RE_UUID: Lazy<Regex> = Lazy::new(|| {
 });
 static RE_BASE64: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(\s*[0-9a-zA-Z+/=]\s*){4,}$").unwrap());
 
+/// Regexes of profiled primitives (`regex` extensions), compiled once and
+/// anchored for a full match. Patterns that do not compile map to `None` and
+/// are not enforced.
+static PROFILE_REGEXES: Lazy<std::sync::RwLock<HashMap<String, Option<Arc<Regex>>>>> =
+    Lazy::new(Default::default);
+
+fn profile_regex(pattern: &str) -> Option<Arc<Regex>> {
+    if let Some(regex) = PROFILE_REGEXES.read().ok()?.get(pattern) {
+        return regex.clone();
+    }
+    let regex = Regex::new(&format!("^(?:{pattern})$")).ok().map(Arc::new);
+    if let Ok(mut cache) = PROFILE_REGEXES.write() {
+        cache.insert(pattern.to_string(), regex.clone());
+    }
+    regex
+}
+
 /// Calendar validity for a FHIR date/dateTime/instant date portion. Accepts
 /// partial dates (`YYYY`, `YYYY-MM`) — only `YYYY-MM-DD` triggers a day-level
 /// check (e.g. rejects `2024-02-31`, `2023-02-29`).
@@ -228,32 +299,49 @@ pub enum FhirSchemaErrorCode {
     ReferenceNotFound = 1015,
     QuestionnaireViolation = 1016,
     ReferenceTargetProfileMismatch = 1017,
+    CapabilityViolation = 1018,
+    DefinitionViolation = 1019,
 }
 
-impl std::fmt::Display for FhirSchemaErrorCode {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl FhirSchemaErrorCode {
+    /// The code as reported in [`ValidationError::error_type`], e.g. `FS1001`.
+    pub fn as_str(&self) -> &'static str {
         match self {
-            FhirSchemaErrorCode::UnknownElement => write!(f, "FS1001"),
-            FhirSchemaErrorCode::UnknownSchema => write!(f, "FS1002"),
-            FhirSchemaErrorCode::ExpectedArray => write!(f, "FS1003"),
-            FhirSchemaErrorCode::UnexpectedArray => write!(f, "FS1004"),
-            FhirSchemaErrorCode::UnknownKeyword => write!(f, "FS1005"),
-            FhirSchemaErrorCode::WrongType => write!(f, "FS1006"),
-            FhirSchemaErrorCode::SlicingUnmatched => write!(f, "FS1007"),
-            FhirSchemaErrorCode::SlicingAmbiguous => write!(f, "FS1008"),
-            FhirSchemaErrorCode::SliceCardinality => write!(f, "FS1009"),
-            FhirSchemaErrorCode::ConstraintViolation => write!(f, "FS1010"),
-            FhirSchemaErrorCode::CardinalityViolation => write!(f, "FS1011"),
-            FhirSchemaErrorCode::BindingViolation => write!(f, "FS1012"),
-            FhirSchemaErrorCode::ReferenceTypeViolation => write!(f, "FS1013"),
-            FhirSchemaErrorCode::InvalidValue => write!(f, "FS1014"),
-            FhirSchemaErrorCode::ReferenceNotFound => write!(f, "FS1015"),
-            FhirSchemaErrorCode::QuestionnaireViolation => write!(f, "FS1016"),
-            FhirSchemaErrorCode::ReferenceTargetProfileMismatch => write!(f, "FS1017"),
+            FhirSchemaErrorCode::UnknownElement => "FS1001",
+            FhirSchemaErrorCode::UnknownSchema => "FS1002",
+            FhirSchemaErrorCode::ExpectedArray => "FS1003",
+            FhirSchemaErrorCode::UnexpectedArray => "FS1004",
+            FhirSchemaErrorCode::UnknownKeyword => "FS1005",
+            FhirSchemaErrorCode::WrongType => "FS1006",
+            FhirSchemaErrorCode::SlicingUnmatched => "FS1007",
+            FhirSchemaErrorCode::SlicingAmbiguous => "FS1008",
+            FhirSchemaErrorCode::SliceCardinality => "FS1009",
+            FhirSchemaErrorCode::ConstraintViolation => "FS1010",
+            FhirSchemaErrorCode::CardinalityViolation => "FS1011",
+            FhirSchemaErrorCode::BindingViolation => "FS1012",
+            FhirSchemaErrorCode::ReferenceTypeViolation => "FS1013",
+            FhirSchemaErrorCode::InvalidValue => "FS1014",
+            FhirSchemaErrorCode::ReferenceNotFound => "FS1015",
+            FhirSchemaErrorCode::QuestionnaireViolation => "FS1016",
+            FhirSchemaErrorCode::ReferenceTargetProfileMismatch => "FS1017",
+            FhirSchemaErrorCode::CapabilityViolation => "FS1018",
+            FhirSchemaErrorCode::DefinitionViolation => "FS1019",
         }
     }
 }
 
+impl std::fmt::Display for FhirSchemaErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<FhirSchemaErrorCode> for std::borrow::Cow<'static, str> {
+    fn from(code: FhirSchemaErrorCode) -> Self {
+        std::borrow::Cow::Borrowed(code.as_str())
+    }
+}
+
 /// Cached element information from schema lookup (single pass optimization).
 /// Collects all needed info about an element in one iteration over schemata.
 #[derive(Debug, Default)]
@@ -275,6 +363,30 @@ pub enum SliceClassification {
     Ambiguous(Vec<String>),
 }
 
+/// Options for [`FhirValidator::validate_with_options`].
+#[derive(Debug, Clone, Default)]
+pub struct ValidationOptions {
+    /// Record how the schemas were resolved for each path in
+    /// [`ValidationResult::explain`]; see [`FhirValidator::explain`].
+    pub explain: bool,
+    /// Additional FHIRPath environment variables for constraint evaluation,
+    /// keyed by name without the leading `%` (e.g. `"tenant"` for `%tenant`).
+    /// `%resource`, `%rootResource` and `%ucum` are always set by the
+    /// validator and cannot be overridden.
+    pub variables: HashMap<String, JsonValue>,
+}
+
+impl ValidationOptions {
+    /// Add the FHIRPath environment variable `%name`; a leading `%` in
+    /// `name` is ignored.
+    pub fn with_variable(mut self, name: impl Into<String>, value: JsonValue) -> Self {
+        let name = name.into();
+        let name = name.strip_prefix('%').map(str::to_string).unwrap_or(name);
+        self.variables.insert(name, value);
+        self
+    }
+}
+
 // =============================================================================
 // FhirValidator - High-performance validator using pre-compiled schemas
 // =============================================================================
@@ -288,7 +400,8 @@ pub struct FhirValidator {
     compiler: SchemaCompiler,
     /// Optional FHIRPath evaluator for constraint validation
     fhirpath_evaluator: Option<Arc<dyn FhirPathEvaluator>>,
-    /// Optional terminology service for binding validation
+    /// Terminology service for binding validation; defaults to the embedded
+    /// core value sets
     terminology_service: Option<Arc<dyn TerminologyService>>,
     /// Optional reference resolver for existence validation
     reference_resolver: Option<Arc<dyn ReferenceResolver>>,
@@ -312,7 +425,7 @@ impl FhirValidator {
         Self {
             compiler: SchemaCompiler::new(schema_provider),
             fhirpath_evaluator: None,
-            terminology_service: None,
+            terminology_service: Some(core_terminology_service()),
             reference_resolver: None,
             questionnaire_provider: None,
             questionnaire_strictness: questionnaire::QrStrictness::default(),
@@ -329,7 +442,7 @@ impl FhirValidator {
         Self {
             compiler: SchemaCompiler::new(schema_provider),
             fhirpath_evaluator: Some(fhirpath_evaluator),
-            terminology_service: None,
+            terminology_service: Some(core_terminology_service()),
             reference_resolver: None,
             questionnaire_provider: None,
             questionnaire_strictness: questionnaire::QrStrictness::default(),
@@ -384,11 +497,54 @@ impl FhirValidator {
     }
 
     /// Add terminology service for binding validation
+    ///
+    /// Replaces the embedded core value sets used by default; wrap them in your
+    /// service (see [`core_terminology_service`]) to keep enforcing them.
     pub fn with_terminology_service(mut self, service: Arc<dyn TerminologyService>) -> Self {
         self.terminology_service = Some(service);
         self
     }
 
+    /// Serve these compiled schemas without compiling them at runtime.
+    ///
+    /// See [`SchemaCompiler::with_precompiled`]; pairs with
+    /// [`get_compiled_schemas`](crate::embedded::get_compiled_schemas) for
+    /// embedded core schemas.
+    pub fn with_precompiled_schemas<I>(mut self, schemas: I) -> Self
+    where
+        I: IntoIterator<Item = SharedCompiledSchema>,
+    {
+        self.compiler = self.compiler.with_precompiled(schemas);
+        self
+    }
+
+    /// Size the compiled schema cache.
+    ///
+    /// See [`SchemaCompiler::with_cache_tuning`]. The terminology cache is
+    /// owned by the terminology service; build it with
+    /// `CachedTerminologyService::new(service, tuning.terminology.clone())`.
+    pub fn with_cache_tuning(mut self, tuning: &CacheTuning) -> Self {
+        self.compiler = self.compiler.with_cache_tuning(tuning);
+        self
+    }
+
+    /// Merge profile chains through a cache shared with other consumers.
+    ///
+    /// See [`SchemaCompiler::with_profile_cache`].
+    pub fn with_profile_cache(mut self, cache: Arc<crate::profiles::ProfileMergeCache>) -> Self {
+        self.compiler = self.compiler.with_profile_cache(cache);
+        self
+    }
+
+    /// Persist compiled schemas in `dir` and reload them on later runs.
+    ///
+    /// See [`SchemaCompiler::with_disk_cache`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_compiled_cache_dir(mut self, dir: impl Into<std::path::PathBuf>) -> Self {
+        self.compiler = self.compiler.with_disk_cache(dir);
+        self
+    }
+
     /// Add reference resolver for existence validation
     pub fn with_reference_resolver(mut self, resolver: Arc<dyn ReferenceResolver>) -> Self {
         self.reference_resolver = Some(resolver);
@@ -432,6 +588,22 @@ impl FhirValidator {
         self
     }
 
+    /// Compile schemas ahead of validation, concurrently, so the first
+    /// resources of a batch do not pay for compilation. Returns the schemas
+    /// that failed to compile.
+    pub async fn warm_up<I, S>(&self, schema_names: I) -> Vec<(String, CompileError)>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.compiler
+            .compile_many(schema_names)
+            .await
+            .into_iter()
+            .filter_map(|(name, result)| result.err().map(|e| (name, e)))
+            .collect()
+    }
+
     /// Validate a resource against its resourceType schema.
     ///
     /// Performs both structural validation and FHIRPath constraint validation.
@@ -445,6 +617,123 @@ impl FhirValidator {
             .await
     }
 
+    /// Validate a resource against its resourceType and `profiles`, given by
+    /// name or canonical URL (optionally `url|version`).
+    ///
+    /// Each profile is compiled once with its whole inheritance chain merged
+    /// and cached by canonical, so later calls naming it any of these ways
+    /// reuse the compiled schema.
+    pub async fn validate_with_profiles(
+        &self,
+        resource: &JsonValue,
+        profiles: &[String],
+    ) -> ValidationResult {
+        let mut schema_names: Vec<String> = resource
+            .get("resourceType")
+            .and_then(JsonValue::as_str)
+            .map(str::to_string)
+            .into_iter()
+            .collect();
+        for profile in profiles {
+            if !schema_names.contains(profile) {
+                schema_names.push(profile.clone());
+            }
+        }
+        self.validate(resource, schema_names).await
+    }
+
+    /// Validate a resource with `options`.
+    pub async fn validate_with_options(
+        &self,
+        resource: &JsonValue,
+        schema_names: Vec<String>,
+        options: &ValidationOptions,
+    ) -> ValidationResult {
+        // Explain first, so the trace shows the cache as validation finds it.
+        let trace = if options.explain {
+            Some(self.explain(resource, &schema_names).await)
+        } else {
+            None
+        };
+        let mut visited = HashSet::new();
+        let mut result = self
+            .validate_impl(
+                resource,
+                schema_names,
+                None,
+                0,
+                &mut visited,
+                None,
+                &options.variables,
+            )
+            .await;
+        result.explain = trace;
+        result
+    }
+
+    /// Parse a resource from JSON bytes with [`crate::input::parse_resource`]
+    /// and validate it.
+    ///
+    /// With the `simd-json` feature the buffer is overwritten during parsing.
+    pub async fn validate_json(
+        &self,
+        bytes: &mut [u8],
+        schema_names: Vec<String>,
+    ) -> crate::error::Result<ValidationResult> {
+        let resource = crate::input::parse_resource(bytes)?;
+        Ok(self.validate(&resource, schema_names).await)
+    }
+
+    /// Validate a resource as the server described by `capability` would
+    /// accept it.
+    ///
+    /// Fails with `FS1018` when the server does not support the resource
+    /// type or a profile claimed in `meta.profile`. Otherwise the resource is
+    /// validated against its type, the server's base profile for it and its
+    /// claimed profiles.
+    pub async fn validate_against_capability(
+        &self,
+        resource: &JsonValue,
+        capability: &capability::CapabilityPolicy,
+    ) -> ValidationResult {
+        let (schema_names, mut errors) = capability.check(resource);
+        if schema_names.is_empty() {
+            return ValidationResult {
+                errors,
+                valid: false,
+                warnings: Vec::new(),
+                explain: None,
+            };
+        }
+
+        let mut result = self.validate(resource, schema_names).await;
+        errors.append(&mut result.errors);
+        result.valid = errors.is_empty();
+        result.errors = errors;
+        result
+    }
+
+    /// Validate an uploaded StructureDefinition before converting it: against
+    /// the StructureDefinition schema and the metaprofile rules of
+    /// [`definition`] (`sdf-*` invariants, element ordering, slicing).
+    ///
+    /// Invariants checked by [`check_structure_definition`] are reported
+    /// once, at the offending element, instead of on the resource root.
+    pub async fn validate_structure_definition(&self, sd: &JsonValue) -> ValidationResult {
+        let mut result = self
+            .validate(sd, vec!["StructureDefinition".to_string()])
+            .await;
+        result.errors.retain(|error| {
+            !error
+                .constraint_key
+                .as_deref()
+                .is_some_and(|key| definition::CHECKED_INVARIANTS.contains(&key))
+        });
+        result.errors.extend(check_structure_definition(sd));
+        result.valid = result.errors.is_empty();
+        result
+    }
+
     /// Validate a resource, treating a set of references as already existing.
     ///
     /// `known_references` is a set of literal `Type/id` reference strings that
@@ -461,14 +750,26 @@ impl FhirValidator {
         known_references: Option<&std::collections::HashSet<String>>,
     ) -> ValidationResult {
         let mut visited = HashSet::new();
-        self.validate_impl(resource, schema_names, known_references, 0, &mut visited)
-            .await
+        self.validate_impl(
+            resource,
+            schema_names,
+            known_references,
+            0,
+            &mut visited,
+            None,
+            &HashMap::new(),
+        )
+        .await
     }
 
     /// Core validation, parameterized by recursion `depth` and the set of
     /// references already being dereferenced on the current path (`visited`).
     /// Both support `targetProfile` conformance: `depth` bounds how far the
     /// transitive check descends, `visited` breaks reference cycles.
+    ///
+    /// With a `scope`, only its dirty branches and the resource root are
+    /// walked; see [`FhirValidator::revalidate`]. `extra_variables` are the
+    /// caller's FHIRPath environment variables for this resource.
     async fn validate_impl(
         &self,
         resource: &JsonValue,
@@ -476,6 +777,8 @@ impl FhirValidator {
         known_references: Option<&std::collections::HashSet<String>>,
         depth: usize,
         visited: &mut HashSet<String>,
+        scope: Option<&RevalidationScope>,
+        extra_variables: &HashMap<String, JsonValue>,
     ) -> ValidationResult {
         let mut errors = Vec::new();
         let mut warnings = Vec::new();
@@ -488,14 +791,14 @@ impl FhirValidator {
             && depth < self.max_reference_depth;
 
         // Prepare constraint variables once (includes %rootResource)
-        let variables = Self::prepare_constraint_variables(resource);
+        let variables = Self::prepare_constraint_variables(resource, extra_variables);
 
-        // Memo of FHIRPath constraint results for this resource, shared across
-        // every schema in `schema_names`. Overlapping profiles (base type +
-        // meta.profile snapshot) repeat the same invariants at the same paths;
-        // this evaluates each `(path, expression)` once. Errors are still
-        // emitted per schema, so output is unchanged.
-        let mut constraint_cache: HashMap<String, bool> = HashMap::new();
+        // Memo of FHIRPath constraint and binding results for this resource,
+        // shared across every schema in `schema_names`. Overlapping profiles
+        // (base type + meta.profile snapshot) repeat the same invariants at
+        // the same paths; this evaluates each `(path, expression)` once.
+        // Errors are still emitted per schema, so output is unchanged.
+        let mut constraint_cache = ConstraintMemo::default();
 
         // Start FHIRPath expressions at the resource's resourceType (e.g. "Patient",
         // "Parameters") so issue.expression matches the FHIRPath spec.
@@ -511,8 +814,9 @@ impl FhirValidator {
             match self.compiler.compile(schema_name).await {
                 Ok(compiled) => {
                     any_schema_compiled = true;
+                    let first_finding = errors.len();
                     // Phase 1: Structural validation (sync)
-                    self.validate_resource(resource, &compiled, &mut errors, &root_path);
+                    self.validate_resource(resource, &compiled, &mut errors, &root_path, scope);
 
                     // Collect Reference sites carrying a targetProfile for the
                     // async conformance phase. Done per compiled schema because
@@ -537,39 +841,18 @@ impl FhirValidator {
                         &mut errors,
                         &root_path,
                         &mut constraint_cache,
+                        scope,
                     )
                     .await;
+                    Self::attribute_findings(&mut errors[first_finding..], &compiled);
                 }
                 Err(e) => {
-                    // An unresolvable profile canonical (e.g. a `meta.profile`
-                    // pointing at a StructureDefinition from a package that is
-                    // not loaded) is non-fatal per the FHIR spec: the resource is
-                    // still validated against every schema that did resolve, and
-                    // the unresolved profile is reported as a warning rather than
-                    // failing validity. Only an unresolvable base type (a plain
-                    // resourceType name, never a URL) is a hard error.
-                    let is_profile_canonical = schema_name.contains("://");
-                    let issue = ValidationError {
-                        error_type: FhirSchemaErrorCode::UnknownSchema.to_string(),
-                        path: vec![],
-                        message: Some(e.message),
-                        value: None,
-                        expected: None,
-                        got: None,
-                        schema_path: None,
-                        constraint_key: None,
-                        constraint_expression: None,
-                        constraint_severity: Some(if is_profile_canonical {
-                            "warning".to_string()
-                        } else {
-                            "error".to_string()
-                        }),
-                    };
-                    if is_profile_canonical {
-                        warnings.push(issue);
-                    } else {
-                        errors.push(issue);
-                    }
+                    Self::record_unresolved_schema(
+                        schema_name,
+                        e.message,
+                        &mut errors,
+                        &mut warnings,
+                    );
                 }
             }
         }
@@ -582,7 +865,7 @@ impl FhirValidator {
         // schemas were validated — but only when at least one schema compiled,
         // matching the previous behavior of running inside the schema loop.
         if any_schema_compiled {
-            self.validate_extensions_recursive(resource, &mut errors, &root_path)
+            self.validate_extensions_recursive(resource, &mut errors, &root_path, scope)
                 .await;
         }
 
@@ -618,6 +901,9 @@ impl FhirValidator {
             if let Some(known) = known_references {
                 references.retain(|(_, reference)| !known.contains(reference));
             }
+            if let Some(scope) = scope {
+                references.retain(|(path, _)| scope.is_dirty_path(path, &root_path));
+            }
             // Resolve all references concurrently. Each resolution is an
             // independent backend round-trip (e.g. a storage existence check);
             // running them sequentially serialized N round-trips (and N pool
@@ -634,10 +920,11 @@ impl FhirValidator {
                 match result {
                     Ok(result) if !result.exists => {
                         errors.push(ValidationError {
-                            error_type: FhirSchemaErrorCode::ReferenceNotFound.to_string(),
-                            path: self.path_to_vec(&ref_path),
-                            message: Some(format!(
-                                "Referenced resource '{reference}' does not exist"
+                            error_type: FhirSchemaErrorCode::ReferenceNotFound.into(),
+                            path: ErrorPath::new(&ref_path),
+                            message: Some(ErrorMessage::template(
+                                "Referenced resource '{}' does not exist",
+                                [reference.clone()],
                             )),
                             value: Some(JsonValue::String(reference.clone())),
                             expected: None,
@@ -646,6 +933,9 @@ impl FhirValidator {
                             constraint_key: None,
                             constraint_expression: None,
                             constraint_severity: Some("error".to_string()),
+                            fix: None,
+                            schema_url: None,
+                            schema_version: None,
                         });
                     }
                     // Found, skipped (external/contained), or a transient resolver
@@ -665,6 +955,9 @@ impl FhirValidator {
         // conformance when the reference is resolvable); a resolvable resource
         // that matches no target is an error. Processed sequentially so the
         // shared `visited` cycle-guard and recursion depth stay consistent.
+        if let Some(scope) = scope {
+            ref_checks.retain(|check| scope.is_dirty_path(&check.path, &root_path));
+        }
         if collect_target_profiles && !ref_checks.is_empty() {
             // NB: unlike existence (Phase 4), `known_references` is NOT used to
             // filter here. That set lists references already known to exist in
@@ -694,13 +987,11 @@ impl FhirValidator {
                         // Unresolvable: warn but do not fail.
                         Ok(None) => {
                             warnings.push(ValidationError {
-                                error_type: FhirSchemaErrorCode::ReferenceTargetProfileMismatch
-                                    .to_string(),
-                                path: self.path_to_vec(&check.path),
-                                message: Some(format!(
+                                error_type: FhirSchemaErrorCode::ReferenceTargetProfileMismatch.into(),
+                                path: ErrorPath::new(&check.path),
+                                message: Some(ErrorMessage::template(
                                     "Could not resolve reference '{}' to verify conformance to targetProfile(s): {}",
-                                    check.reference,
-                                    check.targets.join(", ")
+                                    [check.reference.clone(), check.targets.join(", ")],
                                 )),
                                 value: Some(JsonValue::String(check.reference.clone())),
                                 expected: Some(JsonValue::Array(
@@ -715,6 +1006,9 @@ impl FhirValidator {
                                 constraint_key: None,
                                 constraint_expression: None,
                                 constraint_severity: Some("warning".to_string()),
+                                fix: None,
+                                schema_url: None,
+                                schema_version: None,
                             });
                             continue;
                         }
@@ -731,13 +1025,11 @@ impl FhirValidator {
 
                     if !conforms {
                         errors.push(ValidationError {
-                            error_type: FhirSchemaErrorCode::ReferenceTargetProfileMismatch
-                                .to_string(),
-                            path: self.path_to_vec(&check.path),
-                            message: Some(format!(
+                            error_type: FhirSchemaErrorCode::ReferenceTargetProfileMismatch.into(),
+                            path: ErrorPath::new(&check.path),
+                            message: Some(ErrorMessage::template(
                                 "Referenced resource '{}' does not conform to any declared targetProfile: {}",
-                                check.reference,
-                                check.targets.join(", ")
+                                [check.reference.clone(), check.targets.join(", ")],
                             )),
                             value: Some(JsonValue::String(check.reference.clone())),
                             expected: Some(JsonValue::Array(
@@ -752,6 +1044,9 @@ impl FhirValidator {
                             constraint_key: None,
                             constraint_expression: None,
                             constraint_severity: Some("error".to_string()),
+                            fix: None,
+                            schema_url: None,
+                            schema_version: None,
                         });
                     }
                 }
@@ -762,6 +1057,7 @@ impl FhirValidator {
             valid: errors.is_empty(),
             errors,
             warnings,
+            explain: None,
         }
     }
 
@@ -806,9 +1102,16 @@ impl FhirValidator {
             // so the target's own targetProfiles are checked too (bounded by
             // max_reference_depth via `collect_target_profiles`).
             checked_any_loadable = true;
-            let result =
-                Box::pin(self.validate_impl(body, vec![target.clone()], None, depth + 1, visited))
-                    .await;
+            let result = Box::pin(self.validate_impl(
+                body,
+                vec![target.clone()],
+                None,
+                depth + 1,
+                visited,
+                None,
+                &HashMap::new(),
+            ))
+            .await;
 
             if result.errors.is_empty() {
                 return true;
@@ -918,7 +1221,7 @@ impl FhirValidator {
 
             let display_key = self.choice_display_key(key, elements);
             let element_path = if path.is_empty() {
-                display_key.clone()
+                display_key.into_owned()
             } else {
                 format!("{}.{}", path, display_key)
             };
@@ -998,14 +1301,111 @@ impl FhirValidator {
 
     /// Prepare constraint variables map for FHIRPath evaluation.
     ///
-    /// Creates a variables map containing `%rootResource` which is required
-    /// for evaluating constraints like `ref-1` that reference contained resources.
-    fn prepare_constraint_variables(root_resource: &JsonValue) -> HashMap<String, Arc<JsonValue>> {
-        let mut variables = HashMap::with_capacity(1);
-        variables.insert("rootResource".to_string(), Arc::new(root_resource.clone()));
+    /// Creates a variables map containing `%resource` and `%rootResource`, the
+    /// latter required for evaluating constraints like `ref-1` that reference
+    /// contained resources, `%ucum` and the caller's `extra` variables.
+    fn prepare_constraint_variables(
+        root_resource: &JsonValue,
+        extra: &HashMap<String, JsonValue>,
+    ) -> HashMap<String, Arc<JsonValue>> {
+        let mut variables = HashMap::with_capacity(extra.len() + 2);
+        for (name, value) in extra {
+            variables.insert(name.clone(), Arc::new(value.clone()));
+        }
+        let root = Arc::new(root_resource.clone());
+        variables.insert("resource".to_string(), root.clone());
+        variables.insert("rootResource".to_string(), root);
+        variables.insert(
+            "ucum".to_string(),
+            Arc::new(JsonValue::String(UCUM_SYSTEM.to_string())),
+        );
         variables
     }
 
+    /// Variables for constraints inside `resource`, a resource nested at
+    /// `path` in the one `variables` were prepared for.
+    ///
+    /// `resource` becomes `%resource`. `%rootResource` is the containing
+    /// resource when `resource` is contained, and `resource` itself otherwise
+    /// (e.g. a `Bundle.entry.resource`).
+    fn nested_resource_variables(
+        variables: &HashMap<String, Arc<JsonValue>>,
+        resource: &JsonValue,
+        path: &str,
+    ) -> HashMap<String, Arc<JsonValue>> {
+        let mut nested = variables.clone();
+        let resource = Arc::new(resource.clone());
+        let element = path.strip_suffix(']').map_or(path, |p| {
+            p.rsplit_once('[').map_or(p, |(element, _)| element)
+        });
+        let root = if element.ends_with(".contained") || element == "contained" {
+            variables.get("resource").cloned()
+        } else {
+            None
+        };
+        nested.insert(
+            "rootResource".to_string(),
+            root.unwrap_or_else(|| resource.clone()),
+        );
+        nested.insert("resource".to_string(), resource);
+        nested
+    }
+
+    /// Record `schema` as the source of `findings`, keeping attributions
+    /// already made by a more specific schema.
+    fn attribute_findings(findings: &mut [ValidationError], schema: &CompiledSchema) {
+        let url = if schema.url.is_empty() {
+            &schema.name
+        } else {
+            &schema.url
+        };
+        for finding in findings.iter_mut().filter(|f| f.schema_url.is_none()) {
+            finding.schema_url = Some(url.clone());
+            finding.schema_version = schema.version.clone();
+        }
+    }
+
+    /// Report a schema that could not be compiled.
+    fn record_unresolved_schema(
+        schema_name: &str,
+        message: String,
+        errors: &mut Vec<ValidationError>,
+        warnings: &mut Vec<ValidationError>,
+    ) {
+        // An unresolvable profile canonical (e.g. a `meta.profile`
+        // pointing at a StructureDefinition from a package that is
+        // not loaded) is non-fatal per the FHIR spec: the resource is
+        // still validated against every schema that did resolve, and
+        // the unresolved profile is reported as a warning rather than
+        // failing validity. Only an unresolvable base type (a plain
+        // resourceType name, never a URL) is a hard error.
+        let is_profile_canonical = schema_name.contains("://");
+        let issue = ValidationError {
+            error_type: FhirSchemaErrorCode::UnknownSchema.into(),
+            path: ErrorPath::default(),
+            message: Some(message.into()),
+            value: None,
+            expected: None,
+            got: None,
+            schema_path: None,
+            constraint_key: None,
+            constraint_expression: None,
+            constraint_severity: Some(if is_profile_canonical {
+                "warning".to_string()
+            } else {
+                "error".to_string()
+            }),
+            fix: None,
+            schema_url: None,
+            schema_version: None,
+        };
+        if is_profile_canonical {
+            warnings.push(issue);
+        } else {
+            errors.push(issue);
+        }
+    }
+
     /// Validate resource against compiled schema
     fn validate_resource(
         &self,
@@ -1013,12 +1413,39 @@ impl FhirValidator {
         schema: &CompiledSchema,
         errors: &mut Vec<ValidationError>,
         path: &str,
+        scope: Option<&RevalidationScope>,
+    ) {
+        let first_new = errors.len();
+        let mut scratch = SCRATCH.with(Cell::take);
+        self.validate_resource_in(data, schema, errors, path, &scratch, scope);
+        if scratch.allocated_bytes() > SCRATCH_RETAIN_BYTES {
+            scratch = Bump::new();
+        } else {
+            scratch.reset();
+        }
+        SCRATCH.with(|cell| cell.set(scratch));
+        fixes::suggest_fixes(data, path, &mut errors[first_new..]);
+    }
+
+    /// Structural walk of one resource. Element paths and other per-element
+    /// temporaries are allocated in `scratch` and freed together when the
+    /// walk ends; only paths that end up in an error are copied out.
+    ///
+    /// With a `scope`, top-level elements outside it are skipped.
+    fn validate_resource_in(
+        &self,
+        data: &JsonValue,
+        schema: &CompiledSchema,
+        errors: &mut Vec<ValidationError>,
+        path: &str,
+        scratch: &Bump,
+        scope: Option<&RevalidationScope>,
     ) {
         let JsonValue::Object(obj) = data else {
             errors.push(ValidationError {
-                error_type: FhirSchemaErrorCode::WrongType.to_string(),
-                path: self.path_to_vec(path),
-                message: Some("Expected object".to_string()),
+                error_type: FhirSchemaErrorCode::WrongType.into(),
+                path: ErrorPath::new(path),
+                message: Some("Expected object".into()),
                 value: None,
                 expected: Some(JsonValue::String("object".to_string())),
                 got: Some(JsonValue::String(self.json_type_name(data).to_string())),
@@ -1026,19 +1453,51 @@ impl FhirValidator {
                 constraint_key: None,
                 constraint_expression: None,
                 constraint_severity: None,
+                fix: None,
+                schema_url: None,
+                schema_version: None,
             });
             return;
         };
 
+        // A resource must name its type. The root path is the resourceType,
+        // so an empty path with a resource schema means the key is absent.
+        if schema.is_resource && path.is_empty() && !obj.contains_key("resourceType") {
+            errors.push(ValidationError {
+                error_type: FhirSchemaErrorCode::CardinalityViolation.into(),
+                path: ErrorPath::default(),
+                message: Some("Required element 'resourceType' is missing".into()),
+                value: None,
+                expected: (!schema.type_name.is_empty())
+                    .then(|| JsonValue::String(schema.type_name.clone())),
+                got: None,
+                schema_path: None,
+                constraint_key: None,
+                constraint_expression: None,
+                constraint_severity: None,
+                fix: (!schema.type_name.is_empty()).then(|| {
+                    vec![PatchOperation::Add {
+                        path: "/resourceType".to_string(),
+                        value: JsonValue::String(schema.type_name.clone()),
+                    }]
+                }),
+                schema_url: None,
+                schema_version: None,
+            });
+        }
+
         // Check required elements
         for required in &schema.required {
             if !obj.contains_key(required)
                 && !self.has_choice_variant(obj, required, &schema.elements)
             {
                 errors.push(ValidationError {
-                    error_type: FhirSchemaErrorCode::CardinalityViolation.to_string(),
-                    path: self.path_to_vec(path),
-                    message: Some(format!("Required element '{}' is missing", required)),
+                    error_type: FhirSchemaErrorCode::CardinalityViolation.into(),
+                    path: ErrorPath::new(path),
+                    message: Some(ErrorMessage::template(
+                        "Required element '{}' is missing",
+                        [required.clone()],
+                    )),
                     value: None,
                     expected: None,
                     got: None,
@@ -1046,6 +1505,9 @@ impl FhirValidator {
                     constraint_key: None,
                     constraint_expression: None,
                     constraint_severity: None,
+                    fix: None,
+                    schema_url: None,
+                    schema_version: None,
                 });
             }
         }
@@ -1054,9 +1516,12 @@ impl FhirValidator {
         for excluded in &schema.excluded {
             if obj.contains_key(excluded) {
                 errors.push(ValidationError {
-                    error_type: FhirSchemaErrorCode::UnknownElement.to_string(),
-                    path: self.path_to_vec(path),
-                    message: Some(format!("Excluded element '{}' is present", excluded)),
+                    error_type: FhirSchemaErrorCode::UnknownElement.into(),
+                    path: ErrorPath::new(path),
+                    message: Some(ErrorMessage::template(
+                        "Excluded element '{}' is present",
+                        [excluded.clone()],
+                    )),
                     value: None,
                     expected: None,
                     got: None,
@@ -1064,6 +1529,9 @@ impl FhirValidator {
                     constraint_key: None,
                     constraint_expression: None,
                     constraint_severity: None,
+                    fix: None,
+                    schema_url: None,
+                    schema_version: None,
                 });
             }
         }
@@ -1075,6 +1543,14 @@ impl FhirValidator {
                 continue;
             }
 
+            // Revalidation walks only the edited elements, and of a partly
+            // edited array only the edited items.
+            let items = match scope.map(|scope| scope.branch(key)) {
+                Some(Some(branch)) => branch.items(),
+                Some(None) => continue,
+                None => None,
+            };
+
             // Handle primitive extensions (_element)
             if let Some(sibling) = key.strip_prefix('_') {
                 self.validate_primitive_extension(
@@ -1084,6 +1560,7 @@ impl FhirValidator {
                     obj,
                     errors,
                     path,
+                    scratch,
                 );
                 continue;
             }
@@ -1092,15 +1569,15 @@ impl FhirValidator {
             // FHIRPath-style location strings. Lookup uses raw key; path uses display.
             let display_key = self.choice_display_key(key, &schema.elements);
             let element_path = if path.is_empty() {
-                display_key.clone()
+                &*scratch.alloc_str(&display_key)
             } else {
-                format!("{}.{}", path, display_key)
+                bumpalo::format!(in scratch, "{}.{}", path, display_key).into_bump_str()
             };
 
             // Parallel primitive-extension array (`_key`) — used to allow `null`
             // entries in the value array that are filled by an Element extension.
             let underscore_arr = obj
-                .get(&format!("_{}", key))
+                .get(bumpalo::format!(in scratch, "_{}", key).as_str())
                 .and_then(|v| v.as_array())
                 .map(|v| v.as_slice());
 
@@ -1110,8 +1587,10 @@ impl FhirValidator {
                     element,
                     underscore_arr,
                     errors,
-                    &element_path,
+                    element_path,
                     &schema.elements,
+                    scratch,
+                    items,
                 );
             } else {
                 // Check if this is a choice type variant (e.g., valueString for value[x])
@@ -1132,15 +1611,20 @@ impl FhirValidator {
                             stem_element,
                             underscore_arr,
                             errors,
-                            &element_path,
+                            element_path,
                             &schema.elements,
+                            scratch,
+                            items,
                         );
                     }
                 } else {
                     errors.push(ValidationError {
-                        error_type: FhirSchemaErrorCode::UnknownElement.to_string(),
-                        path: self.path_to_vec(&element_path),
-                        message: Some(format!("Unknown element '{}'", key)),
+                        error_type: FhirSchemaErrorCode::UnknownElement.into(),
+                        path: ErrorPath::new(element_path),
+                        message: Some(ErrorMessage::template(
+                            "Unknown element '{}'",
+                            [key.clone()],
+                        )),
                         value: None,
                         expected: None,
                         got: None,
@@ -1148,6 +1632,9 @@ impl FhirValidator {
                         constraint_key: None,
                         constraint_expression: None,
                         constraint_severity: None,
+                        fix: None,
+                        schema_url: None,
+                        schema_version: None,
                     });
                 }
             }
@@ -1158,6 +1645,7 @@ impl FhirValidator {
     /// primitive-extension array (`_field`). `null` entries inside a primitive
     /// array are allowed only at indices where the parallel `_field[i]` is a
     /// non-null Element supplying extension content.
+    #[allow(clippy::too_many_arguments)]
     fn validate_element_with_underscore(
         &self,
         value: &JsonValue,
@@ -1168,6 +1656,9 @@ impl FhirValidator {
         // Root schema elements, used to resolve `contentReference` targets when
         // descending into elements that reuse another element's definition.
         root: &HashMap<String, CompiledElement>,
+        scratch: &Bump,
+        // Array items to walk, `None` for all. Array-level checks always run.
+        items: Option<&BTreeSet<usize>>,
     ) {
         // Array check
         let is_array = value.is_array();
@@ -1178,13 +1669,16 @@ impl FhirValidator {
                 } else {
                     FhirSchemaErrorCode::UnexpectedArray
                 }
-                .to_string(),
-                path: self.path_to_vec(path),
-                message: Some(if element.is_array {
-                    format!("Expected array for element '{}'", element.name)
-                } else {
-                    format!("Unexpected array for element '{}'", element.name)
-                }),
+                .into(),
+                path: ErrorPath::new(path),
+                message: Some(ErrorMessage::template(
+                    if element.is_array {
+                        "Expected array for element '{}'"
+                    } else {
+                        "Unexpected array for element '{}'"
+                    },
+                    [element.name.clone()],
+                )),
                 value: None,
                 expected: None,
                 got: None,
@@ -1192,6 +1686,9 @@ impl FhirValidator {
                 constraint_key: None,
                 constraint_expression: None,
                 constraint_severity: None,
+                fix: None,
+                schema_url: None,
+                schema_version: None,
             });
             return;
         }
@@ -1203,11 +1700,11 @@ impl FhirValidator {
                 // by omitting the key; `[]` is not allowed.
                 if arr.is_empty() {
                     errors.push(ValidationError {
-                        error_type: FhirSchemaErrorCode::CardinalityViolation.to_string(),
-                        path: self.path_to_vec(path),
-                        message: Some(format!(
+                        error_type: FhirSchemaErrorCode::CardinalityViolation.into(),
+                        path: ErrorPath::new(path),
+                        message: Some(ErrorMessage::template(
                             "Array element '{}' must not be empty",
-                            element.name
+                            [element.name.clone()],
                         )),
                         value: None,
                         expected: None,
@@ -1216,21 +1713,59 @@ impl FhirValidator {
                         constraint_key: None,
                         constraint_expression: None,
                         constraint_severity: None,
+                        fix: None,
+                        schema_url: None,
+                        schema_version: None,
                     });
                     return;
                 }
 
-                // Validate slicing if defined
-                if let Some(slicing) = &element.slicing {
-                    self.validate_slicing(arr, slicing, errors, path);
+                // Count against the element's cardinality. An absent element
+                // is left to the `required` check.
+                let min = usize::try_from(element.min).unwrap_or(0);
+                let max = element.max.and_then(|max| usize::try_from(max).ok());
+                if arr.len() < min || max.is_some_and(|max| arr.len() > max) {
+                    let range = match max {
+                        Some(max) => format!("{min}..{max}"),
+                        None => format!("{min}..*"),
+                    };
+                    errors.push(ValidationError {
+                        error_type: FhirSchemaErrorCode::CardinalityViolation.into(),
+                        path: ErrorPath::new(path),
+                        message: Some(ErrorMessage::template(
+                            "Element '{}' has {} items, expected {}",
+                            [element.name.clone(), arr.len().to_string(), range.clone()],
+                        )),
+                        value: None,
+                        expected: Some(JsonValue::String(range)),
+                        got: Some(JsonValue::from(arr.len())),
+                        schema_path: None,
+                        constraint_key: None,
+                        constraint_expression: None,
+                        constraint_severity: None,
+                        fix: None,
+                        schema_url: None,
+                        schema_version: None,
+                    });
                 }
 
+                // Validate slicing if defined. Items matched to a slice are
+                // validated against the slice's definition below.
+                let classifications = element.slicing.as_ref().and_then(|slicing| {
+                    let classifications = self.classify_items(arr, slicing)?;
+                    self.report_slicing(&classifications, slicing, errors, path);
+                    Some(classifications)
+                });
+
                 // Validate each item. `null` is only valid in parallel primitive-extension
                 // arrays (`_field`); inside a regular value array it is invalid unless
                 // the parallel `_field` array supplies a non-null Element at the same
                 // index (extension-fill pattern).
                 for (i, item) in arr.iter().enumerate() {
-                    let item_path = format!("{}[{}]", path, i);
+                    if items.is_some_and(|items| !items.contains(&i)) {
+                        continue;
+                    }
+                    let item_path = bumpalo::format!(in scratch, "{}[{}]", path, i);
                     if item.is_null() {
                         // null is allowed only when the parallel `_field[i]` is an
                         // Element that actually provides content (extension or any
@@ -1244,11 +1779,11 @@ impl FhirValidator {
                             continue;
                         }
                         errors.push(ValidationError {
-                            error_type: FhirSchemaErrorCode::WrongType.to_string(),
-                            path: self.path_to_vec(&item_path),
-                            message: Some(format!(
+                            error_type: FhirSchemaErrorCode::WrongType.into(),
+                            path: ErrorPath::new(item_path.as_str()),
+                            message: Some(ErrorMessage::template(
                                 "null entries are not allowed in '{}' array",
-                                element.name
+                                [element.name.clone()],
                             )),
                             value: None,
                             expected: None,
@@ -1257,19 +1792,38 @@ impl FhirValidator {
                             constraint_key: None,
                             constraint_expression: None,
                             constraint_severity: None,
+                            fix: None,
+                            schema_url: None,
+                            schema_version: None,
                         });
                         continue;
                     }
-                    self.validate_element_value(item, element, errors, &item_path, root);
+                    let item_element = classifications
+                        .as_ref()
+                        .and_then(|classifications| {
+                            Self::slice_element(element, classifications.get(i)?)
+                        })
+                        .unwrap_or(element);
+                    self.validate_element_value(
+                        item,
+                        item_element,
+                        errors,
+                        &item_path,
+                        root,
+                        scratch,
+                    );
                 }
             }
         } else {
             // `null` for a non-array element is invalid.
             if value.is_null() {
                 errors.push(ValidationError {
-                    error_type: FhirSchemaErrorCode::WrongType.to_string(),
-                    path: self.path_to_vec(path),
-                    message: Some(format!("Element '{}' must not be null", element.name)),
+                    error_type: FhirSchemaErrorCode::WrongType.into(),
+                    path: ErrorPath::new(path),
+                    message: Some(ErrorMessage::template(
+                        "Element '{}' must not be null",
+                        [element.name.clone()],
+                    )),
                     value: None,
                     expected: None,
                     got: Some(JsonValue::String("null".to_string())),
@@ -1277,10 +1831,13 @@ impl FhirValidator {
                     constraint_key: None,
                     constraint_expression: None,
                     constraint_severity: None,
+                    fix: None,
+                    schema_url: None,
+                    schema_version: None,
                 });
                 return;
             }
-            self.validate_element_value(value, element, errors, path, root);
+            self.validate_element_value(value, element, errors, path, root, scratch);
         }
     }
 
@@ -1292,28 +1849,36 @@ impl FhirValidator {
         errors: &mut Vec<ValidationError>,
         path: &str,
         root: &HashMap<String, CompiledElement>,
+        scratch: &Bump,
     ) {
         match &element.type_info {
             CompiledTypeInfo::Primitive(ptype) => {
+                let found = errors.len();
                 self.validate_primitive(value, *ptype, errors, path);
+                // A value already rejected by its type is not matched again
+                if errors.len() == found {
+                    Self::validate_regexes(value, &element.regexes, errors, path);
+                }
             }
             // Nothing is declared about the value's shape here; whichever schema
             // does declare it validates it.
-            CompiledTypeInfo::Unspecified => {}
+            CompiledTypeInfo::Unspecified => {
+                Self::validate_regexes(value, &element.regexes, errors, path);
+            }
             CompiledTypeInfo::Complex | CompiledTypeInfo::BackboneElement => {
                 // Recursively validate using inlined children. When the element
                 // reuses another element's definition via `contentReference`
                 // (its own children are empty), resolve the target element from
-                // the root schema and validate against its children instead.
-                let children = if element.children.is_empty()
+                // the root schema and validate against its definition instead.
+                let definition = if element.children.is_empty()
                     && let Some(target) =
                         Self::resolve_element_reference(root, element.element_reference.as_deref())
                 {
-                    &target.children
+                    target
                 } else {
-                    &element.children
+                    element
                 };
-                self.validate_complex(value, children, errors, path, root);
+                self.validate_complex(value, definition, errors, path, root, scratch);
             }
             CompiledTypeInfo::Reference => {
                 self.validate_reference(value, &element.reference_targets, errors, path);
@@ -1329,6 +1894,49 @@ impl FhirValidator {
         }
     }
 
+    /// Check a primitive value against the regexes of its element and type
+    /// profiles. Values are matched in their string form; objects and arrays
+    /// are left to the type check.
+    fn validate_regexes(
+        value: &JsonValue,
+        regexes: &[String],
+        errors: &mut Vec<ValidationError>,
+        path: &str,
+    ) {
+        if regexes.is_empty() {
+            return;
+        }
+        let text = match value {
+            JsonValue::String(s) => s.clone(),
+            JsonValue::Number(n) => n.to_string(),
+            JsonValue::Bool(b) => b.to_string(),
+            _ => return,
+        };
+        for pattern in regexes {
+            let Some(regex) = profile_regex(pattern) else {
+                continue;
+            };
+            if regex.is_match(&text) {
+                continue;
+            }
+            errors.push(ValidationError {
+                error_type: FhirSchemaErrorCode::InvalidValue.into(),
+                path: ErrorPath::new(path),
+                message: Some(format!("{text:?} does not match the regex {pattern}").into()),
+                value: Some(value.clone()),
+                expected: Some(JsonValue::String(pattern.clone())),
+                got: None,
+                schema_path: None,
+                constraint_key: None,
+                constraint_expression: None,
+                constraint_severity: None,
+                fix: None,
+                schema_url: None,
+                schema_version: None,
+            });
+        }
+    }
+
     /// Validate primitive value
     fn validate_primitive(
         &self,
@@ -1356,12 +1964,11 @@ impl FhirValidator {
 
         if !type_ok {
             errors.push(ValidationError {
-                error_type: FhirSchemaErrorCode::WrongType.to_string(),
-                path: self.path_to_vec(path),
-                message: Some(format!(
+                error_type: FhirSchemaErrorCode::WrongType.into(),
+                path: ErrorPath::new(path),
+                message: Some(ErrorMessage::template(
                     "Expected {} but got {}",
-                    ptype.as_str(),
-                    self.json_type_name(value)
+                    [ptype.as_str(), self.json_type_name(value)],
                 )),
                 value: None,
                 expected: Some(JsonValue::String(ptype.as_str().to_string())),
@@ -1370,6 +1977,9 @@ impl FhirValidator {
                 constraint_key: None,
                 constraint_expression: None,
                 constraint_severity: None,
+                fix: None,
+                schema_url: None,
+                schema_version: None,
             });
             return;
         }
@@ -1496,9 +2106,9 @@ impl FhirValidator {
 
         if let Some(msg) = format_err {
             errors.push(ValidationError {
-                error_type: FhirSchemaErrorCode::InvalidValue.to_string(),
-                path: self.path_to_vec(path),
-                message: Some(msg),
+                error_type: FhirSchemaErrorCode::InvalidValue.into(),
+                path: ErrorPath::new(path),
+                message: Some(msg.into()),
                 value: Some(value.clone()),
                 expected: Some(JsonValue::String(ptype.as_str().to_string())),
                 got: None,
@@ -1506,24 +2116,28 @@ impl FhirValidator {
                 constraint_key: None,
                 constraint_expression: None,
                 constraint_severity: None,
+                fix: None,
+                schema_url: None,
+                schema_version: None,
             });
         }
     }
 
-    /// Validate complex type with children
+    /// Validate complex type against the children of its defining element
     fn validate_complex(
         &self,
         value: &JsonValue,
-        children: &HashMap<String, CompiledElement>,
+        definition: &CompiledElement,
         errors: &mut Vec<ValidationError>,
         path: &str,
         root: &HashMap<String, CompiledElement>,
+        scratch: &Bump,
     ) {
         let JsonValue::Object(obj) = value else {
             errors.push(ValidationError {
-                error_type: FhirSchemaErrorCode::WrongType.to_string(),
-                path: self.path_to_vec(path),
-                message: Some("Expected object".to_string()),
+                error_type: FhirSchemaErrorCode::WrongType.into(),
+                path: ErrorPath::new(path),
+                message: Some("Expected object".into()),
                 value: None,
                 expected: Some(JsonValue::String("object".to_string())),
                 got: Some(JsonValue::String(self.json_type_name(value).to_string())),
@@ -1531,9 +2145,13 @@ impl FhirValidator {
                 constraint_key: None,
                 constraint_expression: None,
                 constraint_severity: None,
+                fix: None,
+                schema_url: None,
+                schema_version: None,
             });
             return;
         };
+        let children = &definition.children;
 
         // FHIR ele-1: complex element must have meaningful content. An object with
         // no entries (or only `id`) violates the constraint and is rejected here so
@@ -1541,9 +2159,9 @@ impl FhirValidator {
         let meaningful = obj.keys().any(|k| k != "id");
         if !meaningful {
             errors.push(ValidationError {
-                error_type: FhirSchemaErrorCode::ConstraintViolation.to_string(),
-                path: self.path_to_vec(path),
-                message: Some("Element must have content (constraint ele-1)".to_string()),
+                error_type: FhirSchemaErrorCode::ConstraintViolation.into(),
+                path: ErrorPath::new(path),
+                message: Some("Element must have content (constraint ele-1)".into()),
                 value: None,
                 expected: None,
                 got: None,
@@ -1553,24 +2171,78 @@ impl FhirValidator {
                     "hasValue() or (children().count() > id.count())".to_string(),
                 ),
                 constraint_severity: Some("error".to_string()),
+                fix: None,
+                schema_url: None,
+                schema_version: None,
             });
             return;
         }
 
+        // Check required children
+        for required in &definition.required {
+            if !obj.contains_key(required) && !self.has_choice_variant(obj, required, children) {
+                errors.push(ValidationError {
+                    error_type: FhirSchemaErrorCode::CardinalityViolation.into(),
+                    path: ErrorPath::new(path),
+                    message: Some(ErrorMessage::template(
+                        "Required element '{}' is missing",
+                        [required.clone()],
+                    )),
+                    value: None,
+                    expected: None,
+                    got: None,
+                    schema_path: None,
+                    constraint_key: None,
+                    constraint_expression: None,
+                    constraint_severity: None,
+                    fix: None,
+                    schema_url: None,
+                    schema_version: None,
+                });
+            }
+        }
+
+        // Check excluded children
+        for excluded in &definition.excluded {
+            if obj.contains_key(excluded) {
+                errors.push(ValidationError {
+                    error_type: FhirSchemaErrorCode::UnknownElement.into(),
+                    path: ErrorPath::new(path),
+                    message: Some(ErrorMessage::template(
+                        "Excluded element '{}' is present",
+                        [excluded.clone()],
+                    )),
+                    value: None,
+                    expected: None,
+                    got: None,
+                    schema_path: None,
+                    constraint_key: None,
+                    constraint_expression: None,
+                    constraint_severity: None,
+                    fix: None,
+                    schema_url: None,
+                    schema_version: None,
+                });
+            }
+        }
+
         // Validate each property
         for (key, val) in obj {
             // Primitive extensions (`_field`): validate shape against the matching
             // sibling primitive element.
             if let Some(sibling) = key.strip_prefix('_') {
-                self.validate_primitive_extension(sibling, val, children, obj, errors, path);
+                self.validate_primitive_extension(
+                    sibling, val, children, obj, errors, path, scratch,
+                );
                 continue;
             }
 
             let display_key = self.choice_display_key(key, children);
-            let element_path = format!("{}.{}", path, display_key);
+            let element_path =
+                bumpalo::format!(in scratch, "{}.{}", path, display_key).into_bump_str();
 
             let underscore_arr = obj
-                .get(&format!("_{}", key))
+                .get(bumpalo::format!(in scratch, "_{}", key).as_str())
                 .and_then(|v| v.as_array())
                 .map(|v| v.as_slice());
 
@@ -1580,8 +2252,10 @@ impl FhirValidator {
                     element,
                     underscore_arr,
                     errors,
-                    &element_path,
+                    element_path,
                     root,
+                    scratch,
+                    None,
                 );
             } else {
                 // Check for choice type variants
@@ -1599,17 +2273,22 @@ impl FhirValidator {
                             stem_element,
                             underscore_arr,
                             errors,
-                            &element_path,
+                            element_path,
                             root,
+                            scratch,
+                            None,
                         );
                     }
                     continue;
                 }
                 if !is_choice && key != "extension" && key != "id" {
                     errors.push(ValidationError {
-                        error_type: FhirSchemaErrorCode::UnknownElement.to_string(),
-                        path: self.path_to_vec(&element_path),
-                        message: Some(format!("Unknown element '{}'", key)),
+                        error_type: FhirSchemaErrorCode::UnknownElement.into(),
+                        path: ErrorPath::new(element_path),
+                        message: Some(ErrorMessage::template(
+                            "Unknown element '{}'",
+                            [key.clone()],
+                        )),
                         value: None,
                         expected: None,
                         got: None,
@@ -1617,25 +2296,31 @@ impl FhirValidator {
                         constraint_key: None,
                         constraint_expression: None,
                         constraint_severity: None,
+                        fix: None,
+                        schema_url: None,
+                        schema_version: None,
                     });
                 }
             }
         }
     }
 
-    /// Validate Reference element
+    /// Validate Reference element: its shape, and the type of the resource it
+    /// points to against the element's target types. Whether the target
+    /// exists is checked in Phase 4, and conformance to target profiles in
+    /// Phase 4b.
     fn validate_reference(
         &self,
         value: &JsonValue,
-        _targets: &Option<Vec<String>>,
+        targets: &Option<Vec<String>>,
         errors: &mut Vec<ValidationError>,
         path: &str,
     ) {
         let JsonValue::Object(obj) = value else {
             errors.push(ValidationError {
-                error_type: FhirSchemaErrorCode::WrongType.to_string(),
-                path: self.path_to_vec(path),
-                message: Some("Reference must be an object".to_string()),
+                error_type: FhirSchemaErrorCode::WrongType.into(),
+                path: ErrorPath::new(path),
+                message: Some("Reference must be an object".into()),
                 value: None,
                 expected: None,
                 got: None,
@@ -1643,6 +2328,9 @@ impl FhirValidator {
                 constraint_key: None,
                 constraint_expression: None,
                 constraint_severity: None,
+                fix: None,
+                schema_url: None,
+                schema_version: None,
             });
             return;
         };
@@ -1654,11 +2342,10 @@ impl FhirValidator {
 
         if !has_reference && !has_identifier && !has_display {
             errors.push(ValidationError {
-                error_type: FhirSchemaErrorCode::CardinalityViolation.to_string(),
-                path: self.path_to_vec(path),
+                error_type: FhirSchemaErrorCode::CardinalityViolation.into(),
+                path: ErrorPath::new(path),
                 message: Some(
-                    "Reference must have at least one of: reference, identifier, display"
-                        .to_string(),
+                    "Reference must have at least one of: reference, identifier, display".into(),
                 ),
                 value: None,
                 expected: None,
@@ -1667,10 +2354,74 @@ impl FhirValidator {
                 constraint_key: None,
                 constraint_expression: None,
                 constraint_severity: None,
+                fix: None,
+                schema_url: None,
+                schema_version: None,
+            });
+        }
+
+        let Some(targets) = targets else { return };
+        let Some(allowed) = Self::reference_target_types(targets) else {
+            return;
+        };
+        // The referenced type, from a literal `reference` or else from `type`
+        let (resource_type, type_path) = match obj.get("reference").and_then(|v| v.as_str()) {
+            Some(reference) => (reference_resource_type(reference), "reference"),
+            None => (
+                obj.get("type")
+                    .and_then(|v| v.as_str())
+                    .filter(|t| !t.contains(['/', ':']))
+                    .map(str::to_string),
+                "type",
+            ),
+        };
+        let Some(resource_type) = resource_type else {
+            return;
+        };
+        if !allowed.iter().any(|t| *t == resource_type) {
+            errors.push(ValidationError {
+                error_type: FhirSchemaErrorCode::ReferenceTypeViolation.into(),
+                path: ErrorPath::from(format!("{}.{}", path, type_path)),
+                message: Some(ErrorMessage::template(
+                    "Reference to {} is not allowed here; expected {}",
+                    [resource_type.to_string(), allowed.join(" | ")],
+                )),
+                value: None,
+                expected: Some(JsonValue::Array(
+                    allowed.iter().map(|t| JsonValue::from(*t)).collect(),
+                )),
+                got: Some(JsonValue::String(resource_type)),
+                schema_path: None,
+                constraint_key: None,
+                constraint_expression: None,
+                constraint_severity: None,
+                fix: None,
+                schema_url: None,
+                schema_version: None,
             });
         }
     }
 
+    /// Resource types a Reference with `targets` may point to, or `None`
+    /// when any type is allowed or a target is a profile whose type is only
+    /// known once it is loaded (left to the targetProfile check).
+    fn reference_target_types(targets: &[String]) -> Option<Vec<&str>> {
+        const CORE: &str = "http://hl7.org/fhir/StructureDefinition/";
+        let mut types = Vec::with_capacity(targets.len());
+        for target in targets {
+            let target = target.split('|').next().unwrap_or(target);
+            // Core profiles such as `vitalsigns` are lower-case; types are not
+            let name = target
+                .strip_prefix(CORE)
+                .filter(|name| name.starts_with(|c: char| c.is_ascii_uppercase()))?;
+            if name == "Resource" || name == "DomainResource" {
+                return None;
+            }
+            types.push(name);
+        }
+        (!types.is_empty()).then_some(types)
+    }
+
     /// Validate contained resource
     fn validate_contained_resource(
         &self,
@@ -1680,9 +2431,9 @@ impl FhirValidator {
     ) {
         let JsonValue::Object(obj) = value else {
             errors.push(ValidationError {
-                error_type: FhirSchemaErrorCode::WrongType.to_string(),
-                path: self.path_to_vec(path),
-                message: Some("Contained resource must be an object".to_string()),
+                error_type: FhirSchemaErrorCode::WrongType.into(),
+                path: ErrorPath::new(path),
+                message: Some("Contained resource must be an object".into()),
                 value: None,
                 expected: None,
                 got: None,
@@ -1690,6 +2441,9 @@ impl FhirValidator {
                 constraint_key: None,
                 constraint_expression: None,
                 constraint_severity: None,
+                fix: None,
+                schema_url: None,
+                schema_version: None,
             });
             return;
         };
@@ -1697,9 +2451,9 @@ impl FhirValidator {
         // Get resourceType
         let Some(resource_type) = obj.get("resourceType").and_then(|v| v.as_str()) else {
             errors.push(ValidationError {
-                error_type: FhirSchemaErrorCode::CardinalityViolation.to_string(),
-                path: self.path_to_vec(path),
-                message: Some("Contained resource must have resourceType".to_string()),
+                error_type: FhirSchemaErrorCode::CardinalityViolation.into(),
+                path: ErrorPath::new(path),
+                message: Some("Contained resource must have resourceType".into()),
                 value: None,
                 expected: None,
                 got: None,
@@ -1707,6 +2461,9 @@ impl FhirValidator {
                 constraint_key: None,
                 constraint_expression: None,
                 constraint_severity: None,
+                fix: None,
+                schema_url: None,
+                schema_version: None,
             });
             return;
         };
@@ -1714,9 +2471,9 @@ impl FhirValidator {
         // Contained resources cannot have contained (per FHIR spec)
         if obj.contains_key("contained") {
             errors.push(ValidationError {
-                error_type: FhirSchemaErrorCode::UnknownElement.to_string(),
-                path: self.path_to_vec(path),
-                message: Some("Contained resources cannot have nested contained".to_string()),
+                error_type: FhirSchemaErrorCode::UnknownElement.into(),
+                path: ErrorPath::new(path),
+                message: Some("Contained resources cannot have nested contained".into()),
                 value: None,
                 expected: None,
                 got: None,
@@ -1724,6 +2481,9 @@ impl FhirValidator {
                 constraint_key: None,
                 constraint_expression: None,
                 constraint_severity: None,
+                fix: None,
+                schema_url: None,
+                schema_version: None,
             });
         }
 
@@ -1737,9 +2497,9 @@ impl FhirValidator {
     fn validate_extension(&self, value: &JsonValue, errors: &mut Vec<ValidationError>, path: &str) {
         let JsonValue::Object(obj) = value else {
             errors.push(ValidationError {
-                error_type: FhirSchemaErrorCode::WrongType.to_string(),
-                path: self.path_to_vec(path),
-                message: Some("Extension must be an object".to_string()),
+                error_type: FhirSchemaErrorCode::WrongType.into(),
+                path: ErrorPath::new(path),
+                message: Some("Extension must be an object".into()),
                 value: None,
                 expected: None,
                 got: None,
@@ -1747,6 +2507,9 @@ impl FhirValidator {
                 constraint_key: None,
                 constraint_expression: None,
                 constraint_severity: None,
+                fix: None,
+                schema_url: None,
+                schema_version: None,
             });
             return;
         };
@@ -1754,9 +2517,9 @@ impl FhirValidator {
         // Extension must have url
         if !obj.contains_key("url") {
             errors.push(ValidationError {
-                error_type: FhirSchemaErrorCode::CardinalityViolation.to_string(),
-                path: self.path_to_vec(path),
-                message: Some("Extension must have url".to_string()),
+                error_type: FhirSchemaErrorCode::CardinalityViolation.into(),
+                path: ErrorPath::new(path),
+                message: Some("Extension must have url".into()),
                 value: None,
                 expected: None,
                 got: None,
@@ -1764,6 +2527,9 @@ impl FhirValidator {
                 constraint_key: None,
                 constraint_expression: None,
                 constraint_severity: None,
+                fix: None,
+                schema_url: None,
+                schema_version: None,
             });
         }
     }
@@ -1812,17 +2578,6 @@ impl FhirValidator {
         Some(current)
     }
 
-    /// Convert path string to vector for ValidationError
-    fn path_to_vec(&self, path: &str) -> Vec<JsonValue> {
-        if path.is_empty() {
-            vec![]
-        } else {
-            path.split('.')
-                .map(|s| JsonValue::String(s.to_string()))
-                .collect()
-        }
-    }
-
     /// Validate a primitive extension property `_field`. `sibling` is the
     /// stripped key (e.g. `"active"` for `_active`). The matching schema
     /// element must exist, be primitive, and the value must be Element-shaped
@@ -1835,20 +2590,13 @@ impl FhirValidator {
         _parent_obj: &serde_json::Map<std::string::String, JsonValue>,
         errors: &mut Vec<ValidationError>,
         parent_path: &str,
+        scratch: &Bump,
     ) {
-        let underscore_path = if parent_path.is_empty() {
-            format!("_{}", sibling)
-        } else {
-            format!("{}._{}", parent_path, sibling)
-        };
+        let display_key = self.choice_display_key(sibling, elements);
         let display_path = if parent_path.is_empty() {
-            self.choice_display_key(sibling, elements)
+            &*scratch.alloc_str(&display_key)
         } else {
-            format!(
-                "{}.{}",
-                parent_path,
-                self.choice_display_key(sibling, elements)
-            )
+            bumpalo::format!(in scratch, "{}.{}", parent_path, display_key).into_bump_str()
         };
 
         // Find the sibling element: direct lookup, then choice variant.
@@ -1862,11 +2610,15 @@ impl FhirValidator {
 
         let Some(element) = element_opt else {
             errors.push(ValidationError {
-                error_type: FhirSchemaErrorCode::UnknownElement.to_string(),
-                path: self.path_to_vec(&underscore_path),
-                message: Some(format!(
+                error_type: FhirSchemaErrorCode::UnknownElement.into(),
+                path: if parent_path.is_empty() {
+                    ErrorPath::from(format!("_{}", sibling))
+                } else {
+                    ErrorPath::from(format!("{}._{}", parent_path, sibling))
+                },
+                message: Some(ErrorMessage::template(
                     "Primitive extension '_{}' has no matching sibling element",
-                    sibling
+                    [sibling.to_string()],
                 )),
                 value: None,
                 expected: None,
@@ -1875,6 +2627,9 @@ impl FhirValidator {
                 constraint_key: None,
                 constraint_expression: None,
                 constraint_severity: None,
+                fix: None,
+                schema_url: None,
+                schema_version: None,
             });
             return;
         };
@@ -1887,11 +2642,11 @@ impl FhirValidator {
             CompiledTypeInfo::Primitive(_) | CompiledTypeInfo::Unspecified
         ) {
             errors.push(ValidationError {
-                error_type: FhirSchemaErrorCode::WrongType.to_string(),
-                path: self.path_to_vec(&display_path),
-                message: Some(format!(
+                error_type: FhirSchemaErrorCode::WrongType.into(),
+                path: ErrorPath::new(display_path),
+                message: Some(ErrorMessage::template(
                     "Primitive extension '_{}' only valid on primitive elements",
-                    sibling
+                    [sibling.to_string()],
                 )),
                 value: None,
                 expected: None,
@@ -1900,6 +2655,9 @@ impl FhirValidator {
                 constraint_key: None,
                 constraint_expression: None,
                 constraint_severity: None,
+                fix: None,
+                schema_url: None,
+                schema_version: None,
             });
             return;
         }
@@ -1909,11 +2667,11 @@ impl FhirValidator {
         if element.is_array {
             let JsonValue::Array(arr) = value else {
                 errors.push(ValidationError {
-                    error_type: FhirSchemaErrorCode::ExpectedArray.to_string(),
-                    path: self.path_to_vec(&display_path),
-                    message: Some(format!(
+                    error_type: FhirSchemaErrorCode::ExpectedArray.into(),
+                    path: ErrorPath::new(display_path),
+                    message: Some(ErrorMessage::template(
                         "_{} must be an array (sibling primitive is repeating)",
-                        sibling
+                        [sibling.to_string()],
                     )),
                     value: None,
                     expected: Some(JsonValue::String("array".to_string())),
@@ -1922,24 +2680,27 @@ impl FhirValidator {
                     constraint_key: None,
                     constraint_expression: None,
                     constraint_severity: None,
+                    fix: None,
+                    schema_url: None,
+                    schema_version: None,
                 });
                 return;
             };
             for (i, item) in arr.iter().enumerate() {
-                let item_path = format!("{}[{}]", display_path, i);
                 if item.is_null() {
                     continue;
                 }
+                let item_path = bumpalo::format!(in scratch, "{}[{}]", display_path, i);
                 self.validate_element_object(item, &item_path, errors);
             }
         } else {
             if value.is_array() {
                 errors.push(ValidationError {
-                    error_type: FhirSchemaErrorCode::UnexpectedArray.to_string(),
-                    path: self.path_to_vec(&display_path),
-                    message: Some(format!(
+                    error_type: FhirSchemaErrorCode::UnexpectedArray.into(),
+                    path: ErrorPath::new(display_path),
+                    message: Some(ErrorMessage::template(
                         "_{} must be an Element object, not an array (sibling primitive is scalar)",
-                        sibling
+                        [sibling.to_string()],
                     )),
                     value: None,
                     expected: Some(JsonValue::String("object".to_string())),
@@ -1948,10 +2709,13 @@ impl FhirValidator {
                     constraint_key: None,
                     constraint_expression: None,
                     constraint_severity: None,
+                    fix: None,
+                    schema_url: None,
+                    schema_version: None,
                 });
                 return;
             }
-            self.validate_element_object(value, &display_path, errors);
+            self.validate_element_object(value, display_path, errors);
         }
     }
 
@@ -1965,9 +2729,9 @@ impl FhirValidator {
     ) {
         let JsonValue::Object(obj) = value else {
             errors.push(ValidationError {
-                error_type: FhirSchemaErrorCode::WrongType.to_string(),
-                path: self.path_to_vec(path),
-                message: Some("Element subpart must be an object with id/extension".to_string()),
+                error_type: FhirSchemaErrorCode::WrongType.into(),
+                path: ErrorPath::new(path),
+                message: Some("Element subpart must be an object with id/extension".into()),
                 value: None,
                 expected: Some(JsonValue::String("object".to_string())),
                 got: Some(JsonValue::String(self.json_type_name(value).to_string())),
@@ -1975,14 +2739,17 @@ impl FhirValidator {
                 constraint_key: None,
                 constraint_expression: None,
                 constraint_severity: None,
+                fix: None,
+                schema_url: None,
+                schema_version: None,
             });
             return;
         };
         if obj.is_empty() {
             errors.push(ValidationError {
-                error_type: FhirSchemaErrorCode::ConstraintViolation.to_string(),
-                path: self.path_to_vec(path),
-                message: Some("Element subpart must have content (id or extension)".to_string()),
+                error_type: FhirSchemaErrorCode::ConstraintViolation.into(),
+                path: ErrorPath::new(path),
+                message: Some("Element subpart must have content (id or extension)".into()),
                 value: None,
                 expected: None,
                 got: None,
@@ -1992,17 +2759,20 @@ impl FhirValidator {
                     "hasValue() or (children().count() > id.count())".to_string(),
                 ),
                 constraint_severity: Some("error".to_string()),
+                fix: None,
+                schema_url: None,
+                schema_version: None,
             });
             return;
         }
         for k in obj.keys() {
             if k != "id" && k != "extension" {
                 errors.push(ValidationError {
-                    error_type: FhirSchemaErrorCode::UnknownElement.to_string(),
-                    path: self.path_to_vec(path),
-                    message: Some(format!(
+                    error_type: FhirSchemaErrorCode::UnknownElement.into(),
+                    path: ErrorPath::new(path),
+                    message: Some(ErrorMessage::template(
                         "Unknown key '{}' in Element (allowed: id, extension)",
-                        k
+                        [k.to_string()],
                     )),
                     value: None,
                     expected: None,
@@ -2011,6 +2781,9 @@ impl FhirValidator {
                     constraint_key: None,
                     constraint_expression: None,
                     constraint_severity: None,
+                    fix: None,
+                    schema_url: None,
+                    schema_version: None,
                 });
             }
         }
@@ -2019,11 +2792,11 @@ impl FhirValidator {
     /// FHIRPath display for a choice variant element: `valueBoolean` →
     /// `value.ofType(boolean)`. Returns the input key unchanged if it isn't a
     /// choice variant of any element in `elements`.
-    fn choice_display_key(
+    fn choice_display_key<'k>(
         &self,
-        key: &str,
+        key: &'k str,
         elements: &HashMap<std::string::String, CompiledElement>,
-    ) -> std::string::String {
+    ) -> Cow<'k, str> {
         for el in elements.values() {
             if let Some(choices) = el.choices.as_ref()
                 && choices.iter().any(|c| c == key)
@@ -2037,11 +2810,11 @@ impl FhirValidator {
                         std::string::String::with_capacity(suffix.len());
                     lower_type.push(lower_first);
                     lower_type.push_str(chars.as_str());
-                    return format!("{}.ofType({})", el.name, lower_type);
+                    return Cow::Owned(format!("{}.ofType({})", el.name, lower_type));
                 }
             }
         }
-        key.to_string()
+        Cow::Borrowed(key)
     }
 
     /// Get JSON type name for error messages
@@ -2182,12 +2955,9 @@ impl FhirValidator {
             if let Some(&satisfied) = cache.get(&key) {
                 if !satisfied {
                     errors.push(ValidationError {
-                        error_type: FhirSchemaErrorCode::ConstraintViolation.to_string(),
-                        path: self.path_to_vec(path),
-                        message: Some(format!(
-                            "Constraint '{}' failed: {}",
-                            constraint.key, constraint.human
-                        )),
+                        error_type: FhirSchemaErrorCode::ConstraintViolation.into(),
+                        path: ErrorPath::new(path),
+                        message: Some(Self::constraint_failure_message(constraint).into()),
                         value: None,
                         expected: None,
                         got: None,
@@ -2195,15 +2965,18 @@ impl FhirValidator {
                         constraint_key: Some(constraint.key.clone()),
                         constraint_expression: Some(constraint.expression.clone()),
                         constraint_severity: Some("error".to_string()),
+                        fix: None,
+                        schema_url: None,
+                        schema_version: None,
                     });
                 }
             } else if let Some(err_msg) = eval_errors.get(&key) {
                 errors.push(ValidationError {
-                    error_type: FhirSchemaErrorCode::ConstraintViolation.to_string(),
-                    path: self.path_to_vec(path),
-                    message: Some(format!(
+                    error_type: FhirSchemaErrorCode::ConstraintViolation.into(),
+                    path: ErrorPath::new(path),
+                    message: Some(ErrorMessage::template(
                         "Constraint '{}' evaluation failed: {}",
-                        constraint.key, err_msg
+                        [constraint.key.clone(), err_msg.clone()],
                     )),
                     value: None,
                     expected: None,
@@ -2212,16 +2985,36 @@ impl FhirValidator {
                     constraint_key: Some(constraint.key.clone()),
                     constraint_expression: Some(constraint.expression.clone()),
                     constraint_severity: Some("error".to_string()),
+                    fix: None,
+                    schema_url: None,
+                    schema_version: None,
                 });
             }
         }
     }
 
+    /// Message for a failed constraint, naming the elements whose
+    /// `condition` ties them to it so the failure can be located.
+    fn constraint_failure_message(constraint: &compiled::CompiledConstraint) -> String {
+        let mut message = format!(
+            "Constraint '{}' failed: {}",
+            constraint.key, constraint.human
+        );
+        if !constraint.implicated.is_empty() {
+            message.push_str(&format!(
+                " (involves: {})",
+                constraint.implicated.join(", ")
+            ));
+        }
+        message
+    }
+
     /// Recursively validate constraints for a resource and all its elements.
     ///
     /// This walks through the compiled schema and evaluates constraints at each level:
     /// - Schema-level constraints on the resource itself
     /// - Element-level constraints on each field
+    #[allow(clippy::too_many_arguments)]
     #[async_recursion::async_recursion]
     async fn validate_constraints_recursive(
         &self,
@@ -2230,7 +3023,8 @@ impl FhirValidator {
         variables: &HashMap<String, Arc<JsonValue>>,
         errors: &mut Vec<ValidationError>,
         path: &str,
-        cache: &mut HashMap<String, bool>,
+        cache: &mut ConstraintMemo,
+        scope: Option<&RevalidationScope>,
     ) {
         // Validate schema-level constraints. `data` is the resource root, which
         // is also stored as the `%rootResource` variable — reuse that Arc to
@@ -2245,7 +3039,7 @@ impl FhirValidator {
             // The resource root describes itself through `resourceType`.
             None,
             root_arc,
-            cache,
+            &mut cache.constraints,
         )
         .await;
 
@@ -2259,6 +3053,12 @@ impl FhirValidator {
                 continue;
             }
 
+            let items = match scope.map(|scope| scope.branch(key)) {
+                Some(Some(branch)) => branch.items(),
+                Some(None) => continue,
+                None => None,
+            };
+
             if let Some(element) = schema.elements.get(key) {
                 let element_path = if path.is_empty() {
                     key.clone()
@@ -2266,15 +3066,48 @@ impl FhirValidator {
                     format!("{}.{}", path, key)
                 };
 
-                self.validate_element_constraints(
-                    value,
-                    element,
-                    variables,
-                    errors,
-                    &element_path,
-                    cache,
-                )
-                .await;
+                match (items, value) {
+                    (Some(items), JsonValue::Array(arr)) => {
+                        let classifications = self
+                            .classify_for_constraints(
+                                arr,
+                                element,
+                                variables,
+                                errors,
+                                &element_path,
+                            )
+                            .await;
+                        for i in items.iter().copied().filter(|i| *i < arr.len()) {
+                            let item_path = format!("{}[{}]", element_path, i);
+                            let item_element = classifications
+                                .as_ref()
+                                .and_then(|classifications| {
+                                    Self::slice_element(element, classifications.get(i)?)
+                                })
+                                .unwrap_or(element);
+                            self.validate_single_element_constraints(
+                                &arr[i],
+                                item_element,
+                                variables,
+                                errors,
+                                &item_path,
+                                cache,
+                            )
+                            .await;
+                        }
+                    }
+                    _ => {
+                        self.validate_element_constraints(
+                            value,
+                            element,
+                            variables,
+                            errors,
+                            &element_path,
+                            cache,
+                        )
+                        .await;
+                    }
+                }
             }
         }
     }
@@ -2288,14 +3121,29 @@ impl FhirValidator {
         variables: &HashMap<String, Arc<JsonValue>>,
         errors: &mut Vec<ValidationError>,
         path: &str,
-        cache: &mut HashMap<String, bool>,
+        cache: &mut ConstraintMemo,
     ) {
         // Handle arrays
         if let JsonValue::Array(arr) = value {
+            // Items matched to a slice carry the slice's constraints too
+            let classifications = self
+                .classify_for_constraints(arr, element, variables, errors, path)
+                .await;
             for (i, item) in arr.iter().enumerate() {
                 let item_path = format!("{}[{}]", path, i);
+                let item_element = classifications
+                    .as_ref()
+                    .and_then(|classifications| {
+                        Self::slice_element(element, classifications.get(i)?)
+                    })
+                    .unwrap_or(element);
                 self.validate_single_element_constraints(
-                    item, element, variables, errors, &item_path, cache,
+                    item,
+                    item_element,
+                    variables,
+                    errors,
+                    &item_path,
+                    cache,
                 )
                 .await;
             }
@@ -2316,8 +3164,20 @@ impl FhirValidator {
         variables: &HashMap<String, Arc<JsonValue>>,
         errors: &mut Vec<ValidationError>,
         path: &str,
-        cache: &mut HashMap<String, bool>,
+        cache: &mut ConstraintMemo,
     ) {
+        // A resource nested in this one (contained, `Bundle.entry.resource`,
+        // `Parameters.parameter.resource`) is the `%resource` of everything
+        // below it.
+        let nested;
+        let (variables, data_arc_hint) =
+            if value.get("resourceType").is_some_and(JsonValue::is_string) {
+                nested = Self::nested_resource_variables(variables, value, path);
+                (&nested, nested.get("resource").cloned())
+            } else {
+                (variables, None)
+            };
+
         // Validate element-level constraints
         self.validate_constraints(
             value,
@@ -2326,13 +3186,14 @@ impl FhirValidator {
             errors,
             path,
             element.context_type(),
-            None,
-            cache,
+            data_arc_hint,
+            &mut cache.constraints,
         )
         .await;
 
         // Validate required ValueSet bindings via the terminology service.
-        self.validate_binding(value, element, errors, path).await;
+        self.validate_binding(value, element, errors, path, &mut cache.bindings)
+            .await;
 
         // Recurse into children for complex types
         if let JsonValue::Object(obj) = value {
@@ -2362,22 +3223,33 @@ impl FhirValidator {
     /// `value[x]` choice is checked against the profile's allowed choice
     /// variants; mismatches emit `WrongType` errors. Missing/unresolvable
     /// profiles are silently ignored to avoid noise when packages are partial.
+    ///
+    /// With a `scope`, only its dirty branches of the resource are walked.
     #[async_recursion::async_recursion]
     async fn validate_extensions_recursive(
         &self,
         value: &JsonValue,
         errors: &mut Vec<ValidationError>,
         path: &str,
+        scope: Option<&RevalidationScope>,
     ) {
         match value {
             JsonValue::Object(obj) => {
                 if let Some(JsonValue::Array(exts)) = obj.get("extension") {
                     for (i, ext) in exts.iter().enumerate() {
+                        if scope.is_some_and(|scope| !scope.is_dirty_item("extension", i)) {
+                            continue;
+                        }
                         let ext_path = format!("{}.extension[{}]", path, i);
                         self.validate_one_extension(ext, errors, &ext_path).await;
                     }
                 }
                 for (k, v) in obj {
+                    if let Some(scope) = scope
+                        && scope.branch(k).is_none()
+                    {
+                        continue;
+                    }
                     let child_path = if path.is_empty() {
                         k.clone()
                     } else if k.starts_with('_') {
@@ -2388,14 +3260,14 @@ impl FhirValidator {
                     } else {
                         format!("{}.{}", path, k)
                     };
-                    self.validate_extensions_recursive(v, errors, &child_path)
+                    self.validate_extensions_recursive(v, errors, &child_path, None)
                         .await;
                 }
             }
             JsonValue::Array(arr) => {
                 for (i, item) in arr.iter().enumerate() {
                     let item_path = format!("{}[{}]", path, i);
-                    self.validate_extensions_recursive(item, errors, &item_path)
+                    self.validate_extensions_recursive(item, errors, &item_path, None)
                         .await;
                 }
             }
@@ -2447,11 +3319,11 @@ impl FhirValidator {
         if !allowed.iter().any(|a| a == used_key) {
             let allowed_list = allowed.join(", ");
             errors.push(ValidationError {
-                error_type: FhirSchemaErrorCode::WrongType.to_string(),
-                path: self.path_to_vec(&format!("{}.{}", path, used_key)),
-                message: Some(format!(
+                error_type: FhirSchemaErrorCode::WrongType.into(),
+                path: ErrorPath::from(format!("{}.{}", path, used_key)),
+                message: Some(ErrorMessage::template(
                     "Extension {} does not allow {}; allowed value[x]: [{}]",
-                    url, used_key, allowed_list
+                    [url.to_string(), used_key.to_string(), allowed_list.clone()],
                 )),
                 value: None,
                 expected: Some(JsonValue::String(allowed_list)),
@@ -2460,6 +3332,9 @@ impl FhirValidator {
                 constraint_key: None,
                 constraint_expression: None,
                 constraint_severity: Some("error".to_string()),
+                fix: None,
+                schema_url: Some(compiled.url.clone()),
+                schema_version: compiled.version.clone(),
             });
         }
     }
@@ -2467,14 +3342,19 @@ impl FhirValidator {
     /// Validate a code value against its bound ValueSet via the configured
     /// `TerminologyService`. Only `required` bindings trigger a hard error
     /// here; weaker strengths (extensible/preferred/example) are advisory and
-    /// left to other checks. If no terminology service is configured, this
-    /// silently no-ops — callers wire one via `with_terminology_service`.
+    /// left to other checks. Value sets unknown to the terminology service are
+    /// skipped, so out of the box only the embedded core value sets apply.
+    ///
+    /// Results are memoized in the per-validate `cache`, keyed by value set,
+    /// system and code, so a code bound by several overlapping schemas or
+    /// repeated across the resource is looked up once.
     async fn validate_binding(
         &self,
         value: &JsonValue,
         element: &compiled::CompiledElement,
         errors: &mut Vec<ValidationError>,
         path: &str,
+        cache: &mut HashMap<BindingKey, bool>,
     ) {
         let Some(binding) = &element.binding else {
             return;
@@ -2527,19 +3407,33 @@ impl FhirValidator {
         }
 
         for (code, system, code_path) in codes {
-            match terminology
-                .validate_code(&binding.value_set, &code, system.as_deref())
-                .await
-            {
-                Ok(result) if !result.valid => {
-                    let msg = format!(
+            let key = (binding.value_set.clone(), system.clone(), code.clone());
+            let valid = match cache.get(&key) {
+                Some(&valid) => Some(valid),
+                None => match terminology
+                    .validate_code(&binding.value_set, &code, system.as_deref())
+                    .await
+                {
+                    Ok(result) => {
+                        cache.insert(key, result.valid);
+                        Some(result.valid)
+                    }
+                    // Lookup failure (unknown ValueSet, transport error, etc.):
+                    // leave as advisory rather than hard error to avoid false
+                    // negatives when the terminology backend is incomplete.
+                    Err(_) => None,
+                },
+            };
+            match valid {
+                Some(false) => {
+                    let msg = ErrorMessage::template(
                         "Code '{}' is not valid in required ValueSet {}",
-                        code, binding.value_set
+                        [code.clone(), binding.value_set.clone()],
                     );
                     errors.push(ValidationError {
-                        error_type: FhirSchemaErrorCode::BindingViolation.to_string(),
-                        path: self.path_to_vec(&code_path),
-                        message: Some(msg),
+                        error_type: FhirSchemaErrorCode::BindingViolation.into(),
+                        path: ErrorPath::new(&code_path),
+                        message: Some(msg.into()),
                         value: Some(JsonValue::String(code.clone())),
                         expected: Some(JsonValue::String(binding.value_set.clone())),
                         got: Some(JsonValue::String(code.clone())),
@@ -2547,14 +3441,12 @@ impl FhirValidator {
                         constraint_key: None,
                         constraint_expression: None,
                         constraint_severity: Some("error".to_string()),
+                        fix: None,
+                        schema_url: None,
+                        schema_version: None,
                     });
                 }
-                Ok(_) => {}
-                Err(_) => {
-                    // Lookup failure (unknown ValueSet, transport error, etc.): leave
-                    // as advisory rather than hard error to avoid false negatives when
-                    // the terminology backend is incomplete.
-                }
+                Some(true) | None => {}
             }
         }
     }
@@ -2621,40 +3513,37 @@ impl FhirValidator {
         }
     }
 
-    /// Classify an array item against slice definitions.
+    /// Classify an array item against the slices of `slicing`.
     ///
-    /// Returns which slice(s) the item matches based on match patterns.
+    /// Items are compared with each slice's match pattern on the values at
+    /// the discriminator paths, or on the whole pattern when the
+    /// discriminators do not constrain the slice. Discriminator paths that
+    /// need FHIRPath fall back to the whole pattern here.
     pub fn classify_slice(
         &self,
         item: &JsonValue,
-        slices: &HashMap<String, compiled::CompiledSlice>,
+        slicing: &compiled::CompiledSlicing,
     ) -> compiled::SliceClassification {
-        let mut matched_slices: Vec<String> = Vec::new();
-
-        for (slice_name, slice_def) in slices {
-            let matches = match &slice_def.match_value {
-                None => true, // No pattern = unconditional match (catch-all)
-                Some(pattern) => {
-                    if let JsonValue::Object(obj) = pattern {
-                        if obj.is_empty() {
-                            true
-                        } else {
-                            Self::deep_partial_match(item, pattern)
-                        }
-                    } else {
-                        Self::deep_partial_match(item, pattern)
-                    }
-                }
-            };
+        let discriminators = Discriminators::new(slicing);
+        Self::classify_discriminated(item, slicing, &discriminators, &HashMap::new())
+    }
 
-            if matches {
-                matched_slices.push(slice_name.clone());
-            }
-        }
+    fn classify_discriminated(
+        item: &JsonValue,
+        slicing: &compiled::CompiledSlicing,
+        discriminators: &Discriminators<'_>,
+        evaluated: &HashMap<String, bool>,
+    ) -> compiled::SliceClassification {
+        let mut matched_slices: Vec<String> = slicing
+            .slices
+            .iter()
+            .filter(|(_, slice_def)| discriminators.matches(item, slice_def, evaluated))
+            .map(|(slice_name, _)| slice_name.clone())
+            .collect();
 
         match matched_slices.len() {
             0 => compiled::SliceClassification::Unmatched,
-            1 => compiled::SliceClassification::Matched(matched_slices.into_iter().next().unwrap()),
+            1 => compiled::SliceClassification::Matched(matched_slices.remove(0)),
             _ => compiled::SliceClassification::Ambiguous(matched_slices),
         }
     }
@@ -2662,6 +3551,8 @@ impl FhirValidator {
     /// Validate slicing for an array element.
     ///
     /// Classifies items, validates cardinality, and enforces slicing rules.
+    /// Slicings with discriminator paths that need FHIRPath are left to
+    /// [`Self::validate_slicing_fhirpath`] when an evaluator is configured.
     pub fn validate_slicing(
         &self,
         items: &[JsonValue],
@@ -2669,10 +3560,154 @@ impl FhirValidator {
         errors: &mut Vec<ValidationError>,
         element_path: &str,
     ) {
+        if let Some(classifications) = self.classify_items(items, slicing) {
+            self.report_slicing(&classifications, slicing, errors, element_path);
+        }
+    }
+
+    /// Classify `items` against the slices of `slicing`, or `None` when there
+    /// are no slices or the discriminators are left to
+    /// [`Self::validate_slicing_fhirpath`].
+    ///
+    /// In the latter case the structural phase validates every item against
+    /// the sliced element itself: it runs before the evaluator, so the
+    /// slice's own element definitions (required children, patterns, types)
+    /// are not checked on those items. The slice's constraints are, in the
+    /// constraint phase, from the FHIRPath classification.
+    fn classify_items(
+        &self,
+        items: &[JsonValue],
+        slicing: &compiled::CompiledSlicing,
+    ) -> Option<Vec<compiled::SliceClassification>> {
         if slicing.slices.is_empty() {
-            return;
+            return None;
+        }
+
+        let discriminators = Discriminators::new(slicing);
+        if discriminators.needs_fhirpath() && self.fhirpath_evaluator.is_some() {
+            return None;
         }
+        let evaluated = HashMap::new();
+        Some(
+            items
+                .iter()
+                .map(|item| {
+                    Self::classify_discriminated(item, slicing, &discriminators, &evaluated)
+                })
+                .collect(),
+        )
+    }
 
+    /// The compiled definition of the slice an item was classified into, if
+    /// it has one.
+    fn slice_element<'a>(
+        element: &'a compiled::CompiledElement,
+        classification: &compiled::SliceClassification,
+    ) -> Option<&'a compiled::CompiledElement> {
+        let compiled::SliceClassification::Matched(slice_name) = classification else {
+            return None;
+        };
+        element
+            .slicing
+            .as_ref()?
+            .slices
+            .get(slice_name)?
+            .schema
+            .as_deref()
+    }
+
+    /// Validate slicing whose discriminator paths need FHIRPath (e.g.
+    /// `reference.resolve()` or `coding.where(...)`), evaluating them on each
+    /// item. Runs in the constraint phase, where the evaluator is available.
+    ///
+    /// Returns the classification of each item, or `None` when the slicing
+    /// was classified natively by [`Self::classify_items`].
+    async fn validate_slicing_fhirpath(
+        &self,
+        items: &[JsonValue],
+        element: &compiled::CompiledElement,
+        variables: &HashMap<String, Arc<JsonValue>>,
+        errors: &mut Vec<ValidationError>,
+        element_path: &str,
+    ) -> Option<Vec<compiled::SliceClassification>> {
+        let (Some(evaluator), Some(slicing)) = (&self.fhirpath_evaluator, &element.slicing) else {
+            return None;
+        };
+        let discriminators = Discriminators::new(slicing);
+        if slicing.slices.is_empty() || !discriminators.needs_fhirpath() {
+            return None;
+        }
+
+        let mut checks: Vec<String> = Vec::new();
+        for slice_def in slicing.slices.values() {
+            for check in discriminators.fhirpath_checks(slice_def) {
+                if !checks.contains(&check) {
+                    checks.push(check);
+                }
+            }
+        }
+        let expressions: Vec<&str> = checks.iter().map(String::as_str).collect();
+
+        let mut classifications = Vec::with_capacity(items.len());
+        for item in items {
+            let mut evaluated = HashMap::new();
+            if !expressions.is_empty()
+                && let Ok(results) = evaluator
+                    .evaluate_constraints_shared_context_typed(
+                        Arc::new(item.clone()),
+                        element.context_type(),
+                        variables,
+                        &expressions,
+                    )
+                    .await
+            {
+                // An expression that fails to evaluate leaves its
+                // discriminator to the whole-pattern fallback.
+                for (check, result) in checks.iter().zip(results) {
+                    if let Ok(matched) = result {
+                        evaluated.insert(check.clone(), matched);
+                    }
+                }
+            }
+            classifications.push(Self::classify_discriminated(
+                item,
+                slicing,
+                &discriminators,
+                &evaluated,
+            ));
+        }
+        self.report_slicing(&classifications, slicing, errors, element_path);
+        Some(classifications)
+    }
+
+    /// Classify `items` for the constraint phase: with FHIRPath when the
+    /// discriminators need it (reporting slicing errors as
+    /// [`Self::validate_slicing_fhirpath`] does), natively otherwise.
+    async fn classify_for_constraints(
+        &self,
+        items: &[JsonValue],
+        element: &compiled::CompiledElement,
+        variables: &HashMap<String, Arc<JsonValue>>,
+        errors: &mut Vec<ValidationError>,
+        element_path: &str,
+    ) -> Option<Vec<compiled::SliceClassification>> {
+        if let Some(classifications) = self
+            .validate_slicing_fhirpath(items, element, variables, errors, element_path)
+            .await
+        {
+            return Some(classifications);
+        }
+        self.classify_items(items, element.slicing.as_ref()?)
+    }
+
+    /// Report unmatched and ambiguous items and slice cardinality.
+    fn report_slicing(
+        &self,
+        classifications: &[compiled::SliceClassification],
+        slicing: &compiled::CompiledSlicing,
+        errors: &mut Vec<ValidationError>,
+        element_path: &str,
+    ) {
         // Track counts per slice and last matched index for openAtEnd
         let mut slice_counts: HashMap<String, usize> = HashMap::new();
         let mut last_matched_index: Option<usize> = None;
@@ -2682,24 +3717,20 @@ impl FhirValidator {
             slice_counts.insert(slice_name.clone(), 0);
         }
 
-        // Classify each item
-        for (index, item) in items.iter().enumerate() {
-            let classification = self.classify_slice(item, &slicing.slices);
-
+        for (index, classification) in classifications.iter().enumerate() {
             match classification {
                 compiled::SliceClassification::Matched(slice_name) => {
-                    *slice_counts.entry(slice_name).or_insert(0) += 1;
+                    *slice_counts.entry(slice_name.clone()).or_insert(0) += 1;
                     last_matched_index = Some(index);
                 }
                 compiled::SliceClassification::Unmatched => {
                     match slicing.rules {
                         compiled::SlicingRules::Closed => {
                             errors.push(ValidationError {
-                                error_type: FhirSchemaErrorCode::SlicingUnmatched.to_string(),
-                                path: self.path_to_vec(&format!("{}[{}]", element_path, index)),
+                                error_type: FhirSchemaErrorCode::SlicingUnmatched.into(),
+                                path: ErrorPath::from(format!("{}[{}]", element_path, index)),
                                 message: Some(
-                                    "Item does not match any defined slice (closed slicing)"
-                                        .to_string(),
+                                    "Item does not match any defined slice (closed slicing)".into(),
                                 ),
                                 value: None,
                                 expected: None,
@@ -2708,6 +3739,9 @@ impl FhirValidator {
                                 constraint_key: None,
                                 constraint_expression: None,
                                 constraint_severity: None,
+                                fix: None,
+                                schema_url: None,
+                                schema_version: None,
                             });
                         }
                         compiled::SlicingRules::OpenAtEnd => {
@@ -2715,11 +3749,11 @@ impl FhirValidator {
                                 && index < last_idx
                             {
                                 errors.push(ValidationError {
-                                    error_type: FhirSchemaErrorCode::SlicingUnmatched.to_string(),
-                                    path: self.path_to_vec(&format!("{}[{}]", element_path, index)),
+                                    error_type: FhirSchemaErrorCode::SlicingUnmatched.into(),
+                                    path: ErrorPath::from(format!("{}[{}]", element_path, index)),
                                     message: Some(
                                         "Unmatched item appears before matched items (openAtEnd)"
-                                            .to_string(),
+                                            .into(),
                                     ),
                                     value: None,
                                     expected: None,
@@ -2728,6 +3762,9 @@ impl FhirValidator {
                                     constraint_key: None,
                                     constraint_expression: None,
                                     constraint_severity: None,
+                                    fix: None,
+                                    schema_url: None,
+                                    schema_version: None,
                                 });
                             }
                         }
@@ -2736,11 +3773,11 @@ impl FhirValidator {
                 }
                 compiled::SliceClassification::Ambiguous(matched_slices) => {
                     errors.push(ValidationError {
-                        error_type: FhirSchemaErrorCode::SlicingAmbiguous.to_string(),
-                        path: self.path_to_vec(&format!("{}[{}]", element_path, index)),
-                        message: Some(format!(
+                        error_type: FhirSchemaErrorCode::SlicingAmbiguous.into(),
+                        path: ErrorPath::from(format!("{}[{}]", element_path, index)),
+                        message: Some(ErrorMessage::template(
                             "Item matches multiple slices: {}",
-                            matched_slices.join(", ")
+                            [matched_slices.join(", ")],
                         )),
                         value: None,
                         expected: None,
@@ -2749,6 +3786,9 @@ impl FhirValidator {
                         constraint_key: None,
                         constraint_expression: None,
                         constraint_severity: None,
+                        fix: None,
+                        schema_url: None,
+                        schema_version: None,
                     });
                 }
             }
@@ -2774,11 +3814,11 @@ impl FhirValidator {
                 && (count as i32) < min
             {
                 errors.push(ValidationError {
-                    error_type: FhirSchemaErrorCode::SliceCardinality.to_string(),
-                    path: self.path_to_vec(element_path),
-                    message: Some(format!(
+                    error_type: FhirSchemaErrorCode::SliceCardinality.into(),
+                    path: ErrorPath::new(element_path),
+                    message: Some(ErrorMessage::template(
                         "Slice '{}' requires minimum {} items, found {}",
-                        slice_name, min, count
+                        [slice_name.to_string(), min.to_string(), count.to_string()],
                     )),
                     value: None,
                     expected: None,
@@ -2787,6 +3827,9 @@ impl FhirValidator {
                     constraint_key: None,
                     constraint_expression: None,
                     constraint_severity: None,
+                    fix: None,
+                    schema_url: None,
+                    schema_version: None,
                 });
             }
 
@@ -2795,11 +3838,11 @@ impl FhirValidator {
                 && (count as i32) > max
             {
                 errors.push(ValidationError {
-                    error_type: FhirSchemaErrorCode::SliceCardinality.to_string(),
-                    path: self.path_to_vec(element_path),
-                    message: Some(format!(
+                    error_type: FhirSchemaErrorCode::SliceCardinality.into(),
+                    path: ErrorPath::new(element_path),
+                    message: Some(ErrorMessage::template(
                         "Slice '{}' allows maximum {} items, found {}",
-                        slice_name, max, count
+                        [slice_name.to_string(), max.to_string(), count.to_string()],
                     )),
                     value: None,
                     expected: None,
@@ -2808,6 +3851,9 @@ impl FhirValidator {
                     constraint_key: None,
                     constraint_expression: None,
                     constraint_severity: None,
+                    fix: None,
+                    schema_url: None,
+                    schema_version: None,
                 });
             }
         }
@@ -24,6 +24,7 @@ use async_trait::async_trait;
 use serde_json::Value as JsonValue;
 
 use super::{FhirSchemaErrorCode, ValidationError};
+use crate::types::ErrorPath;
 
 /// Resolves a `Questionnaire` canonical URL to its JSON definition.
 ///
@@ -164,21 +165,11 @@ fn answer_value_key(answer: &serde_json::Map<String, JsonValue>) -> Option<&str>
         .find(|k| k.starts_with("value"))
 }
 
-fn path_vec(path: &str) -> Vec<JsonValue> {
-    if path.is_empty() {
-        vec![]
-    } else {
-        path.split('.')
-            .map(|s| JsonValue::String(s.to_string()))
-            .collect()
-    }
-}
-
 fn error(path: &str, message: String) -> ValidationError {
     ValidationError {
-        error_type: FhirSchemaErrorCode::QuestionnaireViolation.to_string(),
-        path: path_vec(path),
-        message: Some(message),
+        error_type: FhirSchemaErrorCode::QuestionnaireViolation.into(),
+        path: ErrorPath::new(path),
+        message: Some(message.into()),
         value: None,
         expected: None,
         got: None,
@@ -186,6 +177,9 @@ fn error(path: &str, message: String) -> ValidationError {
         constraint_key: None,
         constraint_expression: None,
         constraint_severity: Some("error".to_string()),
+        fix: None,
+        schema_url: None,
+        schema_version: None,
     }
 }
 
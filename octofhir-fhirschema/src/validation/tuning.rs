@@ -0,0 +1,112 @@
+//! Cache sizing for validators and providers.
+//!
+//! [`CacheTuning`] gathers the capacities of the caches a validation setup
+//! keeps: compiled schemas in the [`SchemaCompiler`](super::SchemaCompiler)
+//! and terminology results in a
+//! [`CachedTerminologyService`](crate::terminology::CachedTerminologyService).
+//! The presets size them for the usual deployment footprints; start from one
+//! and adjust individual fields.
+//!
+//! ```ignore
+//! let tuning = CacheTuning {
+//!     compiled_schema_bytes: Some(128 * 1024 * 1024),
+//!     ..CacheTuning::server()
+//! };
+//! let validator = FhirValidator::new(provider).with_cache_tuning(&tuning);
+//! ```
+
+use std::time::Duration;
+
+use super::compiled::CompiledSchema;
+use crate::terminology::CacheConfig;
+
+const MIB: u64 = 1024 * 1024;
+
+/// Capacities and memory budgets of the validation caches.
+#[derive(Debug, Clone)]
+pub struct CacheTuning {
+    /// Maximum number of compiled schemas kept in memory.
+    pub compiled_schemas: u64,
+    /// Approximate memory budget for compiled schemas, in bytes. When set,
+    /// eviction weighs each schema by [`CompiledSchema::approx_size`] and
+    /// `compiled_schemas` no longer applies.
+    pub compiled_schema_bytes: Option<u64>,
+    /// Entry limit and time-to-live of the terminology result cache.
+    pub terminology: CacheConfig,
+}
+
+impl Default for CacheTuning {
+    /// The sizes used when no tuning is configured.
+    fn default() -> Self {
+        Self {
+            compiled_schemas: 500,
+            compiled_schema_bytes: None,
+            terminology: CacheConfig::default(),
+        }
+    }
+}
+
+impl CacheTuning {
+    /// A short-lived command-line process: keep everything it compiles and
+    /// every terminology answer for the length of the run.
+    pub fn embedded_cli() -> Self {
+        Self {
+            compiled_schemas: 5_000,
+            compiled_schema_bytes: None,
+            terminology: CacheConfig::long_lived(),
+        }
+    }
+
+    /// A long-running server validating many profiles: a bounded memory
+    /// budget for compiled schemas and a large terminology cache.
+    pub fn server() -> Self {
+        Self {
+            compiled_schemas: 5_000,
+            compiled_schema_bytes: Some(512 * MIB),
+            terminology: CacheConfig::new(Duration::from_secs(3600), 50_000),
+        }
+    }
+
+    /// A memory-constrained function instance (AWS Lambda, Cloud Run): small
+    /// caches that fit next to the request being validated.
+    pub fn lambda() -> Self {
+        Self {
+            compiled_schemas: 200,
+            compiled_schema_bytes: Some(64 * MIB),
+            terminology: CacheConfig::new(Duration::from_secs(300), 2_000),
+        }
+    }
+
+    /// Weight of a compiled schema under [`compiled_schema_bytes`](Self::compiled_schema_bytes).
+    pub(crate) fn compiled_schema_weight(schema: &CompiledSchema) -> u32 {
+        u32::try_from(schema.approx_size()).unwrap_or(u32::MAX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embedded::{FhirVersion, get_schemas};
+    use crate::validation::FhirValidator;
+
+    #[tokio::test]
+    async fn test_compiled_schema_budget_evicts() {
+        let schemas = get_schemas(FhirVersion::R4).unwrap().clone();
+        let tuning = CacheTuning {
+            compiled_schema_bytes: Some(1),
+            ..CacheTuning::lambda()
+        };
+        let validator = FhirValidator::from_schemas(schemas, None).with_cache_tuning(&tuning);
+
+        let result = validator
+            .validate(
+                &serde_json::json!({"resourceType": "Patient", "active": "yes"}),
+                vec!["Patient".to_string()],
+            )
+            .await;
+        assert!(!result.valid);
+
+        let patient = validator.compiler.compile("Patient").await.unwrap();
+        assert!(CacheTuning::compiled_schema_weight(&patient) > 1);
+    }
+}
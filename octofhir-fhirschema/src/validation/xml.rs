@@ -0,0 +1,682 @@
+//! FHIR XML input.
+//!
+//! The validator walks the JSON representation, so XML resources are mapped
+//! to it first. The XML format leaves out what JSON spells out, and the
+//! mapping takes it from the compiled schemas:
+//!
+//! - an element that may repeat becomes a JSON array even when it occurs once
+//! - a primitive's `value` attribute becomes a JSON string, number or boolean
+//!   by the element's type; its `id` attribute and extensions go to the
+//!   `_name` sibling
+//! - the narrative `div` is kept as its XHTML markup
+//! - `contained` and other resource-typed elements unwrap the nested resource
+//!   into an object with a `resourceType`
+//!
+//! Elements the schemas do not describe are mapped from their shape, so the
+//! validator reports them instead of the mapping failing. FHIR XML may not
+//! use DTDs, and a document with one is rejected.
+
+use std::collections::HashMap;
+
+use serde_json::{Map, Value as JsonValue};
+
+use super::FhirValidator;
+use super::compiled::{
+    CompiledElement, CompiledTypeInfo, PrimitiveType, SchemaKind, SharedCompiledSchema,
+};
+use crate::error::{FhirSchemaError, Result};
+use crate::types::ValidationResult;
+
+const FHIR_NAMESPACE: &str = "http://hl7.org/fhir";
+const XHTML_NAMESPACE: &str = "http://www.w3.org/1999/xhtml";
+
+/// An element of a parsed XML document.
+#[derive(Debug, Default)]
+struct XmlElement {
+    /// Local name, without a namespace prefix
+    name: String,
+    namespace: Option<String>,
+    /// Attributes other than namespace declarations, by local name
+    attributes: Vec<(String, String)>,
+    children: Vec<XmlElement>,
+    /// Markup of an XHTML element, kept verbatim
+    markup: Option<String>,
+}
+
+impl XmlElement {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Parse an XML document into its root element.
+fn parse_document(src: &str) -> std::result::Result<XmlElement, String> {
+    let mut parser = XmlParser {
+        src,
+        pos: 0,
+        namespaces: Vec::new(),
+    };
+    parser.skip_prolog()?;
+    let root = parser.element()?;
+    parser.skip_misc()?;
+    if parser.pos < src.len() {
+        return Err(parser.error("content after the root element"));
+    }
+    Ok(root)
+}
+
+struct XmlParser<'a> {
+    src: &'a str,
+    pos: usize,
+    /// In-scope namespace declarations as (prefix, URI); `""` is the default
+    namespaces: Vec<(String, String)>,
+}
+
+impl<'a> XmlParser<'a> {
+    fn error(&self, message: &str) -> String {
+        let before = &self.src[..self.pos];
+        let line = before.matches('\n').count() + 1;
+        let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+        format!("line {line}, column {column}: {message}")
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.src[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn skip_past(&mut self, terminator: &str) -> std::result::Result<(), String> {
+        match self.rest().find(terminator) {
+            Some(at) => {
+                self.pos += at + terminator.len();
+                Ok(())
+            }
+            None => Err(self.error(&format!("missing '{terminator}'"))),
+        }
+    }
+
+    fn skip_prolog(&mut self) -> std::result::Result<(), String> {
+        if self.rest().starts_with('\u{feff}') {
+            self.pos += '\u{feff}'.len_utf8();
+        }
+        self.skip_misc()
+    }
+
+    /// Skip whitespace, comments and processing instructions.
+    fn skip_misc(&mut self) -> std::result::Result<(), String> {
+        loop {
+            self.skip_whitespace();
+            let rest = self.rest();
+            if rest.starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if rest.starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if rest.starts_with("<!DOCTYPE") {
+                return Err(self.error("DTDs are not allowed in FHIR XML"));
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn name(&mut self) -> std::result::Result<&'a str, String> {
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| c.is_whitespace() || matches!(c, '/' | '>' | '='))
+            .unwrap_or(rest.len());
+        if len == 0 {
+            return Err(self.error("expected a name"));
+        }
+        self.pos += len;
+        Ok(&rest[..len])
+    }
+
+    fn expect(&mut self, token: &str) -> std::result::Result<(), String> {
+        if self.rest().starts_with(token) {
+            self.pos += token.len();
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{token}'")))
+        }
+    }
+
+    fn resolve(&self, prefix: &str) -> Option<String> {
+        self.namespaces
+            .iter()
+            .rev()
+            .find(|(p, _)| p == prefix)
+            .map(|(_, uri)| uri.clone())
+    }
+
+    fn element(&mut self) -> std::result::Result<XmlElement, String> {
+        let start = self.pos;
+        self.expect("<")?;
+        let qualified = self.name()?.to_string();
+
+        let mut raw_attributes = Vec::new();
+        let empty = loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("/>") {
+                self.pos += 2;
+                break true;
+            }
+            if self.rest().starts_with('>') {
+                self.pos += 1;
+                break false;
+            }
+            let name = self.name()?.to_string();
+            self.skip_whitespace();
+            self.expect("=")?;
+            self.skip_whitespace();
+            let quote = match self.rest().chars().next() {
+                Some(q @ ('"' | '\'')) => q,
+                _ => return Err(self.error("expected a quoted attribute value")),
+            };
+            self.pos += 1;
+            let Some(len) = self.rest().find(quote) else {
+                return Err(self.error("unterminated attribute value"));
+            };
+            let value = decode_entities(&self.rest()[..len]).map_err(|e| self.error(&e))?;
+            self.pos += len + 1;
+            raw_attributes.push((name, value));
+        };
+
+        let scope = self.namespaces.len();
+        let mut attributes = Vec::new();
+        for (name, value) in raw_attributes {
+            if name == "xmlns" {
+                self.namespaces.push((String::new(), value));
+            } else if let Some(prefix) = name.strip_prefix("xmlns:") {
+                self.namespaces.push((prefix.to_string(), value));
+            } else {
+                let local = name.rsplit(':').next().unwrap_or(&name).to_string();
+                attributes.push((local, value));
+            }
+        }
+        let (prefix, name) = qualified.split_once(':').unwrap_or(("", &qualified));
+        let mut element = XmlElement {
+            name: name.to_string(),
+            namespace: self.resolve(prefix),
+            attributes,
+            ..XmlElement::default()
+        };
+        let xhtml = element.namespace.as_deref() == Some(XHTML_NAMESPACE);
+
+        if !empty {
+            self.content(&qualified, &mut element, xhtml)?;
+        }
+        if xhtml {
+            element.markup = Some(self.src[start..self.pos].to_string());
+        }
+        self.namespaces.truncate(scope);
+        Ok(element)
+    }
+
+    /// Read the children of `element` up to its end tag. Text is only
+    /// allowed in XHTML.
+    fn content(
+        &mut self,
+        qualified: &str,
+        element: &mut XmlElement,
+        xhtml: bool,
+    ) -> std::result::Result<(), String> {
+        loop {
+            let rest = self.rest();
+            if rest.is_empty() {
+                return Err(self.error(&format!("missing end tag for <{qualified}>")));
+            } else if rest.starts_with("</") {
+                self.pos += 2;
+                let end = self.name()?;
+                if end != qualified {
+                    return Err(self.error(&format!("expected </{qualified}>, found </{end}>")));
+                }
+                self.skip_whitespace();
+                return self.expect(">");
+            } else if rest.starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if rest.starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if rest.starts_with("<![CDATA[") {
+                if !xhtml {
+                    return Err(self.error(&format!("unexpected CDATA in <{qualified}>")));
+                }
+                self.skip_past("]]>")?;
+            } else if rest.starts_with('<') {
+                let child = self.element()?;
+                element.children.push(child);
+            } else {
+                let len = rest.find('<').unwrap_or(rest.len());
+                if !xhtml && !rest[..len].trim().is_empty() {
+                    return Err(self.error(&format!(
+                        "unexpected text in <{qualified}>; FHIR XML carries values in attributes"
+                    )));
+                }
+                self.pos += len;
+            }
+        }
+    }
+}
+
+/// Replace the predefined entities and character references in `text`.
+fn decode_entities(text: &str) -> std::result::Result<String, String> {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(at) = rest.find(['&', '\t', '\n', '\r']) {
+        out.push_str(&rest[..at]);
+        if !rest[at..].starts_with('&') {
+            // Attribute value normalization turns literal whitespace into spaces.
+            out.push(' ');
+            rest = &rest[at + 1..];
+            continue;
+        }
+        let Some(end) = rest[at..].find(';') else {
+            return Err("unterminated entity reference".to_string());
+        };
+        let entity = &rest[at + 1..at + end];
+        let decoded = match entity {
+            "amp" => '&',
+            "lt" => '<',
+            "gt" => '>',
+            "quot" => '"',
+            "apos" => '\'',
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(str::parse))
+                .and_then(|code| code.ok())
+                .and_then(char::from_u32)
+                .ok_or_else(|| format!("unknown entity '&{entity};'"))?,
+        };
+        out.push(decoded);
+        rest = &rest[at + end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// How an XML element maps to JSON.
+enum Shape<'a> {
+    Primitive(Option<PrimitiveType>),
+    Xhtml,
+    /// A wrapper around a nested resource, e.g. `contained`
+    Resource,
+    Complex(Option<&'a HashMap<String, CompiledElement>>),
+    /// A datatype found from the element name, e.g. `Quantity` for
+    /// `valueQuantity`
+    Datatype(SharedCompiledSchema),
+}
+
+/// Convert a primitive `value` attribute to the JSON type of `primitive`.
+/// Text that does not parse stays a string for the validator to report.
+fn primitive_json(value: &str, primitive: Option<PrimitiveType>) -> JsonValue {
+    use PrimitiveType::*;
+
+    let text = || JsonValue::String(value.to_string());
+    match primitive {
+        Some(Boolean) => match value {
+            "true" => JsonValue::Bool(true),
+            "false" => JsonValue::Bool(false),
+            _ => text(),
+        },
+        Some(Integer | Integer64 | UnsignedInt | PositiveInt) => value
+            .parse::<i64>()
+            .map_or_else(|_| text(), JsonValue::from),
+        Some(Decimal) => value
+            .parse::<serde_json::Number>()
+            .map_or_else(|_| text(), JsonValue::Number),
+        _ => text(),
+    }
+}
+
+/// Suffixes of an element name that may name its type, longest first:
+/// `valueCodeableConcept` gives `CodeableConcept` and `Concept`.
+fn type_suffixes(name: &str) -> impl Iterator<Item = &str> {
+    name.char_indices()
+        .skip(1)
+        .filter(|(_, c)| c.is_ascii_uppercase())
+        .map(move |(i, _)| &name[i..])
+}
+
+fn lower_first(name: &str) -> String {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .map(|first| first.to_ascii_lowercase().to_string() + chars.as_str())
+        .unwrap_or_default()
+}
+
+impl FhirValidator {
+    /// Parse a resource in the FHIR XML representation into its JSON form.
+    ///
+    /// Needs the schemas of the resource and of the datatypes it uses to
+    /// tell repeating elements and primitive types apart, so they are
+    /// compiled on the way.
+    pub async fn parse_xml(&self, bytes: &[u8]) -> Result<JsonValue> {
+        let src = std::str::from_utf8(bytes)
+            .map_err(|e| FhirSchemaError::invalid_resource_xml(e.to_string()))?;
+        let root = parse_document(src).map_err(FhirSchemaError::invalid_resource_xml)?;
+        if root.namespace.as_deref() != Some(FHIR_NAMESPACE) {
+            return Err(FhirSchemaError::invalid_resource_xml(format!(
+                "root element <{}> is not in the FHIR namespace {FHIR_NAMESPACE}",
+                root.name
+            )));
+        }
+        Ok(self.xml_resource(&root).await)
+    }
+
+    /// Parse a FHIR XML resource and validate it against its resourceType
+    /// and `profiles`.
+    pub async fn validate_xml(
+        &self,
+        bytes: &[u8],
+        profiles: Vec<String>,
+    ) -> Result<ValidationResult> {
+        let resource = self.parse_xml(bytes).await?;
+        Ok(self.validate_with_profiles(&resource, &profiles).await)
+    }
+
+    #[async_recursion::async_recursion]
+    async fn xml_resource(&self, element: &XmlElement) -> JsonValue {
+        let mut object = Map::new();
+        object.insert(
+            "resourceType".to_string(),
+            JsonValue::String(element.name.clone()),
+        );
+        let schema = self.compiler.compile(&element.name).await.ok();
+        let elements = schema.as_ref().map(|s| &s.elements);
+        self.xml_members(element, elements, elements, &mut object)
+            .await;
+        JsonValue::Object(object)
+    }
+
+    /// Map the attributes and child elements of `element` into `object`.
+    /// `elements` describes the children when known; `root` holds the
+    /// elements `contentReference`s resolve against.
+    #[async_recursion::async_recursion]
+    async fn xml_members(
+        &self,
+        element: &XmlElement,
+        elements: Option<&HashMap<String, CompiledElement>>,
+        root: Option<&HashMap<String, CompiledElement>>,
+        object: &mut Map<String, JsonValue>,
+    ) {
+        for (name, value) in &element.attributes {
+            if name == "id" || name == "url" {
+                object.insert(name.clone(), JsonValue::String(value.clone()));
+            }
+        }
+
+        let mut groups: Vec<(&str, Vec<&XmlElement>)> = Vec::new();
+        for child in &element.children {
+            match groups.iter_mut().find(|(name, _)| *name == child.name) {
+                Some((_, items)) => items.push(child),
+                None => groups.push((&child.name, vec![child])),
+            }
+        }
+
+        for (name, items) in groups {
+            let definition = elements.and_then(|e| e.get(name));
+            let repeats = match definition {
+                Some(definition) => definition.is_array,
+                None => matches!(name, "extension" | "modifierExtension"),
+            } || items.len() > 1;
+            let shape = self.xml_shape(name, items[0], definition, root).await;
+
+            let mut values = Vec::with_capacity(items.len());
+            let mut extras = Vec::with_capacity(items.len());
+            for item in &items {
+                let (value, extra) = self.xml_value(item, &shape, root).await;
+                values.push(value);
+                extras.push(extra);
+            }
+
+            if repeats {
+                if values.iter().any(|v| !v.is_null()) {
+                    object.insert(name.to_string(), JsonValue::Array(values));
+                }
+                if extras.iter().any(|e| !e.is_null()) {
+                    object.insert(format!("_{name}"), JsonValue::Array(extras));
+                }
+            } else {
+                let (value, extra) = (values.remove(0), extras.remove(0));
+                if !value.is_null() {
+                    object.insert(name.to_string(), value);
+                }
+                if !extra.is_null() {
+                    object.insert(format!("_{name}"), extra);
+                }
+            }
+        }
+    }
+
+    /// Work out how the elements called `name` map to JSON, from their
+    /// definition when there is one and otherwise from the name and `item`.
+    async fn xml_shape<'a>(
+        &self,
+        name: &str,
+        item: &XmlElement,
+        definition: Option<&'a CompiledElement>,
+        root: Option<&'a HashMap<String, CompiledElement>>,
+    ) -> Shape<'a> {
+        if item.namespace.as_deref() == Some(XHTML_NAMESPACE) {
+            return Shape::Xhtml;
+        }
+        if let Some(definition) = definition {
+            match &definition.type_info {
+                CompiledTypeInfo::Primitive(PrimitiveType::Xhtml) => return Shape::Xhtml,
+                CompiledTypeInfo::Primitive(primitive) => {
+                    return Shape::Primitive(Some(*primitive));
+                }
+                CompiledTypeInfo::Resource => return Shape::Resource,
+                _ => {
+                    let children = if definition.children.is_empty() {
+                        root.and_then(|root| {
+                            Self::resolve_element_reference(
+                                root,
+                                definition.element_reference.as_deref(),
+                            )
+                        })
+                        .map(|target| &target.children)
+                    } else {
+                        Some(&definition.children)
+                    };
+                    if let Some(children) = children.filter(|c| !c.is_empty()) {
+                        return Shape::Complex(Some(children));
+                    }
+                }
+            }
+        }
+
+        // Not described: a choice variant such as `valueQuantity`, an
+        // extension's content or an element the schema does not know.
+        if item.attribute("value").is_some() {
+            let primitive =
+                type_suffixes(name).find_map(|suffix| PrimitiveType::parse(&lower_first(suffix)));
+            return Shape::Primitive(primitive);
+        }
+        for suffix in type_suffixes(name) {
+            if let Ok(schema) = self.compiler.compile(suffix).await
+                && schema.kind != SchemaKind::Resource
+            {
+                return Shape::Datatype(schema);
+            }
+        }
+        match item.children.as_slice() {
+            [only] if only.name.starts_with(|c: char| c.is_ascii_uppercase()) => Shape::Resource,
+            _ => Shape::Complex(None),
+        }
+    }
+
+    /// Map one element to its JSON value and the content of its `_name`
+    /// sibling, `null` where there is none.
+    async fn xml_value(
+        &self,
+        item: &XmlElement,
+        shape: &Shape<'_>,
+        root: Option<&HashMap<String, CompiledElement>>,
+    ) -> (JsonValue, JsonValue) {
+        match shape {
+            Shape::Primitive(primitive) => {
+                let value = item
+                    .attribute("value")
+                    .map_or(JsonValue::Null, |v| primitive_json(v, *primitive));
+                let mut extra = Map::new();
+                self.xml_members(item, None, root, &mut extra).await;
+                let extra = if extra.is_empty() {
+                    JsonValue::Null
+                } else {
+                    JsonValue::Object(extra)
+                };
+                (value, extra)
+            }
+            Shape::Xhtml => (
+                item.markup
+                    .clone()
+                    .map_or(JsonValue::Null, JsonValue::String),
+                JsonValue::Null,
+            ),
+            Shape::Resource => {
+                let value = match item.children.first() {
+                    Some(resource) => self.xml_resource(resource).await,
+                    None => JsonValue::Object(Map::new()),
+                };
+                (value, JsonValue::Null)
+            }
+            Shape::Complex(children) => {
+                let mut object = Map::new();
+                self.xml_members(item, *children, root, &mut object).await;
+                (JsonValue::Object(object), JsonValue::Null)
+            }
+            Shape::Datatype(schema) => {
+                let mut object = Map::new();
+                self.xml_members(
+                    item,
+                    Some(&schema.elements),
+                    Some(&schema.elements),
+                    &mut object,
+                )
+                .await;
+                (JsonValue::Object(object), JsonValue::Null)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embedded::{FhirVersion, get_schemas};
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_document() {
+        let root = parse_document(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<!-- comment -->
+<Patient xmlns="http://hl7.org/fhir">
+  <text><div xmlns="http://www.w3.org/1999/xhtml"><p>Jane &amp; <b>Doe</b></p></div></text>
+  <name><family value="O&apos;Brien"/></name>
+</Patient>"#,
+        )
+        .unwrap();
+        assert_eq!(root.name, "Patient");
+        assert_eq!(root.namespace.as_deref(), Some(FHIR_NAMESPACE));
+        let div = &root.children[0].children[0];
+        assert_eq!(
+            div.markup.as_deref(),
+            Some(r#"<div xmlns="http://www.w3.org/1999/xhtml"><p>Jane &amp; <b>Doe</b></p></div>"#)
+        );
+        assert_eq!(
+            root.children[1].children[0].attribute("value"),
+            Some("O'Brien")
+        );
+
+        assert!(parse_document("<Patient><name>text</name></Patient>").is_err());
+        assert!(parse_document("<!DOCTYPE x><Patient/>").is_err());
+        let error = parse_document("<Patient>\n  <active value=\"true\">\n</Patient>").unwrap_err();
+        assert!(error.starts_with("line 3"), "{error}");
+    }
+
+    #[tokio::test]
+    async fn test_parse_xml_resource() {
+        let schemas = get_schemas(FhirVersion::R4).unwrap().clone();
+        let validator = FhirValidator::from_schemas(schemas, None);
+        let xml = br##"<Patient xmlns="http://hl7.org/fhir">
+  <id value="example"/>
+  <text>
+    <status value="generated"/>
+    <div xmlns="http://www.w3.org/1999/xhtml">Jane</div>
+  </text>
+  <contained>
+    <Organization>
+      <id value="org"/>
+      <name value="Acme"/>
+    </Organization>
+  </contained>
+  <extension url="http://example.org/flag">
+    <valueBoolean value="true"/>
+  </extension>
+  <active value="true"/>
+  <name>
+    <given value="Jane"/>
+    <given>
+      <extension url="http://example.org/missing">
+        <valueCode value="unknown"/>
+      </extension>
+    </given>
+  </name>
+  <birthDate id="b1" value="1970-01-01"/>
+  <multipleBirthInteger value="2"/>
+  <managingOrganization>
+    <reference value="#org"/>
+  </managingOrganization>
+</Patient>"##;
+
+        let patient = validator.parse_xml(xml).await.unwrap();
+        assert_eq!(
+            patient,
+            json!({
+                "resourceType": "Patient",
+                "id": "example",
+                "text": {
+                    "status": "generated",
+                    "div": "<div xmlns=\"http://www.w3.org/1999/xhtml\">Jane</div>"
+                },
+                "contained": [{"resourceType": "Organization", "id": "org", "name": "Acme"}],
+                "extension": [{"url": "http://example.org/flag", "valueBoolean": true}],
+                "active": true,
+                "name": [{
+                    "given": ["Jane", null],
+                    "_given": [null, {"extension": [
+                        {"url": "http://example.org/missing", "valueCode": "unknown"}
+                    ]}]
+                }],
+                "birthDate": "1970-01-01",
+                "_birthDate": {"id": "b1"},
+                "multipleBirthInteger": 2,
+                "managingOrganization": {"reference": "#org"}
+            })
+        );
+
+        let result = validator.validate_xml(xml, vec![]).await.unwrap();
+        assert!(result.valid, "errors: {:?}", result.errors);
+
+        let invalid = br#"<Patient xmlns="http://hl7.org/fhir"><active value="maybe"/></Patient>"#;
+        let result = validator.validate_xml(invalid, vec![]).await.unwrap();
+        assert!(!result.valid);
+
+        assert!(
+            validator
+                .parse_xml(b"<Patient><active value=\"true\"/></Patient>")
+                .await
+                .is_err()
+        );
+    }
+}
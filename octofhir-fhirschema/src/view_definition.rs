@@ -0,0 +1,610 @@
+//! SQL-on-FHIR ViewDefinition checking and column typing.
+//!
+//! A ViewDefinition flattens a resource type into rows through nested
+//! `select` clauses whose `column` paths, `forEach`/`forEachOrNull`
+//! iterations and `where` filters are FHIRPath expressions. Validating the
+//! resource against the ViewDefinition logical model only checks its shape;
+//! this module additionally resolves every path against the FHIR Schemas of
+//! the viewed resource, reports paths that do not exist or return more than
+//! one value where a single value is expected, and infers the FHIR type of
+//! each column so analytics pipelines can create typed tables.
+//!
+//! Paths are resolved for the subset of FHIRPath that ViewDefinitions use
+//! in practice: member navigation, indexers, `first()`, `where()`,
+//! `ofType()`, `extension()`, `join()`, `exists()` and the
+//! `getResourceKey()`/`getReferenceKey()` functions. Expressions using
+//! operators or other functions are accepted without a type.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use octofhir_fhirschema::{get_schemas, FhirVersion};
+//! use octofhir_fhirschema::view_definition::analyze_view_definition;
+//!
+//! let analysis = analyze_view_definition(&view, get_schemas(FhirVersion::R4)?);
+//! for column in &analysis.columns {
+//!     println!("{}: {:?}", column.name, column.fhir_type);
+//! }
+//! assert!(analysis.is_valid(), "{:?}", analysis.issues);
+//! ```
+
+use crate::types::{FhirSchema, FhirSchemaElement};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+
+/// A column produced by a ViewDefinition, in output order.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ViewColumn {
+    /// Column name
+    pub name: String,
+    /// FHIRPath expression the column is computed from
+    pub path: String,
+    /// Inferred FHIR type, e.g. `string`, `date`, `Reference`; `None` when
+    /// the path could not be typed
+    pub fhir_type: Option<String>,
+    /// Type declared on the column, if any
+    pub declared_type: Option<String>,
+    /// Whether the column holds a collection
+    pub collection: bool,
+}
+
+/// A problem found in a ViewDefinition.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ViewDefinitionIssue {
+    /// Location within the ViewDefinition, e.g. `select[0].column[1].path`
+    pub location: String,
+    /// Description of the problem
+    pub message: String,
+}
+
+/// Result of [`analyze_view_definition`].
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ViewDefinitionAnalysis {
+    /// Resource type the view is defined on
+    pub resource: Option<String>,
+    /// Output columns in order
+    pub columns: Vec<ViewColumn>,
+    /// Problems found; empty when the view is usable
+    pub issues: Vec<ViewDefinitionIssue>,
+}
+
+impl ViewDefinitionAnalysis {
+    /// Whether no issues were found.
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// Check a ViewDefinition against the schemas of the FHIR version it targets
+/// and infer its column types.
+///
+/// `schemas` is the schema map of that version, e.g. from
+/// [`get_schemas`](crate::get_schemas) or a model provider's `schemas()`.
+pub fn analyze_view_definition(
+    view: &Value,
+    schemas: &HashMap<String, FhirSchema>,
+) -> ViewDefinitionAnalysis {
+    let mut analyzer = Analyzer {
+        model: Model::new(schemas),
+        constants: HashSet::new(),
+        analysis: ViewDefinitionAnalysis::default(),
+    };
+    analyzer.view(view);
+    analyzer.analysis
+}
+
+/// Type of a FHIRPath expression result.
+#[derive(Debug, Clone)]
+struct Node<'a> {
+    /// FHIR type name; `None` when unknown
+    type_name: Option<String>,
+    /// Inline elements of a backbone element
+    elements: Option<&'a HashMap<String, FhirSchemaElement>>,
+    /// Whether the result can hold more than one value
+    collection: bool,
+}
+
+impl Node<'_> {
+    fn unknown(collection: bool) -> Self {
+        Self {
+            type_name: None,
+            elements: None,
+            collection,
+        }
+    }
+
+    fn of_type(type_name: &str, collection: bool) -> Self {
+        Self {
+            type_name: Some(type_name.to_string()),
+            elements: None,
+            collection,
+        }
+    }
+}
+
+/// Schema lookups by name or URL.
+struct Model<'a> {
+    schemas: &'a HashMap<String, FhirSchema>,
+    by_url: HashMap<&'a str, &'a FhirSchema>,
+}
+
+impl<'a> Model<'a> {
+    fn new(schemas: &'a HashMap<String, FhirSchema>) -> Self {
+        let by_url = schemas
+            .values()
+            .map(|schema| (schema.url.as_str(), schema))
+            .collect();
+        Self { schemas, by_url }
+    }
+
+    fn schema(&self, key: &str) -> Option<&'a FhirSchema> {
+        self.schemas
+            .get(key)
+            .or_else(|| self.by_url.get(key).copied())
+    }
+
+    fn is_resource(&self, name: &str) -> bool {
+        self.schema(name)
+            .is_some_and(|schema| schema.kind == "resource")
+    }
+
+    /// Find an element of a type, following its base chain.
+    fn element(&self, type_name: &str, name: &str) -> Option<&'a FhirSchemaElement> {
+        let mut current = self.schema(type_name);
+        let mut depth = 0;
+        while let Some(schema) = current {
+            if let Some(element) = schema.elements.as_ref().and_then(|e| e.get(name)) {
+                return Some(element);
+            }
+            depth += 1;
+            if depth > 16 {
+                break;
+            }
+            current = schema.base.as_deref().and_then(|base| self.schema(base));
+        }
+        None
+    }
+
+    /// Resolve a member of a node, or `None` when it has no such element.
+    fn member(&self, node: &Node<'a>, name: &str) -> Option<Node<'a>> {
+        let element = match node.elements {
+            Some(elements) => elements
+                .get(name)
+                .or_else(|| self.element("BackboneElement", name))?,
+            None => self.element(node.type_name.as_deref()?, name)?,
+        };
+        let collection = node.collection || element.array == Some(true);
+        Some(match (&element.elements, &element.type_name) {
+            (Some(elements), _) => Node {
+                type_name: Some("BackboneElement".to_string()),
+                elements: Some(elements),
+                collection,
+            },
+            (None, Some(type_name)) => Node::of_type(type_name, collection),
+            // Choice element: typed only after ofType()
+            (None, None) => Node::unknown(collection),
+        })
+    }
+}
+
+struct Analyzer<'a> {
+    model: Model<'a>,
+    constants: HashSet<String>,
+    analysis: ViewDefinitionAnalysis,
+}
+
+impl<'a> Analyzer<'a> {
+    fn issue(&mut self, location: impl Into<String>, message: impl Into<String>) {
+        self.analysis.issues.push(ViewDefinitionIssue {
+            location: location.into(),
+            message: message.into(),
+        });
+    }
+
+    fn view(&mut self, view: &Value) {
+        if view.get("resourceType").and_then(Value::as_str) != Some("ViewDefinition") {
+            self.issue("resourceType", "expected ViewDefinition");
+        }
+
+        let Some(resource) = view.get("resource").and_then(Value::as_str) else {
+            self.issue("resource", "resource is required");
+            return;
+        };
+        self.analysis.resource = Some(resource.to_string());
+        if !self.model.is_resource(resource) {
+            self.issue("resource", format!("unknown resource type {resource}"));
+            return;
+        }
+
+        for (idx, constant) in array(view, "constant").iter().enumerate() {
+            match constant.get("name").and_then(Value::as_str) {
+                Some(name) => {
+                    self.constants.insert(name.to_string());
+                }
+                None => self.issue(format!("constant[{idx}].name"), "name is required"),
+            }
+        }
+
+        let root = Node::of_type(resource, false);
+        let selects = array(view, "select");
+        if selects.is_empty() {
+            self.issue("select", "at least one select is required");
+        }
+        for (idx, select) in selects.iter().enumerate() {
+            self.select(select, &root, &format!("select[{idx}]"));
+        }
+
+        for (idx, filter) in array(view, "where").iter().enumerate() {
+            let location = format!("where[{idx}].path");
+            let Some(path) = filter.get("path").and_then(Value::as_str) else {
+                self.issue(location, "path is required");
+                continue;
+            };
+            if let Some(node) = self.resolve(path, &root, &location)
+                && let Some(type_name) = &node.type_name
+                && type_name != "boolean"
+            {
+                self.issue(
+                    location,
+                    format!("where path must be boolean, {path} is {type_name}"),
+                );
+            }
+        }
+
+        let mut seen = HashSet::new();
+        let duplicates: Vec<String> = self
+            .analysis
+            .columns
+            .iter()
+            .filter(|column| !seen.insert(column.name.clone()))
+            .map(|column| column.name.clone())
+            .collect();
+        for name in duplicates {
+            self.issue(
+                "select",
+                format!("column name {name} is used more than once"),
+            );
+        }
+    }
+
+    fn select(&mut self, select: &Value, context: &Node<'a>, location: &str) {
+        let for_each = select.get("forEach").and_then(Value::as_str);
+        let for_each_or_null = select.get("forEachOrNull").and_then(Value::as_str);
+        let context = match (for_each, for_each_or_null) {
+            (Some(_), Some(_)) => {
+                self.issue(
+                    location,
+                    "forEach and forEachOrNull cannot be used together",
+                );
+                return;
+            }
+            (Some(path), None) | (None, Some(path)) => {
+                let key = if for_each.is_some() {
+                    "forEach"
+                } else {
+                    "forEachOrNull"
+                };
+                let Some(node) = self.resolve(path, context, &format!("{location}.{key}")) else {
+                    return;
+                };
+                Node {
+                    collection: false,
+                    ..node
+                }
+            }
+            (None, None) => context.clone(),
+        };
+
+        for (idx, column) in array(select, "column").iter().enumerate() {
+            self.column(column, &context, &format!("{location}.column[{idx}]"));
+        }
+        for (idx, nested) in array(select, "select").iter().enumerate() {
+            self.select(nested, &context, &format!("{location}.select[{idx}]"));
+        }
+
+        let mut branch_columns: Option<Vec<String>> = None;
+        for (idx, branch) in array(select, "unionAll").iter().enumerate() {
+            let first_column = self.analysis.columns.len();
+            let branch_location = format!("{location}.unionAll[{idx}]");
+            self.select(branch, &context, &branch_location);
+            let names: Vec<String> = self.analysis.columns[first_column..]
+                .iter()
+                .map(|column| column.name.clone())
+                .collect();
+            match &branch_columns {
+                None => branch_columns = Some(names),
+                Some(expected) => {
+                    if *expected != names {
+                        self.issue(
+                            branch_location,
+                            "unionAll branches must produce the same columns in the same order",
+                        );
+                    }
+                    // The union has one set of columns
+                    self.analysis.columns.truncate(first_column);
+                }
+            }
+        }
+    }
+
+    fn column(&mut self, column: &Value, context: &Node<'a>, location: &str) {
+        let name = column.get("name").and_then(Value::as_str);
+        let path = column.get("path").and_then(Value::as_str);
+        let (Some(name), Some(path)) = (name, path) else {
+            self.issue(location, "column name and path are required");
+            return;
+        };
+        if !is_column_name(name) {
+            self.issue(
+                format!("{location}.name"),
+                format!("{name} is not a valid column name"),
+            );
+        }
+
+        let declared_type = column
+            .get("type")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        let declared_collection = column.get("collection").and_then(Value::as_bool) == Some(true);
+        let node = self.resolve(path, context, &format!("{location}.path"));
+        let (fhir_type, collection) = match node {
+            Some(node) => (node.type_name, node.collection),
+            None => (None, false),
+        };
+
+        if collection && !declared_collection {
+            self.issue(
+                format!("{location}.path"),
+                format!("{path} can return several values; use first() or set collection to true"),
+            );
+        }
+        if let (Some(declared), Some(inferred)) = (&declared_type, &fhir_type)
+            && declared != inferred
+        {
+            self.issue(
+                format!("{location}.type"),
+                format!("declared type {declared} but {path} is {inferred}"),
+            );
+        }
+
+        self.analysis.columns.push(ViewColumn {
+            name: name.to_string(),
+            path: path.to_string(),
+            fhir_type: fhir_type.or_else(|| declared_type.clone()),
+            declared_type,
+            collection: collection || declared_collection,
+        });
+    }
+
+    /// Type a path relative to a context. Reports and returns `None` for
+    /// members that do not exist; unsupported expressions resolve to an
+    /// unknown type.
+    fn resolve(&mut self, path: &str, context: &Node<'a>, location: &str) -> Option<Node<'a>> {
+        let Some(segments) = split_path(path) else {
+            return Some(Node::unknown(context.collection));
+        };
+
+        let mut node = context.clone();
+        for segment in segments {
+            if let Some(constant) = segment.strip_prefix('%') {
+                if !self.constants.contains(constant) {
+                    self.issue(location, format!("unknown constant %{constant}"));
+                    return None;
+                }
+                node = Node::unknown(false);
+                continue;
+            }
+            if segment == "$this" {
+                continue;
+            }
+
+            let (head, indexed) = match segment.strip_suffix(']') {
+                Some(rest) => match rest.split_once('[') {
+                    Some((head, _)) => (head, true),
+                    None => (segment, false),
+                },
+                None => (segment, false),
+            };
+
+            node = match head.split_once('(') {
+                Some((function, args)) => {
+                    let args = args.strip_suffix(')').unwrap_or(args).trim();
+                    self.function(function, args, node)
+                }
+                None => {
+                    let name = head.trim_matches('`');
+                    if node.type_name.is_none() && node.elements.is_none() {
+                        Node::unknown(node.collection)
+                    } else {
+                        match self.model.member(&node, name) {
+                            Some(member) => member,
+                            None => {
+                                let owner = node.type_name.as_deref().unwrap_or("element");
+                                self.issue(location, format!("{owner} has no element {name}"));
+                                return None;
+                            }
+                        }
+                    }
+                }
+            };
+            if indexed {
+                node.collection = false;
+            }
+        }
+        Some(node)
+    }
+
+    fn function(&mut self, name: &str, args: &str, node: Node<'a>) -> Node<'a> {
+        match name {
+            "first" | "last" | "single" => Node {
+                collection: false,
+                ..node
+            },
+            "where" | "distinct" | "skip" | "take" | "tail" => node,
+            "ofType" | "as" => Node::of_type(args.trim_matches('`'), node.collection),
+            "extension" => Node::of_type("Extension", true),
+            "exists" | "empty" | "not" | "hasValue" | "all" | "allTrue" | "anyTrue" => {
+                Node::of_type("boolean", false)
+            }
+            "count" => Node::of_type("integer", false),
+            "join" | "toString" | "getResourceKey" | "getReferenceKey" => {
+                Node::of_type("string", false)
+            }
+            "lowBoundary" | "highBoundary" => Node {
+                collection: false,
+                ..node
+            },
+            _ => Node::unknown(node.collection),
+        }
+    }
+}
+
+fn array<'v>(value: &'v Value, key: &str) -> &'v [Value] {
+    value
+        .get(key)
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+fn is_column_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Split a path into dot-separated segments, keeping function arguments
+/// and quoted strings intact. Returns `None` for expressions with
+/// operators, which are not typed.
+fn split_path(path: &str) -> Option<Vec<&str>> {
+    let mut segments = Vec::new();
+    let mut depth = 0usize;
+    let mut quote = None;
+    let mut start = 0;
+    for (idx, c) in path.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '`') => quote = Some(c),
+            (None, '(' | '[') => depth += 1,
+            (None, ')' | ']') => depth = depth.checked_sub(1)?,
+            (None, '.') if depth == 0 => {
+                segments.push(path[start..idx].trim());
+                start = idx + 1;
+            }
+            (None, c) if depth == 0 && (c.is_whitespace() || "=<>!|+-*/&~".contains(c)) => {
+                return None;
+            }
+            _ => {}
+        }
+    }
+    segments.push(path[start..].trim());
+    if segments.iter().any(|s| s.is_empty()) || depth != 0 {
+        return None;
+    }
+    Some(segments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embedded::{FhirVersion, get_schemas};
+    use serde_json::json;
+
+    fn analyze(view: Value) -> ViewDefinitionAnalysis {
+        let schemas = get_schemas(FhirVersion::R4).unwrap();
+        analyze_view_definition(&view, schemas)
+    }
+
+    #[test]
+    fn test_column_types() {
+        let analysis = analyze(json!({
+            "resourceType": "ViewDefinition",
+            "resource": "Patient",
+            "select": [{
+                "column": [
+                    {"name": "id", "path": "getResourceKey()"},
+                    {"name": "birth_date", "path": "birthDate"},
+                    {"name": "active", "path": "active"}
+                ]
+            }, {
+                "forEach": "name",
+                "column": [
+                    {"name": "family", "path": "family"},
+                    {"name": "given", "path": "given.join(' ')"}
+                ]
+            }]
+        }));
+
+        assert!(analysis.is_valid(), "{:?}", analysis.issues);
+        let types: Vec<_> = analysis
+            .columns
+            .iter()
+            .map(|c| (c.name.as_str(), c.fhir_type.as_deref()))
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                ("id", Some("string")),
+                ("birth_date", Some("date")),
+                ("active", Some("boolean")),
+                ("family", Some("string")),
+                ("given", Some("string")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reports_unknown_elements_and_collections() {
+        let analysis = analyze(json!({
+            "resourceType": "ViewDefinition",
+            "resource": "Patient",
+            "select": [{
+                "column": [
+                    {"name": "nickname", "path": "nickname"},
+                    {"name": "family", "path": "name.family"},
+                    {"name": "first_family", "path": "name.first().family"}
+                ]
+            }]
+        }));
+
+        let locations: Vec<_> = analysis
+            .issues
+            .iter()
+            .map(|issue| issue.location.as_str())
+            .collect();
+        assert_eq!(
+            locations,
+            vec!["select[0].column[0].path", "select[0].column[1].path"]
+        );
+        assert_eq!(analysis.columns[2].fhir_type.as_deref(), Some("string"));
+    }
+
+    #[test]
+    fn test_union_all_columns_must_match() {
+        let analysis = analyze(json!({
+            "resourceType": "ViewDefinition",
+            "resource": "Patient",
+            "select": [{
+                "unionAll": [
+                    {"forEach": "telecom", "column": [{"name": "value", "path": "value"}]},
+                    {"forEach": "contact.telecom", "column": [{"name": "other", "path": "value"}]}
+                ]
+            }]
+        }));
+
+        assert_eq!(analysis.issues.len(), 1);
+        assert_eq!(analysis.issues[0].location, "select[0].unionAll[1]");
+        assert_eq!(analysis.columns.len(), 1);
+    }
+
+    #[test]
+    fn test_split_path() {
+        assert_eq!(
+            split_path("name.where(use = 'official').given.first()"),
+            Some(vec!["name", "where(use = 'official')", "given", "first()"])
+        );
+        assert_eq!(split_path("a = b"), None);
+    }
+}
@@ -0,0 +1,555 @@
+//! Concurrent schema compilation tests.
+
+use async_trait::async_trait;
+use octofhir_fhirschema::terminology::{
+    CodeValidationResult, TerminologyResult, TerminologyService,
+};
+use octofhir_fhirschema::types::FhirSchema;
+use octofhir_fhirschema::validation::{
+    CompiledConstraint, FhirValidator, SchemaCompiler, SchemaProvider,
+};
+use octofhir_fhirschema::{FhirVersion, get_schemas};
+use serde_json::json;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+/// Yields to the executor once, so concurrently polled compilations
+/// interleave at every schema lookup.
+struct YieldOnce(bool);
+
+impl Future for YieldOnce {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            Poll::Ready(())
+        } else {
+            self.0 = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
+
+/// Serves the embedded R4 schemas and counts lookups per name.
+struct CountingProvider {
+    schemas: HashMap<String, Arc<FhirSchema>>,
+    lookups: Mutex<HashMap<String, usize>>,
+    total: AtomicUsize,
+}
+
+impl CountingProvider {
+    fn new() -> Self {
+        let schemas = get_schemas(FhirVersion::R4)
+            .expect("R4 schemas embedded")
+            .iter()
+            .map(|(name, schema)| (name.clone(), Arc::new(schema.clone())))
+            .collect();
+        Self {
+            schemas,
+            lookups: Mutex::new(HashMap::new()),
+            total: AtomicUsize::new(0),
+        }
+    }
+
+    fn lookups(&self, name: &str) -> usize {
+        self.lookups.lock().unwrap().get(name).copied().unwrap_or(0)
+    }
+}
+
+#[async_trait]
+impl SchemaProvider for CountingProvider {
+    async fn get_schema(&self, name: &str) -> Option<Arc<FhirSchema>> {
+        self.get_schema_by_url(name).await
+    }
+
+    async fn get_schema_by_url(&self, url: &str) -> Option<Arc<FhirSchema>> {
+        YieldOnce(false).await;
+        *self
+            .lookups
+            .lock()
+            .unwrap()
+            .entry(url.to_string())
+            .or_default() += 1;
+        self.total.fetch_add(1, Ordering::Relaxed);
+        if let Some(schema) = self.schemas.get(url) {
+            return Some(schema.clone());
+        }
+        self.schemas.values().find(|s| s.url == url).cloned()
+    }
+}
+
+#[tokio::test]
+async fn test_compile_many_deduplicates_names() {
+    let provider = Arc::new(CountingProvider::new());
+    let compiler = SchemaCompiler::new(provider.clone());
+
+    let results = compiler
+        .compile_many(["Patient", "Observation", "Patient", "NotAType"])
+        .await;
+
+    let names: Vec<&str> = results.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, vec!["Patient", "Observation", "NotAType"]);
+    assert!(results[0].1.is_ok());
+    assert!(results[1].1.is_ok());
+    assert!(results[2].1.is_err());
+    assert_eq!(provider.lookups("Patient"), 1);
+}
+
+#[tokio::test]
+async fn test_compile_many_shares_in_flight_work() {
+    let provider = Arc::new(CountingProvider::new());
+    let compiler = SchemaCompiler::new(provider.clone());
+    compiler.compile("Patient").await.unwrap();
+    let serial = provider.total.load(Ordering::Relaxed);
+
+    let provider = Arc::new(CountingProvider::new());
+    let compiler = SchemaCompiler::new(provider.clone());
+    let (a, b) = futures::join!(compiler.compile("Patient"), compiler.compile("Patient"));
+
+    assert!(Arc::ptr_eq(&a.unwrap(), &b.unwrap()));
+    assert_eq!(provider.total.load(Ordering::Relaxed), serial);
+}
+
+#[tokio::test]
+async fn test_disk_cache_reloads_compiled_schemas() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let provider = Arc::new(CountingProvider::new());
+    let compiler = SchemaCompiler::new(provider.clone()).with_disk_cache(dir.path());
+    let cold = compiler.compile("Patient").await.unwrap();
+    let cold_lookups = provider.total.load(Ordering::Relaxed);
+    assert!(std::fs::read_dir(dir.path()).unwrap().count() > 0);
+
+    let provider = Arc::new(CountingProvider::new());
+    let compiler = SchemaCompiler::new(provider.clone()).with_disk_cache(dir.path());
+    let warm = compiler.compile("Patient").await.unwrap();
+
+    assert_eq!(warm.url, cold.url);
+    assert_eq!(warm.elements.len(), cold.elements.len());
+    assert!(provider.total.load(Ordering::Relaxed) < cold_lookups);
+}
+
+#[tokio::test]
+async fn test_disk_cache_recompiles_when_inlined_datatype_changes() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let provider = Arc::new(CountingProvider::new());
+    let compiler = SchemaCompiler::new(provider).with_disk_cache(dir.path());
+    let cold = compiler.compile("Patient").await.unwrap();
+    assert!(
+        !cold.elements["name"]
+            .required
+            .contains(&"family".to_string())
+    );
+
+    // HumanName is not part of Patient's inheritance chain; it is only
+    // inlined into Patient.name
+    let mut provider = CountingProvider::new();
+    let human_name = Arc::make_mut(provider.schemas.get_mut("HumanName").unwrap());
+    human_name
+        .required
+        .get_or_insert_with(Vec::new)
+        .push("family".to_string());
+    let compiler = SchemaCompiler::new(Arc::new(provider)).with_disk_cache(dir.path());
+    let warm = compiler.compile("Patient").await.unwrap();
+
+    assert!(
+        warm.elements["name"]
+            .required
+            .contains(&"family".to_string())
+    );
+}
+
+#[tokio::test]
+async fn test_compile_shares_schema_across_names() {
+    let provider = Arc::new(CountingProvider::new());
+    let compiler = SchemaCompiler::new(provider.clone());
+
+    let by_name = compiler.compile("Patient").await.unwrap();
+    let by_url = compiler.compile(&by_name.url).await.unwrap();
+
+    assert!(Arc::ptr_eq(&by_name, &by_url));
+    assert_eq!(provider.lookups("Patient"), 1);
+}
+
+#[tokio::test]
+async fn test_validate_with_versioned_profile() {
+    const ACTIVE_PATIENT: &str = "http://example.org/ActivePatient";
+
+    let mut schemas = get_schemas(FhirVersion::R4).unwrap().clone();
+    let profile: FhirSchema = serde_json::from_value(json!({
+        "url": ACTIVE_PATIENT, "version": "1.0.0", "name": "ActivePatient",
+        "type": "Patient", "kind": "resource", "class": "profile",
+        "derivation": "constraint",
+        "base": "http://hl7.org/fhir/StructureDefinition/Patient",
+        "required": ["active"]
+    }))
+    .unwrap();
+    schemas.insert(ACTIVE_PATIENT.to_string(), profile);
+    let validator = FhirValidator::from_schemas(schemas, None);
+    let profiles = vec![format!("{ACTIVE_PATIENT}|1.0.0")];
+
+    let result = validator
+        .validate_with_profiles(&json!({"resourceType": "Patient"}), &profiles)
+        .await;
+    assert!(!result.valid);
+    let missing = result
+        .errors
+        .iter()
+        .find(|e| e.to_string().contains("active"))
+        .unwrap_or_else(|| panic!("errors: {:?}", result.errors));
+    assert_eq!(missing.schema_url.as_deref(), Some(ACTIVE_PATIENT));
+    assert_eq!(missing.schema_version.as_deref(), Some("1.0.0"));
+
+    // Findings of the base resource type are attributed to core FHIR.
+    let result = validator
+        .validate_with_profiles(
+            &json!({"resourceType": "Patient", "active": true, "nickname": "JD"}),
+            &profiles,
+        )
+        .await;
+    assert!(result.errors.iter().any(|e| {
+        e.schema_url.as_deref() == Some("http://hl7.org/fhir/StructureDefinition/Patient")
+    }));
+
+    let result = validator
+        .validate_with_profiles(
+            &json!({"resourceType": "Patient", "active": true}),
+            &profiles,
+        )
+        .await;
+    assert!(result.valid, "errors: {:?}", result.errors);
+
+    let result = validator
+        .validate_with_profiles(
+            &json!({"resourceType": "Patient", "active": true}),
+            &[format!("{ACTIVE_PATIENT}|2.0.0")],
+        )
+        .await;
+    assert!(!result.valid);
+}
+
+#[tokio::test]
+async fn test_validate_primitive_regexes() {
+    const MRN_PATIENT: &str = "http://example.org/MrnPatient";
+    const URN: &str = "http://example.org/StructureDefinition/urn";
+
+    let mut schemas = get_schemas(FhirVersion::R4).unwrap().clone();
+    let urn: FhirSchema = serde_json::from_value(json!({
+        "url": URN, "name": "Urn", "type": "uri", "kind": "primitive-type",
+        "class": "profile", "derivation": "constraint",
+        "base": "http://hl7.org/fhir/StructureDefinition/uri",
+        "elements": {"value": {"regex": "urn:.+"}}
+    }))
+    .unwrap();
+    let profile: FhirSchema = serde_json::from_value(json!({
+        "url": MRN_PATIENT, "name": "MrnPatient",
+        "type": "Patient", "kind": "resource", "class": "profile",
+        "derivation": "constraint",
+        "base": "http://hl7.org/fhir/StructureDefinition/Patient",
+        "elements": {"identifier": {"elements": {
+            "system": {"typeProfile": [URN]},
+            "value": {"regex": "MRN-[0-9]{6}"}
+        }}}
+    }))
+    .unwrap();
+    schemas.insert(URN.to_string(), urn);
+    schemas.insert(MRN_PATIENT.to_string(), profile);
+    let validator = FhirValidator::from_schemas(schemas, None);
+    let profiles = vec![MRN_PATIENT.to_string()];
+
+    let valid = json!({"resourceType": "Patient", "identifier": [
+        {"system": "urn:mrn", "value": "MRN-123456"}
+    ]});
+    let result = validator.validate_with_profiles(&valid, &profiles).await;
+    assert!(result.valid, "errors: {:?}", result.errors);
+
+    let invalid = json!({"resourceType": "Patient", "identifier": [
+        {"system": "http://example.org/mrn", "value": "123456"}
+    ]});
+    let result = validator.validate_with_profiles(&invalid, &profiles).await;
+    let mut patterns: Vec<&str> = result
+        .errors
+        .iter()
+        .filter_map(|e| e.expected.as_ref()?.as_str())
+        .filter(|expected| expected.contains(':') || expected.starts_with("MRN"))
+        .collect();
+    patterns.sort();
+    assert_eq!(
+        patterns,
+        ["MRN-[0-9]{6}", "urn:.+"],
+        "errors: {:?}",
+        result.errors
+    );
+}
+
+#[tokio::test]
+async fn test_compile_constraint_conditions() {
+    const CONTACT_PATIENT: &str = "http://example.org/ContactPatient";
+
+    let profile: FhirSchema = serde_json::from_value(json!({
+        "url": CONTACT_PATIENT, "name": "ContactPatient",
+        "type": "Patient", "kind": "resource", "class": "profile",
+        "derivation": "constraint",
+        "base": "http://hl7.org/fhir/StructureDefinition/Patient",
+        "constraint": {"cp-1": {
+            "expression": "contact.all(name.exists() or telecom.exists())",
+            "human": "A contact needs a name or telecom", "severity": "error"
+        }},
+        "elements": {"contact": {
+            "constraint": {"cp-2": {
+                "expression": "gender.exists() implies name.exists()",
+                "human": "A gendered contact needs a name", "severity": "error"
+            }},
+            "elements": {
+                "name": {"condition": ["cp-1", "cp-2"]},
+                "telecom": {"condition": ["cp-1"]},
+                "gender": {"condition": ["cp-2"]}
+            }
+        }}
+    }))
+    .unwrap();
+    let mut provider = CountingProvider::new();
+    provider
+        .schemas
+        .insert(CONTACT_PATIENT.to_string(), Arc::new(profile));
+    let compiler = SchemaCompiler::new(Arc::new(provider));
+    let compiled = compiler.compile(CONTACT_PATIENT).await.unwrap();
+
+    let implicated = |constraints: &[CompiledConstraint], key: &str| {
+        constraints
+            .iter()
+            .find(|c| c.key == key)
+            .map(|c| c.implicated.clone())
+            .unwrap_or_else(|| panic!("{key} not compiled"))
+    };
+    assert_eq!(
+        implicated(&compiled.constraints, "cp-1"),
+        ["contact.name", "contact.telecom"]
+    );
+    assert_eq!(
+        implicated(&compiled.elements["contact"].constraints, "cp-2"),
+        ["gender", "name"]
+    );
+}
+
+#[tokio::test]
+async fn test_validate_items_against_their_slice() {
+    const SLICED_PATIENT: &str = "http://example.org/SlicedPatient";
+
+    let mut schemas = get_schemas(FhirVersion::R4).unwrap().clone();
+    let profile: FhirSchema = serde_json::from_value(json!({
+        "url": SLICED_PATIENT, "name": "SlicedPatient",
+        "type": "Patient", "kind": "resource", "class": "profile",
+        "derivation": "constraint",
+        "base": "http://hl7.org/fhir/StructureDefinition/Patient",
+        "elements": {"identifier": {"slicing": {
+            "discriminator": [{"type": "value", "path": "system"}],
+            "rules": "open",
+            "slices": {"mrn": {
+                "match": {"system": "urn:mrn"},
+                "min": 1,
+                "schema": {"elements": {"value": {"regex": "MRN-[0-9]{6}"}}}
+            }}
+        }}}
+    }))
+    .unwrap();
+    schemas.insert(SLICED_PATIENT.to_string(), profile);
+    let validator = FhirValidator::from_schemas(schemas, None);
+    let profiles = vec![SLICED_PATIENT.to_string()];
+
+    // Items outside the slice keep the base Identifier definition
+    let valid = json!({"resourceType": "Patient", "identifier": [
+        {"system": "urn:mrn", "value": "MRN-123456"},
+        {"system": "urn:ssn", "value": "123-45-6789"}
+    ]});
+    let result = validator.validate_with_profiles(&valid, &profiles).await;
+    assert!(result.valid, "errors: {:?}", result.errors);
+
+    let invalid = json!({"resourceType": "Patient", "identifier": [
+        {"system": "urn:ssn", "value": "123-45-6789"},
+        {"system": "urn:mrn", "value": "123456"}
+    ]});
+    let result = validator.validate_with_profiles(&invalid, &profiles).await;
+    let paths: Vec<String> = result.errors.iter().map(|e| e.path.to_string()).collect();
+    assert_eq!(
+        paths,
+        ["Patient.identifier[1].value"],
+        "errors: {:?}",
+        result.errors
+    );
+
+    let missing = json!({"resourceType": "Patient", "identifier": [
+        {"system": "urn:ssn", "value": "123-45-6789"}
+    ]});
+    let result = validator.validate_with_profiles(&missing, &profiles).await;
+    assert!(!result.valid);
+}
+
+/// Accepts every code but `pager` and counts the lookups.
+#[derive(Default)]
+struct CountingTerminology {
+    lookups: AtomicUsize,
+}
+
+#[async_trait]
+impl TerminologyService for CountingTerminology {
+    async fn validate_code(
+        &self,
+        _value_set_url: &str,
+        code: &str,
+        _system: Option<&str>,
+    ) -> TerminologyResult<CodeValidationResult> {
+        self.lookups.fetch_add(1, Ordering::SeqCst);
+        Ok(if code == "pager" {
+            CodeValidationResult::invalid()
+        } else {
+            CodeValidationResult::valid()
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_required_bindings_are_looked_up_once() {
+    let terminology = Arc::new(CountingTerminology::default());
+    let validator =
+        FhirValidator::from_schemas(get_schemas(FhirVersion::R4).unwrap().clone(), None)
+            .with_terminology_service(terminology.clone());
+
+    // ContactPoint.system and ContactPoint.use have required bindings
+    let patient = json!({"resourceType": "Patient", "telecom": [
+        {"system": "phone", "use": "home", "value": "555-0100"},
+        {"system": "phone", "use": "home", "value": "555-0101"},
+        {"system": "pager", "value": "555-0102"},
+        {"system": "pager", "value": "555-0103"}
+    ]});
+    let result = validator
+        .validate(&patient, vec!["Patient".to_string()])
+        .await;
+    let mut paths: Vec<String> = result.errors.iter().map(|e| e.path.to_string()).collect();
+    paths.sort();
+    assert_eq!(
+        paths,
+        ["Patient.telecom[2].system", "Patient.telecom[3].system"],
+        "errors: {:?}",
+        result.errors
+    );
+    assert_eq!(terminology.lookups.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn test_validate_array_cardinality() {
+    const COUNTED_PATIENT: &str = "http://example.org/CountedPatient";
+
+    let mut schemas = get_schemas(FhirVersion::R4).unwrap().clone();
+    let profile: FhirSchema = serde_json::from_value(json!({
+        "url": COUNTED_PATIENT, "name": "CountedPatient",
+        "type": "Patient", "kind": "resource", "class": "profile",
+        "derivation": "constraint",
+        "base": "http://hl7.org/fhir/StructureDefinition/Patient",
+        "elements": {
+            "identifier": {"array": true, "max": 2},
+            "name": {"array": true, "min": 2}
+        }
+    }))
+    .unwrap();
+    schemas.insert(COUNTED_PATIENT.to_string(), profile);
+    let validator = FhirValidator::from_schemas(schemas, None);
+    let profiles = vec![COUNTED_PATIENT.to_string()];
+
+    let valid = json!({"resourceType": "Patient",
+        "identifier": [{"value": "1"}, {"value": "2"}],
+        "name": [{"family": "Doe"}, {"family": "Roe"}]
+    });
+    let result = validator.validate_with_profiles(&valid, &profiles).await;
+    assert!(result.valid, "errors: {:?}", result.errors);
+
+    let invalid = json!({"resourceType": "Patient",
+        "identifier": [{"value": "1"}, {"value": "2"}, {"value": "3"}],
+        "name": [{"family": "Doe"}]
+    });
+    let result = validator.validate_with_profiles(&invalid, &profiles).await;
+    let mut findings: Vec<(String, String)> = result
+        .errors
+        .iter()
+        .map(|e| {
+            let expected = e.expected.as_ref().map(|v| v.to_string());
+            (e.path.to_string(), expected.unwrap_or_default())
+        })
+        .collect();
+    findings.sort();
+    assert_eq!(
+        findings,
+        [
+            ("Patient.identifier".to_string(), "\"0..2\"".to_string()),
+            ("Patient.name".to_string(), "\"2..*\"".to_string()),
+        ],
+        "errors: {:?}",
+        result.errors
+    );
+}
+
+#[tokio::test]
+async fn test_validate_required_and_excluded_nested_children() {
+    const CLOSED_BUNDLE: &str = "http://example.org/ClosedBundle";
+
+    let mut schemas = get_schemas(FhirVersion::R4).unwrap().clone();
+    let profile: FhirSchema = serde_json::from_value(json!({
+        "url": CLOSED_BUNDLE, "name": "ClosedBundle",
+        "type": "Bundle", "kind": "resource", "class": "profile",
+        "derivation": "constraint",
+        "base": "http://hl7.org/fhir/StructureDefinition/Bundle",
+        "elements": {
+            "entry": {"elements": {"request": {"excluded": ["ifMatch"]}}}
+        }
+    }))
+    .unwrap();
+    schemas.insert(CLOSED_BUNDLE.to_string(), profile);
+    let validator = FhirValidator::from_schemas(schemas, None);
+
+    // Bundle.entry.request requires method and url in the core schema
+    let bundle = json!({"resourceType": "Bundle", "type": "transaction",
+        "entry": [{"request": {"url": "Patient"}}]
+    });
+    let result = validator
+        .validate(&bundle, vec!["Bundle".to_string()])
+        .await;
+    assert!(
+        result
+            .errors
+            .iter()
+            .any(|e| e.path.to_string() == "Bundle.entry[0].request"
+                && e.message
+                    .as_ref()
+                    .is_some_and(|m| *m == "Required element 'method' is missing")),
+        "errors: {:?}",
+        result.errors
+    );
+
+    let bundle = json!({"resourceType": "Bundle", "type": "transaction",
+        "entry": [{"request": {"method": "PUT", "url": "Patient/1", "ifMatch": "W/\"1\""}}]
+    });
+    let result = validator
+        .validate(&bundle, vec!["Bundle".to_string()])
+        .await;
+    assert!(result.valid, "errors: {:?}", result.errors);
+    let result = validator
+        .validate_with_profiles(&bundle, &[CLOSED_BUNDLE.to_string()])
+        .await;
+    assert!(
+        result
+            .errors
+            .iter()
+            .any(|e| e.path.to_string() == "Bundle.entry[0].request"
+                && e.message
+                    .as_ref()
+                    .is_some_and(|m| *m == "Excluded element 'ifMatch' is present")),
+        "errors: {:?}",
+        result.errors
+    );
+}
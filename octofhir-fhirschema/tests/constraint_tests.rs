@@ -0,0 +1,316 @@
+//! Constraint metadata through conversion, merging and compilation, and
+//! constraint evaluation with caller-supplied FHIRPath variables.
+
+use octofhir_fhirpath::FhirPathEngine;
+use octofhir_fhirschema::types::{
+    FhirSchema, StructureDefinition, ValidationResult, effective_constraints,
+};
+use octofhir_fhirschema::validation::{InMemorySchemaProvider, SchemaCompiler};
+use octofhir_fhirschema::{
+    DynamicSchemaProvider, FhirValidator, FhirVersion, ModelFhirVersion, ValidationOptions,
+    get_schemas, translate,
+};
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+fn narrative_optional_profile() -> FhirSchema {
+    let root = json!({
+        "id": "Patient",
+        "path": "Patient",
+        "constraint": [
+            {
+                "key": "dom-6",
+                "severity": "warning",
+                "human": "A resource should have narrative for robust management",
+                "expression": "text.`div`.exists()",
+                "source": "http://hl7.org/fhir/StructureDefinition/DomainResource",
+                "suppress": true,
+                "extension": [{
+                    "url": "http://hl7.org/fhir/StructureDefinition/elementdefinition-bestpractice",
+                    "valueBoolean": true
+                }]
+            },
+            {
+                "key": "nop-1",
+                "severity": "error",
+                "human": "Patient SHALL have a name",
+                "expression": "name.exists()",
+                "requirements": "Patients are looked up by name"
+            }
+        ]
+    });
+    let sd: StructureDefinition = serde_json::from_value(json!({
+        "resourceType": "StructureDefinition",
+        "url": "http://example.org/StructureDefinition/narrative-optional",
+        "name": "NarrativeOptional",
+        "status": "active",
+        "kind": "resource",
+        "abstract": false,
+        "type": "Patient",
+        "baseDefinition": "http://hl7.org/fhir/StructureDefinition/Patient",
+        "derivation": "constraint",
+        "snapshot": { "element": [root.clone()] },
+        "differential": { "element": [root] }
+    }))
+    .unwrap();
+    translate(sd, None).unwrap()
+}
+
+#[test]
+fn test_constraint_metadata_is_preserved() {
+    let profile = narrative_optional_profile();
+    let constraints = profile.constraint.as_ref().unwrap();
+
+    let dom6 = &constraints["dom-6"];
+    assert!(dom6.is_suppressed());
+    assert_eq!(dom6.best_practice, Some(true));
+    assert_eq!(
+        dom6.source.as_deref(),
+        Some("http://hl7.org/fhir/StructureDefinition/DomainResource")
+    );
+
+    let nop1 = &constraints["nop-1"];
+    assert!(!nop1.is_suppressed());
+    assert_eq!(
+        nop1.requirements.as_deref(),
+        Some("Patients are looked up by name")
+    );
+}
+
+#[test]
+fn test_effective_constraints_across_chain() {
+    let schemas = get_schemas(FhirVersion::R4).unwrap();
+    let profile = narrative_optional_profile();
+    let chain = [
+        &profile,
+        &schemas["Patient"],
+        &schemas["DomainResource"],
+        &schemas["Resource"],
+    ];
+
+    let root = effective_constraints(&chain, "");
+    let keys: Vec<&str> = root.iter().map(|c| c.key.as_str()).collect();
+    assert_eq!(keys, ["dom-2", "dom-3", "dom-4", "dom-5", "dom-6", "nop-1"]);
+
+    let dom6 = root.iter().find(|c| c.key == "dom-6").unwrap();
+    assert_eq!(dom6.defined_in, profile.url);
+    assert!(dom6.constraint.is_suppressed());
+    assert_eq!(
+        dom6.overrides,
+        [
+            "http://hl7.org/fhir/StructureDefinition/Patient",
+            "http://hl7.org/fhir/StructureDefinition/DomainResource",
+        ]
+    );
+
+    let contact = effective_constraints(&chain, "contact");
+    assert!(contact.iter().any(|c| c.key == "pat-1"));
+}
+
+#[tokio::test]
+async fn test_suppressed_constraints_are_not_compiled() {
+    let mut schemas: HashMap<String, Arc<FhirSchema>> = get_schemas(FhirVersion::R4)
+        .unwrap()
+        .iter()
+        .map(|(name, schema)| (name.clone(), Arc::new(schema.clone())))
+        .collect();
+    schemas.insert(
+        "NarrativeOptional".to_string(),
+        Arc::new(narrative_optional_profile()),
+    );
+    let compiler = SchemaCompiler::new(Arc::new(InMemorySchemaProvider::from_map(schemas)));
+
+    let compiled = compiler.compile("NarrativeOptional").await.unwrap();
+    let keys: Vec<&str> = compiled
+        .constraints
+        .iter()
+        .map(|c| c.key.as_str())
+        .collect();
+    assert!(!keys.contains(&"dom-6"));
+    assert!(keys.contains(&"nop-1"));
+    assert_eq!(keys.iter().filter(|k| **k == "dom-2").count(), 1);
+
+    let base = compiler.compile("Patient").await.unwrap();
+    assert!(base.constraints.iter().any(|c| c.key == "dom-6"));
+}
+
+/// Profile of `base` named `name` whose snapshot and differential are
+/// `elements`.
+fn constrained_profile(name: &str, base: &str, elements: Vec<serde_json::Value>) -> FhirSchema {
+    let sd: StructureDefinition = serde_json::from_value(json!({
+        "resourceType": "StructureDefinition",
+        "url": format!("http://example.org/StructureDefinition/{name}"),
+        "name": name,
+        "status": "active",
+        "kind": "resource",
+        "abstract": false,
+        "type": base,
+        "baseDefinition": format!("http://hl7.org/fhir/StructureDefinition/{base}"),
+        "derivation": "constraint",
+        "snapshot": { "element": elements.clone() },
+        "differential": { "element": elements }
+    }))
+    .unwrap();
+    translate(sd, None).unwrap()
+}
+
+/// Validator with the FHIRPath engine over the R4 core schemas and `profile`.
+async fn fhirpath_validator(profile: FhirSchema) -> FhirValidator {
+    let mut schemas = get_schemas(FhirVersion::R4).unwrap().clone();
+    schemas.insert(profile.name.clone(), profile);
+    let model_provider = Arc::new(DynamicSchemaProvider::new(
+        schemas.clone(),
+        ModelFhirVersion::R4,
+    ));
+    let registry = Arc::new(octofhir_fhirpath::create_function_registry());
+    let engine = FhirPathEngine::new(registry, model_provider).await.unwrap();
+    FhirValidator::from_schemas(schemas, Some(Arc::new(engine)))
+}
+
+#[tokio::test]
+async fn test_validation_variables_reach_constraints() {
+    let root = json!({
+        "id": "Patient",
+        "path": "Patient",
+        "constraint": [
+            {
+                "key": "ten-1",
+                "severity": "error",
+                "human": "Patients belong to the validating tenant",
+                "expression": "%tenant = 'acme'"
+            },
+            {
+                "key": "ten-2",
+                "severity": "error",
+                "human": "%ucum is the UCUM system",
+                "expression": "%ucum = 'http://unitsofmeasure.org'"
+            }
+        ]
+    });
+    let validator =
+        fhirpath_validator(constrained_profile("TenantPatient", "Patient", vec![root])).await;
+
+    let patient = json!({ "resourceType": "Patient", "active": true });
+    let profiles = vec!["TenantPatient".to_string()];
+    let constraint_keys = |result: &ValidationResult| -> Vec<String> {
+        result
+            .errors
+            .iter()
+            .filter_map(|e| e.constraint_key.clone())
+            .collect()
+    };
+
+    let options = ValidationOptions::default().with_variable("%tenant", json!("acme"));
+    let result = validator
+        .validate_with_options(&patient, profiles.clone(), &options)
+        .await;
+    assert!(result.valid, "{:?}", result.errors);
+
+    let options = ValidationOptions::default().with_variable("tenant", json!("other"));
+    let result = validator
+        .validate_with_options(&patient, profiles, &options)
+        .await;
+    assert_eq!(constraint_keys(&result), vec!["ten-1".to_string()]);
+}
+
+#[tokio::test]
+async fn test_resource_variable_follows_nested_resources() {
+    let elements = vec![
+        json!({ "id": "Patient", "path": "Patient" }),
+        json!({
+            "id": "Patient.contained",
+            "path": "Patient.contained",
+            "constraint": [{
+                "key": "scope-1",
+                "severity": "error",
+                "human": "%resource is the contained resource, %rootResource its container",
+                "expression": "%resource.resourceType = 'Organization' and %rootResource.resourceType = 'Patient'"
+            }]
+        }),
+    ];
+    let validator =
+        fhirpath_validator(constrained_profile("ScopedPatient", "Patient", elements)).await;
+
+    let scope_failures = |patient: serde_json::Value| {
+        let validator = &validator;
+        async move {
+            validator
+                .validate(&patient, vec!["ScopedPatient".to_string()])
+                .await
+                .errors
+                .into_iter()
+                .filter(|e| e.constraint_key.as_deref() == Some("scope-1"))
+                .count()
+        }
+    };
+
+    let organization = json!({
+        "resourceType": "Patient",
+        "contained": [{ "resourceType": "Organization", "id": "org", "name": "Acme" }],
+        "managingOrganization": { "reference": "#org" }
+    });
+    assert_eq!(scope_failures(organization).await, 0);
+
+    let practitioner = json!({
+        "resourceType": "Patient",
+        "contained": [{ "resourceType": "Practitioner", "id": "gp" }],
+        "generalPractitioner": [{ "reference": "#gp" }]
+    });
+    assert_eq!(scope_failures(practitioner).await, 1);
+}
+
+#[tokio::test]
+async fn test_fhirpath_discriminated_items_carry_slice_constraints() {
+    let profile: FhirSchema = serde_json::from_value(json!({
+        "url": "http://example.org/StructureDefinition/MrnPatient", "name": "MrnPatient",
+        "type": "Patient", "kind": "resource", "class": "profile",
+        "derivation": "constraint",
+        "base": "http://hl7.org/fhir/StructureDefinition/Patient",
+        "elements": {"identifier": {"slicing": {
+            // `where(...)` is not walked natively, so items are classified
+            // with FHIRPath in the constraint phase
+            "discriminator": [{"type": "value", "path": "$this.where(value.exists()).system"}],
+            "rules": "open",
+            "slices": {"mrn": {
+                "match": {"system": "urn:mrn"},
+                "schema": {"constraint": {"mrn-1": {
+                    "expression": "value.startsWith('MRN')",
+                    "human": "An MRN starts with MRN", "severity": "error"
+                }}}
+            }}
+        }}}
+    }))
+    .unwrap();
+    let validator = fhirpath_validator(profile).await;
+
+    let mrn_failures = |identifier: serde_json::Value| {
+        let validator = &validator;
+        async move {
+            let patient = json!({"resourceType": "Patient", "identifier": identifier});
+            validator
+                .validate(&patient, vec!["MrnPatient".to_string()])
+                .await
+                .errors
+                .into_iter()
+                .filter(|e| e.constraint_key.as_deref() == Some("mrn-1"))
+                .map(|e| e.path.to_string())
+                .collect::<Vec<_>>()
+        }
+    };
+
+    let valid = json!([
+        {"system": "urn:ssn", "value": "123-45-6789"},
+        {"system": "urn:mrn", "value": "MRN-123456"}
+    ]);
+    assert!(mrn_failures(valid).await.is_empty());
+
+    // Only the item in the slice is checked against the slice's constraint
+    let invalid = json!([
+        {"system": "urn:ssn", "value": "123-45-6789"},
+        {"system": "urn:mrn", "value": "123456"}
+    ]);
+    let failures = mrn_failures(invalid).await;
+    assert_eq!(failures.len(), 1, "{failures:?}");
+    assert!(failures[0].contains("identifier[1]"), "{failures:?}");
+}
@@ -16,7 +16,7 @@ use std::time::Duration;
 
 /// Helper to create validator with embedded R4 schemas
 fn create_r4_validator() -> FhirValidator {
-    let schemas = get_schemas(FhirVersion::R4);
+    let schemas = get_schemas(FhirVersion::R4).expect("R4 schemas embedded");
     FhirValidator::from_schemas(schemas.clone(), None)
 }
 
@@ -20,6 +20,7 @@ fn test_field_by_field() {
             derivation: None,
             base: None,
             abstract_type: None,
+            title: None,
             description: None,
             package_name: None,
             package_version: None,
@@ -56,6 +57,7 @@ fn test_field_by_field() {
             version: Some("1.0.0".to_string()),
             derivation: Some("constraint".to_string()),
             base: Some("Element".to_string()),
+            title: None,
             description: Some("Test description".to_string()),
             package_name: Some("test.package".to_string()),
             package_version: Some("1.0".to_string()),
@@ -95,6 +97,7 @@ fn test_field_by_field() {
             version: Some("1.0.0".to_string()),
             derivation: Some("constraint".to_string()),
             base: Some("Element".to_string()),
+            title: None,
             description: Some("Test description".to_string()),
             package_name: Some("test.package".to_string()),
             package_version: Some("1.0".to_string()),
@@ -134,6 +137,7 @@ fn test_field_by_field() {
             version: Some("1.0.0".to_string()),
             derivation: Some("constraint".to_string()),
             base: Some("Element".to_string()),
+            title: None,
             description: Some("Test description".to_string()),
             package_name: Some("test.package".to_string()),
             package_version: Some("1.0".to_string()),
@@ -11,12 +11,15 @@ mod common;
 
 use common::{FixtureTestResult, FixtureTestSummary, load_all_fixtures};
 use octofhir_fhirschema::embedded::{FhirVersion, get_schemas};
-use octofhir_fhirschema::validation::FhirValidator;
+use octofhir_fhirschema::validation::{
+    CompiledSchema, FhirValidator, InMemorySchemaProvider, SchemaCompiler,
+};
 use serde_json::Value;
+use std::sync::Arc;
 
 /// Helper to create validator with embedded R4 schemas
 fn create_r4_validator() -> FhirValidator {
-    let schemas = get_schemas(FhirVersion::R4);
+    let schemas = get_schemas(FhirVersion::R4).expect("R4 schemas embedded");
     FhirValidator::from_schemas(schemas.clone(), None)
 }
 
@@ -214,7 +217,10 @@ async fn test_patient_unknown_element_detected() {
 
         // Check that we got an unknown element error
         let has_unknown_element_error = result.errors.iter().any(|e| {
-            e.error_type == "FS1001" || e.message.as_ref().is_some_and(|m| m.contains("unknown"))
+            e.error_type == "FS1001"
+                || e.message
+                    .as_ref()
+                    .is_some_and(|m| m.render().contains("unknown"))
         });
 
         assert!(
@@ -246,6 +252,26 @@ async fn test_patient_wrong_type_detected() {
     }
 }
 
+#[tokio::test]
+async fn test_patient_invalid_gender_detected() {
+    let validator = create_r4_validator();
+
+    if let Some(patient) = common::try_load_fixture("r4/base/invalid/patient_invalid_gender.json") {
+        let result = validator
+            .validate(&patient, vec!["Patient".to_string()])
+            .await;
+
+        // administrative-gender is enforced by the embedded core value sets
+        assert!(
+            result.errors.iter().any(|e| e.error_type == "FS1012"),
+            "Should have binding violation, got: {:?}",
+            result.errors
+        );
+    } else {
+        println!("Fixture not found - skipping");
+    }
+}
+
 #[tokio::test]
 async fn test_observation_missing_required_detected() {
     let validator = create_r4_validator();
@@ -267,6 +293,7 @@ async fn test_observation_missing_required_detected() {
             e.error_type == "FS1011"
                 || e.error_type == "FS1003"
                 || e.message.as_ref().is_some_and(|m| {
+                    let m = m.render();
                     m.contains("required") || m.contains("missing") || m.contains("Missing")
                 })
         });
@@ -323,6 +350,87 @@ async fn test_bundle_with_entries_fixture() {
     }
 }
 
+// =============================================================================
+// Precompiled Schema Tests
+// =============================================================================
+
+#[tokio::test]
+async fn test_validate_with_precompiled_schemas() {
+    let schemas = get_schemas(FhirVersion::R4).expect("R4 schemas embedded");
+    let provider = InMemorySchemaProvider::from_map(
+        schemas
+            .iter()
+            .map(|(name, schema)| (name.clone(), Arc::new(schema.clone())))
+            .collect(),
+    );
+    let compiled = SchemaCompiler::new(Arc::new(provider))
+        .compile("Patient")
+        .await
+        .expect("Patient compiles");
+
+    // Round-trip through JSON the way devtools writes compiled artifacts
+    let json = serde_json::to_vec(compiled.as_ref()).unwrap();
+    let restored: CompiledSchema = serde_json::from_slice(&json).unwrap();
+
+    // The validator has no schemas of its own, so Patient must come from the
+    // precompiled form
+    let validator = FhirValidator::from_schemas(Default::default(), None)
+        .with_precompiled_schemas([Arc::new(restored)]);
+
+    let valid = validator
+        .validate(
+            &serde_json::json!({"resourceType": "Patient", "active": true}),
+            vec!["Patient".to_string()],
+        )
+        .await;
+    assert!(valid.valid, "errors: {:?}", valid.errors);
+
+    let invalid = validator
+        .validate(
+            &serde_json::json!({"resourceType": "Patient", "active": "yes"}),
+            vec!["Patient".to_string()],
+        )
+        .await;
+    assert!(!invalid.valid);
+}
+
+#[tokio::test]
+async fn test_precompiled_schemas_with_shared_name_only_serve_their_urls() {
+    let schemas = get_schemas(FhirVersion::R4).expect("R4 schemas embedded");
+    let provider = InMemorySchemaProvider::from_map(
+        schemas
+            .iter()
+            .map(|(name, schema)| (name.clone(), Arc::new(schema.clone())))
+            .collect(),
+    );
+    let compiled = SchemaCompiler::new(Arc::new(provider))
+        .compile("Patient")
+        .await
+        .expect("Patient compiles");
+
+    // Two profiles from different guides that picked the same name
+    let mut first = CompiledSchema::clone(&compiled);
+    first.url = "http://example.org/a/StructureDefinition/SharedPatient".to_string();
+    first.name = "SharedPatient".to_string();
+    let mut second = first.clone();
+    second.url = "http://example.org/b/StructureDefinition/SharedPatient".to_string();
+
+    let compiler = SchemaCompiler::new(Arc::new(InMemorySchemaProvider::from_map(
+        Default::default(),
+    )))
+    .with_precompiled([Arc::new(first), Arc::new(second)]);
+
+    for url in [
+        "http://example.org/a/StructureDefinition/SharedPatient",
+        "http://example.org/b/StructureDefinition/SharedPatient",
+    ] {
+        let served = compiler.compile(url).await.expect("served by URL");
+        assert_eq!(served.url, url);
+    }
+    // Neither schema may answer for the ambiguous name
+    assert!(compiler.compile("SharedPatient").await.is_err());
+}
+
 // =============================================================================
 // Fixture Statistics Test
 // =============================================================================
@@ -18,6 +18,7 @@ fn test_minimal_schema() {
         derivation: None,
         base: None,
         abstract_type: None,
+        title: None,
         description: None,
         package_name: None,
         package_version: None,
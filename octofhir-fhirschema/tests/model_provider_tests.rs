@@ -1,4 +1,7 @@
-use octofhir_fhirschema::{EmbeddedSchemaProvider, ModelFhirVersion, ModelProvider, TypeInfo};
+use octofhir_fhirschema::{
+    DynamicSchemaProvider, EmbeddedSchemaProvider, FhirSchema, ModelFhirVersion, ModelProvider,
+    TypeInfo,
+};
 use serde_json::json;
 use std::collections::HashSet;
 
@@ -435,3 +438,106 @@ async fn test_fhirpath_workflow() {
     assert_eq!(given_child.singleton, Some(true)); // Individual element is singleton
     assert_eq!(given_child.type_name, "String");
 }
+
+#[test]
+fn test_search_parameters_from_packages() {
+    let mut provider = DynamicSchemaProvider::new(Default::default(), ModelFhirVersion::R4);
+    assert!(provider.search_parameters("Patient").is_empty());
+
+    provider.add_search_parameters(&[
+        json!({
+            "resourceType": "SearchParameter",
+            "url": "http://hl7.org/fhir/SearchParameter/Resource-id",
+            "code": "_id", "type": "token", "base": ["Resource"],
+            "expression": "Resource.id"
+        }),
+        json!({
+            "resourceType": "SearchParameter",
+            "url": "http://hl7.org/fhir/SearchParameter/Patient-organization",
+            "code": "organization", "type": "reference", "base": ["Patient"],
+            "expression": "Patient.managingOrganization",
+            "target": ["Organization"]
+        }),
+    ]);
+
+    let params = provider.search_parameters("Patient");
+    assert_eq!(params.len(), 2);
+    let organization = &params[1];
+    assert_eq!(organization.code, "organization");
+    assert_eq!(organization.param_type, "reference");
+    assert_eq!(
+        organization.expression.as_deref(),
+        Some("Patient.managingOrganization")
+    );
+    assert_eq!(organization.target, ["Organization"]);
+    assert_eq!(provider.search_parameters("Observation").len(), 1);
+}
+
+#[test]
+fn test_profiles_for_resource_type() {
+    let profile = |url: &str, type_name: &str, package: &str| FhirSchema {
+        url: url.to_string(),
+        name: url.rsplit('/').next().unwrap().to_string(),
+        type_name: type_name.to_string(),
+        kind: "resource".to_string(),
+        derivation: Some("constraint".to_string()),
+        base: Some(format!(
+            "http://hl7.org/fhir/StructureDefinition/{type_name}"
+        )),
+        title: Some(format!("{type_name} profile")),
+        package_name: Some(package.to_string()),
+        package_version: Some("1.0.0".to_string()),
+        ..Default::default()
+    };
+    let vitals = profile("http://example.org/vitals", "Observation", "example.vitals");
+    let mut schemas = EmbeddedSchemaProvider::r4().schemas().clone();
+    // Registered under both name and URL, as package loaders do
+    schemas.insert(vitals.name.clone(), vitals.clone());
+    schemas.insert(vitals.url.clone(), vitals);
+    let lab = profile("http://example.org/lab", "Observation", "example.lab");
+    schemas.insert(lab.name.clone(), lab);
+    let patient = profile("http://example.org/patient", "Patient", "example.lab");
+    schemas.insert(patient.name.clone(), patient);
+    let provider = DynamicSchemaProvider::new(schemas, ModelFhirVersion::R4);
+
+    let profiles = provider.profiles_for("Observation");
+    let urls: Vec<_> = profiles.iter().map(|p| p.url.as_str()).collect();
+    assert!(urls.contains(&"http://example.org/vitals"));
+    assert!(urls.contains(&"http://example.org/lab"));
+    assert!(!urls.contains(&"http://example.org/patient"));
+    assert_eq!(
+        urls.iter()
+            .filter(|url| **url == "http://example.org/vitals")
+            .count(),
+        1
+    );
+    let lab = profiles
+        .iter()
+        .find(|p| p.url == "http://example.org/lab")
+        .unwrap();
+    assert_eq!(lab.title.as_deref(), Some("Observation profile"));
+    assert_eq!(lab.package_name.as_deref(), Some("example.lab"));
+    // Core resource definitions are specializations, not profiles
+    assert!(
+        provider
+            .profiles_for("Patient")
+            .iter()
+            .all(|p| p.url != "http://hl7.org/fhir/StructureDefinition/Patient")
+    );
+}
+
+#[tokio::test]
+async fn test_minimal_embedded_provider() {
+    let provider = EmbeddedSchemaProvider::minimal(ModelFhirVersion::R4, &["Patient"]);
+    assert!(provider.schema_count() > 0);
+    assert!(provider.schema_count() < EmbeddedSchemaProvider::r4().schema_count());
+
+    let patient = provider.get_type("Patient").await.unwrap();
+    assert!(patient.is_some());
+    let name = provider
+        .get_element_type(&patient.unwrap(), "name")
+        .await
+        .unwrap();
+    assert!(name.is_some());
+    assert!(provider.get_type("Observation").await.unwrap().is_none());
+}
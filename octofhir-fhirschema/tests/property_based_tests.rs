@@ -13,7 +13,7 @@ use serde_json::{Value, json};
 
 /// Create validator with embedded R4 schemas
 fn create_r4_validator() -> FhirValidator {
-    let schemas = get_schemas(FhirVersion::R4);
+    let schemas = get_schemas(FhirVersion::R4).expect("R4 schemas embedded");
     FhirValidator::from_schemas(schemas.clone(), None)
 }
 
@@ -1,4 +1,5 @@
-//! Tests for `targetProfile` conformance validation (Phase 4b).
+//! Tests for reference target types and `targetProfile` conformance
+//! validation (Phase 4b).
 //!
 //! A reference that declares one or more `targetProfile`s is dereferenced via
 //! the reference resolver and the referenced resource is validated against those
@@ -167,7 +168,7 @@ async fn nonconforming_reference_errors() {
         "expected {MISMATCH}, got {:?}",
         result.errors
     );
-    let path = mismatch.unwrap().path.last().and_then(|v| v.as_str());
+    let path = mismatch.unwrap().path.last();
     assert_eq!(path, Some("reference"));
 }
 
@@ -254,3 +255,49 @@ async fn disabled_by_default_no_fetch() {
     );
     assert!(result.errors.iter().all(|e| e.error_type != MISMATCH));
 }
+
+const WRONG_TYPE: &str = "FS1013";
+
+#[tokio::test]
+async fn reference_to_type_outside_targets_errors() {
+    // Target types are checked structurally, without a resolver.
+    let v = FhirValidator::from_schemas(schemas(&[PATIENT_URL]), None);
+    let result = v
+        .validate(&observation("Practitioner/1"), vec!["Observation".into()])
+        .await;
+    let wrong = result.errors.iter().find(|e| e.error_type == WRONG_TYPE);
+    let path = wrong
+        .unwrap_or_else(|| panic!("expected {WRONG_TYPE}, got {:?}", result.errors))
+        .path
+        .last()
+        .and_then(|v| v.as_str());
+    assert_eq!(path, Some("reference"));
+
+    let mut by_type = observation("Patient/1");
+    by_type["subject"] = json!({"type": "Practitioner", "display": "Dr. Who"});
+    let result = v.validate(&by_type, vec!["Observation".into()]).await;
+    let wrong = result.errors.iter().find(|e| e.error_type == WRONG_TYPE);
+    let path = wrong
+        .unwrap_or_else(|| panic!("expected {WRONG_TYPE}, got {:?}", result.errors))
+        .path
+        .last()
+        .and_then(|v| v.as_str());
+    assert_eq!(path, Some("type"));
+
+    for reference in ["Patient/1", "http://example.org/fhir/Patient/1", "#p1"] {
+        let result = v
+            .validate(&observation(reference), vec!["Observation".into()])
+            .await;
+        assert!(result.valid, "{reference}: {:?}", result.errors);
+    }
+}
+
+#[tokio::test]
+async fn profile_targets_leave_type_to_conformance_check() {
+    // The type a profile constrains is only known once it is loaded.
+    let v = FhirValidator::from_schemas(schemas(&[SPECIAL_PATIENT]), None);
+    let result = v
+        .validate(&observation("Practitioner/1"), vec!["Observation".into()])
+        .await;
+    assert!(result.errors.iter().all(|e| e.error_type != WRONG_TYPE));
+}